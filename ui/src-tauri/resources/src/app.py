@@ -1,13 +1,14 @@
-from fastapi import FastAPI, HTTPException, BackgroundTasks, WebSocket, Depends, status
+from fastapi import FastAPI, HTTPException, BackgroundTasks, WebSocket, Depends, Request, status
 from fastapi.middleware.cors import CORSMiddleware
-from fastapi.responses import JSONResponse
-from pydantic import BaseModel, Field
+from fastapi.responses import JSONResponse, Response
+from fastapi.encoders import jsonable_encoder
+from pydantic import BaseModel, Field, ValidationError
 import os
 import json
+import base64
 import psutil
 import asyncio
 from typing import Optional, Dict, List, Any, Union
-import subprocess
 import uuid
 import time
 from datetime import datetime
@@ -15,9 +16,18 @@ import logging
 from contextlib import asynccontextmanager
 
 # Import existing functionality
-from functions.gen import generate_code, regenerate_code_with_feedback, clean_code_response
-from functions.exec import run_script, run_script_async
+from functions.gen import generate_code, regenerate_code_with_feedback, clean_code_response, generate_text, embed_texts
+from functions.exec import run_script, stop_script
+from functions.steps import read_steps
 from functions.config import configure_model
+from functions.doctor import run_diagnostics
+from functions.capabilities import detect_capabilities
+from functions.compression import COMPRESSION_THRESHOLD, accepted_encoding, choose_encoding, compress, decompress
+from functions import mcp_tools
+from functions.capture import screenshot_base64, screenshot_png
+from functions.handoff import HANDOFF_THRESHOLD, is_handoff_path, write_handoff
+from functions.attachments import describe_repo, image_parts, prompt_with_attachments
+from utils import apply_extra_ca_bundle, approval_required, get_config_dir, get_env_path, get_log_dir, is_worker
 from dotenv import load_dotenv
 
 # Configure logging
@@ -28,6 +38,11 @@ logging.basicConfig(
 )
 logger = logging.getLogger("krya-api")
 
+# Before any model client exists, so they all trust the roots the shell was given
+_ca_bundle = apply_extra_ca_bundle()
+if _ca_bundle:
+    logger.info(f"Trusting extra root certificates from {_ca_bundle}")
+
 # Global state management
 class AppState:
     def __init__(self):
@@ -36,6 +51,11 @@ class AppState:
         self.connected_clients: List[WebSocket] = []  # Active WebSocket connections
         self.recent_prompts: Dict[str, float] = {}  # Map of prompt hash to timestamp
         self.prompt_cooldown = 5.0  # Seconds to wait before allowing the same prompt again
+        self.shell: Optional[Dict[str, Any]] = None  # Version and capabilities from the shell's handshake
+        self.attachments: Dict[str, List[Dict]] = {}  # Map of job_id to files attached before the job runs
+        self.working_directory: Optional[str] = None  # Folder the shell confined this session's file work to
+        self.paused = False  # Set by the shell to refuse new automations until resumed
+        self.dry_run = False  # Set by the shell to generate scripts without running them
         
     def add_log(self, log_entry: Dict):
         """Add a log entry and broadcast to all connected clients"""
@@ -46,6 +66,10 @@ class AppState:
         # Broadcast to all connected clients
         asyncio.create_task(self.broadcast_log(log_entry))
     
+    def publish(self, entry: Dict):
+        """Broadcast an entry to connected clients without keeping it (e.g. streamed model output)"""
+        asyncio.create_task(self.broadcast_log(entry))
+    
     async def broadcast_log(self, log_entry: Dict):
         """Send log to all connected WebSocket clients"""
         for client in self.connected_clients:
@@ -86,34 +110,37 @@ app_state = AppState()
 @asynccontextmanager
 async def lifespan(app: FastAPI):
     # Startup: Load environment variables
-    load_dotenv()
+    load_dotenv(get_env_path())
     # Check if config directory exists
-    os.makedirs(os.path.join(os.getcwd(), "config"), exist_ok=True)
+    os.makedirs(get_config_dir(), exist_ok=True)
     
-    # Clean up any stale flag files on startup
+    # Use the model selected in the shell's settings
     try:
-        cleanup_all_processes()
-        logger.info("Cleaned up flag files and processes on startup")
+        apply_shell_model_settings()
     except Exception as e:
-        logger.error(f"Error cleaning up on startup: {e}")
+        logger.error(f"Error applying model settings: {e}")
+    
+    # Clean up any stale flag files on startup; a worker would be removing the main backend's
+    if not is_worker():
+        try:
+            cleanup_flag_files()
+            logger.info("Cleaned up flag files on startup")
+        except Exception as e:
+            logger.error(f"Error cleaning up on startup: {e}")
     
     # Yield control to the application
     yield
     
-    # Shutdown: Clean up any running processes
+    # Shutdown: have the shell stop any scripts still running
     for job_id, process_info in list(app_state.active_processes.items()):
-        try:
-            process = process_info.get("process")
-            if process and process.poll() is None:
-                process.terminate()
-                logger.info(f"Terminated process for job {job_id}")
-        except Exception as e:
-            logger.error(f"Error terminating process for job {job_id}: {e}")
+        exec_id = process_info.get("exec_id")
+        if exec_id and stop_script(exec_id):
+            logger.info(f"Stopped the script of job {job_id}")
     
-    # Final cleanup of any remaining processes and flag files
+    # Final cleanup of any remaining flag files
     try:
-        cleanup_all_processes()
-        logger.info("Cleaned up flag files and processes on shutdown")
+        cleanup_flag_files()
+        logger.info("Cleaned up flag files on shutdown")
     except Exception as e:
         logger.error(f"Error cleaning up on shutdown: {e}")
 
@@ -139,12 +166,69 @@ app.add_middleware(
 class PromptRequest(BaseModel):
     prompt: str = Field(..., description="The natural language prompt to process")
     max_retries: int = Field(3, description="Maximum number of retry attempts")
+    job_id: Optional[str] = Field(None, description="Job ID chosen by the client, so files can be attached before the prompt runs")
+    require_approval: bool = Field(False, description="Hold the generated script until the client approves it")
 
 class StopRequest(BaseModel):
     job_id: str = Field(..., description="ID of the job to stop")
+    reason: Optional[str] = Field(None, description="Why the job was stopped, if not by the user")
+
+class CompletionRequest(BaseModel):
+    prompt: str = Field(..., description="The prompt to complete as plain text")
+
+class EmbedRequest(BaseModel):
+    texts: List[str] = Field(..., description="The texts to embed")
+    task_type: str = Field("retrieval_document", description="Embedding task type (retrieval_document or retrieval_query)")
+
+class ToolCallRequest(BaseModel):
+    server: str = Field(..., description="ID of the MCP server in the shell's settings")
+    tool: str = Field(..., description="Name of the tool to call")
+    arguments: Dict[str, Any] = Field(default_factory=dict, description="Tool arguments")
+
+class JobRequest(BaseModel):
+    job_id: str = Field(..., description="ID of the job")
+
+class AttachRequest(BaseModel):
+    job_id: str = Field(..., description="ID of the job the file belongs to; it needn't be running yet")
+    name: str = Field(..., description="Original file name")
+    file: str = Field(..., description="Copy of the file in the shell's handoff directory")
+    size: int = Field(..., description="Size in bytes")
+    mime_type: str = Field("application/octet-stream", description="MIME type guessed from the extension")
+
+class WorkingDirectoryRequest(BaseModel):
+    path: Optional[str] = Field(None, description="Folder to confine file work to, or None to lift the restriction")
+
+class PauseRequest(BaseModel):
+    paused: bool = Field(..., description="Refuse new automations until this is cleared")
+
+class DryRunRequest(BaseModel):
+    dry_run: bool = Field(..., description="Generate scripts for new automations without running them")
+
+class ApprovalRequest(BaseModel):
+    job_id: str = Field(..., description="ID of the job waiting for approval")
+    attempt: int = Field(..., description="Attempt whose script was reviewed; a retry's new script needs its own")
+    approved: bool = Field(..., description="Whether the script may run")
+    reason: Optional[str] = Field(None, description="Why it may not, shown as the job's result")
+
+class ScreenshotRequest(BaseModel):
+    handoff: bool = Field(False, description="Return large captures as a handoff file path instead of base64")
+
+class HandshakeRequest(BaseModel):
+    shell_version: str = Field(..., description="Version of the Krya.ai shell")
+    protocol_versions: List[int] = Field(..., description="Protocol versions the shell speaks")
+    capabilities: List[str] = Field(default_factory=list, description="Features the shell offers the backend")
+    encodings: List[str] = Field(default_factory=list, description="Body encodings the shell supports, most preferred first")
+
+class RpcRequest(BaseModel):
+    jsonrpc: str = Field("2.0", description="JSON-RPC version")
+    id: Optional[Union[int, str]] = Field(None, description="Request ID echoed in the response")
+    method: str = Field(..., description="Method name, e.g. text.complete")
+    params: Dict[str, Any] = Field(default_factory=dict, description="Method parameters")
 
 class ConfigUpdateRequest(BaseModel):
     api_key: Optional[str] = Field(None, description="Google Gemini API key")
+    provider: Optional[str] = Field(None, description="Model provider (gemini or ollama)")
+    ollama_url: Optional[str] = Field(None, description="Base URL of the local Ollama server")
     model_name: Optional[str] = Field(None, description="Model name to use")
     temperature: Optional[float] = Field(None, description="Temperature for generation")
     max_output_tokens: Optional[int] = Field(None, description="Maximum output tokens")
@@ -155,19 +239,19 @@ class ConfigUpdateRequest(BaseModel):
 
 def save_config(config: Dict[str, Any]):
     """Save configuration to a JSON file"""
-    config_path = os.path.join(os.getcwd(), "config", "config.json")
+    config_path = os.path.join(get_config_dir(), "config.json")
     with open(config_path, "w") as f:
         json.dump(config, f, indent=2)
     
     # Also update .env file for API key
     if "api_key" in config and config["api_key"]:
-        env_path = os.path.join(os.getcwd(), ".env")
+        env_path = get_env_path()
         with open(env_path, "w") as f:
             f.write(f"GOOGLE_API_KEY={config['api_key']}")
 
 def load_config() -> Dict[str, Any]:
     """Load configuration from JSON file"""
-    config_path = os.path.join(os.getcwd(), "config", "config.json")
+    config_path = os.path.join(get_config_dir(), "config.json")
     if os.path.exists(config_path):
         with open(config_path, "r") as f:
             return json.load(f)
@@ -175,6 +259,7 @@ def load_config() -> Dict[str, Any]:
     # Default config
     default_config = {
         "api_key": os.getenv("GOOGLE_API_KEY", ""),
+        "provider": "gemini",
         "model_name": "gemini-2.5-flash",
         "temperature": 1.55,
         "max_output_tokens": 8192,
@@ -186,7 +271,90 @@ def load_config() -> Dict[str, Any]:
     save_config(default_config)
     return default_config
 
-async def execute_automation(job_id: str, prompt: str, max_retries: int = 3):
+def require_local_client(request: Request, feature: str):
+    """Reject requests that don't come from this machine (the server listens on all interfaces)"""
+    if request.client is None or request.client.host not in ("127.0.0.1", "::1", "localhost", "testclient"):
+        raise HTTPException(
+            status_code=status.HTTP_403_FORBIDDEN,
+            detail=f"{feature} is only available to local clients"
+        )
+
+def api_key_missing(config: Dict[str, Any]) -> bool:
+    """Check whether the configured provider needs an API key that isn't set (local models don't)"""
+    return config.get("provider", "gemini") == "gemini" and not config.get("api_key")
+
+def apply_shell_model_settings():
+    """Adopt the active model the shell passes in at spawn time, so native settings win over config.json"""
+    provider = os.getenv("KRYA_PROVIDER")
+    model_name = os.getenv("KRYA_MODEL")
+    if not provider or not model_name:
+        return
+    
+    config = load_config()
+    config["provider"] = provider
+    config["model_name"] = model_name
+    if os.getenv("KRYA_OLLAMA_URL"):
+        config["ollama_url"] = os.getenv("KRYA_OLLAMA_URL")
+    save_config(config)
+    logger.info(f"Using model from shell settings: {provider}/{model_name}")
+
+# How long a generated script may run before it's killed
+SCRIPT_TIMEOUT = 60
+
+# How long a script waits for the shell to approve it before it's refused
+APPROVAL_TIMEOUT = 600
+APPROVAL_POLL_INTERVAL = 0.25
+
+def job_progress(info: Dict[str, Any]) -> Optional[int]:
+    """Share of the script's time budget used so far, while it's executing"""
+    started = info.get("phase_started")
+    if info.get("status") != "running" or info.get("phase") != "executing" or started is None:
+        return None
+    return min(99, int(100 * (time.time() - started) / SCRIPT_TIMEOUT))
+
+def stream_publisher(job_id: str, attempt: int):
+    """Callback for the generating thread that publishes each piece of the model's reply as a delta entry"""
+    loop = asyncio.get_running_loop()
+    
+    def on_delta(text: str):
+        loop.call_soon_threadsafe(app_state.publish, {
+            "job_id": job_id,
+            "timestamp": datetime.now().isoformat(),
+            "kind": "delta",
+            "attempt": attempt,
+            "text": text
+        })
+    return on_delta
+
+async def wait_for_approval(job_id: str, attempt: int) -> Optional[str]:
+    """Hold a job's script until the shell has reviewed it. Returns why it may not run, or None if it may."""
+    info = app_state.active_processes[job_id]
+    info.update({"approval": None, "phase": "awaiting_approval", "phase_started": time.time()})
+    # So the shell reviews it even if it isn't following this job
+    app_state.publish({
+        "job_id": job_id,
+        "timestamp": datetime.now().isoformat(),
+        "kind": "awaiting_approval",
+        "attempt": attempt
+    })
+    app_state.add_log({
+        "job_id": job_id,
+        "timestamp": datetime.now().isoformat(),
+        "level": "INFO",
+        "message": "Waiting for the script to be approved..."
+    })
+    
+    deadline = time.time() + APPROVAL_TIMEOUT
+    while time.time() < deadline:
+        if info.get("status") == "stopped":
+            return "Stopped while waiting for approval"
+        approval = info.get("approval")
+        if approval is not None and approval["attempt"] == attempt:
+            return None if approval["approved"] else (approval.get("reason") or "The script was not approved")
+        await asyncio.sleep(APPROVAL_POLL_INTERVAL)
+    return f"The script was not approved within {APPROVAL_TIMEOUT} seconds"
+
+async def execute_automation(job_id: str, prompt: str, max_retries: int = 3, images: Optional[List[Dict[str, Any]]] = None):
     """Execute the automation process and update state"""
     if job_id not in app_state.active_processes:
         logger.error(f"Job {job_id} not found in active processes")
@@ -195,6 +363,7 @@ async def execute_automation(job_id: str, prompt: str, max_retries: int = 3):
     # Create a unique execution ID for this job
     execution_id = str(uuid.uuid4())
     app_state.active_processes[job_id]["execution_id"] = execution_id
+    working_directory = app_state.active_processes[job_id].get("working_directory")
     
     try:
         # Clean up any stale flag files before starting
@@ -215,6 +384,13 @@ async def execute_automation(job_id: str, prompt: str, max_retries: int = 3):
             return
             
         try:
+            app_state.active_processes[job_id].update({
+                "phase": "planning",
+                "phase_started": time.time(),
+                "attempt": attempt + 1,
+                "max_attempts": max_retries,
+            })
+            
             # Log the attempt
             app_state.add_log({
                 "job_id": job_id,
@@ -223,13 +399,14 @@ async def execute_automation(job_id: str, prompt: str, max_retries: int = 3):
                 "message": f"Attempt {attempt + 1}/{max_retries}: Generating code..."
             })
             
-            # Generate code
+            # Generate code off the event loop, streaming the reply to log clients as it arrives
+            on_delta = stream_publisher(job_id, attempt + 1)
             if attempt == 0:
-                generated_code = generate_code(prompt)
+                generated_code = await asyncio.to_thread(generate_code, prompt, images, on_delta)
             else:
                 # Use feedback from previous execution
                 feedback = app_state.active_processes[job_id].get("last_result", "")
-                generated_code = regenerate_code_with_feedback(prompt, feedback)
+                generated_code = await asyncio.to_thread(regenerate_code_with_feedback, prompt, feedback, images, on_delta)
             
             # Save the generated code
             app_state.active_processes[job_id]["code"] = generated_code
@@ -245,6 +422,36 @@ async def execute_automation(job_id: str, prompt: str, max_retries: int = 3):
                 logger.info(f"Job {job_id} was stopped after code generation")
                 return
             
+            # A dry run ends with the script to look at; nothing is run
+            if app_state.active_processes[job_id].get("dry_run"):
+                app_state.add_log({
+                    "job_id": job_id,
+                    "timestamp": datetime.now().isoformat(),
+                    "level": "SUCCESS",
+                    "message": "Dry run: the script was generated but not run"
+                })
+                app_state.active_processes[job_id].update({
+                    "status": "completed",
+                    "last_result": "🔍 Dry run: the script was not run"
+                })
+                return
+            
+            # The shell decides what may run, whatever the script turned out to do
+            if app_state.active_processes[job_id].get("require_approval"):
+                refusal = await wait_for_approval(job_id, attempt + 1)
+                if app_state.active_processes[job_id].get("status") == "stopped":
+                    logger.info(f"Job {job_id} was stopped while waiting for approval")
+                    return
+                if refusal:
+                    app_state.add_log({
+                        "job_id": job_id,
+                        "timestamp": datetime.now().isoformat(),
+                        "level": "ERROR",
+                        "message": refusal
+                    })
+                    app_state.active_processes[job_id].update({"status": "failed", "last_result": f"❌ {refusal}"})
+                    return
+            
             # Execute the code
             app_state.add_log({
                 "job_id": job_id,
@@ -253,120 +460,25 @@ async def execute_automation(job_id: str, prompt: str, max_retries: int = 3):
                 "message": "Running script with direct execution..."
             })
             
-            # Run the script asynchronously and store the process
-            try:
-                process, log_file = run_script_async()
-                app_state.active_processes[job_id]["process"] = process
-                app_state.active_processes[job_id]["log_file"] = log_file
-                
-                # Wait for process to complete or timeout
-                try:
-                    exit_code = process.wait(timeout=60)  # 60 second timeout
-                    
-                    # Check if job was stopped during execution
-                    if app_state.active_processes[job_id].get("status") == "stopped":
-                        logger.info(f"Job {job_id} was stopped during execution")
-                        return
-                    
-                    # Read log file for output
-                    with open(log_file, "r") as f:
-                        output = f.read()
-                    
-                    execution_result = f"Exit code: {exit_code}\n\nOutput:\n{output}"
-                    
-                    # Clean up temporary wrapper script if it exists
-                    if hasattr(process, '_wrapper_path') and os.path.exists(process._wrapper_path):
-                        try:
-                            os.unlink(process._wrapper_path)
-                        except Exception as e:
-                            logger.error(f"Error cleaning up wrapper script: {e}")
-                    
-                    # Clean up any execution flag files that might have been created
-                    flag_file = os.path.join(os.path.dirname(os.path.abspath(__file__)), ".execution_in_progress")
-                    if os.path.exists(flag_file):
-                        try:
-                            os.unlink(flag_file)
-                        except Exception as e:
-                            logger.error(f"Error cleaning up flag file: {e}")
-                    
-                    # Clean up any script flag files
-                    script_flag = os.path.join(os.path.dirname(os.path.abspath(__file__)), "generated_output", ".script_executed")
-                    if os.path.exists(script_flag):
-                        try:
-                            os.unlink(script_flag)
-                        except Exception as e:
-                            logger.error(f"Error cleaning up script flag: {e}")
-                    
-                    # Clean up timestamp file
-                    timestamp_file = os.path.join(os.path.dirname(os.path.abspath(__file__)), "generated_output", ".last_run_timestamp")
-                    if os.path.exists(timestamp_file):
-                        try:
-                            os.unlink(timestamp_file)
-                        except Exception as e:
-                            logger.error(f"Error cleaning up timestamp file: {e}")
-                            
-                except subprocess.TimeoutExpired:
-                    # Process took too long, kill it
-                    process.terminate()
-                    try:
-                        process.wait(timeout=5)
-                    except subprocess.TimeoutExpired:
-                        process.kill()
-                        
-                    # Also kill any child processes that might be running
-                    try:
-                        if os.name == 'nt':  # Windows
-                            subprocess.run(['taskkill', '/F', '/T', '/PID', str(process.pid)], 
-                                          stderr=subprocess.DEVNULL)
-                        else:  # Unix/Linux/Mac
-                            # More aggressive process killing for macOS
-                            subprocess.run(['pkill', '-P', str(process.pid)], 
-                                          stderr=subprocess.DEVNULL)
-                            # Also try to kill any Python processes running our script
-                            subprocess.run(['pkill', '-f', 'generated_output.py'], 
-                                          stderr=subprocess.DEVNULL)
-                            # Kill any PyAutoGUI processes
-                            subprocess.run(['pkill', '-f', 'pyautogui'], 
-                                          stderr=subprocess.DEVNULL)
-                    except Exception as e:
-                        logger.error(f"Error killing child processes: {e}")
-                    
-                    execution_result = "❌ Execution timed out after 60 seconds"
-                    
-                    # Clean up temporary wrapper script if it exists
-                    if hasattr(process, '_wrapper_path') and os.path.exists(process._wrapper_path):
-                        try:
-                            os.unlink(process._wrapper_path)
-                        except Exception as e:
-                            logger.error(f"Error cleaning up wrapper script: {e}")
-                    
-                    # Clean up any execution flag files that might have been created
-                    flag_file = os.path.join(os.path.dirname(os.path.abspath(__file__)), ".execution_in_progress")
-                    if os.path.exists(flag_file):
-                        try:
-                            os.unlink(flag_file)
-                        except Exception as e:
-                            logger.error(f"Error cleaning up flag file: {e}")
-                    
-                    # Clean up any script flag files
-                    script_flag = os.path.join(os.path.dirname(os.path.abspath(__file__)), "generated_output", ".script_executed")
-                    if os.path.exists(script_flag):
-                        try:
-                            os.unlink(script_flag)
-                        except Exception as e:
-                            logger.error(f"Error cleaning up script flag: {e}")
-                    
-                    # Clean up timestamp file
-                    timestamp_file = os.path.join(os.path.dirname(os.path.abspath(__file__)), "generated_output", ".last_run_timestamp")
-                    if os.path.exists(timestamp_file):
-                        try:
-                            os.unlink(timestamp_file)
-                        except Exception as e:
-                            logger.error(f"Error cleaning up timestamp file: {e}")
-            except Exception as e:
-                # Fallback to synchronous execution if async fails
-                logger.error(f"Async execution failed: {e}, falling back to sync execution")
-                execution_result = run_script()
+            app_state.active_processes[job_id]["phase"] = "executing"
+            app_state.active_processes[job_id]["phase_started"] = time.time()
+            
+            # Run the script through the shell, which holds it to the allowlist, times it out
+            # together with everything it starts, and audits it
+            step_log = os.path.join(get_log_dir(), "steps", f"{job_id}.jsonl")
+            exec_id = str(uuid.uuid4())
+            app_state.active_processes[job_id]["exec_id"] = exec_id
+            execution_result = await asyncio.to_thread(
+                run_script, cwd=working_directory, step_log=step_log, exec_id=exec_id
+            )
+            # The actions of the latest attempt, for replaying without generating the code again
+            app_state.active_processes[job_id]["steps"] = read_steps(step_log)
+            cleanup_flag_files()
+            
+            # Check if job was stopped during execution
+            if app_state.active_processes[job_id].get("status") == "stopped":
+                logger.info(f"Job {job_id} was stopped during execution")
+                return
             
             app_state.active_processes[job_id]["last_result"] = execution_result
             
@@ -380,8 +492,8 @@ async def execute_automation(job_id: str, prompt: str, max_retries: int = 3):
                 })
                 app_state.active_processes[job_id]["status"] = "completed"
                 
-                # Final cleanup of any remaining processes
-                cleanup_all_processes()
+                # Final cleanup of any leftover flag files
+                cleanup_flag_files()
                 return
             else:
                 app_state.add_log({
@@ -409,8 +521,8 @@ async def execute_automation(job_id: str, prompt: str, max_retries: int = 3):
                     if app_state.active_processes[job_id].get("status") != "stopped":
                         app_state.active_processes[job_id]["status"] = "failed"
                     
-                    # Final cleanup of any remaining processes
-                    cleanup_all_processes()
+                    # Final cleanup of any leftover flag files
+                    cleanup_flag_files()
                     return
                 
         except Exception as e:
@@ -425,23 +537,45 @@ async def execute_automation(job_id: str, prompt: str, max_retries: int = 3):
             if attempt == max_retries - 1:
                 app_state.active_processes[job_id]["status"] = "failed"
                 
-                # Final cleanup of any remaining processes
-                cleanup_all_processes()
+                # Final cleanup of any leftover flag files
+                cleanup_flag_files()
                 return
 
-def cleanup_all_processes():
-    """Kill any lingering processes and clean up flag files"""
+# Prompt for the self-test job: exercises the model without generating or running a script
+SELF_TEST_PROMPT = "Reply with the single word OK."
+
+async def execute_self_test(job_id: str):
+    """Run the self-test job, logging each step so streaming can be checked"""
+    app_state.add_log({
+        "job_id": job_id,
+        "timestamp": datetime.now().isoformat(),
+        "level": "INFO",
+        "message": "Self-test: contacting model..."
+    })
+    
+    try:
+        result = generate_text(SELF_TEST_PROMPT)
+        app_state.active_processes[job_id]["last_result"] = result
+        app_state.active_processes[job_id]["status"] = "completed"
+        app_state.add_log({
+            "job_id": job_id,
+            "timestamp": datetime.now().isoformat(),
+            "level": "SUCCESS",
+            "message": "Self-test completed successfully!"
+        })
+    except Exception as e:
+        app_state.active_processes[job_id]["last_result"] = str(e)
+        app_state.active_processes[job_id]["status"] = "failed"
+        app_state.add_log({
+            "job_id": job_id,
+            "timestamp": datetime.now().isoformat(),
+            "level": "ERROR",
+            "message": f"Self-test failed: {str(e)}"
+        })
+
+def cleanup_flag_files():
+    """Clean up flag files left by generated scripts"""
     try:
-        # Kill any Python processes that might be running our script
-        if os.name == 'nt':  # Windows
-            subprocess.run(['taskkill', '/F', '/IM', 'python.exe', '/FI', 'WINDOWTITLE eq generated_output.py'], 
-                          stderr=subprocess.DEVNULL)
-        else:  # Unix/Linux/Mac
-            # Find and kill any Python processes that might be running our script
-            subprocess.run(['pkill', '-f', 'generated_output.py'], stderr=subprocess.DEVNULL)
-            # Also try to kill any PyAutoGUI processes
-            subprocess.run(['pkill', '-f', 'pyautogui'], stderr=subprocess.DEVNULL)
-        
         # Clean up any execution flag files
         flag_file = os.path.join(os.path.dirname(os.path.abspath(__file__)), ".execution_in_progress")
         if os.path.exists(flag_file):
@@ -458,7 +592,7 @@ def cleanup_all_processes():
             os.unlink(timestamp_file)
             
     except Exception as e:
-        logger.error(f"Error in cleanup_all_processes: {e}")
+        logger.error(f"Error in cleanup_flag_files: {e}")
 
 # --- API Endpoints ---
 
@@ -473,9 +607,20 @@ async def run_automation(
     background_tasks: BackgroundTasks
 ):
     """Run automation based on a natural language prompt"""
+    if is_worker():
+        raise HTTPException(
+            status_code=status.HTTP_409_CONFLICT,
+            detail="This backend is a worker and only answers model calls. Run automations on the main backend."
+        )
+    if app_state.paused:
+        raise HTTPException(
+            status_code=status.HTTP_423_LOCKED,
+            detail="Automation is paused. Resume it from the tray to run prompts."
+        )
+    
     # Check if API key is configured
     config = load_config()
-    if not config.get("api_key"):
+    if api_key_missing(config):
         raise HTTPException(
             status_code=status.HTTP_400_BAD_REQUEST,
             detail="API key not configured. Please set up your API key first."
@@ -496,8 +641,14 @@ async def run_automation(
             
             return {"job_id": job_id, "status": "running", "message": "This prompt is already being processed"}
     
-    # Generate a unique job ID
-    job_id = str(uuid.uuid4())
+    # Use the client's job ID if it attached files under one, otherwise generate a unique one
+    job_id = request.job_id or str(uuid.uuid4())
+    if job_id in app_state.active_processes:
+        raise HTTPException(
+            status_code=status.HTTP_409_CONFLICT,
+            detail=f"Job {job_id} already exists"
+        )
+    attachments = app_state.attachments.pop(job_id, [])
     
     # Initialize job in active processes
     app_state.active_processes[job_id] = {
@@ -505,9 +656,26 @@ async def run_automation(
         "status": "running",
         "start_time": datetime.now().isoformat(),
         "code": None,
-        "last_result": None
+        "last_result": None,
+        "attachments": [attachment["name"] for attachment in attachments],
+        "working_directory": app_state.working_directory,
+        "dry_run": app_state.dry_run,
+        "require_approval": request.require_approval or approval_required()
     }
     
+    prompt = prompt_with_attachments(request.prompt, attachments)
+    if app_state.working_directory:
+        prompt += (
+            f"\n\nOnly read or write files inside {app_state.working_directory}. "
+            "The script runs with it as the current directory."
+        )
+        try:
+            repo = await asyncio.to_thread(mcp_tools.repo_context)
+            if repo:
+                prompt += "\n\n" + describe_repo(repo)
+        except Exception as e:
+            logger.warning(f"Could not get repository context: {e}")
+    
     # Log the start of the job
     app_state.add_log({
         "job_id": job_id,
@@ -520,12 +688,82 @@ async def run_automation(
     background_tasks.add_task(
         execute_automation, 
         job_id=job_id, 
-        prompt=request.prompt,
-        max_retries=request.max_retries
+        prompt=prompt,
+        max_retries=request.max_retries,
+        images=image_parts(attachments)
     )
     
     return {"job_id": job_id, "status": "running"}
 
+@app.post("/attach")
+async def attach_file(request: AttachRequest):
+    """Attach a file the shell copied into its handoff directory to a job's prompt"""
+    if not is_handoff_path(request.file) or not os.path.isfile(request.file):
+        raise HTTPException(
+            status_code=status.HTTP_400_BAD_REQUEST,
+            detail=f"Not a handoff file: {request.file}"
+        )
+    if request.job_id in app_state.active_processes:
+        raise HTTPException(
+            status_code=status.HTTP_409_CONFLICT,
+            detail=f"Job {request.job_id} has already started"
+        )
+    
+    app_state.attachments.setdefault(request.job_id, []).append(request.dict())
+    logger.info(f"Attached {request.name} ({request.size} bytes) to job {request.job_id}")
+    return {"status": "success", "message": f"Attached {request.name}"}
+
+@app.post("/session/working-directory")
+async def set_working_directory(request: WorkingDirectoryRequest):
+    """Confine the file work of jobs started from now on to a folder the user chose in the shell"""
+    if request.path is not None and not os.path.isdir(request.path):
+        raise HTTPException(
+            status_code=status.HTTP_400_BAD_REQUEST,
+            detail=f"Not a folder: {request.path}"
+        )
+    
+    app_state.working_directory = request.path
+    if request.path:
+        logger.info(f"Working directory set to {request.path}")
+        return {"status": "success", "message": f"Working in {request.path}"}
+    logger.info("Working directory cleared")
+    return {"status": "success", "message": "Working directory cleared"}
+
+@app.post("/session/pause")
+async def set_paused(request: PauseRequest):
+    """Pause or resume automation; jobs already running are left to finish"""
+    app_state.paused = request.paused
+    logger.info("Automation paused" if request.paused else "Automation resumed")
+    return {"status": "success", "message": "Automation paused" if request.paused else "Automation resumed"}
+
+@app.post("/session/dry-run")
+async def set_dry_run(request: DryRunRequest):
+    """Generate scripts for new automations without running them, or go back to running them"""
+    app_state.dry_run = request.dry_run
+    message = "Dry run on" if request.dry_run else "Dry run off"
+    logger.info(message)
+    return {"status": "success", "message": message}
+
+@app.post("/jobs/approve")
+async def approve_job(request: ApprovalRequest):
+    """Let a job's generated script run, or refuse it, once the shell has reviewed it"""
+    if request.job_id not in app_state.active_processes:
+        raise HTTPException(
+            status_code=status.HTTP_404_NOT_FOUND,
+            detail=f"Job {request.job_id} not found"
+        )
+    
+    info = app_state.active_processes[request.job_id]
+    if info.get("phase") != "awaiting_approval" or info.get("attempt") != request.attempt:
+        raise HTTPException(
+            status_code=status.HTTP_409_CONFLICT,
+            detail=f"Job {request.job_id} isn't waiting for approval of attempt {request.attempt}"
+        )
+    
+    info["approval"] = {"attempt": request.attempt, "approved": request.approved, "reason": request.reason}
+    message = "Script approved" if request.approved else "Script refused"
+    return {"status": "success", "message": message}
+
 @app.post("/stop")
 async def stop_automation(request: StopRequest):
     """Stop a running automation job"""
@@ -537,77 +775,26 @@ async def stop_automation(request: StopRequest):
     
     job_info = app_state.active_processes[request.job_id]
     job_info["status"] = "stopped"  # Mark as stopped immediately
+    if request.reason:
+        job_info["last_result"] = f"❌ {request.reason}"
     
     # Log the stop request
     app_state.add_log({
         "job_id": request.job_id,
         "timestamp": datetime.now().isoformat(),
-        "level": "INFO",
-        "message": "Stopping job by user request..."
+        "level": "ERROR" if request.reason else "INFO",
+        "message": f"Stopping job: {request.reason}" if request.reason else "Stopping job by user request..."
     })
     
-    # Check if the job has a process that can be terminated
-    if "process" in job_info and job_info["process"]:
-        try:
-            # Try to terminate the process
-            if job_info["process"].poll() is None:
-                job_info["process"].terminate()
-                
-                # Wait a bit and kill if still running
-                time.sleep(0.5)
-                if job_info["process"].poll() is None:
-                    if os.name == 'nt':  # Windows
-                        job_info["process"].kill()
-                        # Also kill any child processes
-                        subprocess.run(['taskkill', '/F', '/T', '/PID', str(job_info["process"].pid)], 
-                                      stderr=subprocess.DEVNULL)
-                    else:  # Unix/Linux/Mac
-                        import signal
-                        try:
-                            os.killpg(os.getpgid(job_info["process"].pid), signal.SIGKILL)
-                        except:
-                            job_info["process"].kill()
-                        
-                        # More aggressive process killing for macOS
-                        try:
-                            subprocess.run(['pkill', '-P', str(job_info["process"].pid)], 
-                                          stderr=subprocess.DEVNULL)
-                            # Also kill any Python processes that might be running our script
-                            subprocess.run(['pkill', '-f', 'generated_output.py'], 
-                                          stderr=subprocess.DEVNULL)
-                        except Exception as e:
-                            logger.error(f"Error killing child processes: {e}")
-            
-            app_state.add_log({
-                "job_id": request.job_id,
-                "timestamp": datetime.now().isoformat(),
-                "level": "SUCCESS",
-                "message": "Job terminated successfully"
-            })
-            
-            # Clean up any temporary files
-            if hasattr(job_info["process"], '_wrapper_path') and os.path.exists(job_info["process"]._wrapper_path):
-                try:
-                    os.unlink(job_info["process"]._wrapper_path)
-                except Exception as e:
-                    logger.error(f"Error cleaning up wrapper script: {e}")
-            
-            # Clean up any execution flag files
-            flag_file = os.path.join(os.path.dirname(os.path.abspath(__file__)), ".execution_in_progress")
-            if os.path.exists(flag_file):
-                try:
-                    os.unlink(flag_file)
-                except Exception as e:
-                    logger.error(f"Error cleaning up flag file: {e}")
-                    
-        except Exception as e:
-            app_state.add_log({
-                "job_id": request.job_id,
-                "timestamp": datetime.now().isoformat(),
-                "level": "WARNING",
-                "message": f"Error while terminating process: {str(e)}"
-            })
-            logger.error(f"Failed to terminate process: {e}")
+    # The shell stops the script with everything it started
+    exec_id = job_info.get("exec_id")
+    if exec_id and stop_script(exec_id):
+        app_state.add_log({
+            "job_id": request.job_id,
+            "timestamp": datetime.now().isoformat(),
+            "level": "SUCCESS",
+            "message": "Job terminated successfully"
+        })
     else:
         app_state.add_log({
             "job_id": request.job_id,
@@ -616,18 +803,6 @@ async def stop_automation(request: StopRequest):
             "message": "No active process found to terminate"
         })
     
-    # Additionally, try to kill any PyAutoGUI processes that might be running
-    try:
-        if os.name == 'nt':  # Windows
-            subprocess.run(['taskkill', '/F', '/IM', 'python.exe', '/FI', 'WINDOWTITLE eq generated_output.py'], stderr=subprocess.DEVNULL)
-        else:  # Unix/Linux/Mac
-            # Find and kill any Python processes that might be running our script
-            subprocess.run(['pkill', '-f', 'generated_output.py'], stderr=subprocess.DEVNULL)
-            # Also try to kill any PyAutoGUI processes
-            subprocess.run(['pkill', '-f', 'pyautogui'], stderr=subprocess.DEVNULL)
-    except Exception as e:
-        logger.error(f"Failed to kill additional processes: {e}")
-    
     return {"status": "stopped", "job_id": request.job_id}
 
 @app.get("/status")
@@ -661,6 +836,134 @@ async def get_status():
         "recent_logs": recent_logs
     }
 
+@app.get("/jobs/{job_id}")
+async def get_job(job_id: str):
+    """Get the details, generated code, result, and logs of a single job"""
+    if job_id not in app_state.active_processes:
+        raise HTTPException(
+            status_code=status.HTTP_404_NOT_FOUND,
+            detail=f"Job {job_id} not found"
+        )
+    
+    info = app_state.active_processes[job_id]
+    return {
+        "job_id": job_id,
+        "prompt": info.get("prompt"),
+        "status": info.get("status"),
+        "start_time": info.get("start_time"),
+        "code": info.get("code"),
+        "last_result": info.get("last_result"),
+        "working_directory": info.get("working_directory"),
+        "dry_run": info.get("dry_run", False),
+        "phase": info.get("phase"),
+        "attempt": info.get("attempt"),
+        "max_attempts": info.get("max_attempts"),
+        "progress": job_progress(info),
+        "steps": info.get("steps", []),
+        "logs": [log for log in app_state.recent_logs if log.get("job_id") == job_id]
+    }
+
+@app.get("/doctor")
+async def doctor():
+    """Diagnose the backend environment (used by the shell's support bundle)"""
+    config = load_config()
+    report = run_diagnostics(api_key_configured=not api_key_missing(config))
+    report["recent_logs"] = app_state.recent_logs[-50:]
+    return report
+
+@app.get("/capabilities")
+async def capabilities():
+    """Report which optional features (vision, voice, code execution, screenshots) are available"""
+    return detect_capabilities(load_config())
+
+@app.post("/self-test")
+async def self_test(background_tasks: BackgroundTasks):
+    """Submit a trivial job that goes through the model and log stream but never touches the UI"""
+    config = load_config()
+    if api_key_missing(config):
+        raise HTTPException(
+            status_code=status.HTTP_400_BAD_REQUEST,
+            detail="API key not configured. Please set up your API key first."
+        )
+    
+    job_id = str(uuid.uuid4())
+    app_state.active_processes[job_id] = {
+        "prompt": SELF_TEST_PROMPT,
+        "status": "running",
+        "start_time": datetime.now().isoformat(),
+        "code": None,
+        "last_result": None
+    }
+    
+    background_tasks.add_task(execute_self_test, job_id=job_id)
+    return {"job_id": job_id, "status": "running"}
+
+@app.post("/complete")
+async def complete_text(request: CompletionRequest):
+    """Generate a plain text completion (used by the shell's text expansion)"""
+    config = load_config()
+    if api_key_missing(config):
+        raise HTTPException(
+            status_code=status.HTTP_400_BAD_REQUEST,
+            detail="API key not configured. Please set up your API key first."
+        )
+    
+    try:
+        text = generate_text(request.prompt)
+    except Exception as e:
+        logger.error(f"Completion failed: {e}")
+        raise HTTPException(
+            status_code=status.HTTP_500_INTERNAL_SERVER_ERROR,
+            detail=f"Completion failed: {str(e)}"
+        )
+    
+    return {"text": text}
+
+@app.post("/embed")
+async def embed(request: EmbedRequest):
+    """Embed texts for the shell's local document index"""
+    config = load_config()
+    if not config.get("api_key"):
+        raise HTTPException(
+            status_code=status.HTTP_400_BAD_REQUEST,
+            detail="API key not configured. Please set up your API key first."
+        )
+    
+    try:
+        embeddings = embed_texts(request.texts, request.task_type)
+    except Exception as e:
+        logger.error(f"Embedding failed: {e}")
+        raise HTTPException(
+            status_code=status.HTTP_500_INTERNAL_SERVER_ERROR,
+            detail=f"Embedding failed: {str(e)}"
+        )
+    
+    return {"embeddings": embeddings}
+
+@app.get("/tools")
+async def list_tools():
+    """List tools from the user's MCP servers, reached through the shell's proxy"""
+    try:
+        return {"tools": mcp_tools.list_tools()}
+    except Exception as e:
+        logger.error(f"Failed to list MCP tools: {e}")
+        raise HTTPException(
+            status_code=status.HTTP_503_SERVICE_UNAVAILABLE,
+            detail=f"MCP tools unavailable: {str(e)}"
+        )
+
+@app.post("/tools/call")
+async def call_tool(request: ToolCallRequest):
+    """Call a tool on one of the user's MCP servers"""
+    try:
+        return mcp_tools.call_tool(request.server, request.tool, request.arguments)
+    except Exception as e:
+        logger.error(f"MCP tool call failed: {e}")
+        raise HTTPException(
+            status_code=status.HTTP_502_BAD_GATEWAY,
+            detail=f"Tool call failed: {str(e)}"
+        )
+
 @app.get("/config")
 async def get_config():
     """Get the current configuration"""
@@ -674,6 +977,34 @@ async def get_config():
     
     return config
 
+@app.get("/config/export")
+async def export_config(request: Request):
+    """Get the unmasked configuration so the shell can bundle it for migration (local clients only)"""
+    require_local_client(request, "Configuration export")
+    return load_config()
+
+@app.get("/screenshot")
+async def screenshot(request: Request, handoff: bool = False):
+    """Capture the screen for the shell's MCP server (local clients only)"""
+    require_local_client(request, "Screenshots")
+    
+    try:
+        if not handoff:
+            return {"image": screenshot_base64()}
+        
+        # Large captures go through a file the shell reads and deletes, instead of megabytes of base64
+        image = screenshot_png()
+        path = write_handoff(image, "png") if len(image) >= HANDOFF_THRESHOLD else None
+        if path is not None:
+            return {"file": path}
+        return {"image": base64.b64encode(image).decode("ascii")}
+    except Exception as e:
+        logger.error(f"Screenshot failed: {e}")
+        raise HTTPException(
+            status_code=status.HTTP_500_INTERNAL_SERVER_ERROR,
+            detail=f"Screenshot failed: {str(e)}"
+        )
+
 @app.post("/config")
 async def update_config(request: ConfigUpdateRequest):
     """Update the configuration"""
@@ -690,6 +1021,117 @@ async def update_config(request: ConfigUpdateRequest):
     
     return {"status": "success", "message": "Configuration updated successfully"}
 
+# --- JSON-RPC ---
+
+# Versions of the shell <-> backend contract this server speaks, oldest first
+PROTOCOL_VERSIONS = [1]
+
+# Features this backend offers the shell
+BACKEND_CAPABILITIES = ["automation", "completion", "embeddings", "mcp_tools", "screenshot", "self_test"]
+
+async def handshake(request: HandshakeRequest):
+    """Agree on the newest protocol version both sides speak (None if there is none)"""
+    common = sorted(set(request.protocol_versions) & set(PROTOCOL_VERSIONS))
+    app_state.shell = request.dict()
+    
+    if common:
+        logger.info(f"Shell {request.shell_version} connected on protocol {common[-1]}")
+    else:
+        logger.error(
+            f"Shell {request.shell_version} speaks protocol {request.protocol_versions}, "
+            f"this backend speaks {PROTOCOL_VERSIONS}"
+        )
+    
+    return {
+        "backend_version": app.version,
+        "protocol_version": common[-1] if common else None,
+        "protocol_versions": PROTOCOL_VERSIONS,
+        "capabilities": BACKEND_CAPABILITIES,
+        "encoding": choose_encoding(request.encodings)
+    }
+
+# Methods the shell calls through /rpc/v1, mapped to (params model, handler); the typed
+# counterparts live in the shell's protocol.rs, so keep both sides in step
+RPC_METHODS = {
+    "system.ping": (None, lambda params, request, tasks: root()),
+    "system.handshake": (HandshakeRequest, lambda params, request, tasks: handshake(params)),
+    "system.doctor": (None, lambda params, request, tasks: doctor()),
+    "system.capabilities": (None, lambda params, request, tasks: capabilities()),
+    "config.get": (None, lambda params, request, tasks: get_config()),
+    "config.export": (None, lambda params, request, tasks: export_config(request)),
+    "config.update": (ConfigUpdateRequest, lambda params, request, tasks: update_config(params)),
+    "jobs.get": (JobRequest, lambda params, request, tasks: get_job(params.job_id)),
+    "jobs.run": (PromptRequest, lambda params, request, tasks: run_automation(params, tasks)),
+    "jobs.selfTest": (None, lambda params, request, tasks: self_test(tasks)),
+    "jobs.attach": (AttachRequest, lambda params, request, tasks: attach_file(params)),
+    "jobs.approve": (ApprovalRequest, lambda params, request, tasks: approve_job(params)),
+    "jobs.stop": (StopRequest, lambda params, request, tasks: stop_automation(params)),
+    "session.setWorkingDirectory": (WorkingDirectoryRequest, lambda params, request, tasks: set_working_directory(params)),
+    "session.setPaused": (PauseRequest, lambda params, request, tasks: set_paused(params)),
+    "session.setDryRun": (DryRunRequest, lambda params, request, tasks: set_dry_run(params)),
+    "text.complete": (CompletionRequest, lambda params, request, tasks: complete_text(params)),
+    "text.embed": (EmbedRequest, lambda params, request, tasks: embed(params)),
+    "desktop.screenshot": (ScreenshotRequest, lambda params, request, tasks: screenshot(request, params.handoff)),
+}
+
+def rpc_error(request_id, code: int, message: str, data: Optional[Dict[str, Any]] = None):
+    error = {"code": code, "message": message}
+    if data is not None:
+        error["data"] = data
+    return {"jsonrpc": "2.0", "id": request_id, "error": error}
+
+@app.post("/rpc/v1")
+async def rpc(request: Request, background_tasks: BackgroundTasks):
+    """Versioned JSON-RPC 2.0 entry point for the shell"""
+    payload = await dispatch_rpc(request, background_tasks)
+    
+    # Large results (screenshots, file contents) are compressed if the shell accepts it
+    data = json.dumps(jsonable_encoder(payload)).encode("utf-8")
+    encoding = accepted_encoding(request.headers.get("accept-encoding"))
+    if encoding and len(data) >= COMPRESSION_THRESHOLD:
+        return Response(
+            content=compress(data, encoding),
+            media_type="application/json",
+            headers={"Content-Encoding": encoding}
+        )
+    return Response(content=data, media_type="application/json")
+
+async def dispatch_rpc(request: Request, background_tasks: BackgroundTasks) -> Dict[str, Any]:
+    try:
+        data = await request.body()
+        encoding = request.headers.get("content-encoding")
+        if encoding:
+            data = decompress(data, encoding.strip().lower())
+        body = json.loads(data)
+    except Exception as e:
+        return rpc_error(None, -32700, f"Parse error: {str(e)}")
+    
+    try:
+        call = RpcRequest(**body) if isinstance(body, dict) else None
+    except ValidationError:
+        call = None
+    if call is None:
+        return rpc_error(body.get("id") if isinstance(body, dict) else None, -32600, "Invalid request")
+    
+    if call.method not in RPC_METHODS:
+        return rpc_error(call.id, -32601, f"Method not found: {call.method}")
+    params_model, handler = RPC_METHODS[call.method]
+    
+    try:
+        params = params_model(**call.params) if params_model else None
+    except ValidationError as e:
+        return rpc_error(call.id, -32602, str(e))
+    
+    try:
+        result = await handler(params, request, background_tasks)
+    except HTTPException as e:
+        return rpc_error(call.id, -32000, str(e.detail), {"status": e.status_code})
+    except Exception as e:
+        logger.error(f"RPC method {call.method} failed: {e}")
+        return rpc_error(call.id, -32603, f"Internal error: {str(e)}")
+    
+    return {"jsonrpc": "2.0", "id": call.id, "result": result}
+
 @app.websocket("/logs")
 async def websocket_endpoint(websocket: WebSocket):
     """WebSocket endpoint for real-time logs"""
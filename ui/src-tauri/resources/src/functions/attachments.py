@@ -0,0 +1,69 @@
+import logging
+from typing import Dict, Any, List
+
+logger = logging.getLogger("krya-attachments")
+
+# Characters of each text attachment included in the prompt; longer files are cut off
+MAX_INLINE_CHARS = 20000
+
+# Non-text/* types whose content is still readable text
+TEXT_MIME_TYPES = ("application/json", "application/xml")
+
+def is_text(attachment: Dict[str, Any]) -> bool:
+    mime_type = attachment.get("mime_type", "")
+    return mime_type.startswith("text/") or mime_type in TEXT_MIME_TYPES
+
+def describe_attachment(attachment: Dict[str, Any]) -> str:
+    name = attachment["name"]
+    if not is_text(attachment):
+        return f"Attached file {name} ({attachment['mime_type']}, {attachment['size']} bytes) is at: {attachment['file']}"
+
+    try:
+        with open(attachment["file"], "r", encoding="utf-8", errors="replace") as f:
+            content = f.read(MAX_INLINE_CHARS + 1)
+    except OSError as e:
+        logger.warning(f"Could not read attachment {name}: {e}")
+        return f"Attached file {name} could not be read."
+
+    if len(content) > MAX_INLINE_CHARS:
+        content = content[:MAX_INLINE_CHARS] + "\n[...truncated]"
+    return f"Attached file {name} (full copy at {attachment['file']}):\n```\n{content}\n```"
+
+def is_image(attachment: Dict[str, Any]) -> bool:
+    return attachment.get("mime_type", "").startswith("image/")
+
+def image_parts(attachments: List[Dict[str, Any]]) -> List[Dict[str, Any]]:
+    """Load attached images as model input parts, so vision models see them directly"""
+    parts = []
+    for attachment in attachments:
+        if not is_image(attachment):
+            continue
+        try:
+            with open(attachment["file"], "rb") as f:
+                parts.append({"mime_type": attachment["mime_type"], "data": f.read()})
+        except OSError as e:
+            logger.warning(f"Could not read image {attachment['name']}: {e}")
+    return parts
+
+def describe_repo(repo: Dict[str, Any]) -> str:
+    """Summarize a git repository's state from the shell for the prompt"""
+    branch = repo.get("branch") or "a detached HEAD"
+    lines = [f"The working directory is a git repository at {repo['root']}, on {branch}."]
+    dirty = repo.get("dirty", [])
+    if dirty:
+        lines.append("Uncommitted changes:")
+        lines.extend(f"- {entry['status']}: {entry['path']}" for entry in dirty)
+        if repo.get("dirty_truncated"):
+            lines.append("- ...and more")
+    commits = repo.get("commits", [])
+    if commits:
+        lines.append("Recent commits:")
+        lines.extend(f"- {commit['id']} {commit['summary']}" for commit in commits)
+    return "\n".join(lines)
+
+def prompt_with_attachments(prompt: str, attachments: List[Dict[str, Any]]) -> str:
+    """Append attached files to a prompt: text inline, anything else by path for the script to open"""
+    if not attachments:
+        return prompt
+    sections = [describe_attachment(attachment) for attachment in attachments]
+    return prompt + "\n\n" + "\n\n".join(sections)
@@ -0,0 +1,75 @@
+import os
+import sys
+import shutil
+import importlib.util
+import logging
+from typing import Dict, Any, Tuple
+
+from utils import get_output_dir
+
+logger = logging.getLogger("krya-capabilities")
+
+# Ollama model families that accept images
+OLLAMA_VISION_MODELS = ("llava", "bakllava", "llama3.2-vision", "moondream", "minicpm-v", "gemma3", "qwen2.5vl")
+
+# Speech-to-text packages the backend can use for voice input
+VOICE_PACKAGES = ("speech_recognition", "whisper", "vosk")
+
+def module_available(module: str) -> bool:
+    try:
+        return importlib.util.find_spec(module) is not None
+    except (ImportError, ValueError):
+        return False
+
+def detect_vision(config: Dict[str, Any]) -> Tuple[bool, str]:
+    provider = config.get("provider", "gemini")
+    model_name = config.get("model_name", "")
+    if provider == "gemini":
+        return True, f"{model_name} accepts images"
+    if any(model_name.startswith(family) for family in OLLAMA_VISION_MODELS):
+        return True, f"{model_name} accepts images"
+    return False, f"{model_name} is a text-only model"
+
+def detect_voice() -> Tuple[bool, str]:
+    installed = [package for package in VOICE_PACKAGES if module_available(package)]
+    if installed:
+        return True, f"Using {installed[0]}"
+    return False, f"No speech-to-text package installed ({', '.join(VOICE_PACKAGES)})"
+
+def detect_code_exec() -> Tuple[bool, str]:
+    # Generated scripts are run with python3 (see functions/exec.py)
+    interpreter = shutil.which("python3")
+    if interpreter is None:
+        return False, "python3 not found on PATH"
+    output_dir = get_output_dir()
+    if not os.access(output_dir if os.path.isdir(output_dir) else os.getcwd(), os.W_OK):
+        return False, f"{output_dir} is not writable"
+    return True, interpreter
+
+def detect_screenshot() -> Tuple[bool, str]:
+    if not module_available("pyautogui"):
+        return False, "pyautogui is not installed"
+    if sys.platform.startswith("linux") and not os.environ.get("DISPLAY"):
+        return False, "No X11 display (screen capture isn't supported on Wayland-only sessions)"
+    return True, "pyautogui"
+
+def detect_capabilities(config: Dict[str, Any]) -> Dict[str, Any]:
+    """
+    Work out which optional features this backend can serve right now
+
+    Args:
+        config: The current model configuration
+
+    Returns:
+        One flag per capability, plus a human-readable detail for each
+    """
+    detected = {
+        "vision": detect_vision(config),
+        "voice": detect_voice(),
+        "code_exec": detect_code_exec(),
+        "screenshot": detect_screenshot(),
+    }
+    
+    result: Dict[str, Any] = {name: available for name, (available, _) in detected.items()}
+    result["details"] = {name: detail for name, (_, detail) in detected.items()}
+    return result
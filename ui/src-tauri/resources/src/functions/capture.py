@@ -0,0 +1,18 @@
+import io
+import base64
+import logging
+
+logger = logging.getLogger("krya-capture")
+
+def screenshot_png() -> bytes:
+    """Capture the primary screen as PNG bytes"""
+    import pyautogui
+
+    image = pyautogui.screenshot()
+    buffer = io.BytesIO()
+    image.save(buffer, format="PNG")
+    return buffer.getvalue()
+
+def screenshot_base64() -> str:
+    """Capture the primary screen and return it as a base64-encoded PNG"""
+    return base64.b64encode(screenshot_png()).decode("ascii")
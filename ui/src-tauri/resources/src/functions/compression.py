@@ -0,0 +1,45 @@
+import gzip
+from typing import List, Optional
+
+try:
+    import zstandard
+except ImportError:  # Optional; gzip is always available
+    zstandard = None
+
+# Bodies smaller than this go out as-is; compressing them costs more than it saves on loopback
+COMPRESSION_THRESHOLD = 16 * 1024
+
+def supported_encodings() -> List[str]:
+    """Body encodings this backend can read and write, most preferred first"""
+    return ["zstd", "gzip"] if zstandard is not None else ["gzip"]
+
+def choose_encoding(offered: List[str]) -> Optional[str]:
+    """Pick the shell's most preferred encoding that we also support"""
+    supported = supported_encodings()
+    return next((name for name in offered if name in supported), None)
+
+def accepted_encoding(header: Optional[str]) -> Optional[str]:
+    """Pick our most preferred encoding from an Accept-Encoding header"""
+    if not header:
+        return None
+    offered = []
+    for part in header.split(","):
+        fields = [field.strip() for field in part.split(";")]
+        if "q=0" not in (field.replace(" ", "") for field in fields[1:]):
+            offered.append(fields[0].lower())
+    return next((name for name in supported_encodings() if name in offered), None)
+
+def compress(data: bytes, encoding: str) -> bytes:
+    if encoding == "zstd" and zstandard is not None:
+        return zstandard.ZstdCompressor(level=3).compress(data)
+    if encoding == "gzip":
+        return gzip.compress(data, compresslevel=1)
+    raise ValueError(f"Unsupported encoding: {encoding}")
+
+def decompress(data: bytes, encoding: str) -> bytes:
+    if encoding == "zstd" and zstandard is not None:
+        # Streaming decompression, since the frame may not record its size
+        return zstandard.ZstdDecompressor().decompressobj().decompress(data)
+    if encoding == "gzip":
+        return gzip.decompress(data)
+    raise ValueError(f"Unsupported encoding: {encoding}")
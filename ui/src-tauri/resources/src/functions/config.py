@@ -2,15 +2,18 @@ import os
 import google.generativeai as genai
 from dotenv import load_dotenv
 import logging
-from typing import Dict, Any, Optional
+from typing import Dict, Any, Optional, Union
+
+from functions.ollama_model import OllamaModel, DEFAULT_OLLAMA_URL
 
 # Import from utils
-from utils import get_full_path, load_json_config, save_json_config
+from utils import get_config_dir, get_env_path, get_full_path, load_json_config, save_json_config
 
 logger = logging.getLogger("krya-config")
 
 # Default configuration
 DEFAULT_CONFIG = {
+    "provider": "gemini",
     "model_name": "gemini-2.5-flash",
         "temperature": 1.55,
         "top_p": 0.95,
@@ -44,7 +47,7 @@ def get_api_key() -> Optional[str]:
         API key as a string, or None if not found
     """
     # Ensure environment variables are loaded
-    load_dotenv()
+    load_dotenv(get_env_path())
     return os.getenv("GOOGLE_API_KEY")
 
 def load_model_config() -> Dict[str, Any]:
@@ -54,7 +57,7 @@ def load_model_config() -> Dict[str, Any]:
     Returns:
         Model configuration as a dictionary
     """
-    config_path = os.path.join(os.getcwd(), "config", "config.json")
+    config_path = os.path.join(get_config_dir(), "config.json")
     config = load_json_config(config_path, DEFAULT_CONFIG)
     
     # Ensure all required keys are present
@@ -74,16 +77,23 @@ def save_model_config(config: Dict[str, Any]) -> bool:
     Returns:
         True if successful, False otherwise
     """
-    config_path = os.path.join(os.getcwd(), "config", "config.json")
+    config_path = os.path.join(get_config_dir(), "config.json")
     return save_json_config(config_path, config)
 
-def configure_model() -> genai.GenerativeModel:
+def configure_model(use_system_instruction: bool = True) -> Union[genai.GenerativeModel, OllamaModel]:
     """
-    Configure and return a GenerativeModel instance
+    Configure and return a model for the configured provider
     
+    Args:
+        use_system_instruction: Whether to apply the code generation system instruction
+        
     Returns:
-        Configured GenerativeModel instance
+        Configured GenerativeModel instance, or an OllamaModel for the local provider
     """
+    config = load_model_config()
+    if config.get("provider") == "ollama":
+        return configure_ollama_model(config, use_system_instruction)
+    
     # Get API key
     api_key = get_api_key()
     if not api_key:
@@ -93,27 +103,37 @@ def configure_model() -> genai.GenerativeModel:
     # Configure genai with API key
     genai.configure(api_key=api_key)
     
-    # Load model configuration
-    config = load_model_config()
-    
-    # Get system instruction
-    instruct = get_system_instruction()
-    
-    # Create generation config
-    generation_config = {
-        "temperature": config.get("temperature", DEFAULT_CONFIG["temperature"]),
-        "top_p": config.get("top_p", DEFAULT_CONFIG["top_p"]),
-        "top_k": config.get("top_k", DEFAULT_CONFIG["top_k"]),
-        "max_output_tokens": config.get("max_output_tokens", DEFAULT_CONFIG["max_output_tokens"]),
-        "response_mime_type": config.get("response_mime_type", DEFAULT_CONFIG["response_mime_type"]),
-    }
+    # Get system instruction (plain text completions don't use it)
+    instruct = get_system_instruction() if use_system_instruction else None
     
     # Create and return model
     model = genai.GenerativeModel(
         model_name=config.get("model_name", DEFAULT_CONFIG["model_name"]),
-        generation_config=generation_config,
+        generation_config=generation_config_from(config),
         system_instruction=instruct,
     )
     
     logger.info(f"Model configured: {config.get('model_name', DEFAULT_CONFIG['model_name'])}")
     return model
+
+def configure_ollama_model(config: Dict[str, Any], use_system_instruction: bool = True) -> OllamaModel:
+    """Configure a model served by a local Ollama install (no API key needed)"""
+    model = OllamaModel(
+        model_name=config.get("model_name", DEFAULT_CONFIG["model_name"]),
+        base_url=config.get("ollama_url") or DEFAULT_OLLAMA_URL,
+        generation_config=generation_config_from(config),
+        system_instruction=get_system_instruction() if use_system_instruction else None,
+    )
+    
+    logger.info(f"Ollama model configured: {model.model_name}")
+    return model
+
+def generation_config_from(config: Dict[str, Any]) -> Dict[str, Any]:
+    """Build the generation config from the model configuration"""
+    return {
+        "temperature": config.get("temperature", DEFAULT_CONFIG["temperature"]),
+        "top_p": config.get("top_p", DEFAULT_CONFIG["top_p"]),
+        "top_k": config.get("top_k", DEFAULT_CONFIG["top_k"]),
+        "max_output_tokens": config.get("max_output_tokens", DEFAULT_CONFIG["max_output_tokens"]),
+        "response_mime_type": config.get("response_mime_type", DEFAULT_CONFIG["response_mime_type"]),
+    }
@@ -0,0 +1,132 @@
+import os
+import sys
+import platform
+import importlib.util
+import logging
+from typing import Dict, Any, List
+
+from utils import get_config_dir
+
+logger = logging.getLogger("krya-doctor")
+
+# Modules the backend needs, mapped to the distribution that provides them
+REQUIRED_PACKAGES = {
+    "fastapi": "fastapi",
+    "uvicorn": "uvicorn",
+    "dotenv": "python-dotenv",
+    "google.generativeai": "google-generativeai",
+    "pyautogui": "pyautogui",
+    "pydantic": "pydantic",
+    "psutil": "psutil",
+}
+
+# How much of each recent script log is included in the report
+SCRIPT_LOG_TAIL_BYTES = 4096
+
+def check_packages() -> List[Dict[str, Any]]:
+    """Check that each required package can be imported and report its version"""
+    from importlib import metadata
+
+    results = []
+    for module, distribution in REQUIRED_PACKAGES.items():
+        try:
+            installed = importlib.util.find_spec(module) is not None
+        except (ImportError, ValueError):
+            installed = False
+
+        version = None
+        if installed:
+            try:
+                version = metadata.version(distribution)
+            except metadata.PackageNotFoundError:
+                pass
+
+        results.append({"name": distribution, "installed": installed, "version": version})
+    return results
+
+def recent_script_logs(log_dir: str, limit: int = 5) -> List[Dict[str, str]]:
+    """Get the tail of the most recent generated-script logs"""
+    if not os.path.isdir(log_dir):
+        return []
+
+    names = sorted(
+        (name for name in os.listdir(log_dir) if name.endswith(".log")),
+        reverse=True
+    )[:limit]
+
+    logs = []
+    for name in names:
+        try:
+            with open(os.path.join(log_dir, name), "rb") as f:
+                f.seek(0, os.SEEK_END)
+                f.seek(max(0, f.tell() - SCRIPT_LOG_TAIL_BYTES))
+                content = f.read().decode("utf-8", errors="replace")
+            logs.append({"name": name, "content": content})
+        except OSError as e:
+            logger.warning(f"Could not read log {name}: {e}")
+    return logs
+
+def accelerator_hints() -> Dict[str, Any]:
+    """Get the accelerators the shell detected, passed in as environment variables at spawn time"""
+    def available(name: str) -> bool:
+        return os.environ.get(name) == "1"
+    
+    return {
+        "preferred": os.environ.get("KRYA_ACCELERATOR", "cpu"),
+        "cuda": available("KRYA_CUDA_AVAILABLE"),
+        "metal": available("KRYA_METAL_AVAILABLE"),
+        "directml": available("KRYA_DIRECTML_AVAILABLE")
+    }
+
+def run_diagnostics(api_key_configured: bool) -> Dict[str, Any]:
+    """
+    Build a report of the backend's environment for troubleshooting
+
+    Args:
+        api_key_configured: Whether an API key is set in the config
+
+    Returns:
+        Dictionary with environment details and a list of pass/fail checks
+    """
+    base_dir = os.getcwd()
+    config_dir = get_config_dir()
+    packages = check_packages()
+    missing = [p["name"] for p in packages if not p["installed"]]
+
+    checks = [
+        {
+            "name": "python_version",
+            "ok": sys.version_info >= (3, 8),
+            "detail": platform.python_version()
+        },
+        {
+            "name": "packages",
+            "ok": not missing,
+            "detail": f"Missing: {', '.join(missing)}" if missing else "All required packages installed"
+        },
+        {
+            "name": "api_key",
+            "ok": api_key_configured,
+            "detail": "API key configured" if api_key_configured else "API key not configured"
+        },
+        {
+            "name": "config_writable",
+            "ok": os.access(config_dir if os.path.isdir(config_dir) else base_dir, os.W_OK),
+            "detail": config_dir
+        },
+    ]
+
+    return {
+        "ok": all(check["ok"] for check in checks),
+        "checks": checks,
+        "python": {
+            "version": platform.python_version(),
+            "executable": sys.executable,
+            "implementation": platform.python_implementation()
+        },
+        "platform": platform.platform(),
+        "working_directory": base_dir,
+        "packages": packages,
+        "accelerator": accelerator_hints(),
+        "script_logs": recent_script_logs(os.path.join(base_dir, "logs"))
+    }
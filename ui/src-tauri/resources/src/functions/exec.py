@@ -1,221 +1,78 @@
 import os
-import time
-import pyautogui
-import subprocess
-import tempfile
-from datetime import datetime
+import sys
 import logging
-from typing import Dict, Any, Tuple, Optional
-import uuid
+from typing import Dict, Any, Optional
 
 # Import from utils
-from utils import format_execution_result, execute_subprocess
+from utils import format_execution_result, get_output_dir
+from functions import mcp_tools
 
 logger = logging.getLogger("krya-exec")
 
-def run_script(script_path: Optional[str] = None) -> str:
+# Runs a script with its actions recorded
+STEPS_RUNNER = os.path.join(os.path.dirname(os.path.abspath(__file__)), "steps.py")
+
+def script_command(script_path: str, step_log: Optional[str] = None) -> Dict[str, Any]:
     """
-    Execute a Python script directly using subprocess
-    
+    The program and arguments that run a script with this backend's interpreter
+
     Args:
-        script_path: Path to the script to execute. If None, uses default path.
-        
+        script_path: Path to the script to run
+        step_log: File to record the script's clicks, keystrokes and file accesses into (see functions/steps.py)
+
     Returns:
-        Formatted execution result as a string
+        {"command", "args"}; the command is what the user has to allow in the shell's settings
     """
-    try:
-        if script_path is None:
-            script_path = os.path.join(os.getcwd(), "generated_output", "generated_output.py")
-        
-        # Ensure the script exists
-        if not os.path.exists(script_path):
-            return f"❌ Script not found at {script_path}"
-        
-        # Execute the script using subprocess
-        exit_code, stdout, stderr = execute_subprocess(
-            ["python3", script_path],
-            timeout=30
-        )
-        
-        # Format and return the result
-        return format_execution_result(exit_code, stdout, stderr)
-            
-    except Exception as e:
-        logger.error(f"Error in run_script: {e}")
-        return f"❌ An error occurred during script execution: {str(e)}"
+    args = [script_path]
+    if step_log is not None:
+        os.makedirs(os.path.dirname(os.path.abspath(step_log)), exist_ok=True)
+        args = [STEPS_RUNNER, os.path.abspath(step_log), script_path]
+    return {"command": sys.executable, "args": args}
 
-def run_script_with_terminal_fallback(script_path: Optional[str] = None) -> str:
+def run_script(
+    script_path: Optional[str] = None,
+    cwd: Optional[str] = None,
+    step_log: Optional[str] = None,
+    exec_id: Optional[str] = None,
+) -> str:
     """
-    Fallback method using terminal automation for interactive scripts
-    
+    Run a Python script through the shell, which only runs allowed programs, times them out
+    together with everything they start, and audits each run
+
     Args:
         script_path: Path to the script to execute. If None, uses default path.
-        
+        cwd: Directory to run the script in (the session's working directory, if set)
+        step_log: File to record the script's actions into
+        exec_id: Id to stop the run by with stop_script
+
     Returns:
-        Status message
+        Formatted execution result as a string
     """
     try:
         if script_path is None:
-            script_path = os.path.join(os.getcwd(), "generated_output", "generated_output.py")
+            script_path = os.path.join(get_output_dir(), "generated_output.py")
         
         # Ensure the script exists
         if not os.path.exists(script_path):
             return f"❌ Script not found at {script_path}"
         
-        # Use macOS key handling method for Spotlight
-        pyautogui.keyDown('command')
-        pyautogui.press('space')
-        pyautogui.keyUp('command')
-        time.sleep(1)
-        
-        pyautogui.typewrite('terminal', interval=0.1)
-        pyautogui.press('enter')
-        time.sleep(2)
-        
-        # Open new terminal tab with correct macOS key handling
-        pyautogui.keyDown('command')
-        pyautogui.press('t')
-        pyautogui.keyUp('command')
-        time.sleep(2)
-        
-        # Run the script
-        pyautogui.typewrite(f'python3 "{script_path}"', interval=0.01)
-        pyautogui.press('enter')
-        
-        time.sleep(3)  # Give time for execution
-        
-        return "Script execution initiated via terminal. Check terminal window for results."
-        
+        command = script_command(script_path, step_log)
+        result = mcp_tools.run_command(command["command"], command["args"], cwd=cwd, exec_id=exec_id)
+        if result.get("timed_out"):
+            return "❌ Execution timed out"
+        if result.get("stopped"):
+            return "❌ Execution was stopped"
+        exit_code = result.get("exit_code")
+        return format_execution_result(-1 if exit_code is None else exit_code, result.get("stdout", ""), result.get("stderr", ""))
+            
     except Exception as e:
-        logger.error(f"Error in run_script_with_terminal_fallback: {e}")
-        return f"❌ Terminal execution failed: {str(e)}"
+        logger.error(f"Error in run_script: {e}")
+        return f"❌ An error occurred during script execution: {str(e)}"
 
-def run_script_async(script_path: Optional[str] = None) -> Tuple[subprocess.Popen, str]:
-    """
-    Execute a Python script asynchronously and return the process object
-    
-    Args:
-        script_path: Path to the script to execute. If None, uses default path.
-        
-    Returns:
-        Tuple of (process object, log file path)
-    """
+def stop_script(exec_id: str) -> bool:
+    """Have the shell stop a script started by run_script, with everything it started; False if it wasn't running"""
     try:
-        if script_path is None:
-            script_path = os.path.join(os.getcwd(), "generated_output", "generated_output.py")
-        
-        # Ensure the script exists
-        if not os.path.exists(script_path):
-            raise FileNotFoundError(f"Script not found at {script_path}")
-        
-        # Create a log file
-        log_dir = os.path.join(os.getcwd(), "logs")
-        os.makedirs(log_dir, exist_ok=True)
-        log_file = os.path.join(log_dir, f"script_{datetime.now().strftime('%Y%m%d_%H%M%S')}.log")
-        
-        # Start the process
-        with open(log_file, "w") as f:
-            # Add a timeout wrapper script with one-time execution flag
-            wrapper_script = f"""
-import sys
-import os
-import time
-import signal
-import subprocess
-import uuid
-
-# Generate a unique execution ID for this run
-EXECUTION_ID = str(uuid.uuid4())
-
-# Create a flag file to prevent multiple executions
-flag_file = os.path.join(os.path.dirname(os.path.abspath(__file__)), ".execution_in_progress")
-exec_flag_file = os.path.join(os.path.dirname(os.path.abspath(__file__)), ".last_execution_id")
-
-# Check if another instance is running
-if os.path.exists(flag_file):
-    # Check if the flag file is stale (older than 2 minutes)
-    if time.time() - os.path.getmtime(flag_file) > 120:
-        os.remove(flag_file)
-    else:
-        print("Another execution is already in progress. Exiting.")
-        sys.exit(0)
-
-# Create the flag file
-with open(flag_file, 'w') as ff:
-    ff.write(str(time.time()))
-
-# Store the execution ID
-with open(exec_flag_file, 'w') as ef:
-    ef.write(EXECUTION_ID)
-
-def cleanup_flag():
-    if os.path.exists(flag_file):
-        try:
-            os.remove(flag_file)
-        except:
-            pass
-
-def timeout_handler(signum, frame):
-    print("Script execution timed out after 30 seconds")
-    cleanup_flag()
-    sys.exit(1)
-
-# Set timeout handler
-signal.signal(signal.SIGALRM, timeout_handler)
-signal.alarm(30)  # 30 second timeout
-
-try:
-    # Execute the actual script with the execution ID as an environment variable
-    env = os.environ.copy()
-    env['KRYA_EXECUTION_ID'] = EXECUTION_ID
-    
-    result = subprocess.run(
-        [sys.executable, "{script_path}"], 
-        stdout=subprocess.PIPE, 
-        stderr=subprocess.PIPE, 
-        text=True,
-        check=False,
-        env=env
-    )
-    
-    # Print output
-    print(result.stdout)
-    if result.stderr:
-        print("ERRORS:", file=sys.stderr)
-        print(result.stderr, file=sys.stderr)
-    
-    # Exit with the same code
-    cleanup_flag()
-    sys.exit(result.returncode)
-except Exception as e:
-    print(f"Error executing script: {{e}}")
-    cleanup_flag()
-    sys.exit(1)
-finally:
-    # Cancel the alarm
-    signal.alarm(0)
-    cleanup_flag()
-"""
-            # Create a temporary wrapper script
-            import tempfile
-            wrapper_fd, wrapper_path = tempfile.mkstemp(suffix='.py')
-            with os.fdopen(wrapper_fd, 'w') as wrapper_file:
-                wrapper_file.write(wrapper_script)
-            
-            # Execute the wrapper script instead
-            process = subprocess.Popen(
-                ["python3", wrapper_path],
-                stdout=f,
-                stderr=subprocess.STDOUT,
-                text=True,
-                bufsize=1
-            )
-            
-            # Store the wrapper path to clean up later
-            process._wrapper_path = wrapper_path
-        
-        return process, log_file
-        
+        return mcp_tools.stop_command(exec_id)
     except Exception as e:
-        logger.error(f"Error in run_script_async: {e}")
-        raise
+        logger.error(f"Error in stop_script: {e}")
+        return False
@@ -1,20 +1,37 @@
 import os
 import google.generativeai as genai
-from functions.config import configure_model
+from functions.config import configure_model, get_api_key
 import logging
-from typing import Optional
+from typing import Any, Callable, Dict, List, Optional
 
 # Import from utils
-from utils import ensure_dir_exists
+from utils import ensure_dir_exists, get_output_dir
 
 logger = logging.getLogger("krya-gen")
 
-def generate_code(prompt: str) -> str:
+def send_message(chat_session, message: List[Any], on_delta: Optional[Callable[[str], None]] = None) -> str:
+    """Send a chat message, passing each piece of the reply to on_delta as it arrives"""
+    if on_delta is None:
+        return chat_session.send_message(message).text
+    
+    text = ""
+    for chunk in chat_session.send_message(message, stream=True):
+        text += chunk.text
+        on_delta(chunk.text)
+    return text
+
+def generate_code(
+    prompt: str,
+    images: Optional[List[Dict[str, Any]]] = None,
+    on_delta: Optional[Callable[[str], None]] = None
+) -> str:
     """
     Generate code based on a natural language prompt
     
     Args:
         prompt: The natural language prompt
+        images: Attached images as {"mime_type", "data"} parts, for vision models
+        on_delta: Called with each piece of the model's reply as it streams in
         
     Returns:
         Generated Python code as a string
@@ -32,8 +49,7 @@ def generate_code(prompt: str) -> str:
             ]
         )
         
-        response = chat_session.send_message(prompt)
-        generated_code = response.text
+        generated_code = send_message(chat_session, [prompt] + (images or []), on_delta)
         
         # Clean the response
         generated_code = clean_code_response(generated_code)
@@ -48,13 +64,20 @@ def generate_code(prompt: str) -> str:
         logger.error(f"Error generating code: {e}")
         raise
 
-def regenerate_code_with_feedback(original_prompt: str, execution_feedback: str) -> str:
+def regenerate_code_with_feedback(
+    original_prompt: str,
+    execution_feedback: str,
+    images: Optional[List[Dict[str, Any]]] = None,
+    on_delta: Optional[Callable[[str], None]] = None
+) -> str:
     """
     Generate improved code based on execution feedback
     
     Args:
         original_prompt: The original natural language prompt
         execution_feedback: Feedback from previous execution attempt
+        images: Attached images sent with the original prompt
+        on_delta: Called with each piece of the model's reply as it streams in
         
     Returns:
         Improved Python code as a string
@@ -88,8 +111,7 @@ def regenerate_code_with_feedback(original_prompt: str, execution_feedback: str)
             ]
         )
         
-        response = chat_session.send_message(feedback_prompt)
-        improved_code = response.text
+        improved_code = send_message(chat_session, [feedback_prompt] + (images or []), on_delta)
         
         # Clean and save improved code
         improved_code = clean_code_response(improved_code)
@@ -102,6 +124,54 @@ def regenerate_code_with_feedback(original_prompt: str, execution_feedback: str)
         logger.error(f"Error regenerating code: {e}")
         raise
 
+def generate_text(prompt: str) -> str:
+    """
+    Generate a plain text completion for a prompt
+    
+    Args:
+        prompt: The prompt to complete
+        
+    Returns:
+        The model's response text
+    """
+    try:
+        logger.info(f"Generating text for prompt: {prompt[:50]}...")
+        model = configure_model(use_system_instruction=False)
+        response = model.generate_content(prompt)
+        return response.text.strip()
+    
+    except Exception as e:
+        logger.error(f"Error generating text: {e}")
+        raise
+
+def embed_texts(texts: List[str], task_type: str = "retrieval_document") -> List[List[float]]:
+    """
+    Embed a batch of texts for semantic search
+    
+    Args:
+        texts: The texts to embed
+        task_type: "retrieval_document" for indexed content, "retrieval_query" for search queries
+        
+    Returns:
+        One embedding vector per input text
+    """
+    api_key = get_api_key()
+    if not api_key:
+        raise ValueError("API key not found")
+    
+    try:
+        genai.configure(api_key=api_key)
+        result = genai.embed_content(
+            model="models/text-embedding-004",
+            content=texts,
+            task_type=task_type
+        )
+        return result["embedding"]
+    
+    except Exception as e:
+        logger.error(f"Error embedding texts: {e}")
+        raise
+
 def clean_code_response(code_text: str) -> str:
     """
     Clean the LLM response to extract pure Python code
@@ -141,7 +211,7 @@ def save_generated_code(code: str, file_path: Optional[str] = None) -> str:
         Path where the code was saved
     """
     if file_path is None:
-        output_dir = os.path.join(os.getcwd(), "generated_output")
+        output_dir = get_output_dir()
         ensure_dir_exists(output_dir)
         file_path = os.path.join(output_dir, "generated_output.py")
     
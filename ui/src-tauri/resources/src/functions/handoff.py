@@ -0,0 +1,57 @@
+import os
+import uuid
+import logging
+from typing import Optional
+
+logger = logging.getLogger("krya-handoff")
+
+# Payloads at least this big go through a file instead of base64 in the JSON body
+HANDOFF_THRESHOLD = 1024 * 1024
+
+def handoff_dir() -> Optional[str]:
+    """Get the private directory the shell shares files through, passed in at spawn time"""
+    directory = os.getenv("KRYA_HANDOFF_DIR")
+    return directory if directory and os.path.isdir(directory) else None
+
+def is_handoff_path(path: str) -> bool:
+    """Check that a path the shell gave us really is one of its handoff files"""
+    directory = handoff_dir()
+    if directory is None:
+        return False
+    return os.path.dirname(os.path.realpath(path)) == os.path.realpath(directory)
+
+def read_handoff(path: str, remove: bool = True) -> bytes:
+    """
+    Read a handoff file from the shell
+
+    Args:
+        path: Path from the shell's message
+        remove: Delete the file once read (for one-shot transfers; job files are cleaned up by the shell)
+
+    Returns:
+        The file contents
+    """
+    if not is_handoff_path(path):
+        raise ValueError(f"Not a handoff file: {path}")
+
+    with open(path, "rb") as f:
+        data = f.read()
+    if remove:
+        try:
+            os.remove(path)
+        except OSError as e:
+            logger.warning(f"Could not remove handoff file {path}: {e}")
+    return data
+
+def write_handoff(data: bytes, extension: str) -> Optional[str]:
+    """Write a payload for the shell to pick up, or return None if there's no handoff directory"""
+    directory = handoff_dir()
+    if directory is None:
+        return None
+
+    path = os.path.join(directory, f"{uuid.uuid4().hex}.{extension}")
+    # Only this user may read it, like the directory itself
+    fd = os.open(path, os.O_WRONLY | os.O_CREAT | os.O_EXCL, 0o600)
+    with os.fdopen(fd, "wb") as f:
+        f.write(data)
+    return path
@@ -0,0 +1,126 @@
+import os
+import json
+import logging
+import urllib.request
+import urllib.error
+from typing import Dict, Any, List, Optional
+
+from functions.compression import COMPRESSION_THRESHOLD, supported_encodings, compress, decompress
+from functions.handoff import read_handoff
+
+logger = logging.getLogger("krya-mcp")
+
+def proxy_settings() -> Optional[Dict[str, str]]:
+    """Get the shell's MCP proxy address and token, passed in at spawn time"""
+    url = os.getenv("KRYA_MCP_PROXY_URL")
+    token = os.getenv("KRYA_MCP_PROXY_TOKEN")
+    if not url or not token:
+        return None
+    return {"url": url.rstrip("/"), "token": token}
+
+def proxy_request(path: str, body: Optional[Dict[str, Any]] = None, timeout: float = 130) -> Dict[str, Any]:
+    """Send a request to the shell's MCP proxy"""
+    settings = proxy_settings()
+    if settings is None:
+        raise RuntimeError("MCP proxy is not available (backend not started by the Krya shell)")
+
+    headers = {
+        "Content-Type": "application/json",
+        "X-Krya-Token": settings["token"],
+        "Accept-Encoding": ", ".join(supported_encodings())
+    }
+    data = json.dumps(body).encode("utf-8") if body is not None else None
+    if data is not None and len(data) >= COMPRESSION_THRESHOLD:
+        encoding = supported_encodings()[0]
+        data = compress(data, encoding)
+        headers["Content-Encoding"] = encoding
+
+    request = urllib.request.Request(
+        f"{settings['url']}{path}",
+        data=data,
+        headers=headers,
+        method="POST" if body is not None else "GET"
+    )
+
+    try:
+        with urllib.request.urlopen(request, timeout=timeout) as response:
+            content = response.read()
+            encoding = response.headers.get("Content-Encoding")
+            if encoding:
+                content = decompress(content, encoding.strip().lower())
+            result = json.loads(content.decode("utf-8"))
+            # Very large results (e.g. images from a tool) come as a file the shell wrote for us
+            if isinstance(result, dict) and set(result) == {"handoff"}:
+                result = json.loads(read_handoff(result["handoff"]).decode("utf-8"))
+            return result
+    except urllib.error.HTTPError as e:
+        detail = e.read().decode("utf-8", errors="replace")
+        raise RuntimeError(f"MCP proxy returned {e.code}: {detail}")
+
+def list_tools() -> List[Dict[str, Any]]:
+    """List the tools of every connected MCP server"""
+    return proxy_request("/mcp/tools", timeout=10).get("tools", [])
+
+def call_tool(server: str, tool: str, arguments: Optional[Dict[str, Any]] = None) -> Dict[str, Any]:
+    """
+    Call a tool on one of the user's MCP servers
+
+    Args:
+        server: ID of the MCP server in the shell's settings
+        tool: Name of the tool
+        arguments: Tool arguments matching its input schema
+
+    Returns:
+        The MCP tool result ({"content": [...], "isError": bool})
+    """
+    logger.info(f"Calling MCP tool {server}/{tool}")
+    return proxy_request("/mcp/call", {"server": server, "tool": tool, "arguments": arguments or {}})
+
+def repo_context() -> Optional[Dict[str, Any]]:
+    """Get branch, changed files and recent commits of the working directory's git repository, if any"""
+    return proxy_request("/context/repo", timeout=10).get("repo")
+
+def run_command(
+    command: str,
+    args: Optional[List[str]] = None,
+    cwd: Optional[str] = None,
+    exec_id: Optional[str] = None,
+) -> Dict[str, Any]:
+    """
+    Run a program through the shell, which only runs those the user allowed and audits each run
+
+    Args:
+        command: Program name as on the shell's allowlist, or its full path
+        args: Arguments, passed as they are without a shell
+        cwd: Folder to run in; must be one the user allowed
+        exec_id: Id to stop it by with stop_command; the shell makes one up if not given
+
+    Returns:
+        {"exec_id", "exit_code", "timed_out", "stopped", "stdout", "stderr"}; exit_code is None if it was killed
+    """
+    logger.info(f"Running {command} through the shell")
+    body = {"command": command, "args": args or [], "cwd": cwd}
+    if exec_id:
+        body["exec_id"] = exec_id
+    # The shell enforces the command's own timeout; this only has to outlast it
+    return proxy_request("/exec", body, timeout=3600)
+
+def stop_command(exec_id: str) -> bool:
+    """Stop a command started by run_command, and everything it started; False if it had already finished"""
+    return bool(proxy_request("/exec/stop", {"exec_id": exec_id}, timeout=10).get("stopped"))
+
+def _file_request(operation: str, path: str, contents: Optional[str] = None) -> Dict[str, Any]:
+    """Have the shell touch a file, within the folders and size limit the user allowed"""
+    return proxy_request("/files", {"operation": operation, "path": path, "contents": contents}, timeout=60)
+
+def read_file(path: str) -> str:
+    """Read a text file through the shell"""
+    return _file_request("read", path)["contents"]
+
+def write_file(path: str, contents: str) -> str:
+    """Replace a file's contents through the shell, returning where it was written"""
+    return _file_request("write", path, contents)["path"]
+
+def append_file(path: str, contents: str) -> str:
+    """Append to a file through the shell, returning where it was written"""
+    return _file_request("append", path, contents)["path"]
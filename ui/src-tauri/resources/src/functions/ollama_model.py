@@ -0,0 +1,94 @@
+import json
+import base64
+import logging
+import urllib.request
+from typing import Dict, Any, List, Optional, Union
+
+logger = logging.getLogger("krya-ollama")
+
+DEFAULT_OLLAMA_URL = "http://localhost:11434"
+
+class OllamaResponse:
+    """Response with the same `text` attribute as a Gemini response"""
+    def __init__(self, text: str):
+        self.text = text
+
+    def __iter__(self):
+        # Replies aren't streamed from Ollama, so a "stream" is the whole reply as one chunk
+        return iter([self])
+
+class OllamaChat:
+    """Chat session mirroring genai's ChatSession.send_message"""
+    def __init__(self, model: "OllamaModel", history: Optional[List[Dict[str, Any]]] = None):
+        self.model = model
+        self.messages = [to_ollama_message(entry) for entry in (history or [])]
+
+    def send_message(self, message: Union[str, List[Any]], stream: bool = False) -> OllamaResponse:
+        parts = message if isinstance(message, list) else [message]
+        self.messages.append(to_ollama_message({"role": "user", "parts": parts}))
+        text = self.model.chat(self.messages)
+        self.messages.append({"role": "assistant", "content": text})
+        return OllamaResponse(text)
+
+def to_ollama_message(entry: Dict[str, Any]) -> Dict[str, Any]:
+    """Convert a Gemini-style history entry ({"role", "parts"}) to an Ollama chat message"""
+    role = "assistant" if entry.get("role") == "model" else entry.get("role", "user")
+    parts = entry.get("parts", [])
+    # Image parts are {"mime_type", "data"} blobs; Ollama takes them base64-encoded alongside the text
+    images = [base64.b64encode(part["data"]).decode("ascii") for part in parts if isinstance(part, dict)]
+    content = "\n".join(str(part) for part in parts if not isinstance(part, dict))
+    message = {"role": role, "content": content}
+    if images:
+        message["images"] = images
+    return message
+
+class OllamaModel:
+    """Stand-in for genai.GenerativeModel backed by a local Ollama server"""
+    def __init__(
+        self,
+        model_name: str,
+        base_url: str = DEFAULT_OLLAMA_URL,
+        generation_config: Optional[Dict[str, Any]] = None,
+        system_instruction: Optional[str] = None
+    ):
+        self.model_name = model_name
+        self.base_url = base_url.rstrip("/")
+        self.generation_config = generation_config or {}
+        self.system_instruction = system_instruction
+
+    def options(self) -> Dict[str, Any]:
+        """Map Gemini generation settings to Ollama options"""
+        config = self.generation_config
+        options = {
+            "temperature": config.get("temperature"),
+            "top_p": config.get("top_p"),
+            "top_k": config.get("top_k"),
+            "num_predict": config.get("max_output_tokens"),
+        }
+        return {key: value for key, value in options.items() if value is not None}
+
+    def chat(self, messages: List[Dict[str, str]]) -> str:
+        if self.system_instruction:
+            messages = [{"role": "system", "content": self.system_instruction}] + messages
+
+        body = json.dumps({
+            "model": self.model_name,
+            "messages": messages,
+            "stream": False,
+            "options": self.options()
+        }).encode("utf-8")
+        request = urllib.request.Request(
+            f"{self.base_url}/api/chat",
+            data=body,
+            headers={"Content-Type": "application/json"}
+        )
+
+        with urllib.request.urlopen(request, timeout=300) as response:
+            data = json.loads(response.read().decode("utf-8"))
+        return data.get("message", {}).get("content", "")
+
+    def generate_content(self, prompt: str) -> OllamaResponse:
+        return OllamaResponse(self.chat([{"role": "user", "content": prompt}]))
+
+    def start_chat(self, history: Optional[List[Dict[str, Any]]] = None) -> OllamaChat:
+        return OllamaChat(self, history)
@@ -2,7 +2,7 @@ import base64
 import pyperclip
 import streamlit as st
 from functions.gen import generate_code, regenerate_code_with_feedback
-from functions.exec import run_script, run_script_with_terminal_fallback
+from functions.exec import run_script
 import os
 from dotenv import load_dotenv
 import re
@@ -23,13 +23,8 @@ def process_prompt(prompt, max_retries=3):
             
             st.success("✅ Code generated successfully!")
             
-            # Determine execution method based on code content
-            if requires_interactive_execution(generated_code):
-                st.info("🖥️ Detected interactive script - using terminal execution...")
-                execution_message = run_script_with_terminal_fallback()
-            else:
-                st.info("⚡ Running script with direct execution...")
-    execution_message = run_script()
+            st.info("⚡ Running script through the shell...")
+            execution_message = run_script()
             
             # Store execution result in session state for potential retry
             st.session_state['last_execution_result'] = execution_message
@@ -0,0 +1,232 @@
+"""
+Record the concrete actions a generated script performs.
+
+Run as `python3 steps.py <step log> <script>`: the script runs as usual, but its pyautogui calls,
+sleeps and the files it opens are appended to the step log as JSON lines, e.g.
+{"action": "click", "x": 120, "y": 40, "button": "left", "clicks": 1}. The shell keeps them as a
+script the user can inspect, edit and replay without generating the code again.
+
+When the shell runs it, each pyautogui input call first asks the shell whether input may go to the
+application in front, so blocklisted apps are refused before a click or keystroke reaches them.
+"""
+import builtins
+import json
+import os
+import runpy
+import sys
+import threading
+import time
+import urllib.error
+import urllib.request
+from typing import Any, Dict, List
+
+# pyautogui calls itself (doubleClick clicks, write presses, everything sleeps); only the
+# outermost call is one of the script's actions
+_state = threading.local()
+
+_open = builtins.open
+_sleep = time.sleep
+
+
+def read_steps(path: str) -> List[Dict[str, Any]]:
+    """Read a step log, skipping lines a crashed script left half written"""
+    steps = []
+    try:
+        with _open(path, "r") as f:
+            for line in f:
+                try:
+                    steps.append(json.loads(line))
+                except ValueError:
+                    continue
+    except OSError:
+        pass
+    return steps
+
+
+class InputRefused(Exception):
+    """The shell won't let input go to the application in front"""
+
+
+def check_input():
+    """Ask the shell whether input may go to the application in front; raises InputRefused if not"""
+    url = os.getenv("KRYA_MCP_PROXY_URL")
+    token = os.getenv("KRYA_MCP_PROXY_TOKEN")
+    if not url or not token:
+        return
+    request = urllib.request.Request(
+        f"{url.rstrip('/')}/input/check",
+        data=b"{}",
+        headers={"Content-Type": "application/json", "X-Krya-Token": token},
+        method="POST",
+    )
+    try:
+        with urllib.request.urlopen(request, timeout=5):
+            return
+    except urllib.error.HTTPError as e:
+        try:
+            reason = json.loads(e.read().decode("utf-8")).get("error")
+        except ValueError:
+            reason = None
+        raise InputRefused(reason or f"The shell refused input ({e.code})")
+    except OSError as e:
+        raise InputRefused(f"Couldn't ask the shell whether input is allowed: {e}")
+
+
+class StepLog:
+    def __init__(self, path: str):
+        self.path = os.path.abspath(path)
+        self.files = set()
+        f = _open(self.path, "w")
+        f.close()
+
+    def write(self, step: Dict[str, Any]):
+        with _open(self.path, "a") as f:
+            f.write(json.dumps(step) + "\n")
+
+    def file(self, path: Any, mode: str):
+        if not isinstance(path, (str, bytes, os.PathLike)) or isinstance(path, int):
+            return
+        path = os.path.abspath(os.fsdecode(path))
+        access = "write" if any(c in mode for c in "wax+") else "read"
+        if path == self.path or (path, access) in self.files:
+            return
+        self.files.add((path, access))
+        self.write({"action": "file", "path": path, "access": access})
+
+
+def _outermost(record):
+    """Run the wrapped call, recording it only if the script made it directly"""
+    def wrap(original):
+        def wrapper(*args, **kwargs):
+            if getattr(_state, "depth", 0):
+                return original(*args, **kwargs)
+            _state.depth = 1
+            try:
+                result = original(*args, **kwargs)
+                record(result, *args, **kwargs)
+                return result
+            finally:
+                _state.depth = 0
+        wrapper.__wrapped__ = original
+        return wrapper
+    return wrap
+
+
+def _guarded(call):
+    """Check with the shell before the script's own input calls, not the ones pyautogui makes itself"""
+    def wrapper(*args, **kwargs):
+        if not getattr(_state, "depth", 0):
+            check_input()
+        return call(*args, **kwargs)
+    wrapper.__wrapped__ = call
+    return wrapper
+
+
+# Input calls that aren't recorded as steps but still have to be checked
+UNRECORDED_INPUT = ["mouseDown", "mouseUp", "move", "moveRel", "drag", "dragRel", "hscroll", "vscroll"]
+
+
+def _keys(keys) -> List[str]:
+    return [keys] if isinstance(keys, str) else [str(key) for key in keys]
+
+
+def install(log: StepLog):
+    """Patch pyautogui, time.sleep and open so the script's actions end up in the log"""
+    def opened(result, file, mode="r", *args, **kwargs):
+        log.file(file, mode)
+
+    def slept(result, seconds, *args, **kwargs):
+        if seconds:
+            log.write({"action": "wait", "seconds": float(seconds)})
+
+    try:
+        import pyautogui
+    except Exception:
+        pyautogui = None
+    builtins.open = _outermost(opened)(_open)
+    time.sleep = _outermost(slept)(_sleep)
+    if pyautogui is None:
+        return
+
+    # Pointer actions are recorded where the pointer ended up, so a click on an image that
+    # was found on screen replays at the coordinates it was found at
+    def pointer(action, **fixed):
+        def record(result, *args, **kwargs):
+            x, y = pyautogui.position()
+            step = {"action": action, "x": int(x), "y": int(y)}
+            for name, default in fixed.items():
+                step[name] = kwargs.get(name, default)
+            log.write(step)
+        return record
+
+    def clicked(button="left", clicks=1):
+        def record(result, *args, **kwargs):
+            x, y = pyautogui.position()
+            log.write({
+                "action": "click",
+                "x": int(x),
+                "y": int(y),
+                "button": str(kwargs.get("button", button)),
+                "clicks": int(kwargs.get("clicks", clicks)),
+            })
+        return record
+
+    def typed(result, message, *args, **kwargs):
+        text = message if isinstance(message, str) else "".join(message)
+        log.write({"action": "type", "text": text})
+
+    def pressed(result, keys, presses=1, *args, **kwargs):
+        log.write({"action": "press", "keys": _keys(keys) * int(kwargs.get("presses", presses))})
+
+    def hotkey(result, *keys, **kwargs):
+        log.write({"action": "hotkey", "keys": [str(key) for key in keys]})
+
+    def key(action):
+        def record(result, name, *args, **kwargs):
+            log.write({"action": action, "key": str(name)})
+        return record
+
+    def scrolled(result, clicks, *args, **kwargs):
+        log.write({"action": "scroll", "amount": int(clicks)})
+
+    recorders = {
+        "click": clicked(),
+        "leftClick": clicked("left"),
+        "rightClick": clicked("right"),
+        "middleClick": clicked("middle"),
+        "doubleClick": clicked("left", 2),
+        "tripleClick": clicked("left", 3),
+        "moveTo": pointer("move"),
+        "dragTo": pointer("drag", button="left"),
+        "write": typed,
+        "typewrite": typed,
+        "press": pressed,
+        "hotkey": hotkey,
+        "keyDown": key("key_down"),
+        "keyUp": key("key_up"),
+        "scroll": scrolled,
+    }
+    for name, record in recorders.items():
+        original = getattr(pyautogui, name, None)
+        if original is not None:
+            setattr(pyautogui, name, _guarded(_outermost(record)(original)))
+    for name in UNRECORDED_INPUT:
+        original = getattr(pyautogui, name, None)
+        if original is not None:
+            setattr(pyautogui, name, _guarded(original))
+
+
+def main(argv: List[str]) -> int:
+    if len(argv) < 3:
+        print("usage: steps.py <step log> <script>", file=sys.stderr)
+        return 2
+    log_path, script_path = argv[1], argv[2]
+    install(StepLog(log_path))
+    sys.argv = [script_path] + argv[3:]
+    sys.path[0] = os.path.dirname(os.path.abspath(script_path))
+    runpy.run_path(script_path, run_name="__main__")
+    return 0
+
+
+if __name__ == "__main__":
+    sys.exit(main(sys.argv))
@@ -3,7 +3,7 @@ import uvicorn
 import os
 import sys
 import logging
-from utils import find_available_port, is_port_in_use
+from utils import find_available_port, get_log_dir, get_output_dir, is_port_in_use
 
 # Configure logging
 logging.basicConfig(
@@ -46,8 +46,8 @@ if __name__ == "__main__":
     args = parser.parse_args()
     
     # Create necessary directories
-    os.makedirs(os.path.join(os.getcwd(), "generated_output"), exist_ok=True)
-    os.makedirs(os.path.join(os.getcwd(), "logs"), exist_ok=True)
+    os.makedirs(get_output_dir(), exist_ok=True)
+    os.makedirs(get_log_dir(), exist_ok=True)
     os.makedirs(os.path.join(os.getcwd(), "config"), exist_ok=True)
     
     run_server(host=args.host, port=args.port, reload=args.reload) 
\ No newline at end of file
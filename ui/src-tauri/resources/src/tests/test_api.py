@@ -2,10 +2,12 @@ import pytest
 from fastapi.testclient import TestClient
 import os
 import json
+import gzip
 from unittest.mock import patch, MagicMock
 
 # Import the FastAPI app
 from app import app
+from utils import apply_extra_ca_bundle, get_log_dir
 
 # Create a test client
 client = TestClient(app)
@@ -58,6 +60,60 @@ def test_get_config(mock_load_config):
     assert data["api_key"].startswith("••••••••")
     assert data["api_key_set"] is True
 
+@patch("app.load_config")
+def test_export_config(mock_load_config):
+    """Test the GET /config/export endpoint returns the unmasked config"""
+    mock_load_config.return_value = {
+        "api_key": "test_api_key",
+        "model_name": "gemini-2.5-flash"
+    }
+    
+    response = client.get("/config/export")
+    
+    assert response.status_code == 200
+    assert response.json()["api_key"] == "test_api_key"
+
+@patch("app.screenshot_base64")
+def test_screenshot(mock_screenshot):
+    """Test the GET /screenshot endpoint"""
+    mock_screenshot.return_value = "iVBORw0KGgo="
+    
+    response = client.get("/screenshot")
+    
+    assert response.status_code == 200
+    assert response.json() == {"image": "iVBORw0KGgo="}
+
+@patch("app.screenshot_png")
+def test_screenshot_handoff(mock_screenshot, tmp_path):
+    """Test that large captures are handed off as a private file when asked"""
+    mock_screenshot.return_value = b"\x89PNG" + b"\0" * (2 * 1024 * 1024)
+    
+    with patch.dict(os.environ, {"KRYA_HANDOFF_DIR": str(tmp_path)}):
+        response = client.get("/screenshot", params={"handoff": "true"})
+    
+    assert response.status_code == 200
+    path = response.json()["file"]
+    assert os.path.dirname(path) == str(tmp_path)
+    with open(path, "rb") as f:
+        assert f.read() == mock_screenshot.return_value
+
+@patch("app.screenshot_png")
+def test_screenshot_handoff_small(mock_screenshot, tmp_path):
+    """Test that small captures stay inline even when handoff is allowed"""
+    mock_screenshot.return_value = b"\x89PNG"
+    
+    with patch.dict(os.environ, {"KRYA_HANDOFF_DIR": str(tmp_path)}):
+        response = client.get("/screenshot", params={"handoff": "true"})
+    
+    assert response.json() == {"image": "iVBORw=="}
+
+def test_config_dir_follows_profile(tmp_path):
+    """Test that the shell's profile directory holds the config and API key"""
+    from utils import get_config_dir, get_env_path
+    with patch.dict(os.environ, {"KRYA_CONFIG_DIR": str(tmp_path)}):
+        assert get_config_dir() == str(tmp_path)
+        assert get_env_path() == os.path.join(str(tmp_path), ".env")
+
 @patch("app.save_config")
 @patch("app.load_config")
 def test_update_config(mock_load_config, mock_save_config):
@@ -107,6 +163,42 @@ def test_run_automation(mock_load_config, mock_execute_automation):
     assert "job_id" in data
     assert data["status"] == "running"
 
+@patch("app.execute_automation")
+@patch("app.load_config")
+def test_rpc_run_automation(mock_load_config, mock_execute_automation):
+    """Test that the shell can start a saved automation over RPC"""
+    mock_load_config.return_value = {"api_key": "test_api_key"}
+    
+    response = client.post(
+        "/rpc/v1",
+        json={"jsonrpc": "2.0", "id": 1, "method": "jobs.run", "params": {"prompt": "Back up my notes folder"}}
+    )
+    
+    assert response.status_code == 200
+    result = response.json()["result"]
+    assert result["status"] == "running"
+    assert client.get(f"/jobs/{result['job_id']}").json()["prompt"] == "Back up my notes folder"
+
+@patch("app.execute_automation")
+@patch("app.load_config")
+def test_rpc_run_requires_approval(mock_load_config, mock_execute_automation):
+    """A job the shell asks to review is held for approval whatever the backend was started with"""
+    from app import app_state
+    mock_load_config.return_value = {"api_key": "test_api_key"}
+
+    response = client.post(
+        "/rpc/v1",
+        json={
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "jobs.run",
+            "params": {"prompt": "Tidy up my desktop", "require_approval": True}
+        }
+    )
+
+    job_id = response.json()["result"]["job_id"]
+    assert app_state.active_processes[job_id]["require_approval"]
+
 @patch("app.load_config")
 def test_run_automation_no_api_key(mock_load_config):
     """Test the POST /run endpoint with no API key"""
@@ -122,6 +214,474 @@ def test_run_automation_no_api_key(mock_load_config):
     assert response.status_code == 400
     assert "API key not configured" in response.json()["detail"]
 
+@patch("app.execute_automation")
+@patch("app.load_config")
+def test_run_automation_with_attachment(mock_load_config, mock_execute_automation, tmp_path):
+    """Test that files attached under a job ID are added to that job's prompt"""
+    mock_load_config.return_value = {"api_key": "test_api_key"}
+    attached = tmp_path / "notes.txt"
+    attached.write_text("Buy milk")
+    
+    with patch.dict(os.environ, {"KRYA_HANDOFF_DIR": str(tmp_path)}):
+        response = client.post(
+            "/rpc/v1",
+            json={
+                "jsonrpc": "2.0",
+                "id": 1,
+                "method": "jobs.attach",
+                "params": {"job_id": "job-with-file", "name": "notes.txt", "file": str(attached), "size": 8, "mime_type": "text/plain"}
+            }
+        )
+    assert response.json()["result"]["status"] == "success"
+    
+    response = client.post("/run", json={"prompt": "Summarize my notes", "job_id": "job-with-file"})
+    
+    assert response.status_code == 200
+    assert response.json()["job_id"] == "job-with-file"
+    prompt = mock_execute_automation.call_args.kwargs["prompt"]
+    assert prompt.startswith("Summarize my notes")
+    assert "Buy milk" in prompt
+
+@patch("app.execute_automation")
+@patch("app.load_config")
+def test_run_automation_with_image(mock_load_config, mock_execute_automation, tmp_path):
+    """Test that attached images are passed to the model rather than inlined as text"""
+    mock_load_config.return_value = {"api_key": "test_api_key"}
+    pasted = tmp_path / "paste.png"
+    pasted.write_bytes(b"\x89PNG")
+    
+    with patch.dict(os.environ, {"KRYA_HANDOFF_DIR": str(tmp_path)}):
+        client.post(
+            "/attach",
+            json={"job_id": "job-with-image", "name": "Pasted image.png", "file": str(pasted), "size": 4, "mime_type": "image/png"}
+        )
+    response = client.post("/run", json={"prompt": "What is in this image?", "job_id": "job-with-image"})
+    
+    assert response.status_code == 200
+    images = mock_execute_automation.call_args.kwargs["images"]
+    assert images == [{"mime_type": "image/png", "data": b"\x89PNG"}]
+
+@patch("app.execute_automation")
+@patch("app.load_config")
+def test_run_automation_in_working_directory(mock_load_config, mock_execute_automation, tmp_path):
+    """Test that jobs started after the shell sets a working directory are confined to it"""
+    mock_load_config.return_value = {"api_key": "test_api_key"}
+    
+    response = client.post(
+        "/rpc/v1",
+        json={"jsonrpc": "2.0", "id": 1, "method": "session.setWorkingDirectory", "params": {"path": str(tmp_path)}}
+    )
+    assert response.json()["result"]["status"] == "success"
+    
+    try:
+        response = client.post("/run", json={"prompt": "Rename the photos here by date"})
+        job_id = response.json()["job_id"]
+        assert str(tmp_path) in mock_execute_automation.call_args.kwargs["prompt"]
+        assert client.get(f"/jobs/{job_id}").json()["working_directory"] == str(tmp_path)
+    finally:
+        client.post("/session/working-directory", json={"path": None})
+
+@patch("app.mcp_tools.repo_context")
+@patch("app.execute_automation")
+@patch("app.load_config")
+def test_run_automation_in_repository(mock_load_config, mock_execute_automation, mock_repo_context, tmp_path):
+    """Test that the repository state from the shell is added to prompts in a working directory"""
+    mock_load_config.return_value = {"api_key": "test_api_key"}
+    mock_repo_context.return_value = {
+        "root": str(tmp_path),
+        "branch": "main",
+        "head": "1a2b3c4d",
+        "dirty": [{"path": "src/app.py", "status": "modified"}],
+        "dirty_truncated": False,
+        "commits": [{"id": "1a2b3c4d", "summary": "Add login form", "author": "Dev", "time": 0}]
+    }
+    client.post("/session/working-directory", json={"path": str(tmp_path)})
+    
+    try:
+        client.post("/run", json={"prompt": "Run the tests for the files I changed"})
+        prompt = mock_execute_automation.call_args.kwargs["prompt"]
+        assert "on main" in prompt
+        assert "modified: src/app.py" in prompt
+        assert "1a2b3c4d Add login form" in prompt
+    finally:
+        client.post("/session/working-directory", json={"path": None})
+
+@patch("app.execute_automation")
+@patch("app.load_config")
+def test_run_automation_paused(mock_load_config, mock_execute_automation):
+    """Test that no automation runs while the shell has paused it"""
+    mock_load_config.return_value = {"api_key": "test_api_key"}
+    
+    response = client.post(
+        "/rpc/v1",
+        json={"jsonrpc": "2.0", "id": 1, "method": "session.setPaused", "params": {"paused": True}}
+    )
+    assert response.json()["result"]["status"] == "success"
+    
+    try:
+        response = client.post("/run", json={"prompt": "Open my calendar"})
+        assert response.status_code == 423
+        assert "paused" in response.json()["detail"]
+        mock_execute_automation.assert_not_called()
+    finally:
+        client.post("/session/pause", json={"paused": False})
+    
+    response = client.post("/run", json={"prompt": "Open my calendar"})
+    assert response.status_code == 200
+
+@patch("app.run_script")
+@patch("app.generate_code")
+def test_dry_run_does_not_run_the_script(mock_generate_code, mock_run_script):
+    """A job started during a dry run keeps its script without running it"""
+    import asyncio
+    import app as app_module
+    mock_generate_code.return_value = "print('hello')"
+
+    response = client.post(
+        "/rpc/v1",
+        json={"jsonrpc": "2.0", "id": 1, "method": "session.setDryRun", "params": {"dry_run": True}}
+    )
+    assert response.json()["result"]["status"] == "success"
+    assert app_module.app_state.dry_run
+    client.post("/session/dry-run", json={"dry_run": False})
+
+    with patch.dict("app.app_state.active_processes", {"job-5": {"status": "running", "dry_run": True}}):
+        asyncio.run(app_module.execute_automation("job-5", "Say hello"))
+        info = app_module.app_state.active_processes["job-5"]
+        assert info["status"] == "completed"
+        assert info["code"] == "print('hello')"
+        assert "Dry run" in info["last_result"]
+        mock_run_script.assert_not_called()
+
+@patch("app.execute_automation")
+@patch("app.load_config")
+def test_run_automation_on_worker(mock_load_config, mock_execute_automation):
+    """Test that workers leave automations to the main backend"""
+    mock_load_config.return_value = {"api_key": "test_api_key"}
+    
+    with patch.dict(os.environ, {"KRYA_WORKER": "1"}):
+        response = client.post("/run", json={"prompt": "Open my calendar"})
+    
+    assert response.status_code == 409
+    assert "worker" in response.json()["detail"]
+    mock_execute_automation.assert_not_called()
+
+def test_set_working_directory_missing(tmp_path):
+    """Test that a working directory must be an existing folder"""
+    response = client.post("/session/working-directory", json={"path": str(tmp_path / "missing")})
+    
+    assert response.status_code == 400
+    assert "Not a folder" in response.json()["detail"]
+
+def test_attach_outside_handoff_dir(tmp_path):
+    """Test that only files in the shell's handoff directory can be attached"""
+    outside = tmp_path / "secret.txt"
+    outside.write_text("secret")
+    
+    with patch.dict(os.environ, {"KRYA_HANDOFF_DIR": str(tmp_path / "handoff")}):
+        (tmp_path / "handoff").mkdir()
+        response = client.post(
+            "/attach",
+            json={"job_id": "some-job", "name": "secret.txt", "file": str(outside), "size": 6}
+        )
+    
+    assert response.status_code == 400
+    assert "Not a handoff file" in response.json()["detail"]
+
+@patch("app.load_config")
+def test_doctor(mock_load_config):
+    """Test the GET /doctor endpoint"""
+    mock_load_config.return_value = {"api_key": ""}
+    
+    response = client.get("/doctor")
+    
+    assert response.status_code == 200
+    data = response.json()
+    assert data["ok"] is False
+    checks = {check["name"]: check for check in data["checks"]}
+    assert checks["api_key"]["ok"] is False
+    assert "python" in data and "packages" in data
+    assert data["accelerator"]["preferred"] in ("cuda", "metal", "directml", "cpu")
+
+@patch("app.load_config")
+def test_capabilities(mock_load_config):
+    """Test the GET /capabilities endpoint"""
+    mock_load_config.return_value = {"provider": "ollama", "model_name": "llama3.2"}
+    
+    response = client.get("/capabilities")
+    
+    assert response.status_code == 200
+    data = response.json()
+    assert data["vision"] is False
+    assert {"vision", "voice", "code_exec", "screenshot"} <= set(data["details"])
+
+@patch("app.load_config")
+def test_capabilities_vision_model(mock_load_config):
+    """Test that a multimodal model is reported as supporting vision"""
+    mock_load_config.return_value = {"provider": "ollama", "model_name": "llava:13b"}
+    
+    response = client.post("/rpc/v1", json={"jsonrpc": "2.0", "id": 1, "method": "system.capabilities"})
+    
+    assert response.json()["result"]["vision"] is True
+
+@patch("app.generate_text")
+@patch("app.load_config")
+def test_self_test(mock_load_config, mock_generate_text):
+    """Test the POST /self-test endpoint"""
+    mock_load_config.return_value = {"api_key": "test_api_key"}
+    mock_generate_text.return_value = "OK"
+    
+    response = client.post("/self-test")
+    
+    assert response.status_code == 200
+    job_id = response.json()["job_id"]
+    
+    # Background tasks run before the test client returns
+    job = client.get(f"/jobs/{job_id}").json()
+    assert job["status"] == "completed"
+    assert job["last_result"] == "OK"
+    assert any("Self-test" in log["message"] for log in job["logs"])
+
+@patch("app.load_config")
+def test_self_test_no_api_key(mock_load_config):
+    """Test the POST /self-test endpoint with no API key"""
+    mock_load_config.return_value = {"api_key": ""}
+    
+    response = client.post("/self-test")
+    
+    assert response.status_code == 400
+
+@patch("app.generate_text")
+@patch("app.load_config")
+def test_complete_text(mock_load_config, mock_generate_text):
+    """Test the POST /complete endpoint"""
+    mock_load_config.return_value = {"api_key": "test_api_key"}
+    mock_generate_text.return_value = "221B Baker Street"
+    
+    response = client.post(
+        "/complete",
+        json={
+            "prompt": "My address"
+        }
+    )
+    
+    assert response.status_code == 200
+    assert response.json() == {"text": "221B Baker Street"}
+    mock_generate_text.assert_called_once_with("My address")
+
+@patch("app.generate_text")
+@patch("app.load_config")
+def test_complete_text_local_provider(mock_load_config, mock_generate_text):
+    """Test that POST /complete works without an API key when using Ollama"""
+    mock_load_config.return_value = {"api_key": "", "provider": "ollama", "model_name": "llama3.2"}
+    mock_generate_text.return_value = "Hello"
+    
+    response = client.post(
+        "/complete",
+        json={
+            "prompt": "Say hello"
+        }
+    )
+    
+    assert response.status_code == 200
+    assert response.json() == {"text": "Hello"}
+
+@patch("app.embed_texts")
+@patch("app.load_config")
+def test_embed_texts(mock_load_config, mock_embed_texts):
+    """Test the POST /embed endpoint"""
+    mock_load_config.return_value = {"api_key": "test_api_key"}
+    mock_embed_texts.return_value = [[0.1, 0.2], [0.3, 0.4]]
+    
+    response = client.post(
+        "/embed",
+        json={
+            "texts": ["first chunk", "second chunk"],
+            "task_type": "retrieval_document"
+        }
+    )
+    
+    assert response.status_code == 200
+    assert response.json() == {"embeddings": [[0.1, 0.2], [0.3, 0.4]]}
+    mock_embed_texts.assert_called_once_with(["first chunk", "second chunk"], "retrieval_document")
+
+def test_send_message_streams_deltas():
+    """Test that a streamed reply is passed on piece by piece and joined"""
+    from functions.gen import send_message
+    chat_session = MagicMock()
+    chat_session.send_message.return_value = [MagicMock(text="print("), MagicMock(text="'hi')")]
+    
+    deltas = []
+    assert send_message(chat_session, ["prompt"], deltas.append) == "print('hi')"
+    assert deltas == ["print(", "'hi')"]
+    chat_session.send_message.assert_called_once_with(["prompt"], stream=True)
+
+def test_rpc_ping():
+    """Test that /rpc/v1 dispatches system.ping and echoes the request id"""
+    response = client.post("/rpc/v1", json={"jsonrpc": "2.0", "id": 7, "method": "system.ping", "params": {}})
+    
+    assert response.status_code == 200
+    assert response.json() == {
+        "jsonrpc": "2.0",
+        "id": 7,
+        "result": {"status": "online", "service": "Krya.ai API"}
+    }
+
+@patch("app.generate_text")
+@patch("app.load_config")
+def test_rpc_complete(mock_load_config, mock_generate_text):
+    """Test text.complete over /rpc/v1"""
+    mock_load_config.return_value = {"api_key": "test_api_key"}
+    mock_generate_text.return_value = "221B Baker Street"
+    
+    response = client.post("/rpc/v1", json={"jsonrpc": "2.0", "id": 1, "method": "text.complete", "params": {"prompt": "My address"}})
+    
+    assert response.status_code == 200
+    assert response.json()["result"] == {"text": "221B Baker Street"}
+    mock_generate_text.assert_called_once_with("My address")
+
+@patch("app.load_config")
+def test_rpc_endpoint_error(mock_load_config):
+    """Test that endpoint errors come back as JSON-RPC errors carrying the HTTP status"""
+    mock_load_config.return_value = {"api_key": ""}
+    
+    response = client.post("/rpc/v1", json={"jsonrpc": "2.0", "id": 2, "method": "text.complete", "params": {"prompt": "Hi"}})
+    
+    error = response.json()["error"]
+    assert error["code"] == -32000
+    assert error["data"]["status"] == 400
+
+def test_rpc_handshake():
+    """Test that the handshake picks the newest protocol version both sides speak"""
+    response = client.post("/rpc/v1", json={
+        "jsonrpc": "2.0",
+        "id": 5,
+        "method": "system.handshake",
+        "params": {
+            "shell_version": "0.1.0",
+            "protocol_versions": [1, 99],
+            "capabilities": ["mcp_proxy"],
+            "encodings": ["zstd", "gzip"]
+        }
+    })
+    
+    result = response.json()["result"]
+    assert result["protocol_version"] == 1
+    assert 1 in result["protocol_versions"]
+    assert result["encoding"] in ("zstd", "gzip")
+
+def test_rpc_handshake_mismatch():
+    """Test that the handshake reports no common version for an incompatible shell"""
+    response = client.post("/rpc/v1", json={
+        "jsonrpc": "2.0",
+        "id": 6,
+        "method": "system.handshake",
+        "params": {"shell_version": "9.0.0", "protocol_versions": [99]}
+    })
+    
+    assert response.json()["result"]["protocol_version"] is None
+
+def test_rpc_compressed_request():
+    """Test that /rpc/v1 accepts gzip-compressed request bodies"""
+    body = gzip.compress(json.dumps({"jsonrpc": "2.0", "id": 8, "method": "system.ping"}).encode("utf-8"))
+    
+    response = client.post(
+        "/rpc/v1",
+        content=body,
+        headers={"Content-Type": "application/json", "Content-Encoding": "gzip"}
+    )
+    
+    assert response.json()["result"]["status"] == "online"
+
+@patch("app.generate_text")
+@patch("app.load_config")
+def test_rpc_compressed_response(mock_load_config, mock_generate_text):
+    """Test that large /rpc/v1 results are compressed when the shell accepts it"""
+    mock_load_config.return_value = {"api_key": "test_api_key"}
+    mock_generate_text.return_value = "x" * 50000
+    
+    response = client.post(
+        "/rpc/v1",
+        json={"jsonrpc": "2.0", "id": 9, "method": "text.complete", "params": {"prompt": "Long"}},
+        headers={"Accept-Encoding": "gzip"}
+    )
+    
+    assert response.headers["content-encoding"] == "gzip"
+    assert response.json()["result"]["text"] == "x" * 50000
+
+def test_rpc_unknown_method():
+    """Test that /rpc/v1 rejects methods outside the contract"""
+    response = client.post("/rpc/v1", json={"jsonrpc": "2.0", "id": 3, "method": "system.reboot"})
+    
+    assert response.json()["error"]["code"] == -32601
+    assert response.json()["id"] == 3
+
+def test_rpc_invalid_params():
+    """Test that /rpc/v1 validates params against the method's model"""
+    response = client.post("/rpc/v1", json={"jsonrpc": "2.0", "id": 4, "method": "jobs.get", "params": {}})
+    
+    assert response.json()["error"]["code"] == -32602
+
+@patch("app.mcp_tools.list_tools")
+def test_list_tools(mock_list_tools):
+    """Test the GET /tools endpoint"""
+    mock_list_tools.return_value = [{"server": "github", "name": "create_issue", "description": None, "input_schema": {}}]
+    
+    response = client.get("/tools")
+    
+    assert response.status_code == 200
+    assert response.json()["tools"][0]["name"] == "create_issue"
+
+def test_list_tools_without_proxy():
+    """Test that GET /tools reports the proxy as unavailable outside the shell"""
+    with patch.dict(os.environ, {}, clear=True):
+        response = client.get("/tools")
+    
+    assert response.status_code == 503
+
+@patch("app.mcp_tools.call_tool")
+def test_call_tool(mock_call_tool):
+    """Test the POST /tools/call endpoint"""
+    mock_call_tool.return_value = {"content": [{"type": "text", "text": "done"}], "isError": False}
+    
+    response = client.post(
+        "/tools/call",
+        json={"server": "github", "tool": "create_issue", "arguments": {"title": "Bug"}}
+    )
+    
+    assert response.status_code == 200
+    assert response.json()["content"][0]["text"] == "done"
+    mock_call_tool.assert_called_once_with("github", "create_issue", {"title": "Bug"})
+
+@patch("functions.mcp_tools.proxy_request")
+def test_run_command(mock_proxy_request):
+    """Programs are run by the shell, which checks them against its allowlist"""
+    from functions import mcp_tools
+    mock_proxy_request.return_value = {
+        "exec_id": "e1", "exit_code": 0, "timed_out": False, "stdout": "ok\n", "stderr": ""
+    }
+
+    result = mcp_tools.run_command("git", ["status"], cwd="/tmp/project")
+
+    assert result["exit_code"] == 0
+    path, body = mock_proxy_request.call_args.args
+    assert path == "/exec"
+    assert body == {"command": "git", "args": ["status"], "cwd": "/tmp/project"}
+
+@patch("functions.mcp_tools.proxy_request")
+def test_file_helpers(mock_proxy_request):
+    """Files are read and written by the shell, within the folders the user allowed"""
+    from functions import mcp_tools
+    mock_proxy_request.return_value = {"path": "/home/me/notes.txt", "bytes": 5, "contents": "hello"}
+
+    assert mcp_tools.read_file("~/notes.txt") == "hello"
+    assert mock_proxy_request.call_args.args == (
+        "/files", {"operation": "read", "path": "~/notes.txt", "contents": None}
+    )
+
+    assert mcp_tools.append_file("~/notes.txt", "!") == "/home/me/notes.txt"
+    assert mock_proxy_request.call_args.args[1]["operation"] == "append"
+
 @patch("app.app_state.active_processes")
 def test_stop_automation_not_found(mock_active_processes):
     """Test the POST /stop endpoint with a non-existent job ID"""
@@ -137,18 +697,67 @@ def test_stop_automation_not_found(mock_active_processes):
     assert response.status_code == 404
     assert "not found" in response.json()["detail"]
 
+def test_get_job_not_found():
+    """Test the GET /jobs/{job_id} endpoint with a non-existent job ID"""
+    response = client.get("/jobs/non-existent-id")
+    
+    assert response.status_code == 404
+    assert "not found" in response.json()["detail"]
+
+@patch("app.app_state.recent_logs", [
+    {"job_id": "job-1", "level": "INFO", "message": "Started"},
+    {"job_id": "job-2", "level": "INFO", "message": "Other job"}
+])
+@patch.dict("app.app_state.active_processes", {
+    "job-1": {
+        "prompt": "Open the calculator",
+        "status": "completed",
+        "start_time": "2024-01-01T00:00:00",
+        "code": "print('hi')",
+        "last_result": "hi"
+    }
+})
+def test_get_job():
+    """Test the GET /jobs/{job_id} endpoint with a known job"""
+    response = client.get("/jobs/job-1")
+    
+    assert response.status_code == 200
+    data = response.json()
+    assert data["prompt"] == "Open the calculator"
+    assert data["status"] == "completed"
+    assert data["last_result"] == "hi"
+    assert [log["message"] for log in data["logs"]] == ["Started"]
+
+@patch.dict("app.app_state.active_processes", {
+    "job-1": {
+        "prompt": "Open the calculator",
+        "status": "running",
+        "phase": "executing",
+        "phase_started": 1000.0,
+        "attempt": 2,
+        "max_attempts": 3
+    }
+})
+@patch("app.time.time", return_value=1030.0)
+def test_get_job_progress(mock_time):
+    """Test that a running job reports its phase and how much of the script's time budget it used"""
+    response = client.get("/jobs/job-1")
+    
+    assert response.status_code == 200
+    data = response.json()
+    assert data["phase"] == "executing"
+    assert data["attempt"] == 2
+    assert data["max_attempts"] == 3
+    assert data["progress"] == 50
+
+@patch("app.stop_script", return_value=True)
 @patch("app.app_state.active_processes")
-def test_stop_automation_success(mock_active_processes):
+def test_stop_automation_success(mock_active_processes, mock_stop_script):
     """Test the POST /stop endpoint with a valid job ID"""
-    # Mock process
-    mock_process = MagicMock()
-    mock_process.poll.return_value = None
-    mock_process.terminate.return_value = None
-    
     # Mock job info
     mock_job_info = {
         "status": "running",
-        "process": mock_process
+        "exec_id": "exec-1"
     }
     
     # Setup mock
@@ -165,7 +774,44 @@ def test_stop_automation_success(mock_active_processes):
     assert response.status_code == 200
     assert response.json() == {"status": "stopped", "job_id": "valid-id"}
     assert mock_job_info["status"] == "stopped"
-    mock_process.terminate.assert_called_once()
+    mock_stop_script.assert_called_once_with("exec-1")
+
+@patch("functions.mcp_tools.proxy_request")
+def test_scripts_run_through_the_shell(mock_proxy_request, tmp_path):
+    """Generated scripts are run by the shell with this interpreter, not spawned by the backend"""
+    import sys
+    from functions.exec import STEPS_RUNNER, run_script
+    script = tmp_path / "script.py"
+    script.write_text("print('hi')\n")
+    step_log = tmp_path / "steps.jsonl"
+    mock_proxy_request.return_value = {
+        "exec_id": "exec-1", "exit_code": 0, "timed_out": False, "stopped": False, "stdout": "hi\n", "stderr": ""
+    }
+
+    result = run_script(str(script), cwd=str(tmp_path), step_log=str(step_log), exec_id="exec-1")
+
+    assert "✅" in result
+    path, body = mock_proxy_request.call_args.args
+    assert path == "/exec"
+    assert body == {
+        "command": sys.executable,
+        "args": [STEPS_RUNNER, str(step_log), str(script)],
+        "cwd": str(tmp_path),
+        "exec_id": "exec-1",
+    }
+
+@patch("app.app_state.active_processes")
+def test_stop_automation_with_reason(mock_active_processes):
+    """Test that a reason given by the shell becomes the stopped job's result"""
+    mock_job_info = {"status": "running"}
+    mock_active_processes.__contains__.return_value = True
+    mock_active_processes.__getitem__.return_value = mock_job_info
+    
+    response = client.post("/stop", json={"job_id": "valid-id", "reason": "1Password is blocklisted"})
+    
+    assert response.status_code == 200
+    assert mock_job_info["status"] == "stopped"
+    assert mock_job_info["last_result"] == "❌ 1Password is blocklisted"
 
 @patch("app.app_state.active_processes")
 @patch("app.app_state.recent_logs")
@@ -199,4 +845,129 @@ def test_get_status(mock_recent_logs, mock_active_processes):
     assert data["job_counts"]["running"] == 1
     assert data["job_counts"]["completed"] == 1
     assert data["job_counts"]["failed"] == 1
-    assert "job1" in data["active_jobs"] 
\ No newline at end of file
+    assert "job1" in data["active_jobs"] 
+def test_apply_extra_ca_bundle(tmp_path):
+    """Extra roots from the shell are added to the default ones, not put in their place"""
+    base = tmp_path / "base.pem"
+    base.write_text("BASE ROOTS")
+    extra = tmp_path / "extra-ca-bundle.pem"
+    extra.write_text("CORPORATE ROOT")
+
+    with patch.dict(os.environ, {"KRYA_EXTRA_CA_BUNDLE": str(extra), "SSL_CERT_FILE": str(base)}):
+        combined = apply_extra_ca_bundle()
+        assert os.environ["SSL_CERT_FILE"] == combined
+        assert os.environ["REQUESTS_CA_BUNDLE"] == combined
+        assert os.environ["GRPC_DEFAULT_SSL_ROOTS_FILE_PATH"] == combined
+
+    contents = open(combined).read()
+    assert "BASE ROOTS" in contents
+    assert "CORPORATE ROOT" in contents
+
+def test_apply_extra_ca_bundle_without_one():
+    """Nothing changes when the shell has no extra roots"""
+    with patch.dict(os.environ, {}, clear=False):
+        os.environ.pop("KRYA_EXTRA_CA_BUNDLE", None)
+        assert apply_extra_ca_bundle() is None
+
+def test_script_steps_are_recorded(tmp_path):
+    """Running a script through the step recorder logs its sleeps and the files it touches"""
+    import subprocess
+    import sys
+    from functions.exec import STEPS_RUNNER
+    from functions.steps import read_steps
+
+    script = tmp_path / "script.py"
+    output = tmp_path / "output.txt"
+    script.write_text(
+        "import time\n"
+        f"with open({str(output)!r}, 'w') as f:\n"
+        "    f.write('done')\n"
+        "time.sleep(0.01)\n"
+    )
+    step_log = tmp_path / "steps.jsonl"
+    result = subprocess.run([sys.executable, STEPS_RUNNER, str(step_log), str(script)], timeout=60)
+
+    assert result.returncode == 0
+    steps = read_steps(str(step_log))
+    assert {"action": "file", "path": str(output), "access": "write"} in steps
+    assert {"action": "wait", "seconds": 0.01} in steps
+
+@patch.dict("app.app_state.active_processes", {
+    "job-2": {
+        "prompt": "Open the calculator",
+        "status": "completed",
+        "steps": [{"action": "click", "x": 120, "y": 40, "button": "left", "clicks": 1}]
+    }
+})
+def test_get_job_steps():
+    """The recorded actions of a job are returned with it"""
+    response = client.get("/jobs/job-2")
+
+    assert response.status_code == 200
+    assert response.json()["steps"] == [{"action": "click", "x": 120, "y": 40, "button": "left", "clicks": 1}]
+
+def test_get_log_dir(tmp_path):
+    """Logs go where the shell says, and next to the sources only when run on its own"""
+    with patch.dict(os.environ, {"KRYA_LOG_DIR": str(tmp_path)}):
+        assert get_log_dir() == str(tmp_path)
+    with patch.dict(os.environ, {}, clear=False):
+        os.environ.pop("KRYA_LOG_DIR", None)
+        assert get_log_dir() == os.path.join(os.getcwd(), "logs")
+
+def test_get_output_dir(tmp_path):
+    """Generated scripts are kept in the shell's per-user data directory"""
+    from utils import get_output_dir
+    with patch.dict(os.environ, {"KRYA_DATA_DIR": str(tmp_path)}):
+        assert get_output_dir() == os.path.join(str(tmp_path), "generated_output")
+
+@patch.dict("app.app_state.active_processes", {
+    "job-3": {"prompt": "Empty the downloads folder", "status": "running", "phase": "awaiting_approval", "attempt": 1}
+})
+def test_approve_job():
+    """The shell's verdict is kept for the attempt it reviewed"""
+    from app import app_state
+    stale = client.post("/jobs/approve", json={"job_id": "job-3", "attempt": 2, "approved": True})
+    assert stale.status_code == 409
+
+    response = client.post(
+        "/jobs/approve", json={"job_id": "job-3", "attempt": 1, "approved": False, "reason": "Blocked"}
+    )
+    assert response.status_code == 200
+    assert app_state.active_processes["job-3"]["approval"] == {"attempt": 1, "approved": False, "reason": "Blocked"}
+
+def test_wait_for_approval():
+    """A script waits for the shell and is refused if it never answers"""
+    import asyncio
+    import app as app_module
+    with patch.dict("app.app_state.active_processes", {"job-4": {"status": "running", "attempt": 1}}):
+        info = app_module.app_state.active_processes["job-4"]
+
+        async def approve_soon():
+            await asyncio.sleep(0.05)
+            info["approval"] = {"attempt": 1, "approved": True, "reason": None}
+
+        async def approved():
+            return (await asyncio.gather(app_module.wait_for_approval("job-4", 1), approve_soon()))[0]
+
+        assert asyncio.run(approved()) is None
+        assert info["phase"] == "awaiting_approval"
+
+        with patch("app.APPROVAL_TIMEOUT", 0.1):
+            assert "not approved" in asyncio.run(app_module.wait_for_approval("job-4", 1))
+
+def test_script_input_is_checked_with_the_shell():
+    """A script's input call is refused when the shell says the app in front is blocklisted"""
+    import io
+    import urllib.error
+    from functions import steps
+
+    refusal = urllib.error.HTTPError(
+        "http://127.0.0.1:1/input/check", 403, "Forbidden", {},
+        io.BytesIO(b'{"error": "1Password is blocklisted, so it can\'t be controlled"}')
+    )
+    clicked = MagicMock()
+    env = {"KRYA_MCP_PROXY_URL": "http://127.0.0.1:1", "KRYA_MCP_PROXY_TOKEN": "token"}
+    with patch.dict(os.environ, env), patch("urllib.request.urlopen", side_effect=refusal):
+        with pytest.raises(steps.InputRefused, match="1Password is blocklisted"):
+            steps._guarded(clicked)(10, 20)
+    clicked.assert_not_called()
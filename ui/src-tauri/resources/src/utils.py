@@ -2,7 +2,6 @@ import os
 import re
 import json
 import logging
-import subprocess
 from typing import Dict, Any, Tuple, List, Optional
 from datetime import datetime
 
@@ -22,6 +21,64 @@ def get_full_path(relative_path: str) -> str:
     """Get the full path from a path relative to the base directory"""
     return os.path.join(get_base_dir(), relative_path)
 
+def get_config_dir() -> str:
+    """Directory holding config.json: the active profile's when the shell passes one, else ./config"""
+    return os.getenv("KRYA_CONFIG_DIR") or os.path.join(os.getcwd(), "config")
+
+def get_env_path() -> str:
+    """The .env file holding the API key, kept with the profile's config when there is one"""
+    profile_dir = os.getenv("KRYA_CONFIG_DIR")
+    return os.path.join(profile_dir, ".env") if profile_dir else os.path.join(os.getcwd(), ".env")
+
+def get_output_dir() -> str:
+    """Directory for generated scripts: in the shell's per-user data directory when it passes one, else here"""
+    return os.path.join(os.getenv("KRYA_DATA_DIR") or os.getcwd(), "generated_output")
+
+def get_log_dir() -> str:
+    """Directory for script output and step logs: the shell's log directory when it passes one, else ./logs"""
+    return os.getenv("KRYA_LOG_DIR") or os.path.join(os.getcwd(), "logs")
+
+def is_worker() -> bool:
+    """True in the extra instances the shell starts to answer model calls alongside the main backend"""
+    return os.getenv("KRYA_WORKER") == "1"
+
+def approval_required() -> bool:
+    """True when the shell reviews every generated script before it runs"""
+    return os.getenv("KRYA_REQUIRE_APPROVAL") == "1"
+
+def apply_extra_ca_bundle() -> Optional[str]:
+    """Trust the root certificates the shell was given (KRYA_EXTRA_CA_BUNDLE) on top of the usual ones,
+    for networks whose proxy intercepts TLS. Returns the combined bundle now in use, or None."""
+    extra = os.getenv("KRYA_EXTRA_CA_BUNDLE")
+    if not extra or not os.path.isfile(extra):
+        return None
+    combined = os.path.join(os.path.dirname(extra), "combined-ca-bundle.pem")
+
+    # The variables below replace the default roots rather than add to them, so start from those
+    base = os.getenv("SSL_CERT_FILE")
+    if not base or not os.path.isfile(base) or os.path.abspath(base) == os.path.abspath(combined):
+        try:
+            import certifi
+            base = certifi.where()
+        except ImportError:
+            base = None
+
+    parts = []
+    for path in (base, extra):
+        if path:
+            with open(path, "r", encoding="utf-8", errors="replace") as f:
+                parts.append(f.read().strip() + "\n")
+    # Workers start at the same time and write the same thing; replacing keeps readers from a partial file
+    temp = f"{combined}.{os.getpid()}.tmp"
+    with open(temp, "w", encoding="utf-8") as f:
+        f.write("".join(parts))
+    os.replace(temp, combined)
+
+    # httpx and the stdlib, requests, and the gRPC transport the Gemini SDK uses by default
+    for name in ("SSL_CERT_FILE", "REQUESTS_CA_BUNDLE", "GRPC_DEFAULT_SSL_ROOTS_FILE_PATH"):
+        os.environ[name] = combined
+    return combined
+
 def ensure_dir_exists(dir_path: str) -> None:
     """Ensure a directory exists, creating it if necessary"""
     os.makedirs(dir_path, exist_ok=True)
@@ -67,26 +124,6 @@ def requires_interactive_execution(code: str) -> bool:
             return True
     return False
 
-def execute_subprocess(
-    cmd: List[str], 
-    timeout: int = 30,
-    cwd: Optional[str] = None
-) -> Tuple[int, str, str]:
-    """Execute a subprocess and return exit code, stdout, and stderr"""
-    try:
-        result = subprocess.run(
-            cmd,
-            capture_output=True,
-            text=True,
-            timeout=timeout,
-            cwd=cwd
-        )
-        return result.returncode, result.stdout, result.stderr
-    except subprocess.TimeoutExpired:
-        return 124, "", f"Command timed out after {timeout} seconds"
-    except Exception as e:
-        return 1, "", f"Error executing command: {str(e)}"
-
 def format_execution_result(
     exit_code: int, 
     stdout: str, 
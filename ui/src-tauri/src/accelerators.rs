@@ -0,0 +1,125 @@
+use serde::Serialize;
+use std::process::Command;
+use std::sync::Mutex;
+use tauri::Manager;
+
+#[derive(Clone, Debug, Serialize)]
+pub struct CudaInfo {
+    pub devices: Vec<String>,
+    pub driver_version: Option<String>,
+}
+
+// Hardware acceleration available for local models
+#[derive(Clone, Debug, Serialize)]
+pub struct Accelerators {
+    pub cuda: Option<CudaInfo>,
+    pub metal: bool,
+    pub directml: bool,
+    // Best option in order cuda, metal, directml, cpu
+    pub preferred: String,
+}
+
+// Detection shells out, so it runs once and is reused for every backend spawn
+#[derive(Default)]
+pub struct AcceleratorState(pub Mutex<Option<Accelerators>>);
+
+fn detect_cuda() -> Option<CudaInfo> {
+    let output = Command::new("nvidia-smi")
+        .args(["--query-gpu=name,driver_version", "--format=csv,noheader"])
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+
+    let mut devices = Vec::new();
+    let mut driver_version = None;
+    for line in String::from_utf8_lossy(&output.stdout).lines() {
+        let mut fields = line.split(',').map(|field| field.trim());
+        if let Some(name) = fields.next().filter(|name| !name.is_empty()) {
+            devices.push(name.to_string());
+        }
+        if driver_version.is_none() {
+            driver_version = fields.next().map(|version| version.to_string());
+        }
+    }
+
+    if devices.is_empty() {
+        None
+    } else {
+        Some(CudaInfo { devices, driver_version })
+    }
+}
+
+// Every Mac that runs a supported macOS has a Metal-capable GPU
+fn detect_metal() -> bool {
+    cfg!(target_os = "macos")
+}
+
+// DirectML ships with Windows 10 1903 and later
+fn detect_directml() -> bool {
+    if !cfg!(target_os = "windows") {
+        return false;
+    }
+    let system_root = std::env::var("SystemRoot").unwrap_or_else(|_| "C:\\Windows".to_string());
+    std::path::Path::new(&system_root)
+        .join("System32")
+        .join("DirectML.dll")
+        .exists()
+}
+
+pub fn detect() -> Accelerators {
+    let cuda = detect_cuda();
+    let metal = detect_metal();
+    let directml = detect_directml();
+
+    let preferred = if cuda.is_some() {
+        "cuda"
+    } else if metal {
+        "metal"
+    } else if directml {
+        "directml"
+    } else {
+        "cpu"
+    };
+
+    Accelerators {
+        cuda,
+        metal,
+        directml,
+        preferred: preferred.to_string(),
+    }
+}
+
+// Get the cached detection result, detecting on first use
+pub fn current(app_handle: &tauri::AppHandle) -> Accelerators {
+    let state = app_handle.state::<AcceleratorState>();
+    let mut cached = state.0.lock().unwrap();
+    cached.get_or_insert_with(detect).clone()
+}
+
+// Environment variables telling the Python server which local inference path to use
+pub fn env_hints(app_handle: &tauri::AppHandle) -> Vec<(&'static str, String)> {
+    let accelerators = current(app_handle);
+    let flag = |available: bool| if available { "1" } else { "0" }.to_string();
+
+    vec![
+        ("KRYA_ACCELERATOR", accelerators.preferred.clone()),
+        ("KRYA_CUDA_AVAILABLE", flag(accelerators.cuda.is_some())),
+        ("KRYA_METAL_AVAILABLE", flag(accelerators.metal)),
+        ("KRYA_DIRECTML_AVAILABLE", flag(accelerators.directml)),
+    ]
+}
+
+// Command for the settings pane; `refresh` re-runs detection (e.g. after installing drivers)
+#[tauri::command(async)]
+pub fn get_accelerators(
+    app_handle: tauri::AppHandle,
+    state: tauri::State<AcceleratorState>,
+    refresh: Option<bool>,
+) -> Accelerators {
+    if refresh.unwrap_or(false) {
+        *state.0.lock().unwrap() = None;
+    }
+    current(&app_handle)
+}
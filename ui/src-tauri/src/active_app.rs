@@ -0,0 +1,84 @@
+use serde::Serialize;
+
+// The application that currently has keyboard focus
+#[derive(Clone, Debug, Serialize)]
+pub struct ActiveApp {
+    pub name: String,
+    pub title: String,
+    pub process_path: String,
+    pub process_id: u64,
+}
+
+impl ActiveApp {
+    // Match a user-entered app name against this app (case-insensitive, by name or executable)
+    pub fn matches(&self, pattern: &str) -> bool {
+        let pattern = pattern.trim().to_lowercase();
+        if pattern.is_empty() {
+            return false;
+        }
+
+        self.name.to_lowercase() == pattern
+            || std::path::Path::new(&self.process_path)
+                .file_stem()
+                .map(|stem| stem.to_string_lossy().to_lowercase() == pattern)
+                .unwrap_or(false)
+    }
+}
+
+#[cfg(target_os = "macos")]
+pub fn frontmost_app() -> Option<ActiveApp> {
+    use cocoa::base::{id, nil};
+    use objc::{class, msg_send, sel, sel_impl};
+    use std::ffi::CStr;
+    use std::os::raw::c_char;
+
+    unsafe fn to_string(ns_string: id) -> String {
+        if ns_string == nil {
+            return String::new();
+        }
+        let bytes: *const c_char = msg_send![ns_string, UTF8String];
+        if bytes.is_null() {
+            return String::new();
+        }
+        CStr::from_ptr(bytes).to_string_lossy().to_string()
+    }
+
+    unsafe {
+        let workspace: id = msg_send![class!(NSWorkspace), sharedWorkspace];
+        let app: id = msg_send![workspace, frontmostApplication];
+        if app == nil {
+            return None;
+        }
+
+        let name: id = msg_send![app, localizedName];
+        let pid: i32 = msg_send![app, processIdentifier];
+        let url: id = msg_send![app, executableURL];
+        let path: id = if url == nil { nil } else { msg_send![url, path] };
+
+        // Window titles need the accessibility API, so we only report the app itself
+        Some(ActiveApp {
+            name: to_string(name),
+            title: String::new(),
+            process_path: to_string(path),
+            process_id: pid as u64,
+        })
+    }
+}
+
+#[cfg(not(target_os = "macos"))]
+pub fn frontmost_app() -> Option<ActiveApp> {
+    active_win_pos_rs::get_active_window()
+        .ok()
+        .map(|window| ActiveApp {
+            name: window.app_name,
+            title: window.title,
+            process_path: window.process_path.to_string_lossy().to_string(),
+            process_id: window.process_id,
+        })
+}
+
+// Command to get the frontmost application
+#[tauri::command]
+pub fn get_active_app() -> Option<ActiveApp> {
+    frontmost_app()
+}
@@ -0,0 +1,142 @@
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::time::Duration;
+use tauri::{Manager, PhysicalPosition, WebviewWindow};
+
+use crate::{overlay, settings};
+
+const FRAMES: u32 = 10;
+
+// About 140ms in all: long enough to read as a fade, short enough not to hold up typing
+const FRAME_INTERVAL: Duration = Duration::from_millis(14);
+
+// How far the spotlight slides down into place, in logical pixels
+const SLIDE_DISTANCE: f64 = 12.0;
+
+#[derive(Default)]
+pub struct AnimationState {
+    // Bumped by every animation, so one that was overtaken (hiding while still appearing) stops
+    generation: AtomicU64,
+    // Fading out: still visible, but on its way to being hidden
+    hiding: AtomicBool,
+}
+
+impl AnimationState {
+    // Stop whatever animation is running
+    fn interrupt(&self) {
+        self.hiding.store(false, Ordering::SeqCst);
+        self.generation.fetch_add(1, Ordering::SeqCst);
+    }
+}
+
+fn reduce_motion(window: &WebviewWindow) -> bool {
+    settings::current(window.app_handle()).appearance.reduce_motion
+}
+
+fn ease_out(t: f64) -> f64 {
+    1.0 - (1.0 - t).powi(3)
+}
+
+// Step `frame` from the first frame to the last on the UI thread, unless another animation starts
+fn run(
+    window: &WebviewWindow,
+    frame: impl Fn(&WebviewWindow, f64) + Send + Sync + 'static,
+    done: impl FnOnce(&WebviewWindow) + Send + 'static,
+) {
+    let generation = window.state::<AnimationState>().generation.load(Ordering::SeqCst);
+    let window = window.clone();
+    let frame = std::sync::Arc::new(frame);
+    std::thread::spawn(move || {
+        let current = || window.state::<AnimationState>().generation.load(Ordering::SeqCst) == generation;
+        for i in 1..=FRAMES {
+            std::thread::sleep(FRAME_INTERVAL);
+            if !current() {
+                return;
+            }
+            let (target, frame) = (window.clone(), frame.clone());
+            let progress = ease_out(i as f64 / FRAMES as f64);
+            if let Err(e) = window.run_on_main_thread(move || frame(&target, progress)) {
+                eprintln!("Failed to animate the window: {}", e);
+                break;
+            }
+        }
+        if current() {
+            let target = window.clone();
+            if let Err(e) = window.run_on_main_thread(move || done(&target)) {
+                eprintln!("Failed to finish animating the window: {}", e);
+            }
+        }
+    });
+}
+
+// Show a window, faded in as it slides down to where `place` puts it once shown
+pub fn show(window: &WebviewWindow, place: impl FnOnce(&WebviewWindow) -> tauri::Result<()>) -> tauri::Result<()> {
+    window.state::<AnimationState>().interrupt();
+    let animate = !reduce_motion(window);
+    // Invisible until the first frame, so it can be placed without being seen jumping there
+    if let Err(e) = overlay::set_opacity(window, if animate { 0.0 } else { 1.0 }) {
+        eprintln!("{}", e);
+    }
+    window.show()?;
+    place(window)?;
+    if !animate {
+        return Ok(());
+    }
+
+    let end = window.outer_position()?;
+    let distance = SLIDE_DISTANCE * window.scale_factor()?;
+    window.set_position(PhysicalPosition::new(end.x, end.y - distance.round() as i32))?;
+    run(
+        window,
+        move |window, progress| {
+            let y = end.y as f64 - distance * (1.0 - progress);
+            if let Err(e) = window.set_position(PhysicalPosition::new(end.x, y.round() as i32)) {
+                eprintln!("Failed to move the window: {}", e);
+            }
+            if let Err(e) = overlay::set_opacity(window, progress) {
+                eprintln!("{}", e);
+            }
+        },
+        move |window| {
+            if let Err(e) = window.set_position(end) {
+                eprintln!("Failed to move the window: {}", e);
+            }
+        },
+    );
+    Ok(())
+}
+
+// Fade a window out, then hide it and make it opaque again for whoever shows it next
+pub fn hide(window: &WebviewWindow) -> tauri::Result<()> {
+    let state = window.state::<AnimationState>();
+    if is_hiding(window) {
+        return Ok(());
+    }
+    state.interrupt();
+    if reduce_motion(window) {
+        return window.hide();
+    }
+    state.hiding.store(true, Ordering::SeqCst);
+    run(
+        window,
+        |window, progress| {
+            if let Err(e) = overlay::set_opacity(window, 1.0 - progress) {
+                eprintln!("{}", e);
+            }
+        },
+        |window| {
+            window.state::<AnimationState>().hiding.store(false, Ordering::SeqCst);
+            if let Err(e) = window.hide() {
+                eprintln!("Failed to hide the window: {}", e);
+            }
+            if let Err(e) = overlay::set_opacity(window, 1.0) {
+                eprintln!("{}", e);
+            }
+        },
+    );
+    Ok(())
+}
+
+// A window fading out counts as hidden already, so toggling it brings it straight back
+pub fn is_hiding(window: &WebviewWindow) -> bool {
+    window.state::<AnimationState>().hiding.load(Ordering::SeqCst)
+}
@@ -0,0 +1,92 @@
+use serde::Serialize;
+use tauri::Manager;
+
+use crate::apps::AppIndex;
+use crate::cache::ResultCache;
+use crate::files::FileIndex;
+use crate::{automation, logs, profiles, AppState};
+
+// Something the app itself can do, listed when the spotlight's input starts with `>`
+#[derive(Clone, Debug, Serialize)]
+pub struct AppAction {
+    pub id: &'static str,
+    pub title: String,
+    // Other words the action should be found by
+    pub keywords: &'static [&'static str],
+}
+
+fn action(id: &'static str, title: &str, keywords: &'static [&'static str]) -> AppAction {
+    AppAction {
+        id,
+        title: title.to_string(),
+        keywords,
+    }
+}
+
+// The actions in the order the palette shows them before anything is typed
+pub fn actions(app_handle: &tauri::AppHandle) -> Vec<AppAction> {
+    let pause_title = if automation::is_paused(app_handle) {
+        "Resume Automation"
+    } else {
+        "Pause Automation"
+    };
+    let dry_run_title = if automation::is_dry_run(app_handle) {
+        "Turn Dry Run Off"
+    } else {
+        "Turn Dry Run On"
+    };
+    vec![
+        action("open_settings", "Open Settings", &["preferences", "options"]),
+        action("open_console", "Open Console", &["jobs", "output"]),
+        action("toggle_pause", pause_title, &["stop", "resume", "automation"]),
+        action("toggle_dry_run", dry_run_title, &["preview", "simulate", "test"]),
+        action("restart_backend", "Restart Backend", &["server", "python", "reload"]),
+        action("export_logs", "Export Logs", &["save", "debug", "support"]),
+        action("reindex_files", "Reindex Files", &["search", "refresh"]),
+        action("refresh_apps", "Refresh Installed Apps", &["applications", "launcher"]),
+        action("clear_result_cache", "Clear Cached Results", &["responses", "reset"]),
+        action("quit", "Quit Krya.ai", &["exit", "close"]),
+    ]
+}
+
+pub fn run(app_handle: &tauri::AppHandle, id: &str) -> Result<(), String> {
+    match id {
+        "open_settings" => crate::open_settings_window(app_handle),
+        "open_console" => crate::open_console_window(app_handle),
+        "toggle_pause" => {
+            let paused = !automation::is_paused(app_handle);
+            automation::set_paused(app_handle, paused)?;
+        }
+        "toggle_dry_run" => {
+            let dry_run = !automation::is_dry_run(app_handle);
+            automation::set_dry_run(app_handle, dry_run)?;
+        }
+        "restart_backend" => profiles::restart_backend(app_handle)?,
+        // Cancelling the save dialog isn't a failure
+        "export_logs" => {
+            logs::export_logs(app_handle.clone(), None, None)?;
+        }
+        "reindex_files" => app_handle.state::<FileIndex>().request_reindex(),
+        "refresh_apps" => app_handle.state::<AppIndex>().refresh_in_background(),
+        "clear_result_cache" => app_handle.state::<ResultCache>().clear(),
+        "quit" => {
+            crate::stop_api_server(&app_handle.state::<AppState>());
+            crate::ollama::stop(app_handle);
+            app_handle.exit(0);
+        }
+        _ => return Err(format!("Unknown app action: {}", id)),
+    }
+    Ok(())
+}
+
+#[tauri::command]
+pub fn list_app_actions(app_handle: tauri::AppHandle) -> Vec<AppAction> {
+    actions(&app_handle)
+}
+
+// Command to run an action from the palette; some wait on the backend or a dialog
+#[tauri::command(async)]
+pub fn run_app_action(app_handle: tauri::AppHandle, id: String) -> Result<(), String> {
+    println!("Running app action {}", id);
+    run(&app_handle, &id)
+}
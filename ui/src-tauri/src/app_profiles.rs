@@ -0,0 +1,105 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Mutex;
+use tauri::{Emitter, Manager, WebviewWindow};
+
+use crate::active_app::{self, ActiveApp};
+use crate::{quick_actions, settings};
+
+// Overrides for prompts started while a particular application was in front
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AppProfile {
+    // Application name or executable, as for the hot corner's excluded apps
+    pub app: String,
+    // Wraps prompts typed in the spotlight; may use {{prompt}} and {{app}}
+    pub prompt_template: Option<String>,
+    // Attach a screenshot of the application to the spotlight's prompt
+    pub attach_screenshot: bool,
+    // Never type or press keys into the application, from expansions or MCP clients
+    pub disable_input: bool,
+}
+
+// The application the spotlight was last opened over and the profile that matched it
+#[derive(Clone, Debug, Serialize)]
+pub struct AppliedProfile {
+    pub app: ActiveApp,
+    pub profile: Option<AppProfile>,
+}
+
+#[derive(Default)]
+pub struct AppProfileState(Mutex<Option<AppliedProfile>>);
+
+fn profile_for(app_handle: &tauri::AppHandle, app: &ActiveApp) -> Option<AppProfile> {
+    settings::current(app_handle)
+        .app_profiles
+        .into_iter()
+        .find(|profile| app.matches(&profile.app))
+}
+
+// Remember the frontmost application before the spotlight takes focus from it
+pub fn capture(window: &WebviewWindow) -> Option<AppProfile> {
+    let app = active_app::frontmost_app()?;
+    // Reopening the spotlight from the spotlight itself keeps the app it was opened over
+    if app.process_id == std::process::id() as u64 {
+        return current(window.app_handle()).and_then(|applied| applied.profile);
+    }
+    let profile = profile_for(window.app_handle(), &app);
+    *window.state::<AppProfileState>().0.lock().unwrap() = Some(AppliedProfile {
+        app,
+        profile: profile.clone(),
+    });
+    profile
+}
+
+// The application the spotlight was last opened over
+pub fn opened_over(app_handle: &tauri::AppHandle) -> Option<ActiveApp> {
+    current(app_handle).map(|applied| applied.app)
+}
+
+// Tell a spotlight that was just opened which profile its prompt will use
+pub fn announce(window: &WebviewWindow) {
+    if let Some(applied) = current(window.app_handle()) {
+        if let Err(e) = window.emit_to(window.label(), "app-profile-applied", applied) {
+            eprintln!("Failed to emit app-profile-applied: {}", e);
+        }
+    }
+}
+
+fn current(app_handle: &tauri::AppHandle) -> Option<AppliedProfile> {
+    app_handle.state::<AppProfileState>().0.lock().unwrap().clone()
+}
+
+// Wrap a spotlight prompt in the template of the application it was opened over
+pub fn apply_template(app_handle: &tauri::AppHandle, prompt: String) -> String {
+    let applied = match current(app_handle) {
+        Some(applied) => applied,
+        None => return prompt,
+    };
+    let template = match applied.profile.and_then(|profile| profile.prompt_template) {
+        Some(template) if !template.trim().is_empty() => template,
+        _ => return prompt,
+    };
+    if !quick_actions::placeholders(&template).iter().any(|name| name == "prompt") {
+        return format!("{}\n\n{}", template.trim_end(), prompt);
+    }
+
+    let mut values = HashMap::new();
+    values.insert("prompt".to_string(), prompt);
+    values.insert("app".to_string(), applied.app.name);
+    quick_actions::fill_placeholders(&template, &values)
+}
+
+// Refuse to inject input when the application that would receive it has input disabled
+pub fn check_input(app_handle: &tauri::AppHandle, app: &ActiveApp) -> Result<(), String> {
+    match profile_for(app_handle, app) {
+        Some(profile) if profile.disable_input => Err(format!("Input is disabled for {}", app.name)),
+        _ => Ok(()),
+    }
+}
+
+// Command for the spotlight to show which profile applies after a reload
+#[tauri::command]
+pub fn get_app_profile(app_handle: tauri::AppHandle) -> Option<AppliedProfile> {
+    current(&app_handle)
+}
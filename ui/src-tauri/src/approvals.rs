@@ -0,0 +1,337 @@
+use once_cell::sync::Lazy;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::sync::Mutex;
+use tauri::{Emitter, Manager};
+use tauri_plugin_dialog::{DialogExt, MessageDialogButtons, MessageDialogKind};
+
+use crate::protocol::{self, ApprovalParams, JobInfo};
+use crate::{policy, settings};
+
+// Phase of a job whose generated script waits for the shell's verdict before it runs
+pub const AWAITING_PHASE: &str = "awaiting_approval";
+
+// Longest line of a script quoted as the reason it needs confirming
+const MAX_EVIDENCE_CHARS: usize = 100;
+
+// Kinds of action a generated script can only take once the user has confirmed them
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ActionCategory {
+    FileDeletion,
+    SendingEmail,
+    ShellCommands,
+    Purchases,
+}
+
+impl ActionCategory {
+    pub const ALL: &'static [ActionCategory] = &[
+        ActionCategory::FileDeletion,
+        ActionCategory::SendingEmail,
+        ActionCategory::ShellCommands,
+        ActionCategory::Purchases,
+    ];
+
+    pub fn description(&self) -> &'static str {
+        match self {
+            ActionCategory::FileDeletion => "Delete files",
+            ActionCategory::SendingEmail => "Send email",
+            ActionCategory::ShellCommands => "Run shell commands",
+            ActionCategory::Purchases => "Buy things",
+        }
+    }
+
+    // What a script doing this looks like. These err on the side of asking.
+    fn code_patterns(&self) -> &'static [&'static str] {
+        match self {
+            ActionCategory::FileDeletion => &[
+                r"\bos\.(remove|unlink|rmdir|removedirs)\s*\(",
+                r"\bshutil\.rmtree\s*\(",
+                r"\.(unlink|rmdir)\s*\(",
+                r"\bsend2trash\b",
+                r"\brm\s+-[a-z]*[rf]",
+                r"\b(del|rd|rmdir)\s+/[sq]",
+                r"\bRemove-Item\b",
+            ],
+            ActionCategory::SendingEmail => &[
+                r"\bsmtplib\b",
+                r"\b(sendmail|send_message)\s*\(",
+                r"\byagmail\b",
+                r"mailto:",
+                r"Outlook\.Application",
+            ],
+            ActionCategory::ShellCommands => &[
+                r"\bsubprocess\b",
+                r"\bos\.(system|popen|exec\w*|spawn\w*)\s*\(",
+                r"\bpty\.spawn\b",
+                r"\bcreate_subprocess_(exec|shell)\b",
+            ],
+            ActionCategory::Purchases => &[
+                r"\b(checkout|check out|place (your )?order|buy now|pay now|complete (your )?purchase)\b",
+                r"\badd to (cart|basket|bag)\b",
+                r"\b(card number|cvv|cvc)\b",
+            ],
+        }
+    }
+
+    // The same in the prompt, for scripts that only click through an app's own buttons
+    fn prompt_patterns(&self) -> &'static [&'static str] {
+        match self {
+            ActionCategory::FileDeletion => &[r"\b(delete|erase|wipe|trash)\b"],
+            ActionCategory::SendingEmail => &[r"\b(send|reply|forward)\b.*\b(e-?mail|mail)\b"],
+            ActionCategory::ShellCommands => &[r"\b(terminal|shell|command prompt|powershell|bash)\b"],
+            ActionCategory::Purchases => &[r"\b(buy|purchase|checkout|pay for|order)\b"],
+        }
+    }
+}
+
+static CODE_PATTERNS: Lazy<Vec<(ActionCategory, Regex)>> = Lazy::new(|| compile(ActionCategory::code_patterns));
+static PROMPT_PATTERNS: Lazy<Vec<(ActionCategory, Regex)>> = Lazy::new(|| compile(ActionCategory::prompt_patterns));
+
+fn compile(patterns: fn(&ActionCategory) -> &'static [&'static str]) -> Vec<(ActionCategory, Regex)> {
+    ActionCategory::ALL
+        .iter()
+        .flat_map(|category| {
+            patterns(category)
+                .iter()
+                .map(move |pattern| (*category, Regex::new(&format!("(?i){}", pattern)).unwrap()))
+        })
+        .collect()
+}
+
+// An action a job's script would take, and what gave it away
+#[derive(Clone, Debug, Serialize)]
+pub struct Finding {
+    pub category: ActionCategory,
+    pub evidence: String,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct ConfirmationPolicy {
+    pub category: ActionCategory,
+    pub confirm: bool,
+    // Set when the policy forces the confirmation on
+    pub locked: bool,
+    pub description: String,
+}
+
+#[derive(Clone, Debug, Serialize)]
+struct ApprovalRequested<'a> {
+    job_id: &'a str,
+    findings: &'a [Finding],
+}
+
+// Jobs and attempts already being decided, so a poll doesn't ask twice
+#[derive(Default)]
+pub struct ApprovalState {
+    asked: Mutex<HashSet<(String, u32)>>,
+}
+
+fn shorten(line: &str) -> String {
+    let line = line.trim();
+    if line.chars().count() > MAX_EVIDENCE_CHARS {
+        format!("{}…", line.chars().take(MAX_EVIDENCE_CHARS).collect::<String>())
+    } else {
+        line.to_string()
+    }
+}
+
+// Each category a script and its prompt fall into, with the first line showing it
+pub fn classify(prompt: &str, code: &str) -> Vec<Finding> {
+    let mut findings: Vec<Finding> = Vec::new();
+    let mut add = |category: ActionCategory, evidence: String| {
+        if !findings.iter().any(|finding| finding.category == category) {
+            findings.push(Finding { category, evidence });
+        }
+    };
+    for line in code.lines().filter(|line| !line.trim_start().starts_with('#')) {
+        for (category, pattern) in CODE_PATTERNS.iter() {
+            if pattern.is_match(line) {
+                add(*category, shorten(line));
+            }
+        }
+    }
+    for (category, pattern) in PROMPT_PATTERNS.iter() {
+        if let Some(found) = pattern.find(prompt) {
+            add(*category, format!("the prompt says \"{}\"", found.as_str()));
+        }
+    }
+    findings.sort_by_key(|finding| ActionCategory::ALL.iter().position(|c| *c == finding.category));
+    findings
+}
+
+// Ask the user about a script that does something they want to confirm; anything else runs
+fn decide(app_handle: &tauri::AppHandle, job: &JobInfo) -> (bool, Option<String>) {
+    let confirm = settings::current(app_handle).permissions.confirm;
+    let prompt = job.prompt.as_deref().unwrap_or_default();
+    let findings: Vec<Finding> = classify(prompt, job.code.as_deref().unwrap_or_default())
+        .into_iter()
+        .filter(|finding| confirm.contains(&finding.category))
+        .collect();
+    if findings.is_empty() {
+        return (true, None);
+    }
+
+    let request = ApprovalRequested {
+        job_id: &job.job_id,
+        findings: &findings,
+    };
+    if let Err(e) = app_handle.emit("approval-requested", request) {
+        eprintln!("Failed to emit approval-requested: {}", e);
+    }
+    let actions: Vec<String> = findings
+        .iter()
+        .map(|finding| format!("• {}: {}", finding.category.description(), finding.evidence))
+        .collect();
+    let message = format!(
+        "\"{}\" wants to:\n\n{}\n\nLet it run?",
+        job.prompt.as_deref().unwrap_or("An automation"),
+        actions.join("\n")
+    );
+    let allowed = app_handle
+        .dialog()
+        .message(message)
+        .title("Krya.ai needs your confirmation")
+        .kind(MessageDialogKind::Warning)
+        .buttons(MessageDialogButtons::OkCancelCustom("Allow".to_string(), "Block".to_string()))
+        .blocking_show();
+    if allowed {
+        (true, None)
+    } else {
+        let blocked: Vec<&str> = findings.iter().map(|finding| finding.category.description()).collect();
+        (false, Some(format!("Blocked by you: {}", blocked.join(", ").to_lowercase())))
+    }
+}
+
+// Called for each poll of a job waiting for approval; decides once per attempt, off the polling thread
+pub fn review(app_handle: &tauri::AppHandle, job: &JobInfo) {
+    let attempt = job.attempt.unwrap_or(1);
+    let key = (job.job_id.clone(), attempt);
+    if !app_handle.state::<ApprovalState>().asked.lock().unwrap().insert(key.clone()) {
+        return;
+    }
+
+    let app_handle = app_handle.clone();
+    let job = job.clone();
+    std::thread::spawn(move || {
+        let (approved, reason) = decide(&app_handle, &job);
+        println!(
+            "Job {} attempt {} {}",
+            job.job_id,
+            attempt,
+            if approved { "approved" } else { "blocked" }
+        );
+        let params = ApprovalParams {
+            job_id: job.job_id.clone(),
+            attempt,
+            approved,
+            reason,
+        };
+        if let Err(e) = protocol::call::<protocol::ApproveJob>(&params).and_then(|status| status.into_result()) {
+            eprintln!("Failed to send the verdict on job {}: {}", job.job_id, e);
+        }
+        app_handle.state::<ApprovalState>().asked.lock().unwrap().remove(&key);
+    });
+}
+
+// Tell the Python server to hold every generated script for review, also those of jobs submitted to it
+// by something other than the shell; the shell's own ask for it with each job
+pub fn env_hints() -> Vec<(&'static str, String)> {
+    vec![("KRYA_REQUIRE_APPROVAL", "1".to_string())]
+}
+
+#[tauri::command]
+pub fn get_confirmation_policies(app_handle: tauri::AppHandle) -> Vec<ConfirmationPolicy> {
+    let confirm = settings::current(&app_handle).permissions.confirm;
+    ActionCategory::ALL
+        .iter()
+        .map(|category| ConfirmationPolicy {
+            category: *category,
+            confirm: confirm.contains(category),
+            locked: policy::get().always_confirm.contains(category),
+            description: category.description().to_string(),
+        })
+        .collect()
+}
+
+#[tauri::command]
+pub fn set_confirmation_policy(
+    app_handle: tauri::AppHandle,
+    category: ActionCategory,
+    confirm: bool,
+) -> Result<(), String> {
+    if !confirm && policy::get().always_confirm.contains(&category) {
+        return Err(format!("\"{}\" always needs confirming by your organization's policy", category.description()));
+    }
+    let mut current = settings::current(&app_handle);
+    current.permissions.confirm.retain(|existing| *existing != category);
+    if confirm {
+        current.permissions.confirm.push(category);
+    }
+    settings::replace(&app_handle, current)
+}
+
+// Command for the spotlight to preview which confirmations a script would need
+#[tauri::command]
+pub fn classify_actions(prompt: String, code: String) -> Vec<Finding> {
+    classify(&prompt, &code)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn categories(prompt: &str, code: &str) -> Vec<ActionCategory> {
+        classify(prompt, code).into_iter().map(|finding| finding.category).collect()
+    }
+
+    #[test]
+    fn finds_file_deletion() {
+        assert_eq!(categories("", "os.remove(path)"), [ActionCategory::FileDeletion]);
+        assert_eq!(categories("", "shutil.rmtree(folder)"), [ActionCategory::FileDeletion]);
+        assert_eq!(categories("", "Path('a.txt').unlink()"), [ActionCategory::FileDeletion]);
+        assert_eq!(categories("Delete my old screenshots", ""), [ActionCategory::FileDeletion]);
+        assert!(categories("", "os.path.exists(path)").is_empty());
+        assert!(categories("", "    # os.remove(path) is too risky here").is_empty());
+    }
+
+    #[test]
+    fn finds_sending_email() {
+        assert_eq!(categories("", "import smtplib"), [ActionCategory::SendingEmail]);
+        assert_eq!(categories("", "server.send_message(msg)"), [ActionCategory::SendingEmail]);
+        assert_eq!(categories("Send an email to Sam", ""), [ActionCategory::SendingEmail]);
+        assert!(categories("Read my latest email", "").is_empty());
+    }
+
+    #[test]
+    fn finds_shell_commands() {
+        assert_eq!(categories("", "subprocess.run(['ls'])"), [ActionCategory::ShellCommands]);
+        assert_eq!(categories("", "os.system('echo hi')"), [ActionCategory::ShellCommands]);
+        assert_eq!(categories("Open a terminal", ""), [ActionCategory::ShellCommands]);
+        assert!(categories("", "pyautogui.write('system')").is_empty());
+    }
+
+    #[test]
+    fn finds_purchases() {
+        assert_eq!(categories("", "click('Buy now')"), [ActionCategory::Purchases]);
+        assert_eq!(categories("", "find_button('Add to cart')"), [ActionCategory::Purchases]);
+        assert_eq!(categories("Buy me a new charger", ""), [ActionCategory::Purchases]);
+        assert!(categories("Compare prices of chargers", "").is_empty());
+    }
+
+    #[test]
+    fn harmless_scripts_need_no_confirmation() {
+        let code = "import pyautogui\npyautogui.hotkey('command', 'space')\npyautogui.write('Calculator')";
+        assert!(classify("Open the calculator", code).is_empty());
+    }
+
+    #[test]
+    fn reports_each_category_once_in_order() {
+        let code = "import smtplib\nos.remove(a)\nos.remove(b)";
+        let findings = classify("", code);
+        let found: Vec<ActionCategory> = findings.iter().map(|finding| finding.category).collect();
+        assert_eq!(found, [ActionCategory::FileDeletion, ActionCategory::SendingEmail]);
+        assert_eq!(findings[0].evidence, "os.remove(a)");
+    }
+}
@@ -0,0 +1,261 @@
+use fuzzy_matcher::skim::SkimMatcherV2;
+use fuzzy_matcher::FuzzyMatcher;
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::{Arc, Mutex};
+use walkdir::WalkDir;
+
+// An installed application that can be launched from the spotlight
+#[derive(Clone, Debug, Serialize)]
+pub struct AppEntry {
+    pub name: String,
+    pub path: String,
+    // Command line from a .desktop file (Linux only)
+    #[serde(skip)]
+    pub exec: Option<String>,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct AppMatch {
+    #[serde(flatten)]
+    pub app: AppEntry,
+    pub score: i64,
+}
+
+#[derive(Clone, Default)]
+pub struct AppIndex {
+    apps: Arc<Mutex<Vec<AppEntry>>>,
+}
+
+impl AppIndex {
+    // Rebuild the catalog on a background thread so startup isn't blocked
+    pub fn refresh_in_background(&self) {
+        let apps = self.apps.clone();
+        std::thread::spawn(move || {
+            let found = scan_applications();
+            println!("Indexed {} installed applications", found.len());
+            *apps.lock().unwrap() = found;
+        });
+    }
+
+    pub fn search(&self, query: &str, limit: usize) -> Vec<AppMatch> {
+        let matcher = SkimMatcherV2::default();
+        let apps = self.apps.lock().unwrap();
+
+        let mut matches: Vec<AppMatch> = apps
+            .iter()
+            .filter_map(|app| {
+                matcher.fuzzy_match(&app.name, query).map(|score| AppMatch {
+                    app: app.clone(),
+                    score,
+                })
+            })
+            .collect();
+
+        matches.sort_by(|a, b| b.score.cmp(&a.score).then_with(|| a.app.name.cmp(&b.app.name)));
+        matches.truncate(limit);
+        matches
+    }
+
+    pub fn with_apps<R>(&self, f: impl FnOnce(&[AppEntry]) -> R) -> R {
+        f(&self.apps.lock().unwrap())
+    }
+
+    fn find(&self, path: &str) -> Option<AppEntry> {
+        self.apps.lock().unwrap().iter().find(|app| app.path == path).cloned()
+    }
+}
+
+#[cfg(not(target_os = "windows"))]
+fn home_dir() -> Option<PathBuf> {
+    std::env::var_os("HOME").map(PathBuf::from)
+}
+
+#[cfg(target_os = "macos")]
+fn scan_applications() -> Vec<AppEntry> {
+    let mut roots = vec![
+        PathBuf::from("/Applications"),
+        PathBuf::from("/System/Applications"),
+    ];
+    if let Some(home) = home_dir() {
+        roots.push(home.join("Applications"));
+    }
+
+    let mut apps = Vec::new();
+    for root in roots {
+        // Depth 2 picks up folders like /Applications/Utilities
+        let mut walker = WalkDir::new(&root).max_depth(2).into_iter();
+        while let Some(Ok(entry)) = walker.next() {
+            let path = entry.path();
+            if path.extension().map(|ext| ext == "app").unwrap_or(false) {
+                apps.push(AppEntry {
+                    name: file_stem(path),
+                    path: path.to_string_lossy().to_string(),
+                    exec: None,
+                });
+                // Don't descend into the bundle itself
+                walker.skip_current_dir();
+            }
+        }
+    }
+    dedup(apps)
+}
+
+#[cfg(target_os = "windows")]
+fn scan_applications() -> Vec<AppEntry> {
+    let mut roots = Vec::new();
+    if let Some(program_data) = std::env::var_os("ProgramData") {
+        roots.push(PathBuf::from(program_data).join("Microsoft\\Windows\\Start Menu\\Programs"));
+    }
+    if let Some(app_data) = std::env::var_os("APPDATA") {
+        roots.push(PathBuf::from(app_data).join("Microsoft\\Windows\\Start Menu\\Programs"));
+    }
+
+    let mut apps = Vec::new();
+    for root in roots {
+        for entry in WalkDir::new(&root).into_iter().flatten() {
+            let path = entry.path();
+            if path.extension().map(|ext| ext.eq_ignore_ascii_case("lnk")).unwrap_or(false) {
+                let name = file_stem(path);
+                // Skip uninstaller shortcuts that clutter the Start Menu
+                if name.to_lowercase().contains("uninstall") {
+                    continue;
+                }
+                apps.push(AppEntry {
+                    name,
+                    path: path.to_string_lossy().to_string(),
+                    exec: None,
+                });
+            }
+        }
+    }
+    dedup(apps)
+}
+
+#[cfg(all(not(target_os = "macos"), not(target_os = "windows")))]
+fn scan_applications() -> Vec<AppEntry> {
+    let mut roots = vec![
+        PathBuf::from("/usr/share/applications"),
+        PathBuf::from("/usr/local/share/applications"),
+        PathBuf::from("/var/lib/flatpak/exports/share/applications"),
+    ];
+    if let Some(home) = home_dir() {
+        roots.push(home.join(".local/share/applications"));
+        roots.push(home.join(".local/share/flatpak/exports/share/applications"));
+    }
+
+    let mut apps = Vec::new();
+    for root in roots {
+        for entry in WalkDir::new(&root).into_iter().flatten() {
+            let path = entry.path();
+            if path.extension().map(|ext| ext == "desktop").unwrap_or(false) {
+                if let Some(app) = parse_desktop_file(path) {
+                    apps.push(app);
+                }
+            }
+        }
+    }
+    dedup(apps)
+}
+
+// Read the [Desktop Entry] section of a .desktop file
+#[cfg(all(not(target_os = "macos"), not(target_os = "windows")))]
+fn parse_desktop_file(path: &Path) -> Option<AppEntry> {
+    let contents = std::fs::read_to_string(path).ok()?;
+
+    let mut in_entry = false;
+    let mut name = None;
+    let mut exec = None;
+    let mut is_application = false;
+
+    for line in contents.lines() {
+        let line = line.trim();
+        if line.starts_with('[') {
+            in_entry = line == "[Desktop Entry]";
+            continue;
+        }
+        if !in_entry {
+            continue;
+        }
+
+        match line.split_once('=') {
+            Some(("Name", value)) if name.is_none() => name = Some(value.to_string()),
+            Some(("Exec", value)) => exec = Some(value.to_string()),
+            Some(("Type", value)) => is_application = value == "Application",
+            Some(("NoDisplay", "true")) | Some(("Hidden", "true")) => return None,
+            _ => {}
+        }
+    }
+
+    if !is_application {
+        return None;
+    }
+
+    Some(AppEntry {
+        name: name?,
+        path: path.to_string_lossy().to_string(),
+        exec: Some(strip_field_codes(&exec?)),
+    })
+}
+
+// Remove %f, %U and friends from a desktop Exec line
+#[cfg(all(not(target_os = "macos"), not(target_os = "windows")))]
+fn strip_field_codes(exec: &str) -> String {
+    exec.split_whitespace()
+        .filter(|part| !(part.len() == 2 && part.starts_with('%')))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+#[cfg(any(target_os = "macos", target_os = "windows"))]
+fn file_stem(path: &Path) -> String {
+    path.file_stem()
+        .map(|stem| stem.to_string_lossy().to_string())
+        .unwrap_or_default()
+}
+
+fn dedup(mut apps: Vec<AppEntry>) -> Vec<AppEntry> {
+    apps.sort_by_key(|app| app.name.to_lowercase());
+    apps.dedup_by(|a, b| a.name.eq_ignore_ascii_case(&b.name));
+    apps
+}
+
+pub fn launch(app: &AppEntry) -> Result<(), String> {
+    let result = if cfg!(target_os = "macos") {
+        Command::new("open").arg(&app.path).spawn()
+    } else if cfg!(target_os = "windows") {
+        Command::new("cmd").args(["/C", "start", "", &app.path]).spawn()
+    } else {
+        let exec = app
+            .exec
+            .as_ref()
+            .ok_or_else(|| format!("No launch command for {}", app.name))?;
+        Command::new("sh").arg("-c").arg(exec).spawn()
+    };
+
+    result
+        .map(|_| ())
+        .map_err(|e| format!("Failed to launch {}: {}", app.name, e))
+}
+
+// Command to fuzzy-search installed applications
+#[tauri::command]
+pub fn search_apps(index: tauri::State<AppIndex>, query: String, limit: Option<usize>) -> Vec<AppMatch> {
+    index.search(&query, limit.unwrap_or(10))
+}
+
+// Command to launch an application previously returned by search_apps
+#[tauri::command]
+pub fn launch_app(index: tauri::State<AppIndex>, path: String) -> Result<(), String> {
+    let app = index
+        .find(&path)
+        .ok_or_else(|| format!("Application not found in index: {}", path))?;
+    launch(&app)
+}
+
+// Command to rescan installed applications (e.g. after installing something new)
+#[tauri::command]
+pub fn refresh_app_index(index: tauri::State<AppIndex>) {
+    index.refresh_in_background();
+}
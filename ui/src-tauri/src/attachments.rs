@@ -0,0 +1,301 @@
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use serde::Serialize;
+use std::fs::File;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use tauri::{Emitter, Manager};
+
+use crate::handoff::HandoffState;
+use crate::protocol::{self, AttachParams, ScreenshotParams};
+use crate::scopes;
+
+// Larger files are better pointed at by path in the prompt than copied
+const MAX_ATTACHMENT_BYTES: u64 = 100 * 1024 * 1024;
+
+const CHUNK_SIZE: usize = 256 * 1024;
+
+// Types the backend reads as text; anything else is passed along as a file
+const MIME_TYPES: &[(&str, &str)] = &[
+    ("txt", "text/plain"),
+    ("md", "text/markdown"),
+    ("csv", "text/csv"),
+    ("json", "application/json"),
+    ("xml", "application/xml"),
+    ("html", "text/html"),
+    ("py", "text/x-python"),
+    ("js", "text/javascript"),
+    ("ts", "text/typescript"),
+    ("rs", "text/x-rust"),
+    ("pdf", "application/pdf"),
+    ("png", "image/png"),
+    ("jpg", "image/jpeg"),
+    ("jpeg", "image/jpeg"),
+    ("gif", "image/gif"),
+    ("webp", "image/webp"),
+    ("docx", "application/vnd.openxmlformats-officedocument.wordprocessingml.document"),
+    ("xlsx", "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet"),
+];
+
+#[derive(Clone, Debug, Serialize)]
+pub struct Attachment {
+    pub job_id: String,
+    pub name: String,
+    pub size: u64,
+    pub mime_type: String,
+}
+
+// A dropped file that couldn't be attached, and why
+#[derive(Clone, Debug, Serialize)]
+pub struct RejectedFile {
+    pub path: String,
+    pub error: String,
+}
+
+#[derive(Clone, Debug, Serialize)]
+struct FilesDropped {
+    job_id: String,
+    attachments: Vec<Attachment>,
+    rejected: Vec<RejectedFile>,
+}
+
+// Job that files dropped on the spotlight are attached to until the prompt is submitted
+#[derive(Default)]
+pub struct AttachmentsState {
+    pending_job: Mutex<Option<String>>,
+}
+
+impl AttachmentsState {
+    fn pending_job(&self) -> String {
+        self.pending_job
+            .lock()
+            .unwrap()
+            .get_or_insert_with(|| uuid::Uuid::new_v4().to_string())
+            .clone()
+    }
+}
+
+#[derive(Clone, Debug, Serialize)]
+struct AttachmentProgress<'a> {
+    job_id: &'a str,
+    name: &'a str,
+    sent: u64,
+    total: u64,
+    done: bool,
+}
+
+fn mime_type(path: &Path) -> &'static str {
+    let extension = path
+        .extension()
+        .map(|e| e.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+    MIME_TYPES
+        .iter()
+        .find(|(ext, _)| *ext == extension)
+        .map(|(_, mime)| *mime)
+        .unwrap_or("application/octet-stream")
+}
+
+// Copy the source into the handoff file, reporting progress each time another percent is done
+fn copy_with_progress(
+    app_handle: &tauri::AppHandle,
+    source: &mut File,
+    target: &mut File,
+    progress: &mut AttachmentProgress,
+) -> std::io::Result<()> {
+    let mut buffer = vec![0u8; CHUNK_SIZE];
+    let mut last_percent = 0;
+    loop {
+        let read = source.read(&mut buffer)?;
+        if read == 0 {
+            return target.flush();
+        }
+        target.write_all(&buffer[..read])?;
+        progress.sent += read as u64;
+
+        let percent = progress.sent * 100 / progress.total.max(1);
+        if percent != last_percent {
+            last_percent = percent;
+            let _ = app_handle.emit("attachment-progress", &*progress);
+        }
+    }
+}
+
+// Attach a file to a job's prompt; the backend reads it when the job runs
+pub fn attach(app_handle: &tauri::AppHandle, job_id: String, path: &str) -> Result<Attachment, String> {
+    let path = scopes::check_path(app_handle, path)?;
+    let metadata = path.metadata().map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    if !metadata.is_file() {
+        return Err(format!("{} is not a file", path.display()));
+    }
+    if metadata.len() > MAX_ATTACHMENT_BYTES {
+        return Err(format!(
+            "{} is larger than the {} MB attachment limit",
+            path.display(),
+            MAX_ATTACHMENT_BYTES / (1024 * 1024)
+        ));
+    }
+
+    let name = path
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let extension = path
+        .extension()
+        .map(|e| e.to_string_lossy().to_lowercase())
+        .unwrap_or_else(|| "bin".to_string());
+    let mime_type = mime_type(&path).to_string();
+
+    let handoff = app_handle.state::<HandoffState>();
+    let (copy, mut target) = handoff.create(Some(&job_id), &extension)?;
+    let mut progress = AttachmentProgress {
+        job_id: &job_id,
+        name: &name,
+        sent: 0,
+        total: metadata.len(),
+        done: false,
+    };
+
+    let copied = File::open(&path)
+        .and_then(|mut source| copy_with_progress(app_handle, &mut source, &mut target, &mut progress));
+    if let Err(e) = copied {
+        handoff.discard(&copy);
+        return Err(format!("Failed to read {}: {}", path.display(), e));
+    }
+
+    let attachment = register(&handoff, job_id.clone(), name.clone(), &copy, progress.sent, mime_type)?;
+    progress.done = true;
+    let _ = app_handle.emit("attachment-progress", &progress);
+    Ok(attachment)
+}
+
+// Tell the backend about a file now in the handoff directory, removing it if the backend refuses
+fn register(
+    handoff: &HandoffState,
+    job_id: String,
+    name: String,
+    copy: &Path,
+    size: u64,
+    mime_type: String,
+) -> Result<Attachment, String> {
+    let params = AttachParams {
+        job_id,
+        name,
+        file: copy.to_string_lossy().to_string(),
+        size,
+        mime_type,
+    };
+    if let Err(e) = protocol::call::<protocol::AttachFile>(&params).and_then(|reply| reply.into_result()) {
+        handoff.discard(copy);
+        return Err(e);
+    }
+    Ok(Attachment {
+        job_id: params.job_id,
+        name: params.name,
+        size,
+        mime_type: params.mime_type,
+    })
+}
+
+#[tauri::command(async)]
+pub fn attach_file(app_handle: tauri::AppHandle, job_id: String, path: String) -> Result<Attachment, String> {
+    attach(&app_handle, job_id, &path)
+}
+
+// Attach files dropped on the spotlight to its pending job, reporting which ones were refused
+pub fn handle_drop(app_handle: &tauri::AppHandle, paths: Vec<PathBuf>) {
+    let job_id = app_handle.state::<AttachmentsState>().pending_job();
+    let app_handle = app_handle.clone();
+    // Copying large files shouldn't block the event loop
+    std::thread::spawn(move || {
+        let mut dropped = FilesDropped {
+            job_id: job_id.clone(),
+            attachments: Vec::new(),
+            rejected: Vec::new(),
+        };
+        for path in paths {
+            let path = path.to_string_lossy().to_string();
+            match attach(&app_handle, job_id.clone(), &path) {
+                Ok(attachment) => dropped.attachments.push(attachment),
+                Err(error) => {
+                    eprintln!("Not attaching dropped file {}: {}", path, error);
+                    dropped.rejected.push(RejectedFile { path, error });
+                }
+            }
+        }
+        if let Err(e) = app_handle.emit("files-dropped", &dropped) {
+            eprintln!("Failed to emit files-dropped: {}", e);
+        }
+    });
+}
+
+fn encode_png(image: &arboard::ImageData) -> Result<Vec<u8>, String> {
+    let mut data = Vec::new();
+    let mut encoder = png::Encoder::new(&mut data, image.width as u32, image.height as u32);
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+    encoder
+        .write_header()
+        .and_then(|mut writer| writer.write_image_data(&image.bytes))
+        .map_err(|e| format!("Failed to encode pasted image: {}", e))?;
+    Ok(data)
+}
+
+// Attach an image on the clipboard to the spotlight's pending job. Returns None when the
+// clipboard holds no image, so the UI can let the paste through as text.
+#[tauri::command(async)]
+pub fn paste_clipboard_image(app_handle: tauri::AppHandle) -> Result<Option<Attachment>, String> {
+    let image = match arboard::Clipboard::new().and_then(|mut clipboard| clipboard.get_image()) {
+        Ok(image) => image,
+        Err(arboard::Error::ContentNotAvailable) => return Ok(None),
+        Err(e) => return Err(format!("Failed to read clipboard: {}", e)),
+    };
+    let data = encode_png(&image)?;
+
+    let job_id = app_handle.state::<AttachmentsState>().pending_job();
+    let handoff = app_handle.state::<HandoffState>();
+    let copy = handoff.write(Some(&job_id), "png", &data)?;
+    let name = format!("Pasted image {}x{}.png", image.width, image.height);
+    let attachment = register(&handoff, job_id, name, &copy, data.len() as u64, "image/png".to_string())?;
+    Ok(Some(attachment))
+}
+
+// Attach a screenshot of the screen as it is now to the spotlight's pending job
+pub fn attach_screenshot(app_handle: &tauri::AppHandle) -> Result<Attachment, String> {
+    let capture = protocol::call::<protocol::Screenshot>(&ScreenshotParams { handoff: true })?;
+    let handoff = app_handle.state::<HandoffState>();
+    let data = match (capture.file, capture.image) {
+        (Some(file), _) => handoff.take(&file)?,
+        (None, Some(image)) => BASE64
+            .decode(image)
+            .map_err(|e| format!("Failed to decode screenshot: {}", e))?,
+        (None, None) => return Err("Screenshot response did not contain an image".to_string()),
+    };
+
+    let job_id = app_handle.state::<AttachmentsState>().pending_job();
+    let copy = handoff.write(Some(&job_id), "png", &data)?;
+    let attachment = register(
+        &handoff,
+        job_id.clone(),
+        "Screenshot.png".to_string(),
+        &copy,
+        data.len() as u64,
+        "image/png".to_string(),
+    )?;
+    let dropped = FilesDropped {
+        job_id,
+        attachments: vec![attachment.clone()],
+        rejected: Vec::new(),
+    };
+    if let Err(e) = app_handle.emit("files-dropped", &dropped) {
+        eprintln!("Failed to emit files-dropped: {}", e);
+    }
+    Ok(attachment)
+}
+
+// Hand the pending job to the prompt being submitted; later drops start a new one
+#[tauri::command]
+pub fn take_dropped_job(app_handle: tauri::AppHandle) -> Option<String> {
+    app_handle.state::<AttachmentsState>().pending_job.lock().unwrap().take()
+}
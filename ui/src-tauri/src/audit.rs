@@ -0,0 +1,79 @@
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+use crate::util::now_secs;
+use crate::{logs, paths};
+
+// One JSON object per line in its own folder under the app data, so it can be read without the app
+// and isn't swept up by log cleanup
+const AUDIT_FILE: &str = "audit.jsonl";
+
+// Moved aside once it's this big; the one before is kept
+const MAX_AUDIT_BYTES: u64 = 5 * 1024 * 1024;
+
+// Writes from different threads mustn't interleave within a line
+static WRITE_LOCK: Lazy<Mutex<()>> = Lazy::new(|| Mutex::new(()));
+
+// Something the app did on someone's behalf that may need accounting for later
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct AuditEntry {
+    pub timestamp: u64,
+    pub action: String,
+    // Who asked: the frontend or the backend
+    pub caller: String,
+    pub outcome: String,
+    #[serde(default)]
+    pub detail: Value,
+}
+
+fn path(app_handle: &tauri::AppHandle) -> Result<PathBuf, String> {
+    Ok(paths::audit_dir(app_handle)?.join(AUDIT_FILE))
+}
+
+pub fn record(app_handle: &tauri::AppHandle, action: &str, caller: &str, outcome: &str, detail: Value) {
+    let entry = AuditEntry {
+        timestamp: now_secs(),
+        action: action.to_string(),
+        caller: caller.to_string(),
+        outcome: outcome.to_string(),
+        detail,
+    };
+    let result = path(app_handle).and_then(|path| {
+        let line = serde_json::to_string(&entry).map_err(|e| format!("Failed to encode audit entry: {}", e))?;
+        let _guard = WRITE_LOCK.lock().unwrap();
+        if fs::metadata(&path).is_ok_and(|meta| meta.len() >= MAX_AUDIT_BYTES) {
+            let _ = fs::rename(&path, logs::rotated_path(&path, 1));
+        }
+        OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&path)
+            .and_then(|mut file| writeln!(file, "{}", line))
+            .map_err(|e| format!("Failed to write {:?}: {}", path, e))
+    });
+    if let Err(e) = result {
+        eprintln!("Failed to record {} in the audit log: {}", action, e);
+    }
+}
+
+// Command for the settings pane: the latest entries, newest first
+#[tauri::command]
+pub fn get_audit_log(app_handle: tauri::AppHandle, limit: Option<usize>) -> Result<Vec<AuditEntry>, String> {
+    let path = path(&app_handle)?;
+    let contents = match fs::read_to_string(&path) {
+        Ok(contents) => contents,
+        Err(_) if !path.exists() => return Ok(Vec::new()),
+        Err(e) => return Err(format!("Failed to read {:?}: {}", path, e)),
+    };
+    Ok(contents
+        .lines()
+        .rev()
+        .filter_map(|line| serde_json::from_str(line).ok())
+        .take(limit.unwrap_or(200))
+        .collect())
+}
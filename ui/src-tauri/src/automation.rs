@@ -0,0 +1,92 @@
+use serde::Serialize;
+use std::sync::atomic::{AtomicBool, Ordering};
+use tauri::{Emitter, Manager};
+
+use crate::protocol::{self, DryRunParams, PauseParams};
+
+// Whether new automations are refused, or only have their scripts generated for a look;
+// kept here so it survives backend restarts
+#[derive(Default)]
+pub struct AutomationState {
+    paused: AtomicBool,
+    dry_run: AtomicBool,
+}
+
+#[derive(Clone, Debug, Serialize)]
+struct PausedChanged {
+    paused: bool,
+}
+
+#[derive(Clone, Debug, Serialize)]
+struct DryRunChanged {
+    dry_run: bool,
+}
+
+pub fn is_paused(app_handle: &tauri::AppHandle) -> bool {
+    app_handle.state::<AutomationState>().paused.load(Ordering::SeqCst)
+}
+
+pub fn is_dry_run(app_handle: &tauri::AppHandle) -> bool {
+    app_handle.state::<AutomationState>().dry_run.load(Ordering::SeqCst)
+}
+
+// Tell the backend whether to refuse new jobs and whether to run their scripts; also needed after it restarts
+pub fn sync(app_handle: &tauri::AppHandle) -> Result<(), String> {
+    let params = PauseParams {
+        paused: is_paused(app_handle),
+    };
+    protocol::call::<protocol::SetPaused>(&params)?.into_result()?;
+    let params = DryRunParams {
+        dry_run: is_dry_run(app_handle),
+    };
+    protocol::call::<protocol::SetDryRun>(&params)?.into_result()
+}
+
+pub fn set_paused(app_handle: &tauri::AppHandle, paused: bool) -> Result<(), String> {
+    let was_paused = app_handle.state::<AutomationState>().paused.swap(paused, Ordering::SeqCst);
+    if was_paused == paused {
+        return Ok(());
+    }
+    println!("Automation {}", if paused { "paused" } else { "resumed" });
+
+    if let Err(e) = app_handle.emit("automation-paused-changed", PausedChanged { paused }) {
+        eprintln!("Failed to emit automation-paused-changed: {}", e);
+    }
+    crate::tray::refresh(app_handle);
+    sync(app_handle)
+}
+
+// In a dry run new automations stop once their script is generated, so it can be looked over
+// without anything happening
+pub fn set_dry_run(app_handle: &tauri::AppHandle, dry_run: bool) -> Result<(), String> {
+    let was_dry_run = app_handle.state::<AutomationState>().dry_run.swap(dry_run, Ordering::SeqCst);
+    if was_dry_run == dry_run {
+        return Ok(());
+    }
+    println!("Dry run {}", if dry_run { "on" } else { "off" });
+
+    if let Err(e) = app_handle.emit("automation-dry-run-changed", DryRunChanged { dry_run }) {
+        eprintln!("Failed to emit automation-dry-run-changed: {}", e);
+    }
+    sync(app_handle)
+}
+
+#[tauri::command]
+pub fn get_automation_paused(app_handle: tauri::AppHandle) -> bool {
+    is_paused(&app_handle)
+}
+
+#[tauri::command(async)]
+pub fn set_automation_paused(app_handle: tauri::AppHandle, paused: bool) -> Result<(), String> {
+    set_paused(&app_handle, paused)
+}
+
+#[tauri::command]
+pub fn get_automation_dry_run(app_handle: tauri::AppHandle) -> bool {
+    is_dry_run(&app_handle)
+}
+
+#[tauri::command(async)]
+pub fn set_automation_dry_run(app_handle: tauri::AppHandle, dry_run: bool) -> Result<(), String> {
+    set_dry_run(&app_handle, dry_run)
+}
@@ -0,0 +1,121 @@
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tauri::{Emitter, Manager};
+
+use crate::protocol::{self, CompleteParams, EmbedParams};
+
+// Address of the Python API server started by start_api_server
+pub const BACKEND_URL: &str = "http://localhost:8000";
+pub const BACKEND_PORT: u16 = 8000;
+
+// Address of the main backend: the one the policy fixes, or ours
+pub fn base_url() -> &'static str {
+    crate::policy::get().backend_url.as_deref().unwrap_or(BACKEND_URL)
+}
+
+// Whether the policy points at a shared backend, so none is started here
+pub fn is_remote() -> bool {
+    crate::policy::get().backend_url.is_some()
+}
+
+// Address of a backend started on another port
+pub fn url(port: u16) -> String {
+    format!("http://localhost:{}", port)
+}
+
+// The sources as tauri.conf.json bundles them, relative to the resource directory
+const BUNDLED_SOURCES: &str = "../../src";
+// Where builds before the resource resolver put them
+const LEGACY_SOURCES: &str = "resources/src";
+
+#[derive(Clone, Debug, Serialize)]
+struct ResourceNotFound<'a> {
+    resource: &'a str,
+    probed: Vec<String>,
+}
+
+// Folders a bundle may keep its resources in. The resolver knows the layout it was built for; an
+// AppImage or .deb can still be started in a way it doesn't expect, e.g. from an unpacked AppImage.
+fn resource_roots(app_handle: &tauri::AppHandle) -> Vec<PathBuf> {
+    let mut roots: Vec<PathBuf> = app_handle.path().resource_dir().into_iter().collect();
+    if let Some(exe_dir) = std::env::current_exe().ok().and_then(|exe| exe.parent().map(PathBuf::from)) {
+        roots.push(exe_dir.clone());
+        if cfg!(target_os = "macos") {
+            roots.push(exe_dir.join("..").join("Resources"));
+        }
+        if cfg!(target_os = "linux") {
+            let package = app_handle.package_info().name.clone();
+            if let Some(appdir) = std::env::var_os("APPDIR") {
+                roots.push(PathBuf::from(appdir).join("usr").join("lib").join(&package));
+            }
+            roots.push(exe_dir.join("..").join("lib").join(&package));
+            roots.push(PathBuf::from("/usr/lib").join(&package));
+        }
+    }
+    let mut unique = Vec::new();
+    for root in roots {
+        if !unique.contains(&root) {
+            unique.push(root);
+        }
+    }
+    unique
+}
+
+// Directory holding the backend's Python sources: bundled resources, or src/ in a checkout.
+// Says which paths were tried when there's none, as an event the UI can show and in the log.
+pub fn source_dir(app_handle: &tauri::AppHandle) -> Result<PathBuf, String> {
+    let mut probed = Vec::new();
+    for root in resource_roots(app_handle) {
+        // Mapped the way the resolver does, to the `_up_` folders the bundler writes for `..`
+        probed.push(root.join(tauri::utils::resources::resource_relpath(Path::new(BUNDLED_SOURCES))));
+        probed.push(root.join(LEGACY_SOURCES));
+    }
+    // Development runs start from ui/src-tauri
+    if let Ok(dir) = std::env::current_dir() {
+        probed.push(dir.join("..").join("..").join("src"));
+    }
+    if let Some(dir) = probed.iter().find(|dir| dir.join("run_server.py").exists()) {
+        return Ok(dir.clone());
+    }
+
+    let probed: Vec<String> = probed.iter().map(|dir| dir.display().to_string()).collect();
+    eprintln!("Backend sources not found; looked in:\n  {}", probed.join("\n  "));
+    let event = ResourceNotFound {
+        resource: "backend sources",
+        probed: probed.clone(),
+    };
+    if let Err(e) = app_handle.emit("resource-not-found", event) {
+        eprintln!("Failed to emit resource-not-found: {}", e);
+    }
+    Err(format!("Backend sources not found in any of: {}", probed.join(", ")))
+}
+
+pub fn client(timeout: Duration) -> Result<reqwest::blocking::Client, String> {
+    reqwest::blocking::Client::builder()
+        .timeout(timeout)
+        .build()
+        .map_err(|e| format!("Failed to create HTTP client: {}", e))
+}
+
+// Ask the backend for a plain-text completion of a prompt
+pub fn complete(prompt: &str) -> Result<String, String> {
+    protocol::call::<protocol::Complete>(&CompleteParams {
+        prompt: prompt.to_string(),
+    })
+    .map(|response| response.text)
+}
+
+// Embed a batch of texts, returning one vector per input
+pub fn embed(texts: &[String], task_type: &str) -> Result<Vec<Vec<f32>>, String> {
+    let vectors = protocol::call::<protocol::Embed>(&EmbedParams {
+        texts: texts.to_vec(),
+        task_type: task_type.to_string(),
+    })?
+    .embeddings;
+
+    if vectors.len() != texts.len() {
+        return Err(format!("Expected {} embeddings, got {}", texts.len(), vectors.len()));
+    }
+    Ok(vectors)
+}
@@ -0,0 +1,377 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader, Read};
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+use tauri::{Emitter, Manager};
+
+use crate::notifications::{self, NotificationKind};
+use crate::util::now_secs;
+use crate::{backend, idle, profiles, python};
+
+// Requirements rarely change between releases, so once a day is plenty
+const CHECK_INTERVAL: Duration = Duration::from_secs(24 * 60 * 60);
+
+// Let the backend get started before competing with it for the interpreter's attention
+const FIRST_CHECK_DELAY: Duration = Duration::from_secs(60);
+
+// Compare requirements.txt to what's installed using the same specifier rules pip uses
+const CHECK_SCRIPT: &str = r##"
+import json, sys
+from importlib import metadata
+try:
+    from packaging.requirements import Requirement
+except ImportError:
+    from pip._vendor.packaging.requirements import Requirement
+found = []
+for line in open(sys.argv[1], encoding="utf-8"):
+    line = line.split("#", 1)[0].strip()
+    if not line or line.startswith("-"):
+        continue
+    requirement = Requirement(line)
+    if requirement.marker is not None and not requirement.marker.evaluate():
+        continue
+    try:
+        installed = metadata.version(requirement.name)
+    except metadata.PackageNotFoundError:
+        installed = None
+    found.append({
+        "name": requirement.name,
+        "required": str(requirement.specifier),
+        "installed": installed,
+        "satisfied": installed is not None and requirement.specifier.contains(installed, prereleases=True),
+    })
+print(json.dumps(found))
+"##;
+
+// One line of requirements.txt and how the interpreter the backend runs on measures up to it
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Requirement {
+    pub name: String,
+    // Empty when any version will do
+    pub required: String,
+    pub installed: Option<String>,
+    pub satisfied: bool,
+}
+
+// A required package with a newer release than the one installed
+#[derive(Clone, Debug, Serialize)]
+pub struct OutdatedPackage {
+    pub name: String,
+    pub installed: String,
+    pub latest: String,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct DepsStatus {
+    pub checked_at: u64,
+    pub requirements: Vec<Requirement>,
+    // Empty when PyPI couldn't be reached
+    pub outdated: Vec<OutdatedPackage>,
+}
+
+impl DepsStatus {
+    fn up_to_date(&self) -> bool {
+        self.outdated.is_empty() && self.requirements.iter().all(|r| r.satisfied)
+    }
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct VersionChange {
+    pub name: String,
+    // None when the package was newly installed
+    pub from: Option<String>,
+    pub to: String,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct DepsUpdate {
+    pub changes: Vec<VersionChange>,
+    // One line per change, e.g. "fastapi 0.104.1 → 0.110.0"
+    pub summary: String,
+    pub restarted: bool,
+}
+
+#[derive(Clone, Debug, Serialize)]
+struct DepsProgress {
+    // "collecting", "downloading", "installing" or "done"
+    stage: &'static str,
+    package: Option<String>,
+    line: String,
+}
+
+#[derive(Default)]
+pub struct BackendDepsState {
+    status: Mutex<Option<DepsStatus>>,
+    updating: AtomicBool,
+}
+
+#[derive(Deserialize)]
+struct ListedPackage {
+    name: String,
+    version: String,
+    #[serde(default)]
+    latest_version: Option<String>,
+}
+
+fn requirements_file(app_handle: &tauri::AppHandle) -> Result<PathBuf, String> {
+    let file = backend::source_dir(app_handle)?.join("requirements.txt");
+    if !file.exists() {
+        return Err(format!("Backend requirements not found at {}", file.display()));
+    }
+    Ok(file)
+}
+
+// PyPI treats names case-insensitively and `_`, `-` and `.` alike
+fn normalize(name: &str) -> String {
+    name.to_lowercase().replace(['_', '.'], "-")
+}
+
+fn run_python(app_handle: &tauri::AppHandle, args: &[&str]) -> Result<String, String> {
+    let interpreter = python::resolve(app_handle)?;
+    let output = interpreter
+        .command()
+        .args(args)
+        .output()
+        .map_err(|e| format!("Failed to run {}: {}", interpreter.path.display(), e))?;
+    if !output.status.success() {
+        return Err(String::from_utf8_lossy(&output.stderr).trim().to_string());
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).to_string())
+}
+
+fn list_packages(app_handle: &tauri::AppHandle, outdated: bool) -> Result<Vec<ListedPackage>, String> {
+    let mut args = vec!["-m", "pip", "list", "--format=json", "--disable-pip-version-check"];
+    if outdated {
+        args.push("--outdated");
+    }
+    let listed = run_python(app_handle, &args)?;
+    serde_json::from_str(&listed).map_err(|e| format!("Failed to read pip's package list: {}", e))
+}
+
+fn installed_versions(app_handle: &tauri::AppHandle) -> Result<HashMap<String, (String, String)>, String> {
+    Ok(list_packages(app_handle, false)?
+        .into_iter()
+        .map(|package| (normalize(&package.name), (package.name, package.version)))
+        .collect())
+}
+
+pub fn check(app_handle: &tauri::AppHandle) -> Result<DepsStatus, String> {
+    let requirements_file = requirements_file(app_handle)?;
+    let checked = run_python(app_handle, &["-c", CHECK_SCRIPT, &requirements_file.to_string_lossy()])?;
+    let requirements: Vec<Requirement> =
+        serde_json::from_str(&checked).map_err(|e| format!("Failed to read the requirements check: {}", e))?;
+
+    let required: Vec<String> = requirements.iter().map(|r| normalize(&r.name)).collect();
+    let outdated = match list_packages(app_handle, true) {
+        Ok(packages) => packages
+            .into_iter()
+            .filter(|package| required.contains(&normalize(&package.name)))
+            .filter_map(|package| {
+                Some(OutdatedPackage {
+                    latest: package.latest_version?,
+                    name: package.name,
+                    installed: package.version,
+                })
+            })
+            .collect(),
+        Err(e) => {
+            eprintln!("Failed to look for newer backend packages: {}", e);
+            Vec::new()
+        }
+    };
+
+    let status = DepsStatus {
+        checked_at: now_secs(),
+        requirements,
+        outdated,
+    };
+    *app_handle.state::<BackendDepsState>().status.lock().unwrap() = Some(status.clone());
+    Ok(status)
+}
+
+// What a line of pip's output says it's doing
+fn progress(line: &str) -> DepsProgress {
+    let word_after = |prefix: &str| {
+        line.strip_prefix(prefix)
+            .and_then(|rest| rest.split_whitespace().next())
+            .map(|word| word.to_string())
+    };
+    let (stage, package) = if let Some(package) = word_after("Collecting ") {
+        ("collecting", Some(package))
+    } else if let Some(file) = word_after("Downloading ") {
+        ("downloading", Some(file))
+    } else if line.starts_with("Installing collected packages") {
+        ("installing", None)
+    } else if line.starts_with("Successfully installed") {
+        ("done", None)
+    } else {
+        ("collecting", None)
+    };
+    DepsProgress {
+        stage,
+        package,
+        line: line.to_string(),
+    }
+}
+
+fn install(app_handle: &tauri::AppHandle, requirements_file: &Path) -> Result<(), String> {
+    let mut child = python::resolve(app_handle)?
+        .command()
+        .args(["-m", "pip", "install", "--upgrade", "--progress-bar", "off", "--disable-pip-version-check", "-r"])
+        .arg(requirements_file)
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| format!("Failed to run pip: {}", e))?;
+
+    // Drained on its own thread so a chatty stderr can't stall pip
+    let mut stderr = child.stderr.take();
+    let errors = std::thread::spawn(move || {
+        let mut text = String::new();
+        if let Some(stderr) = stderr.as_mut() {
+            let _ = stderr.read_to_string(&mut text);
+        }
+        text
+    });
+    if let Some(stdout) = child.stdout.take() {
+        for line in BufReader::new(stdout).lines().map_while(Result::ok) {
+            let line = line.trim();
+            if line.is_empty() {
+                continue;
+            }
+            if let Err(e) = app_handle.emit("backend-deps-progress", progress(line)) {
+                eprintln!("Failed to emit backend-deps-progress: {}", e);
+            }
+        }
+    }
+
+    let status = child.wait().map_err(|e| format!("Failed to wait for pip: {}", e))?;
+    let errors = errors.join().unwrap_or_default();
+    if !status.success() {
+        let detail = errors.lines().rev().find(|line| !line.trim().is_empty()).unwrap_or("no details");
+        return Err(format!("pip failed: {}", detail.trim()));
+    }
+    Ok(())
+}
+
+fn changes(before: &HashMap<String, (String, String)>, after: HashMap<String, (String, String)>) -> Vec<VersionChange> {
+    let mut changes: Vec<VersionChange> = after
+        .into_iter()
+        .filter_map(|(key, (name, to))| {
+            let from = before.get(&key).map(|(_, version)| version.clone());
+            if from.as_deref() == Some(to.as_str()) {
+                return None;
+            }
+            Some(VersionChange { name, from, to })
+        })
+        .collect();
+    changes.sort_by_key(|change| normalize(&change.name));
+    changes
+}
+
+fn summarize(changes: &[VersionChange]) -> String {
+    if changes.is_empty() {
+        return "All backend packages were already up to date".to_string();
+    }
+    changes
+        .iter()
+        .map(|change| match &change.from {
+            Some(from) => format!("{} {} → {}", change.name, from, change.to),
+            None => format!("{} {} (new)", change.name, change.to),
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+// Check the backend's packages soon and once a day, telling the UI when they need updating.
+// Each check waits until the user is away, since pip is slow to start.
+pub fn start(app_handle: &tauri::AppHandle) {
+    let app_handle = app_handle.clone();
+    std::thread::spawn(move || {
+        std::thread::sleep(FIRST_CHECK_DELAY);
+        // Only what's new goes to the notification center, not the same updates every day
+        let mut announced: Vec<String> = Vec::new();
+        loop {
+            idle::wait_until_idle(&app_handle);
+            match check(&app_handle) {
+                Ok(status) if !status.up_to_date() => {
+                    if let Err(e) = app_handle.emit("backend-deps-status", &status) {
+                        eprintln!("Failed to emit backend-deps-status: {}", e);
+                    }
+                    let pending: Vec<String> = status
+                        .requirements
+                        .iter()
+                        .filter(|r| !r.satisfied)
+                        .map(|r| r.name.clone())
+                        .chain(status.outdated.iter().map(|p| format!("{} {}", p.name, p.latest)))
+                        .collect();
+                    if pending.iter().any(|package| !announced.contains(package)) {
+                        notifications::push(
+                            &app_handle,
+                            NotificationKind::UpdateAvailable,
+                            "Backend packages need updating",
+                            Some(pending.join(", ")),
+                        );
+                        announced = pending;
+                    }
+                }
+                Ok(_) => {}
+                Err(e) => eprintln!("Failed to check backend packages: {}", e),
+            }
+            std::thread::sleep(CHECK_INTERVAL);
+        }
+    });
+}
+
+// Command for the settings pane; None until the first check has run, unless `refresh` asks for one
+#[tauri::command(async)]
+pub fn get_backend_deps_status(
+    app_handle: tauri::AppHandle,
+    refresh: Option<bool>,
+) -> Result<Option<DepsStatus>, String> {
+    if refresh.unwrap_or(false) {
+        return check(&app_handle).map(Some);
+    }
+    Ok(app_handle.state::<BackendDepsState>().status.lock().unwrap().clone())
+}
+
+// Command to upgrade the backend's packages with pip, reporting each step as `backend-deps-progress`.
+// The backend is restarted afterwards to load what changed unless `restart` is false.
+#[tauri::command(async)]
+pub fn update_backend_deps(app_handle: tauri::AppHandle, restart: Option<bool>) -> Result<DepsUpdate, String> {
+    let state = app_handle.state::<BackendDepsState>();
+    if state.updating.swap(true, Ordering::SeqCst) {
+        return Err("The backend's packages are already being updated".to_string());
+    }
+    let result = requirements_file(&app_handle).and_then(|requirements_file| {
+        let before = installed_versions(&app_handle)?;
+        println!("Updating backend packages from {}", requirements_file.display());
+        install(&app_handle, &requirements_file)?;
+        Ok(changes(&before, installed_versions(&app_handle)?))
+    });
+    state.updating.store(false, Ordering::SeqCst);
+    let changes = result?;
+
+    let restarted = restart.unwrap_or(true) && !changes.is_empty();
+    if restarted {
+        profiles::restart_backend(&app_handle)?;
+        notifications::push(
+            &app_handle,
+            NotificationKind::BackendRestarted,
+            "Backend restarted with updated packages",
+            Some(summarize(&changes)),
+        );
+    }
+    if let Err(e) = check(&app_handle) {
+        eprintln!("Failed to check backend packages after updating: {}", e);
+    }
+    Ok(DepsUpdate {
+        summary: summarize(&changes),
+        changes,
+        restarted,
+    })
+}
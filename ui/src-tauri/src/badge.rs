@@ -0,0 +1,159 @@
+use tauri::Emitter;
+
+// Desktop entry the Unity launcher API identifies us by (the bundler names it after the binary)
+#[cfg(target_os = "linux")]
+const DESKTOP_ID: &str = "krya-ai.desktop";
+
+// 3x5 pixel digits for the Windows overlay icon, one row per entry with the high bit on the left
+#[cfg(target_os = "windows")]
+const DIGITS: [[u8; 5]; 10] = [
+    [0b111, 0b101, 0b101, 0b101, 0b111],
+    [0b010, 0b110, 0b010, 0b010, 0b111],
+    [0b111, 0b001, 0b111, 0b100, 0b111],
+    [0b111, 0b001, 0b111, 0b001, 0b111],
+    [0b101, 0b101, 0b111, 0b001, 0b001],
+    [0b111, 0b100, 0b111, 0b001, 0b111],
+    [0b111, 0b100, 0b111, 0b101, 0b111],
+    [0b111, 0b001, 0b010, 0b010, 0b010],
+    [0b111, 0b101, 0b111, 0b101, 0b111],
+    [0b111, 0b101, 0b111, 0b001, 0b111],
+];
+
+#[cfg(target_os = "macos")]
+fn set_badge(_app_handle: &tauri::AppHandle, count: usize) -> Result<(), String> {
+    use cocoa::appkit::NSApp;
+    use cocoa::base::{id, nil};
+    use cocoa::foundation::NSString;
+    use objc::{msg_send, sel, sel_impl};
+
+    unsafe {
+        let dock_tile: id = msg_send![NSApp(), dockTile];
+        if count == 0 {
+            let _: () = msg_send![dock_tile, setBadgeLabel: nil];
+        } else {
+            let label = NSString::alloc(nil).init_str(&count.to_string());
+            let _: () = msg_send![dock_tile, setBadgeLabel: label];
+            let _: () = msg_send![label, release];
+        }
+    }
+    Ok(())
+}
+
+// A red dot with the count (up to 9) over the taskbar button; the exact count goes in its description
+#[cfg(target_os = "windows")]
+fn set_badge(app_handle: &tauri::AppHandle, count: usize) -> Result<(), String> {
+    use tauri::Manager;
+    use windows::core::{HSTRING, PCWSTR};
+    use windows::Win32::Foundation::HWND;
+    use windows::Win32::Graphics::Gdi::{CreateBitmap, DeleteObject};
+    use windows::Win32::System::Com::{CoCreateInstance, CoInitializeEx, CLSCTX_INPROC_SERVER, COINIT_APARTMENTTHREADED};
+    use windows::Win32::UI::Shell::{ITaskbarList3, TaskbarList};
+    use windows::Win32::UI::WindowsAndMessaging::{CreateIconIndirect, DestroyIcon, HICON, ICONINFO};
+
+    const SIZE: usize = 16;
+
+    let window = match app_handle.get_webview_window("main") {
+        Some(window) => window,
+        None => return Ok(()),
+    };
+    let hwnd = HWND(window.hwnd().map_err(|e| format!("Failed to get the window handle: {}", e))?.0 as isize);
+    let error = |e: windows::core::Error| format!("Failed to update the taskbar badge: {}", e);
+
+    unsafe {
+        // The webview has already set up COM on the main thread, so this only adds a reference
+        let _ = CoInitializeEx(None, COINIT_APARTMENTTHREADED);
+        let taskbar: ITaskbarList3 = CoCreateInstance(&TaskbarList, None, CLSCTX_INPROC_SERVER).map_err(error)?;
+        taskbar.HrInit().map_err(error)?;
+        if count == 0 {
+            return taskbar.SetOverlayIcon(hwnd, HICON(0), PCWSTR::null()).map_err(error);
+        }
+
+        // Premultiplied BGRA: a red disc, and the digit in white scaled up 2x in the middle
+        let mut pixels = vec![0u8; SIZE * SIZE * 4];
+        let center = (SIZE as f64 - 1.0) / 2.0;
+        for y in 0..SIZE {
+            for x in 0..SIZE {
+                let distance = ((x as f64 - center).powi(2) + (y as f64 - center).powi(2)).sqrt();
+                if distance <= SIZE as f64 / 2.0 {
+                    pixels[(y * SIZE + x) * 4..][..4].copy_from_slice(&[0x30, 0x30, 0xe0, 0xff]);
+                }
+            }
+        }
+        if count <= 9 {
+            for (row, bits) in DIGITS[count].iter().enumerate() {
+                for column in 0..3 {
+                    if bits & (0b100 >> column) == 0 {
+                        continue;
+                    }
+                    for (dy, dx) in [(0, 0), (0, 1), (1, 0), (1, 1)] {
+                        let (x, y) = (5 + column * 2 + dx, 3 + row * 2 + dy);
+                        pixels[(y * SIZE + x) * 4..][..4].copy_from_slice(&[0xff, 0xff, 0xff, 0xff]);
+                    }
+                }
+            }
+        }
+
+        let mask_bits = [0u8; SIZE * SIZE / 8];
+        let color = CreateBitmap(SIZE as i32, SIZE as i32, 1, 32, Some(pixels.as_ptr() as *const _));
+        let mask = CreateBitmap(SIZE as i32, SIZE as i32, 1, 1, Some(mask_bits.as_ptr() as *const _));
+        let info = ICONINFO {
+            fIcon: true.into(),
+            xHotspot: 0,
+            yHotspot: 0,
+            hbmMask: mask,
+            hbmColor: color,
+        };
+        let icon = CreateIconIndirect(&info);
+        DeleteObject(color);
+        DeleteObject(mask);
+        let icon = icon.map_err(error)?;
+
+        let description = HSTRING::from(format!("{} new results", count));
+        let result = taskbar.SetOverlayIcon(hwnd, icon, &description).map_err(error);
+        let _ = DestroyIcon(icon);
+        result
+    }
+}
+
+// Through the Unity launcher API, which KDE's task manager and the GNOME docks also read
+#[cfg(target_os = "linux")]
+fn set_badge(_app_handle: &tauri::AppHandle, count: usize) -> Result<(), String> {
+    use gtk::gio;
+    use gtk::glib::{ToVariant, Variant};
+    use std::collections::HashMap;
+
+    let connection = gio::bus_get_sync(gio::BusType::Session, gio::Cancellable::NONE)
+        .map_err(|e| format!("Failed to connect to the session bus: {}", e))?;
+    let mut properties: HashMap<String, Variant> = HashMap::new();
+    properties.insert("count".to_string(), (count as i64).to_variant());
+    properties.insert("count-visible".to_string(), (count > 0).to_variant());
+    let parameters = (format!("application://{}", DESKTOP_ID), properties).to_variant();
+    connection
+        .emit_signal(
+            None,
+            "/com/krya/ai",
+            "com.canonical.Unity.LauncherEntry",
+            "Update",
+            Some(&parameters),
+        )
+        .map_err(|e| format!("Failed to update the launcher badge: {}", e))
+}
+
+#[cfg(not(any(target_os = "macos", target_os = "windows", target_os = "linux")))]
+fn set_badge(_app_handle: &tauri::AppHandle, _count: usize) -> Result<(), String> {
+    Ok(())
+}
+
+// Show how many results are waiting on the dock or taskbar icon, clearing it at zero
+pub fn show(app_handle: &tauri::AppHandle, count: usize) {
+    let handle = app_handle.clone();
+    let result = app_handle.run_on_main_thread(move || {
+        if let Err(e) = set_badge(&handle, count) {
+            eprintln!("{}", e);
+        }
+    });
+    if let Err(e) = result {
+        eprintln!("Failed to update the badge: {}", e);
+    }
+    let _ = app_handle.emit("unread-results-changed", count);
+}
@@ -0,0 +1,335 @@
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::sync::{Arc, Condvar, Mutex};
+use std::time::{Duration, Instant};
+use tauri::Manager;
+
+use crate::protocol::{self, CallError, RunParams};
+use crate::queue::{self, PendingPrompt};
+use crate::{app_profiles, automation, hot_swap, jobs, scopes, settings};
+
+// Long enough to cover reopening a spotlight that was closed by accident
+const TTL: Duration = Duration::from_secs(10 * 60);
+
+const MAX_ENTRIES: usize = 100;
+
+struct Entry {
+    result: String,
+    job_id: Option<String>,
+    stored: Instant,
+}
+
+// A response served from the cache instead of asking the backend again
+#[derive(Clone, Debug, Serialize)]
+pub struct CachedResult {
+    pub result: String,
+    // The job that produced it, for automations
+    pub job_id: Option<String>,
+    pub age_secs: u64,
+}
+
+// A submission that identical ones arriving meanwhile wait on instead of sending their own
+#[derive(Default)]
+struct InFlight {
+    outcome: Mutex<Option<Result<Submission, String>>>,
+    done: Condvar,
+}
+
+// Owned by the first of identical submissions. Dropping it hands the outcome to those waiting,
+// or an error if the submission panicked, so they aren't left waiting forever.
+struct FlightGuard<'a> {
+    cache: &'a ResultCache,
+    key: String,
+    flight: Arc<InFlight>,
+    outcome: Option<Result<Submission, String>>,
+}
+
+impl Drop for FlightGuard<'_> {
+    fn drop(&mut self) {
+        // The locks may be poisoned by the very panic being unwound
+        self.cache
+            .in_flight
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .remove(&self.key);
+        let outcome = self
+            .outcome
+            .take()
+            .unwrap_or_else(|| Err("Submission failed unexpectedly".to_string()));
+        *self.flight.outcome.lock().unwrap_or_else(|e| e.into_inner()) = Some(outcome);
+        self.flight.done.notify_all();
+    }
+}
+
+// Recent backend responses, keyed by a hash of everything that shaped them
+#[derive(Default)]
+pub struct ResultCache {
+    entries: Mutex<HashMap<String, Entry>>,
+    // Submitted jobs whose results should be cached under a key once they complete
+    expected: Mutex<HashMap<String, String>>,
+    in_flight: Mutex<HashMap<String, Arc<InFlight>>>,
+}
+
+// The prompt alone isn't enough: the same words can mean something else with other context,
+// another working directory or another model
+pub fn key(app_handle: &tauri::AppHandle, kind: &str, prompt: &str, context: Option<&str>) -> String {
+    let model = settings::current(app_handle).model;
+    let working_directory = scopes::working_directory(app_handle);
+
+    hash(&[
+        kind,
+        prompt,
+        context.unwrap_or(""),
+        &working_directory.map(|dir| dir.to_string_lossy().to_string()).unwrap_or_default(),
+        &model.provider,
+        &model.name,
+        // A dry run's result is only the script, not what running it did
+        if automation::is_dry_run(app_handle) { "dry run" } else { "" },
+    ])
+}
+
+fn hash(parts: &[&str]) -> String {
+    let mut hasher = Sha256::new();
+    for part in parts {
+        hasher.update(part.as_bytes());
+        // Keeps ("ab", "c") and ("a", "bc") apart
+        hasher.update([0]);
+    }
+    hasher.finalize().iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+impl ResultCache {
+    pub fn get(&self, key: &str) -> Option<CachedResult> {
+        let mut entries = self.entries.lock().unwrap();
+        entries.retain(|_, entry| entry.stored.elapsed() < TTL);
+        entries.get(key).map(|entry| CachedResult {
+            result: entry.result.clone(),
+            job_id: entry.job_id.clone(),
+            age_secs: entry.stored.elapsed().as_secs(),
+        })
+    }
+
+    pub fn insert(&self, key: String, result: String, job_id: Option<String>) {
+        let mut entries = self.entries.lock().unwrap();
+        if entries.len() >= MAX_ENTRIES {
+            let oldest = entries
+                .iter()
+                .min_by_key(|(_, entry)| entry.stored)
+                .map(|(key, _)| key.clone());
+            if let Some(oldest) = oldest {
+                entries.remove(&oldest);
+            }
+        }
+        entries.insert(
+            key,
+            Entry {
+                result,
+                job_id,
+                stored: Instant::now(),
+            },
+        );
+    }
+
+    // Cache a submitted job's result under `key` if it completes
+    pub fn clear(&self) {
+        self.entries.lock().unwrap().clear();
+    }
+
+    pub fn expect_job(&self, job_id: &str, key: String) {
+        self.expected.lock().unwrap().insert(job_id.to_string(), key);
+    }
+
+    // A job started for the same key that hasn't finished yet
+    fn running_job(&self, key: &str) -> Option<String> {
+        self.expected
+            .lock()
+            .unwrap()
+            .iter()
+            .find(|(_, expected)| expected.as_str() == key)
+            .map(|(job_id, _)| job_id.clone())
+    }
+}
+
+// Called by the job tracker when a job finishes; only completed jobs with a result are kept
+pub fn job_finished(app_handle: &tauri::AppHandle, job_id: &str, result: Option<String>) {
+    let cache = app_handle.state::<ResultCache>();
+    let key = cache.expected.lock().unwrap().remove(job_id);
+    if let (Some(key), Some(result)) = (key, result) {
+        cache.insert(key, result, Some(job_id.to_string()));
+    }
+}
+
+#[derive(Clone, Debug, Serialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum Submission {
+    Started { job_id: String },
+    // An identical prompt ran recently; no job was started
+    Cached(CachedResult),
+    // The backend couldn't be reached; the prompt runs once it's back
+    Queued(PendingPrompt),
+}
+
+// The prompt as the backend receives it, with its context block in front
+pub fn full_prompt(prompt: &str, context: Option<&str>) -> String {
+    match context {
+        Some(context) => format!("{}\n\n{}", context, prompt),
+        None => prompt.to_string(),
+    }
+}
+
+// Hand a prompt to the backend and follow its job, caching the result under `key` if it completes
+pub fn start(app_handle: &tauri::AppHandle, params: &RunParams, key: Option<String>) -> Result<String, CallError> {
+    // Treated like a backend that's down, so the prompt is queued until the new one is up
+    if hot_swap::is_draining(app_handle) {
+        return Err(CallError::Unreachable("the backend is restarting to load updated code".to_string()));
+    }
+    let submitted = protocol::try_call::<protocol::RunJob>(params)?;
+    if let Some(key) = key {
+        app_handle.state::<ResultCache>().expect_job(&submitted.job_id, key);
+    }
+    jobs::track(app_handle, &submitted.job_id);
+    Ok(submitted.job_id)
+}
+
+fn submit_once(
+    app_handle: &tauri::AppHandle,
+    prompt: String,
+    context: Option<String>,
+    job_id: Option<String>,
+    force: bool,
+) -> Result<Submission, String> {
+    let key = match job_id {
+        Some(_) => None,
+        None => Some(key(app_handle, "jobs.run", &prompt, context.as_deref())),
+    };
+    if let (Some(key), false) = (&key, force) {
+        let cache = app_handle.state::<ResultCache>();
+        if let Some(cached) = cache.get(key) {
+            println!("Answering from the result cache ({}s old)", cached.age_secs);
+            return Ok(Submission::Cached(cached));
+        }
+        // Still running from an earlier submission, so follow that job rather than start another
+        if let Some(job_id) = cache.running_job(key) {
+            println!("Prompt is already running as job {}", job_id);
+            return Ok(Submission::Started { job_id });
+        }
+    }
+
+    let attached = job_id.is_some();
+    let params = RunParams::new(full_prompt(&prompt, context.as_deref()), job_id);
+    match start(app_handle, &params, key) {
+        Ok(job_id) => Ok(Submission::Started { job_id }),
+        // Attachments live in the backend, so a prompt that has them can't wait for the next one
+        Err(e @ CallError::Unreachable(_)) | Err(e @ CallError::Offline) if !attached => {
+            Ok(Submission::Queued(queue::enqueue(app_handle, prompt, context, e.to_string())))
+        }
+        Err(e) => Err(e.to_string()),
+    }
+}
+
+// Run a prompt with its context block. Unless `force`d, an identical one that ran recently is answered
+// from the cache, which callers only want for prompts that look something up: an automation that
+// moves files or sends a message has to run again when asked to. Prompts with attachments (a preset
+// job id) always run.
+// Identical submissions that arrive while one is on its way (a double Enter, a re-render) share its outcome,
+// so an automation never runs twice by accident
+pub fn submit(
+    app_handle: &tauri::AppHandle,
+    prompt: String,
+    context: Option<String>,
+    job_id: Option<String>,
+    force: bool,
+) -> Result<Submission, String> {
+    let kind = match &job_id {
+        Some(job_id) => format!("jobs.run {}", job_id),
+        None => "jobs.run".to_string(),
+    };
+    let flight_key = key(app_handle, &kind, &prompt, context.as_deref());
+    let cache = app_handle.state::<ResultCache>();
+    let (flight, first) = {
+        let mut in_flight = cache.in_flight.lock().unwrap();
+        match in_flight.get(&flight_key) {
+            Some(flight) => (flight.clone(), false),
+            None => {
+                let flight = Arc::new(InFlight::default());
+                in_flight.insert(flight_key.clone(), flight.clone());
+                (flight, true)
+            }
+        }
+    };
+
+    if !first {
+        println!("Coalescing a duplicate submission");
+        let mut outcome = flight.outcome.lock().unwrap();
+        while outcome.is_none() {
+            outcome = flight.done.wait(outcome).unwrap();
+        }
+        return outcome.clone().unwrap_or_else(|| Err("Submission was lost".to_string()));
+    }
+
+    let mut guard = FlightGuard {
+        cache: &cache,
+        key: flight_key,
+        flight,
+        outcome: None,
+    };
+    let outcome = submit_once(app_handle, prompt, context, job_id, force);
+    guard.outcome = Some(outcome.clone());
+    outcome
+}
+
+#[tauri::command(async)]
+pub fn submit_prompt(
+    app_handle: tauri::AppHandle,
+    prompt: String,
+    context: Option<String>,
+    job_id: Option<String>,
+    force: Option<bool>,
+) -> Result<Submission, String> {
+    let prompt = app_profiles::apply_template(&app_handle, prompt);
+    // Automations have side effects, so a cached result is only served when the caller asks for one
+    submit(&app_handle, prompt, context, job_id, force.unwrap_or(true))
+}
+
+#[tauri::command]
+pub fn clear_result_cache(cache: tauri::State<ResultCache>) {
+    cache.clear();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keys_are_stable_and_keep_parts_apart() {
+        let key = hash(&["jobs.run", "Sort my downloads", "", "/home/me", "openai", "gpt-4o"]);
+        assert_eq!(key, hash(&["jobs.run", "Sort my downloads", "", "/home/me", "openai", "gpt-4o"]));
+        assert_eq!(key.len(), 64);
+        assert_ne!(key, hash(&["jobs.run", "Sort my downloads", "", "/home/me", "openai", "gpt-4o-mini"]));
+        assert_ne!(hash(&["ab", "c"]), hash(&["a", "bc"]));
+    }
+
+    #[test]
+    fn entries_expire_after_the_ttl() {
+        let cache = ResultCache::default();
+        cache.insert("fresh".to_string(), "Done".to_string(), Some("job-1".to_string()));
+        let cached = cache.get("fresh").expect("a fresh entry is served");
+        assert_eq!(cached.result, "Done");
+        assert_eq!(cached.job_id.as_deref(), Some("job-1"));
+
+        // Only on a machine that has been up longer than the TTL can an entry be that old
+        if let Some(stored) = Instant::now().checked_sub(TTL) {
+            cache.entries.lock().unwrap().insert(
+                "stale".to_string(),
+                Entry {
+                    result: "Done".to_string(),
+                    job_id: None,
+                    stored,
+                },
+            );
+            assert!(cache.get("stale").is_none());
+            assert!(!cache.entries.lock().unwrap().contains_key("stale"));
+        }
+    }
+}
@@ -0,0 +1,596 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use crate::util::now_secs;
+use crate::{storage, tls};
+
+const RATES_FILE: &str = "currency_rates.json";
+const RATES_URL: &str = "https://open.er-api.com/v6/latest/USD";
+
+// Exchange rates are refetched when older than this
+const RATES_MAX_AGE: Duration = Duration::from_secs(12 * 60 * 60);
+
+#[derive(Clone, Debug, Serialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum InlineKind {
+    Math,
+    Unit,
+    Currency,
+}
+
+// An instant answer shown in the spotlight instead of sending the query to the backend
+#[derive(Clone, Debug, Serialize)]
+pub struct InlineResult {
+    pub kind: InlineKind,
+    pub value: f64,
+    pub display: String,
+}
+
+// Exchange rates relative to USD, cached in the app data directory
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CurrencyRates {
+    pub fetched_at: u64,
+    pub rates: HashMap<String, f64>,
+}
+
+pub struct CalcState {
+    rates: Arc<Mutex<CurrencyRates>>,
+    refreshing: Arc<AtomicBool>,
+}
+
+impl CalcState {
+    pub fn load(app_handle: &tauri::AppHandle) -> Self {
+        CalcState {
+            rates: Arc::new(Mutex::new(storage::load_json(app_handle, RATES_FILE))),
+            refreshing: Arc::new(AtomicBool::new(false)),
+        }
+    }
+
+    // Fetch fresh exchange rates in the background if the cached ones are stale
+    pub fn refresh_rates_if_stale(&self, app_handle: &tauri::AppHandle) {
+        let age = now_secs().saturating_sub(self.rates.lock().unwrap().fetched_at);
+        if age < RATES_MAX_AGE.as_secs() || self.refreshing.swap(true, Ordering::SeqCst) {
+            return;
+        }
+
+        let rates = self.rates.clone();
+        let refreshing = self.refreshing.clone();
+        let app_handle = app_handle.clone();
+        std::thread::spawn(move || {
+            match fetch_rates() {
+                Ok(fetched) => {
+                    if let Err(e) = storage::save_json(&app_handle, RATES_FILE, &fetched, storage::Data::Config) {
+                        eprintln!("{}", e);
+                    }
+                    *rates.lock().unwrap() = fetched;
+                }
+                Err(e) => eprintln!("Failed to update currency rates: {}", e),
+            }
+            refreshing.store(false, Ordering::SeqCst);
+        });
+    }
+}
+
+fn fetch_rates() -> Result<CurrencyRates, String> {
+    #[derive(Deserialize)]
+    struct RatesResponse {
+        rates: HashMap<String, f64>,
+    }
+
+    let response: RatesResponse = tls::client_builder()
+        .timeout(Duration::from_secs(10))
+        .build()
+        .map_err(|e| format!("Failed to create HTTP client: {}", e))?
+        .get(RATES_URL)
+        .send()
+        .and_then(|r| r.error_for_status())
+        .map_err(|e| format!("Failed to fetch rates: {}", e))?
+        .json()
+        .map_err(|e| format!("Invalid rates response: {}", e))?;
+
+    Ok(CurrencyRates {
+        fetched_at: now_secs(),
+        rates: response.rates,
+    })
+}
+
+// Arithmetic expression parser
+
+#[derive(Clone, Debug, PartialEq)]
+enum Token {
+    Number(f64),
+    Ident(String),
+    Op(char),
+    LParen,
+    RParen,
+    Comma,
+}
+
+fn tokenize(input: &str) -> Result<Vec<Token>, String> {
+    let chars: Vec<char> = input.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+
+    while i < chars.len() {
+        let c = chars[i];
+        if c.is_whitespace() {
+            i += 1;
+        } else if c.is_ascii_digit() || c == '.' {
+            let start = i;
+            while i < chars.len() && (chars[i].is_ascii_digit() || chars[i] == '.' || chars[i] == '_') {
+                i += 1;
+            }
+            // Scientific notation, e.g. 1.5e3
+            if i + 1 < chars.len()
+                && (chars[i] == 'e' || chars[i] == 'E')
+                && (chars[i + 1].is_ascii_digit() || (matches!(chars[i + 1], '+' | '-') && i + 2 < chars.len() && chars[i + 2].is_ascii_digit()))
+            {
+                i += 2;
+                while i < chars.len() && chars[i].is_ascii_digit() {
+                    i += 1;
+                }
+            }
+            let text: String = chars[start..i].iter().filter(|c| **c != '_').collect();
+            let number = text.parse().map_err(|_| format!("Not a number: {}", text))?;
+            tokens.push(Token::Number(number));
+        } else if c.is_alphabetic() {
+            let start = i;
+            while i < chars.len() && chars[i].is_alphanumeric() {
+                i += 1;
+            }
+            tokens.push(Token::Ident(chars[start..i].iter().collect::<String>().to_lowercase()));
+        } else {
+            tokens.push(match c {
+                '+' | '-' | '*' | '/' | '^' | '%' => Token::Op(c),
+                '×' => Token::Op('*'),
+                '÷' => Token::Op('/'),
+                '(' => Token::LParen,
+                ')' => Token::RParen,
+                ',' => Token::Comma,
+                _ => return Err(format!("Unexpected character: {}", c)),
+            });
+            i += 1;
+        }
+    }
+
+    Ok(tokens)
+}
+
+// Deeper than anyone types; each level is a few stack frames, so a pasted wall of
+// parentheses gets an error instead of overflowing the stack
+const MAX_DEPTH: usize = 64;
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+    depth: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn next(&mut self) -> Result<Token, String> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        token.ok_or_else(|| "Unexpected end of expression".to_string())
+    }
+
+    fn expect(&mut self, token: Token) -> Result<(), String> {
+        let next = self.next()?;
+        if next == token {
+            Ok(())
+        } else {
+            Err(format!("Expected {:?}, found {:?}", token, next))
+        }
+    }
+
+    // expr := term (('+' | '-') term)*
+    fn expr(&mut self) -> Result<f64, String> {
+        let mut value = self.term()?;
+        while let Some(Token::Op(op @ ('+' | '-'))) = self.peek().cloned() {
+            self.pos += 1;
+            let rhs = self.term()?;
+            value = if op == '+' { value + rhs } else { value - rhs };
+        }
+        Ok(value)
+    }
+
+    // term := unary (('*' | '/' | '%') unary)*
+    fn term(&mut self) -> Result<f64, String> {
+        let mut value = self.unary()?;
+        while let Some(Token::Op(op @ ('*' | '/' | '%'))) = self.peek().cloned() {
+            self.pos += 1;
+            let rhs = self.unary()?;
+            if op != '*' && rhs == 0.0 {
+                return Err("Division by zero".to_string());
+            }
+            value = match op {
+                '*' => value * rhs,
+                '/' => value / rhs,
+                _ => value % rhs,
+            };
+        }
+        Ok(value)
+    }
+
+    // unary := '-' unary | power. Every level of nesting comes through here, so it's where depth is counted.
+    fn unary(&mut self) -> Result<f64, String> {
+        if self.depth >= MAX_DEPTH {
+            return Err("Expression is nested too deeply".to_string());
+        }
+        self.depth += 1;
+        let value = match self.peek() {
+            Some(Token::Op('-')) => {
+                self.pos += 1;
+                self.unary().map(|value| -value)
+            }
+            Some(Token::Op('+')) => {
+                self.pos += 1;
+                self.unary()
+            }
+            _ => self.power(),
+        };
+        self.depth -= 1;
+        value
+    }
+
+    // power := primary ('^' unary)?, right-associative
+    fn power(&mut self) -> Result<f64, String> {
+        let base = self.primary()?;
+        if self.peek() == Some(&Token::Op('^')) {
+            self.pos += 1;
+            return Ok(base.powf(self.unary()?));
+        }
+        Ok(base)
+    }
+
+    fn primary(&mut self) -> Result<f64, String> {
+        match self.next()? {
+            Token::Number(n) => Ok(n),
+            Token::LParen => {
+                let value = self.expr()?;
+                self.expect(Token::RParen)?;
+                Ok(value)
+            }
+            Token::Ident(name) => {
+                if self.peek() == Some(&Token::LParen) {
+                    self.pos += 1;
+                    let mut args = vec![self.expr()?];
+                    while self.peek() == Some(&Token::Comma) {
+                        self.pos += 1;
+                        args.push(self.expr()?);
+                    }
+                    self.expect(Token::RParen)?;
+                    call_function(&name, &args).ok_or_else(|| format!("Unknown function: {}", name))
+                } else {
+                    match name.as_str() {
+                        "pi" => Ok(std::f64::consts::PI),
+                        "e" => Ok(std::f64::consts::E),
+                        "tau" => Ok(std::f64::consts::TAU),
+                        _ => Err(format!("Unknown name: {}", name)),
+                    }
+                }
+            }
+            token => Err(format!("Unexpected {:?}", token)),
+        }
+    }
+}
+
+fn call_function(name: &str, args: &[f64]) -> Option<f64> {
+    let value = match (name, args) {
+        ("sqrt", [x]) => x.sqrt(),
+        ("cbrt", [x]) => x.cbrt(),
+        ("abs", [x]) => x.abs(),
+        ("sin", [x]) => x.sin(),
+        ("cos", [x]) => x.cos(),
+        ("tan", [x]) => x.tan(),
+        ("asin", [x]) => x.asin(),
+        ("acos", [x]) => x.acos(),
+        ("atan", [x]) => x.atan(),
+        ("ln", [x]) => x.ln(),
+        ("log", [x]) => x.log10(),
+        ("log", [x, base]) => x.log(*base),
+        ("exp", [x]) => x.exp(),
+        ("round", [x]) => x.round(),
+        ("floor", [x]) => x.floor(),
+        ("ceil", [x]) => x.ceil(),
+        ("min", [a, b]) => a.min(*b),
+        ("max", [a, b]) => a.max(*b),
+        _ => return None,
+    };
+    Some(value)
+}
+
+fn evaluate_expression(input: &str) -> Result<f64, String> {
+    let mut parser = Parser {
+        tokens: tokenize(input)?,
+        pos: 0,
+        depth: 0,
+    };
+    let value = parser.expr()?;
+    if parser.pos != parser.tokens.len() {
+        return Err(format!("Unexpected {:?}", parser.tokens[parser.pos]));
+    }
+    if !value.is_finite() {
+        return Err("Result is not a finite number".to_string());
+    }
+    Ok(value)
+}
+
+// Units
+
+#[derive(Clone, Copy, PartialEq)]
+enum Dimension {
+    Length,
+    Mass,
+    Volume,
+    Time,
+    Data,
+    Speed,
+    Area,
+    Temperature,
+}
+
+// (aliases, dimension, factor to the dimension's base unit)
+const UNITS: &[(&[&str], Dimension, f64)] = &[
+    (&["mm", "millimeter", "millimeters", "millimetre", "millimetres"], Dimension::Length, 0.001),
+    (&["cm", "centimeter", "centimeters", "centimetre", "centimetres"], Dimension::Length, 0.01),
+    (&["m", "meter", "meters", "metre", "metres"], Dimension::Length, 1.0),
+    (&["km", "kilometer", "kilometers", "kilometre", "kilometres"], Dimension::Length, 1000.0),
+    (&["in", "inch", "inches"], Dimension::Length, 0.0254),
+    (&["ft", "foot", "feet"], Dimension::Length, 0.3048),
+    (&["yd", "yard", "yards"], Dimension::Length, 0.9144),
+    (&["mi", "mile", "miles"], Dimension::Length, 1609.344),
+    (&["nmi", "nauticalmile"], Dimension::Length, 1852.0),
+    (&["mg", "milligram", "milligrams"], Dimension::Mass, 0.001),
+    (&["g", "gram", "grams"], Dimension::Mass, 1.0),
+    (&["kg", "kilogram", "kilograms", "kilo", "kilos"], Dimension::Mass, 1000.0),
+    (&["t", "tonne", "tonnes"], Dimension::Mass, 1_000_000.0),
+    (&["oz", "ounce", "ounces"], Dimension::Mass, 28.349523125),
+    (&["lb", "lbs", "pound", "pounds"], Dimension::Mass, 453.59237),
+    (&["st", "stone", "stones"], Dimension::Mass, 6350.29318),
+    (&["ml", "milliliter", "milliliters", "millilitre", "millilitres"], Dimension::Volume, 0.001),
+    (&["l", "liter", "liters", "litre", "litres"], Dimension::Volume, 1.0),
+    (&["tsp", "teaspoon", "teaspoons"], Dimension::Volume, 0.00492892159375),
+    (&["tbsp", "tablespoon", "tablespoons"], Dimension::Volume, 0.01478676478125),
+    (&["floz"], Dimension::Volume, 0.0295735295625),
+    (&["cup", "cups"], Dimension::Volume, 0.2365882365),
+    (&["pt", "pint", "pints"], Dimension::Volume, 0.473176473),
+    (&["qt", "quart", "quarts"], Dimension::Volume, 0.946352946),
+    (&["gal", "gallon", "gallons"], Dimension::Volume, 3.785411784),
+    (&["ms", "millisecond", "milliseconds"], Dimension::Time, 0.001),
+    (&["s", "sec", "secs", "second", "seconds"], Dimension::Time, 1.0),
+    (&["min", "mins", "minute", "minutes"], Dimension::Time, 60.0),
+    (&["h", "hr", "hrs", "hour", "hours"], Dimension::Time, 3600.0),
+    (&["d", "day", "days"], Dimension::Time, 86400.0),
+    (&["wk", "week", "weeks"], Dimension::Time, 604800.0),
+    (&["yr", "year", "years"], Dimension::Time, 31_557_600.0),
+    (&["b", "byte", "bytes"], Dimension::Data, 1.0),
+    (&["kb", "kilobyte", "kilobytes"], Dimension::Data, 1e3),
+    (&["mb", "megabyte", "megabytes"], Dimension::Data, 1e6),
+    (&["gb", "gigabyte", "gigabytes"], Dimension::Data, 1e9),
+    (&["tb", "terabyte", "terabytes"], Dimension::Data, 1e12),
+    (&["kib", "kibibyte", "kibibytes"], Dimension::Data, 1024.0),
+    (&["mib", "mebibyte", "mebibytes"], Dimension::Data, 1_048_576.0),
+    (&["gib", "gibibyte", "gibibytes"], Dimension::Data, 1_073_741_824.0),
+    (&["tib", "tebibyte", "tebibytes"], Dimension::Data, 1_099_511_627_776.0),
+    (&["mps"], Dimension::Speed, 1.0),
+    (&["kph", "kmh", "kmph"], Dimension::Speed, 1000.0 / 3600.0),
+    (&["mph"], Dimension::Speed, 0.44704),
+    (&["knot", "knots", "kn"], Dimension::Speed, 0.514444),
+    (&["sqm", "m2"], Dimension::Area, 1.0),
+    (&["sqkm", "km2"], Dimension::Area, 1e6),
+    (&["sqft", "ft2"], Dimension::Area, 0.09290304),
+    (&["acre", "acres"], Dimension::Area, 4046.8564224),
+    (&["ha", "hectare", "hectares"], Dimension::Area, 10_000.0),
+    (&["c", "celsius"], Dimension::Temperature, 0.0),
+    (&["f", "fahrenheit"], Dimension::Temperature, 0.0),
+    (&["k", "kelvin"], Dimension::Temperature, 0.0),
+];
+
+fn find_unit(name: &str) -> Option<(&'static str, Dimension, f64)> {
+    let name = name.trim().trim_start_matches('°').to_lowercase();
+    UNITS
+        .iter()
+        .find(|(aliases, _, _)| aliases.contains(&name.as_str()))
+        .map(|(aliases, dimension, factor)| (aliases[0], *dimension, *factor))
+}
+
+fn to_kelvin(value: f64, unit: &str) -> f64 {
+    match unit {
+        "c" => value + 273.15,
+        "f" => (value - 32.0) * 5.0 / 9.0 + 273.15,
+        _ => value,
+    }
+}
+
+fn from_kelvin(value: f64, unit: &str) -> f64 {
+    match unit {
+        "c" => value - 273.15,
+        "f" => (value - 273.15) * 9.0 / 5.0 + 32.0,
+        _ => value,
+    }
+}
+
+fn display_unit(unit: &str) -> String {
+    match unit {
+        "c" => "°C".to_string(),
+        "f" => "°F".to_string(),
+        "k" => "K".to_string(),
+        _ => unit.to_string(),
+    }
+}
+
+// Currency symbols people commonly type instead of ISO codes
+fn currency_code(name: &str) -> String {
+    match name.trim() {
+        "$" => "USD".to_string(),
+        "€" => "EUR".to_string(),
+        "£" => "GBP".to_string(),
+        "¥" => "JPY".to_string(),
+        "₹" => "INR".to_string(),
+        other => other.to_uppercase(),
+    }
+}
+
+// Split "<amount> <unit> to <unit>" into its parts
+fn split_conversion(query: &str) -> Option<(String, String, String)> {
+    let lower = query.to_ascii_lowercase();
+    let (left, target) = [" to ", " in ", " as ", " -> ", " => "]
+        .iter()
+        .filter_map(|sep| lower.rfind(sep).map(|i| (i, sep.len())))
+        .max_by_key(|(i, _)| *i)
+        .map(|(i, len)| (&query[..i], &query[i + len..]))?;
+
+    let left = left.trim();
+    // Currency symbols can come first ("$20"), units always follow the amount
+    for symbol in ["$", "€", "£", "¥", "₹"] {
+        if let Some(amount) = left.strip_prefix(symbol) {
+            return Some((amount.trim().to_string(), symbol.to_string(), target.trim().to_string()));
+        }
+    }
+
+    let unit_start = left
+        .char_indices()
+        .rev()
+        .take_while(|(_, c)| c.is_alphabetic() || *c == '°' || *c == '$' || *c == '€' || *c == '£')
+        .last()
+        .map(|(i, _)| i)?;
+    let amount = left[..unit_start].trim();
+    let unit = left[unit_start..].trim();
+    let amount = if amount.is_empty() { "1" } else { amount };
+
+    Some((amount.to_string(), unit.to_string(), target.trim().to_string()))
+}
+
+fn convert(query: &str, rates: &CurrencyRates) -> Option<InlineResult> {
+    let (amount, from, to) = split_conversion(query)?;
+    let amount = evaluate_expression(&amount).ok()?;
+
+    if let (Some((from_name, from_dim, from_factor)), Some((to_name, to_dim, to_factor))) =
+        (find_unit(&from), find_unit(&to))
+    {
+        if from_dim != to_dim {
+            return None;
+        }
+        let value = if from_dim == Dimension::Temperature {
+            from_kelvin(to_kelvin(amount, from_name), to_name)
+        } else {
+            amount * from_factor / to_factor
+        };
+        return Some(InlineResult {
+            kind: InlineKind::Unit,
+            value,
+            display: format!("{} {}", format_number(value), display_unit(to_name)),
+        });
+    }
+
+    let (from, to) = (currency_code(&from), currency_code(&to));
+    let from_rate = rates.rates.get(&from)?;
+    let to_rate = rates.rates.get(&to)?;
+    let value = amount / from_rate * to_rate;
+    Some(InlineResult {
+        kind: InlineKind::Currency,
+        value,
+        display: format!("{:.2} {}", value, to),
+    })
+}
+
+// Format a result without float noise (0.30000000000000004 -> 0.3)
+fn format_number(value: f64) -> String {
+    if value.fract() == 0.0 && value.abs() < 1e15 {
+        return format!("{}", value as i64);
+    }
+    if value.abs() >= 1e15 || value.abs() < 1e-6 {
+        return format!("{:e}", value);
+    }
+    let formatted = format!("{:.6}", value);
+    formatted.trim_end_matches('0').trim_end_matches('.').to_string()
+}
+
+pub fn evaluate(query: &str, rates: &CurrencyRates) -> Option<InlineResult> {
+    let query = query.trim().trim_start_matches('=').trim();
+    if query.is_empty() {
+        return None;
+    }
+
+    if let Some(result) = convert(query, rates) {
+        return Some(result);
+    }
+
+    // A bare number isn't worth showing as an answer
+    let tokens = tokenize(query).ok()?;
+    if tokens.len() < 2 {
+        return None;
+    }
+
+    evaluate_expression(query).ok().map(|value| InlineResult {
+        kind: InlineKind::Math,
+        value,
+        display: format_number(value),
+    })
+}
+
+// Command to resolve arithmetic and conversions locally, returning None for anything else
+#[tauri::command]
+pub fn evaluate_inline(
+    app_handle: tauri::AppHandle,
+    state: tauri::State<CalcState>,
+    query: String,
+) -> Option<InlineResult> {
+    state.refresh_rates_if_stale(&app_handle);
+    let rates = state.rates.lock().unwrap();
+    evaluate(&query, &rates)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn precedence() {
+        assert_eq!(evaluate_expression("2 + 3 * 4"), Ok(14.0));
+        assert_eq!(evaluate_expression("(2 + 3) * 4"), Ok(20.0));
+        assert_eq!(evaluate_expression("10 - 4 - 3"), Ok(3.0));
+        assert_eq!(evaluate_expression("2 ^ 3 ^ 2"), Ok(512.0));
+        assert_eq!(evaluate_expression("7 % 4 * 2"), Ok(6.0));
+    }
+
+    #[test]
+    fn unary_minus() {
+        assert_eq!(evaluate_expression("-3 + 5"), Ok(2.0));
+        assert_eq!(evaluate_expression("--3"), Ok(3.0));
+        assert_eq!(evaluate_expression("2 * -3"), Ok(-6.0));
+        assert_eq!(evaluate_expression("-2 ^ 2"), Ok(-4.0));
+    }
+
+    #[test]
+    fn division_by_zero() {
+        assert!(evaluate_expression("1 / 0").is_err());
+        assert!(evaluate_expression("5 % (2 - 2)").is_err());
+    }
+
+    #[test]
+    fn malformed_input() {
+        for input in ["", "1 +", "(1 + 2", "1 + 2)", "2 3", "1 $ 2", "foo(1)", "sqrt(1, 2)", "1..2"] {
+            assert!(evaluate_expression(input).is_err(), "{:?} should not evaluate", input);
+        }
+    }
+
+    #[test]
+    fn depth_limit() {
+        let nested = |depth: usize| format!("{}1{}", "(".repeat(depth), ")".repeat(depth));
+        assert_eq!(evaluate_expression(&nested(MAX_DEPTH - 1)), Ok(1.0));
+        assert_eq!(
+            evaluate_expression(&nested(100_000)),
+            Err("Expression is nested too deeply".to_string())
+        );
+        assert!(evaluate_expression(&"-".repeat(100_000)).is_err());
+    }
+}
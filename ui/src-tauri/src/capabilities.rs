@@ -0,0 +1,78 @@
+use std::sync::Mutex;
+use tauri::{Emitter, Manager};
+
+use crate::protocol::{self, Capabilities, NoParams};
+use crate::tray;
+
+// Capabilities that gate native features; vision and voice only gate frontend inputs
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Capability {
+    CodeExec,
+    Screenshot,
+}
+
+impl Capability {
+    fn description(&self) -> &'static str {
+        match self {
+            Capability::CodeExec => "Running automations",
+            Capability::Screenshot => "Screen capture",
+        }
+    }
+}
+
+// What the running backend reported; None until it has answered
+#[derive(Default)]
+pub struct CapabilitiesState(pub Mutex<Option<Capabilities>>);
+
+// Features count as available until the backend says otherwise, so nothing is hidden while it starts
+pub fn is_available(app_handle: &tauri::AppHandle, capability: Capability) -> bool {
+    let state = app_handle.state::<CapabilitiesState>();
+    let current = state.0.lock().unwrap();
+    match current.as_ref() {
+        Some(capabilities) => match capability {
+            Capability::CodeExec => capabilities.code_exec,
+            Capability::Screenshot => capabilities.screenshot,
+        },
+        None => true,
+    }
+}
+
+pub fn require(app_handle: &tauri::AppHandle, capability: Capability) -> Result<(), String> {
+    if is_available(app_handle, capability) {
+        Ok(())
+    } else {
+        Err(format!("{} is not available with the current backend", capability.description()))
+    }
+}
+
+// Ask the backend what it supports and tell the frontend, which hides unsupported inputs
+pub fn refresh(app_handle: &tauri::AppHandle) -> Result<Capabilities, String> {
+    let capabilities = protocol::call::<protocol::GetCapabilities>(&NoParams {})?;
+    *app_handle.state::<CapabilitiesState>().0.lock().unwrap() = Some(capabilities.clone());
+
+    // Quick actions and pinned prompts disappear from the tray when automations can't run
+    tray::refresh(app_handle);
+    if let Err(e) = app_handle.emit("capabilities", &capabilities) {
+        eprintln!("Failed to emit capabilities: {}", e);
+    }
+    Ok(capabilities)
+}
+
+pub fn refresh_in_background(app_handle: &tauri::AppHandle) {
+    let app_handle = app_handle.clone();
+    std::thread::spawn(move || {
+        if let Err(e) = refresh(&app_handle) {
+            eprintln!("Failed to fetch backend capabilities: {}", e);
+        }
+    });
+}
+
+// Command for the frontend; `refresh` asks the backend again instead of using the startup answer
+#[tauri::command(async)]
+pub fn get_capabilities(app_handle: tauri::AppHandle, refresh: Option<bool>) -> Result<Capabilities, String> {
+    let cached = app_handle.state::<CapabilitiesState>().0.lock().unwrap().clone();
+    match cached {
+        Some(capabilities) if !refresh.unwrap_or(false) => Ok(capabilities),
+        _ => self::refresh(&app_handle),
+    }
+}
@@ -0,0 +1,217 @@
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tauri::{Emitter, Manager, WebviewWindow};
+use tauri_plugin_notification::NotificationExt;
+
+use crate::{privacy, quick_actions, quiet, settings};
+
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+// Past this a copy is a document, not something to ask about
+const MAX_TEXT_LEN: usize = 20_000;
+
+// How long after the suggestion the next spotlight opens pre-filled
+const SUGGESTION_TTL: Duration = Duration::from_secs(120);
+
+// How much of the copied text the notification shows
+const PREVIEW_LEN: usize = 80;
+
+const URL_PATTERN: &str = r"^https?://\S+$";
+// UPS, USPS and FedEx numbers
+const TRACKING_PATTERN: &str = r"\b(1Z[0-9A-Z]{16}|9[2-5]\d{20}|\d{12})\b";
+// Python, JavaScript/Java and Rust
+const STACK_TRACE_PATTERN: &str =
+    r#"(?m)Traceback \(most recent call last\)|^\s+File ".+", line \d+|^\s+at \S+.*:\d+|panicked at"#;
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum ClipboardPattern {
+    Url,
+    TrackingNumber,
+    StackTrace,
+    Custom { regex: String },
+}
+
+impl ClipboardPattern {
+    fn regex(&self) -> Result<Regex, String> {
+        let pattern = match self {
+            ClipboardPattern::Url => URL_PATTERN,
+            ClipboardPattern::TrackingNumber => TRACKING_PATTERN,
+            ClipboardPattern::StackTrace => STACK_TRACE_PATTERN,
+            ClipboardPattern::Custom { regex } => regex,
+        };
+        Regex::new(pattern).map_err(|e| format!("Invalid clipboard pattern {}: {}", pattern, e))
+    }
+}
+
+// When copied text matches, suggest asking about it; `prompt` may use {{clipboard}}
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ClipboardRule {
+    #[serde(flatten)]
+    pub pattern: ClipboardPattern,
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+    pub suggestion: String,
+    pub prompt: String,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+pub fn default_rules() -> Vec<ClipboardRule> {
+    let rule = |pattern, suggestion: &str, prompt: &str| ClipboardRule {
+        pattern,
+        enabled: true,
+        suggestion: suggestion.to_string(),
+        prompt: prompt.to_string(),
+    };
+    vec![
+        rule(
+            ClipboardPattern::StackTrace,
+            "Ask Krya about this error?",
+            "Explain this error and how to fix it:\n{{clipboard}}",
+        ),
+        rule(ClipboardPattern::Url, "Summarize this link?", "Summarize {{clipboard}}"),
+        rule(
+            ClipboardPattern::TrackingNumber,
+            "Track this package?",
+            "Open the tracking page for package {{clipboard}}",
+        ),
+    ]
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct Suggestion {
+    pub suggestion: String,
+    pub prompt: String,
+}
+
+#[derive(Default)]
+pub struct ClipboardState {
+    pending: Mutex<Option<(Suggestion, Instant)>>,
+}
+
+fn preview(text: &str) -> String {
+    let line = text.lines().find(|line| !line.trim().is_empty()).unwrap_or("").trim();
+    if line.chars().count() > PREVIEW_LEN {
+        format!("{}…", line.chars().take(PREVIEW_LEN).collect::<String>())
+    } else {
+        line.to_string()
+    }
+}
+
+// The first enabled rule the copied text matches, as a suggestion
+fn suggest(rules: &[ClipboardRule], text: &str) -> Option<Suggestion> {
+    rules.iter().filter(|rule| rule.enabled).find_map(|rule| {
+        let regex = match rule.pattern.regex() {
+            Ok(regex) => regex,
+            Err(e) => {
+                eprintln!("{}", e);
+                return None;
+            }
+        };
+        let found = regex.find(text)?;
+        // Tracking numbers and the like are asked about on their own; errors and links as copied
+        let subject = match rule.pattern {
+            ClipboardPattern::TrackingNumber => found.as_str(),
+            _ => text.trim(),
+        };
+        let mut values = HashMap::new();
+        values.insert("clipboard".to_string(), subject.to_string());
+        Some(Suggestion {
+            suggestion: rule.suggestion.clone(),
+            prompt: quick_actions::fill_placeholders(&rule.prompt, &values),
+        })
+    })
+}
+
+fn offer(app_handle: &tauri::AppHandle, suggestion: Suggestion, text: &str) {
+    *app_handle.state::<ClipboardState>().pending.lock().unwrap() = Some((suggestion.clone(), Instant::now()));
+    if let Err(e) = app_handle.emit("clipboard-suggestion", &suggestion) {
+        eprintln!("Failed to emit clipboard-suggestion: {}", e);
+    }
+
+    if quiet::is_quiet(app_handle) {
+        return;
+    }
+    let body = format!("{}\nOpen the spotlight to ask.", preview(text));
+    let notification = app_handle
+        .notification()
+        .builder()
+        .title(&suggestion.suggestion)
+        .body(body);
+    if let Err(e) = notification.show() {
+        eprintln!("Failed to show clipboard suggestion: {}", e);
+    }
+}
+
+// Watch the clipboard while the user has opted in and privacy mode is off, suggesting prompts for text
+// that matches a rule
+pub fn start_watching(app_handle: &tauri::AppHandle) {
+    let app_handle = app_handle.clone();
+    std::thread::spawn(move || {
+        let mut clipboard = None;
+        let mut last_text: Option<String> = None;
+        loop {
+            std::thread::sleep(POLL_INTERVAL);
+            let config = settings::current(&app_handle).clipboard;
+            if !config.enabled || privacy::is_active(&app_handle) {
+                clipboard = None;
+                last_text = None;
+                continue;
+            }
+
+            if clipboard.is_none() {
+                clipboard = match arboard::Clipboard::new() {
+                    Ok(clipboard) => Some(clipboard),
+                    Err(e) => {
+                        eprintln!("Failed to open the clipboard: {}", e);
+                        continue;
+                    }
+                };
+            }
+            let text = match clipboard.as_mut().map(|clipboard| clipboard.get_text()) {
+                Some(Ok(text)) => text,
+                _ => continue,
+            };
+            // The first read is what was there before, not a new copy
+            let previous = last_text.replace(text.clone());
+            if previous.is_none() || previous.as_deref() == Some(text.as_str()) || text.len() > MAX_TEXT_LEN {
+                continue;
+            }
+
+            // Text copied out of Krya itself doesn't need suggesting back
+            let own_copy = app_handle
+                .webview_windows()
+                .values()
+                .any(|window| window.is_focused().unwrap_or(false));
+            if own_copy {
+                continue;
+            }
+            if let Some(suggestion) = suggest(&config.rules, &text) {
+                offer(&app_handle, suggestion, &text);
+            }
+        }
+    });
+}
+
+// Pre-fill a spotlight that was just opened with a recent suggestion, once
+pub fn fill_pending(window: &WebviewWindow) {
+    let pending = window.state::<ClipboardState>().pending.lock().unwrap().take();
+    if let Some((suggestion, at)) = pending {
+        if at.elapsed() < SUGGESTION_TTL {
+            if let Err(e) = window.emit_to(window.label(), "prefill-prompt", suggestion) {
+                eprintln!("Failed to emit prefill-prompt: {}", e);
+            }
+        }
+    }
+}
+
+#[tauri::command]
+pub fn dismiss_clipboard_suggestion(state: tauri::State<ClipboardState>) {
+    state.pending.lock().unwrap().take();
+}
@@ -0,0 +1,100 @@
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use std::io::{Read, Write};
+use std::sync::atomic::{AtomicU8, Ordering};
+
+// Bodies smaller than this go out as-is; compressing them costs more than it saves on loopback
+pub const THRESHOLD: usize = 16 * 1024;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Encoding {
+    Zstd,
+    Gzip,
+}
+
+// In order of preference
+pub const SUPPORTED: &[Encoding] = &[Encoding::Zstd, Encoding::Gzip];
+
+impl Encoding {
+    pub fn name(self) -> &'static str {
+        match self {
+            Encoding::Zstd => "zstd",
+            Encoding::Gzip => "gzip",
+        }
+    }
+
+    pub fn parse(name: &str) -> Option<Self> {
+        SUPPORTED.iter().copied().find(|e| e.name().eq_ignore_ascii_case(name.trim()))
+    }
+}
+
+pub fn compress(encoding: Encoding, data: &[u8]) -> Result<Vec<u8>, String> {
+    match encoding {
+        Encoding::Zstd => zstd::encode_all(data, 3).map_err(|e| format!("Failed to compress payload: {}", e)),
+        Encoding::Gzip => {
+            let mut encoder = GzEncoder::new(Vec::new(), flate2::Compression::fast());
+            encoder
+                .write_all(data)
+                .and_then(|_| encoder.finish())
+                .map_err(|e| format!("Failed to compress payload: {}", e))
+        }
+    }
+}
+
+pub fn decompress(encoding: Encoding, data: &[u8]) -> Result<Vec<u8>, String> {
+    let mut out = Vec::new();
+    let result = match encoding {
+        Encoding::Zstd => zstd::stream::read::Decoder::new(data).and_then(|mut d| d.read_to_end(&mut out)),
+        Encoding::Gzip => GzDecoder::new(data).read_to_end(&mut out),
+    };
+    result
+        .map(|_| out)
+        .map_err(|e| format!("Failed to decompress {} payload: {}", encoding.name(), e))
+}
+
+// Compress a body if it's big enough to be worth it, returning the encoding used
+pub fn maybe_compress(encoding: Option<Encoding>, data: Vec<u8>) -> Result<(Vec<u8>, Option<Encoding>), String> {
+    match encoding {
+        Some(encoding) if data.len() >= THRESHOLD => Ok((compress(encoding, &data)?, Some(encoding))),
+        _ => Ok((data, None)),
+    }
+}
+
+// Our most preferred encoding among those in an Accept-Encoding header
+pub fn from_accept_encoding(header: &str) -> Option<Encoding> {
+    let offered: Vec<&str> = header
+        .split(',')
+        .filter_map(|part| {
+            let mut fields = part.split(';').map(|f| f.trim());
+            let name = fields.next()?;
+            let refused = fields.any(|f| f.replace(' ', "") == "q=0");
+            if refused {
+                None
+            } else {
+                Some(name)
+            }
+        })
+        .collect();
+    SUPPORTED
+        .iter()
+        .copied()
+        .find(|e| offered.iter().any(|name| name.eq_ignore_ascii_case(e.name())))
+}
+
+// Encoding agreed with the backend in the handshake: 0 = none, otherwise 1 + index into SUPPORTED
+static NEGOTIATED: AtomicU8 = AtomicU8::new(0);
+
+pub fn set_negotiated(encoding: Option<Encoding>) {
+    let value = encoding
+        .and_then(|e| SUPPORTED.iter().position(|s| *s == e))
+        .map(|i| i as u8 + 1)
+        .unwrap_or(0);
+    NEGOTIATED.store(value, Ordering::Relaxed);
+}
+
+pub fn negotiated() -> Option<Encoding> {
+    match NEGOTIATED.load(Ordering::Relaxed) {
+        0 => None,
+        value => SUPPORTED.get(value as usize - 1).copied(),
+    }
+}
@@ -0,0 +1,209 @@
+use portable_pty::{native_pty_system, Child, CommandBuilder, MasterPty, PtySize};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::io::{Read, Write};
+use std::sync::Mutex;
+use tauri::{Emitter, Manager};
+
+use crate::{accelerators, backend, handoff, mcp, models, paths, python, scopes, settings, tls};
+
+// Window that console output is sent to
+const CONSOLE_WINDOW: &str = "console";
+
+#[derive(Clone, Copy, Debug, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ConsoleKind {
+    // The user's shell, in the working directory
+    Shell,
+    // An interactive Python in the backend's sources, with the backend's environment
+    Backend,
+}
+
+struct ConsoleSession {
+    master: Box<dyn MasterPty + Send>,
+    writer: Box<dyn Write + Send>,
+    child: Box<dyn Child + Send + Sync>,
+}
+
+#[derive(Default)]
+pub struct ConsoleState {
+    sessions: Mutex<HashMap<String, ConsoleSession>>,
+}
+
+#[derive(Clone, Debug, Serialize)]
+struct ConsoleOutput<'a> {
+    session_id: &'a str,
+    data: String,
+}
+
+#[derive(Clone, Debug, Serialize)]
+struct ConsoleExit<'a> {
+    session_id: &'a str,
+    exit_code: Option<u32>,
+}
+
+fn default_shell() -> String {
+    if cfg!(target_os = "windows") {
+        std::env::var("COMSPEC").unwrap_or_else(|_| "cmd.exe".to_string())
+    } else {
+        std::env::var("SHELL").unwrap_or_else(|_| "/bin/sh".to_string())
+    }
+}
+
+fn command(app_handle: &tauri::AppHandle, kind: ConsoleKind) -> Result<CommandBuilder, String> {
+    let mut command = match kind {
+        ConsoleKind::Shell => {
+            let mut command = CommandBuilder::new(default_shell());
+            let dir = scopes::working_directory(app_handle).unwrap_or_else(|| settings::expand_home("~"));
+            command.cwd(dir);
+            command
+        }
+        ConsoleKind::Backend => {
+            let python = python::resolve(app_handle)?;
+            let mut command = CommandBuilder::new(&python.path);
+            for (key, value) in python.activation_env() {
+                command.env(key, value);
+            }
+            command.arg("-i");
+            command.cwd(backend::source_dir(app_handle)?);
+            command
+        }
+    };
+    // Both kinds can reach the backend and the shell's services the way the backend does
+    let hints = accelerators::env_hints(app_handle)
+        .into_iter()
+        .chain(models::env_hints(app_handle))
+        .chain(mcp::env_hints(app_handle))
+        .chain(handoff::env_hints(app_handle))
+        .chain(paths::env_hints(app_handle))
+        .chain(tls::env_hints(app_handle));
+    for (key, value) in hints {
+        command.env(key, value);
+    }
+    command.env("TERM", "xterm-256color");
+    Ok(command)
+}
+
+// Hand output to the console window, holding back a UTF-8 sequence split across reads
+fn stream_output(app_handle: tauri::AppHandle, session_id: String, mut reader: Box<dyn Read + Send>) {
+    let mut buffer = [0u8; 8192];
+    let mut pending: Vec<u8> = Vec::new();
+    loop {
+        let read = match reader.read(&mut buffer) {
+            Ok(0) | Err(_) => break,
+            Ok(read) => read,
+        };
+        pending.extend_from_slice(&buffer[..read]);
+
+        let valid = match std::str::from_utf8(&pending) {
+            Ok(_) => pending.len(),
+            // An incomplete sequence at the end waits for the next read; invalid bytes go out replaced
+            Err(e) if e.error_len().is_none() => e.valid_up_to(),
+            Err(_) => pending.len(),
+        };
+        let data = String::from_utf8_lossy(&pending[..valid]).to_string();
+        pending.drain(..valid);
+        let _ = app_handle.emit_to(CONSOLE_WINDOW, "console-output", ConsoleOutput { session_id: &session_id, data });
+    }
+
+    let session = app_handle.state::<ConsoleState>().sessions.lock().unwrap().remove(&session_id);
+    let exit_code = session.and_then(|mut session| session.child.wait().ok()).map(|status| status.exit_code());
+    let _ = app_handle.emit_to(CONSOLE_WINDOW, "console-exit", ConsoleExit { session_id: &session_id, exit_code });
+}
+
+// Start a console session in a new pseudo-terminal, returning its id
+#[tauri::command]
+pub fn console_open(app_handle: tauri::AppHandle, kind: ConsoleKind, cols: u16, rows: u16) -> Result<String, String> {
+    let size = PtySize {
+        rows,
+        cols,
+        pixel_width: 0,
+        pixel_height: 0,
+    };
+    let pair = native_pty_system()
+        .openpty(size)
+        .map_err(|e| format!("Failed to open terminal: {}", e))?;
+    let child = pair
+        .slave
+        .spawn_command(command(&app_handle, kind)?)
+        .map_err(|e| format!("Failed to start console: {}", e))?;
+    // The child holds its own handle; ours would keep the terminal open after it exits
+    drop(pair.slave);
+
+    let reader = pair
+        .master
+        .try_clone_reader()
+        .map_err(|e| format!("Failed to read from terminal: {}", e))?;
+    let writer = pair
+        .master
+        .take_writer()
+        .map_err(|e| format!("Failed to write to terminal: {}", e))?;
+
+    let session_id = uuid::Uuid::new_v4().to_string();
+    app_handle.state::<ConsoleState>().sessions.lock().unwrap().insert(
+        session_id.clone(),
+        ConsoleSession {
+            master: pair.master,
+            writer,
+            child,
+        },
+    );
+
+    let thread_handle = app_handle.clone();
+    let thread_session = session_id.clone();
+    std::thread::spawn(move || stream_output(thread_handle, thread_session, reader));
+    Ok(session_id)
+}
+
+#[tauri::command]
+pub fn console_write(app_handle: tauri::AppHandle, session_id: String, data: String) -> Result<(), String> {
+    let state = app_handle.state::<ConsoleState>();
+    let mut sessions = state.sessions.lock().unwrap();
+    let session = sessions
+        .get_mut(&session_id)
+        .ok_or_else(|| format!("Console session {} not found", session_id))?;
+    session
+        .writer
+        .write_all(data.as_bytes())
+        .and_then(|_| session.writer.flush())
+        .map_err(|e| format!("Failed to write to console: {}", e))
+}
+
+#[tauri::command]
+pub fn console_resize(app_handle: tauri::AppHandle, session_id: String, cols: u16, rows: u16) -> Result<(), String> {
+    let state = app_handle.state::<ConsoleState>();
+    let sessions = state.sessions.lock().unwrap();
+    let session = sessions
+        .get(&session_id)
+        .ok_or_else(|| format!("Console session {} not found", session_id))?;
+    session
+        .master
+        .resize(PtySize {
+            rows,
+            cols,
+            pixel_width: 0,
+            pixel_height: 0,
+        })
+        .map_err(|e| format!("Failed to resize console: {}", e))
+}
+
+// End a session; its output stream reports the exit once the process is gone
+#[tauri::command]
+pub fn console_close(app_handle: tauri::AppHandle, session_id: String) -> Result<(), String> {
+    let state = app_handle.state::<ConsoleState>();
+    let mut sessions = state.sessions.lock().unwrap();
+    match sessions.get_mut(&session_id) {
+        Some(session) => session
+            .child
+            .kill()
+            .map_err(|e| format!("Failed to stop console: {}", e)),
+        None => Ok(()),
+    }
+}
+
+// Stop every session, e.g. when the console window closes
+pub fn close_all(app_handle: &tauri::AppHandle) {
+    for session in app_handle.state::<ConsoleState>().sessions.lock().unwrap().values_mut() {
+        let _ = session.child.kill();
+    }
+}
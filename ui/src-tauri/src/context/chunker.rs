@@ -0,0 +1,74 @@
+use std::path::Path;
+
+// Target chunk size in characters, and how much consecutive chunks overlap
+const CHUNK_SIZE: usize = 1200;
+const CHUNK_OVERLAP: usize = 200;
+
+// Skip anything bigger than this; it's almost certainly not prose or source code
+const MAX_FILE_SIZE: u64 = 2 * 1024 * 1024;
+
+const TEXT_EXTENSIONS: &[&str] = &[
+    "txt", "md", "markdown", "rst", "csv", "tsv", "json", "yaml", "yml", "toml", "ini", "html", "htm",
+    "xml", "py", "rs", "js", "jsx", "ts", "tsx", "java", "kt", "go", "rb", "php", "c", "h", "cpp",
+    "hpp", "cs", "swift", "sh", "sql", "css", "tex",
+];
+
+#[derive(Clone, Debug)]
+pub struct Chunk {
+    pub start_line: usize,
+    pub end_line: usize,
+    pub text: String,
+}
+
+pub fn is_indexable(path: &Path) -> bool {
+    let extension = path
+        .extension()
+        .map(|ext| ext.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+
+    TEXT_EXTENSIONS.contains(&extension.as_str())
+        && path
+            .metadata()
+            .map(|m| m.len() <= MAX_FILE_SIZE)
+            .unwrap_or(false)
+}
+
+// Split text into overlapping chunks on line boundaries
+pub fn chunk_text(text: &str) -> Vec<Chunk> {
+    let lines: Vec<&str> = text.lines().collect();
+    let mut chunks = Vec::new();
+    let mut start = 0;
+
+    while start < lines.len() {
+        let mut end = start;
+        let mut size = 0;
+        while end < lines.len() && (size == 0 || size + lines[end].len() < CHUNK_SIZE) {
+            size += lines[end].len() + 1;
+            end += 1;
+        }
+
+        let chunk_text = lines[start..end].join("\n");
+        if !chunk_text.trim().is_empty() {
+            chunks.push(Chunk {
+                start_line: start + 1,
+                end_line: end,
+                text: chunk_text,
+            });
+        }
+
+        if end >= lines.len() {
+            break;
+        }
+
+        // Step back far enough to keep roughly CHUNK_OVERLAP characters of context
+        let mut next = end;
+        let mut overlap = 0;
+        while next > start + 1 && overlap < CHUNK_OVERLAP {
+            next -= 1;
+            overlap += lines[next].len() + 1;
+        }
+        start = next;
+    }
+
+    chunks
+}
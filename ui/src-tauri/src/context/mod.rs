@@ -0,0 +1,244 @@
+mod chunker;
+mod store;
+
+use serde::Serialize;
+use std::collections::HashSet;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::UNIX_EPOCH;
+use tauri::Emitter;
+use walkdir::WalkDir;
+
+use crate::{backend, files, idle, settings, storage};
+use store::{StoredChunk, VectorStore};
+
+const STORE_FILE: &str = "context_index.json";
+
+// Number of chunks sent to the backend per embedding request
+const EMBED_BATCH_SIZE: usize = 32;
+
+#[derive(Clone, Debug, Serialize)]
+pub struct ContextMatch {
+    pub path: String,
+    pub start_line: usize,
+    pub end_line: usize,
+    pub text: String,
+    pub score: f32,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct ContextIndexProgress {
+    pub indexed_files: usize,
+    pub total_files: usize,
+    pub done: bool,
+}
+
+// Embedded chunks of the user-selected folders, used to add relevant file snippets to prompts
+pub struct ContextIndex {
+    store: Arc<Mutex<VectorStore>>,
+    indexing: Arc<AtomicBool>,
+}
+
+impl ContextIndex {
+    pub fn load(app_handle: &tauri::AppHandle) -> Self {
+        ContextIndex {
+            store: Arc::new(Mutex::new(storage::load_json(app_handle, STORE_FILE))),
+            indexing: Arc::new(AtomicBool::new(false)),
+        }
+    }
+
+    // Re-embed changed files in the configured folders on a background thread
+    pub fn rebuild_in_background(&self, app_handle: &tauri::AppHandle) {
+        if self.indexing.swap(true, Ordering::SeqCst) {
+            return;
+        }
+
+        let store = self.store.clone();
+        let indexing = self.indexing.clone();
+        let app_handle = app_handle.clone();
+        std::thread::spawn(move || {
+            if let Err(e) = rebuild(&app_handle, &store) {
+                eprintln!("Failed to build context index: {}", e);
+            }
+            indexing.store(false, Ordering::SeqCst);
+        });
+    }
+
+    pub fn search(&self, query: &str, limit: usize) -> Result<Vec<ContextMatch>, String> {
+        if self.store.lock().unwrap().chunks.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let query_vector = backend::embed(&[query.to_string()], "retrieval_query")?
+            .pop()
+            .ok_or_else(|| "Embedding response was empty".to_string())?;
+
+        let store = self.store.lock().unwrap();
+        Ok(store
+            .search(&query_vector, limit)
+            .into_iter()
+            .map(|(score, chunk)| ContextMatch {
+                path: chunk.path.clone(),
+                start_line: chunk.start_line,
+                end_line: chunk.end_line,
+                text: chunk.text.clone(),
+                score,
+            })
+            .collect())
+    }
+}
+
+fn modified_time(path: &std::path::Path) -> u64 {
+    path.metadata()
+        .ok()
+        .and_then(|m| m.modified().ok())
+        .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+fn rebuild(app_handle: &tauri::AppHandle, store: &Mutex<VectorStore>) -> Result<(), String> {
+    // Embedding is slow and keeps the backend busy, so only a first build runs while the user is around
+    let deferred = !store.lock().unwrap().chunks.is_empty();
+    if deferred {
+        idle::wait_until_idle(app_handle);
+    }
+    let config = settings::current(app_handle);
+
+    // Collect every indexable file in the selected folders
+    let mut paths = Vec::new();
+    for folder in &config.context.folders {
+        let root = settings::expand_home(folder);
+        let walker = WalkDir::new(&root)
+            .into_iter()
+            .filter_entry(|entry| entry.depth() == 0 || !files::is_excluded(entry.path(), &config.file_index));
+        for entry in walker.flatten() {
+            if entry.file_type().is_file() && chunker::is_indexable(entry.path()) {
+                paths.push(entry.into_path());
+            }
+        }
+    }
+
+    // Forget files that were deleted or are no longer in a selected folder
+    {
+        let current: HashSet<String> = paths.iter().map(|p| p.to_string_lossy().to_string()).collect();
+        let mut store = store.lock().unwrap();
+        let stale: Vec<String> = store
+            .file_versions
+            .keys()
+            .filter(|path| !current.contains(*path))
+            .cloned()
+            .collect();
+        for path in stale {
+            store.remove_file(&path);
+        }
+    }
+
+    let total_files = paths.len();
+    for (i, path) in paths.iter().enumerate() {
+        if deferred {
+            idle::wait_until_idle(app_handle);
+        }
+        let path_str = path.to_string_lossy().to_string();
+        let modified = modified_time(path);
+
+        let unchanged = store.lock().unwrap().file_versions.get(&path_str) == Some(&modified);
+        if !unchanged {
+            let text = match std::fs::read_to_string(path) {
+                Ok(text) => text,
+                // Not valid UTF-8, so not something we can embed
+                Err(_) => continue,
+            };
+
+            let chunks = chunker::chunk_text(&text);
+            let mut embedded = Vec::with_capacity(chunks.len());
+            for batch in chunks.chunks(EMBED_BATCH_SIZE) {
+                let texts: Vec<String> = batch.iter().map(|c| c.text.clone()).collect();
+                let vectors = backend::embed(&texts, "retrieval_document")?;
+                for (chunk, vector) in batch.iter().zip(vectors) {
+                    embedded.push(StoredChunk {
+                        path: path_str.clone(),
+                        start_line: chunk.start_line,
+                        end_line: chunk.end_line,
+                        text: chunk.text.clone(),
+                        vector,
+                    });
+                }
+            }
+
+            let mut store = store.lock().unwrap();
+            store.remove_file(&path_str);
+            store.chunks.extend(embedded);
+            store.file_versions.insert(path_str, modified);
+        }
+
+        let _ = app_handle.emit(
+            "context-index-progress",
+            ContextIndexProgress {
+                indexed_files: i + 1,
+                total_files,
+                done: false,
+            },
+        );
+    }
+
+    storage::save_json(app_handle, STORE_FILE, &*store.lock().unwrap(), storage::Data::Config)?;
+
+    let _ = app_handle.emit(
+        "context-index-progress",
+        ContextIndexProgress {
+            indexed_files: total_files,
+            total_files,
+            done: true,
+        },
+    );
+    println!("Context index covers {} files", total_files);
+    Ok(())
+}
+
+// Command to find the file snippets most relevant to a query
+#[tauri::command(async)]
+pub fn semantic_search(
+    index: tauri::State<ContextIndex>,
+    query: String,
+    limit: Option<usize>,
+) -> Result<Vec<ContextMatch>, String> {
+    index.search(&query, limit.unwrap_or(5))
+}
+
+// Command to build the context block that gets prepended to a prompt, if enabled
+#[tauri::command(async)]
+pub fn get_prompt_context(
+    app_handle: tauri::AppHandle,
+    index: tauri::State<ContextIndex>,
+    prompt: String,
+) -> Result<Option<String>, String> {
+    let config = settings::current(&app_handle).context;
+    if !config.auto_attach || config.folders.is_empty() {
+        return Ok(None);
+    }
+
+    let matches: Vec<ContextMatch> = index
+        .search(&prompt, config.max_snippets)?
+        .into_iter()
+        .filter(|m| m.score >= config.min_score)
+        .collect();
+
+    if matches.is_empty() {
+        return Ok(None);
+    }
+
+    let mut context = String::from("Relevant file snippets:\n");
+    for m in matches {
+        context.push_str(&format!(
+            "\n--- {} (lines {}-{})\n{}\n",
+            m.path, m.start_line, m.end_line, m.text
+        ));
+    }
+    Ok(Some(context))
+}
+
+#[tauri::command]
+pub fn rebuild_context_index(app_handle: tauri::AppHandle, index: tauri::State<ContextIndex>) {
+    index.rebuild_in_background(&app_handle);
+}
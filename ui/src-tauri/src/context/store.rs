@@ -0,0 +1,62 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct StoredChunk {
+    pub path: String,
+    pub start_line: usize,
+    pub end_line: usize,
+    pub text: String,
+    pub vector: Vec<f32>,
+}
+
+// Embedded chunks for every indexed file, persisted as JSON in the app data directory
+#[derive(Default, Serialize, Deserialize)]
+pub struct VectorStore {
+    #[serde(default)]
+    pub chunks: Vec<StoredChunk>,
+    // Modification time of each file when it was embedded, so unchanged files are skipped
+    #[serde(default)]
+    pub file_versions: HashMap<String, u64>,
+}
+
+impl VectorStore {
+    pub fn remove_file(&mut self, path: &str) {
+        self.chunks.retain(|c| c.path != path);
+        self.file_versions.remove(path);
+    }
+
+    // Return the `limit` chunks most similar to the query vector
+    pub fn search(&self, query: &[f32], limit: usize) -> Vec<(f32, &StoredChunk)> {
+        let mut scored: Vec<(f32, &StoredChunk)> = self
+            .chunks
+            .iter()
+            .map(|chunk| (cosine_similarity(query, &chunk.vector), chunk))
+            .collect();
+
+        scored.sort_by(|a, b| b.0.partial_cmp(&a.0).unwrap_or(std::cmp::Ordering::Equal));
+        scored.truncate(limit);
+        scored
+    }
+}
+
+fn cosine_similarity(a: &[f32], b: &[f32]) -> f32 {
+    if a.len() != b.len() || a.is_empty() {
+        return 0.0;
+    }
+
+    let mut dot = 0.0;
+    let mut norm_a = 0.0;
+    let mut norm_b = 0.0;
+    for (x, y) in a.iter().zip(b) {
+        dot += x * y;
+        norm_a += x * x;
+        norm_b += y * y;
+    }
+
+    if norm_a == 0.0 || norm_b == 0.0 {
+        0.0
+    } else {
+        dot / (norm_a.sqrt() * norm_b.sqrt())
+    }
+}
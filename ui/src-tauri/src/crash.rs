@@ -0,0 +1,398 @@
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tauri::Manager;
+
+use crate::notifications::{self, NotificationKind};
+use crate::util::now_secs;
+use crate::{events, logs, settings, storage, support, tls, AppState};
+
+pub const CRASH_DIR: &str = "crashes";
+
+// Left in CRASH_DIR by a fatal panic, whether or not crash reporting is on, so the next launch can tell
+const CRASH_MARKER: &str = "last-crash.marker";
+
+// How often the supervisor checks whether the Python server is still alive
+const BACKEND_POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+// How long the Python server gets to shut down on its own after a crash before it's killed
+const BACKEND_STOP_GRACE: Duration = Duration::from_secs(3);
+
+// Mirrors settings.crash_reporting.enabled so the panic hook never has to take a lock
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum CrashKind {
+    // A Rust panic in the shell
+    Panic,
+    // The Python server exited without being asked to
+    BackendExit,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct CrashReport {
+    pub id: String,
+    // Shared by every report from one app launch, to line up native and backend crashes
+    pub session_id: String,
+    pub kind: CrashKind,
+    pub timestamp: u64,
+    pub app_version: String,
+    pub os: String,
+    pub arch: String,
+    pub message: String,
+    pub location: Option<String>,
+    pub exit_code: Option<i32>,
+    pub signal: Option<i32>,
+    // End of backend.log at the time of a backend crash
+    pub log_tail: Option<String>,
+}
+
+// Identity of the current launch, shared by the panic hook and the backend watcher
+#[derive(Clone)]
+pub struct CrashReporter {
+    session_id: String,
+    app_version: String,
+    dir: Option<PathBuf>,
+}
+
+impl CrashReporter {
+    pub fn new(app_handle: &tauri::AppHandle) -> Self {
+        let dir = storage::data_dir(app_handle).ok().map(|dir| dir.join(CRASH_DIR));
+        CrashReporter {
+            session_id: uuid::Uuid::new_v4().to_string(),
+            app_version: app_handle.package_info().version.to_string(),
+            dir,
+        }
+    }
+
+    fn report(&self, kind: CrashKind, message: String) -> CrashReport {
+        CrashReport {
+            id: uuid::Uuid::new_v4().to_string(),
+            session_id: self.session_id.clone(),
+            kind,
+            timestamp: now_secs(),
+            app_version: self.app_version.clone(),
+            os: std::env::consts::OS.to_string(),
+            arch: std::env::consts::ARCH.to_string(),
+            message,
+            location: None,
+            exit_code: None,
+            signal: None,
+            log_tail: None,
+        }
+    }
+
+    // Queue a report on disk; does nothing unless the user opted in
+    fn queue(&self, report: &CrashReport) {
+        if !ENABLED.load(Ordering::SeqCst) {
+            return;
+        }
+        if let Some(dir) = &self.dir {
+            if let Err(e) = write_report(dir, report) {
+                eprintln!("Failed to save crash report: {}", e);
+            }
+        }
+    }
+}
+
+fn write_report(dir: &Path, report: &CrashReport) -> Result<(), String> {
+    std::fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+    let contents = serde_json::to_string_pretty(report).map_err(|e| e.to_string())?;
+    std::fs::write(dir.join(format!("{}.json", report.id)), contents).map_err(|e| e.to_string())
+}
+
+fn queued_reports(dir: &Path) -> Vec<(PathBuf, CrashReport)> {
+    let entries = match std::fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(_) => return Vec::new(),
+    };
+
+    let mut reports: Vec<(PathBuf, CrashReport)> = entries
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().map(|ext| ext == "json").unwrap_or(false))
+        .filter_map(|path| {
+            let report = serde_json::from_str(&std::fs::read_to_string(&path).ok()?).ok()?;
+            Some((path, report))
+        })
+        .collect();
+    reports.sort_by_key(|(_, report)| report.timestamp);
+    reports
+}
+
+fn write_marker(dir: &Path, report: &CrashReport) -> Result<(), String> {
+    std::fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+    let contents = serde_json::to_string_pretty(report).map_err(|e| e.to_string())?;
+    std::fs::write(dir.join(CRASH_MARKER), contents).map_err(|e| e.to_string())
+}
+
+// The crash the previous launch ended with, picked up once at startup
+#[derive(Default)]
+pub struct LastCrash(Mutex<Option<CrashReport>>);
+
+impl LastCrash {
+    pub fn load(reporter: &CrashReporter) -> Self {
+        let path = match &reporter.dir {
+            Some(dir) => dir.join(CRASH_MARKER),
+            None => return LastCrash::default(),
+        };
+        let report = std::fs::read_to_string(&path)
+            .ok()
+            .and_then(|contents| serde_json::from_str(&contents).ok());
+        if path.exists() {
+            if let Err(e) = std::fs::remove_file(&path) {
+                eprintln!("Failed to remove crash marker: {}", e);
+            }
+        }
+        LastCrash(Mutex::new(report))
+    }
+}
+
+// Ask the Python server to exit so it can clean up, killing it if it doesn't in time.
+// Uses try_lock because the panicking thread may be the one holding the process.
+fn stop_backend(app_state: &AppState) {
+    let mut process = match app_state.api_server_process.try_lock() {
+        Ok(process) => process,
+        Err(_) => return,
+    };
+    let mut child = match process.take() {
+        Some(child) => child,
+        None => return,
+    };
+    println!("Stopping Python API server after a crash");
+
+    #[cfg(unix)]
+    let _ = Command::new("kill").arg("-TERM").arg(child.id().to_string()).status();
+    #[cfg(not(unix))]
+    let _ = Command::new("taskkill")
+        .args(["/F", "/T", "/PID", &child.id().to_string()])
+        .output();
+
+    let started = Instant::now();
+    while started.elapsed() < BACKEND_STOP_GRACE {
+        match child.try_wait() {
+            Ok(Some(_)) | Err(_) => break,
+            Ok(None) => std::thread::sleep(Duration::from_millis(100)),
+        }
+    }
+    if let Ok(None) = child.try_wait() {
+        let _ = child.kill();
+    }
+    if let Ok(mut running) = app_state.api_server_running.try_lock() {
+        *running = false;
+    }
+}
+
+const CREATE_BUNDLE: &str = "Create support bundle";
+
+// Explain the crash and offer a support bundle before the app goes away. rfd shows it on the calling
+// thread, which here is the panicking main thread; the dialog plugin would wait on that same thread
+// and never return.
+fn show_crash_dialog(app_handle: &tauri::AppHandle, report: &CrashReport) {
+    let mut details = report.message.clone();
+    if let Some(location) = &report.location {
+        details.push_str(&format!("\n({})", location));
+    }
+    let message = format!(
+        "Krya.ai ran into a problem and has to close.\n\n{}\n\n\
+         A support bundle collects logs, settings and system details you can attach to a bug report.",
+        details
+    );
+    let choice = rfd::MessageDialog::new()
+        .set_level(rfd::MessageLevel::Error)
+        .set_title("Krya.ai crashed")
+        .set_description(message)
+        .set_buttons(rfd::MessageButtons::OkCancelCustom(
+            CREATE_BUNDLE.to_string(),
+            "Close".to_string(),
+        ))
+        .show();
+    let create_bundle = match choice {
+        rfd::MessageDialogResult::Ok => true,
+        rfd::MessageDialogResult::Custom(label) => label == CREATE_BUNDLE,
+        _ => false,
+    };
+    if !create_bundle {
+        return;
+    }
+
+    // On its own thread, so a second panic while gathering the bundle can't abort this one
+    let bundle_handle = app_handle.clone();
+    match std::thread::spawn(move || support::generate_support_bundle(bundle_handle)).join() {
+        Ok(Ok(path)) => println!("Support bundle saved to {}", path),
+        Ok(Err(e)) => eprintln!("Failed to create support bundle: {}", e),
+        Err(_) => eprintln!("Failed to create support bundle"),
+    }
+}
+
+// Keep the panic hook's view of consent in sync with settings
+pub fn apply_settings(app_handle: &tauri::AppHandle) {
+    ENABLED.store(settings::current(app_handle).crash_reporting.enabled, Ordering::SeqCst);
+}
+
+// Record panics as crash reports, keeping the default hook's output (and backtrace, with RUST_BACKTRACE set).
+// A panic on the main thread takes the app down, so that one also stops the backend, flushes logs,
+// leaves a crash marker for the next launch and then tells the user what happened.
+pub fn install_panic_hook(app_handle: &tauri::AppHandle, reporter: CrashReporter) {
+    let default_hook = std::panic::take_hook();
+    let app_handle = app_handle.clone();
+
+    std::panic::set_hook(Box::new(move |info| {
+        let message = info
+            .payload()
+            .downcast_ref::<&str>()
+            .map(|s| s.to_string())
+            .or_else(|| info.payload().downcast_ref::<String>().cloned())
+            .unwrap_or_else(|| "Unknown panic".to_string());
+
+        let mut report = reporter.report(CrashKind::Panic, message);
+        report.location = info
+            .location()
+            .map(|l| format!("{}:{}:{}", l.file(), l.line(), l.column()));
+        reporter.queue(&report);
+
+        default_hook(info);
+
+        // Panics elsewhere only end their own thread
+        if std::thread::current().name() != Some("main") {
+            return;
+        }
+        if let Some(app_state) = app_handle.try_state::<AppState>() {
+            stop_backend(&app_state);
+        }
+        logs::flush(&app_handle);
+        if let Some(dir) = &reporter.dir {
+            if let Err(e) = write_marker(dir, &report) {
+                eprintln!("Failed to write crash marker: {}", e);
+            }
+        }
+        show_crash_dialog(&app_handle, &report);
+    }));
+}
+
+// Watch the Python server and record a crash report if it exits on its own
+pub fn watch_backend(app_handle: &tauri::AppHandle, app_state: AppState) {
+    let app_handle = app_handle.clone();
+    std::thread::spawn(move || loop {
+        std::thread::sleep(BACKEND_POLL_INTERVAL);
+
+        let status = {
+            let mut process = app_state.api_server_process.lock().unwrap();
+            let child = match process.as_mut() {
+                Some(child) => child,
+                // stop_api_server took the process, so this was a clean shutdown
+                None => return,
+            };
+            match child.try_wait() {
+                Ok(Some(status)) => {
+                    process.take();
+                    *app_state.api_server_running.lock().unwrap() = false;
+                    status
+                }
+                Ok(None) => continue,
+                Err(e) => {
+                    eprintln!("Failed to check API server process: {}", e);
+                    continue;
+                }
+            }
+        };
+
+        eprintln!("API server exited unexpectedly: {}", status);
+        let reporter = app_handle.state::<CrashReporter>();
+        let mut report = reporter.report(CrashKind::BackendExit, format!("API server exited: {}", status));
+        report.exit_code = status.code();
+        #[cfg(unix)]
+        {
+            use std::os::unix::process::ExitStatusExt;
+            report.signal = status.signal();
+        }
+        report.log_tail = logs::tail(&app_handle, logs::BACKEND_LOG_FILE, 16 * 1024);
+        reporter.queue(&report);
+
+        notifications::push(
+            &app_handle,
+            NotificationKind::BackendCrashed,
+            "The backend stopped unexpectedly",
+            Some(format!("It exited with {}", status)),
+        );
+        events::emit(&app_handle, &report);
+        return;
+    });
+}
+
+// Send reports queued by earlier launches to the configured endpoint, deleting each once accepted
+pub fn upload_pending(app_handle: &tauri::AppHandle) {
+    let config = settings::current(app_handle).crash_reporting;
+    let upload_url = match config.upload_url {
+        Some(url) if config.enabled && !url.trim().is_empty() => url,
+        _ => return,
+    };
+    let dir = match &app_handle.state::<CrashReporter>().dir {
+        Some(dir) => dir.clone(),
+        None => return,
+    };
+
+    std::thread::spawn(move || {
+        let client = match tls::client_builder()
+            .timeout(Duration::from_secs(15))
+            .build()
+        {
+            Ok(client) => client,
+            Err(e) => {
+                eprintln!("Failed to create HTTP client: {}", e);
+                return;
+            }
+        };
+
+        for (path, report) in queued_reports(&dir) {
+            match client
+                .post(&upload_url)
+                .json(&report)
+                .send()
+                .and_then(|r| r.error_for_status())
+            {
+                Ok(_) => {
+                    let _ = std::fs::remove_file(&path);
+                }
+                Err(e) => {
+                    // Leave the rest queued for the next launch
+                    eprintln!("Failed to upload crash report: {}", e);
+                    return;
+                }
+            }
+        }
+    });
+}
+
+pub fn pending_reports(reporter: &CrashReporter) -> Vec<CrashReport> {
+    match &reporter.dir {
+        Some(dir) => queued_reports(dir).into_iter().map(|(_, report)| report).collect(),
+        None => Vec::new(),
+    }
+}
+
+// Command to list crash reports waiting to be uploaded, for the settings preview
+#[tauri::command]
+pub fn list_crash_reports(reporter: tauri::State<CrashReporter>) -> Vec<CrashReport> {
+    pending_reports(&reporter)
+}
+
+// Command for the UI to find out, once, whether the previous launch crashed
+#[tauri::command]
+pub fn take_last_crash(last_crash: tauri::State<LastCrash>) -> Option<CrashReport> {
+    last_crash.0.lock().unwrap().take()
+}
+
+#[tauri::command]
+pub fn clear_crash_reports(reporter: tauri::State<CrashReporter>) -> Result<(), String> {
+    if let Some(dir) = &reporter.dir {
+        for (path, _) in queued_reports(dir) {
+            std::fs::remove_file(&path).map_err(|e| format!("Failed to delete {:?}: {}", path, e))?;
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,41 @@
+use crate::settings;
+
+// Show or hide the dock icon. Without one Krya runs as an accessory app, like LSUIElement apps,
+// reachable from the menu bar and shortcuts only.
+#[cfg(target_os = "macos")]
+fn set_dock_icon_visible(visible: bool) {
+    use cocoa::appkit::{NSApp, NSApplication, NSApplicationActivationPolicy};
+    use cocoa::base::YES;
+
+    let policy = if visible {
+        NSApplicationActivationPolicy::NSApplicationActivationPolicyRegular
+    } else {
+        NSApplicationActivationPolicy::NSApplicationActivationPolicyAccessory
+    };
+    unsafe {
+        let app = NSApp();
+        app.setActivationPolicy_(policy);
+        // Changing the policy deactivates the app, which would hide an open settings window
+        app.activateIgnoringOtherApps_(YES);
+    }
+}
+
+// Only macOS has a dock icon separate from the windows
+#[cfg(not(target_os = "macos"))]
+fn set_dock_icon_visible(_visible: bool) {}
+
+// Apply the dock icon setting; AppKit has to be called on the main thread
+pub fn apply(app_handle: &tauri::AppHandle) {
+    let visible = settings::current(app_handle).appearance.show_dock_icon;
+    if let Err(e) = app_handle.run_on_main_thread(move || set_dock_icon_visible(visible)) {
+        eprintln!("Failed to update the dock icon: {}", e);
+    }
+}
+
+// Command to switch between a regular app and an accessory app without a dock icon
+#[tauri::command]
+pub fn set_show_dock_icon(app_handle: tauri::AppHandle, visible: bool) -> Result<(), String> {
+    let mut settings = settings::current(&app_handle);
+    settings.appearance.show_dock_icon = visible;
+    settings::replace(&app_handle, settings)
+}
@@ -0,0 +1,53 @@
+use serde::Serialize;
+use std::fmt::Display;
+use tauri::Emitter;
+use tauri_plugin_dialog::{DialogExt, MessageDialogKind};
+
+#[derive(Clone, Debug, Serialize)]
+struct AppError {
+    context: String,
+    message: String,
+}
+
+// Log a failure and tell the UI, for things the user would otherwise only notice as a
+// window that didn't open or a shortcut that does nothing
+pub fn report(app_handle: &tauri::AppHandle, context: &str, error: impl Display) {
+    let message = error.to_string();
+    eprintln!("{}: {}", context, message);
+    let _ = app_handle.emit(
+        "app-error",
+        AppError {
+            context: context.to_string(),
+            message,
+        },
+    );
+}
+
+// Report a failure the user has to act on, with a native dialog in case no window can show it
+pub fn alert(app_handle: &tauri::AppHandle, context: &str, error: impl Display) {
+    let message = error.to_string();
+    report(app_handle, context, &message);
+    app_handle
+        .dialog()
+        .message(format!("{}.\n\n{}", context, message))
+        .title("Krya.ai")
+        .kind(MessageDialogKind::Error)
+        .show(|_| {});
+}
+
+pub trait ReportExt<T> {
+    // Report an error and carry on without the value
+    fn or_report(self, app_handle: &tauri::AppHandle, context: &str) -> Option<T>;
+}
+
+impl<T, E: Display> ReportExt<T> for Result<T, E> {
+    fn or_report(self, app_handle: &tauri::AppHandle, context: &str) -> Option<T> {
+        match self {
+            Ok(value) => Some(value),
+            Err(e) => {
+                report(app_handle, context, e);
+                None
+            }
+        }
+    }
+}
@@ -0,0 +1,100 @@
+use serde::Serialize;
+use tauri::Emitter;
+
+use crate::crash::CrashReport;
+use crate::jobs::{JobProgress, JournalEntry};
+use crate::network::NetworkStatus;
+use crate::protocol::HandshakeResult;
+
+// An event the shell sends the frontend: its name and payload are declared together here, as backend
+// methods are in protocol.rs, so the name can't drift from what's sent under it
+pub trait Event: Serialize {
+    const NAME: &'static str;
+}
+
+pub fn emit<E: Event>(app_handle: &tauri::AppHandle, event: &E) {
+    if let Err(e) = app_handle.emit(E::NAME, event) {
+        eprintln!("Failed to emit {}: {}", E::NAME, e);
+    }
+}
+
+// The backend supervisor
+
+#[derive(Clone, Debug, Serialize)]
+pub struct BackendStarted {
+    pub pid: u32,
+    pub port: u16,
+}
+
+impl Event for BackendStarted {
+    const NAME: &'static str = "backend-started";
+}
+
+// Sent once the handshake succeeded
+impl Event for HandshakeResult {
+    const NAME: &'static str = "backend-ready";
+}
+
+// Restarted on its changed code
+#[derive(Clone, Debug, Serialize)]
+pub struct BackendReloaded {
+    pub files: Vec<String>,
+}
+
+impl Event for BackendReloaded {
+    const NAME: &'static str = "backend-reloaded";
+}
+
+impl Event for CrashReport {
+    const NAME: &'static str = "backend-crashed";
+}
+
+// The job manager
+
+impl Event for JobProgress {
+    const NAME: &'static str = "job-progress";
+}
+
+// Every job that can be offered again, not just the latest
+#[derive(Clone, Debug, Serialize)]
+pub struct JobsInterrupted<'a>(pub &'a [JournalEntry]);
+
+impl Event for JobsInterrupted<'_> {
+    const NAME: &'static str = "jobs-interrupted";
+}
+
+// A running job was stopped for typing into an app it may not control
+#[derive(Clone, Debug, Serialize)]
+pub struct JobInputBlocked<'a> {
+    pub job_id: &'a str,
+    pub message: &'a str,
+}
+
+impl Event for JobInputBlocked<'_> {
+    const NAME: &'static str = "job-input-blocked";
+}
+
+// A line of output from a command run through exec_command
+#[derive(Clone, Copy, Debug, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ExecStream {
+    Stdout,
+    Stderr,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct ExecOutput<'a> {
+    pub exec_id: &'a str,
+    pub stream: ExecStream,
+    pub line: &'a str,
+}
+
+impl Event for ExecOutput<'_> {
+    const NAME: &'static str = "exec-output";
+}
+
+// The health monitor
+
+impl Event for NetworkStatus {
+    const NAME: &'static str = "network-status";
+}
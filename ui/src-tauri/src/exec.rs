@@ -0,0 +1,449 @@
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::io::{BufRead, BufReader, Read};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tauri::Manager;
+
+use crate::events::{self, ExecOutput, ExecStream};
+use crate::{audit, automation, mcp, scopes, settings};
+
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+// How long to wait for the rest of the output once the command is gone. Something it started in the
+// background can hold its pipes open indefinitely.
+const OUTPUT_GRACE: Duration = Duration::from_secs(2);
+
+// Output kept for the result past this is dropped; the events still carry all of it
+const MAX_CAPTURED_BYTES: usize = 1024 * 1024;
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct ExecRequest {
+    pub command: String,
+    #[serde(default)]
+    pub args: Vec<String>,
+    #[serde(default)]
+    pub cwd: Option<String>,
+    // To stop it by before it's done; one is made up when not given
+    #[serde(default)]
+    pub exec_id: Option<String>,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct ExecResult {
+    pub exec_id: String,
+    // None when it was killed
+    pub exit_code: Option<i32>,
+    pub timed_out: bool,
+    // Stopped through stop() before it finished
+    pub stopped: bool,
+    pub stdout: String,
+    pub stderr: String,
+}
+
+// Commands still running, so the backend can stop the script of a job it stops
+#[derive(Default)]
+pub struct ExecState {
+    running: Mutex<HashMap<String, Arc<AtomicBool>>>,
+}
+
+impl ExecState {
+    fn start(&self, exec_id: &str) -> Result<Arc<AtomicBool>, String> {
+        let mut running = self.running.lock().unwrap();
+        if running.contains_key(exec_id) {
+            return Err(format!("Exec {} is already running", exec_id));
+        }
+        let stop = Arc::new(AtomicBool::new(false));
+        running.insert(exec_id.to_string(), stop.clone());
+        Ok(stop)
+    }
+
+    fn finish(&self, exec_id: &str) {
+        self.running.lock().unwrap().remove(exec_id);
+    }
+
+    // Whether it was still running
+    pub fn stop(&self, exec_id: &str) -> bool {
+        match self.running.lock().unwrap().get(exec_id) {
+            Some(stop) => {
+                stop.store(true, Ordering::SeqCst);
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+// A bare name only matches a bare name, so a program of the same name elsewhere can't pass for an
+// allowed one
+fn matches(allowed: &str, command: &str) -> bool {
+    let is_path = |name: &str| name.contains('/') || name.contains('\\');
+    if is_path(allowed) || is_path(command) {
+        Path::new(allowed) == Path::new(command)
+    } else if cfg!(target_os = "windows") {
+        allowed.eq_ignore_ascii_case(command)
+    } else {
+        allowed == command
+    }
+}
+
+// The allowlist entry a command matches, and how long it may run
+fn allowed(app_handle: &tauri::AppHandle, command: &str) -> Result<Duration, String> {
+    let config = settings::current(app_handle).exec;
+    config
+        .allowed
+        .iter()
+        .find(|entry| matches(entry.command.trim(), command))
+        .map(|entry| Duration::from_secs(entry.timeout_secs.unwrap_or(config.default_timeout_secs)))
+        .ok_or_else(|| format!("{} isn't on the list of commands that may be run", command))
+}
+
+// Where it runs: an allowed folder, the working directory when there's one, or the home folder
+fn working_dir(app_handle: &tauri::AppHandle, cwd: Option<&str>) -> Result<PathBuf, String> {
+    match cwd.filter(|cwd| !cwd.trim().is_empty()) {
+        Some(cwd) => scopes::check_path(app_handle, cwd),
+        None => Ok(scopes::working_directory(app_handle).unwrap_or_else(|| settings::expand_home("~"))),
+    }
+}
+
+// Everything the command starts, so a timeout can stop all of it and not just the program itself:
+// its own process group on unix, a job object on Windows
+struct ProcessTree {
+    #[cfg(target_os = "windows")]
+    job: windows_sys::Win32::Foundation::HANDLE,
+}
+
+impl ProcessTree {
+    // Put the command in a new process group before it runs
+    fn prepare(command: &mut Command) {
+        #[cfg(unix)]
+        {
+            use std::os::unix::process::CommandExt;
+            // Only calls setpgid, which is safe between fork and exec
+            unsafe {
+                command.pre_exec(|| {
+                    if libc::setpgid(0, 0) == 0 {
+                        Ok(())
+                    } else {
+                        Err(std::io::Error::last_os_error())
+                    }
+                });
+            }
+        }
+        #[cfg(target_os = "windows")]
+        {
+            use std::os::windows::process::CommandExt;
+            use windows_sys::Win32::System::Threading::{CREATE_NO_WINDOW, CREATE_SUSPENDED};
+            // Suspended until it's in the job, so nothing it starts can get out first; no window, so
+            // console programs don't flash one
+            command.creation_flags(CREATE_NO_WINDOW | CREATE_SUSPENDED);
+        }
+    }
+
+    #[cfg(unix)]
+    fn attach(_child: &Child) -> Result<Self, String> {
+        Ok(ProcessTree {})
+    }
+
+    // Put the suspended process in a new job, then let it run
+    #[cfg(target_os = "windows")]
+    fn attach(child: &Child) -> Result<Self, String> {
+        use std::os::windows::io::AsRawHandle;
+        use windows_sys::Win32::System::JobObjects::{AssignProcessToJobObject, CreateJobObjectW};
+        let job = unsafe { CreateJobObjectW(std::ptr::null(), std::ptr::null()) };
+        if job == 0 {
+            return Err(format!("Failed to create a job: {}", std::io::Error::last_os_error()));
+        }
+        let tree = ProcessTree { job };
+        if unsafe { AssignProcessToJobObject(job, child.as_raw_handle() as _) } == 0 {
+            return Err(format!(
+                "Failed to put process {} in a job: {}",
+                child.id(),
+                std::io::Error::last_os_error()
+            ));
+        }
+        resume(child.id())?;
+        Ok(tree)
+    }
+
+    fn kill(&self, child: &mut Child) {
+        #[cfg(unix)]
+        unsafe {
+            libc::kill(-(child.id() as libc::pid_t), libc::SIGKILL);
+        }
+        #[cfg(target_os = "windows")]
+        {
+            if self.job != 0 {
+                unsafe { windows_sys::Win32::System::JobObjects::TerminateJobObject(self.job, 1) };
+            }
+        }
+        let _ = child.kill();
+    }
+}
+
+// Resume the threads of a process started suspended; std doesn't keep its main thread's handle
+#[cfg(target_os = "windows")]
+fn resume(pid: u32) -> Result<(), String> {
+    use windows_sys::Win32::Foundation::{CloseHandle, INVALID_HANDLE_VALUE};
+    use windows_sys::Win32::System::Diagnostics::ToolHelp::{
+        CreateToolhelp32Snapshot, Thread32First, Thread32Next, TH32CS_SNAPTHREAD, THREADENTRY32,
+    };
+    use windows_sys::Win32::System::Threading::{OpenThread, ResumeThread, THREAD_SUSPEND_RESUME};
+
+    let snapshot = unsafe { CreateToolhelp32Snapshot(TH32CS_SNAPTHREAD, 0) };
+    if snapshot == INVALID_HANDLE_VALUE {
+        return Err(format!("Failed to list threads: {}", std::io::Error::last_os_error()));
+    }
+    let mut entry: THREADENTRY32 = unsafe { std::mem::zeroed() };
+    entry.dwSize = std::mem::size_of::<THREADENTRY32>() as u32;
+    let mut resumed = 0;
+    let mut more = unsafe { Thread32First(snapshot, &mut entry) } != 0;
+    while more {
+        if entry.th32OwnerProcessID == pid {
+            let thread = unsafe { OpenThread(THREAD_SUSPEND_RESUME, 0, entry.th32ThreadID) };
+            if thread != 0 {
+                if unsafe { ResumeThread(thread) } != u32::MAX {
+                    resumed += 1;
+                }
+                unsafe { CloseHandle(thread) };
+            }
+        }
+        more = unsafe { Thread32Next(snapshot, &mut entry) } != 0;
+    }
+    unsafe { CloseHandle(snapshot) };
+    if resumed == 0 {
+        return Err(format!("Failed to resume process {}", pid));
+    }
+    Ok(())
+}
+
+#[cfg(target_os = "windows")]
+impl Drop for ProcessTree {
+    fn drop(&mut self) {
+        if self.job != 0 {
+            unsafe { windows_sys::Win32::Foundation::CloseHandle(self.job) };
+        }
+    }
+}
+
+// Send each line on as it comes and keep it for the result, which is delivered once the pipe closes.
+// Stops at the next line once `done` is set, so nothing is sent for a command that's been reported.
+fn forward(
+    app_handle: &tauri::AppHandle,
+    exec_id: &str,
+    stream: ExecStream,
+    pipe: impl Read + Send + 'static,
+    done: Arc<AtomicBool>,
+) -> Receiver<String> {
+    let app_handle = app_handle.clone();
+    let exec_id = exec_id.to_string();
+    let (sender, receiver) = mpsc::channel();
+    std::thread::spawn(move || {
+        let mut captured = String::new();
+        for line in BufReader::new(pipe).lines().map_while(Result::ok) {
+            if done.load(Ordering::SeqCst) {
+                return;
+            }
+            events::emit(
+                &app_handle,
+                &ExecOutput {
+                    exec_id: &exec_id,
+                    stream,
+                    line: &line,
+                },
+            );
+            if captured.len() + line.len() < MAX_CAPTURED_BYTES {
+                captured.push_str(&line);
+                captured.push('\n');
+            }
+        }
+        let _ = sender.send(captured);
+    });
+    receiver
+}
+
+// Run an allowlisted program, killing it once its timeout is up or it's stopped. Every attempt is
+// audited, refused ones too; `caller` says whether the frontend or the backend asked.
+pub fn run(app_handle: &tauri::AppHandle, request: &ExecRequest, caller: &str) -> Result<ExecResult, String> {
+    let exec_id = request
+        .exec_id
+        .clone()
+        .filter(|id| !id.trim().is_empty())
+        .unwrap_or_else(|| uuid::Uuid::new_v4().to_string());
+    let detail = json!({
+        "exec_id": exec_id,
+        "command": request.command,
+        "args": request.args,
+        "cwd": request.cwd,
+    });
+    // A dry run must not have side effects, so scripts can't reach out through exec either
+    let prepared = if caller == "backend" && automation::is_dry_run(app_handle) {
+        Err("Dry run is on, so commands aren't run".to_string())
+    } else {
+        allowed(app_handle, &request.command)
+    }
+    .and_then(|timeout| Ok((timeout, working_dir(app_handle, request.cwd.as_deref())?)));
+    let (timeout, cwd) = match prepared {
+        Ok(prepared) => prepared,
+        Err(e) => {
+            audit::record(app_handle, "exec", caller, &format!("refused: {}", e), detail);
+            return Err(e);
+        }
+    };
+
+    let state = app_handle.state::<ExecState>();
+    let stop = state.start(&exec_id)?;
+    let result = spawn_and_wait(app_handle, request, caller, &exec_id, timeout, &cwd, &stop);
+    state.finish(&exec_id);
+    match result {
+        Ok(result) => {
+            let outcome = match (result.timed_out, result.stopped, result.exit_code) {
+                (true, _, _) => format!("timed out after {}s", timeout.as_secs()),
+                (_, true, _) => "stopped".to_string(),
+                (_, _, Some(code)) => format!("exited with {}", code),
+                (_, _, None) => "killed".to_string(),
+            };
+            audit::record(app_handle, "exec", caller, &outcome, detail);
+            println!("Exec {} for the {} {}", exec_id, caller, outcome);
+            Ok(result)
+        }
+        Err(e) => {
+            audit::record(app_handle, "exec", caller, &format!("failed: {}", e), detail);
+            Err(e)
+        }
+    }
+}
+
+fn spawn_and_wait(
+    app_handle: &tauri::AppHandle,
+    request: &ExecRequest,
+    caller: &str,
+    exec_id: &str,
+    timeout: Duration,
+    cwd: &Path,
+    stop: &AtomicBool,
+) -> Result<ExecResult, String> {
+    let mut command = Command::new(&request.command);
+    command
+        .args(&request.args)
+        .current_dir(cwd)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped());
+    // The backend's scripts ask the proxy whether their input may go to the app in front
+    if caller == "backend" {
+        command.envs(mcp::env_hints(app_handle));
+    }
+    ProcessTree::prepare(&mut command);
+    let mut child = command
+        .spawn()
+        .map_err(|e| format!("Failed to run {}: {}", request.command, e))?;
+    let tree = match ProcessTree::attach(&child) {
+        Ok(tree) => tree,
+        Err(e) => {
+            let _ = child.kill();
+            let _ = child.wait();
+            return Err(e);
+        }
+    };
+    println!("Running {} (exec {})", request.command, exec_id);
+
+    let done = Arc::new(AtomicBool::new(false));
+    let stdout = child
+        .stdout
+        .take()
+        .map(|pipe| forward(app_handle, exec_id, ExecStream::Stdout, pipe, done.clone()));
+    let stderr = child
+        .stderr
+        .take()
+        .map(|pipe| forward(app_handle, exec_id, ExecStream::Stderr, pipe, done.clone()));
+    let started = Instant::now();
+    let mut exit_code = None;
+    let mut timed_out = false;
+    let mut stopped = false;
+    loop {
+        match child.try_wait() {
+            Ok(Some(exit)) => {
+                exit_code = exit.code();
+                break;
+            }
+            Ok(None) if started.elapsed() >= timeout || stop.load(Ordering::SeqCst) => {
+                timed_out = !stop.load(Ordering::SeqCst);
+                stopped = !timed_out;
+                tree.kill(&mut child);
+                let _ = child.wait();
+                break;
+            }
+            Ok(None) => std::thread::sleep(POLL_INTERVAL),
+            Err(e) => {
+                eprintln!("Failed to wait for exec {}: {}", exec_id, e);
+                tree.kill(&mut child);
+                break;
+            }
+        }
+    }
+    let deadline = Instant::now() + OUTPUT_GRACE;
+    let collect = |reader: Option<Receiver<String>>| {
+        reader
+            .and_then(|reader| reader.recv_timeout(deadline.saturating_duration_since(Instant::now())).ok())
+            .unwrap_or_default()
+    };
+    let result = ExecResult {
+        exec_id: exec_id.to_string(),
+        exit_code,
+        timed_out,
+        stopped,
+        stdout: collect(stdout),
+        stderr: collect(stderr),
+    };
+    // Whatever still holds the pipes open after the grace period isn't reported any more
+    done.store(true, Ordering::SeqCst);
+    Ok(result)
+}
+
+// Command to run an allowlisted program, streaming its output as `exec-output` events
+#[tauri::command(async)]
+pub fn exec_command(
+    app_handle: tauri::AppHandle,
+    cmd: String,
+    args: Option<Vec<String>>,
+    cwd: Option<String>,
+) -> Result<ExecResult, String> {
+    let request = ExecRequest {
+        command: cmd,
+        args: args.unwrap_or_default(),
+        cwd,
+        exec_id: None,
+    };
+    run(&app_handle, &request, "frontend")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bare_names_only_match_bare_names() {
+        assert!(matches("git", "git"));
+        assert!(!matches("git", "/tmp/git"));
+        assert!(!matches("git", "./git"));
+        assert!(!matches("git", "gitk"));
+    }
+
+    #[test]
+    fn paths_only_match_the_same_path() {
+        assert!(matches("/usr/bin/git", "/usr/bin/git"));
+        assert!(!matches("/usr/bin/git", "git"));
+        assert!(!matches("/usr/bin/git", "/tmp/usr/bin/git"));
+    }
+
+    #[test]
+    fn case_only_matters_outside_windows() {
+        assert_eq!(matches("git", "GIT"), cfg!(target_os = "windows"));
+    }
+}
@@ -0,0 +1,257 @@
+use rdev::{EventType, Key};
+use serde::{Deserialize, Serialize};
+use std::sync::{Arc, Mutex};
+use tauri::Manager;
+
+use crate::input::InputHub;
+use crate::quick_actions::QuickActionsState;
+use crate::{active_app, automation, backend, flags, input, storage};
+
+const CONFIG_FILE: &str = "text_expansion.json";
+const LISTENER_NAME: &str = "text-expansion";
+
+// Longest run of typed characters we keep around for matching
+const MAX_BUFFER_LEN: usize = 64;
+
+// What an abbreviation expands into
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum ExpansionTarget {
+    Text { text: String },
+    Snippet { snippet_id: String },
+    // Sent to the backend and replaced by the model's answer
+    Llm { prompt: String },
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Expansion {
+    pub abbreviation: String,
+    #[serde(flatten)]
+    pub target: ExpansionTarget,
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct ExpansionConfig {
+    // Opt-in: the keystroke listener only runs when this is set
+    #[serde(default)]
+    pub enabled: bool,
+    #[serde(default)]
+    pub expansions: Vec<Expansion>,
+    // Applications (by name or executable) where expansion never fires
+    #[serde(default)]
+    pub disabled_apps: Vec<String>,
+}
+
+pub struct ExpansionState {
+    config: Arc<Mutex<ExpansionConfig>>,
+    buffer: Arc<Mutex<String>>,
+}
+
+impl ExpansionState {
+    pub fn load(app_handle: &tauri::AppHandle) -> Self {
+        ExpansionState {
+            config: Arc::new(Mutex::new(storage::load_json(app_handle, CONFIG_FILE))),
+            buffer: Arc::new(Mutex::new(String::new())),
+        }
+    }
+}
+
+// Start or stop the listener to match the saved config
+pub fn apply(app_handle: &tauri::AppHandle) {
+    let state = app_handle.state::<ExpansionState>();
+    let hub = app_handle.state::<InputHub>();
+
+    if !state.config.lock().unwrap().enabled {
+        hub.unsubscribe(LISTENER_NAME);
+        return;
+    }
+
+    let config = state.config.clone();
+    let buffer = state.buffer.clone();
+    let app_handle = app_handle.clone();
+    hub.subscribe(
+        LISTENER_NAME,
+        Box::new(move |event| {
+            if let Some(expansion) = handle_event(event, &config, &buffer) {
+                let app_handle = app_handle.clone();
+                std::thread::spawn(move || expand(&app_handle, &expansion));
+            }
+        }),
+    );
+}
+
+// Track typed characters and return an expansion when an abbreviation was just completed
+fn handle_event(
+    event: &rdev::Event,
+    config: &Mutex<ExpansionConfig>,
+    buffer: &Mutex<String>,
+) -> Option<Expansion> {
+    let mut buffer = buffer.lock().unwrap();
+
+    match event.event_type {
+        EventType::KeyPress(Key::Backspace) => {
+            buffer.pop();
+            return None;
+        }
+        EventType::KeyPress(
+            Key::Return | Key::Tab | Key::Escape | Key::UpArrow | Key::DownArrow | Key::LeftArrow | Key::RightArrow,
+        )
+        | EventType::ButtonPress(_) => {
+            // Cursor moved somewhere else, so earlier keystrokes no longer count
+            buffer.clear();
+            return None;
+        }
+        EventType::KeyPress(_) => {}
+        _ => return None,
+    }
+
+    let typed = event.name.as_ref()?;
+    if typed.chars().any(|c| c.is_control()) {
+        return None;
+    }
+    buffer.push_str(typed);
+
+    if buffer.chars().count() > MAX_BUFFER_LEN {
+        let excess = buffer.chars().count() - MAX_BUFFER_LEN;
+        *buffer = buffer.chars().skip(excess).collect();
+    }
+
+    let config = config.lock().unwrap();
+    let expansion = config
+        .expansions
+        .iter()
+        .find(|e| !e.abbreviation.is_empty() && buffer.ends_with(&e.abbreviation))?
+        .clone();
+
+    if let Some(app) = active_app::frontmost_app() {
+        if config.disabled_apps.iter().any(|pattern| app.matches(pattern)) {
+            return None;
+        }
+    }
+
+    buffer.clear();
+    Some(expansion)
+}
+
+// Replace the typed abbreviation with its expansion
+fn expand(app_handle: &tauri::AppHandle, expansion: &Expansion) {
+    if !flags::is_enabled(app_handle, flags::NATIVE_INPUT_INJECTION) {
+        eprintln!("Skipping expansion '{}': native input injection is disabled", expansion.abbreviation);
+        return;
+    }
+    if automation::is_paused(app_handle) {
+        eprintln!("Skipping expansion '{}': automation is paused", expansion.abbreviation);
+        return;
+    }
+
+    let text = match &expansion.target {
+        ExpansionTarget::Text { text } => Ok(text.clone()),
+        ExpansionTarget::Snippet { snippet_id } => {
+            let state = app_handle.state::<QuickActionsState>();
+            let store = state.0.lock().unwrap();
+            store
+                .snippets
+                .iter()
+                .find(|s| &s.id == snippet_id)
+                .map(|s| s.content.clone())
+                .ok_or_else(|| format!("Snippet not found: {}", snippet_id))
+        }
+        ExpansionTarget::Llm { prompt } => backend::complete(prompt),
+    };
+
+    let result = text.and_then(|text| {
+        input::press_backspace(app_handle, expansion.abbreviation.chars().count())?;
+        input::type_text(app_handle, &text)
+    });
+
+    if let Err(e) = result {
+        eprintln!("Failed to expand '{}': {}", expansion.abbreviation, e);
+    }
+}
+
+fn save(app_handle: &tauri::AppHandle, config: &ExpansionConfig) -> Result<(), String> {
+    storage::save_json(app_handle, CONFIG_FILE, config, storage::Data::Config)
+}
+
+pub fn current_config(app_handle: &tauri::AppHandle) -> ExpansionConfig {
+    app_handle.state::<ExpansionState>().config.lock().unwrap().clone()
+}
+
+// Replace the whole expansion config (used when importing settings)
+pub fn replace_config(app_handle: &tauri::AppHandle, config: ExpansionConfig) -> Result<(), String> {
+    {
+        let state = app_handle.state::<ExpansionState>();
+        let mut current = state.config.lock().unwrap();
+        save(app_handle, &config)?;
+        *current = config;
+    }
+
+    apply(app_handle);
+    Ok(())
+}
+
+#[tauri::command]
+pub fn get_text_expansion_config(state: tauri::State<ExpansionState>) -> ExpansionConfig {
+    state.config.lock().unwrap().clone()
+}
+
+#[tauri::command]
+pub fn set_text_expansion_enabled(
+    app_handle: tauri::AppHandle,
+    state: tauri::State<ExpansionState>,
+    enabled: bool,
+) -> Result<(), String> {
+    {
+        let mut config = state.config.lock().unwrap();
+        config.enabled = enabled;
+        save(&app_handle, &config)?;
+    }
+
+    apply(&app_handle);
+    Ok(())
+}
+
+// Create an expansion, or replace the one with the same abbreviation
+#[tauri::command]
+pub fn save_expansion(
+    app_handle: tauri::AppHandle,
+    state: tauri::State<ExpansionState>,
+    expansion: Expansion,
+) -> Result<(), String> {
+    if expansion.abbreviation.trim().is_empty() {
+        return Err("Abbreviation cannot be empty".to_string());
+    }
+
+    let mut config = state.config.lock().unwrap();
+    match config
+        .expansions
+        .iter_mut()
+        .find(|e| e.abbreviation == expansion.abbreviation)
+    {
+        Some(existing) => *existing = expansion,
+        None => config.expansions.push(expansion),
+    }
+    save(&app_handle, &config)
+}
+
+#[tauri::command]
+pub fn delete_expansion(
+    app_handle: tauri::AppHandle,
+    state: tauri::State<ExpansionState>,
+    abbreviation: String,
+) -> Result<(), String> {
+    let mut config = state.config.lock().unwrap();
+    config.expansions.retain(|e| e.abbreviation != abbreviation);
+    save(&app_handle, &config)
+}
+
+#[tauri::command]
+pub fn set_expansion_disabled_apps(
+    app_handle: tauri::AppHandle,
+    state: tauri::State<ExpansionState>,
+    apps: Vec<String>,
+) -> Result<(), String> {
+    let mut config = state.config.lock().unwrap();
+    config.disabled_apps = apps;
+    save(&app_handle, &config)
+}
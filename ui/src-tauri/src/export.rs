@@ -0,0 +1,194 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::path::PathBuf;
+use tauri::Manager;
+use tauri_plugin_dialog::DialogExt;
+
+use crate::history::HistoryState;
+use crate::protocol::{self, JobParams};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ExportFormat {
+    Markdown,
+    Html,
+    Json,
+}
+
+impl ExportFormat {
+    pub fn parse(format: &str) -> Result<Self, String> {
+        match format.to_lowercase().as_str() {
+            "markdown" | "md" => Ok(ExportFormat::Markdown),
+            "html" | "htm" => Ok(ExportFormat::Html),
+            "json" => Ok(ExportFormat::Json),
+            other => Err(format!("Unsupported export format: {}", other)),
+        }
+    }
+
+    fn extension(self) -> &'static str {
+        match self {
+            ExportFormat::Markdown => "md",
+            ExportFormat::Html => "html",
+            ExportFormat::Json => "json",
+        }
+    }
+
+    fn filter_name(self) -> &'static str {
+        match self {
+            ExportFormat::Markdown => "Markdown",
+            ExportFormat::Html => "HTML",
+            ExportFormat::Json => "JSON",
+        }
+    }
+}
+
+// Everything known about a job, from the backend if it still has it, otherwise from history
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct JobReport {
+    pub job_id: String,
+    pub prompt: String,
+    pub status: String,
+    pub start_time: Option<String>,
+    pub code: Option<String>,
+    pub result: Option<String>,
+    pub logs: Vec<Value>,
+}
+
+fn load_report(app_handle: &tauri::AppHandle, job_id: &str) -> Result<JobReport, String> {
+    let backend_job = protocol::call::<protocol::GetJob>(&JobParams {
+        job_id: job_id.to_string(),
+    });
+
+    let history_item = app_handle.state::<HistoryState>().find_by_job(job_id)?;
+
+    match (backend_job, history_item) {
+        (Ok(job), history_item) => Ok(JobReport {
+            job_id: job.job_id,
+            prompt: job.prompt.unwrap_or_default(),
+            status: job.status.unwrap_or_default(),
+            start_time: job.start_time,
+            code: job.code,
+            // The shell may have recorded a friendlier result than the raw execution output
+            result: history_item.and_then(|item| item.result).or(job.last_result),
+            logs: job.logs,
+        }),
+        (Err(_), Some(item)) => Ok(JobReport {
+            job_id: job_id.to_string(),
+            prompt: item.prompt,
+            status: item.status,
+            start_time: Some(item.created_at.to_string()),
+            code: None,
+            result: item.result,
+            logs: Vec::new(),
+        }),
+        (Err(e), None) => Err(format!("Job {} not found: {}", job_id, e)),
+    }
+}
+
+fn log_line(log: &Value) -> (String, String, String) {
+    let field = |key: &str| log.get(key).and_then(|v| v.as_str()).unwrap_or_default().to_string();
+    (field("timestamp"), field("level"), field("message"))
+}
+
+fn render_markdown(report: &JobReport) -> String {
+    let mut out = format!("# Job {}\n\n", report.job_id);
+    out.push_str(&format!("**Prompt:** {}\n\n", report.prompt));
+    out.push_str(&format!("**Status:** {}\n\n", report.status));
+    if let Some(start_time) = &report.start_time {
+        out.push_str(&format!("**Started:** {}\n\n", start_time));
+    }
+    if let Some(result) = &report.result {
+        out.push_str(&format!("## Result\n\n```\n{}\n```\n\n", result.trim_end()));
+    }
+    if let Some(code) = &report.code {
+        out.push_str(&format!("## Generated code\n\n```python\n{}\n```\n\n", code.trim_end()));
+    }
+    if !report.logs.is_empty() {
+        out.push_str("## Logs\n\n");
+        for log in &report.logs {
+            let (timestamp, level, message) = log_line(log);
+            out.push_str(&format!("- `{}` **{}** {}\n", timestamp, level, message));
+        }
+    }
+    out
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn render_html(report: &JobReport) -> String {
+    let mut body = format!("<h1>Job {}</h1>\n", escape_html(&report.job_id));
+    body.push_str(&format!("<p><strong>Prompt:</strong> {}</p>\n", escape_html(&report.prompt)));
+    body.push_str(&format!("<p><strong>Status:</strong> {}</p>\n", escape_html(&report.status)));
+    if let Some(start_time) = &report.start_time {
+        body.push_str(&format!("<p><strong>Started:</strong> {}</p>\n", escape_html(start_time)));
+    }
+    if let Some(result) = &report.result {
+        body.push_str(&format!("<h2>Result</h2>\n<pre>{}</pre>\n", escape_html(result)));
+    }
+    if let Some(code) = &report.code {
+        body.push_str(&format!("<h2>Generated code</h2>\n<pre><code>{}</code></pre>\n", escape_html(code)));
+    }
+    if !report.logs.is_empty() {
+        body.push_str("<h2>Logs</h2>\n<table>\n");
+        for log in &report.logs {
+            let (timestamp, level, message) = log_line(log);
+            body.push_str(&format!(
+                "<tr><td>{}</td><td>{}</td><td>{}</td></tr>\n",
+                escape_html(&timestamp),
+                escape_html(&level),
+                escape_html(&message)
+            ));
+        }
+        body.push_str("</table>\n");
+    }
+
+    format!(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>Job {}</title>\n<style>\nbody {{ font-family: -apple-system, sans-serif; max-width: 900px; margin: 2em auto; }}\npre {{ background: #f4f4f4; padding: 1em; overflow-x: auto; }}\ntd {{ padding: 0.2em 0.6em; vertical-align: top; }}\n</style>\n</head>\n<body>\n{}</body>\n</html>\n",
+        escape_html(&report.job_id),
+        body
+    )
+}
+
+pub fn render(report: &JobReport, format: ExportFormat) -> Result<String, String> {
+    match format {
+        ExportFormat::Markdown => Ok(render_markdown(report)),
+        ExportFormat::Html => Ok(render_html(report)),
+        ExportFormat::Json => serde_json::to_string_pretty(report).map_err(|e| format!("Failed to serialize report: {}", e)),
+    }
+}
+
+// Command to save a job's report; without a path the native save dialog is shown.
+// Returns the saved path, or None if the user cancelled the dialog.
+#[tauri::command(async)]
+pub fn export_result(
+    app_handle: tauri::AppHandle,
+    job_id: String,
+    format: Option<String>,
+    path: Option<String>,
+) -> Result<Option<String>, String> {
+    let format = ExportFormat::parse(format.as_deref().unwrap_or("markdown"))?;
+    let report = load_report(&app_handle, &job_id)?;
+    let contents = render(&report, format)?;
+
+    let path = match path {
+        Some(path) => PathBuf::from(path),
+        None => {
+            let file_name = format!("krya-job-{}.{}", job_id, format.extension());
+            match app_handle.dialog().file()
+                .set_file_name(&file_name)
+                .add_filter(format.filter_name(), &[format.extension()])
+                .blocking_save_file()
+            {
+                Some(path) => path.into_path().map_err(|e| e.to_string())?,
+                None => return Ok(None),
+            }
+        }
+    };
+
+    std::fs::write(&path, contents).map_err(|e| format!("Failed to write {:?}: {}", path, e))?;
+    Ok(Some(path.to_string_lossy().to_string()))
+}
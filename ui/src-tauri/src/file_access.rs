@@ -0,0 +1,280 @@
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Component, Path, PathBuf};
+
+use crate::{audit, scopes, settings};
+
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FileOperation {
+    Read,
+    Write,
+    Append,
+}
+
+impl FileOperation {
+    fn action(&self) -> &'static str {
+        match self {
+            FileOperation::Read => "read_file",
+            FileOperation::Write => "write_file",
+            FileOperation::Append => "append_file",
+        }
+    }
+}
+
+// What the backend sends to /files; `contents` is left out for reads
+#[derive(Clone, Debug, Deserialize)]
+pub struct FileRequest {
+    pub operation: FileOperation,
+    pub path: String,
+    #[serde(default)]
+    pub contents: Option<String>,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct FileResult {
+    // Where it really is, after `~` and symlinks
+    pub path: String,
+    pub bytes: u64,
+    // Only for reads
+    pub contents: Option<String>,
+}
+
+fn max_bytes(app_handle: &tauri::AppHandle) -> u64 {
+    settings::current(app_handle).permissions.max_file_mb * 1024 * 1024
+}
+
+// A file to write that may not exist yet: its folder has to pass the scope checks, and so does the
+// file itself if it's there already. Symlinks are refused rather than followed. Also says whether the
+// file exists.
+fn writable_path(path: &Path, check: &impl Fn(&Path) -> Result<PathBuf, String>) -> Result<(PathBuf, bool), String> {
+    let name = match path.components().next_back() {
+        Some(Component::Normal(name)) => name.to_owned(),
+        _ => return Err(format!("{} isn't a file name", path.display())),
+    };
+    let parent = path.parent().filter(|parent| !parent.as_os_str().is_empty()).unwrap_or(Path::new("."));
+    let target = check(parent)?.join(name);
+    match fs::symlink_metadata(&target) {
+        Ok(meta) if meta.file_type().is_symlink() => Err(format!("{} is a symlink", target.display())),
+        Ok(_) => Ok((check(&target)?, true)),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok((target, false)),
+        Err(e) => Err(format!("Cannot access {}: {}", target.display(), e)),
+    }
+}
+
+// Open without following a symlink put there since the path was checked. A new file is only ever
+// created here, never opened, so one that turns up meanwhile is refused instead of written to.
+fn open_nofollow(path: &Path, append: bool, create: bool) -> std::io::Result<fs::File> {
+    let mut options = OpenOptions::new();
+    options.create_new(create).write(true).append(append);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.custom_flags(libc::O_NOFOLLOW);
+    }
+    #[cfg(target_os = "windows")]
+    {
+        use std::os::windows::fs::OpenOptionsExt;
+        // FILE_FLAG_OPEN_REPARSE_POINT: open the link itself, which the check below then refuses
+        options.custom_flags(0x0020_0000);
+    }
+    options.open(path)
+}
+
+fn read(app_handle: &tauri::AppHandle, path: &str) -> Result<FileResult, String> {
+    let path = scopes::check_path(app_handle, path)?;
+    let size = fs::metadata(&path).map_err(|e| format!("Cannot access {}: {}", path.display(), e))?.len();
+    let limit = max_bytes(app_handle);
+    if size > limit {
+        return Err(format!("{} is larger than the {} MB limit", path.display(), limit / 1024 / 1024));
+    }
+    let contents = fs::read_to_string(&path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    Ok(FileResult {
+        path: path.display().to_string(),
+        bytes: size,
+        contents: Some(contents),
+    })
+}
+
+fn write(app_handle: &tauri::AppHandle, path: &str, contents: &str, append: bool) -> Result<FileResult, String> {
+    let check = |path: &Path| scopes::check_path(app_handle, &path.to_string_lossy());
+    write_checked(&settings::expand_home(path), contents, append, max_bytes(app_handle), check)
+}
+
+// Write a file whose path and every folder on the way `check` resolves and allows
+fn write_checked(
+    path: &Path,
+    contents: &str,
+    append: bool,
+    limit: u64,
+    check: impl Fn(&Path) -> Result<PathBuf, String>,
+) -> Result<FileResult, String> {
+    let (path, exists) = writable_path(path, &check)?;
+    let file = open_nofollow(&path, append, !exists).map_err(|e| format!("Failed to open {}: {}", path.display(), e))?;
+    let written = write_opened(file, &path, contents, append, limit, &check);
+    if written.is_err() && !exists {
+        // Made by the open above, possibly somewhere it mustn't be; nothing else can have written it
+        let _ = fs::remove_file(&path);
+    }
+    written
+}
+
+fn write_opened(
+    mut file: fs::File,
+    path: &Path,
+    contents: &str,
+    append: bool,
+    limit: u64,
+    check: &impl Fn(&Path) -> Result<PathBuf, String>,
+) -> Result<FileResult, String> {
+    // A folder on the way may have been swapped for a link meanwhile; nothing is changed until
+    // where the file really is has passed the checks again
+    let opened = check(path)?;
+    if opened != path || fs::symlink_metadata(path).is_ok_and(|meta| meta.file_type().is_symlink()) {
+        return Err(format!("{} moved while it was being opened", path.display()));
+    }
+    let existing = if append { file.metadata().map_or(0, |meta| meta.len()) } else { 0 };
+    let size = existing + contents.len() as u64;
+    if size > limit {
+        return Err(format!("{} would be larger than the {} MB limit", path.display(), limit / 1024 / 1024));
+    }
+    if !append {
+        file.set_len(0).map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+    }
+    file.write_all(contents.as_bytes())
+        .map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+    Ok(FileResult {
+        path: path.display().to_string(),
+        bytes: size,
+        contents: None,
+    })
+}
+
+// Read, write or append to a file inside the folders the user allowed. Every attempt is audited with
+// its path and size, never its contents; `caller` says whether the frontend or the backend asked.
+pub fn run(app_handle: &tauri::AppHandle, request: &FileRequest, caller: &str) -> Result<FileResult, String> {
+    let contents = request.contents.as_deref().unwrap_or_default();
+    let result = match request.operation {
+        FileOperation::Read => read(app_handle, &request.path),
+        FileOperation::Write => write(app_handle, &request.path, contents, false),
+        FileOperation::Append => write(app_handle, &request.path, contents, true),
+    };
+    let (outcome, detail) = match &result {
+        Ok(file) => ("done".to_string(), json!({ "path": file.path, "bytes": file.bytes })),
+        Err(e) => (format!("refused: {}", e), json!({ "path": request.path })),
+    };
+    audit::record(app_handle, request.operation.action(), caller, &outcome, detail);
+    result
+}
+
+fn run_for_frontend(
+    app_handle: &tauri::AppHandle,
+    operation: FileOperation,
+    path: String,
+    contents: Option<String>,
+) -> Result<FileResult, String> {
+    let request = FileRequest {
+        operation,
+        path,
+        contents,
+    };
+    run(app_handle, &request, "frontend")
+}
+
+#[tauri::command(async)]
+pub fn read_file(app_handle: tauri::AppHandle, path: String) -> Result<FileResult, String> {
+    run_for_frontend(&app_handle, FileOperation::Read, path, None)
+}
+
+// Command to replace a file's contents, creating it if needed; its folder has to exist
+#[tauri::command(async)]
+pub fn write_file(app_handle: tauri::AppHandle, path: String, contents: String) -> Result<FileResult, String> {
+    run_for_frontend(&app_handle, FileOperation::Write, path, Some(contents))
+}
+
+#[tauri::command(async)]
+pub fn append_file(app_handle: tauri::AppHandle, path: String, contents: String) -> Result<FileResult, String> {
+    run_for_frontend(&app_handle, FileOperation::Append, path, Some(contents))
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use std::cell::Cell;
+    use std::os::unix::fs::symlink;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("krya-files-{}-{}", std::process::id(), name));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(dir.join("allowed")).unwrap();
+        fs::create_dir_all(dir.join("outside")).unwrap();
+        dir.canonicalize().unwrap()
+    }
+
+    // Allows what resolves to inside `root`, as the scope checks do
+    fn inside(root: &Path) -> impl Fn(&Path) -> Result<PathBuf, String> + '_ {
+        move |path: &Path| {
+            let resolved = match path.canonicalize() {
+                Ok(resolved) => resolved,
+                Err(_) => path.parent().unwrap().canonicalize().unwrap().join(path.file_name().unwrap()),
+            };
+            if resolved.starts_with(root) {
+                Ok(resolved)
+            } else {
+                Err(format!("{} is outside {}", resolved.display(), root.display()))
+            }
+        }
+    }
+
+    #[test]
+    fn writes_inside_the_allowed_folder() {
+        let dir = temp_dir("inside");
+        let root = dir.join("allowed");
+        let result = write_checked(&root.join("notes.txt"), "hello", false, 1024, inside(&root)).unwrap();
+        assert_eq!(result.bytes, 5);
+        assert_eq!(fs::read_to_string(root.join("notes.txt")).unwrap(), "hello");
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn symlinked_parent_creates_no_file() {
+        let dir = temp_dir("symlinked");
+        let root = dir.join("allowed");
+        symlink(dir.join("outside"), root.join("link")).unwrap();
+        assert!(write_checked(&root.join("link").join("new.txt"), "hello", false, 1024, inside(&root)).is_err());
+        assert!(!dir.join("outside").join("new.txt").exists());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn parent_swapped_after_the_check_leaves_no_file() {
+        let dir = temp_dir("swapped");
+        let root = dir.join("allowed");
+        fs::create_dir(root.join("sub")).unwrap();
+        // Once the folder has passed, it's replaced by a link out of the allowed one
+        let checks = Cell::new(0);
+        let check = |path: &Path| {
+            let result = inside(&root)(path);
+            checks.set(checks.get() + 1);
+            if checks.get() == 1 {
+                fs::remove_dir(root.join("sub")).unwrap();
+                symlink(dir.join("outside"), root.join("sub")).unwrap();
+            }
+            result
+        };
+        assert!(write_checked(&root.join("sub").join("new.txt"), "hello", false, 1024, check).is_err());
+        assert!(!dir.join("outside").join("new.txt").exists());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn refused_new_file_is_removed() {
+        let dir = temp_dir("too-large");
+        let root = dir.join("allowed");
+        assert!(write_checked(&root.join("big.txt"), "hello", false, 2, inside(&root)).is_err());
+        assert!(!root.join("big.txt").exists());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,207 @@
+use fuzzy_matcher::skim::SkimMatcherV2;
+use fuzzy_matcher::FuzzyMatcher;
+use serde::Serialize;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, RecvTimeoutError, Sender};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, UNIX_EPOCH};
+use walkdir::WalkDir;
+
+use crate::idle;
+use crate::settings::{self, FileIndexSettings};
+
+// How often the index is rebuilt when nothing asks for it sooner
+const REINDEX_INTERVAL: Duration = Duration::from_secs(30 * 60);
+
+#[derive(Clone, Debug, Serialize)]
+pub struct IndexedFile {
+    pub name: String,
+    pub path: String,
+    // Last modification time in seconds since the Unix epoch
+    pub modified: u64,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct FileMatch {
+    #[serde(flatten)]
+    pub file: IndexedFile,
+    pub score: i64,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct FileIndexStatus {
+    pub indexing: bool,
+    pub file_count: usize,
+}
+
+#[derive(Default)]
+pub struct FileIndex {
+    files: Arc<Mutex<Vec<IndexedFile>>>,
+    indexing: Arc<AtomicBool>,
+    reindex_tx: Mutex<Option<Sender<()>>>,
+}
+
+impl FileIndex {
+    // Start the background indexer thread
+    pub fn start(&self, app_handle: &tauri::AppHandle) {
+        let (tx, rx) = mpsc::channel();
+        *self.reindex_tx.lock().unwrap() = Some(tx);
+
+        let files = self.files.clone();
+        let indexing = self.indexing.clone();
+        let app_handle = app_handle.clone();
+        std::thread::spawn(move || loop {
+            // The first index is built right away, since search needs one; refreshes wait until the user is away
+            let refresh = !files.lock().unwrap().is_empty();
+            if refresh {
+                idle::wait_until_idle(&app_handle);
+            }
+            let config = settings::current(&app_handle).file_index;
+            if config.enabled {
+                indexing.store(true, Ordering::SeqCst);
+                let found = scan(&config, || {
+                    if refresh {
+                        idle::wait_until_idle(&app_handle);
+                    }
+                });
+                println!("Indexed {} files", found.len());
+                *files.lock().unwrap() = found;
+                indexing.store(false, Ordering::SeqCst);
+            } else {
+                files.lock().unwrap().clear();
+            }
+
+            match rx.recv_timeout(REINDEX_INTERVAL) {
+                Ok(()) | Err(RecvTimeoutError::Timeout) => {}
+                Err(RecvTimeoutError::Disconnected) => break,
+            }
+        });
+    }
+
+    // Ask the indexer to rebuild now (e.g. after include/exclude paths change)
+    pub fn request_reindex(&self) {
+        if let Some(tx) = self.reindex_tx.lock().unwrap().as_ref() {
+            let _ = tx.send(());
+        }
+    }
+
+    pub fn search(&self, query: &str, limit: usize) -> Vec<FileMatch> {
+        let matcher = SkimMatcherV2::default();
+        let files = self.files.lock().unwrap();
+
+        let mut matches: Vec<FileMatch> = files
+            .iter()
+            .filter_map(|file| {
+                // Prefer matches on the file name, fall back to the full path
+                let score = matcher
+                    .fuzzy_match(&file.name, query)
+                    .or_else(|| matcher.fuzzy_match(&file.path, query).map(|s| s / 2))?;
+                Some(FileMatch {
+                    file: file.clone(),
+                    score,
+                })
+            })
+            .collect();
+
+        matches.sort_by(|a, b| {
+            b.score
+                .cmp(&a.score)
+                .then_with(|| b.file.modified.cmp(&a.file.modified))
+        });
+        matches.truncate(limit);
+        matches
+    }
+
+    // Run `f` over the indexed files without copying them
+    pub fn with_files<R>(&self, f: impl FnOnce(&[IndexedFile]) -> R) -> R {
+        f(&self.files.lock().unwrap())
+    }
+
+    pub fn status(&self) -> FileIndexStatus {
+        FileIndexStatus {
+            indexing: self.indexing.load(Ordering::SeqCst),
+            file_count: self.files.lock().unwrap().len(),
+        }
+    }
+}
+
+pub fn is_excluded(path: &Path, config: &FileIndexSettings) -> bool {
+    let name = path
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_default();
+
+    // Hidden files and folders are never indexed
+    if name.starts_with('.') {
+        return true;
+    }
+
+    config.exclude_paths.iter().any(|exclude| {
+        if exclude.contains('/') || exclude.contains('\\') || exclude.starts_with('~') {
+            path.starts_with(settings::expand_home(exclude))
+        } else {
+            name == *exclude
+        }
+    })
+}
+
+// `pause` is called before each file, to hold the scan while it shouldn't run
+fn scan(config: &FileIndexSettings, pause: impl Fn()) -> Vec<IndexedFile> {
+    let mut files = Vec::new();
+
+    for include in &config.include_paths {
+        let root = settings::expand_home(include);
+        if !root.exists() {
+            continue;
+        }
+
+        let walker = WalkDir::new(&root)
+            .into_iter()
+            .filter_entry(|entry| entry.depth() == 0 || !is_excluded(entry.path(), config));
+
+        for entry in walker.flatten() {
+            if !entry.file_type().is_file() {
+                continue;
+            }
+            pause();
+
+            let modified = entry
+                .metadata()
+                .ok()
+                .and_then(|m| m.modified().ok())
+                .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+                .map(|d| d.as_secs())
+                .unwrap_or(0);
+
+            files.push(IndexedFile {
+                name: entry.file_name().to_string_lossy().to_string(),
+                path: entry.path().to_string_lossy().to_string(),
+                modified,
+            });
+
+            if files.len() >= config.max_files {
+                println!("File index reached the limit of {} files", config.max_files);
+                return files;
+            }
+        }
+    }
+
+    files
+}
+
+// Command to fuzzy-search the local file index
+#[tauri::command]
+pub fn search_files(index: tauri::State<FileIndex>, query: String, limit: Option<usize>) -> Vec<FileMatch> {
+    index.search(&query, limit.unwrap_or(20))
+}
+
+#[tauri::command]
+pub fn reindex_files(index: tauri::State<FileIndex>) {
+    index.request_reindex();
+}
+
+#[tauri::command]
+pub fn get_file_index_status(index: tauri::State<FileIndex>) -> FileIndexStatus {
+    index.status()
+}
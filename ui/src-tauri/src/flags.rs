@@ -0,0 +1,103 @@
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Mutex;
+use std::time::Duration;
+use tauri::{Emitter, Manager};
+
+use crate::{settings, storage, tls};
+
+const MANIFEST_FILE: &str = "flags_manifest.json";
+
+pub const NATIVE_INPUT_INJECTION: &str = "native_input_injection";
+pub const WAKE_WORD: &str = "wake_word";
+
+// Every known flag with its built-in default. Unknown names from settings or the manifest are ignored.
+const FLAGS: &[(&str, bool)] = &[
+    // Typing into other apps via enigo (text expansion)
+    (NATIVE_INPUT_INJECTION, true),
+    // Voice activation
+    (WAKE_WORD, false),
+];
+
+// Last manifest fetched from the remote URL, cached so flags survive offline launches
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct FlagManifest {
+    #[serde(default)]
+    pub flags: HashMap<String, bool>,
+}
+
+pub struct FlagsState(pub Mutex<FlagManifest>);
+
+impl FlagsState {
+    pub fn load(app_handle: &tauri::AppHandle) -> Self {
+        FlagsState(Mutex::new(storage::load_json(app_handle, MANIFEST_FILE)))
+    }
+}
+
+// Resolve every flag: local override, then remote manifest, then built-in default
+pub fn resolve(app_handle: &tauri::AppHandle) -> BTreeMap<String, bool> {
+    let overrides = settings::current(app_handle).flags.overrides;
+    let state = app_handle.state::<FlagsState>();
+    let manifest = state.0.lock().unwrap();
+
+    FLAGS
+        .iter()
+        .map(|(name, default)| {
+            let enabled = overrides
+                .get(*name)
+                .or_else(|| manifest.flags.get(*name))
+                .copied()
+                .unwrap_or(*default);
+            (name.to_string(), enabled)
+        })
+        .collect()
+}
+
+pub fn is_enabled(app_handle: &tauri::AppHandle, name: &str) -> bool {
+    resolve(app_handle).get(name).copied().unwrap_or(false)
+}
+
+// Tell the frontend to re-read its flags
+pub fn emit_changed(app_handle: &tauri::AppHandle) {
+    if let Err(e) = app_handle.emit("flags-changed", resolve(app_handle)) {
+        eprintln!("Failed to emit flags change: {}", e);
+    }
+}
+
+fn fetch_manifest(url: &str) -> Result<FlagManifest, String> {
+    tls::client_builder()
+        .timeout(Duration::from_secs(10))
+        .build()
+        .map_err(|e| format!("Failed to create HTTP client: {}", e))?
+        .get(url)
+        .send()
+        .and_then(|r| r.error_for_status())
+        .map_err(|e| format!("Failed to fetch flag manifest: {}", e))?
+        .json()
+        .map_err(|e| format!("Failed to parse flag manifest: {}", e))
+}
+
+// Fetch the remote manifest in the background, if one is configured
+pub fn refresh_manifest(app_handle: &tauri::AppHandle) {
+    let url = match settings::current(app_handle).flags.manifest_url {
+        Some(url) if !url.trim().is_empty() => url,
+        _ => return,
+    };
+
+    let app_handle = app_handle.clone();
+    std::thread::spawn(move || match fetch_manifest(&url) {
+        Ok(manifest) => {
+            if let Err(e) = storage::save_json(&app_handle, MANIFEST_FILE, &manifest, storage::Data::Config) {
+                eprintln!("{}", e);
+            }
+            *app_handle.state::<FlagsState>().0.lock().unwrap() = manifest;
+            emit_changed(&app_handle);
+        }
+        Err(e) => eprintln!("{}", e),
+    });
+}
+
+#[tauri::command]
+pub fn get_flags(app_handle: tauri::AppHandle) -> BTreeMap<String, bool> {
+    resolve(&app_handle)
+}
@@ -0,0 +1,355 @@
+use frizbee::{Config, Matcher};
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use std::cmp::{Ordering, Reverse};
+use std::collections::{BinaryHeap, HashSet};
+use tauri::Manager;
+
+use crate::apps::AppIndex;
+use crate::files::FileIndex;
+use crate::history::HistoryState;
+use crate::quick_actions::QuickActionsState;
+
+// Past this the suggestion list is too long to read anyway
+const MAX_LIMIT: usize = 500;
+
+// Older prompts are still found by search_history
+const MAX_HISTORY: usize = 5_000;
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MatchSource {
+    History,
+    QuickActions,
+    Apps,
+    Files,
+}
+
+const ALL_SOURCES: &[MatchSource] = &[
+    MatchSource::QuickActions,
+    MatchSource::Apps,
+    MatchSource::History,
+    MatchSource::Files,
+];
+
+#[derive(Clone, Debug, Serialize)]
+pub struct FuzzyMatch {
+    // None for candidates passed in by the caller
+    pub source: Option<MatchSource>,
+    // The candidate's index for passed-in candidates, else the history id, action id, app path or file path
+    pub id: String,
+    pub text: String,
+    pub score: i64,
+    // Character positions in `text` that matched, for highlighting
+    pub indices: Vec<usize>,
+}
+
+struct Scored {
+    score: i64,
+    // Earlier candidates win ties, so results don't shuffle between keystrokes
+    order: usize,
+    source: Option<MatchSource>,
+    id: String,
+    text: String,
+}
+
+impl PartialEq for Scored {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Scored {}
+
+impl PartialOrd for Scored {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Scored {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.score.cmp(&other.score).then_with(|| other.order.cmp(&self.order))
+    }
+}
+
+// Something to match, with a weaker text to try when its own doesn't match (a quick action's prompt
+// rather than its title, a file's path rather than its name)
+struct Candidate<'a> {
+    id: Cow<'a, str>,
+    text: &'a str,
+    fallback: Option<&'a str>,
+}
+
+// Matches on the fallback text rank lower
+const FALLBACK_DIVISOR: i64 = 2;
+
+// Keeps the best `limit` candidates offered to it without sorting all of them.
+// Candidates are scored a source at a time, so the SIMD matcher gets whole lists to work through.
+struct Ranker {
+    matcher: Matcher,
+    best: BinaryHeap<Reverse<Scored>>,
+    limit: usize,
+    offered: usize,
+}
+
+impl Ranker {
+    fn new(query: &str, limit: usize) -> Self {
+        Ranker {
+            matcher: Matcher::new(query, &Config::default()),
+            best: BinaryHeap::with_capacity(limit + 1),
+            limit,
+            offered: 0,
+        }
+    }
+
+    // Score candidates in one pass over their texts, then one over the fallbacks of those that didn't match
+    fn offer(&mut self, source: Option<MatchSource>, candidates: &[Candidate]) {
+        let texts: Vec<&str> = candidates.iter().map(|candidate| candidate.text).collect();
+        let mut scores: Vec<Option<(i64, &str)>> = vec![None; candidates.len()];
+        for matched in self.matcher.match_list(&texts) {
+            let index = matched.index as usize;
+            scores[index] = Some((matched.score as i64, candidates[index].text));
+        }
+
+        let fallbacks: Vec<(usize, &str)> = candidates
+            .iter()
+            .enumerate()
+            .filter(|(index, _)| scores[*index].is_none())
+            .filter_map(|(index, candidate)| candidate.fallback.map(|fallback| (index, fallback)))
+            .collect();
+        if !fallbacks.is_empty() {
+            let texts: Vec<&str> = fallbacks.iter().map(|(_, text)| *text).collect();
+            for matched in self.matcher.match_list(&texts) {
+                let (index, text) = fallbacks[matched.index as usize];
+                scores[index] = Some((matched.score as i64 / FALLBACK_DIVISOR, text));
+            }
+        }
+
+        for (candidate, score) in candidates.iter().zip(scores) {
+            self.offered += 1;
+            if let Some((score, text)) = score {
+                self.keep(score, source, &candidate.id, text);
+            }
+        }
+    }
+
+    fn keep(&mut self, score: i64, source: Option<MatchSource>, id: &str, text: &str) {
+        if self.best.len() == self.limit {
+            match self.best.peek() {
+                Some(Reverse(worst)) if worst.score >= score => return,
+                _ => {}
+            }
+        }
+        self.best.push(Reverse(Scored {
+            score,
+            order: self.offered,
+            source,
+            id: id.to_string(),
+            text: text.to_string(),
+        }));
+        if self.best.len() > self.limit {
+            self.best.pop();
+        }
+    }
+
+    // The best matches, highest score first, with the positions to highlight
+    fn finish(mut self) -> Vec<FuzzyMatch> {
+        let mut best: Vec<Scored> = self.best.into_iter().map(|Reverse(scored)| scored).collect();
+        best.sort_by(|a, b| b.cmp(a));
+
+        // The matcher gives byte offsets; the UI highlights characters
+        let texts: Vec<&str> = best.iter().map(|scored| scored.text.as_str()).collect();
+        let mut indices: Vec<Vec<usize>> = vec![Vec::new(); best.len()];
+        for matched in self.matcher.match_list_indices(&texts) {
+            let index = matched.index as usize;
+            let bytes: HashSet<usize> = matched.indices.iter().map(|&byte| byte as usize).collect();
+            indices[index] = texts[index]
+                .char_indices()
+                .enumerate()
+                .filter(|(_, (byte, _))| bytes.contains(byte))
+                .map(|(position, _)| position)
+                .collect();
+        }
+
+        best.into_iter()
+            .zip(indices)
+            .map(|(scored, indices)| FuzzyMatch {
+                indices,
+                source: scored.source,
+                id: scored.id,
+                text: scored.text,
+                score: scored.score,
+            })
+            .collect()
+    }
+}
+
+fn offer_source(app_handle: &tauri::AppHandle, ranker: &mut Ranker, source: MatchSource) -> Result<(), String> {
+    match source {
+        MatchSource::History => {
+            let items = app_handle.state::<HistoryState>().page(0, MAX_HISTORY)?.items;
+            let candidates: Vec<Candidate> = items
+                .iter()
+                .map(|item| Candidate {
+                    id: Cow::Owned(item.id.to_string()),
+                    text: &item.prompt,
+                    fallback: None,
+                })
+                .collect();
+            ranker.offer(Some(source), &candidates);
+        }
+        MatchSource::QuickActions => {
+            let state = app_handle.state::<QuickActionsState>();
+            let store = state.0.lock().unwrap();
+            let candidates: Vec<Candidate> = store
+                .quick_actions
+                .iter()
+                .map(|action| Candidate {
+                    id: Cow::Borrowed(&action.id),
+                    text: &action.title,
+                    fallback: Some(&action.prompt),
+                })
+                .collect();
+            ranker.offer(Some(source), &candidates);
+        }
+        MatchSource::Apps => app_handle.state::<AppIndex>().with_apps(|apps| {
+            let candidates: Vec<Candidate> = apps
+                .iter()
+                .map(|app| Candidate {
+                    id: Cow::Borrowed(&app.path),
+                    text: &app.name,
+                    fallback: None,
+                })
+                .collect();
+            ranker.offer(Some(source), &candidates);
+        }),
+        // Prefer matches on the file name, as search_files does
+        MatchSource::Files => app_handle.state::<FileIndex>().with_files(|files| {
+            let candidates: Vec<Candidate> = files
+                .iter()
+                .map(|file| Candidate {
+                    id: Cow::Borrowed(&file.path),
+                    text: &file.name,
+                    fallback: Some(&file.path),
+                })
+                .collect();
+            ranker.offer(Some(source), &candidates);
+        }),
+    }
+    Ok(())
+}
+
+// Command to rank suggestions for the spotlight: the given candidates, or else the chosen sources (all by default)
+#[tauri::command(async)]
+pub fn fuzzy_match(
+    app_handle: tauri::AppHandle,
+    query: String,
+    candidates: Option<Vec<String>>,
+    sources: Option<Vec<MatchSource>>,
+    limit: Option<usize>,
+) -> Result<Vec<FuzzyMatch>, String> {
+    let limit = limit.unwrap_or(50).min(MAX_LIMIT);
+    if query.trim().is_empty() || limit == 0 {
+        return Ok(Vec::new());
+    }
+
+    let mut ranker = Ranker::new(&query, limit);
+    match candidates {
+        Some(candidates) => {
+            let candidates: Vec<Candidate> = candidates
+                .iter()
+                .enumerate()
+                .map(|(index, candidate)| Candidate {
+                    id: Cow::Owned(index.to_string()),
+                    text: candidate,
+                    fallback: None,
+                })
+                .collect();
+            ranker.offer(None, &candidates);
+        }
+        None => {
+            for source in sources.as_deref().unwrap_or(ALL_SOURCES) {
+                offer_source(&app_handle, &mut ranker, *source)?;
+            }
+        }
+    }
+    Ok(ranker.finish())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use fuzzy_matcher::skim::SkimMatcherV2;
+    use fuzzy_matcher::FuzzyMatcher;
+    use std::time::Instant;
+
+    fn candidate<'a>(id: &'a str, text: &'a str, fallback: Option<&'a str>) -> Candidate<'a> {
+        Candidate {
+            id: Cow::Borrowed(id),
+            text,
+            fallback,
+        }
+    }
+
+    #[test]
+    fn ranks_text_above_fallback_and_keeps_the_limit() {
+        let mut ranker = Ranker::new("rep", 2);
+        ranker.offer(
+            Some(MatchSource::Files),
+            &[
+                candidate("a", "notes.txt", Some("/reports/notes.txt")),
+                candidate("b", "report.pdf", Some("/docs/report.pdf")),
+                candidate("c", "photo.png", None),
+                candidate("d", "repo.md", None),
+            ],
+        );
+        let ranked = ranker.finish();
+        let ids: Vec<&str> = ranked.iter().map(|m| m.id.as_str()).collect();
+        assert_eq!(ranked.len(), 2);
+        assert!(!ids.contains(&"a") && !ids.contains(&"c"), "{:?}", ids);
+    }
+
+    #[test]
+    fn highlights_characters_not_bytes() {
+        let mut ranker = Ranker::new("lcaf", 1);
+        ranker.offer(None, &[candidate("0", "ümlaut café", None)]);
+        let ranked = ranker.finish();
+        assert_eq!(ranked[0].indices, vec![2, 7, 8, 9]);
+    }
+
+    // Run with `cargo test --release -- --ignored --nocapture ranking_speed`
+    #[test]
+    #[ignore]
+    fn ranking_speed() {
+        let words = ["src", "report", "final", "draft", "notes", "invoice", "photo", "budget", "krya", "backup"];
+        let paths: Vec<String> = (0..200_000)
+            .map(|i| {
+                format!(
+                    "/home/user/{}/{}-{}/{}_{}.txt",
+                    words[i % 10],
+                    words[(i / 10) % 10],
+                    i,
+                    words[(i / 100) % 10],
+                    words[(i / 1000) % 10]
+                )
+            })
+            .collect();
+        let query = "rpfinal";
+
+        let started = Instant::now();
+        let mut ranker = Ranker::new(query, 50);
+        let candidates: Vec<Candidate> = paths.iter().map(|path| candidate(path, path, None)).collect();
+        ranker.offer(None, &candidates);
+        let ranked = ranker.finish();
+        let simd = started.elapsed();
+
+        let started = Instant::now();
+        let skim = SkimMatcherV2::default();
+        let matched = paths.iter().filter_map(|path| skim.fuzzy_match(path, query)).count();
+        let scalar = started.elapsed();
+
+        println!("{} paths: SIMD ranker {:?}, scalar skim {:?} ({} matched)", paths.len(), simd, scalar, matched);
+        assert_eq!(ranked.len(), 50);
+    }
+}
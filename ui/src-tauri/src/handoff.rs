@@ -0,0 +1,163 @@
+use std::collections::HashMap;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use tauri::Manager;
+
+use crate::storage;
+
+// Payloads at least this big go through a file instead of base64 in the JSON body
+pub const THRESHOLD: usize = 1024 * 1024;
+
+const HANDOFF_DIR: &str = "handoff";
+
+// Job statuses after which the backend won't read a job's files again
+const FINISHED_STATUSES: &[&str] = &["completed", "failed", "stopped"];
+
+// Files shared with the backend, kept in a directory only this user can read
+pub struct HandoffState {
+    dir: Option<PathBuf>,
+    by_job: Mutex<HashMap<String, Vec<PathBuf>>>,
+}
+
+fn create_private_dir(dir: &Path) -> std::io::Result<()> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::{DirBuilderExt, PermissionsExt};
+        fs::DirBuilder::new().recursive(true).mode(0o700).create(dir)?;
+        // The directory may predate us with looser permissions
+        fs::set_permissions(dir, fs::Permissions::from_mode(0o700))
+    }
+    // The app data directory is already inside the user's profile
+    #[cfg(not(unix))]
+    {
+        fs::create_dir_all(dir)
+    }
+}
+
+fn create_private_file(path: &Path) -> std::io::Result<fs::File> {
+    let mut options = OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    options.open(path)
+}
+
+impl HandoffState {
+    // Set up the directory, removing anything left behind by an earlier run
+    pub fn init(app_handle: &tauri::AppHandle) -> Self {
+        let dir = storage::data_dir(app_handle)
+            .map(|dir| dir.join(HANDOFF_DIR))
+            .and_then(|dir| {
+                if dir.exists() {
+                    fs::remove_dir_all(&dir).map_err(|e| format!("Failed to clear {:?}: {}", dir, e))?;
+                }
+                create_private_dir(&dir).map_err(|e| format!("Failed to create {:?}: {}", dir, e))?;
+                Ok(dir)
+            });
+
+        let dir = match dir {
+            Ok(dir) => Some(dir),
+            Err(e) => {
+                eprintln!("Large payloads will be sent inline: {}", e);
+                None
+            }
+        };
+        HandoffState {
+            dir,
+            by_job: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn dir(&self) -> Result<&Path, String> {
+        self.dir.as_deref().ok_or_else(|| "Handoff directory is not available".to_string())
+    }
+
+    // Open a new file for the backend to read; files tied to a job are removed when it finishes
+    pub fn create(&self, job_id: Option<&str>, extension: &str) -> Result<(PathBuf, fs::File), String> {
+        let path = self
+            .dir()?
+            .join(format!("{}.{}", uuid::Uuid::new_v4().simple(), extension));
+        let file = create_private_file(&path).map_err(|e| format!("Failed to create handoff file: {}", e))?;
+
+        if let Some(job_id) = job_id {
+            self.by_job
+                .lock()
+                .unwrap()
+                .entry(job_id.to_string())
+                .or_default()
+                .push(path.clone());
+        }
+        Ok((path, file))
+    }
+
+    pub fn write(&self, job_id: Option<&str>, extension: &str, data: &[u8]) -> Result<PathBuf, String> {
+        let (path, mut file) = self.create(job_id, extension)?;
+        if let Err(e) = file.write_all(data) {
+            self.discard(&path);
+            return Err(format!("Failed to write handoff file: {}", e));
+        }
+        Ok(path)
+    }
+
+    // Remove a file we created that the backend will never be told about
+    pub fn discard(&self, path: &Path) {
+        for files in self.by_job.lock().unwrap().values_mut() {
+            files.retain(|p| p != path);
+        }
+        if let Err(e) = fs::remove_file(path) {
+            eprintln!("Failed to remove handoff file {:?}: {}", path, e);
+        }
+    }
+
+    // Read and remove a file the backend handed to us, refusing paths outside the handoff directory
+    pub fn take(&self, path: &str) -> Result<Vec<u8>, String> {
+        let dir = self.dir()?;
+        let path = fs::canonicalize(path).map_err(|e| format!("Handoff file not found: {}", e))?;
+        let dir = fs::canonicalize(dir).map_err(|e| format!("Handoff directory not found: {}", e))?;
+        if path.parent() != Some(dir.as_path()) {
+            return Err(format!("Refusing to read {:?} outside the handoff directory", path));
+        }
+
+        let data = fs::read(&path).map_err(|e| format!("Failed to read handoff file: {}", e))?;
+        if let Err(e) = fs::remove_file(&path) {
+            eprintln!("Failed to remove handoff file {:?}: {}", path, e);
+        }
+        Ok(data)
+    }
+
+    // Whether a running job's request still refers to the file
+    pub fn is_held(&self, path: &Path) -> bool {
+        self.by_job.lock().unwrap().values().any(|files| files.iter().any(|p| p == path))
+    }
+
+    pub fn release_job(&self, job_id: &str) {
+        let files = self.by_job.lock().unwrap().remove(job_id).unwrap_or_default();
+        for path in files {
+            if let Err(e) = fs::remove_file(&path) {
+                if e.kind() != std::io::ErrorKind::NotFound {
+                    eprintln!("Failed to remove handoff file {:?}: {}", path, e);
+                }
+            }
+        }
+    }
+}
+
+// Clean up a job's handoff files once its status says it's done
+pub fn job_status_changed(app_handle: &tauri::AppHandle, job_id: &str, status: &str) {
+    if FINISHED_STATUSES.contains(&status) {
+        app_handle.state::<HandoffState>().release_job(job_id);
+    }
+}
+
+// Tell the Python server where handoff files live, so it can check paths it's given
+pub fn env_hints(app_handle: &tauri::AppHandle) -> Vec<(&'static str, String)> {
+    match &app_handle.state::<HandoffState>().dir {
+        Some(dir) => vec![("KRYA_HANDOFF_DIR", dir.to_string_lossy().to_string())],
+        None => Vec::new(),
+    }
+}
@@ -0,0 +1,136 @@
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tauri::Manager;
+use tauri_plugin_dialog::{DialogExt, MessageDialogButtons, MessageDialogKind};
+use tauri_plugin_opener::OpenerExt;
+
+use crate::compression::{self, Encoding};
+use crate::protocol::{self, CallError, HandshakeParams, HandshakeResult};
+use crate::{automation, capabilities, events, jobs, ollama, queue, scopes, AppState};
+
+// How long the backend gets to answer the handshake after it was spawned
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(30);
+
+const DOWNLOAD_URL: &str = "https://github.com/devdattatalele/Krya.ai/releases/latest";
+
+// What the shell offers the backend, announced in the handshake
+const SHELL_CAPABILITIES: &[&str] = &["mcp_proxy", "mcp_server", "native_input"];
+
+// The backend's answer to the last successful handshake
+#[derive(Default)]
+pub struct HandshakeState(pub Mutex<Option<HandshakeResult>>);
+
+fn params() -> HandshakeParams {
+    HandshakeParams {
+        shell_version: env!("CARGO_PKG_VERSION").to_string(),
+        protocol_versions: protocol::PROTOCOL_VERSIONS.to_vec(),
+        capabilities: SHELL_CAPABILITIES.iter().map(|c| c.to_string()).collect(),
+        encodings: compression::SUPPORTED.iter().map(|e| e.name().to_string()).collect(),
+    }
+}
+
+// Ok(None) while the backend is still starting; Err when it can't be used with this shell
+fn attempt() -> Result<Option<HandshakeResult>, String> {
+    match protocol::try_call::<protocol::Handshake>(&params()) {
+        Ok(result) if result.protocol_version.is_some() => Ok(Some(result)),
+        Ok(result) => Err(format!(
+            "The backend (version {}) speaks protocol {:?}, but this app needs {:?}.",
+            result.backend_version,
+            result.protocol_versions,
+            protocol::PROTOCOL_VERSIONS
+        )),
+        Err(CallError::Unsupported(_)) => {
+            Err("The backend is from an older release and doesn't support version negotiation.".to_string())
+        }
+        Err(CallError::Unreachable(_)) | Err(CallError::Offline) => Ok(None),
+        Err(CallError::Failed(e)) => {
+            eprintln!("Backend handshake failed, retrying: {}", e);
+            Ok(None)
+        }
+    }
+}
+
+// Mismatched halves of an install can't be used safely, so explain, offer the fix and quit
+fn refuse(app_handle: &tauri::AppHandle, app_state: &AppState, detail: &str) {
+    eprintln!("Backend protocol mismatch: {}", detail);
+    crate::stop_api_server(app_state);
+
+    let message = format!(
+        "Krya.ai {} can't work with its bundled backend. {}\n\n\
+         This usually means an update was only partly installed. \
+         Reinstall the latest release of Krya.ai, then start it again.",
+        env!("CARGO_PKG_VERSION"),
+        detail
+    );
+    let download = app_handle
+        .dialog()
+        .message(message)
+        .title("Krya.ai needs to be reinstalled")
+        .kind(MessageDialogKind::Error)
+        .buttons(MessageDialogButtons::OkCancelCustom(
+            "Open download page".to_string(),
+            "Quit".to_string(),
+        ))
+        .blocking_show();
+
+    if download {
+        if let Err(e) = app_handle.opener().open_url(DOWNLOAD_URL, None::<&str>) {
+            eprintln!("Failed to open download page: {}", e);
+        }
+    }
+    ollama::stop(app_handle);
+    app_handle.exit(1);
+}
+
+// Negotiate a protocol version with a freshly started backend, in the background
+pub fn negotiate(app_handle: &tauri::AppHandle, app_state: AppState) {
+    let app_handle = app_handle.clone();
+    // A restarted backend may not support what the previous one did
+    compression::set_negotiated(None);
+    std::thread::spawn(move || {
+        let started = Instant::now();
+        while started.elapsed() < HANDSHAKE_TIMEOUT {
+            match attempt() {
+                Ok(Some(result)) => {
+                    println!(
+                        "Backend {} ready on protocol {}",
+                        result.backend_version,
+                        result.protocol_version.unwrap_or_default()
+                    );
+                    compression::set_negotiated(result.encoding.as_deref().and_then(Encoding::parse));
+                    *app_handle.state::<HandshakeState>().0.lock().unwrap() = Some(result.clone());
+                    events::emit(&app_handle, &result);
+                    if let Err(e) = capabilities::refresh(&app_handle) {
+                        eprintln!("Failed to fetch backend capabilities: {}", e);
+                    }
+                    if automation::is_paused(&app_handle) || automation::is_dry_run(&app_handle) {
+                        if let Err(e) = automation::sync(&app_handle) {
+                            eprintln!("Failed to keep automation paused or in a dry run on the backend: {}", e);
+                        }
+                    }
+                    if scopes::working_directory(&app_handle).is_some() {
+                        if let Err(e) = scopes::sync_working_directory(&app_handle) {
+                            eprintln!("Failed to restore working directory on the backend: {}", e);
+                        }
+                    }
+                    jobs::offer_rerun(&app_handle);
+                    queue::backend_recovered(&app_handle);
+                    return;
+                }
+                Ok(None) => std::thread::sleep(Duration::from_secs(1)),
+                Err(detail) => {
+                    refuse(&app_handle, &app_state, &detail);
+                    return;
+                }
+            }
+        }
+        // A backend that never came up is the crash watcher's business, not a mismatch
+        eprintln!("Backend did not answer the handshake within {}s", HANDSHAKE_TIMEOUT.as_secs());
+    });
+}
+
+// Command for the frontend to learn what it's talking to; None until the handshake succeeded
+#[tauri::command]
+pub fn get_backend_info(app_handle: tauri::AppHandle) -> Option<HandshakeResult> {
+    app_handle.state::<HandshakeState>().0.lock().unwrap().clone()
+}
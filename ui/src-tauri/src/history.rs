@@ -0,0 +1,596 @@
+use chrono::{DateTime, SecondsFormat, TimeZone};
+use rusqlite::{params, Connection, Row};
+use tauri::{Emitter, Manager};
+use tauri_plugin_dialog::DialogExt;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::PathBuf;
+use std::ops::Deref;
+use std::sync::{Mutex, MutexGuard};
+
+use crate::migrations::{self, SqlMigration};
+use crate::util::now_secs;
+use crate::{handoff, jobs, profiles, sessions, storage, telemetry};
+use crate::errors::ReportExt;
+
+const HISTORY_DB: &str = "history.db";
+
+// What add_history_item returns for a prompt that wasn't kept; no row ever has it
+const UNSAVED_ID: i64 = 0;
+
+// A prompt sent from the spotlight together with its outcome
+#[derive(Clone, Debug, Serialize)]
+pub struct HistoryItem {
+    pub id: i64,
+    pub prompt: String,
+    pub result: Option<String>,
+    // "pending", "running", "completed", "failed" or "stopped", mirroring the backend job status
+    pub status: String,
+    pub job_id: Option<String>,
+    // Seconds since the Unix epoch
+    pub created_at: u64,
+    pub updated_at: u64,
+}
+
+// A favorite prompt that can be rerun from the spotlight or tray
+#[derive(Clone, Debug, Serialize)]
+pub struct PinnedPrompt {
+    pub id: i64,
+    pub title: String,
+    pub prompt: String,
+    pub created_at: u64,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct HistoryPage {
+    pub items: Vec<HistoryItem>,
+    pub total: u64,
+    pub page: usize,
+    pub page_size: usize,
+}
+
+#[derive(Clone, Copy, Debug, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportFormat {
+    Csv,
+    Json,
+}
+
+// Creation times of the prompts to export, as RFC 3339 timestamps; either end may be open
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct HistoryRange {
+    pub since: Option<String>,
+    pub until: Option<String>,
+}
+
+impl HistoryRange {
+    // The range in seconds since the Unix epoch, open ends filled in
+    fn bounds(&self) -> Result<(i64, i64), String> {
+        let parse = |value: &Option<String>, what: &str, open: i64| -> Result<i64, String> {
+            match value.as_deref() {
+                Some(value) => DateTime::parse_from_rfc3339(value)
+                    .map(|time| time.timestamp())
+                    .map_err(|e| format!("Invalid {} time {}: {}", what, value, e)),
+                None => Ok(open),
+            }
+        };
+        Ok((parse(&self.since, "start", 0)?, parse(&self.until, "end", i64::MAX)?))
+    }
+}
+
+// The history database; None when not even an in-memory one could be set up, which turns history off
+pub struct HistoryState(Mutex<Option<Connection>>);
+
+// The open database, held locked
+pub struct HistoryConnection<'a>(MutexGuard<'a, Option<Connection>>);
+
+impl Deref for HistoryConnection<'_> {
+    type Target = Connection;
+
+    fn deref(&self) -> &Connection {
+        // Only made when there is one
+        self.0.as_ref().unwrap()
+    }
+}
+
+impl HistoryState {
+    pub fn open(app_handle: &tauri::AppHandle) -> Self {
+        let connection = storage::data_dir(app_handle)
+            .map(|dir| dir.join(profiles::file(app_handle, HISTORY_DB)))
+            .and_then(|path| {
+                let conn = Connection::open(&path).map_err(|e| e.to_string())?;
+                migrations::migrate_database(app_handle, &conn, Some(&path), MIGRATIONS).map(|_| conn)
+            })
+            .or_else(|e| {
+                // Keep the app usable; history just won't survive a restart
+                eprintln!("Failed to open history database, using in-memory history: {}", e);
+                let conn = Connection::open_in_memory().map_err(|e| e.to_string())?;
+                migrations::migrate_database(app_handle, &conn, None, MIGRATIONS).map(|_| conn)
+            })
+            .map_err(|e| eprintln!("Failed to set up in-memory history, turning history off: {}", e))
+            .ok();
+
+        HistoryState(Mutex::new(connection))
+    }
+
+    // Switch to the active profile's database
+    pub fn reopen(&self, app_handle: &tauri::AppHandle) {
+        let fresh = HistoryState::open(app_handle).0.into_inner().unwrap();
+        *self.0.lock().unwrap() = fresh;
+    }
+
+    pub fn connection(&self) -> Result<HistoryConnection<'_>, String> {
+        let guard = self.0.lock().unwrap();
+        if guard.is_none() {
+            return Err("History is off, since its database couldn't be opened".to_string());
+        }
+        Ok(HistoryConnection(guard))
+    }
+
+    pub fn add(&self, prompt: &str, job_id: Option<&str>) -> Result<i64, String> {
+        let now = now_secs();
+        let conn = self.connection()?;
+        conn.execute(
+            "INSERT INTO history (prompt, status, job_id, created_at, updated_at) VALUES (?1, 'pending', ?2, ?3, ?3)",
+            params![prompt, job_id, now],
+        )
+        .map_err(|e| format!("Failed to save history item: {}", e))?;
+        Ok(conn.last_insert_rowid())
+    }
+
+    pub fn update(&self, id: i64, status: &str, result: Option<&str>, job_id: Option<&str>) -> Result<(), String> {
+        let conn = self.connection()?;
+        conn.execute(
+            "UPDATE history SET status = ?2, result = COALESCE(?3, result), job_id = COALESCE(?4, job_id), updated_at = ?5 WHERE id = ?1",
+            params![id, status, result, job_id, now_secs()],
+        )
+        .map_err(|e| format!("Failed to update history item: {}", e))?;
+        Ok(())
+    }
+
+    // Most recent history item recorded for a backend job
+    pub fn find_by_job(&self, job_id: &str) -> Result<Option<HistoryItem>, String> {
+        let conn = self.connection()?;
+        let mut statement = conn
+            .prepare(&format!(
+                "SELECT {} FROM history WHERE job_id = ?1 ORDER BY id DESC LIMIT 1",
+                COLUMNS
+            ))
+            .map_err(|e| format!("Failed to read history: {}", e))?;
+        let mut items = statement
+            .query_map(params![job_id], from_row)
+            .and_then(|rows| rows.collect::<Result<Vec<_>, _>>())
+            .map_err(|e| format!("Failed to read history: {}", e))?;
+        Ok(items.pop())
+    }
+
+    pub fn search(&self, query: &str, limit: usize) -> Result<Vec<HistoryItem>, String> {
+        let pattern = format!("%{}%", escape_like(query));
+        let conn = self.connection()?;
+        let mut statement = conn
+            .prepare(&format!(
+                "SELECT {} FROM history WHERE prompt LIKE ?1 ESCAPE '\\' OR result LIKE ?1 ESCAPE '\\' ORDER BY created_at DESC, id DESC LIMIT ?2",
+                COLUMNS
+            ))
+            .map_err(|e| format!("Failed to search history: {}", e))?;
+        let items = statement
+            .query_map(params![pattern, limit as i64], from_row)
+            .and_then(|rows| rows.collect::<Result<Vec<_>, _>>())
+            .map_err(|e| format!("Failed to search history: {}", e))?;
+        Ok(items)
+    }
+
+    pub fn page(&self, page: usize, page_size: usize) -> Result<HistoryPage, String> {
+        let conn = self.connection()?;
+        let total: i64 = conn
+            .query_row("SELECT COUNT(*) FROM history", [], |row| row.get(0))
+            .map_err(|e| format!("Failed to read history: {}", e))?;
+
+        let mut statement = conn
+            .prepare(&format!(
+                "SELECT {} FROM history ORDER BY created_at DESC, id DESC LIMIT ?1 OFFSET ?2",
+                COLUMNS
+            ))
+            .map_err(|e| format!("Failed to read history: {}", e))?;
+        let items = statement
+            .query_map(params![page_size as i64, (page * page_size) as i64], from_row)
+            .and_then(|rows| rows.collect::<Result<Vec<_>, _>>())
+            .map_err(|e| format!("Failed to read history: {}", e))?;
+
+        Ok(HistoryPage {
+            items,
+            total: total as u64,
+            page,
+            page_size,
+        })
+    }
+
+    // Pin a prompt, updating the title if it is already pinned
+    pub fn pin(&self, prompt: &str, title: &str) -> Result<PinnedPrompt, String> {
+        let conn = self.connection()?;
+        conn.execute(
+            "INSERT INTO pinned_prompts (prompt, title, created_at) VALUES (?1, ?2, ?3)
+             ON CONFLICT(prompt) DO UPDATE SET title = excluded.title",
+            params![prompt, title, now_secs()],
+        )
+        .map_err(|e| format!("Failed to pin prompt: {}", e))?;
+
+        conn.query_row(
+            "SELECT id, title, prompt, created_at FROM pinned_prompts WHERE prompt = ?1",
+            params![prompt],
+            pinned_from_row,
+        )
+        .map_err(|e| format!("Failed to read pinned prompt: {}", e))
+    }
+
+    pub fn unpin(&self, id: i64) -> Result<bool, String> {
+        let conn = self.connection()?;
+        let deleted = conn
+            .execute("DELETE FROM pinned_prompts WHERE id = ?1", params![id])
+            .map_err(|e| format!("Failed to unpin prompt: {}", e))?;
+        Ok(deleted > 0)
+    }
+
+    pub fn pinned(&self) -> Result<Vec<PinnedPrompt>, String> {
+        let conn = self.connection()?;
+        let mut statement = conn
+            .prepare("SELECT id, title, prompt, created_at FROM pinned_prompts ORDER BY created_at, id")
+            .map_err(|e| format!("Failed to read pinned prompts: {}", e))?;
+        let items = statement
+            .query_map([], pinned_from_row)
+            .and_then(|rows| rows.collect::<Result<Vec<_>, _>>())
+            .map_err(|e| format!("Failed to read pinned prompts: {}", e))?;
+        Ok(items)
+    }
+
+    pub fn delete(&self, id: i64) -> Result<bool, String> {
+        let conn = self.connection()?;
+        let deleted = conn
+            .execute("DELETE FROM session_messages WHERE history_id = ?1", params![id])
+            .and_then(|_| conn.execute("DELETE FROM history WHERE id = ?1", params![id]))
+            .map_err(|e| format!("Failed to delete history item: {}", e))?;
+        Ok(deleted > 0)
+    }
+
+    // Prompts created within a range, oldest first
+    pub fn between(&self, since: i64, until: i64) -> Result<Vec<HistoryItem>, String> {
+        let conn = self.connection()?;
+        let mut statement = conn
+            .prepare(&format!(
+                "SELECT {} FROM history WHERE created_at >= ?1 AND created_at <= ?2 ORDER BY created_at, id",
+                COLUMNS
+            ))
+            .map_err(|e| format!("Failed to read history: {}", e))?;
+        let items = statement
+            .query_map(params![since, until], from_row)
+            .and_then(|rows| rows.collect::<Result<Vec<_>, _>>())
+            .map_err(|e| format!("Failed to read history: {}", e))?;
+        Ok(items)
+    }
+
+    // A session's messages in the order they were sent
+    pub fn in_session(&self, session_id: i64) -> Result<Vec<HistoryItem>, String> {
+        let conn = self.connection()?;
+        let mut statement = conn
+            .prepare(&format!(
+                "SELECT {} FROM history WHERE id IN (SELECT history_id FROM session_messages WHERE session_id = ?1) \
+                 ORDER BY created_at, id",
+                COLUMNS
+            ))
+            .map_err(|e| format!("Failed to read session messages: {}", e))?;
+        let items = statement
+            .query_map(params![session_id], from_row)
+            .and_then(|rows| rows.collect::<Result<Vec<_>, _>>())
+            .map_err(|e| format!("Failed to read session messages: {}", e))?;
+        Ok(items)
+    }
+}
+
+const COLUMNS: &str = "id, prompt, result, status, job_id, created_at, updated_at";
+
+fn from_row(row: &Row) -> rusqlite::Result<HistoryItem> {
+    Ok(HistoryItem {
+        id: row.get(0)?,
+        prompt: row.get(1)?,
+        result: row.get(2)?,
+        status: row.get(3)?,
+        job_id: row.get(4)?,
+        created_at: row.get::<_, i64>(5)? as u64,
+        updated_at: row.get::<_, i64>(6)? as u64,
+    })
+}
+
+fn pinned_from_row(row: &Row) -> rusqlite::Result<PinnedPrompt> {
+    Ok(PinnedPrompt {
+        id: row.get(0)?,
+        title: row.get(1)?,
+        prompt: row.get(2)?,
+        created_at: row.get::<_, i64>(3)? as u64,
+    })
+}
+
+// Each release that changes the tables adds a step; steps already applied are never edited
+const MIGRATIONS: &[SqlMigration] = &[SqlMigration {
+    version: 1,
+    description: "History, pinned prompts and sessions",
+    sql: "CREATE TABLE IF NOT EXISTS history (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            prompt TEXT NOT NULL,
+            result TEXT,
+            status TEXT NOT NULL,
+            job_id TEXT,
+            created_at INTEGER NOT NULL,
+            updated_at INTEGER NOT NULL
+        );
+        CREATE INDEX IF NOT EXISTS history_created_at ON history (created_at);
+        CREATE INDEX IF NOT EXISTS history_job_id ON history (job_id);
+        CREATE TABLE IF NOT EXISTS pinned_prompts (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            prompt TEXT NOT NULL UNIQUE,
+            title TEXT NOT NULL,
+            created_at INTEGER NOT NULL
+        );
+        CREATE TABLE IF NOT EXISTS sessions (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            title TEXT NOT NULL,
+            created_at INTEGER NOT NULL,
+            updated_at INTEGER NOT NULL
+        );
+        CREATE TABLE IF NOT EXISTS session_messages (
+            session_id INTEGER NOT NULL,
+            history_id INTEGER NOT NULL,
+            PRIMARY KEY (session_id, history_id)
+        );
+        CREATE INDEX IF NOT EXISTS session_messages_history_id ON session_messages (history_id);",
+}];
+
+// Escape LIKE wildcards so user input is matched literally
+fn escape_like(query: &str) -> String {
+    query.replace('\\', "\\\\").replace('%', "\\%").replace('_', "\\_")
+}
+
+// Pinned prompts shown in the tray; an unreadable database just means an empty submenu
+pub fn tray_pinned(app_handle: &tauri::AppHandle) -> Vec<PinnedPrompt> {
+    app_handle.state::<HistoryState>().pinned().unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        Vec::new()
+    })
+}
+
+// Show the spotlight and rerun a pinned prompt
+pub fn trigger_pinned_prompt(app_handle: &tauri::AppHandle, id: i64) {
+    let pinned = tray_pinned(app_handle).into_iter().find(|p| p.id == id);
+    let pinned = match pinned {
+        Some(pinned) => pinned,
+        None => {
+            eprintln!("Pinned prompt not found: {}", id);
+            return;
+        }
+    };
+
+    if let Some(window) = app_handle.get_webview_window("main") {
+        if !window.is_visible().unwrap_or(false) {
+            crate::toggle_spotlight_window(&window).or_report(app_handle, "Failed to show the spotlight");
+        }
+        if let Err(e) = window.emit_to(window.label(), "run-pinned-prompt", pinned) {
+            eprintln!("Failed to emit pinned prompt: {}", e);
+        }
+    }
+}
+
+fn local_time(secs: u64) -> String {
+    chrono::Local
+        .timestamp_opt(secs as i64, 0)
+        .single()
+        .map(|time| time.to_rfc3339_opts(SecondsFormat::Secs, false))
+        .unwrap_or_default()
+}
+
+// Quote a CSV field when it holds a separator, quote or line break
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+fn to_csv(items: &[HistoryItem]) -> String {
+    let mut out = String::from("id,created_at,updated_at,status,prompt,result,job_id\n");
+    for item in items {
+        let fields = [
+            item.id.to_string(),
+            local_time(item.created_at),
+            local_time(item.updated_at),
+            item.status.clone(),
+            item.prompt.clone(),
+            item.result.clone().unwrap_or_default(),
+            item.job_id.clone().unwrap_or_default(),
+        ];
+        let fields: Vec<String> = fields.iter().map(|field| csv_field(field)).collect();
+        out.push_str(&fields.join(","));
+        out.push('\n');
+    }
+    out
+}
+
+fn to_json(items: &[HistoryItem], range: &HistoryRange) -> Result<String, String> {
+    let export = serde_json::json!({
+        "app_version": env!("CARGO_PKG_VERSION"),
+        "exported_at": chrono::Local::now().to_rfc3339_opts(SecondsFormat::Secs, false),
+        "since": range.since,
+        "until": range.until,
+        "items": items,
+    });
+    serde_json::to_string_pretty(&export).map_err(|e| format!("Failed to serialize history: {}", e))
+}
+
+// Command to record a prompt when it is submitted, returning its history id.
+// It joins `session_id`, or the active session when that's not given. Nothing is written in privacy mode.
+#[tauri::command]
+pub fn add_history_item(
+    app_handle: tauri::AppHandle,
+    history: tauri::State<HistoryState>,
+    prompt: String,
+    job_id: Option<String>,
+    session_id: Option<i64>,
+) -> Result<i64, String> {
+    let id = if !storage::may_record(&app_handle) {
+        UNSAVED_ID
+    } else {
+        let id = history.add(&prompt, job_id.as_deref())?;
+        sessions::attach(&app_handle, &history, id, session_id, &prompt)?;
+        id
+    };
+    telemetry::record(&app_handle, "job_submitted", &[]);
+    if let Some(job_id) = &job_id {
+        jobs::track(&app_handle, job_id);
+    }
+    Ok(id)
+}
+
+// Command to record a job's outcome once the backend reports it
+#[tauri::command]
+pub fn update_history_item(
+    app_handle: tauri::AppHandle,
+    history: tauri::State<HistoryState>,
+    id: i64,
+    status: String,
+    result: Option<String>,
+    job_id: Option<String>,
+) -> Result<(), String> {
+    if id != UNSAVED_ID && storage::may_record(&app_handle) {
+        history.update(id, &status, result.as_deref(), job_id.as_deref())?;
+    }
+    if let Some(job_id) = &job_id {
+        handoff::job_status_changed(&app_handle, job_id, &status);
+    }
+    if status == "failed" {
+        telemetry::record(&app_handle, "job_failed", &[]);
+    }
+    Ok(())
+}
+
+#[tauri::command]
+pub fn search_history(
+    history: tauri::State<HistoryState>,
+    query: String,
+    limit: Option<usize>,
+) -> Result<Vec<HistoryItem>, String> {
+    history.search(&query, limit.unwrap_or(50))
+}
+
+// Command to page through history, newest first (the spotlight's up-arrow recall reads page 0)
+#[tauri::command]
+pub fn get_history_page(
+    history: tauri::State<HistoryState>,
+    page: Option<usize>,
+    page_size: Option<usize>,
+) -> Result<HistoryPage, String> {
+    history.page(page.unwrap_or(0), page_size.unwrap_or(50).max(1))
+}
+
+#[tauri::command]
+pub fn delete_history_item(history: tauri::State<HistoryState>, id: i64) -> Result<(), String> {
+    if history.delete(id)? {
+        Ok(())
+    } else {
+        Err(format!("History item not found: {}", id))
+    }
+}
+
+// Command to write the prompts created in a range, with their timestamps and outcomes, to a CSV
+// or JSON file, asking where with the save dialog when no path is given. Returns the path, or
+// None if cancelled.
+#[tauri::command(async)]
+pub fn export_history(
+    app_handle: tauri::AppHandle,
+    history: tauri::State<HistoryState>,
+    format: ExportFormat,
+    range: Option<HistoryRange>,
+    path: Option<String>,
+) -> Result<Option<String>, String> {
+    let range = range.unwrap_or_default();
+    let (since, until) = range.bounds()?;
+
+    let path = match path {
+        Some(path) => PathBuf::from(path),
+        None => {
+            let (extension, filter) = match format {
+                ExportFormat::Csv => ("csv", "CSV files"),
+                ExportFormat::Json => ("json", "JSON files"),
+            };
+            let file_name = format!(
+                "krya-history-{}.{}",
+                chrono::Local::now().format("%Y%m%d-%H%M%S"),
+                extension
+            );
+            match app_handle.dialog().file()
+                .set_file_name(&file_name)
+                .add_filter(filter, &[extension])
+                .blocking_save_file()
+            {
+                Some(path) => path.into_path().map_err(|e| e.to_string())?,
+                None => return Ok(None),
+            }
+        }
+    };
+
+    let items = history.between(since, until)?;
+    let out = match format {
+        ExportFormat::Csv => to_csv(&items),
+        ExportFormat::Json => to_json(&items, &range)?,
+    };
+    fs::write(&path, out).map_err(|e| format!("Failed to write {:?}: {}", path, e))?;
+    Ok(Some(path.to_string_lossy().to_string()))
+}
+
+// Command to pin a prompt; the title defaults to the start of the prompt
+#[tauri::command]
+pub fn pin_prompt(
+    app_handle: tauri::AppHandle,
+    history: tauri::State<HistoryState>,
+    prompt: String,
+    title: Option<String>,
+) -> Result<PinnedPrompt, String> {
+    let prompt = prompt.trim();
+    if prompt.is_empty() {
+        return Err("Prompt cannot be empty".to_string());
+    }
+
+    let title = match title.as_deref().map(str::trim) {
+        Some(title) if !title.is_empty() => title.to_string(),
+        _ => default_title(prompt),
+    };
+
+    let pinned = history.pin(prompt, &title)?;
+    crate::tray::refresh(&app_handle);
+    Ok(pinned)
+}
+
+#[tauri::command]
+pub fn unpin_prompt(app_handle: tauri::AppHandle, history: tauri::State<HistoryState>, id: i64) -> Result<(), String> {
+    if !history.unpin(id)? {
+        return Err(format!("Pinned prompt not found: {}", id));
+    }
+    crate::tray::refresh(&app_handle);
+    Ok(())
+}
+
+#[tauri::command]
+pub fn list_pinned(history: tauri::State<HistoryState>) -> Result<Vec<PinnedPrompt>, String> {
+    history.pinned()
+}
+
+pub fn default_title(prompt: &str) -> String {
+    const MAX_TITLE_CHARS: usize = 40;
+
+    let first_line = prompt.lines().next().unwrap_or_default();
+    if first_line.chars().count() > MAX_TITLE_CHARS {
+        let truncated: String = first_line.chars().take(MAX_TITLE_CHARS - 1).collect();
+        format!("{}…", truncated.trim_end())
+    } else {
+        first_line.to_string()
+    }
+}
@@ -0,0 +1,278 @@
+use rdev::EventType;
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tauri::Manager;
+
+use crate::input::InputHub;
+use crate::placement::Rect;
+use crate::settings::{self, HotCornerSettings, ScreenCorner};
+use crate::{active_app, errors};
+
+const LISTENER_NAME: &str = "hot-corner";
+
+// How close to the very corner the pointer has to be, in the listener's units
+const CORNER_SIZE: f64 = 4.0;
+
+// A shake is this many changes of direction, each after at least SHAKE_TRAVEL of movement,
+// all within SHAKE_WINDOW
+const SHAKE_REVERSALS: usize = 4;
+const SHAKE_TRAVEL: f64 = 60.0;
+const SHAKE_WINDOW: Duration = Duration::from_millis(700);
+
+// Screens are looked up again when the pointer leaves all of them, at most this often
+const SCREEN_REFRESH: Duration = Duration::from_secs(2);
+
+#[derive(Default)]
+struct Tracker {
+    // Held buttons mean a drag, which may well end in a corner on purpose
+    buttons_down: usize,
+    in_corner: bool,
+    // Horizontal position where the current stroke started, and its direction
+    stroke_start: Option<f64>,
+    direction: f64,
+    last_x: Option<f64>,
+    reversals: VecDeque<Instant>,
+}
+
+pub struct HotCornerState {
+    // Monitor bounds in the same units rdev reports the pointer in
+    screens: Arc<Mutex<Vec<Rect>>>,
+    screens_checked: Arc<Mutex<Option<Instant>>>,
+    tracker: Arc<Mutex<Tracker>>,
+    // Bumped whenever the pointer enters or leaves a corner, so a pending dwell knows it's stale
+    visit: Arc<AtomicUsize>,
+}
+
+impl Default for HotCornerState {
+    fn default() -> Self {
+        HotCornerState {
+            screens: Arc::new(Mutex::new(Vec::new())),
+            screens_checked: Arc::new(Mutex::new(None)),
+            tracker: Arc::new(Mutex::new(Tracker::default())),
+            visit: Arc::new(AtomicUsize::new(0)),
+        }
+    }
+}
+
+// rdev reports points on macOS and physical pixels elsewhere
+fn screens(app_handle: &tauri::AppHandle) -> Vec<Rect> {
+    let monitors = match app_handle.get_webview_window("main").map(|window| window.available_monitors()) {
+        Some(Ok(monitors)) => monitors,
+        Some(Err(e)) => {
+            eprintln!("Failed to list monitors for the hot corner: {}", e);
+            return Vec::new();
+        }
+        None => return Vec::new(),
+    };
+
+    monitors
+        .iter()
+        .map(|monitor| {
+            let scale = if cfg!(target_os = "macos") { monitor.scale_factor() } else { 1.0 };
+            Rect {
+                x: (monitor.position().x as f64 / scale).round() as i32,
+                y: (monitor.position().y as f64 / scale).round() as i32,
+                width: (monitor.size().width as f64 / scale).round() as u32,
+                height: (monitor.size().height as f64 / scale).round() as u32,
+            }
+        })
+        .collect()
+}
+
+fn refresh_screens(app_handle: &tauri::AppHandle) {
+    let state = app_handle.state::<HotCornerState>();
+    {
+        let mut checked = state.screens_checked.lock().unwrap();
+        if checked.is_some_and(|checked| checked.elapsed() < SCREEN_REFRESH) {
+            return;
+        }
+        *checked = Some(Instant::now());
+    }
+    *state.screens.lock().unwrap() = screens(app_handle);
+}
+
+fn corner_of(screen: &Rect, corner: ScreenCorner) -> (f64, f64) {
+    let left = screen.x as f64;
+    let top = screen.y as f64;
+    let right = left + screen.width as f64 - 1.0;
+    let bottom = top + screen.height as f64 - 1.0;
+    match corner {
+        ScreenCorner::TopLeft => (left, top),
+        ScreenCorner::TopRight => (right, top),
+        ScreenCorner::BottomLeft => (left, bottom),
+        ScreenCorner::BottomRight => (right, bottom),
+    }
+}
+
+fn on_screen(screen: &Rect, x: f64, y: f64) -> bool {
+    x >= screen.x as f64
+        && x < (screen.x + screen.width as i32) as f64
+        && y >= screen.y as f64
+        && y < (screen.y + screen.height as i32) as f64
+}
+
+// What the pointer did that should open the spotlight
+enum Trigger {
+    // Entered the corner; opens once it's still there after the dwell
+    Corner,
+    Shake,
+}
+
+#[derive(Default)]
+struct Outcome {
+    trigger: Option<Trigger>,
+    // Entered or left the corner
+    corner_changed: bool,
+    // Somewhere no known screen covers, so the monitors have probably changed
+    off_screen: bool,
+}
+
+fn track_shake(tracker: &mut Tracker, x: f64) -> bool {
+    let last_x = match tracker.last_x.replace(x) {
+        Some(last_x) => last_x,
+        None => return false,
+    };
+    if x == last_x {
+        return false;
+    }
+
+    let direction = (x - last_x).signum();
+    let start = *tracker.stroke_start.get_or_insert(last_x);
+    if direction != tracker.direction {
+        // Only strokes long enough to be deliberate count as a change of direction
+        if tracker.direction != 0.0 && (last_x - start).abs() >= SHAKE_TRAVEL {
+            tracker.reversals.push_back(Instant::now());
+        }
+        tracker.direction = direction;
+        tracker.stroke_start = Some(last_x);
+    }
+
+    while tracker.reversals.front().is_some_and(|at| at.elapsed() > SHAKE_WINDOW) {
+        tracker.reversals.pop_front();
+    }
+    if tracker.reversals.len() >= SHAKE_REVERSALS {
+        tracker.reversals.clear();
+        return true;
+    }
+    false
+}
+
+fn handle_event(
+    event: &rdev::Event,
+    config: &HotCornerSettings,
+    screens: &[Rect],
+    tracker: &Mutex<Tracker>,
+) -> Outcome {
+    let mut tracker = tracker.lock().unwrap();
+    let (x, y) = match event.event_type {
+        EventType::MouseMove { x, y } => (x, y),
+        EventType::ButtonPress(_) => {
+            tracker.buttons_down += 1;
+            return Outcome::default();
+        }
+        EventType::ButtonRelease(_) => {
+            tracker.buttons_down = tracker.buttons_down.saturating_sub(1);
+            return Outcome::default();
+        }
+        _ => return Outcome::default(),
+    };
+
+    let in_corner = tracker.buttons_down == 0
+        && config.corner.is_some_and(|corner| {
+            screens.iter().any(|screen| {
+                let (corner_x, corner_y) = corner_of(screen, corner);
+                (x - corner_x).abs() <= CORNER_SIZE && (y - corner_y).abs() <= CORNER_SIZE
+            })
+        });
+    let mut outcome = Outcome {
+        trigger: None,
+        corner_changed: in_corner != tracker.in_corner,
+        off_screen: !screens.iter().any(|screen| on_screen(screen, x, y)),
+    };
+    if in_corner && !tracker.in_corner {
+        outcome.trigger = Some(Trigger::Corner);
+    }
+    tracker.in_corner = in_corner;
+
+    if tracker.buttons_down > 0 {
+        tracker.reversals.clear();
+    } else if outcome.trigger.is_none() && config.shake && track_shake(&mut tracker, x) {
+        outcome.trigger = Some(Trigger::Shake);
+    }
+    outcome
+}
+
+fn open(app_handle: &tauri::AppHandle, excluded_apps: &[String]) {
+    if let Some(app) = active_app::frontmost_app() {
+        if excluded_apps.iter().any(|pattern| app.matches(pattern)) {
+            return;
+        }
+    }
+    match app_handle.get_webview_window("main") {
+        // Only ever opens; hiding from a corner would be too easy to do by accident
+        Some(window) => {
+            if !window.is_visible().unwrap_or(false) {
+                crate::toggle_spotlight(app_handle);
+            }
+        }
+        None => errors::report(app_handle, "Failed to open the spotlight", "the window is missing"),
+    }
+}
+
+// Start or stop the mouse listener to match the settings
+pub fn apply(app_handle: &tauri::AppHandle) {
+    let config = settings::current(app_handle).hot_corner;
+    let hub = app_handle.state::<InputHub>();
+    if !config.enabled || (config.corner.is_none() && !config.shake) {
+        hub.unsubscribe(LISTENER_NAME);
+        return;
+    }
+
+    let state = app_handle.state::<HotCornerState>();
+    *state.screens_checked.lock().unwrap() = None;
+    refresh_screens(app_handle);
+
+    let screens = state.screens.clone();
+    let tracker = state.tracker.clone();
+    let visit = state.visit.clone();
+    let app_handle = app_handle.clone();
+    hub.subscribe(
+        LISTENER_NAME,
+        Box::new(move |event| {
+            let outcome = {
+                let screens = screens.lock().unwrap();
+                handle_event(event, &config, &screens, &tracker)
+            };
+            if outcome.corner_changed {
+                visit.fetch_add(1, Ordering::SeqCst);
+            }
+            if outcome.off_screen {
+                let app_handle = app_handle.clone();
+                std::thread::spawn(move || refresh_screens(&app_handle));
+            }
+
+            let app_handle = app_handle.clone();
+            let excluded_apps = config.excluded_apps.clone();
+            match outcome.trigger {
+                Some(Trigger::Corner) => {
+                    let entered = visit.load(Ordering::SeqCst);
+                    let visit = visit.clone();
+                    let dwell = Duration::from_millis(config.dwell_ms);
+                    std::thread::spawn(move || {
+                        std::thread::sleep(dwell);
+                        // A different visit means the pointer left in the meantime
+                        if visit.load(Ordering::SeqCst) == entered {
+                            open(&app_handle, &excluded_apps);
+                        }
+                    });
+                }
+                Some(Trigger::Shake) => {
+                    std::thread::spawn(move || open(&app_handle, &excluded_apps));
+                }
+                None => {}
+            }
+        }),
+    );
+}
@@ -0,0 +1,135 @@
+use notify::{RecommendedWatcher, RecursiveMode, Watcher};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tauri::Manager;
+
+use crate::events::{self, BackendReloaded};
+use crate::notifications::{self, NotificationKind};
+use crate::{backend, jobs, profiles};
+
+// Saving a file, checking out a branch and an installer unpacking all touch many files in a row;
+// restart once they've stopped
+const SETTLE_DELAY: Duration = Duration::from_secs(2);
+
+// Matches how long the job tracker follows a job before giving up on it
+const DRAIN_TIMEOUT: Duration = Duration::from_secs(15 * 60);
+const DRAIN_POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+// Written by the backend as it runs, so changes there aren't changes to its code
+const IGNORED_DIRS: &[&str] = &["__pycache__", "generated_output", "logs", "config", "UI", "tests"];
+
+#[derive(Default)]
+pub struct HotSwapState {
+    watcher: Mutex<Option<RecommendedWatcher>>,
+    // Bumped by every change, so only the last of a burst restarts the backend
+    changes: AtomicU64,
+    changed: Mutex<Vec<PathBuf>>,
+    // Waiting for running jobs to finish; new prompts are queued until the backend is back
+    draining: AtomicBool,
+}
+
+pub fn is_draining(app_handle: &tauri::AppHandle) -> bool {
+    app_handle.state::<HotSwapState>().draining.load(Ordering::SeqCst)
+}
+
+fn is_code(source_dir: &Path, path: &Path) -> bool {
+    let relative = match path.strip_prefix(source_dir) {
+        Ok(relative) => relative,
+        Err(_) => return false,
+    };
+    let ignored = relative
+        .components()
+        .any(|part| IGNORED_DIRS.iter().any(|dir| part.as_os_str() == *dir));
+    let name = relative.file_name().map(|name| name.to_string_lossy().to_string()).unwrap_or_default();
+    !ignored && (name.ends_with(".py") || name == "requirements.txt")
+}
+
+// Let running jobs finish, then restart the backend on the new code
+fn reload(app_handle: &tauri::AppHandle) {
+    let state = app_handle.state::<HotSwapState>();
+    let files: Vec<String> = state
+        .changed
+        .lock()
+        .unwrap()
+        .drain(..)
+        .map(|path| path.display().to_string())
+        .collect();
+    println!("Backend code changed ({} files), restarting once running jobs finish", files.len());
+
+    state.draining.store(true, Ordering::SeqCst);
+    let started = Instant::now();
+    while jobs::running_count(app_handle) > 0 {
+        if started.elapsed() > DRAIN_TIMEOUT {
+            eprintln!("Jobs still running after {:?}, restarting the backend anyway", DRAIN_TIMEOUT);
+            break;
+        }
+        std::thread::sleep(DRAIN_POLL_INTERVAL);
+    }
+    let result = profiles::restart_backend(app_handle);
+    state.draining.store(false, Ordering::SeqCst);
+
+    match result {
+        Ok(()) => {
+            let body = format!("Its code changed in {} files", files.len());
+            notifications::push(app_handle, NotificationKind::BackendRestarted, "Backend reloaded", Some(body));
+            events::emit(app_handle, &BackendReloaded { files });
+        }
+        Err(e) => eprintln!("Failed to restart the backend on its updated code: {}", e),
+    }
+}
+
+fn on_change(app_handle: &tauri::AppHandle, source_dir: &Path, event: notify::Event) {
+    if event.kind.is_access() {
+        return;
+    }
+    let paths: Vec<PathBuf> = event.paths.into_iter().filter(|path| is_code(source_dir, path)).collect();
+    if paths.is_empty() {
+        return;
+    }
+    let state = app_handle.state::<HotSwapState>();
+    {
+        let mut changed = state.changed.lock().unwrap();
+        for path in paths {
+            if !changed.contains(&path) {
+                changed.push(path);
+            }
+        }
+    }
+    let generation = state.changes.fetch_add(1, Ordering::SeqCst) + 1;
+    let app_handle = app_handle.clone();
+    std::thread::spawn(move || {
+        std::thread::sleep(SETTLE_DELAY);
+        let state = app_handle.state::<HotSwapState>();
+        // A later change will do it, and a restart already underway picks this change up
+        if state.changes.load(Ordering::SeqCst) != generation || state.draining.load(Ordering::SeqCst) {
+            return;
+        }
+        reload(&app_handle);
+    });
+}
+
+// Watch the backend's sources, bundled or in a checkout, and restart it when they change
+pub fn start(app_handle: &tauri::AppHandle) {
+    let source_dir = match backend::source_dir(app_handle) {
+        Ok(dir) => dir,
+        Err(e) => return eprintln!("Not watching the backend for changes: {}", e),
+    };
+    // Events carry absolute paths, which don't start with a relative `../../src`
+    let source_dir = source_dir.canonicalize().unwrap_or(source_dir);
+    let handle = app_handle.clone();
+    let watched = source_dir.clone();
+    let watcher = notify::recommended_watcher(move |event: notify::Result<notify::Event>| match event {
+        Ok(event) => on_change(&handle, &watched, event),
+        Err(e) => eprintln!("Watching the backend for changes failed: {}", e),
+    });
+    let mut watcher = match watcher {
+        Ok(watcher) => watcher,
+        Err(e) => return eprintln!("Failed to watch {}: {}", source_dir.display(), e),
+    };
+    if let Err(e) = watcher.watch(&source_dir, RecursiveMode::Recursive) {
+        return eprintln!("Failed to watch {}: {}", source_dir.display(), e);
+    }
+    *app_handle.state::<HotSwapState>().watcher.lock().unwrap() = Some(watcher);
+}
@@ -0,0 +1,127 @@
+use serde::Serialize;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+use tauri::{Emitter, Manager};
+
+use crate::settings;
+
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+#[derive(Default)]
+pub struct IdleState {
+    idle: AtomicBool,
+}
+
+#[derive(Clone, Debug, Serialize)]
+struct IdleChanged {
+    idle: bool,
+}
+
+// Time since the last keyboard or mouse input anywhere in the session, where the OS says
+#[cfg(target_os = "windows")]
+fn idle_time() -> Option<Duration> {
+    use windows::Win32::System::SystemInformation::GetTickCount;
+    use windows::Win32::UI::Input::KeyboardAndMouse::{GetLastInputInfo, LASTINPUTINFO};
+
+    let mut info = LASTINPUTINFO {
+        cbSize: std::mem::size_of::<LASTINPUTINFO>() as u32,
+        dwTime: 0,
+    };
+    if !unsafe { GetLastInputInfo(&mut info) }.as_bool() {
+        return None;
+    }
+    // Both wrap around after 49 days
+    let elapsed = unsafe { GetTickCount() }.wrapping_sub(info.dwTime);
+    Some(Duration::from_millis(elapsed as u64))
+}
+
+#[cfg(target_os = "macos")]
+fn idle_time() -> Option<Duration> {
+    // kCGEventSourceStateCombinedSessionState and kCGAnyInputEventType
+    const COMBINED_SESSION_STATE: i32 = 0;
+    const ANY_INPUT_EVENT: u32 = !0;
+
+    #[link(name = "CoreGraphics", kind = "framework")]
+    extern "C" {
+        fn CGEventSourceSecondsSinceLastEventType(state: i32, event_type: u32) -> f64;
+    }
+
+    let seconds = unsafe { CGEventSourceSecondsSinceLastEventType(COMBINED_SESSION_STATE, ANY_INPUT_EVENT) };
+    if seconds.is_finite() && seconds >= 0.0 {
+        Some(Duration::from_secs_f64(seconds))
+    } else {
+        None
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn idle_time() -> Option<Duration> {
+    use gtk::gio;
+
+    // GNOME's compositor keeps track itself, which also works on Wayland; KDE answers as a screen saver
+    let connection = gio::bus_get_sync(gio::BusType::Session, gio::Cancellable::NONE).ok()?;
+    let ask = |name: &str, path: &str, method: &str| {
+        connection
+            .call_sync(
+                Some(name),
+                path,
+                name,
+                method,
+                None,
+                None,
+                gio::DBusCallFlags::NO_AUTO_START,
+                1000,
+                gio::Cancellable::NONE,
+            )
+            .ok()
+    };
+    let millis = ask("org.gnome.Mutter.IdleMonitor", "/org/gnome/Mutter/IdleMonitor/Core", "GetIdletime")
+        .and_then(|reply| reply.get::<(u64,)>())
+        .map(|(millis,)| millis)
+        .or_else(|| {
+            ask("org.freedesktop.ScreenSaver", "/org/freedesktop/ScreenSaver", "GetSessionIdleTime")
+                .and_then(|reply| reply.get::<(u32,)>())
+                .map(|(millis,)| millis as u64)
+        })?;
+    Some(Duration::from_millis(millis))
+}
+
+#[cfg(not(any(target_os = "windows", target_os = "macos", target_os = "linux")))]
+fn idle_time() -> Option<Duration> {
+    None
+}
+
+// Whether background work may run now. Without a way to tell, the user counts as away, so work isn't
+// put off forever.
+pub fn is_idle(app_handle: &tauri::AppHandle) -> bool {
+    app_handle.state::<IdleState>().idle.load(Ordering::SeqCst)
+        || !settings::current(app_handle).idle.defer_background_work
+}
+
+// Hold a background task until the user steps away. Called between steps of long work too, so it
+// pauses as soon as they come back.
+pub fn wait_until_idle(app_handle: &tauri::AppHandle) {
+    while !is_idle(app_handle) {
+        std::thread::sleep(POLL_INTERVAL);
+    }
+}
+
+pub fn start_watching(app_handle: &tauri::AppHandle) {
+    let app_handle = app_handle.clone();
+    std::thread::spawn(move || loop {
+        let threshold = Duration::from_secs(settings::current(&app_handle).idle.idle_after_secs);
+        let idle = idle_time().map_or(true, |idle| idle >= threshold);
+        if app_handle.state::<IdleState>().idle.swap(idle, Ordering::SeqCst) != idle {
+            println!("User {}", if idle { "idle" } else { "active" });
+            if let Err(e) = app_handle.emit("user-idle-changed", IdleChanged { idle }) {
+                eprintln!("Failed to emit user-idle-changed: {}", e);
+            }
+        }
+        std::thread::sleep(POLL_INTERVAL);
+    });
+}
+
+#[tauri::command]
+pub fn is_user_idle(state: tauri::State<IdleState>) -> bool {
+    state.idle.load(Ordering::SeqCst)
+}
@@ -0,0 +1,162 @@
+use enigo::{Direction, Enigo, Key, Keyboard, Settings};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+
+use crate::{active_app, app_profiles, settings};
+
+pub type InputCallback = Box<dyn Fn(&rdev::Event) + Send>;
+
+// Set while we are injecting synthetic input so listeners ignore our own events
+static INJECTING: AtomicBool = AtomicBool::new(false);
+
+// Set while the session is locked or switched away from, until the user says input may go on
+static SUSPENDED: AtomicBool = AtomicBool::new(false);
+
+// Shared global keyboard/mouse listener. rdev only supports a single listener per
+// process, so every feature subscribes here instead of calling rdev::listen itself.
+#[derive(Clone, Default)]
+pub struct InputHub {
+    subscribers: Arc<Mutex<Vec<(String, InputCallback)>>>,
+    started: Arc<Mutex<bool>>,
+}
+
+impl InputHub {
+    // Register a named callback, starting the listener thread on first use
+    pub fn subscribe(&self, name: &str, callback: InputCallback) {
+        {
+            let mut subscribers = self.subscribers.lock().unwrap();
+            subscribers.retain(|(existing, _)| existing != name);
+            subscribers.push((name.to_string(), callback));
+        }
+        self.start();
+    }
+
+    pub fn unsubscribe(&self, name: &str) {
+        self.subscribers.lock().unwrap().retain(|(existing, _)| existing != name);
+    }
+
+    fn start(&self) {
+        let mut started = self.started.lock().unwrap();
+        if *started {
+            return;
+        }
+        *started = true;
+
+        let subscribers = self.subscribers.clone();
+        let started_flag = self.started.clone();
+        std::thread::spawn(move || {
+            println!("Starting global input listener");
+            let result = rdev::listen(move |event| {
+                if INJECTING.load(Ordering::SeqCst) {
+                    return;
+                }
+                for (_, callback) in subscribers.lock().unwrap().iter() {
+                    callback(&event);
+                }
+            });
+
+            // listen only returns on failure (e.g. missing accessibility permission)
+            if let Err(e) = result {
+                eprintln!("Global input listener failed: {:?}", e);
+            }
+            *started_flag.lock().unwrap() = false;
+        });
+    }
+}
+
+// Refuse synthetic input for the frontmost application if settings or its profile don't allow it.
+// Checked before every event, since what's in front can change in the middle of a job.
+pub fn check_target(app_handle: &tauri::AppHandle) -> Result<(), String> {
+    if SUSPENDED.load(Ordering::SeqCst) {
+        return Err("Input is suspended while the session is locked".to_string());
+    }
+    let input = settings::current(app_handle).input;
+    let app = match active_app::frontmost_app() {
+        Some(app) if app.process_id == std::process::id() as u64 => return Ok(()),
+        Some(app) => app,
+        // Without knowing what's in front there's no telling whether it's blocklisted
+        None if !input.blocked_apps.is_empty() || !input.allowed_apps.is_empty() => {
+            return Err("The application in front can't be identified, so it can't be controlled".to_string())
+        }
+        None => return Ok(()),
+    };
+    if input.blocked_apps.iter().any(|pattern| app.matches(pattern)) {
+        return Err(format!("{} is blocklisted, so it can't be controlled", app.name));
+    }
+    if !input.allowed_apps.is_empty() && !input.allowed_apps.iter().any(|pattern| app.matches(pattern)) {
+        return Err(format!("{} isn't on the list of applications that may be controlled", app.name));
+    }
+    app_profiles::check_input(app_handle, &app)
+}
+
+// Refuse all synthetic input, e.g. while the screen is locked
+pub fn suspend(suspended: bool) {
+    SUSPENDED.store(suspended, Ordering::SeqCst);
+}
+
+// Run a block of synthetic input while listeners are muted. A block sending several events checks
+// check_target before each of them itself.
+pub fn inject<F>(app_handle: &tauri::AppHandle, f: F) -> Result<(), String>
+where
+    F: FnOnce(&mut Enigo) -> Result<(), String>,
+{
+    check_target(app_handle)?;
+    let mut enigo = Enigo::new(&Settings::default())
+        .map_err(|e| format!("Failed to initialize input injection: {}", e))?;
+
+    INJECTING.store(true, Ordering::SeqCst);
+    let result = f(&mut enigo);
+    INJECTING.store(false, Ordering::SeqCst);
+    result
+}
+
+// Type text into the frontmost application
+pub fn type_text(app_handle: &tauri::AppHandle, text: &str) -> Result<(), String> {
+    inject(app_handle, |enigo| {
+        enigo
+            .text(text)
+            .map_err(|e| format!("Failed to type text: {}", e))
+    })
+}
+
+// Copy whatever is selected in the frontmost application to the clipboard
+pub fn press_copy(app_handle: &tauri::AppHandle) -> Result<(), String> {
+    let modifier = if cfg!(target_os = "macos") { Key::Meta } else { Key::Control };
+    inject(app_handle, |enigo| {
+        let press = |enigo: &mut Enigo, key, direction| {
+            enigo
+                .key(key, direction)
+                .map_err(|e| format!("Failed to press the copy shortcut: {}", e))
+        };
+        press(enigo, modifier, Direction::Press)?;
+        let copied = press(enigo, Key::Unicode('c'), Direction::Click);
+        // Released even if the click failed, so the modifier isn't left held down
+        press(enigo, modifier, Direction::Release)?;
+        copied
+    })
+}
+
+// Send one raw event, e.g. a step of a recorded macro, without listeners seeing it.
+// Releases always go through, so a refused replay can't leave a key held down.
+pub fn simulate(app_handle: &tauri::AppHandle, event: &rdev::EventType) -> Result<(), String> {
+    if !matches!(event, rdev::EventType::KeyRelease(_) | rdev::EventType::ButtonRelease(_)) {
+        check_target(app_handle)?;
+    }
+    INJECTING.store(true, Ordering::SeqCst);
+    let result = rdev::simulate(event).map_err(|_| format!("Failed to simulate {:?}", event));
+    INJECTING.store(false, Ordering::SeqCst);
+    result
+}
+
+// Erase characters before the cursor in the frontmost application
+pub fn press_backspace(app_handle: &tauri::AppHandle, count: usize) -> Result<(), String> {
+    inject(app_handle, |enigo| {
+        for _ in 0..count {
+            check_target(app_handle)?;
+            enigo
+                .key(Key::Backspace, Direction::Click)
+                .map_err(|e| format!("Failed to press backspace: {}", e))?;
+        }
+        Ok(())
+    })
+}
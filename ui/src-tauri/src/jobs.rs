@@ -0,0 +1,498 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tauri::Manager;
+use tauri_plugin_dialog::{DialogExt, MessageDialogButtons, MessageDialogKind};
+
+use crate::events::{self, JobInputBlocked, JobsInterrupted};
+use crate::metrics::{self, JobOutcome};
+use crate::notifications::{self, NotificationKind};
+use crate::protocol::{self, JobParams, RunParams, StopParams};
+use crate::taskbar::{self, TaskbarState};
+use crate::util::now_secs;
+use crate::{approvals, badge, cache, input, scripts, storage, stream};
+
+// Jobs that haven't finished yet, rewritten on every step so a crash can't lose them
+const JOURNAL_FILE: &str = "job_journal.json";
+
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+// Long enough for every retry of a slow job
+const TRACK_TIMEOUT: Duration = Duration::from_secs(15 * 60);
+
+// How long the backend may be unreachable before a job is given up on
+const UNREACHABLE_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum JobPhase {
+    // Generating code
+    Planning,
+    // Running the generated script
+    Executing,
+    Completed,
+    Failed,
+    Stopped,
+}
+
+impl JobPhase {
+    fn from_job(status: Option<&str>, phase: Option<&str>) -> Self {
+        match (status, phase) {
+            (Some("completed"), _) => JobPhase::Completed,
+            (Some("failed"), _) => JobPhase::Failed,
+            (Some("stopped"), _) => JobPhase::Stopped,
+            (_, Some("executing")) => JobPhase::Executing,
+            _ => JobPhase::Planning,
+        }
+    }
+
+    pub fn is_finished(self) -> bool {
+        matches!(self, JobPhase::Completed | JobPhase::Failed | JobPhase::Stopped)
+    }
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct JobProgress {
+    pub job_id: String,
+    pub phase: JobPhase,
+    // Share of the script's time budget used, while executing
+    pub percent: Option<u8>,
+    pub attempt: Option<u32>,
+    pub max_attempts: Option<u32>,
+}
+
+// The last step the backend confirmed for an unfinished job
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct JournalEntry {
+    pub job_id: String,
+    pub prompt: Option<String>,
+    pub phase: JobPhase,
+    pub attempt: Option<u32>,
+    pub max_attempts: Option<u32>,
+    pub updated: u64,
+}
+
+impl JournalEntry {
+    fn step(&self) -> String {
+        let phase = match self.phase {
+            JobPhase::Executing => "running its script",
+            _ => "planning",
+        };
+        match (self.attempt, self.max_attempts) {
+            (Some(attempt), Some(max_attempts)) => format!("{}, attempt {} of {}", phase, attempt, max_attempts),
+            _ => phase.to_string(),
+        }
+    }
+}
+
+// Jobs the shell follows so native UI can show their progress while the spotlight is out of sight
+#[derive(Default)]
+pub struct JobsState {
+    running: Mutex<HashMap<String, JobProgress>>,
+    journal: Mutex<HashMap<String, JournalEntry>>,
+    // Jobs the last run (or a backend that went away) never saw finish
+    interrupted: Mutex<Vec<JournalEntry>>,
+    // A job failed since the user last looked at the spotlight
+    failed: AtomicBool,
+    // Jobs that completed while the spotlight was hidden, counted on the app icon's badge
+    unread: AtomicUsize,
+}
+
+// Combined state of every running job, for the one taskbar button
+fn taskbar_state(state: &JobsState) -> TaskbarState {
+    let running = state.running.lock().unwrap();
+    let executing: Vec<u8> = running
+        .values()
+        .filter(|job| job.phase == JobPhase::Executing)
+        .map(|job| job.percent.unwrap_or(0))
+        .collect();
+    if !executing.is_empty() {
+        // The least advanced job decides, so the bar never runs ahead of what's left
+        TaskbarState::Normal(executing.into_iter().min().unwrap_or(0))
+    } else if !running.is_empty() {
+        TaskbarState::Indeterminate
+    } else if state.failed.load(Ordering::SeqCst) {
+        TaskbarState::Error
+    } else {
+        TaskbarState::None
+    }
+}
+
+// Both the jobs being followed and the interrupted ones waiting to be run again or discarded, so
+// neither is lost if the app closes again first. Lock the journal before `interrupted`.
+fn save_journal(
+    app_handle: &tauri::AppHandle,
+    journal: &HashMap<String, JournalEntry>,
+    interrupted: &[JournalEntry],
+    data: storage::Data,
+) {
+    let entries: Vec<&JournalEntry> = journal.values().chain(interrupted).collect();
+    if let Err(e) = storage::save_json(app_handle, JOURNAL_FILE, &entries, data) {
+        eprintln!("Failed to save the job journal: {}", e);
+    }
+}
+
+fn record_step(app_handle: &tauri::AppHandle, progress: &JobProgress, prompt: Option<String>) {
+    let state = app_handle.state::<JobsState>();
+    let mut journal = state.journal.lock().unwrap();
+    let data = if progress.phase.is_finished() {
+        journal.remove(&progress.job_id);
+        storage::Data::Removal
+    } else if !storage::may_record(app_handle) {
+        // The journal is kept on disk, so a job run in privacy mode isn't offered again after a crash
+        return;
+    } else {
+        let prompt = prompt.or_else(|| journal.get(&progress.job_id).and_then(|entry| entry.prompt.clone()));
+        let entry = JournalEntry {
+            job_id: progress.job_id.clone(),
+            prompt,
+            phase: progress.phase,
+            attempt: progress.attempt,
+            max_attempts: progress.max_attempts,
+            updated: now_secs(),
+        };
+        journal.insert(progress.job_id.clone(), entry);
+        storage::Data::Activity
+    };
+    save_journal(app_handle, &journal, &state.interrupted.lock().unwrap(), data);
+}
+
+// Stop following a job without it having finished; `interrupted` keeps it to offer again
+fn forget(app_handle: &tauri::AppHandle, job_id: &str, interrupted: bool) {
+    let state = app_handle.state::<JobsState>();
+    state.running.lock().unwrap().remove(job_id);
+    cache::job_finished(app_handle, job_id, None);
+    stream::finish(app_handle, job_id, None);
+    let interrupted = {
+        let mut journal = state.journal.lock().unwrap();
+        let mut kept = state.interrupted.lock().unwrap();
+        let entry = journal.remove(job_id);
+        let added = match (interrupted, entry) {
+            (true, Some(entry)) => {
+                kept.push(entry);
+                true
+            }
+            _ => false,
+        };
+        save_journal(app_handle, &journal, &kept, storage::Data::Removal);
+        added.then(|| kept.clone())
+    };
+    if let Some(interrupted) = interrupted {
+        events::emit(app_handle, &JobsInterrupted(&interrupted));
+    }
+    taskbar::show(app_handle, taskbar_state(&state));
+}
+
+fn update(app_handle: &tauri::AppHandle, progress: JobProgress, prompt: Option<String>) {
+    let state = app_handle.state::<JobsState>();
+    {
+        let mut running = state.running.lock().unwrap();
+        if progress.phase.is_finished() {
+            running.remove(&progress.job_id);
+        } else {
+            running.insert(progress.job_id.clone(), progress.clone());
+        }
+    }
+    record_step(app_handle, &progress, prompt);
+    match progress.phase {
+        JobPhase::Completed => {
+            let hidden = app_handle
+                .get_webview_window("main")
+                .map(|window| !window.is_visible().unwrap_or(false))
+                .unwrap_or(true);
+            if hidden {
+                badge::show(app_handle, state.unread.fetch_add(1, Ordering::SeqCst) + 1);
+            }
+        }
+        JobPhase::Failed => state.failed.store(true, Ordering::SeqCst),
+        // A new job replaces the previous failure on the taskbar
+        JobPhase::Planning => state.failed.store(false, Ordering::SeqCst),
+        _ => {}
+    }
+
+    taskbar::show(app_handle, taskbar_state(&state));
+    events::emit(app_handle, &progress);
+}
+
+// The job's progress, prompt and latest result. A script held for approval is reviewed here.
+fn poll(app_handle: &tauri::AppHandle, job_id: &str) -> Result<(JobProgress, Option<String>, Option<String>), String> {
+    let job = protocol::call::<protocol::GetJob>(&JobParams {
+        job_id: job_id.to_string(),
+    })?;
+    if job.phase.as_deref() == Some(approvals::AWAITING_PHASE) {
+        approvals::review(app_handle, &job);
+    }
+    let progress = JobProgress {
+        job_id: job_id.to_string(),
+        phase: JobPhase::from_job(job.status.as_deref(), job.phase.as_deref()),
+        percent: job.progress,
+        attempt: job.attempt,
+        max_attempts: job.max_attempts,
+    };
+    Ok((progress, job.prompt, job.last_result))
+}
+
+// Stop a job from the shell's side; the backend keeps the reason as its result
+pub fn stop(job_id: &str, reason: &str) -> Result<(), String> {
+    let params = StopParams {
+        job_id: job_id.to_string(),
+        reason: Some(reason.to_string()),
+    };
+    let stopped = protocol::call::<protocol::StopJob>(&params)?;
+    println!("Stopped job {}: {}", stopped.job_id, reason);
+    Ok(())
+}
+
+pub fn stop_all(app_handle: &tauri::AppHandle, reason: &str) {
+    let running: Vec<String> = app_handle.state::<JobsState>().running.lock().unwrap().keys().cloned().collect();
+    for job_id in running {
+        if let Err(e) = stop(&job_id, reason) {
+            eprintln!("Failed to stop job {}: {}", job_id, e);
+        }
+    }
+}
+
+// A running script drives the keyboard and mouse itself, so it's stopped as soon as an application
+// it mustn't control comes to the front. Returns whether it was.
+fn guard_input(app_handle: &tauri::AppHandle, job_id: &str) -> bool {
+    let reason = match input::check_target(app_handle) {
+        Ok(()) => return false,
+        Err(reason) => reason,
+    };
+    if let Err(e) = stop(job_id, &reason) {
+        eprintln!("Failed to stop job {}: {}", job_id, e);
+        return false;
+    }
+    events::emit(
+        app_handle,
+        &JobInputBlocked {
+            job_id,
+            message: &reason,
+        },
+    );
+    true
+}
+
+// Follow a submitted job until it finishes, reporting each change of phase or progress
+pub fn track(app_handle: &tauri::AppHandle, job_id: &str) {
+    if app_handle.state::<JobsState>().running.lock().unwrap().contains_key(job_id) {
+        return;
+    }
+    metrics::record_job_started();
+    update(
+        app_handle,
+        JobProgress {
+            job_id: job_id.to_string(),
+            phase: JobPhase::Planning,
+            percent: None,
+            attempt: None,
+            max_attempts: None,
+        },
+        None,
+    );
+
+    let app_handle = app_handle.clone();
+    let job_id = job_id.to_string();
+    std::thread::spawn(move || {
+        let started = Instant::now();
+        let mut last_reached = Instant::now();
+        let mut last: Option<(JobPhase, Option<u8>, Option<u32>)> = None;
+        let mut stopped = false;
+        while started.elapsed() < TRACK_TIMEOUT {
+            std::thread::sleep(POLL_INTERVAL);
+            let (progress, prompt, result) = match poll(&app_handle, &job_id) {
+                Ok(polled) => polled,
+                Err(e) if last_reached.elapsed() > UNREACHABLE_TIMEOUT => {
+                    // The backend went away with the job, so it can be offered again like after a crash
+                    eprintln!("Lost track of job {}: {}", job_id, e);
+                    metrics::record_job_finished(JobOutcome::Lost);
+                    forget(&app_handle, &job_id, true);
+                    return;
+                }
+                Err(_) => continue,
+            };
+            last_reached = Instant::now();
+            if progress.phase == JobPhase::Executing && !stopped {
+                stopped = guard_input(&app_handle, &job_id);
+            }
+
+            let current = (progress.phase, progress.percent, progress.attempt);
+            if last != Some(current) {
+                last = Some(current);
+                let phase = progress.phase;
+                let body = prompt.clone();
+                update(&app_handle, progress, prompt);
+                if phase.is_finished() {
+                    notify_finished(&app_handle, phase, body.or_else(|| result.clone()));
+                    metrics::record_job_finished(match phase {
+                        JobPhase::Completed => JobOutcome::Completed,
+                        JobPhase::Failed => JobOutcome::Failed,
+                        _ => JobOutcome::Stopped,
+                    });
+                    let result = if phase == JobPhase::Completed { result } else { None };
+                    cache::job_finished(&app_handle, &job_id, result);
+                    scripts::job_finished(&app_handle, &job_id);
+                    stream::finish(&app_handle, &job_id, Some(phase));
+                    return;
+                }
+            }
+        }
+        // Don't leave the taskbar showing a job we no longer know anything about
+        metrics::record_job_finished(JobOutcome::Lost);
+        forget(&app_handle, &job_id, false);
+    });
+}
+
+fn notify_finished(app_handle: &tauri::AppHandle, phase: JobPhase, prompt: Option<String>) {
+    let (kind, title) = match phase {
+        JobPhase::Completed => (NotificationKind::JobCompleted, "Automation finished"),
+        JobPhase::Failed => (NotificationKind::JobFailed, "Automation failed"),
+        _ => (NotificationKind::JobStopped, "Automation stopped"),
+    };
+    notifications::push(app_handle, kind, title, prompt);
+}
+
+// Pick up the jobs an earlier run was following when it ended
+pub fn restore_journal(app_handle: &tauri::AppHandle) {
+    let entries: Vec<JournalEntry> = storage::load_json(app_handle, JOURNAL_FILE);
+    if entries.is_empty() {
+        return;
+    }
+    println!("{} job(s) were interrupted by the last exit", entries.len());
+    // They stay in the journal file until each is run again or discarded
+    let state = app_handle.state::<JobsState>();
+    let journal = state.journal.lock().unwrap();
+    let mut interrupted = state.interrupted.lock().unwrap();
+    interrupted.extend(entries);
+    save_journal(app_handle, &journal, &interrupted, storage::Data::Removal);
+}
+
+// Take an interrupted job off the list, and out of the journal file
+fn take_interrupted(app_handle: &tauri::AppHandle, job_id: &str) -> Result<JournalEntry, String> {
+    let state = app_handle.state::<JobsState>();
+    let journal = state.journal.lock().unwrap();
+    let mut interrupted = state.interrupted.lock().unwrap();
+    let index = interrupted
+        .iter()
+        .position(|entry| entry.job_id == job_id)
+        .ok_or_else(|| format!("No interrupted job {}", job_id))?;
+    let entry = interrupted.remove(index);
+    save_journal(app_handle, &journal, &interrupted, storage::Data::Removal);
+    Ok(entry)
+}
+
+// Submit an interrupted job's prompt again as a new job. Nothing carries over: its script can't
+// continue from where it was cut off, so this generates and runs everything from the start.
+fn rerun(app_handle: &tauri::AppHandle, job_id: &str) -> Result<String, String> {
+    let prompt = {
+        let state = app_handle.state::<JobsState>();
+        let interrupted = state.interrupted.lock().unwrap();
+        let entry = interrupted
+            .iter()
+            .find(|entry| entry.job_id == job_id)
+            .ok_or_else(|| format!("No interrupted job {}", job_id))?;
+        entry
+            .prompt
+            .clone()
+            .ok_or_else(|| format!("Job {} can't be run again: its prompt is unknown", job_id))?
+    };
+    let submitted = protocol::call::<protocol::RunJob>(&RunParams::new(prompt, None))?;
+    // Only once the new job exists, so a failed submission leaves it to try again
+    take_interrupted(app_handle, job_id)?;
+    track(app_handle, &submitted.job_id);
+    Ok(submitted.job_id)
+}
+
+// Once the backend is up, ask whether to run again what the last run left unfinished
+pub fn offer_rerun(app_handle: &tauri::AppHandle) {
+    let entries = app_handle.state::<JobsState>().interrupted.lock().unwrap().clone();
+    if entries.is_empty() {
+        return;
+    }
+    events::emit(app_handle, &JobsInterrupted(&entries));
+
+    let app_handle = app_handle.clone();
+    std::thread::spawn(move || {
+        let list: Vec<String> = entries
+            .iter()
+            .map(|entry| {
+                let prompt = entry.prompt.as_deref().unwrap_or("(unknown prompt)");
+                format!("• {} ({})", prompt, entry.step())
+            })
+            .collect();
+        let message = format!(
+            "These automations were still running when Krya.ai last closed:\n\n{}\n\n\
+             They can't pick up where they stopped. Running them again starts each prompt over as a \
+             new automation, from generating its script onwards.",
+            list.join("\n")
+        );
+        let rerun_all = app_handle
+            .dialog()
+            .message(message)
+            .title("Run interrupted automations again?")
+            .kind(MessageDialogKind::Warning)
+            .buttons(MessageDialogButtons::OkCancelCustom(
+                "Run again".to_string(),
+                "Discard".to_string(),
+            ))
+            .blocking_show();
+
+        for entry in entries {
+            // One without a prompt can't be run again, so it goes either way
+            let result = if rerun_all && entry.prompt.is_some() {
+                rerun(&app_handle, &entry.job_id).map(|_| ())
+            } else {
+                take_interrupted(&app_handle, &entry.job_id).map(|_| ())
+            };
+            if let Err(e) = result {
+                eprintln!("{}", e);
+            }
+        }
+        let remaining = app_handle.state::<JobsState>().interrupted.lock().unwrap().clone();
+        events::emit(&app_handle, &JobsInterrupted(&remaining));
+    });
+}
+
+// The user is looking at the spotlight, so its results are seen and a failure needn't be flagged any more
+pub fn acknowledge(app_handle: &tauri::AppHandle) {
+    let state = app_handle.state::<JobsState>();
+    if state.failed.swap(false, Ordering::SeqCst) {
+        taskbar::show(app_handle, taskbar_state(&state));
+    }
+    if state.unread.swap(0, Ordering::SeqCst) > 0 {
+        badge::show(app_handle, 0);
+    }
+}
+
+// Command for the UI to follow a job it submitted outside the prompt history
+#[tauri::command]
+pub fn track_job(app_handle: tauri::AppHandle, job_id: String) {
+    track(&app_handle, &job_id);
+}
+
+pub fn running_count(app_handle: &tauri::AppHandle) -> usize {
+    app_handle.state::<JobsState>().running.lock().unwrap().len()
+}
+
+#[tauri::command]
+pub fn get_running_jobs(jobs: tauri::State<JobsState>) -> Vec<JobProgress> {
+    jobs.running.lock().unwrap().values().cloned().collect()
+}
+
+#[tauri::command]
+pub fn get_interrupted_jobs(jobs: tauri::State<JobsState>) -> Vec<JournalEntry> {
+    jobs.interrupted.lock().unwrap().clone()
+}
+
+// Submits the prompt again as a new job, from the start, and returns that job's id
+#[tauri::command(async)]
+pub fn rerun_interrupted_job(app_handle: tauri::AppHandle, job_id: String) -> Result<String, String> {
+    rerun(&app_handle, &job_id)
+}
+
+#[tauri::command]
+pub fn discard_interrupted_job(app_handle: tauri::AppHandle, job_id: String) -> Result<(), String> {
+    take_interrupted(&app_handle, &job_id).map(|_| ())
+}
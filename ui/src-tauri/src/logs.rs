@@ -0,0 +1,725 @@
+use chrono::{DateTime, FixedOffset, SecondsFormat};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufRead, BufReader, Read, Seek, SeekFrom, Write};
+use std::path::PathBuf;
+use std::process::Stdio;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use tauri::{Emitter, Manager};
+use tauri_plugin_dialog::DialogExt;
+
+use crate::{paths, settings, storage};
+
+pub const APP_LOG_FILE: &str = "krya.log";
+pub const BACKEND_LOG_FILE: &str = "backend.log";
+
+// Recent lines kept in memory for the console's filters
+const BUFFER_LINES: usize = 5000;
+
+// Window that streamed log lines are sent to
+const CONSOLE_WINDOW: &str = "console";
+
+// Rotated copies kept of each log, from krya.log.1 (newest) to krya.log.5
+const ROTATED_LOGS: usize = 5;
+
+// How long a flush waits for the capture threads to write out what's still in the pipes
+const FLUSH_GRACE: std::time::Duration = std::time::Duration::from_millis(250);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LogSource {
+    Rust,
+    Python,
+}
+
+impl LogSource {
+    pub fn name(self) -> &'static str {
+        match self {
+            LogSource::Rust => "rust",
+            LogSource::Python => "python",
+        }
+    }
+
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "rust" => Some(LogSource::Rust),
+            "python" => Some(LogSource::Python),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LogLevel {
+    Debug,
+    Info,
+    Warn,
+    Error,
+}
+
+impl LogLevel {
+    pub fn name(self) -> &'static str {
+        match self {
+            LogLevel::Debug => "DEBUG",
+            LogLevel::Info => "INFO",
+            LogLevel::Warn => "WARN",
+            LogLevel::Error => "ERROR",
+        }
+    }
+
+    // Also accepts Python's level names
+    pub fn parse(name: &str) -> Option<Self> {
+        match name.trim().to_ascii_uppercase().as_str() {
+            "DEBUG" => Some(LogLevel::Debug),
+            "INFO" | "SUCCESS" => Some(LogLevel::Info),
+            "WARN" | "WARNING" => Some(LogLevel::Warn),
+            "ERROR" | "CRITICAL" => Some(LogLevel::Error),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct LogLine {
+    // Order within this run; lines read back from files have none
+    pub seq: Option<u64>,
+    pub timestamp: String,
+    pub source: LogSource,
+    pub level: LogLevel,
+    pub message: String,
+}
+
+impl LogLine {
+    // One line of a persisted log: "<RFC 3339 timestamp> <LEVEL> <source>: <message>"
+    pub fn format(&self) -> String {
+        format!("{} {} {}: {}", self.timestamp, self.level.name(), self.source.name(), self.message)
+    }
+
+    // Read back a line written by format; lines from before tagging return None
+    pub fn parse(line: &str) -> Option<Self> {
+        let (timestamp, rest) = line.split_once(' ')?;
+        DateTime::parse_from_rfc3339(timestamp).ok()?;
+        let (level, rest) = rest.split_once(' ')?;
+        let (source, message) = rest.split_once(": ")?;
+        Some(LogLine {
+            seq: None,
+            timestamp: timestamp.to_string(),
+            source: LogSource::parse(source)?,
+            level: LogLevel::parse(level)?,
+            message: message.to_string(),
+        })
+    }
+}
+
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct LogFilter {
+    // Empty means every source
+    pub sources: Vec<LogSource>,
+    pub min_level: Option<LogLevel>,
+    // Case-insensitive substring of the message
+    pub contains: Option<String>,
+}
+
+impl LogFilter {
+    pub fn matches(&self, line: &LogLine) -> bool {
+        (self.sources.is_empty() || self.sources.contains(&line.source))
+            && self.min_level.map_or(true, |min| line.level >= min)
+            && self
+                .contains
+                .as_ref()
+                .map_or(true, |text| line.message.to_lowercase().contains(&text.to_lowercase()))
+    }
+}
+
+// Captured lines of this run, and the filter the console is streaming with, if any
+pub struct LogBuffer {
+    lines: Mutex<VecDeque<LogLine>>,
+    next_seq: AtomicU64,
+    stream: Mutex<Option<LogFilter>>,
+    // One writer per log file, shared by the threads capturing a process's stdout and stderr
+    writers: Mutex<HashMap<PathBuf, Arc<Mutex<LogWriter>>>>,
+    // Kept here rather than read from settings per line, which would lock them on every write
+    max_log_bytes: AtomicU64,
+}
+
+fn megabytes(mb: u64) -> u64 {
+    mb.max(1) * 1024 * 1024
+}
+
+impl Default for LogBuffer {
+    fn default() -> Self {
+        LogBuffer {
+            lines: Mutex::default(),
+            next_seq: AtomicU64::new(0),
+            stream: Mutex::default(),
+            writers: Mutex::default(),
+            // Logs are captured before settings are loaded
+            max_log_bytes: AtomicU64::new(megabytes(settings::StorageSettings::default().max_log_mb)),
+        }
+    }
+}
+
+// Pick up a new rotation size from settings
+pub fn apply_settings(app_handle: &tauri::AppHandle) {
+    let max_mb = settings::current(app_handle).storage.max_log_mb;
+    app_handle
+        .state::<LogBuffer>()
+        .max_log_bytes
+        .store(megabytes(max_mb), Ordering::Relaxed);
+}
+
+pub fn rotated_path(path: &std::path::Path, index: usize) -> PathBuf {
+    let name = path.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+    path.with_file_name(format!("{}.{}", name, index))
+}
+
+// Appends to a log file, moving it aside once it's over the size cap
+struct LogWriter {
+    path: PathBuf,
+    file: Option<File>,
+    size: u64,
+}
+
+impl LogWriter {
+    fn open(path: PathBuf) -> std::io::Result<Self> {
+        let file = open_append(&path)?;
+        let size = file.metadata()?.len();
+        Ok(LogWriter {
+            path,
+            file: Some(file),
+            size,
+        })
+    }
+
+    fn write_line(&mut self, line: &str, max_bytes: u64) {
+        if self.size >= max_bytes {
+            self.rotate();
+        }
+        if let Some(file) = self.file.as_mut() {
+            if writeln!(file, "{}", line).is_ok() {
+                self.size += line.len() as u64 + 1;
+            }
+        }
+    }
+
+    fn rotate(&mut self) {
+        // Windows can't rename a file that's still open
+        self.file = None;
+        for index in (1..ROTATED_LOGS).rev() {
+            let _ = fs::rename(rotated_path(&self.path, index), rotated_path(&self.path, index + 1));
+        }
+        let _ = fs::rename(&self.path, rotated_path(&self.path, 1));
+        self.file = open_append(&self.path).ok();
+        self.size = 0;
+    }
+}
+
+fn writer(app_handle: &tauri::AppHandle, path: PathBuf) -> Option<Arc<Mutex<LogWriter>>> {
+    let buffer = app_handle.state::<LogBuffer>();
+    let mut writers = buffer.writers.lock().unwrap();
+    if let Some(writer) = writers.get(&path) {
+        return Some(writer.clone());
+    }
+    let writer = Arc::new(Mutex::new(LogWriter::open(path.clone()).ok()?));
+    writers.insert(path, writer.clone());
+    Some(writer)
+}
+
+// Resolve the log directory and start capturing our own output.
+// Release builds send stdout/stderr to krya.log; debug builds keep printing to the terminal.
+pub fn init(app_handle: &tauri::AppHandle) {
+    app_handle.manage(LogBuffer::default());
+
+    let dir = match log_dir(app_handle) {
+        Some(dir) => dir,
+        None => return,
+    };
+
+    if cfg!(not(debug_assertions)) {
+        match writer(app_handle, dir.join(APP_LOG_FILE)) {
+            Some(writer) => redirect_output(app_handle, writer),
+            None => eprintln!("Failed to open app log"),
+        }
+    }
+}
+
+// Get everything logged so far onto disk, e.g. before a crash takes the process down.
+// Only tries each lock, since the caller may be a thread that panicked while holding one.
+pub fn flush(app_handle: &tauri::AppHandle) {
+    let _ = std::io::stdout().flush();
+    if cfg!(not(debug_assertions)) {
+        std::thread::sleep(FLUSH_GRACE);
+    }
+
+    let buffer = match app_handle.try_state::<LogBuffer>() {
+        Some(buffer) => buffer,
+        None => return,
+    };
+    let writers = match buffer.writers.try_lock() {
+        Ok(writers) => writers,
+        Err(_) => return,
+    };
+    for writer in writers.values() {
+        if let Ok(mut writer) = writer.try_lock() {
+            if let Some(file) = writer.file.as_mut() {
+                let _ = file.sync_all();
+            }
+        }
+    }
+}
+
+// Get the log directory, creating it if needed
+pub fn log_dir(app_handle: &tauri::AppHandle) -> Option<PathBuf> {
+    paths::logs_dir(app_handle).map_err(|e| eprintln!("{}", e)).ok()
+}
+
+fn open_append(path: &std::path::Path) -> std::io::Result<File> {
+    OpenOptions::new().create(true).append(true).open(path)
+}
+
+fn now() -> String {
+    chrono::Local::now().to_rfc3339_opts(SecondsFormat::Millis, false)
+}
+
+// Our stderr carries both warnings and failures
+fn rust_level(line: &str, default: LogLevel) -> LogLevel {
+    let lower = line.to_lowercase();
+    if default == LogLevel::Warn && (lower.contains("fail") || lower.contains("error")) {
+        LogLevel::Error
+    } else {
+        default
+    }
+}
+
+// Python's logging ("time - name - LEVEL - message") and uvicorn ("LEVEL:     message") prefixes
+fn python_level(line: &str) -> Option<LogLevel> {
+    line.split(" - ")
+        .nth(2)
+        .and_then(LogLevel::parse)
+        .or_else(|| line.split_once(':').and_then(|(prefix, _)| LogLevel::parse(prefix)))
+}
+
+// Tag each line read from one of a process's streams and keep it in the file and buffer.
+// Only backend output is ever echoed; our own output may be the very stream being read.
+fn capture(
+    app_handle: tauri::AppHandle,
+    reader: impl Read + Send + 'static,
+    source: LogSource,
+    default_level: LogLevel,
+    writer: Option<Arc<Mutex<LogWriter>>>,
+    echo: bool,
+) {
+    std::thread::spawn(move || {
+        let mut reader = BufReader::new(reader);
+        let mut bytes = Vec::new();
+        let mut previous = default_level;
+        loop {
+            bytes.clear();
+            match reader.read_until(b'\n', &mut bytes) {
+                Ok(0) | Err(_) => break,
+                Ok(_) => {}
+            }
+            let message = String::from_utf8_lossy(&bytes).trim_end().to_string();
+
+            let level = match source {
+                LogSource::Rust => rust_level(&message, default_level),
+                LogSource::Python => match python_level(&message) {
+                    Some(level) => level,
+                    None if message.starts_with("Traceback") => LogLevel::Error,
+                    // Indented lines continue a traceback or multi-line message
+                    None if message.starts_with(char::is_whitespace) => previous,
+                    None => default_level,
+                },
+            };
+            previous = level;
+
+            let line = LogLine {
+                seq: None,
+                timestamp: now(),
+                source,
+                level,
+                message,
+            };
+            // Prompts and what the backend did with them end up in here, so not in privacy mode
+            if let Some(writer) = writer.as_ref().filter(|_| storage::may_record(&app_handle)) {
+                let max_bytes = app_handle.state::<LogBuffer>().max_log_bytes.load(Ordering::Relaxed);
+                writer.lock().unwrap().write_line(&line.format(), max_bytes);
+            }
+            if echo {
+                let _ = writeln!(std::io::stdout(), "{}", line.message);
+            }
+            record(&app_handle, line);
+        }
+    });
+}
+
+fn record(app_handle: &tauri::AppHandle, mut line: LogLine) {
+    let buffer = app_handle.state::<LogBuffer>();
+    line.seq = Some(buffer.next_seq.fetch_add(1, Ordering::Relaxed));
+
+    let streaming = matches!(&*buffer.stream.lock().unwrap(), Some(filter) if filter.matches(&line));
+    if streaming {
+        let _ = app_handle.emit_to(CONSOLE_WINDOW, "log-line", &line);
+    }
+
+    let mut lines = buffer.lines.lock().unwrap();
+    if lines.len() >= BUFFER_LINES {
+        lines.pop_front();
+    }
+    lines.push_back(line);
+}
+
+// Route stdout/stderr through pipes we read, so each line can be tagged before it's written
+fn redirect_output(app_handle: &tauri::AppHandle, log: Arc<Mutex<LogWriter>>) {
+    let pipes = os_pipe::pipe().and_then(|out| os_pipe::pipe().map(|err| (out, err)));
+    let ((out_reader, out_writer), (err_reader, err_writer)) = match pipes {
+        Ok(pipes) => pipes,
+        Err(e) => {
+            eprintln!("Failed to open app log: {}", e);
+            return;
+        }
+    };
+
+    match (gag::Redirect::stdout(out_writer), gag::Redirect::stderr(err_writer)) {
+        (Ok(stdout), Ok(stderr)) => {
+            // The redirects last for the rest of the process
+            std::mem::forget(stdout);
+            std::mem::forget(stderr);
+            capture(app_handle.clone(), out_reader, LogSource::Rust, LogLevel::Info, Some(log.clone()), false);
+            capture(app_handle.clone(), err_reader, LogSource::Rust, LogLevel::Warn, Some(log), false);
+        }
+        (Err(e), _) | (_, Err(e)) => eprintln!("Failed to redirect output to the app log: {}", e),
+    }
+}
+
+// Stdout/stderr for the Python server, tagged and sent to backend.log in release builds
+// or echoed to the terminal otherwise
+pub fn backend_stdio(app_handle: &tauri::AppHandle) -> (Stdio, Stdio) {
+    let log = if cfg!(not(debug_assertions)) {
+        log_dir(app_handle).and_then(|dir| writer(app_handle, dir.join(BACKEND_LOG_FILE)))
+    } else {
+        None
+    };
+    let echo = log.is_none();
+
+    match (os_pipe::pipe(), os_pipe::pipe()) {
+        (Ok((out_reader, out_writer)), Ok((err_reader, err_writer))) => {
+            capture(app_handle.clone(), out_reader, LogSource::Python, LogLevel::Info, log.clone(), echo);
+            // Python's logging writes everything to stderr, so the level comes from the line itself
+            capture(app_handle.clone(), err_reader, LogSource::Python, LogLevel::Info, log, echo);
+            (Stdio::from(out_writer), Stdio::from(err_writer))
+        }
+        (Err(e), _) | (_, Err(e)) => {
+            eprintln!("Failed to capture backend output: {}", e);
+            (Stdio::inherit(), Stdio::inherit())
+        }
+    }
+}
+
+// Read at most the last `max_bytes` of a log file
+pub fn tail(app_handle: &tauri::AppHandle, file_name: &str, max_bytes: u64) -> Option<String> {
+    let path = log_dir(app_handle)?.join(file_name);
+    let mut file = File::open(path).ok()?;
+    let len = file.metadata().ok()?.len();
+    file.seek(SeekFrom::Start(len.saturating_sub(max_bytes))).ok()?;
+
+    let mut bytes = Vec::new();
+    file.read_to_end(&mut bytes).ok()?;
+    Some(String::from_utf8_lossy(&bytes).to_string())
+}
+
+// The most recent captured lines matching a filter, oldest first
+#[tauri::command]
+pub fn get_logs(app_handle: tauri::AppHandle, filter: Option<LogFilter>, limit: Option<usize>) -> Vec<LogLine> {
+    let filter = filter.unwrap_or_default();
+    let buffer = app_handle.state::<LogBuffer>();
+    let lines = buffer.lines.lock().unwrap();
+    let mut matching: Vec<LogLine> = lines
+        .iter()
+        .rev()
+        .filter(|line| filter.matches(line))
+        .take(limit.unwrap_or(500))
+        .cloned()
+        .collect();
+    matching.reverse();
+    matching
+}
+
+// Stream lines matching a filter to the console as "log-line" events; None stops streaming
+#[tauri::command]
+pub fn set_log_stream(app_handle: tauri::AppHandle, filter: Option<LogFilter>) {
+    *app_handle.state::<LogBuffer>().stream.lock().unwrap() = filter;
+}
+
+// Most hits a search returns; enough to find the first occurrence and see a pattern
+const MAX_SEARCH_HITS: usize = 1000;
+
+// Time window of persisted logs, as RFC 3339 timestamps; either end may be open
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct LogRange {
+    pub since: Option<String>,
+    pub until: Option<String>,
+}
+
+struct ParsedRange {
+    since: Option<DateTime<FixedOffset>>,
+    until: Option<DateTime<FixedOffset>>,
+}
+
+impl LogRange {
+    fn parse(&self) -> Result<ParsedRange, String> {
+        let parse = |value: &Option<String>, what: &str| -> Result<Option<DateTime<FixedOffset>>, String> {
+            value
+                .as_deref()
+                .map(|value| {
+                    DateTime::parse_from_rfc3339(value).map_err(|e| format!("Invalid {} time {}: {}", what, value, e))
+                })
+                .transpose()
+        };
+        Ok(ParsedRange {
+            since: parse(&self.since, "start")?,
+            until: parse(&self.until, "end")?,
+        })
+    }
+}
+
+impl ParsedRange {
+    fn is_open(&self) -> bool {
+        self.since.is_none() && self.until.is_none()
+    }
+
+    fn contains(&self, timestamp: &str) -> bool {
+        match DateTime::parse_from_rfc3339(timestamp) {
+            Ok(time) => {
+                self.since.map_or(true, |since| time >= since) && self.until.map_or(true, |until| time <= until)
+            }
+            Err(_) => false,
+        }
+    }
+}
+
+// Where a query matched in a line's text, in UTF-16 code units so it can index JS strings directly
+#[derive(Clone, Copy, Debug, Serialize)]
+pub struct MatchSpan {
+    pub start: usize,
+    pub end: usize,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct SearchHit {
+    pub file: String,
+    pub line_number: usize,
+    // None for lines written before logs were tagged
+    pub timestamp: Option<String>,
+    pub source: LogSource,
+    pub level: Option<LogLevel>,
+    pub text: String,
+    pub matches: Vec<MatchSpan>,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct SearchResults {
+    // Oldest first, so the first hit is when the text first appeared
+    pub hits: Vec<SearchHit>,
+    pub truncated: bool,
+}
+
+// Persisted log files, rotated ones first, with the source of their untagged lines
+fn log_files(dir: &std::path::Path) -> Vec<(PathBuf, LogSource)> {
+    let mut files = Vec::new();
+    for (name, source) in [(APP_LOG_FILE, LogSource::Rust), (BACKEND_LOG_FILE, LogSource::Python)] {
+        let live = dir.join(name);
+        for index in (1..=ROTATED_LOGS).rev() {
+            files.push((rotated_path(&live, index), source));
+        }
+        files.push((live, source));
+    }
+    files.retain(|(path, _)| path.exists());
+    files
+}
+
+// Case-insensitive matches of a query in a line, falling back to exact case where
+// lowercasing would shift byte offsets
+fn find_matches(text: &str, query: &str) -> Vec<MatchSpan> {
+    let (haystack, needle) = {
+        let (lower_text, lower_query) = (text.to_lowercase(), query.to_lowercase());
+        if lower_text.len() == text.len() && lower_query.len() == query.len() {
+            (lower_text, lower_query)
+        } else {
+            (text.to_string(), query.to_string())
+        }
+    };
+    let utf16_offset = |byte: usize| text[..byte].encode_utf16().count();
+    haystack
+        .match_indices(&needle)
+        .filter(|(start, _)| text.is_char_boundary(*start) && text.is_char_boundary(start + needle.len()))
+        .map(|(start, matched)| MatchSpan {
+            start: utf16_offset(start),
+            end: utf16_offset(start + matched.len()),
+        })
+        .collect()
+}
+
+// A line of a persisted log within the requested range
+struct FileLine {
+    line_number: usize,
+    // None for lines written before logs were tagged
+    timestamp: Option<String>,
+    source: LogSource,
+    level: Option<LogLevel>,
+    text: String,
+}
+
+fn read_log_file(path: &std::path::Path, source: LogSource, range: &ParsedRange, mut visit: impl FnMut(FileLine)) {
+    let file = match File::open(path) {
+        Ok(file) => file,
+        Err(e) => {
+            eprintln!("Failed to open {:?}: {}", path, e);
+            return;
+        }
+    };
+
+    let mut bytes = Vec::new();
+    let mut reader = BufReader::new(file);
+    let mut line_number = 0;
+    loop {
+        bytes.clear();
+        match reader.read_until(b'\n', &mut bytes) {
+            Ok(0) | Err(_) => break,
+            Ok(_) => line_number += 1,
+        }
+        let raw = String::from_utf8_lossy(&bytes);
+        let raw = raw.trim_end();
+
+        let line = match LogLine::parse(raw) {
+            Some(line) => FileLine {
+                line_number,
+                timestamp: Some(line.timestamp),
+                source: line.source,
+                level: Some(line.level),
+                text: line.message,
+            },
+            None => FileLine {
+                line_number,
+                timestamp: None,
+                source,
+                level: None,
+                text: raw.to_string(),
+            },
+        };
+        // Untagged lines have no time, so they only count when the range is unbounded
+        let in_range = match &line.timestamp {
+            Some(timestamp) => range.contains(timestamp),
+            None => range.is_open(),
+        };
+        if in_range {
+            visit(line);
+        }
+    }
+}
+
+fn sort_key(timestamp: &Option<String>) -> Option<DateTime<FixedOffset>> {
+    timestamp.as_deref().and_then(|t| DateTime::parse_from_rfc3339(t).ok())
+}
+
+// Search the persisted Rust and Python logs for a piece of text
+#[tauri::command(async)]
+pub fn search_logs(
+    app_handle: tauri::AppHandle,
+    query: String,
+    range: Option<LogRange>,
+) -> Result<SearchResults, String> {
+    if query.trim().is_empty() {
+        return Err("Search text is empty".to_string());
+    }
+    let range = range.unwrap_or_default().parse()?;
+    let dir = log_dir(&app_handle).ok_or_else(|| "Log directory is not available".to_string())?;
+
+    let mut hits = Vec::new();
+    for (path, source) in log_files(&dir) {
+        let file = path.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+        read_log_file(&path, source, &range, |line| {
+            let matches = find_matches(&line.text, &query);
+            if !matches.is_empty() {
+                hits.push(SearchHit {
+                    file: file.clone(),
+                    line_number: line.line_number,
+                    timestamp: line.timestamp,
+                    source: line.source,
+                    level: line.level,
+                    text: line.text,
+                    matches,
+                });
+            }
+        });
+    }
+
+    // Interleave the files by time; untagged lines keep their file order ahead of tagged ones
+    hits.sort_by_cached_key(|hit| sort_key(&hit.timestamp));
+    let truncated = hits.len() > MAX_SEARCH_HITS;
+    hits.truncate(MAX_SEARCH_HITS);
+    Ok(SearchResults { hits, truncated })
+}
+
+// Write the Rust and Python logs in a range to one file, interleaved by time, asking where
+// with the save dialog when no path is given. Returns the path, or None if cancelled.
+#[tauri::command(async)]
+pub fn export_logs(
+    app_handle: tauri::AppHandle,
+    range: Option<LogRange>,
+    path: Option<String>,
+) -> Result<Option<String>, String> {
+    let range = range.unwrap_or_default();
+    let parsed = range.parse()?;
+    let dir = log_dir(&app_handle).ok_or_else(|| "Log directory is not available".to_string())?;
+
+    let path = match path {
+        Some(path) => PathBuf::from(path),
+        None => {
+            let file_name = format!("krya-logs-{}.log", chrono::Local::now().format("%Y%m%d-%H%M%S"));
+            match app_handle.dialog().file()
+                .set_file_name(&file_name)
+                .add_filter("Log files", &["log", "txt"])
+                .blocking_save_file()
+            {
+                Some(path) => path.into_path().map_err(|e| e.to_string())?,
+                None => return Ok(None),
+            }
+        }
+    };
+
+    let mut lines = Vec::new();
+    for (log_path, source) in log_files(&dir) {
+        read_log_file(&log_path, source, &parsed, |line| lines.push(line));
+    }
+    lines.sort_by_cached_key(|line| sort_key(&line.timestamp));
+
+    let mut out = format!(
+        "# Krya.ai {} logs from {} to {}, exported {}\n",
+        env!("CARGO_PKG_VERSION"),
+        range.since.as_deref().unwrap_or("the beginning"),
+        range.until.as_deref().unwrap_or("now"),
+        now()
+    );
+    for line in lines {
+        match (line.timestamp, line.level) {
+            (Some(timestamp), Some(level)) => out.push_str(&format!(
+                "{} {} {}: {}\n",
+                timestamp,
+                level.name(),
+                line.source.name(),
+                line.text
+            )),
+            // Untagged lines keep their source so the two logs stay apart
+            _ => out.push_str(&format!("{}: {}\n", line.source.name(), line.text)),
+        }
+    }
+
+    fs::write(&path, out).map_err(|e| format!("Failed to write {:?}: {}", path, e))?;
+    Ok(Some(path.to_string_lossy().to_string()))
+}
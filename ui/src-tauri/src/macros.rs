@@ -0,0 +1,320 @@
+use rdev::{EventType, Key};
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime};
+use tauri::Manager;
+
+use crate::cache::{self, Submission};
+use crate::input::{self, InputHub};
+use crate::util::now_secs;
+use crate::{flags, storage};
+
+const STORE_FILE: &str = "macros.json";
+const LISTENER_NAME: &str = "macros";
+
+// Pointer moves come in at the screen's refresh rate; a few per second are enough to replay a drag
+const MOVE_INTERVAL: Duration = Duration::from_millis(50);
+
+// A pause while recording (reading something, fetching a coffee) isn't worth sitting through on replay
+const MAX_STEP_DELAY: Duration = Duration::from_secs(5);
+
+// One recorded input event and how long after the previous one it happened
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct MacroStep {
+    pub delay_ms: u64,
+    pub event: EventType,
+    // What a key press typed, per the keyboard layout, for describing the macro
+    #[serde(default)]
+    pub text: Option<String>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Macro {
+    pub id: String,
+    pub name: String,
+    pub created: u64,
+    pub steps: Vec<MacroStep>,
+}
+
+struct Recording {
+    steps: Vec<MacroStep>,
+    last: SystemTime,
+    last_move: Option<SystemTime>,
+}
+
+pub struct MacroState {
+    macros: Mutex<Vec<Macro>>,
+    recording: Arc<Mutex<Option<Recording>>>,
+    playing: AtomicBool,
+    cancel: AtomicBool,
+}
+
+impl MacroState {
+    pub fn load(app_handle: &tauri::AppHandle) -> Self {
+        MacroState {
+            macros: Mutex::new(storage::load_json(app_handle, STORE_FILE)),
+            recording: Arc::new(Mutex::new(None)),
+            playing: AtomicBool::new(false),
+            cancel: AtomicBool::new(false),
+        }
+    }
+}
+
+fn save(app_handle: &tauri::AppHandle, macros: &[Macro], data: storage::Data) -> Result<(), String> {
+    storage::save_json(app_handle, STORE_FILE, &macros, data)
+}
+
+fn record(recording: &Mutex<Option<Recording>>, event: &rdev::Event) {
+    let mut recording = recording.lock().unwrap();
+    let recording = match recording.as_mut() {
+        Some(recording) => recording,
+        None => return,
+    };
+    if let EventType::MouseMove { .. } = event.event_type {
+        let recent = recording
+            .last_move
+            .and_then(|last| event.time.duration_since(last).ok())
+            .is_some_and(|since| since < MOVE_INTERVAL);
+        if recent {
+            return;
+        }
+        recording.last_move = Some(event.time);
+    }
+    let delay = event.time.duration_since(recording.last).unwrap_or_default();
+    recording.last = event.time;
+    recording.steps.push(MacroStep {
+        delay_ms: delay.as_millis() as u64,
+        event: event.event_type,
+        text: event.name.clone().filter(|text| !text.chars().any(|c| c.is_control())),
+    });
+}
+
+fn is_modifier(key: Key) -> bool {
+    matches!(
+        key,
+        Key::ShiftLeft
+            | Key::ShiftRight
+            | Key::ControlLeft
+            | Key::ControlRight
+            | Key::Alt
+            | Key::AltGr
+            | Key::MetaLeft
+            | Key::MetaRight
+    )
+}
+
+// The macro as plain steps ("Typed …", "Pressed ControlLeft+KeyS", "Clicked Left at 120, 40") for the model
+pub fn describe(steps: &[MacroStep]) -> Vec<String> {
+    let mut lines = Vec::new();
+    let mut typed = String::new();
+    let mut held: Vec<Key> = Vec::new();
+    let mut pointer = (0.0, 0.0);
+    let flush = |typed: &mut String, lines: &mut Vec<String>| {
+        if !typed.is_empty() {
+            lines.push(format!("Typed {:?}", typed));
+            typed.clear();
+        }
+    };
+
+    for step in steps {
+        match step.event {
+            EventType::KeyPress(key) if is_modifier(key) => {
+                if !held.contains(&key) {
+                    held.push(key);
+                }
+            }
+            EventType::KeyRelease(key) => held.retain(|k| *k != key),
+            EventType::KeyPress(key) => {
+                let shortcut = held.iter().any(|k| !matches!(k, Key::ShiftLeft | Key::ShiftRight));
+                match (&step.text, shortcut) {
+                    (Some(text), false) => typed.push_str(text),
+                    _ => {
+                        flush(&mut typed, &mut lines);
+                        let names: Vec<String> =
+                            held.iter().chain(std::iter::once(&key)).map(|k| format!("{:?}", k)).collect();
+                        lines.push(format!("Pressed {}", names.join("+")));
+                    }
+                }
+            }
+            EventType::ButtonPress(button) => {
+                flush(&mut typed, &mut lines);
+                lines.push(format!("Clicked {:?} at {:.0}, {:.0}", button, pointer.0, pointer.1));
+            }
+            EventType::MouseMove { x, y } => pointer = (x, y),
+            EventType::Wheel { delta_x, delta_y } => {
+                flush(&mut typed, &mut lines);
+                lines.push(format!("Scrolled by {}, {}", delta_x, delta_y));
+            }
+            EventType::ButtonRelease(_) => {}
+        }
+    }
+    flush(&mut typed, &mut lines);
+    lines
+}
+
+fn find(app_handle: &tauri::AppHandle, id: &str) -> Result<Macro, String> {
+    app_handle
+        .state::<MacroState>()
+        .macros
+        .lock()
+        .unwrap()
+        .iter()
+        .find(|m| m.id == id)
+        .cloned()
+        .ok_or_else(|| format!("Macro not found: {}", id))
+}
+
+// Command to start capturing keyboard and mouse input into a new macro. A macro is a recording of
+// what the user did, so none are made or changed in privacy mode.
+#[tauri::command]
+pub fn start_macro_recording(app_handle: tauri::AppHandle) -> Result<(), String> {
+    storage::check_may_record(&app_handle, "Macros can't be recorded")?;
+    let state = app_handle.state::<MacroState>();
+    {
+        let mut recording = state.recording.lock().unwrap();
+        if recording.is_some() {
+            return Err("A macro is already being recorded".to_string());
+        }
+        *recording = Some(Recording {
+            steps: Vec::new(),
+            last: SystemTime::now(),
+            last_move: None,
+        });
+    }
+    let recording = state.recording.clone();
+    app_handle
+        .state::<InputHub>()
+        .subscribe(LISTENER_NAME, Box::new(move |event| record(&recording, event)));
+    println!("Recording a macro");
+    Ok(())
+}
+
+// Command to stop recording and save what was captured
+#[tauri::command]
+pub fn stop_macro_recording(app_handle: tauri::AppHandle, name: Option<String>) -> Result<Macro, String> {
+    app_handle.state::<InputHub>().unsubscribe(LISTENER_NAME);
+    let state = app_handle.state::<MacroState>();
+    let recording = state
+        .recording
+        .lock()
+        .unwrap()
+        .take()
+        .ok_or_else(|| "No macro is being recorded".to_string())?;
+    storage::check_may_record(&app_handle, "Macros can't be recorded")?;
+
+    let created = now_secs();
+    let recorded = Macro {
+        id: uuid::Uuid::new_v4().to_string(),
+        name: name
+            .filter(|name| !name.trim().is_empty())
+            .unwrap_or_else(|| format!("Macro {}", chrono::Local::now().format("%Y-%m-%d %H:%M"))),
+        created,
+        steps: recording.steps,
+    };
+    let mut macros = state.macros.lock().unwrap();
+    macros.push(recorded.clone());
+    save(&app_handle, &macros, storage::Data::Activity)?;
+    println!("Recorded macro {} with {} steps", recorded.id, recorded.steps.len());
+    Ok(recorded)
+}
+
+#[tauri::command]
+pub fn list_macros(state: tauri::State<MacroState>) -> Vec<Macro> {
+    state.macros.lock().unwrap().clone()
+}
+
+// Create or update a macro, e.g. after editing its steps or name
+#[tauri::command]
+pub fn save_macro(
+    app_handle: tauri::AppHandle,
+    state: tauri::State<MacroState>,
+    mut recorded: Macro,
+) -> Result<Macro, String> {
+    storage::check_may_record(&app_handle, "Macros can't be recorded")?;
+    let mut macros = state.macros.lock().unwrap();
+    if recorded.id.is_empty() {
+        recorded.id = uuid::Uuid::new_v4().to_string();
+    }
+    match macros.iter_mut().find(|m| m.id == recorded.id) {
+        Some(existing) => *existing = recorded.clone(),
+        None => macros.push(recorded.clone()),
+    }
+    save(&app_handle, &macros, storage::Data::Activity)?;
+    Ok(recorded)
+}
+
+#[tauri::command]
+pub fn delete_macro(app_handle: tauri::AppHandle, state: tauri::State<MacroState>, id: String) -> Result<(), String> {
+    let mut macros = state.macros.lock().unwrap();
+    macros.retain(|m| m.id != id);
+    save(&app_handle, &macros, storage::Data::Removal)
+}
+
+// Command to replay a macro into whatever is in front; `speed` 2.0 halves the pauses
+#[tauri::command(async)]
+pub fn play_macro(app_handle: tauri::AppHandle, id: String, speed: Option<f64>) -> Result<(), String> {
+    if !flags::is_enabled(&app_handle, flags::NATIVE_INPUT_INJECTION) {
+        return Err("Native input injection is disabled".to_string());
+    }
+    input::check_target(&app_handle)?;
+    let recorded = find(&app_handle, &id)?;
+    let state = app_handle.state::<MacroState>();
+    if state.recording.lock().unwrap().is_some() {
+        return Err("Stop recording before playing a macro".to_string());
+    }
+    if state.playing.swap(true, Ordering::SeqCst) {
+        return Err("A macro is already playing".to_string());
+    }
+    state.cancel.store(false, Ordering::SeqCst);
+
+    let speed = speed.filter(|speed| *speed > 0.0).unwrap_or(1.0);
+    let mut pressed: Vec<EventType> = Vec::new();
+    let mut result = Ok(());
+    for step in &recorded.steps {
+        if state.cancel.load(Ordering::SeqCst) {
+            break;
+        }
+        let delay = Duration::from_millis(step.delay_ms).min(MAX_STEP_DELAY);
+        std::thread::sleep(Duration::from_secs_f64(delay.as_secs_f64() / speed));
+        if let Err(e) = input::simulate(&app_handle, &step.event) {
+            result = Err(e);
+            break;
+        }
+        match step.event {
+            EventType::KeyPress(key) => pressed.push(EventType::KeyRelease(key)),
+            EventType::ButtonPress(button) => pressed.push(EventType::ButtonRelease(button)),
+            EventType::KeyRelease(_) | EventType::ButtonRelease(_) => pressed.retain(|release| *release != step.event),
+            _ => {}
+        }
+    }
+    // A stopped or failed replay mustn't leave a modifier or button held down
+    for release in pressed.iter().rev() {
+        let _ = input::simulate(&app_handle, release);
+    }
+    state.playing.store(false, Ordering::SeqCst);
+    result
+}
+
+#[tauri::command]
+pub fn stop_macro_playback(state: tauri::State<MacroState>) {
+    state.cancel.store(true, Ordering::SeqCst);
+}
+
+// Command to run a prompt with the macro as a demonstration, e.g. "do this again for every file in the folder".
+// Always runs, since doing it again is the point.
+#[tauri::command(async)]
+pub fn submit_macro_prompt(app_handle: tauri::AppHandle, id: String, prompt: String) -> Result<Submission, String> {
+    let recorded = find(&app_handle, &id)?;
+    let steps: Vec<String> = describe(&recorded.steps)
+        .iter()
+        .enumerate()
+        .map(|(index, line)| format!("{}. {}", index + 1, line))
+        .collect();
+    let context = format!(
+        "The user demonstrated the following steps (macro \"{}\"):\n{}",
+        recorded.name,
+        steps.join("\n")
+    );
+    cache::submit(&app_handle, prompt, Some(context), None, true)
+}
@@ -3,19 +3,106 @@
     windows_subsystem = "windows"
 )]
 
+mod accelerators;
+mod active_app;
+mod animation;
+mod app_actions;
+mod app_profiles;
+mod approvals;
+mod apps;
+mod attachments;
+mod audit;
+mod automation;
+mod backend;
+mod backend_deps;
+mod badge;
+mod cache;
+mod calc;
+mod capabilities;
+mod clipboard;
+mod compression;
+mod console;
+mod context;
+mod crash;
+mod dock;
+mod errors;
+mod events;
+mod exec;
+mod expansion;
+mod export;
+mod file_access;
+mod files;
+mod flags;
+mod fuzzy;
+mod handoff;
+mod handshake;
+mod history;
+mod hot_corner;
+mod hot_swap;
+mod idle;
+mod input;
+mod jobs;
+mod logs;
+mod macros;
+mod mcp;
+mod metrics;
+mod migrations;
+mod models;
+mod network;
+mod notifications;
+mod ollama;
+mod overlay;
+mod paths;
+mod placement;
+mod policy;
+mod popover;
+mod privacy;
+mod profiles;
+mod protocol;
+mod python;
+mod quick_actions;
+mod queue;
+mod quiet;
+mod recorder;
+mod repo;
+mod retention;
+mod scopes;
+mod screen_lock;
+mod scripts;
+mod selftest;
+mod sessions;
+mod settings;
+mod settings_bundle;
+mod sidebar;
+mod storage;
+mod stream;
+mod support;
+mod system_info;
+mod taskbar;
+mod telemetry;
+mod templates;
+mod tls;
+mod transcode;
+mod tray;
+mod triggers;
+mod util;
+mod validation;
+#[cfg(target_os = "linux")]
+mod wayland;
+mod workers;
+
 use std::sync::{Arc, Mutex};
-use tauri::{
-    CustomMenuItem, Manager, SystemTray, SystemTrayEvent, SystemTrayMenu, SystemTrayMenuItem,
-    Window, WindowEvent,
-};
-use tauri::GlobalShortcutManager;
-use std::process::Command;
-use reqwest;
+use tauri::{DragDropEvent, Manager, WebviewWindow, WindowEvent};
+use tauri_plugin_global_shortcut::{GlobalShortcutExt, ShortcutState};
+
+use errors::ReportExt;
 
 // State to track if the API server is running
 struct AppState {
     api_server_running: Arc<Mutex<bool>>,
     api_server_process: Arc<Mutex<Option<std::process::Child>>>,
+    // Extra backends answering model calls, on the ports after the main one's
+    worker_processes: Arc<Mutex<Vec<std::process::Child>>>,
 }
 
 // Clone implementation for AppState
@@ -24,44 +111,130 @@ impl Clone for AppState {
         AppState {
             api_server_running: self.api_server_running.clone(),
             api_server_process: self.api_server_process.clone(),
+            worker_processes: self.worker_processes.clone(),
         }
     }
 }
 
+// Where the spotlight appears, per the user's placement setting
+fn position_spotlight(window: &WebviewWindow) -> tauri::Result<()> {
+    match settings::current(window.app_handle()).appearance.spotlight_position {
+        settings::SpotlightPosition::TopCenter => placement::place(window, placement::Anchor::UpperCenter),
+        settings::SpotlightPosition::NearCursor => placement::place_near_cursor(window),
+        settings::SpotlightPosition::Docked => sidebar::expand(window),
+    }
+}
+
 // Function to toggle the spotlight window
-fn toggle_spotlight_window(window: &Window) {
-    if window.is_visible().unwrap() {
-        window.hide().unwrap();
-    } else {
-        window.show().unwrap();
-        window.set_focus().unwrap();
-        
-        // Position window at the top center (1/4 position)
-        let monitor = window.current_monitor().unwrap().unwrap();
-        let monitor_size = monitor.size();
-        let window_size = window.inner_size().unwrap();
-        
-        let x = (monitor_size.width as i32 - window_size.width as i32) / 2;
-        let y = monitor_size.height as i32 / 4 - window_size.height as i32 / 2;
-        
-        window.set_position(tauri::Position::Physical(tauri::PhysicalPosition { x, y })).unwrap();
+fn toggle_spotlight_window(window: &WebviewWindow) -> tauri::Result<()> {
+    // The docked spotlight never hides, it collapses back to its strip
+    let docked = sidebar::is_docked(window.app_handle());
+    if docked && sidebar::is_expanded(window) {
+        return sidebar::collapse(window);
+    }
+    if !docked && window.is_visible()? && !animation::is_hiding(window) {
+        return animation::hide(window);
+    }
+    // Templates can use the selected text, which has to be copied while its application is still in front
+    templates::capture_selection(window.app_handle());
+    match app_profiles::capture(window) {
+        Some(profile) if profile.attach_screenshot => {
+            // The screenshot is of the app, so it has to be taken before the spotlight covers it
+            let window = window.clone();
+            std::thread::spawn(move || {
+                let app_handle = window.app_handle();
+                attachments::attach_screenshot(app_handle).or_report(app_handle, "Failed to attach a screenshot");
+                let target = window.clone();
+                window
+                    .run_on_main_thread(move || {
+                        open_spotlight(&target).or_report(target.app_handle(), "Failed to toggle the spotlight");
+                    })
+                    .or_report(app_handle, "Failed to toggle the spotlight");
+            });
+            Ok(())
+        }
+        _ => open_spotlight(window),
+    }
+}
+
+fn open_spotlight(window: &WebviewWindow) -> tauri::Result<()> {
+    if sidebar::is_docked(window.app_handle()) {
+        sidebar::expand(window)?;
+        clipboard::fill_pending(window);
+        app_profiles::announce(window);
+        return Ok(());
+    }
+    animation::show(window, |window| {
+        window.set_focus()?;
+        clipboard::fill_pending(window);
+        app_profiles::announce(window);
+        position_spotlight(window)
+    })
+}
+
+// Toggle the spotlight from a shortcut or the tray, reporting rather than panicking on failure
+fn toggle_spotlight(app_handle: &tauri::AppHandle) {
+    match app_handle.get_webview_window("main") {
+        Some(window) => {
+            toggle_spotlight_window(&window).or_report(app_handle, "Failed to toggle the spotlight");
+        }
+        None => errors::report(app_handle, "Failed to toggle the spotlight", "the window is missing"),
+    }
+}
+
+fn show_window(window: &WebviewWindow) -> tauri::Result<()> {
+    window.show()?;
+    window.set_focus()
+}
+
+// Function to (re)register the global shortcuts from settings
+fn register_shortcuts(app_handle: &tauri::AppHandle) {
+    let shortcut_manager = app_handle.global_shortcut();
+    if let Err(e) = shortcut_manager.unregister_all() {
+        eprintln!("Failed to unregister shortcuts: {}", e);
+    }
+
+    let shortcuts = settings::current(app_handle).shortcuts.spotlight;
+    let mut failed = Vec::new();
+    for shortcut in &shortcuts {
+        let registered = shortcut_manager.on_shortcut(shortcut.as_str(), |app_handle, _, event| {
+            if event.state == ShortcutState::Pressed {
+                toggle_spotlight(app_handle);
+            }
+        });
+        if let Err(e) = registered {
+            errors::report(app_handle, &format!("Failed to register shortcut {}", shortcut), e);
+            failed.push(shortcut.clone());
+        }
+    }
+
+    // Without any working shortcut the spotlight is only reachable from the tray
+    if !shortcuts.is_empty() && failed.len() == shortcuts.len() {
+        errors::alert(
+            app_handle,
+            "The spotlight shortcut couldn't be registered",
+            format!(
+                "{} may already be in use by another app. Open the spotlight from the tray icon \
+                 and choose a different shortcut in Settings.",
+                failed.join(", ")
+            ),
+        );
     }
 }
 
 // Function to create the settings window
 fn open_settings_window(app_handle: &tauri::AppHandle) {
     // Check if settings window already exists
-    if let Some(settings_window) = app_handle.get_window("settings") {
-        settings_window.show().unwrap();
-        settings_window.set_focus().unwrap();
+    if let Some(settings_window) = app_handle.get_webview_window("settings") {
+        show_window(&settings_window).or_report(app_handle, "Failed to show settings");
         return;
     }
 
     // Create settings window
-    let settings_window = tauri::WindowBuilder::new(
+    let settings_window = match tauri::WebviewWindowBuilder::new(
         app_handle,
         "settings",
-        tauri::WindowUrl::App("index.html".into()),
+        tauri::WebviewUrl::App("index.html".into()),
     )
     .title("Krya.ai Settings")
     .inner_size(600.0, 500.0)
@@ -69,30 +242,36 @@ fn open_settings_window(app_handle: &tauri::AppHandle) {
     .decorations(true)
     .center()
     .build()
-    .expect("Failed to create settings window");
+    {
+        Ok(window) => window,
+        Err(e) => return errors::alert(app_handle, "Failed to open settings", e),
+    };
 
     // Set always on top
-    settings_window.set_always_on_top(true).unwrap();
-    
+    settings_window
+        .set_always_on_top(true)
+        .or_report(app_handle, "Failed to keep settings on top");
+
+    placement::place(&settings_window, placement::Anchor::Center)
+        .or_report(app_handle, "Failed to position settings");
+
     // Set window to be shown
-    settings_window.show().unwrap();
-    settings_window.set_focus().unwrap();
+    show_window(&settings_window).or_report(app_handle, "Failed to show settings");
 }
 
 // Function to open console window
 fn open_console_window(app_handle: &tauri::AppHandle) {
     // Check if console window already exists
-    if let Some(console_window) = app_handle.get_window("console") {
-        console_window.show().unwrap();
-        console_window.set_focus().unwrap();
+    if let Some(console_window) = app_handle.get_webview_window("console") {
+        show_window(&console_window).or_report(app_handle, "Failed to show the console");
         return;
     }
 
     // Create console window
-    let console_window = tauri::WindowBuilder::new(
+    let console_window = match tauri::WebviewWindowBuilder::new(
         app_handle,
         "console",
-        tauri::WindowUrl::App("index.html".into()),
+        tauri::WebviewUrl::App("index.html".into()),
     )
     .title("Krya.ai Console")
     .inner_size(700.0, 500.0)
@@ -100,245 +279,122 @@ fn open_console_window(app_handle: &tauri::AppHandle) {
     .decorations(true)
     .center()
     .build()
-    .expect("Failed to create console window");
+    {
+        Ok(window) => window,
+        Err(e) => return errors::alert(app_handle, "Failed to open the console", e),
+    };
+
+    placement::place(&console_window, placement::Anchor::Center)
+        .or_report(app_handle, "Failed to position the console");
+    show_window(&console_window).or_report(app_handle, "Failed to show the console");
+}
+
+// Open the tray menu as a window, for desktops without a tray
+pub fn open_menu_window(app_handle: &tauri::AppHandle) {
+    if let Some(menu_window) = app_handle.get_webview_window(tray::MENU_WINDOW) {
+        show_window(&menu_window).or_report(app_handle, "Failed to show the menu");
+        return;
+    }
+
+    let menu_window = match tauri::WebviewWindowBuilder::new(
+        app_handle,
+        tray::MENU_WINDOW,
+        tauri::WebviewUrl::App("index.html".into()),
+    )
+    .title("Krya.ai")
+    .inner_size(280.0, 420.0)
+    .resizable(false)
+    .decorations(true)
+    .center()
+    .build()
+    {
+        Ok(window) => window,
+        Err(e) => return errors::alert(app_handle, "Failed to open the menu", e),
+    };
 
-    console_window.show().unwrap();
-    console_window.set_focus().unwrap();
+    placement::place(&menu_window, placement::Anchor::Center)
+        .or_report(app_handle, "Failed to position the menu");
+    show_window(&menu_window).or_report(app_handle, "Failed to show the menu");
+}
+
+// Start a backend process listening on `port`
+fn spawn_backend(app_handle: &tauri::AppHandle, port: u16) -> Result<std::process::Child, String> {
+    // Bundled resources in an installed build, `src` at the project root while developing
+    let source_dir = backend::source_dir(app_handle)?;
+    let run_server_path = source_dir.join("run_server.py");
+    println!("Starting Python server at: {:?}", source_dir);
+
+    // The configured interpreter, or the best one found on this machine
+    let python = python::resolve(app_handle)?;
+    // Advanced users can wrap the server in a debugger or point it at another checkout
+    let backend_settings = settings::current(app_handle).backend;
+    let working_dir = backend_settings
+        .working_dir
+        .as_deref()
+        .filter(|dir| !dir.trim().is_empty())
+        .map(settings::expand_home)
+        .unwrap_or(source_dir);
+
+    profiles::adopt_legacy_config(app_handle, &working_dir);
+
+    // Start the API server in a separate process
+    let (stdout, stderr) = logs::backend_stdio(app_handle);
+    let child = python
+        .command()
+        .args(&backend_settings.python_args)
+        .arg(&run_server_path)
+        .arg("--port")
+        .arg(port.to_string())
+        .args(&backend_settings.args)
+        .current_dir(&working_dir)
+        .envs(accelerators::env_hints(app_handle))
+        .envs(models::env_hints(app_handle))
+        .envs(mcp::env_hints(app_handle))
+        .envs(handoff::env_hints(app_handle))
+        .envs(paths::env_hints(app_handle))
+        .envs(profiles::env_hints(app_handle))
+        .envs(tls::env_hints(app_handle))
+        .envs(workers::env_hints(port))
+        .envs(approvals::env_hints())
+        .stdout(stdout)
+        .stderr(stderr)
+        .spawn();
+
+    child.map_err(|e| {
+        eprintln!("Failed to start Python API server: {}", e);
+        format!("Failed to start API server: {}", e)
+    })
 }
 
 // Function to start the API server
-fn start_api_server(app_state: &tauri::State<AppState>) -> Result<(), String> {
+fn start_api_server(app_handle: &tauri::AppHandle, app_state: &tauri::State<AppState>) -> Result<(), String> {
     let mut api_server_running = app_state.api_server_running.lock().unwrap();
     let mut api_server_process = app_state.api_server_process.lock().unwrap();
     
     if *api_server_running {
         return Ok(());
     }
-    
-    // Try to find the resource directory using current_exe
-    let exe_path = std::env::current_exe().map_err(|e| format!("Failed to get current executable path: {}", e))?;
-    let exe_dir = exe_path.parent().ok_or_else(|| "Failed to get executable directory".to_string())?;
-    
-    // Try different possible resource paths
-    let possible_resource_paths = vec![
-        exe_dir.join("resources").join("src"),
-        exe_dir.join("..").join("Resources").join("resources").join("src"),  // macOS bundle
-    ];
-    
-    let mut resource_path = None;
-    for path in possible_resource_paths {
-        if path.exists() {
-            resource_path = Some(path);
-            break;
-        }
+    if backend::is_remote() {
+        println!("Using the backend at {} set by policy", backend::base_url());
+        *api_server_running = true;
+        return Ok(());
     }
     
-    println!("Checking for resource directory...");
-    
-    // Check if the resource path exists
-    if resource_path.is_none() {
-        // Fall back to development paths
-        println!("Resource directory not found, falling back to development paths");
-        
-        // For development, use relative path
-        let mut server_path = std::env::current_dir()
-            .map_err(|e| format!("Failed to get current directory: {}", e))?;
-        
-        println!("Current directory: {:?}", server_path);
-        
-        // Move up from src-tauri directory to ui
-        server_path.pop();
-        
-        // Move up from ui directory to the project root
-        server_path.pop();
-        
-        println!("Project root directory: {:?}", server_path);
-        
-        // Add path to Python server
-        let mut python_server_path = server_path.clone();
-        python_server_path.push("src");
-        python_server_path.push("run_server.py");
-        
-        println!("Starting Python server at: {:?}", python_server_path);
-        
-        // Check if the file exists
-        if !python_server_path.exists() {
-            return Err(format!("Python server script not found at: {:?}", python_server_path));
-        }
-        
-        // Determine Python command based on platform
-        let python_cmd = if cfg!(target_os = "windows") {
-            "python"
-        } else {
-            "python3"
-        };
-        
-        // Start the API server in a separate process
-        let child = Command::new(python_cmd)
-            .arg(&python_server_path)
-            .arg("--port")
-            .arg("8000")
-            .current_dir(server_path.join("src"))
-            .spawn();
-        
-        match child {
-            Ok(process) => {
-                println!("Python API server started with PID: {}", process.id());
-                *api_server_process = Some(process);
-                *api_server_running = true;
-                
-                // Give the server more time to start (increased from 2 to 5 seconds)
-                std::thread::sleep(std::time::Duration::from_secs(5));
-                
-                // Try to ping the server to make sure it's running
-                let status_check = std::thread::spawn(|| {
-                    // Try several times to connect to the server
-                    for _ in 0..5 {
-                        std::thread::sleep(std::time::Duration::from_secs(1));
-                        match reqwest::blocking::get("http://localhost:8000/") {
-                            Ok(response) => {
-                                if response.status().is_success() {
-                                    println!("API server is responding correctly");
-                                    return true;
-                                }
-                            },
-                            Err(_) => {}
-                        }
-                    }
-                    println!("API server is not responding after multiple attempts");
-                    false
-                });
-                
-                // Wait for the status check to complete
-                match status_check.join() {
-                    Ok(true) => println!("API server connection verified"),
-                    _ => println!("Could not verify API server connection, but continuing anyway"),
-                }
-                
-                Ok(())
-            },
-            Err(e) => {
-                eprintln!("Failed to start Python API server: {}", e);
-                Err(format!("Failed to start API server: {}", e))
-            }
-        }
-    } else {
-        // Production mode - use bundled resources
-        let resource_path = resource_path.unwrap();
-        let run_server_path = resource_path.join("run_server.py");
-        println!("Starting Python server from bundled resources at: {:?}", run_server_path);
-        
-        // Determine Python command based on platform
-        let python_cmd = if cfg!(target_os = "windows") {
-            "python"
-        } else {
-            "python3"
-        };
-        
-        // Start the API server in a separate process
-        let child = Command::new(python_cmd)
-            .arg(&run_server_path)
-            .arg("--port")
-            .arg("8000")
-            .current_dir(&resource_path)
-            .spawn();
-        
-        match child {
-            Ok(process) => {
-                println!("Python API server started with PID: {}", process.id());
-                *api_server_process = Some(process);
-                *api_server_running = true;
-                
-                // Give the server more time to start (increased from 2 to 5 seconds)
-                std::thread::sleep(std::time::Duration::from_secs(5));
-                
-                // Try to ping the server to make sure it's running
-                let status_check = std::thread::spawn(|| {
-                    // Try several times to connect to the server
-                    for _ in 0..5 {
-                        std::thread::sleep(std::time::Duration::from_secs(1));
-                        match reqwest::blocking::get("http://localhost:8000/") {
-                            Ok(response) => {
-                                if response.status().is_success() {
-                                    println!("API server is responding correctly");
-                                    return true;
-                                }
-                            },
-                            Err(_) => {}
-                        }
-                    }
-                    println!("API server is not responding after multiple attempts");
-                    false
-                });
-                
-                // Wait for the status check to complete
-                match status_check.join() {
-                    Ok(true) => println!("API server connection verified"),
-                    _ => println!("Could not verify API server connection, but continuing anyway"),
-                }
-                
-                Ok(())
-            },
-            Err(e) => {
-                eprintln!("Failed to start Python API server: {}", e);
-                
-                // Try alternative Python command if first attempt failed
-                if python_cmd == "python3" {
-                    println!("Trying with 'python' instead...");
-                    let child = Command::new("python")
-                        .arg(&run_server_path)
-                        .arg("--port")
-                        .arg("8000")
-                        .current_dir(&resource_path)
-                        .spawn();
-                    
-                    match child {
-                        Ok(process) => {
-                            println!("Python API server started with PID: {}", process.id());
-                            *api_server_process = Some(process);
-                            *api_server_running = true;
-                            
-                            // Give the server more time to start (increased from 2 to 5 seconds)
-                            std::thread::sleep(std::time::Duration::from_secs(5));
-                            
-                            // Try to ping the server to make sure it's running
-                            let status_check = std::thread::spawn(|| {
-                                // Try several times to connect to the server
-                                for _ in 0..5 {
-                                    std::thread::sleep(std::time::Duration::from_secs(1));
-                                    match reqwest::blocking::get("http://localhost:8000/") {
-                                        Ok(response) => {
-                                            if response.status().is_success() {
-                                                println!("API server is responding correctly");
-                                                return true;
-                                            }
-                                        },
-                                        Err(_) => {}
-                                    }
-                                }
-                                println!("API server is not responding after multiple attempts");
-                                false
-                            });
-                            
-                            // Wait for the status check to complete
-                            match status_check.join() {
-                                Ok(true) => println!("API server connection verified"),
-                                _ => println!("Could not verify API server connection, but continuing anyway"),
-                            }
-                            
-                            return Ok(());
-                        },
-                        Err(e2) => {
-                            eprintln!("Failed to start with alternative Python command: {}", e2);
-                            return Err(format!("Failed to start API server with both python3 and python: {} / {}", e, e2));
-                        }
-                    }
-                }
-                
-                Err(format!("Failed to start API server: {}", e))
-            }
-        }
-    }
+    let process = spawn_backend(app_handle, backend::BACKEND_PORT)?;
+    println!("Python API server started with PID: {}", process.id());
+    events::emit(
+        app_handle,
+        &events::BackendStarted {
+            pid: process.id(),
+            port: backend::BACKEND_PORT,
+        },
+    );
+    *api_server_process = Some(process);
+    *api_server_running = true;
+
+    // Extra backends for model calls, if the settings ask for them
+    workers::start(app_handle, app_state);
+    Ok(())
 }
 
 // Function to stop the API server
@@ -348,21 +404,25 @@ fn stop_api_server(app_state: &AppState) {
     
     if let Some(mut process) = api_server_process.take() {
         println!("Stopping Python API server");
-        #[cfg(target_os = "windows")]
-        {
-            // On Windows, we need to use taskkill to kill the process tree
-            let _ = Command::new("taskkill")
-                .args(&["/F", "/T", "/PID", &process.id().to_string()])
-                .output();
-        }
-        #[cfg(not(target_os = "windows"))]
-        {
-            // On Unix-like systems, we can kill the process directly
-            let _ = process.kill();
-        }
-        
+        kill_backend(&mut process);
         *api_server_running = false;
     }
+    workers::stop(app_state);
+}
+
+fn kill_backend(process: &mut std::process::Child) {
+    #[cfg(target_os = "windows")]
+    {
+        // On Windows, we need to use taskkill to kill the process tree
+        let _ = std::process::Command::new("taskkill")
+            .args(["/F", "/T", "/PID", &process.id().to_string()])
+            .output();
+    }
+    #[cfg(not(target_os = "windows"))]
+    {
+        // On Unix-like systems, we can kill the process directly
+        let _ = process.kill();
+    }
 }
 
 // Command to open settings window
@@ -377,121 +437,470 @@ fn open_console(app_handle: tauri::AppHandle) {
     open_console_window(&app_handle);
 }
 
+// Command to open the windowed tray menu
+#[tauri::command]
+fn open_menu(app_handle: tauri::AppHandle) {
+    open_menu_window(&app_handle);
+}
+
 // Command to quit the application
 #[tauri::command]
 fn quit_app(app_handle: tauri::AppHandle, app_state: tauri::State<AppState>) {
-    // Stop the API server before quitting
+    // Stop the API server (and Ollama, if we started it) before quitting
     stop_api_server(&app_state);
+    ollama::stop(&app_handle);
     app_handle.exit(0);
 }
 
 fn main() {
-    // Create system tray menu
-    let quit = CustomMenuItem::new("quit".to_string(), "Quit");
-    let show = CustomMenuItem::new("show".to_string(), "Show");
-    let settings = CustomMenuItem::new("settings".to_string(), "Settings");
-    let console = CustomMenuItem::new("console".to_string(), "Console");
-    
-    let tray_menu = SystemTrayMenu::new()
-        .add_item(show)
-        .add_item(settings)
-        .add_item(console)
-        .add_native_item(SystemTrayMenuItem::Separator)
-        .add_item(quit);
-    
-    let system_tray = SystemTray::new().with_menu(tray_menu);
+    // Choose how to present the spotlight on Wayland before GTK starts
+    #[cfg(target_os = "linux")]
+    wayland::prepare();
 
     // Initialize app state
     let app_state = AppState {
         api_server_running: Arc::new(Mutex::new(false)),
         api_server_process: Arc::new(Mutex::new(None)),
+        worker_processes: Arc::new(Mutex::new(Vec::new())),
     };
     
     tauri::Builder::default()
+        // Launching the app again brings up the running one's spotlight
+        .plugin(tauri_plugin_single_instance::init(|app_handle, _, _| toggle_spotlight(app_handle)))
+        .plugin(tauri_plugin_global_shortcut::Builder::new().build())
+        .plugin(tauri_plugin_dialog::init())
+        .plugin(tauri_plugin_notification::init())
+        .plugin(tauri_plugin_opener::init())
+        .plugin(tauri_plugin_shell::init())
+        .plugin(tauri_plugin_clipboard_manager::init())
+        .plugin(tauri_plugin_process::init())
         .manage(app_state.clone())
-        .invoke_handler(tauri::generate_handler![open_settings, open_console, quit_app])
-        .system_tray(system_tray)
-        .on_system_tray_event(|app, event| match event {
-            SystemTrayEvent::MenuItemClick { id, .. } => match id.as_str() {
-                "quit" => {
-                    // Stop the API server before quitting
-                    let app_state = app.state::<AppState>();
-                    stop_api_server(&app_state);
-                    app.exit(0);
-                }
-                "show" => {
-                    let window = app.get_window("main").unwrap();
-                    toggle_spotlight_window(&window);
+        .manage(input::InputHub::default())
+        .manage(hot_corner::HotCornerState::default())
+        .manage(apps::AppIndex::default())
+        .manage(files::FileIndex::default())
+        .manage(accelerators::AcceleratorState::default())
+        .manage(ollama::OllamaState::default())
+        .manage(mcp::McpState::default())
+        .manage(handshake::HandshakeState::default())
+        .manage(capabilities::CapabilitiesState::default())
+        .manage(attachments::AttachmentsState::default())
+        .manage(scopes::WorkingDirectory::default())
+        .manage(automation::AutomationState::default())
+        .manage(quiet::QuietState::default())
+        .manage(privacy::PrivacyState::default())
+        .manage(screen_lock::ScreenLockState::default())
+        .manage(idle::IdleState::default())
+        .manage(clipboard::ClipboardState::default())
+        .manage(cache::ResultCache::default())
+        .manage(queue::QueueState::default())
+        .manage(stream::StreamState::default())
+        .manage(sessions::ActiveSession::default())
+        .manage(app_profiles::AppProfileState::default())
+        .manage(animation::AnimationState::default())
+        .manage(sidebar::SidebarState::default())
+        .manage(backend_deps::BackendDepsState::default())
+        .manage(python::PythonState::default())
+        .manage(hot_swap::HotSwapState::default())
+        .manage(metrics::MetricsState::default())
+        .manage(console::ConsoleState::default())
+        .manage(jobs::JobsState::default())
+        .manage(approvals::ApprovalState::default())
+        .manage(exec::ExecState::default())
+        .invoke_handler(tauri::generate_handler![
+            open_settings,
+            open_console,
+            open_menu,
+            quit_app,
+            quick_actions::list_snippets,
+            quick_actions::save_snippet,
+            quick_actions::delete_snippet,
+            quick_actions::list_quick_actions,
+            quick_actions::save_quick_action,
+            quick_actions::delete_quick_action,
+            quick_actions::render_quick_action,
+            templates::list_prompt_templates,
+            templates::save_prompt_template,
+            templates::delete_prompt_template,
+            templates::render_template,
+            templates::export_prompt_templates,
+            templates::import_prompt_templates,
+            macros::start_macro_recording,
+            macros::stop_macro_recording,
+            macros::list_macros,
+            macros::save_macro,
+            macros::delete_macro,
+            macros::play_macro,
+            macros::stop_macro_playback,
+            macros::submit_macro_prompt,
+            scripts::list_scripts,
+            scripts::get_script,
+            scripts::save_script,
+            scripts::delete_script,
+            scripts::replay_script,
+            scripts::stop_script_replay,
+            active_app::get_active_app,
+            app_profiles::get_app_profile,
+            app_actions::list_app_actions,
+            app_actions::run_app_action,
+            overlay::set_window_opacity,
+            overlay::set_window_click_through,
+            expansion::get_text_expansion_config,
+            expansion::set_text_expansion_enabled,
+            expansion::save_expansion,
+            expansion::delete_expansion,
+            expansion::set_expansion_disabled_apps,
+            apps::search_apps,
+            apps::launch_app,
+            apps::refresh_app_index,
+            settings::get_settings,
+            settings::save_settings,
+            settings::get_settings_recovery,
+            paths::get_app_paths,
+            paths::open_data_dir,
+            settings::restore_settings_snapshot,
+            validation::validate_settings,
+            files::search_files,
+            fuzzy::fuzzy_match,
+            files::reindex_files,
+            files::get_file_index_status,
+            context::semantic_search,
+            context::get_prompt_context,
+            context::rebuild_context_index,
+            calc::evaluate_inline,
+            history::add_history_item,
+            history::update_history_item,
+            history::search_history,
+            history::get_history_page,
+            history::delete_history_item,
+            history::export_history,
+            history::pin_prompt,
+            history::unpin_prompt,
+            history::list_pinned,
+            sessions::list_sessions,
+            sessions::get_active_session,
+            sessions::create_session,
+            sessions::switch_session,
+            sessions::rename_session,
+            sessions::delete_session,
+            sessions::get_session_messages,
+            profiles::list_profiles,
+            profiles::create_profile,
+            profiles::rename_profile,
+            profiles::delete_profile,
+            profiles::switch_profile,
+            export::export_result,
+            settings_bundle::export_settings,
+            settings_bundle::import_settings,
+            support::generate_support_bundle,
+            crash::list_crash_reports,
+            crash::clear_crash_reports,
+            crash::take_last_crash,
+            telemetry::set_telemetry,
+            telemetry::get_telemetry_preview,
+            flags::get_flags,
+            selftest::run_self_test,
+            system_info::get_system_info,
+            accelerators::get_accelerators,
+            ollama::get_ollama_status,
+            ollama::list_local_models,
+            ollama::start_ollama,
+            ollama::stop_ollama,
+            models::list_providers,
+            models::get_active_model,
+            models::set_active_model,
+            mcp::get_mcp_presets,
+            mcp::list_mcp_servers,
+            mcp::set_mcp_server_enabled,
+            mcp::list_mcp_tools,
+            mcp::call_mcp_tool,
+            mcp::get_mcp_endpoint,
+            mcp::set_mcp_endpoint_enabled,
+            scopes::get_permission_scopes,
+            scopes::set_permission_scope,
+            approvals::get_confirmation_policies,
+            policy::get_policy,
+            exec::exec_command,
+            file_access::read_file,
+            file_access::write_file,
+            file_access::append_file,
+            audit::get_audit_log,
+            approvals::set_confirmation_policy,
+            approvals::classify_actions,
+            handshake::get_backend_info,
+            python::get_python_interpreter,
+            python::list_python_interpreters,
+            python::list_conda_envs,
+            workers::get_backend_workers,
+            recorder::set_request_recording,
+            recorder::get_request_recording,
+            recorder::get_request_traces,
+            recorder::list_request_trace_files,
+            recorder::replay_request_trace,
+            recorder::replay_request,
+            network::get_network_status,
+            tls::import_ca_certificate,
+            tls::remove_ca_certificate,
+            backend_deps::get_backend_deps_status,
+            backend_deps::update_backend_deps,
+            capabilities::get_capabilities,
+            attachments::attach_file,
+            attachments::take_dropped_job,
+            attachments::paste_clipboard_image,
+            scopes::set_working_directory,
+            scopes::clear_working_directory,
+            scopes::get_working_directory,
+            repo::get_repo_context,
+            console::console_open,
+            console::console_write,
+            console::console_resize,
+            console::console_close,
+            logs::get_logs,
+            logs::set_log_stream,
+            logs::search_logs,
+            logs::export_logs,
+            retention::clean_storage,
+            retention::get_storage_usage,
+            tray::get_menu_items,
+            tray::activate_menu_item,
+            jobs::track_job,
+            jobs::get_running_jobs,
+            jobs::get_interrupted_jobs,
+            jobs::rerun_interrupted_job,
+            jobs::discard_interrupted_job,
+            dock::set_show_dock_icon,
+            popover::hide_popover,
+            automation::get_automation_paused,
+            automation::set_automation_paused,
+            automation::get_automation_dry_run,
+            automation::set_automation_dry_run,
+            quiet::get_quiet_mode,
+            notifications::get_notifications,
+            notifications::get_unread_notification_count,
+            notifications::mark_read,
+            notifications::clear_notifications,
+            screen_lock::is_suspended_by_screen_lock,
+            screen_lock::resume_after_screen_lock,
+            idle::is_user_idle,
+            privacy::get_privacy_mode,
+            privacy::set_privacy_mode,
+            privacy::set_incognito_session,
+            triggers::list_triggers,
+            triggers::save_trigger,
+            triggers::delete_trigger,
+            triggers::set_trigger_enabled,
+            triggers::list_trigger_firings,
+            clipboard::dismiss_clipboard_suggestion,
+            cache::submit_prompt,
+            cache::clear_result_cache,
+            queue::list_pending_prompts,
+            queue::cancel_pending_prompt,
+            stream::get_job_stream
+        ])
+        .on_window_event(|window, event| {
+            let app_handle = window.app_handle();
+            match (window.label(), event) {
+                ("main", WindowEvent::DragDrop(DragDropEvent::Drop { paths, .. })) => {
+                    attachments::handle_drop(app_handle, paths.clone());
                 }
-                "settings" => {
-                    open_settings_window(app);
-                }
-                "console" => {
-                    open_console_window(app);
+                ("main", WindowEvent::Moved(position)) => {
+                    if let Some(window) = app_handle.get_webview_window("main") {
+                        sidebar::handle_moved(&window, *position);
+                    }
                 }
+                ("console", WindowEvent::Destroyed) => console::close_all(app_handle),
+                ("main", WindowEvent::Focused(true)) => jobs::acknowledge(app_handle),
+                (popover::POPOVER_WINDOW, WindowEvent::Focused(false)) => popover::hide(app_handle),
+                // The spotlight doesn't hide when it loses focus, since it may be watching a job run
                 _ => {}
-            },
-            SystemTrayEvent::LeftClick { .. } => {
-                let window = app.get_window("main").unwrap();
-                toggle_spotlight_window(&window);
-            }
-            _ => {}
-        })
-        .on_window_event(|event| {
-            if let WindowEvent::Focused(false) = event.event() {
-                // Auto-hide the main window when it loses focus (spotlight behavior)
-                // But only if it's not actively processing a job
-                if event.window().label() == "main" {
-                    // Don't hide the window when it's processing a job
-                    // This is handled by checking the activeJobId in the frontend
-                    // We'll keep this commented for now
-                    // event.window().hide().unwrap();
-                }
             }
         })
         .setup(|app| {
-            // Register global shortcut (Ctrl+K or Cmd+K)
-            let app_handle = app.handle();
-            let mut shortcut_manager = app_handle.global_shortcut_manager();
-            
-            // Register multiple shortcuts for better user experience
-            let shortcuts = ["CommandOrControl+K", "CommandOrControl+Space"];
-            for shortcut in shortcuts.iter() {
-                let app_handle_clone = app_handle.clone();
-                shortcut_manager
-                    .register(shortcut, move || {
-                        let window = app_handle_clone.get_window("main").unwrap();
-                        toggle_spotlight_window(&window);
-                    })
-                    .unwrap_or_else(|e| println!("Failed to register shortcut {}: {}", shortcut, e));
-            }
-            
-            // Start API server
-            let app_state = app.state::<AppState>();
-            match start_api_server(&app_state) {
-                Ok(_) => println!("API server started"),
-                Err(e) => eprintln!("Failed to start API server: {}", e),
-            }
-            
-            // Get main window and set properties
-            let main_window = app.get_window("main").unwrap();
-            
-            // Set window properties
-            main_window.set_always_on_top(true).unwrap();
-            
-            // Position window at the top center (1/4 position)
-            let monitor = main_window.current_monitor().unwrap().unwrap();
-            let monitor_size = monitor.size();
-            let window_size = main_window.inner_size().unwrap();
-            
-            let x = (monitor_size.width as i32 - window_size.width as i32) / 2;
-            let y = monitor_size.height as i32 / 4 - window_size.height as i32 / 2;
-            
-            main_window.set_position(tauri::Position::Physical(tauri::PhysicalPosition { x, y })).unwrap();
-            
-            // Hide window on startup (will be shown with shortcut)
-            main_window.hide().unwrap();
-            
+            let launched = std::time::Instant::now();
+            let app_handle = app.handle().clone();
+
+            // Catch panics before anything else can cause one, so even a failing startup stops the
+            // backend and explains itself. Reports are only queued once settings say the user agreed.
+            let crash_reporter = crash::CrashReporter::new(&app_handle);
+            crash::install_panic_hook(&app_handle, crash_reporter.clone());
+            app.manage(crash::LastCrash::load(&crash_reporter));
+            app.manage(crash_reporter);
+
+            // Set up log files first so everything after this is captured
+            logs::init(&app_handle);
+
+            // The active profile decides which settings, history and backend config are used
+            app.manage(profiles::ProfileState::load(&app_handle));
+
+            // Load native settings before anything that depends on them
+            app.manage(settings::SettingsState::load(&app_handle));
+            logs::apply_settings(&app_handle);
+            crash::apply_settings(&app_handle);
+            dock::apply(&app_handle);
+
+            // Rotate and expire logs, recordings and screenshots within the storage cap, and run the
+            // cleanup policy
+            retention::start(&app_handle);
+            // Shrink screen recordings while the user is away
+            transcode::start(&app_handle);
+
+            // Steps that only read and write files run on their own threads while the main thread
+            // sets up the spotlight, shortcuts and tray, which have to be created there. Everything
+            // after the scope can count on all of them being done.
+            std::thread::scope(|scope| {
+                // Extra root certificates, before the first request leaves the machine
+                scope.spawn(|| tls::apply(&app_handle));
+                // Private directory for handing large payloads to the backend, cleared of leftovers
+                scope.spawn(|| {
+                    app_handle.manage(handoff::HandoffState::init(&app_handle));
+                });
+                // Feature flags from the cached remote manifest; a fresh copy is fetched below
+                scope.spawn(|| {
+                    app_handle.manage(flags::FlagsState::load(&app_handle));
+                });
+                // What the tray lists: prompt history and pinned prompts (SQLite in the app data
+                // directory), and saved snippets and quick actions
+                let tray_data = scope.spawn(|| {
+                    (
+                        history::HistoryState::open(&app_handle),
+                        quick_actions::QuickActionsState::load(&app_handle),
+                    )
+                });
+
+                // Get main window and set properties
+                match app.get_webview_window("main") {
+                    Some(main_window) => {
+                        #[cfg(target_os = "linux")]
+                        wayland::setup_spotlight(&main_window)
+                            .or_report(&app_handle, "Failed to set up the spotlight overlay");
+
+                        // Set window properties
+                        main_window
+                            .set_always_on_top(true)
+                            .or_report(&app_handle, "Failed to keep the spotlight on top");
+                        placement::place(&main_window, placement::Anchor::UpperCenter)
+                            .or_report(&app_handle, "Failed to position the spotlight");
+
+                        // Hide window on startup (will be shown with shortcut)
+                        main_window.hide().or_report(&app_handle, "Failed to hide the spotlight");
+                        // Unless it's docked, in which case its strip stays on screen
+                        sidebar::apply(&app_handle);
+                    }
+                    None => errors::alert(&app_handle, "Failed to set up the spotlight", "the main window is missing"),
+                }
+
+                // Register global shortcuts (Ctrl+K / Cmd+K and Ctrl+Space / Cmd+Space by default)
+                register_shortcuts(&app_handle);
+                println!("Spotlight ready {}ms after launch", launched.elapsed().as_millis());
+
+                // A panic loading them is raised here, on the main thread, where the panic hook acts on it
+                let (history, quick_actions) = tray_data.join().unwrap_or_else(|panic| std::panic::resume_unwind(panic));
+                app.manage(history);
+                app.manage(quick_actions);
+                tray::create(&app_handle);
+                // Privacy mode left on last time shows in the tray from the start
+                privacy::apply(&app_handle);
+            });
+            flags::refresh_manifest(&app_handle);
+
+            // Reports queued by earlier launches are only sent with the user's consent
+            crash::upload_pending(&app_handle);
+
+            // What happened while the user wasn't looking, for the notification center
+            app.manage(notifications::NotificationState::load(&app_handle));
+
+            // Jobs still running when the last run ended are offered again once the backend is up
+            jobs::restore_journal(&app_handle);
+            // And prompts queued while the backend was unreachable are sent once it's back
+            queue::restore(&app_handle);
+
+            // Opt-in anonymous telemetry, queued locally and sent in the background
+            let telemetry_state = telemetry::TelemetryState::load(&app_handle);
+            telemetry_state.start_flushing(&app_handle);
+            app.manage(telemetry_state);
+            telemetry::record(&app_handle, "app_start", &[("os", std::env::consts::OS)]);
+
+            // The shared prompt templates
+            app.manage(templates::TemplatesState::load(&app_handle));
+            // Recorded keyboard and mouse macros
+            app.manage(macros::MacroState::load(&app_handle));
+            // And the actions finished jobs performed, for replaying them
+            app.manage(scripts::ScriptsState::load(&app_handle));
+
+            // Text expansion is opt-in, so this only starts listening if enabled
+            app.manage(expansion::ExpansionState::load(&app_handle));
+            expansion::apply(&app_handle);
+            // Also opt-in, and sharing the same listener
+            hot_corner::apply(&app_handle);
+
+            // Hold back notifications (and optionally automation) during presentations and focus modes
+            quiet::start_watching(&app_handle);
+            // Never type into a lock screen or another user's session
+            screen_lock::start_watching(&app_handle);
+
+            // Saved automations bound to system events and to files changing in watched folders
+            app.manage(triggers::TriggersState::load(&app_handle));
+            triggers::start_watching(&app_handle);
+            triggers::apply_watches(&app_handle);
+
+            // Suggestions for copied errors, links and tracking numbers, if opted in
+            clipboard::start_watching(&app_handle);
+
+            // Model output streamed to each job's event channel
+            stream::start(&app_handle);
+
+            // Index installed applications for the spotlight launcher
+            app.state::<apps::AppIndex>().refresh_in_background();
+
+            // Heavy background work below waits for the user to step away
+            idle::start_watching(&app_handle);
+
+            // Index local files in the background (include/exclude paths come from settings)
+            app.state::<files::FileIndex>().start(&app_handle);
+
+            // Load embedded document context and pick up any files that changed since last run
+            let context_index = context::ContextIndex::load(&app_handle);
+            context_index.rebuild_in_background(&app_handle);
+            app.manage(context_index);
+
+            let calc_state = calc::CalcState::load(&app_handle);
+            calc_state.refresh_rates_if_stale(&app_handle);
+            app.manage(calc_state);
+
+            // Local models via Ollama, if the user wants it managed
+            ollama::start_if_enabled(&app_handle);
+
+            // Connect enabled MCP servers and serve their tools to the backend
+            mcp::apply_in_background(&app_handle);
+            mcp::start_proxy(&app_handle);
+
+            // Prometheus metrics on localhost, if enabled
+            metrics::apply(&app_handle);
+
+            // Start the API server off the main thread; the handshake says when it's ready
+            let backend_handle = app_handle.clone();
+            std::thread::spawn(move || {
+                let app_state = backend_handle.state::<AppState>();
+                match start_api_server(&backend_handle, &app_state) {
+                    Ok(_) => {
+                        println!("API server started");
+                        crash::watch_backend(&backend_handle, app_state.inner().clone());
+                        handshake::negotiate(&backend_handle, app_state.inner().clone());
+                    }
+                    Err(e) => eprintln!("Failed to start API server: {}", e),
+                }
+            });
+
+            // Look out for backend packages that are missing or have updates
+            backend_deps::start(&app_handle);
+            // And for its code changing under it, after an update or while developing
+            hot_swap::start(&app_handle);
+            // So model calls fail fast and prompts queue while the internet is down
+            network::start(&app_handle);
+
+            // Settings that couldn't be read were replaced by defaults; offer the last ones that worked
+            settings::offer_recovery(&app_handle);
+
             println!("Using CSS backdrop-filter for visual effects across all platforms");
             
             // We'll handle cleanup in the quit_app command instead of using listen_global
@@ -0,0 +1,203 @@
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader, Write};
+use std::process::{Child, ChildStdin, Command, Stdio};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+pub const PROTOCOL_VERSION: &str = "2024-11-05";
+
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+const TOOL_CALL_TIMEOUT: Duration = Duration::from_secs(120);
+
+#[derive(Clone, Debug, Serialize)]
+pub struct McpTool {
+    pub server: String,
+    pub name: String,
+    pub description: Option<String>,
+    pub input_schema: Value,
+}
+
+// A connection to one MCP server over stdio (newline-delimited JSON-RPC)
+pub struct McpClient {
+    server_id: String,
+    child: Child,
+    stdin: Arc<Mutex<ChildStdin>>,
+    responses: Receiver<Value>,
+    next_id: u64,
+    pub tools: Vec<McpTool>,
+}
+
+fn write_message(stdin: &Mutex<ChildStdin>, message: &Value) -> Result<(), String> {
+    let mut stdin = stdin.lock().unwrap();
+    writeln!(stdin, "{}", message)
+        .and_then(|_| stdin.flush())
+        .map_err(|e| format!("Failed to write to MCP server: {}", e))
+}
+
+impl McpClient {
+    pub fn connect(
+        server_id: &str,
+        command: &str,
+        args: &[String],
+        env: &HashMap<String, String>,
+    ) -> Result<Self, String> {
+        let mut child = Command::new(command)
+            .args(args)
+            .envs(env)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()
+            .map_err(|e| format!("Failed to start MCP server '{}': {}", command, e))?;
+
+        let stdin = Arc::new(Mutex::new(child.stdin.take().ok_or("MCP server has no stdin")?));
+        let stdout = child.stdout.take().ok_or("MCP server has no stdout")?;
+        let (sender, responses) = mpsc::channel();
+
+        // Forward responses to whoever is waiting and answer the server's own requests
+        let reader_stdin = stdin.clone();
+        std::thread::spawn(move || {
+            for line in BufReader::new(stdout).lines() {
+                let line = match line {
+                    Ok(line) => line,
+                    Err(_) => break,
+                };
+                let message: Value = match serde_json::from_str(&line) {
+                    Ok(message) => message,
+                    // Some servers log to stdout; skip anything that isn't JSON-RPC
+                    Err(_) => continue,
+                };
+
+                let delivered = match (message.get("id"), message.get("method").and_then(|m| m.as_str())) {
+                    (Some(id), Some(method)) => {
+                        let reply = if method == "ping" {
+                            json!({ "jsonrpc": "2.0", "id": id, "result": {} })
+                        } else {
+                            json!({
+                                "jsonrpc": "2.0",
+                                "id": id,
+                                "error": { "code": -32601, "message": format!("Method not supported: {}", method) }
+                            })
+                        };
+                        let _ = write_message(&reader_stdin, &reply);
+                        true
+                    }
+                    (Some(_), None) => sender.send(message).is_ok(),
+                    // Notifications aren't used yet
+                    _ => true,
+                };
+                if !delivered {
+                    break;
+                }
+            }
+        });
+
+        let mut client = McpClient {
+            server_id: server_id.to_string(),
+            child,
+            stdin,
+            responses,
+            next_id: 1,
+            tools: Vec::new(),
+        };
+
+        client.request(
+            "initialize",
+            json!({
+                "protocolVersion": PROTOCOL_VERSION,
+                "capabilities": {},
+                "clientInfo": { "name": "krya", "version": env!("CARGO_PKG_VERSION") }
+            }),
+            REQUEST_TIMEOUT,
+        )?;
+        write_message(
+            &client.stdin,
+            &json!({ "jsonrpc": "2.0", "method": "notifications/initialized" }),
+        )?;
+        client.tools = client.list_tools()?;
+        Ok(client)
+    }
+
+    fn request(&mut self, method: &str, params: Value, timeout: Duration) -> Result<Value, String> {
+        let id = self.next_id;
+        self.next_id += 1;
+        write_message(
+            &self.stdin,
+            &json!({ "jsonrpc": "2.0", "id": id, "method": method, "params": params }),
+        )?;
+
+        let started = Instant::now();
+        loop {
+            let remaining = timeout
+                .checked_sub(started.elapsed())
+                .ok_or_else(|| format!("MCP server timed out on {}", method))?;
+            let message = match self.responses.recv_timeout(remaining) {
+                Ok(message) => message,
+                Err(RecvTimeoutError::Timeout) => return Err(format!("MCP server timed out on {}", method)),
+                Err(RecvTimeoutError::Disconnected) => return Err("MCP server exited".to_string()),
+            };
+
+            // Drop late responses to requests that already timed out
+            if message.get("id").and_then(|i| i.as_u64()) != Some(id) {
+                continue;
+            }
+            if let Some(error) = message.get("error") {
+                let text = error.get("message").and_then(|m| m.as_str()).unwrap_or("unknown error");
+                return Err(format!("MCP {} failed: {}", method, text));
+            }
+            return Ok(message.get("result").cloned().unwrap_or(Value::Null));
+        }
+    }
+
+    fn list_tools(&mut self) -> Result<Vec<McpTool>, String> {
+        let mut tools = Vec::new();
+        let mut cursor: Option<String> = None;
+
+        loop {
+            let params = match &cursor {
+                Some(cursor) => json!({ "cursor": cursor }),
+                None => json!({}),
+            };
+            let result = self.request("tools/list", params, REQUEST_TIMEOUT)?;
+
+            for tool in result.get("tools").and_then(|t| t.as_array()).into_iter().flatten() {
+                if let Some(name) = tool.get("name").and_then(|n| n.as_str()) {
+                    tools.push(McpTool {
+                        server: self.server_id.clone(),
+                        name: name.to_string(),
+                        description: tool.get("description").and_then(|d| d.as_str()).map(|d| d.to_string()),
+                        input_schema: tool.get("inputSchema").cloned().unwrap_or_else(|| json!({})),
+                    });
+                }
+            }
+
+            cursor = result.get("nextCursor").and_then(|c| c.as_str()).map(|c| c.to_string());
+            if cursor.is_none() {
+                return Ok(tools);
+            }
+        }
+    }
+
+    // Call a tool, returning the MCP result ({"content": [...], "isError": bool})
+    pub fn call_tool(&mut self, name: &str, arguments: Value) -> Result<Value, String> {
+        self.request(
+            "tools/call",
+            json!({ "name": name, "arguments": arguments }),
+            TOOL_CALL_TIMEOUT,
+        )
+    }
+
+    pub fn is_alive(&mut self) -> bool {
+        matches!(self.child.try_wait(), Ok(None))
+    }
+}
+
+impl Drop for McpClient {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
@@ -0,0 +1,282 @@
+mod client;
+mod proxy;
+mod server;
+
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use tauri::Manager;
+
+use crate::settings::{self, McpServerConfig, McpServerSettings};
+use client::McpClient;
+pub use client::McpTool;
+
+#[derive(Clone, Debug, Serialize)]
+pub struct McpServerStatus {
+    pub id: String,
+    pub name: String,
+    pub enabled: bool,
+    pub connected: bool,
+    pub tool_count: usize,
+    pub error: Option<String>,
+}
+
+struct Connection {
+    // The config it was started with, so edits trigger a reconnect
+    config: McpServerConfig,
+    tools: Vec<McpTool>,
+    client: Arc<Mutex<McpClient>>,
+}
+
+// Connections to the user's MCP servers, plus the token the backend uses to reach them
+pub struct McpState {
+    connections: Mutex<HashMap<String, Connection>>,
+    errors: Mutex<HashMap<String, String>>,
+    // Held while connecting so overlapping settings saves don't start servers twice
+    syncing: Mutex<()>,
+    pub proxy_token: String,
+}
+
+impl Default for McpState {
+    fn default() -> Self {
+        McpState {
+            connections: Mutex::new(HashMap::new()),
+            errors: Mutex::new(HashMap::new()),
+            syncing: Mutex::new(()),
+            proxy_token: uuid::Uuid::new_v4().to_string(),
+        }
+    }
+}
+
+impl McpState {
+    pub fn tools(&self) -> Vec<McpTool> {
+        let connections = self.connections.lock().unwrap();
+        let mut tools: Vec<McpTool> = connections
+            .values()
+            .flat_map(|connection| connection.tools.clone())
+            .collect();
+        tools.sort_by(|a, b| (&a.server, &a.name).cmp(&(&b.server, &b.name)));
+        tools
+    }
+
+    pub fn call(&self, server: &str, tool: &str, arguments: Value) -> Result<Value, String> {
+        let client = self
+            .connections
+            .lock()
+            .unwrap()
+            .get(server)
+            .map(|connection| connection.client.clone())
+            .ok_or_else(|| format!("MCP server not connected: {}", server))?;
+
+        let mut client = client.lock().unwrap();
+        client.call_tool(tool, arguments)
+    }
+
+    // Connect enabled servers and drop disabled, removed, edited, or dead ones
+    fn sync(&self, servers: &[McpServerConfig]) {
+        let _guard = self.syncing.lock().unwrap();
+
+        {
+            let mut connections = self.connections.lock().unwrap();
+            connections.retain(|id, connection| {
+                let wanted = servers
+                    .iter()
+                    .any(|config| config.enabled && &config.id == id && config == &connection.config);
+                // A client busy with a tool call is alive by definition
+                let alive = connection
+                    .client
+                    .try_lock()
+                    .map(|mut client| client.is_alive())
+                    .unwrap_or(true);
+                wanted && alive
+            });
+        }
+        self.errors
+            .lock()
+            .unwrap()
+            .retain(|id, _| servers.iter().any(|config| config.enabled && &config.id == id));
+
+        for config in servers.iter().filter(|config| config.enabled) {
+            if self.connections.lock().unwrap().contains_key(&config.id) {
+                continue;
+            }
+
+            let args: Vec<String> = config
+                .args
+                .iter()
+                .map(|arg| settings::expand_home(arg).to_string_lossy().to_string())
+                .collect();
+            match McpClient::connect(&config.id, &config.command, &args, &config.env) {
+                Ok(client) => {
+                    println!("Connected to MCP server '{}' ({} tools)", config.name, client.tools.len());
+                    self.errors.lock().unwrap().remove(&config.id);
+                    self.connections.lock().unwrap().insert(
+                        config.id.clone(),
+                        Connection {
+                            config: config.clone(),
+                            tools: client.tools.clone(),
+                            client: Arc::new(Mutex::new(client)),
+                        },
+                    );
+                }
+                Err(e) => {
+                    eprintln!("Failed to connect to MCP server '{}': {}", config.name, e);
+                    self.errors.lock().unwrap().insert(config.id.clone(), e);
+                }
+            }
+        }
+    }
+}
+
+// Bring connections in line with settings without blocking the caller
+pub fn apply_in_background(app_handle: &tauri::AppHandle) {
+    let app_handle = app_handle.clone();
+    std::thread::spawn(move || {
+        let servers = settings::current(&app_handle).mcp.servers;
+        app_handle.state::<McpState>().sync(&servers);
+    });
+}
+
+// Start the loopback proxy the backend calls MCP tools through
+pub fn start_proxy(app_handle: &tauri::AppHandle) {
+    proxy::start(app_handle, settings::current(app_handle).mcp.proxy_port);
+}
+
+// Environment telling the Python server where the proxy is
+pub fn env_hints(app_handle: &tauri::AppHandle) -> Vec<(&'static str, String)> {
+    let port = settings::current(app_handle).mcp.proxy_port;
+    vec![
+        ("KRYA_MCP_PROXY_URL", format!("http://127.0.0.1:{}", port)),
+        ("KRYA_MCP_PROXY_TOKEN", app_handle.state::<McpState>().proxy_token.clone()),
+    ]
+}
+
+fn preset(id: &str, name: &str, package: &str, extra_args: &[&str], env: &[&str]) -> McpServerConfig {
+    let mut args = vec!["-y".to_string(), package.to_string()];
+    args.extend(extra_args.iter().map(|a| a.to_string()));
+    McpServerConfig {
+        id: id.to_string(),
+        name: name.to_string(),
+        command: "npx".to_string(),
+        args,
+        env: env.iter().map(|key| (key.to_string(), String::new())).collect(),
+        enabled: false,
+    }
+}
+
+// Templates for common servers; empty env values are for the user to fill in
+#[tauri::command]
+pub fn get_mcp_presets() -> Vec<McpServerConfig> {
+    vec![
+        preset(
+            "filesystem",
+            "Filesystem",
+            "@modelcontextprotocol/server-filesystem",
+            &["~/Documents"],
+            &[],
+        ),
+        preset(
+            "github",
+            "GitHub",
+            "@modelcontextprotocol/server-github",
+            &[],
+            &["GITHUB_PERSONAL_ACCESS_TOKEN"],
+        ),
+        preset(
+            "slack",
+            "Slack",
+            "@modelcontextprotocol/server-slack",
+            &[],
+            &["SLACK_BOT_TOKEN", "SLACK_TEAM_ID"],
+        ),
+    ]
+}
+
+#[tauri::command]
+pub fn list_mcp_servers(app_handle: tauri::AppHandle, state: tauri::State<McpState>) -> Vec<McpServerStatus> {
+    let connections = state.connections.lock().unwrap();
+    let errors = state.errors.lock().unwrap();
+
+    settings::current(&app_handle)
+        .mcp
+        .servers
+        .into_iter()
+        .map(|config| {
+            let connection = connections.get(&config.id);
+            McpServerStatus {
+                connected: connection.is_some(),
+                tool_count: connection.map(|c| c.tools.len()).unwrap_or(0),
+                error: errors.get(&config.id).cloned(),
+                id: config.id,
+                name: config.name,
+                enabled: config.enabled,
+            }
+        })
+        .collect()
+}
+
+#[tauri::command]
+pub fn set_mcp_server_enabled(app_handle: tauri::AppHandle, id: String, enabled: bool) -> Result<(), String> {
+    let mut current = settings::current(&app_handle);
+    let server = current
+        .mcp
+        .servers
+        .iter_mut()
+        .find(|server| server.id == id)
+        .ok_or_else(|| format!("MCP server not found: {}", id))?;
+    server.enabled = enabled;
+    settings::replace(&app_handle, current)
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct McpEndpointInfo {
+    pub enabled: bool,
+    pub url: String,
+    // Bearer token MCP clients must send
+    pub token: String,
+}
+
+fn endpoint_info(app_handle: &tauri::AppHandle) -> McpEndpointInfo {
+    let current = settings::current(app_handle).mcp;
+    McpEndpointInfo {
+        enabled: current.server.enabled,
+        url: format!("http://127.0.0.1:{}{}", current.proxy_port, proxy::SERVER_PATH),
+        token: current.server.token,
+    }
+}
+
+// Command for the settings pane: where other agents can reach Krya's MCP server
+#[tauri::command]
+pub fn get_mcp_endpoint(app_handle: tauri::AppHandle) -> McpEndpointInfo {
+    endpoint_info(&app_handle)
+}
+
+// Turn Krya's MCP server on or off; a token is generated the first time it is enabled
+#[tauri::command]
+pub fn set_mcp_endpoint_enabled(app_handle: tauri::AppHandle, enabled: bool) -> Result<McpEndpointInfo, String> {
+    let mut current = settings::current(&app_handle);
+    let token = if current.mcp.server.token.is_empty() {
+        uuid::Uuid::new_v4().simple().to_string()
+    } else {
+        current.mcp.server.token.clone()
+    };
+    current.mcp.server = McpServerSettings { enabled, token };
+    settings::replace(&app_handle, current)?;
+    Ok(endpoint_info(&app_handle))
+}
+
+#[tauri::command]
+pub fn list_mcp_tools(state: tauri::State<McpState>) -> Vec<McpTool> {
+    state.tools()
+}
+
+#[tauri::command(async)]
+pub fn call_mcp_tool(
+    state: tauri::State<McpState>,
+    server: String,
+    tool: String,
+    arguments: Option<Value>,
+) -> Result<Value, String> {
+    state.call(&server, &tool, arguments.unwrap_or_else(|| serde_json::json!({})))
+}
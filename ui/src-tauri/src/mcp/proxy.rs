@@ -0,0 +1,241 @@
+use serde_json::{json, Value};
+use tauri::Manager;
+use tiny_http::{Header, Method, Request, Response, Server};
+
+use super::{server, McpState};
+use crate::exec::{self, ExecRequest, ExecState};
+use crate::file_access::{self, FileRequest};
+use crate::{compression, input, repo, scopes};
+use crate::handoff::{self, HandoffState};
+
+// Header the backend must send with the per-launch token
+const TOKEN_HEADER: &str = "X-Krya-Token";
+
+// Where outside agents reach Krya's own MCP server
+pub const SERVER_PATH: &str = "/mcp";
+
+fn respond(request: Request, status: u16, body: &Value) {
+    // Tool results can carry images, so compress large ones for clients that accept it
+    let accepted = header(&request, "Accept-Encoding").and_then(compression::from_accept_encoding);
+    let (body, encoding) = match compression::maybe_compress(accepted, body.to_string().into_bytes()) {
+        Ok(compressed) => compressed,
+        Err(e) => {
+            eprintln!("{}", e);
+            (body.to_string().into_bytes(), None)
+        }
+    };
+
+    let content_type = Header::from_bytes(&b"Content-Type"[..], &b"application/json"[..])
+        .expect("static header is valid");
+    let mut response = Response::from_data(body)
+        .with_status_code(status)
+        .with_header(content_type);
+    if let Some(encoding) = encoding {
+        let content_encoding = Header::from_bytes(&b"Content-Encoding"[..], encoding.name().as_bytes())
+            .expect("encoding names are valid header values");
+        response = response.with_header(content_encoding);
+    }
+    if let Err(e) = request.respond(response) {
+        eprintln!("Failed to send MCP proxy response: {}", e);
+    }
+}
+
+fn read_body(request: &mut Request) -> Result<String, String> {
+    let encoding = match header(request, "Content-Encoding") {
+        Some(name) => Some(compression::Encoding::parse(name).ok_or_else(|| format!("Unsupported encoding: {}", name))?),
+        None => None,
+    };
+
+    let mut body = Vec::new();
+    request
+        .as_reader()
+        .read_to_end(&mut body)
+        .map_err(|e| format!("Failed to read request: {}", e))?;
+    let body = match encoding {
+        Some(encoding) => compression::decompress(encoding, &body)?,
+        None => body,
+    };
+    String::from_utf8(body).map_err(|e| format!("Request is not valid UTF-8: {}", e))
+}
+
+// Very large tool results (e.g. images) go to the backend as a file it reads once and deletes
+fn hand_off_if_large(app_handle: &tauri::AppHandle, result: Value) -> Value {
+    let body = result.to_string();
+    if body.len() < handoff::THRESHOLD {
+        return result;
+    }
+    match app_handle.state::<HandoffState>().write(None, "json", body.as_bytes()) {
+        Ok(path) => json!({ "handoff": path }),
+        Err(e) => {
+            eprintln!("Sending large tool result inline: {}", e);
+            result
+        }
+    }
+}
+
+fn route(app_handle: &tauri::AppHandle, method: &Method, path: &str, body: &str) -> (u16, Value) {
+    let state = app_handle.state::<McpState>();
+    match (method, path) {
+        (Method::Get, "/mcp/tools") => (200, json!({ "tools": state.tools() })),
+        // Lets the backend see project state without shelling out to git
+        (Method::Get, "/context/repo") => match scopes::working_directory(app_handle).map(|dir| repo::context(&dir)) {
+            Some(Ok(context)) => (200, json!({ "repo": context })),
+            Some(Err(e)) => (500, json!({ "error": e })),
+            None => (200, json!({ "repo": null })),
+        },
+        (Method::Post, "/mcp/call") => {
+            let request: Value = match serde_json::from_str(body) {
+                Ok(request) => request,
+                Err(e) => return (400, json!({ "error": format!("Invalid request: {}", e) })),
+            };
+            let server = request.get("server").and_then(|s| s.as_str()).unwrap_or("");
+            let tool = request.get("tool").and_then(|t| t.as_str()).unwrap_or("");
+            let arguments = request.get("arguments").cloned().unwrap_or_else(|| json!({}));
+
+            match state.call(server, tool, arguments) {
+                Ok(result) => (200, hand_off_if_large(app_handle, result)),
+                Err(e) => (502, json!({ "error": e })),
+            }
+        }
+        // Programs the backend runs go through the same allowlist and audit log as the frontend's
+        (Method::Post, "/exec") => {
+            let request: ExecRequest = match serde_json::from_str(body) {
+                Ok(request) => request,
+                Err(e) => return (400, json!({ "error": format!("Invalid request: {}", e) })),
+            };
+            match exec::run(app_handle, &request, "backend") {
+                Ok(result) => (200, json!(result)),
+                Err(e) => (403, json!({ "error": e })),
+            }
+        }
+        // Asked by a running script before each click or keystroke, as for the shell's own input
+        (Method::Post, "/input/check") => match input::check_target(app_handle) {
+            Ok(()) => (200, json!({ "allowed": true })),
+            Err(e) => (403, json!({ "error": e })),
+        },
+        // For when a job is stopped while its script runs
+        (Method::Post, "/exec/stop") => {
+            let exec_id = serde_json::from_str::<Value>(body)
+                .ok()
+                .and_then(|request| request.get("exec_id").and_then(|id| id.as_str()).map(str::to_string));
+            match exec_id {
+                Some(exec_id) => (200, json!({ "stopped": app_handle.state::<ExecState>().stop(&exec_id) })),
+                None => (400, json!({ "error": "Missing exec_id" })),
+            }
+        }
+        // Instead of the backend's own file calls, so they're held to the allowed folders and audited
+        (Method::Post, "/files") => {
+            let request: FileRequest = match serde_json::from_str(body) {
+                Ok(request) => request,
+                Err(e) => return (400, json!({ "error": format!("Invalid request: {}", e) })),
+            };
+            match file_access::run(app_handle, &request, "backend") {
+                Ok(result) => (200, json!(result)),
+                Err(e) => (403, json!({ "error": e })),
+            }
+        }
+        _ => (404, json!({ "error": format!("Not found: {}", path) })),
+    }
+}
+
+fn header<'a>(request: &'a Request, name: &'static str) -> Option<&'a str> {
+    request
+        .headers()
+        .iter()
+        .find(|h| h.field.equiv(name))
+        .map(|h| h.value.as_str())
+}
+
+// Streamable HTTP transport for outside MCP clients (POST only; we never push server messages)
+fn handle_server(app_handle: &tauri::AppHandle, request: Request, method: &Method, body: &str) {
+    if method != &Method::Post {
+        respond(request, 405, &json!({ "error": "Only POST is supported" }));
+        return;
+    }
+
+    let message: Value = match serde_json::from_str(body) {
+        Ok(message) => message,
+        Err(e) => {
+            let error = json!({ "jsonrpc": "2.0", "id": null, "error": { "code": -32700, "message": e.to_string() } });
+            respond(request, 400, &error);
+            return;
+        }
+    };
+
+    let reply = match &message {
+        Value::Array(batch) => {
+            let replies: Vec<Value> = batch
+                .iter()
+                .filter_map(|message| server::handle_message(app_handle, message))
+                .collect();
+            if replies.is_empty() {
+                None
+            } else {
+                Some(Value::Array(replies))
+            }
+        }
+        message => server::handle_message(app_handle, message),
+    };
+
+    match reply {
+        Some(reply) => respond(request, 200, &reply),
+        // Notifications are only acknowledged
+        None => {
+            let _ = request.respond(Response::empty(202));
+        }
+    }
+}
+
+fn handle(app_handle: &tauri::AppHandle, mut request: Request) {
+    let path = request.url().split('?').next().unwrap_or("").to_string();
+
+    // Outside MCP clients use the token from settings; the backend uses the per-launch token
+    let authorized = if path == SERVER_PATH {
+        let bearer = header(&request, "Authorization").and_then(|value| value.strip_prefix("Bearer "));
+        server::is_authorized(app_handle, bearer)
+    } else {
+        let token = app_handle.state::<McpState>().proxy_token.clone();
+        header(&request, TOKEN_HEADER) == Some(token.as_str())
+    };
+    if !authorized {
+        respond(request, 401, &json!({ "error": "Missing or invalid token" }));
+        return;
+    }
+
+    let body = match read_body(&mut request) {
+        Ok(body) => body,
+        Err(e) => {
+            respond(request, 400, &json!({ "error": e }));
+            return;
+        }
+    };
+
+    let method = request.method().clone();
+    if path == SERVER_PATH {
+        handle_server(app_handle, request, &method, &body);
+    } else {
+        let (status, response) = route(app_handle, &method, &path, &body);
+        respond(request, status, &response);
+    }
+}
+
+// Serve MCP tools to the Python backend, and Krya's MCP server to other agents, on a loopback-only port
+pub fn start(app_handle: &tauri::AppHandle, port: u16) {
+    let server = match Server::http(("127.0.0.1", port)) {
+        Ok(server) => server,
+        Err(e) => {
+            eprintln!("Failed to start MCP proxy on port {}: {}", port, e);
+            return;
+        }
+    };
+    println!("MCP proxy listening on 127.0.0.1:{}", port);
+
+    let app_handle = app_handle.clone();
+    std::thread::spawn(move || {
+        for request in server.incoming_requests() {
+            // Tool calls can take a while, so each request gets its own thread
+            let app_handle = app_handle.clone();
+            std::thread::spawn(move || handle(&app_handle, request));
+        }
+    });
+}
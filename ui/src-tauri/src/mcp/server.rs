@@ -0,0 +1,183 @@
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use serde_json::{json, Value};
+use tauri::Manager;
+
+use crate::apps::{self, AppIndex};
+use crate::capabilities::{self, Capability};
+use crate::scopes::{self, Scope};
+use crate::handoff::HandoffState;
+use crate::protocol::{self, ScreenshotParams};
+use crate::{input, settings};
+
+// Protocol versions we can speak; the client's choice wins if we support it
+const SUPPORTED_VERSIONS: &[&str] = &["2025-03-26", super::client::PROTOCOL_VERSION];
+
+struct ServerTool {
+    name: &'static str,
+    description: &'static str,
+    scope: Scope,
+    // Backend feature the tool depends on, if any
+    capability: Option<Capability>,
+    input_schema: fn() -> Value,
+}
+
+const TOOLS: &[ServerTool] = &[
+    ServerTool {
+        name: "screenshot",
+        description: "Capture the user's screen as a PNG image",
+        scope: Scope::Screenshot,
+        capability: Some(Capability::Screenshot),
+        input_schema: || json!({ "type": "object", "properties": {} }),
+    },
+    ServerTool {
+        name: "type_text",
+        description: "Type text into the application that currently has focus",
+        scope: Scope::InputInjection,
+        capability: None,
+        input_schema: || {
+            json!({
+                "type": "object",
+                "properties": { "text": { "type": "string", "description": "Text to type" } },
+                "required": ["text"]
+            })
+        },
+    },
+    ServerTool {
+        name: "launch_app",
+        description: "Open an installed application by name",
+        scope: Scope::AppLaunch,
+        capability: None,
+        input_schema: || {
+            json!({
+                "type": "object",
+                "properties": { "name": { "type": "string", "description": "Application name, e.g. Safari" } },
+                "required": ["name"]
+            })
+        },
+    },
+];
+
+fn text_result(text: String, is_error: bool) -> Value {
+    json!({ "content": [{ "type": "text", "text": text }], "isError": is_error })
+}
+
+fn string_argument(arguments: &Value, name: &str) -> Result<String, String> {
+    arguments
+        .get(name)
+        .and_then(|v| v.as_str())
+        .map(|v| v.to_string())
+        .ok_or_else(|| format!("Missing argument: {}", name))
+}
+
+fn run_tool(app_handle: &tauri::AppHandle, name: &str, arguments: &Value) -> Result<Value, String> {
+    match name {
+        "screenshot" => {
+            let capture = protocol::call::<protocol::Screenshot>(&ScreenshotParams { handoff: true })?;
+            let image = match (capture.image, capture.file) {
+                (Some(image), _) => image,
+                (None, Some(file)) => BASE64.encode(app_handle.state::<HandoffState>().take(&file)?),
+                (None, None) => return Err("Screenshot response did not contain an image".to_string()),
+            };
+            Ok(json!({ "content": [{ "type": "image", "data": image, "mimeType": "image/png" }] }))
+        }
+        "type_text" => {
+            let text = string_argument(arguments, "text")?;
+            input::type_text(app_handle, &text)?;
+            Ok(text_result(format!("Typed {} characters", text.chars().count()), false))
+        }
+        "launch_app" => {
+            let query = string_argument(arguments, "name")?;
+            let found = app_handle
+                .state::<AppIndex>()
+                .search(&query, 1)
+                .into_iter()
+                .next()
+                .ok_or_else(|| format!("No installed application matches '{}'", query))?;
+            apps::launch(&found.app)?;
+            Ok(text_result(format!("Opened {}", found.app.name), false))
+        }
+        _ => Err(format!("Unknown tool: {}", name)),
+    }
+}
+
+fn call_tool(app_handle: &tauri::AppHandle, params: &Value) -> Value {
+    let name = params.get("name").and_then(|n| n.as_str()).unwrap_or("");
+    let arguments = params.get("arguments").cloned().unwrap_or_else(|| json!({}));
+
+    let result = TOOLS
+        .iter()
+        .find(|tool| tool.name == name)
+        .ok_or_else(|| format!("Unknown tool: {}", name))
+        .and_then(|tool| {
+            scopes::require(app_handle, tool.scope)?;
+            match tool.capability {
+                Some(capability) => capabilities::require(app_handle, capability),
+                None => Ok(()),
+            }
+        })
+        .and_then(|_| run_tool(app_handle, name, &arguments));
+
+    // Tool failures are reported in the result so the calling model can see them
+    result.unwrap_or_else(|e| text_result(e, true))
+}
+
+fn list_tools(app_handle: &tauri::AppHandle) -> Value {
+    let tools: Vec<Value> = TOOLS
+        .iter()
+        .filter(|tool| scopes::is_granted(app_handle, tool.scope))
+        .filter(|tool| {
+            tool.capability
+                .map(|capability| capabilities::is_available(app_handle, capability))
+                .unwrap_or(true)
+        })
+        .map(|tool| {
+            json!({
+                "name": tool.name,
+                "description": tool.description,
+                "inputSchema": (tool.input_schema)()
+            })
+        })
+        .collect();
+    json!({ "tools": tools })
+}
+
+fn initialize(params: &Value) -> Value {
+    let requested = params.get("protocolVersion").and_then(|v| v.as_str()).unwrap_or("");
+    let version = SUPPORTED_VERSIONS
+        .iter()
+        .find(|v| **v == requested)
+        .unwrap_or(&SUPPORTED_VERSIONS[0]);
+
+    json!({
+        "protocolVersion": version,
+        "capabilities": { "tools": {} },
+        "serverInfo": { "name": "krya", "version": env!("CARGO_PKG_VERSION") }
+    })
+}
+
+// Whether a bearer token from an MCP client matches the one shown in settings
+pub fn is_authorized(app_handle: &tauri::AppHandle, bearer: Option<&str>) -> bool {
+    let config = settings::current(app_handle).mcp.server;
+    config.enabled && !config.token.is_empty() && bearer == Some(config.token.as_str())
+}
+
+// Handle one JSON-RPC message; returns None for notifications, which get no reply
+pub fn handle_message(app_handle: &tauri::AppHandle, message: &Value) -> Option<Value> {
+    let id = message.get("id")?.clone();
+    let method = message.get("method").and_then(|m| m.as_str()).unwrap_or("");
+    let params = message.get("params").cloned().unwrap_or_else(|| json!({}));
+
+    let result = match method {
+        "initialize" => Ok(initialize(&params)),
+        "ping" => Ok(json!({})),
+        "tools/list" => Ok(list_tools(app_handle)),
+        "tools/call" => Ok(call_tool(app_handle, &params)),
+        _ => Err(json!({ "code": -32601, "message": format!("Method not found: {}", method) })),
+    };
+
+    Some(match result {
+        Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+        Err(error) => json!({ "jsonrpc": "2.0", "id": id, "error": error }),
+    })
+}
@@ -0,0 +1,193 @@
+use std::fmt::Write;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tauri::Manager;
+use tiny_http::{Header, Method, Request, Response, Server};
+
+use crate::queue::QueueState;
+use crate::{jobs, settings, workers, AppState};
+
+const METRICS_PATH: &str = "/metrics";
+
+// Upper bounds of the request latency buckets, in seconds; model calls can take minutes
+const LATENCY_BUCKETS: [f64; 11] = [0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0, 60.0, 120.0];
+
+static LATENCY_COUNTS: [AtomicU64; 11] = [
+    AtomicU64::new(0),
+    AtomicU64::new(0),
+    AtomicU64::new(0),
+    AtomicU64::new(0),
+    AtomicU64::new(0),
+    AtomicU64::new(0),
+    AtomicU64::new(0),
+    AtomicU64::new(0),
+    AtomicU64::new(0),
+    AtomicU64::new(0),
+    AtomicU64::new(0),
+];
+static LATENCY_SUM_MICROS: AtomicU64 = AtomicU64::new(0);
+
+// Backend requests by outcome, in the order of REQUEST_OUTCOMES
+const REQUEST_OUTCOMES: [&str; 4] = ["ok", "unreachable", "unsupported", "failed"];
+static REQUESTS: [AtomicU64; 4] = [AtomicU64::new(0), AtomicU64::new(0), AtomicU64::new(0), AtomicU64::new(0)];
+
+// Finished jobs by outcome, in the order of JOB_OUTCOMES; `lost` ones went away with the backend
+const JOB_OUTCOMES: [&str; 4] = ["completed", "failed", "stopped", "lost"];
+static JOBS_FINISHED: [AtomicU64; 4] = [AtomicU64::new(0), AtomicU64::new(0), AtomicU64::new(0), AtomicU64::new(0)];
+static JOBS_STARTED: AtomicU64 = AtomicU64::new(0);
+
+static BACKEND_RESTARTS: AtomicU64 = AtomicU64::new(0);
+
+#[derive(Clone, Copy)]
+pub enum RequestOutcome {
+    Ok,
+    Unreachable,
+    Unsupported,
+    Failed,
+}
+
+impl RequestOutcome {
+    pub fn name(self) -> &'static str {
+        REQUEST_OUTCOMES[self as usize]
+    }
+}
+
+#[derive(Clone, Copy)]
+pub enum JobOutcome {
+    Completed,
+    Failed,
+    Stopped,
+    Lost,
+}
+
+// The metrics endpoint, while it's enabled, and the port it's on
+#[derive(Default)]
+pub struct MetricsState(Mutex<Option<(u16, Arc<Server>)>>);
+
+pub fn record_request(outcome: RequestOutcome, elapsed: Duration) {
+    REQUESTS[outcome as usize].fetch_add(1, Ordering::Relaxed);
+    let seconds = elapsed.as_secs_f64();
+    if let Some(bucket) = LATENCY_BUCKETS.iter().position(|bound| seconds <= *bound) {
+        LATENCY_COUNTS[bucket].fetch_add(1, Ordering::Relaxed);
+    }
+    LATENCY_SUM_MICROS.fetch_add(elapsed.as_micros() as u64, Ordering::Relaxed);
+}
+
+pub fn record_job_started() {
+    JOBS_STARTED.fetch_add(1, Ordering::Relaxed);
+}
+
+pub fn record_job_finished(outcome: JobOutcome) {
+    JOBS_FINISHED[outcome as usize].fetch_add(1, Ordering::Relaxed);
+}
+
+pub fn record_backend_restart() {
+    BACKEND_RESTARTS.fetch_add(1, Ordering::Relaxed);
+}
+
+fn metric(out: &mut String, name: &str, kind: &str, help: &str) {
+    let _ = writeln!(out, "# HELP {} {}", name, help);
+    let _ = writeln!(out, "# TYPE {} {}", name, kind);
+}
+
+// Everything in Prometheus' text exposition format
+fn render(app_handle: &tauri::AppHandle) -> String {
+    let mut out = String::new();
+
+    metric(&mut out, "krya_jobs_started_total", "counter", "Automation jobs submitted to the backend.");
+    let _ = writeln!(out, "krya_jobs_started_total {}", JOBS_STARTED.load(Ordering::Relaxed));
+    metric(&mut out, "krya_jobs_finished_total", "counter", "Automation jobs that finished, by outcome.");
+    for (outcome, count) in JOB_OUTCOMES.iter().zip(JOBS_FINISHED.iter()) {
+        let count = count.load(Ordering::Relaxed);
+        let _ = writeln!(out, "krya_jobs_finished_total{{outcome=\"{}\"}} {}", outcome, count);
+    }
+    metric(&mut out, "krya_jobs_running", "gauge", "Automation jobs running right now.");
+    let _ = writeln!(out, "krya_jobs_running {}", jobs::running_count(app_handle));
+    metric(&mut out, "krya_pending_prompts", "gauge", "Prompts queued until the backend is reachable.");
+    let pending = app_handle.state::<QueueState>().len();
+    let _ = writeln!(out, "krya_pending_prompts {}", pending);
+
+    metric(&mut out, "krya_backend_up", "gauge", "Whether the backend process is running.");
+    let up = *app_handle.state::<AppState>().api_server_running.lock().unwrap();
+    let _ = writeln!(out, "krya_backend_up {}", up as u8);
+    metric(&mut out, "krya_backend_workers_ready", "gauge", "Extra backend workers answering model calls.");
+    let _ = writeln!(out, "krya_backend_workers_ready {}", workers::ready_count());
+    metric(&mut out, "krya_backend_restarts_total", "counter", "Times the backend was restarted.");
+    let _ = writeln!(out, "krya_backend_restarts_total {}", BACKEND_RESTARTS.load(Ordering::Relaxed));
+
+    metric(&mut out, "krya_backend_requests_total", "counter", "Requests to the backend, by outcome.");
+    for (outcome, count) in REQUEST_OUTCOMES.iter().zip(REQUESTS.iter()) {
+        let count = count.load(Ordering::Relaxed);
+        let _ = writeln!(out, "krya_backend_requests_total{{outcome=\"{}\"}} {}", outcome, count);
+    }
+
+    let name = "krya_backend_request_duration_seconds";
+    metric(&mut out, name, "histogram", "Time taken by requests to the backend.");
+    let mut cumulative = 0;
+    for (bound, count) in LATENCY_BUCKETS.iter().zip(LATENCY_COUNTS.iter()) {
+        cumulative += count.load(Ordering::Relaxed);
+        let _ = writeln!(out, "{}_bucket{{le=\"{}\"}} {}", name, bound, cumulative);
+    }
+    let total: u64 = REQUESTS.iter().map(|count| count.load(Ordering::Relaxed)).sum();
+    let _ = writeln!(out, "{}_bucket{{le=\"+Inf\"}} {}", name, total);
+    let sum = LATENCY_SUM_MICROS.load(Ordering::Relaxed) as f64 / 1_000_000.0;
+    let _ = writeln!(out, "{}_sum {}", name, sum);
+    let _ = writeln!(out, "{}_count {}", name, total);
+    out
+}
+
+fn handle(app_handle: &tauri::AppHandle, request: Request) {
+    let path = request.url().split('?').next().unwrap_or("");
+    let response = if request.method() != &Method::Get {
+        Response::from_string("Only GET is supported").with_status_code(405)
+    } else if path != METRICS_PATH {
+        Response::from_string(format!("Not found: {}", path)).with_status_code(404)
+    } else {
+        let content_type = Header::from_bytes(&b"Content-Type"[..], &b"text/plain; version=0.0.4"[..])
+            .expect("static header is valid");
+        Response::from_string(render(app_handle)).with_header(content_type)
+    };
+    if let Err(e) = request.respond(response) {
+        eprintln!("Failed to send metrics: {}", e);
+    }
+}
+
+fn serve(app_handle: &tauri::AppHandle, port: u16) -> Result<Arc<Server>, String> {
+    // Loopback only: job counts say when someone is using their computer
+    let server = Server::http(("127.0.0.1", port))
+        .map(Arc::new)
+        .map_err(|e| format!("Failed to start the metrics endpoint on port {}: {}", port, e))?;
+    println!("Metrics available at http://127.0.0.1:{}{}", port, METRICS_PATH);
+
+    let app_handle = app_handle.clone();
+    let incoming = server.clone();
+    std::thread::spawn(move || {
+        // Ends once the server is unblocked by `apply`
+        for request in incoming.incoming_requests() {
+            handle(&app_handle, request);
+        }
+    });
+    Ok(server)
+}
+
+// Start, move or stop the metrics endpoint to match the settings
+pub fn apply(app_handle: &tauri::AppHandle) {
+    let wanted = settings::current(app_handle).metrics;
+    let state = app_handle.state::<MetricsState>();
+    let mut running = state.0.lock().unwrap();
+    if let Some((port, _)) = running.as_ref() {
+        if wanted.enabled && *port == wanted.port {
+            return;
+        }
+    }
+    if let Some((_, server)) = running.take() {
+        server.unblock();
+    }
+    if wanted.enabled {
+        match serve(app_handle, wanted.port) {
+            Ok(server) => *running = Some((wanted.port, server)),
+            Err(e) => eprintln!("{}", e),
+        }
+    }
+}
@@ -0,0 +1,280 @@
+use rusqlite::Connection;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::storage;
+
+// Schema version of each JSON file in the app data directory, keyed by its path there.
+// Kept outside the files so a settings object saved by an older UI can't reset it.
+const VERSIONS_FILE: &str = "schema_versions.json";
+
+// Copies of files taken just before they were upgraded
+const BACKUP_DIR: &str = "backups";
+
+// Upgrades a JSON file from the version before `version` to `version`
+pub struct JsonMigration {
+    pub version: u32,
+    pub description: &'static str,
+    pub apply: fn(&mut Value),
+}
+
+// Upgrades a SQLite database to `version`, tracked in its user_version
+pub struct SqlMigration {
+    pub version: u32,
+    pub description: &'static str,
+    pub sql: &'static str,
+}
+
+// settings.json as of each release that changed its shape; append, never edit
+pub const SETTINGS: &[JsonMigration] = &[JsonMigration {
+    version: 1,
+    description: "Start tracking the settings schema version",
+    apply: |_| {},
+}];
+
+fn latest<T>(migrations: &[T], version: impl Fn(&T) -> u32) -> u32 {
+    migrations.iter().map(version).max().unwrap_or(0)
+}
+
+fn backup(data_dir: &Path, path: &Path, from: u32) -> Result<PathBuf, String> {
+    let dir = data_dir.join(BACKUP_DIR);
+    fs::create_dir_all(&dir).map_err(|e| format!("Failed to create {:?}: {}", dir, e))?;
+    let name = path.file_name().map(|name| name.to_string_lossy().to_string()).unwrap_or_default();
+    let target = dir.join(format!(
+        "{}.v{}-{}.bak",
+        name,
+        from,
+        chrono::Local::now().format("%Y%m%d-%H%M%S")
+    ));
+    fs::copy(path, &target).map_err(|e| format!("Failed to back up {:?}: {}", path, e))?;
+    Ok(target)
+}
+
+// Bring a JSON file in the app data directory up to the latest version, backing it up first.
+// A file that doesn't exist yet is new and written in the latest format, so it's only stamped.
+pub fn migrate_json(
+    app_handle: &tauri::AppHandle,
+    file_name: &str,
+    migrations: &[JsonMigration],
+) -> Result<(), String> {
+    migrate_json_in(&storage::data_dir(app_handle)?, file_name, migrations)
+}
+
+fn migrate_json_in(data_dir: &Path, file_name: &str, migrations: &[JsonMigration]) -> Result<(), String> {
+    let path = data_dir.join(file_name);
+    let latest = latest(migrations, |m| m.version);
+    let mut versions: HashMap<String, u32> = storage::load_in(data_dir, VERSIONS_FILE);
+    let from = if path.exists() {
+        versions.get(file_name).copied().unwrap_or(0)
+    } else {
+        latest
+    };
+    if from > latest {
+        eprintln!("{} is from a newer version (schema {}, this build knows {})", file_name, from, latest);
+        return Ok(());
+    }
+
+    if from < latest {
+        let contents = fs::read_to_string(&path).map_err(|e| format!("Failed to read {:?}: {}", path, e))?;
+        let mut value: Value =
+            serde_json::from_str(&contents).map_err(|e| format!("Failed to parse {:?}: {}", path, e))?;
+        let backup = backup(data_dir, &path, from)?;
+        for migration in migrations.iter().filter(|m| m.version > from) {
+            println!("Migrating {} to schema {}: {}", file_name, migration.version, migration.description);
+            (migration.apply)(&mut value);
+        }
+        storage::save_in(data_dir, file_name, &value, storage::Data::Config, false)?;
+        println!("Migrated {} from schema {} to {} (backup at {:?})", file_name, from, latest, backup);
+    }
+
+    if versions.get(file_name) != Some(&latest) {
+        versions.insert(file_name.to_string(), latest);
+        storage::save_in(data_dir, VERSIONS_FILE, &versions, storage::Data::Config, false)?;
+    }
+    Ok(())
+}
+
+// Bring a database up to the latest version, each step in its own transaction. `path` is where the
+// database lives on disk, if it does, so it can be backed up before anything already in it changes.
+pub fn migrate_database(
+    app_handle: &tauri::AppHandle,
+    conn: &Connection,
+    path: Option<&Path>,
+    migrations: &[SqlMigration],
+) -> Result<(), String> {
+    let data_dir = path.map(|_| storage::data_dir(app_handle)).transpose()?;
+    migrate_database_in(data_dir.as_deref(), conn, path, migrations)
+}
+
+// `data_dir` is where backups go, for a database on disk
+fn migrate_database_in(
+    data_dir: Option<&Path>,
+    conn: &Connection,
+    path: Option<&Path>,
+    migrations: &[SqlMigration],
+) -> Result<(), String> {
+    let from: u32 = conn
+        .query_row("PRAGMA user_version", [], |row| row.get(0))
+        .map_err(|e| format!("Failed to read the database version: {}", e))?;
+    let latest = latest(migrations, |m| m.version);
+    if from >= latest {
+        if from > latest {
+            eprintln!("Database is from a newer version (schema {}, this build knows {})", from, latest);
+        }
+        return Ok(());
+    }
+
+    let tables: u32 = conn
+        .query_row("SELECT count(*) FROM sqlite_master", [], |row| row.get(0))
+        .map_err(|e| format!("Failed to inspect the database: {}", e))?;
+    if let (Some(data_dir), Some(path)) = (data_dir, path.filter(|_| tables > 0)) {
+        let backup = backup(data_dir, path, from)?;
+        println!("Backed up {:?} to {:?} before migrating it", path, backup);
+    }
+
+    for migration in migrations.iter().filter(|m| m.version > from) {
+        println!("Migrating database to schema {}: {}", migration.version, migration.description);
+        let sql = format!(
+            "BEGIN; {} PRAGMA user_version = {}; COMMIT;",
+            migration.sql, migration.version
+        );
+        if let Err(e) = conn.execute_batch(&sql) {
+            let _ = conn.execute_batch("ROLLBACK;");
+            return Err(format!("Failed to migrate the database to schema {}: {}", migration.version, e));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("krya-migrations-{}-{}", std::process::id(), name));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn backups(dir: &Path) -> Vec<PathBuf> {
+        match fs::read_dir(dir.join(BACKUP_DIR)) {
+            Ok(entries) => entries.map(|entry| entry.unwrap().path()).collect(),
+            Err(_) => Vec::new(),
+        }
+    }
+
+    const JSON: &[JsonMigration] = &[
+        JsonMigration {
+            version: 1,
+            description: "Add a",
+            apply: |value| value["a"] = Value::from(1),
+        },
+        JsonMigration {
+            version: 2,
+            description: "Rename a to b",
+            apply: |value| {
+                let a = value.as_object_mut().unwrap().remove("a").unwrap();
+                value["b"] = a;
+            },
+        },
+    ];
+
+    const SQL: &[SqlMigration] = &[
+        SqlMigration {
+            version: 1,
+            description: "Create notes",
+            sql: "CREATE TABLE notes (text TEXT);",
+        },
+        SqlMigration {
+            version: 2,
+            description: "Add pinned",
+            sql: "ALTER TABLE notes ADD COLUMN pinned INTEGER NOT NULL DEFAULT 0;",
+        },
+    ];
+
+    fn user_version(conn: &Connection) -> u32 {
+        conn.query_row("PRAGMA user_version", [], |row| row.get(0)).unwrap()
+    }
+
+    #[test]
+    fn json_files_are_upgraded_and_backed_up() {
+        let dir = temp_dir("json");
+        fs::write(dir.join("settings.json"), r#"{"theme": "dark"}"#).unwrap();
+
+        migrate_json_in(&dir, "settings.json", JSON).unwrap();
+        let value: Value = serde_json::from_str(&fs::read_to_string(dir.join("settings.json")).unwrap()).unwrap();
+        assert_eq!(value, serde_json::json!({"theme": "dark", "b": 1}));
+        let versions: HashMap<String, u32> = storage::load_in(&dir, VERSIONS_FILE);
+        assert_eq!(versions.get("settings.json"), Some(&2));
+        let backed_up = backups(&dir);
+        assert_eq!(backed_up.len(), 1);
+        assert!(backed_up[0].file_name().unwrap().to_string_lossy().starts_with("settings.json.v0-"));
+        assert_eq!(fs::read_to_string(&backed_up[0]).unwrap(), r#"{"theme": "dark"}"#);
+
+        // Already up to date, so nothing runs or gets backed up again
+        migrate_json_in(&dir, "settings.json", JSON).unwrap();
+        assert_eq!(backups(&dir).len(), 1);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn new_json_files_are_only_stamped() {
+        let dir = temp_dir("new-json");
+        migrate_json_in(&dir, "settings.json", JSON).unwrap();
+        let versions: HashMap<String, u32> = storage::load_in(&dir, VERSIONS_FILE);
+        assert_eq!(versions.get("settings.json"), Some(&2));
+        assert!(!dir.join("settings.json").exists());
+        assert!(backups(&dir).is_empty());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn databases_are_upgraded_and_backed_up() {
+        let dir = temp_dir("database");
+        let path = dir.join("history.db");
+        let conn = Connection::open(&path).unwrap();
+        conn.execute_batch(SQL[0].sql).unwrap();
+        conn.execute_batch("PRAGMA user_version = 1;").unwrap();
+
+        migrate_database_in(Some(&dir), &conn, Some(&path), SQL).unwrap();
+        assert_eq!(user_version(&conn), 2);
+        conn.execute("INSERT INTO notes (text, pinned) VALUES ('hi', 1)", []).unwrap();
+        let backed_up = backups(&dir);
+        assert_eq!(backed_up.len(), 1);
+        assert!(backed_up[0].file_name().unwrap().to_string_lossy().starts_with("history.db.v1-"));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn empty_databases_are_not_backed_up() {
+        let dir = temp_dir("empty-database");
+        let path = dir.join("history.db");
+        let conn = Connection::open(&path).unwrap();
+        migrate_database_in(Some(&dir), &conn, Some(&path), SQL).unwrap();
+        assert_eq!(user_version(&conn), 2);
+        assert!(backups(&dir).is_empty());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn failed_steps_are_rolled_back() {
+        let conn = Connection::open_in_memory().unwrap();
+        let broken = [
+            SqlMigration {
+                version: 1,
+                description: "Create notes",
+                sql: SQL[0].sql,
+            },
+            SqlMigration {
+                version: 2,
+                description: "Broken",
+                sql: "ALTER TABLE missing ADD COLUMN pinned INTEGER;",
+            },
+        ];
+        assert!(migrate_database_in(None, &conn, None, &broken).is_err());
+        assert_eq!(user_version(&conn), 1);
+    }
+}
@@ -0,0 +1,118 @@
+use serde::Serialize;
+
+use crate::{capabilities, ollama};
+use crate::protocol::{self, ConfigUpdate, NoParams};
+use crate::settings::{self, ModelSettings};
+
+pub const GEMINI: &str = "gemini";
+pub const OLLAMA: &str = "ollama";
+
+// Gemini models offered in the picker; any other name can still be set directly
+const GEMINI_MODELS: &[&str] = &["gemini-2.5-flash", "gemini-2.5-pro", "gemini-2.0-flash"];
+
+#[derive(Clone, Debug, Serialize)]
+pub struct Provider {
+    pub id: String,
+    pub name: String,
+    pub available: bool,
+    pub models: Vec<String>,
+    // Why the provider isn't available, for the settings pane
+    pub detail: Option<String>,
+}
+
+fn gemini_provider() -> Provider {
+    let api_key_set = protocol::call::<protocol::GetConfig>(&NoParams {})
+        .ok()
+        .and_then(|config| config.get("api_key_set").and_then(|set| set.as_bool()));
+
+    Provider {
+        id: GEMINI.to_string(),
+        name: "Google Gemini".to_string(),
+        available: api_key_set.unwrap_or(false),
+        models: GEMINI_MODELS.iter().map(|m| m.to_string()).collect(),
+        detail: match api_key_set {
+            Some(true) => None,
+            Some(false) => Some("API key not configured".to_string()),
+            None => Some("API server is not running".to_string()),
+        },
+    }
+}
+
+fn ollama_provider(app_handle: &tauri::AppHandle) -> Provider {
+    let (models, detail) = match ollama::list_models(app_handle) {
+        Ok(models) => (models.into_iter().map(|m| m.name).collect::<Vec<String>>(), None),
+        Err(e) => (Vec::new(), Some(e)),
+    };
+
+    Provider {
+        id: OLLAMA.to_string(),
+        name: "Ollama (local)".to_string(),
+        available: detail.is_none() && !models.is_empty(),
+        detail: detail.or_else(|| {
+            if models.is_empty() {
+                Some("No models pulled yet".to_string())
+            } else {
+                None
+            }
+        }),
+        models,
+    }
+}
+
+// Environment for the Python server so it starts on the model chosen in native settings
+pub fn env_hints(app_handle: &tauri::AppHandle) -> Vec<(&'static str, String)> {
+    let current = settings::current(app_handle);
+    vec![
+        ("KRYA_PROVIDER", current.model.provider),
+        ("KRYA_MODEL", current.model.name),
+        ("KRYA_OLLAMA_URL", current.ollama.url),
+    ]
+}
+
+// Push the active model to a running backend
+fn forward(app_handle: &tauri::AppHandle) -> Result<(), String> {
+    let current = settings::current(app_handle);
+    protocol::call::<protocol::UpdateConfig>(&ConfigUpdate {
+        provider: Some(current.model.provider),
+        model_name: Some(current.model.name),
+        ollama_url: Some(current.ollama.url),
+        ..Default::default()
+    })
+    .and_then(|response| response.into_result())
+}
+
+#[tauri::command(async)]
+pub fn list_providers(app_handle: tauri::AppHandle) -> Vec<Provider> {
+    vec![gemini_provider(), ollama_provider(&app_handle)]
+}
+
+#[tauri::command]
+pub fn get_active_model(app_handle: tauri::AppHandle) -> ModelSettings {
+    settings::current(&app_handle).model
+}
+
+// Command to switch model; saved natively and sent to the backend if it's running
+#[tauri::command(async)]
+pub fn set_active_model(app_handle: tauri::AppHandle, provider: String, model: String) -> Result<ModelSettings, String> {
+    if provider != GEMINI && provider != OLLAMA {
+        return Err(format!("Unknown provider: {}", provider));
+    }
+    if model.trim().is_empty() {
+        return Err("Model name is required".to_string());
+    }
+
+    let mut current = settings::current(&app_handle);
+    current.model = ModelSettings {
+        provider,
+        name: model.trim().to_string(),
+    };
+    settings::replace(&app_handle, current.clone())?;
+
+    // The backend also picks this up from the environment on its next start
+    // Whether images are understood depends on the model
+    match forward(&app_handle) {
+        Ok(()) => capabilities::refresh_in_background(&app_handle),
+        Err(e) => eprintln!("Failed to send model change to API server: {}", e),
+    }
+    Ok(current.model)
+}
@@ -0,0 +1,95 @@
+use serde::Serialize;
+use std::net::{TcpStream, ToSocketAddrs};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::time::Duration;
+
+use crate::util::now_secs;
+use crate::{events, models, queue, settings};
+
+// Checked rarely while things work, often while they don't, so coming back online is noticed quickly
+const ONLINE_INTERVAL: Duration = Duration::from_secs(30);
+const OFFLINE_INTERVAL: Duration = Duration::from_secs(5);
+
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(3);
+
+// The model provider first, then public resolvers in case a network blocks one or the other
+const PROBES: &[&str] = &["generativelanguage.googleapis.com:443", "1.1.1.1:443", "8.8.8.8:53"];
+
+// Assumed until the first check says otherwise, so nothing is refused while starting up
+static ONLINE: AtomicBool = AtomicBool::new(true);
+static CHANGED_AT: AtomicU64 = AtomicU64::new(0);
+
+// A model served from this machine answers without the internet
+static LOCAL_MODEL: AtomicBool = AtomicBool::new(false);
+
+#[derive(Clone, Debug, Serialize)]
+pub struct NetworkStatus {
+    pub online: bool,
+    // When it last changed, in seconds since the epoch; 0 if it hasn't since startup
+    pub since: u64,
+}
+
+fn status() -> NetworkStatus {
+    NetworkStatus {
+        online: ONLINE.load(Ordering::SeqCst),
+        since: CHANGED_AT.load(Ordering::SeqCst),
+    }
+}
+
+// Behind a proxy only the proxy can be reached directly, and it answering is as good as it gets
+fn proxy_probe() -> Option<String> {
+    let url = ["HTTPS_PROXY", "https_proxy", "ALL_PROXY", "all_proxy"]
+        .iter()
+        .find_map(|name| std::env::var(name).ok().filter(|value| !value.is_empty()))?;
+    let url = reqwest::Url::parse(&url).ok()?;
+    Some(format!("{}:{}", url.host_str()?, url.port_or_known_default()?))
+}
+
+fn reachable(probe: &str) -> bool {
+    let addrs = match probe.to_socket_addrs() {
+        Ok(addrs) => addrs,
+        Err(_) => return false,
+    };
+    addrs.into_iter().any(|addr| TcpStream::connect_timeout(&addr, CONNECT_TIMEOUT).is_ok())
+}
+
+fn check() -> bool {
+    match proxy_probe() {
+        Some(proxy) => reachable(&proxy),
+        None => PROBES.iter().any(|probe| reachable(probe)),
+    }
+}
+
+// Whether calls that need the model provider should be refused right away instead of timing out
+pub fn is_offline() -> bool {
+    !ONLINE.load(Ordering::SeqCst) && !LOCAL_MODEL.load(Ordering::SeqCst)
+}
+
+// Keep track of which provider is active after the settings changed
+pub fn apply(app_handle: &tauri::AppHandle) {
+    let local = settings::current(app_handle).model.provider == models::OLLAMA;
+    LOCAL_MODEL.store(local, Ordering::SeqCst);
+}
+
+// Check internet reachability in the background for as long as the app runs
+pub fn start(app_handle: &tauri::AppHandle) {
+    apply(app_handle);
+    let app_handle = app_handle.clone();
+    std::thread::spawn(move || loop {
+        let online = check();
+        if ONLINE.swap(online, Ordering::SeqCst) != online {
+            CHANGED_AT.store(now_secs(), Ordering::SeqCst);
+            println!("Network is {}", if online { "back" } else { "unreachable" });
+            events::emit(&app_handle, &status());
+            if online {
+                queue::backend_recovered(&app_handle);
+            }
+        }
+        std::thread::sleep(if online { ONLINE_INTERVAL } else { OFFLINE_INTERVAL });
+    });
+}
+
+#[tauri::command]
+pub fn get_network_status() -> NetworkStatus {
+    status()
+}
@@ -0,0 +1,162 @@
+use serde::{Deserialize, Serialize};
+use std::sync::Mutex;
+use tauri::{Emitter, Manager};
+use tauri_plugin_notification::NotificationExt;
+
+use crate::util::now_secs;
+use crate::{quiet, storage};
+
+const NOTIFICATIONS_FILE: &str = "notifications.json";
+
+// Oldest notifications are dropped past this
+const MAX_NOTIFICATIONS: usize = 200;
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NotificationKind {
+    JobCompleted,
+    JobFailed,
+    JobStopped,
+    BackendRestarted,
+    BackendCrashed,
+    UpdateAvailable,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Notification {
+    pub id: u64,
+    pub kind: NotificationKind,
+    pub title: String,
+    #[serde(default)]
+    pub body: Option<String>,
+    pub timestamp: u64,
+    #[serde(default)]
+    pub read: bool,
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+struct NotificationLog {
+    #[serde(default)]
+    next_id: u64,
+    #[serde(default)]
+    notifications: Vec<Notification>,
+}
+
+impl NotificationLog {
+    fn unread(&self) -> usize {
+        self.notifications.iter().filter(|notification| !notification.read).count()
+    }
+}
+
+// Newest first, as the notification center lists them
+#[derive(Clone, Debug, Serialize)]
+pub struct NotificationList {
+    pub notifications: Vec<Notification>,
+    pub unread: usize,
+}
+
+#[derive(Clone, Debug, Serialize)]
+struct NotificationsChanged<'a> {
+    added: Option<&'a Notification>,
+    unread: usize,
+}
+
+// What happened while the user may not have been looking, kept across restarts
+pub struct NotificationState {
+    log: Mutex<NotificationLog>,
+}
+
+impl NotificationState {
+    pub fn load(app_handle: &tauri::AppHandle) -> Self {
+        NotificationState {
+            log: Mutex::new(storage::load_json(app_handle, NOTIFICATIONS_FILE)),
+        }
+    }
+}
+
+fn save_and_emit(app_handle: &tauri::AppHandle, log: &NotificationLog, added: Option<&Notification>) {
+    let data = if added.is_some() {
+        storage::Data::Activity
+    } else {
+        storage::Data::Removal
+    };
+    if let Err(e) = storage::save_json(app_handle, NOTIFICATIONS_FILE, log, data) {
+        eprintln!("Failed to save notifications: {}", e);
+    }
+    let changed = NotificationsChanged {
+        added,
+        unread: log.unread(),
+    };
+    if let Err(e) = app_handle.emit("notifications-changed", changed) {
+        eprintln!("Failed to emit notifications-changed: {}", e);
+    }
+}
+
+// Log a notification and show it as an OS toast too, unless notifications are being held back.
+// The log is kept on disk, so in privacy mode there's only the toast.
+pub fn push(app_handle: &tauri::AppHandle, kind: NotificationKind, title: &str, body: Option<String>) {
+    if storage::may_record(app_handle) {
+        let state = app_handle.state::<NotificationState>();
+        let mut log = state.log.lock().unwrap();
+        log.next_id += 1;
+        let notification = Notification {
+            id: log.next_id,
+            kind,
+            title: title.to_string(),
+            body: body.clone(),
+            timestamp: now_secs(),
+            read: false,
+        };
+        log.notifications.push(notification.clone());
+        let excess = log.notifications.len().saturating_sub(MAX_NOTIFICATIONS);
+        log.notifications.drain(..excess);
+        save_and_emit(app_handle, &log, Some(&notification));
+    }
+
+    if quiet::is_quiet(app_handle) {
+        return;
+    }
+    let mut toast = app_handle.notification().builder().title(title);
+    if let Some(body) = &body {
+        toast = toast.body(body);
+    }
+    if let Err(e) = toast.show() {
+        eprintln!("Failed to show notification: {}", e);
+    }
+}
+
+#[tauri::command]
+pub fn get_notifications(state: tauri::State<NotificationState>) -> NotificationList {
+    let log = state.log.lock().unwrap();
+    NotificationList {
+        notifications: log.notifications.iter().rev().cloned().collect(),
+        unread: log.unread(),
+    }
+}
+
+#[tauri::command]
+pub fn get_unread_notification_count(state: tauri::State<NotificationState>) -> usize {
+    state.log.lock().unwrap().unread()
+}
+
+// Command to mark the given notifications read, or all of them without `ids`; returns the unread count
+#[tauri::command]
+pub fn mark_read(app_handle: tauri::AppHandle, ids: Option<Vec<u64>>) -> usize {
+    let state = app_handle.state::<NotificationState>();
+    let mut log = state.log.lock().unwrap();
+    for notification in log.notifications.iter_mut() {
+        if ids.as_ref().map_or(true, |ids| ids.contains(&notification.id)) {
+            notification.read = true;
+        }
+    }
+    save_and_emit(&app_handle, &log, None);
+    log.unread()
+}
+
+#[tauri::command]
+pub fn clear_notifications(app_handle: tauri::AppHandle) {
+    let state = app_handle.state::<NotificationState>();
+    let mut log = state.log.lock().unwrap();
+    log.notifications.clear();
+    save_and_emit(&app_handle, &log, None);
+}
@@ -0,0 +1,197 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::path::PathBuf;
+use std::process::{Child, Command, Stdio};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tauri::Manager;
+
+use crate::{settings, tls};
+
+// How long `ollama serve` gets to start answering
+const START_TIMEOUT: Duration = Duration::from_secs(15);
+
+// An `ollama serve` process we started ourselves; one the user runs is never stopped
+#[derive(Default)]
+pub struct OllamaState(pub Mutex<Option<Child>>);
+
+#[derive(Clone, Debug, Serialize)]
+pub struct OllamaStatus {
+    pub installed: bool,
+    pub binary: Option<String>,
+    pub running: bool,
+    pub version: Option<String>,
+    // Whether the running server was started by Krya
+    pub managed: bool,
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ModelDetails {
+    pub family: Option<String>,
+    pub parameter_size: Option<String>,
+    pub quantization_level: Option<String>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct LocalModel {
+    pub name: String,
+    #[serde(default)]
+    pub size: u64,
+    #[serde(default)]
+    pub modified_at: Option<String>,
+    #[serde(default)]
+    pub details: ModelDetails,
+}
+
+fn base_url(app_handle: &tauri::AppHandle) -> String {
+    settings::current(app_handle).ollama.url.trim_end_matches('/').to_string()
+}
+
+fn get(app_handle: &tauri::AppHandle, path: &str, timeout: Duration) -> Result<Value, String> {
+    tls::client_builder()
+        .timeout(timeout)
+        .build()
+        .map_err(|e| format!("Failed to create HTTP client: {}", e))?
+        .get(format!("{}{}", base_url(app_handle), path))
+        .send()
+        .and_then(|r| r.error_for_status())
+        .map_err(|e| format!("Failed to reach Ollama: {}", e))?
+        .json()
+        .map_err(|e| format!("Invalid response from Ollama: {}", e))
+}
+
+fn server_version(app_handle: &tauri::AppHandle) -> Option<String> {
+    get(app_handle, "/api/version", Duration::from_secs(2))
+        .ok()?
+        .get("version")
+        .and_then(|v| v.as_str())
+        .map(|v| v.to_string())
+}
+
+// Look for the ollama binary on PATH, then where the official installers put it
+pub fn find_binary() -> Option<PathBuf> {
+    let exe = if cfg!(target_os = "windows") { "ollama.exe" } else { "ollama" };
+
+    let mut candidates: Vec<PathBuf> = std::env::var_os("PATH")
+        .map(|path| std::env::split_paths(&path).map(|dir| dir.join(exe)).collect())
+        .unwrap_or_default();
+
+    if cfg!(target_os = "macos") {
+        candidates.push(PathBuf::from("/Applications/Ollama.app/Contents/Resources/ollama"));
+        candidates.push(PathBuf::from("/usr/local/bin/ollama"));
+        candidates.push(PathBuf::from("/opt/homebrew/bin/ollama"));
+    } else if cfg!(target_os = "windows") {
+        if let Some(local) = std::env::var_os("LOCALAPPDATA") {
+            candidates.push(PathBuf::from(local).join("Programs").join("Ollama").join(exe));
+        }
+    } else {
+        candidates.push(PathBuf::from("/usr/local/bin/ollama"));
+        candidates.push(PathBuf::from("/usr/bin/ollama"));
+    }
+
+    candidates.into_iter().find(|path| path.is_file())
+}
+
+fn is_managed(app_handle: &tauri::AppHandle) -> bool {
+    let state = app_handle.state::<OllamaState>();
+    let mut process = state.0.lock().unwrap();
+    match process.as_mut().map(|child| child.try_wait()) {
+        Some(Ok(None)) => true,
+        Some(_) => {
+            // Our server exited on its own
+            process.take();
+            false
+        }
+        None => false,
+    }
+}
+
+pub fn status(app_handle: &tauri::AppHandle) -> OllamaStatus {
+    let binary = find_binary();
+    let version = server_version(app_handle);
+    OllamaStatus {
+        installed: binary.is_some() || version.is_some(),
+        binary: binary.map(|path| path.to_string_lossy().to_string()),
+        running: version.is_some(),
+        version,
+        managed: is_managed(app_handle),
+    }
+}
+
+// Start `ollama serve` unless a server is already answering
+pub fn start(app_handle: &tauri::AppHandle) -> Result<(), String> {
+    if server_version(app_handle).is_some() {
+        return Ok(());
+    }
+
+    let binary = find_binary().ok_or_else(|| "Ollama is not installed".to_string())?;
+    let child = Command::new(&binary)
+        .arg("serve")
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .map_err(|e| format!("Failed to start Ollama: {}", e))?;
+    println!("Ollama started with PID: {}", child.id());
+    *app_handle.state::<OllamaState>().0.lock().unwrap() = Some(child);
+
+    let started = Instant::now();
+    while started.elapsed() < START_TIMEOUT {
+        if server_version(app_handle).is_some() {
+            return Ok(());
+        }
+        std::thread::sleep(Duration::from_millis(500));
+    }
+    Err(format!("Ollama did not respond within {}s", START_TIMEOUT.as_secs()))
+}
+
+// Stop the server if we started it
+pub fn stop(app_handle: &tauri::AppHandle) {
+    if let Some(mut child) = app_handle.state::<OllamaState>().0.lock().unwrap().take() {
+        println!("Stopping Ollama");
+        let _ = child.kill();
+        let _ = child.wait();
+    }
+}
+
+// Start Ollama in the background at launch if the user asked for it
+pub fn start_if_enabled(app_handle: &tauri::AppHandle) {
+    if !settings::current(app_handle).ollama.auto_start {
+        return;
+    }
+    let app_handle = app_handle.clone();
+    std::thread::spawn(move || {
+        if let Err(e) = start(&app_handle) {
+            eprintln!("{}", e);
+        }
+    });
+}
+
+pub fn list_models(app_handle: &tauri::AppHandle) -> Result<Vec<LocalModel>, String> {
+    let response = get(app_handle, "/api/tags", Duration::from_secs(10))?;
+    serde_json::from_value(response.get("models").cloned().unwrap_or(Value::Array(Vec::new())))
+        .map_err(|e| format!("Invalid model list from Ollama: {}", e))
+}
+
+#[tauri::command(async)]
+pub fn get_ollama_status(app_handle: tauri::AppHandle) -> OllamaStatus {
+    status(&app_handle)
+}
+
+// Command to list models pulled into the local Ollama install
+#[tauri::command(async)]
+pub fn list_local_models(app_handle: tauri::AppHandle) -> Result<Vec<LocalModel>, String> {
+    list_models(&app_handle)
+}
+
+#[tauri::command(async)]
+pub fn start_ollama(app_handle: tauri::AppHandle) -> Result<OllamaStatus, String> {
+    start(&app_handle)?;
+    Ok(status(&app_handle))
+}
+
+#[tauri::command(async)]
+pub fn stop_ollama(app_handle: tauri::AppHandle) -> OllamaStatus {
+    stop(&app_handle);
+    status(&app_handle)
+}
@@ -0,0 +1,88 @@
+use tauri::{Manager, WebviewWindow};
+
+// Set a window's opacity right away; only call this on the UI thread
+#[cfg(target_os = "windows")]
+pub fn set_opacity(window: &WebviewWindow, opacity: f64) -> Result<(), String> {
+    use windows::Win32::Foundation::{COLORREF, HWND};
+    use windows::Win32::UI::WindowsAndMessaging::{
+        GetWindowLongW, SetLayeredWindowAttributes, SetWindowLongW, GWL_EXSTYLE, LWA_ALPHA, WS_EX_LAYERED,
+    };
+
+    let hwnd = HWND(window.hwnd().map_err(|e| format!("Failed to get the window handle: {}", e))?.0 as isize);
+    unsafe {
+        // Only layered windows can be translucent
+        let style = GetWindowLongW(hwnd, GWL_EXSTYLE);
+        if style & WS_EX_LAYERED.0 as i32 == 0 {
+            SetWindowLongW(hwnd, GWL_EXSTYLE, style | WS_EX_LAYERED.0 as i32);
+        }
+        SetLayeredWindowAttributes(hwnd, COLORREF(0), (opacity * 255.0).round() as u8, LWA_ALPHA)
+            .map_err(|e| format!("Failed to set window opacity: {}", e))
+    }
+}
+
+#[cfg(target_os = "macos")]
+pub fn set_opacity(window: &WebviewWindow, opacity: f64) -> Result<(), String> {
+    use cocoa::base::id;
+    use objc::{msg_send, sel, sel_impl};
+
+    let ns_window = window
+        .ns_window()
+        .map_err(|e| format!("Failed to get the window handle: {}", e))? as id;
+    unsafe {
+        let _: () = msg_send![ns_window, setAlphaValue: opacity];
+    }
+    Ok(())
+}
+
+// Needs a compositing window manager; without one the window stays opaque
+#[cfg(target_os = "linux")]
+pub fn set_opacity(window: &WebviewWindow, opacity: f64) -> Result<(), String> {
+    use gtk::prelude::WidgetExt;
+
+    let gtk_window = window
+        .gtk_window()
+        .map_err(|e| format!("Failed to get the window handle: {}", e))?;
+    gtk_window.set_opacity(opacity);
+    Ok(())
+}
+
+fn find_window(app_handle: &tauri::AppHandle, label: &str) -> Result<WebviewWindow, String> {
+    app_handle
+        .get_webview_window(label)
+        .ok_or_else(|| format!("Window not found: {}", label))
+}
+
+// Make a window translucent, from 0.0 (invisible) to 1.0 (opaque)
+pub fn apply_opacity(window: &WebviewWindow, opacity: f64) -> Result<(), String> {
+    if !opacity.is_finite() {
+        return Err("Opacity must be a number between 0 and 1".to_string());
+    }
+    let opacity = opacity.clamp(0.0, 1.0);
+    let target = window.clone();
+    // Window handles belong to the UI thread on every platform
+    window
+        .run_on_main_thread(move || {
+            if let Err(e) = set_opacity(&target, opacity) {
+                eprintln!("{}", e);
+            }
+        })
+        .map_err(|e| format!("Failed to set window opacity: {}", e))
+}
+
+// Let clicks pass through a window to whatever is under it, so overlays shown during
+// automation never catch the clicks it makes
+pub fn apply_click_through(window: &WebviewWindow, enabled: bool) -> Result<(), String> {
+    window
+        .set_ignore_cursor_events(enabled)
+        .map_err(|e| format!("Failed to set click-through: {}", e))
+}
+
+#[tauri::command]
+pub fn set_window_opacity(app_handle: tauri::AppHandle, label: String, value: f64) -> Result<(), String> {
+    apply_opacity(&find_window(&app_handle, &label)?, value)
+}
+
+#[tauri::command]
+pub fn set_window_click_through(app_handle: tauri::AppHandle, label: String, enabled: bool) -> Result<(), String> {
+    apply_click_through(&find_window(&app_handle, &label)?, enabled)
+}
@@ -0,0 +1,115 @@
+use serde::Serialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use tauri::Manager;
+
+// Folders under the app data directory for captured screen recordings and screenshots
+pub const RECORDINGS_DIR: &str = "recordings";
+pub const SCREENSHOTS_DIR: &str = "screenshots";
+
+// Kept out of the log directory, which storage cleanup is allowed to empty
+pub const AUDIT_DIR: &str = "audit";
+
+// Where the app keeps things, as shown in settings and passed to the backend
+#[derive(Clone, Debug, Serialize)]
+pub struct AppPaths {
+    pub data: String,
+    pub cache: String,
+    pub logs: String,
+    pub recordings: String,
+}
+
+fn ensure(dir: Option<PathBuf>, what: &str) -> Result<PathBuf, String> {
+    let dir = dir.ok_or_else(|| format!("Failed to resolve the {} directory", what))?;
+    fs::create_dir_all(&dir).map_err(|e| format!("Failed to create the {} directory {:?}: {}", what, dir, e))?;
+    Ok(dir)
+}
+
+// Settings, history and everything else that has to survive an update
+pub fn data_dir(app_handle: &tauri::AppHandle) -> Result<PathBuf, String> {
+    ensure(app_handle.path().app_data_dir().ok(), "app data")
+}
+
+// Downloads and indexes that can be rebuilt, which the OS may clear
+pub fn cache_dir(app_handle: &tauri::AppHandle) -> Result<PathBuf, String> {
+    ensure(app_handle.path().app_cache_dir().ok(), "cache")
+}
+
+pub fn logs_dir(app_handle: &tauri::AppHandle) -> Result<PathBuf, String> {
+    ensure(app_handle.path().app_log_dir().ok(), "log")
+}
+
+pub fn recordings_dir(app_handle: &tauri::AppHandle) -> Result<PathBuf, String> {
+    ensure(Some(data_dir(app_handle)?.join(RECORDINGS_DIR)), "recordings")
+}
+
+pub fn screenshots_dir(app_handle: &tauri::AppHandle) -> Result<PathBuf, String> {
+    ensure(Some(data_dir(app_handle)?.join(SCREENSHOTS_DIR)), "screenshots")
+}
+
+pub fn audit_dir(app_handle: &tauri::AppHandle) -> Result<PathBuf, String> {
+    ensure(Some(data_dir(app_handle)?.join(AUDIT_DIR)), "audit")
+}
+
+pub fn all(app_handle: &tauri::AppHandle) -> Result<AppPaths, String> {
+    let display = |dir: PathBuf| dir.display().to_string();
+    Ok(AppPaths {
+        data: display(data_dir(app_handle)?),
+        cache: display(cache_dir(app_handle)?),
+        logs: display(logs_dir(app_handle)?),
+        recordings: display(recordings_dir(app_handle)?),
+    })
+}
+
+// Environment telling the backend where to keep its own files, instead of next to its sources
+pub fn env_hints(app_handle: &tauri::AppHandle) -> Vec<(&'static str, String)> {
+    let mut hints = Vec::new();
+    for (name, dir) in [
+        ("KRYA_DATA_DIR", data_dir(app_handle)),
+        ("KRYA_CACHE_DIR", cache_dir(app_handle)),
+        ("KRYA_LOG_DIR", logs_dir(app_handle)),
+    ] {
+        match dir {
+            Ok(dir) => hints.push((name, dir.display().to_string())),
+            Err(e) => eprintln!("{}", e),
+        }
+    }
+    hints
+}
+
+fn open_folder(dir: &Path) -> Result<(), String> {
+    #[cfg(target_os = "macos")]
+    let result = Command::new("open").arg(dir).spawn();
+
+    #[cfg(target_os = "windows")]
+    let result = Command::new("explorer").arg(dir).spawn();
+
+    #[cfg(not(any(target_os = "macos", target_os = "windows")))]
+    let result = Command::new("xdg-open").arg(dir).spawn();
+
+    result
+        .map(|_| ())
+        .map_err(|e| format!("Failed to open {:?}: {}", dir, e))
+}
+
+#[tauri::command]
+pub fn get_app_paths(app_handle: tauri::AppHandle) -> Result<AppPaths, String> {
+    all(&app_handle)
+}
+
+// Command to show one of the app's folders in the file manager: data (the default), cache, logs,
+// recordings or screenshots
+#[tauri::command]
+pub fn open_data_dir(app_handle: tauri::AppHandle, kind: Option<String>) -> Result<String, String> {
+    let dir = match kind.as_deref().unwrap_or("data") {
+        "data" => data_dir(&app_handle)?,
+        "cache" => cache_dir(&app_handle)?,
+        "logs" => logs_dir(&app_handle)?,
+        "recordings" => recordings_dir(&app_handle)?,
+        "screenshots" => screenshots_dir(&app_handle)?,
+        other => return Err(format!("Unknown folder: {}", other)),
+    };
+    open_folder(&dir)?;
+    Ok(dir.display().to_string())
+}
@@ -0,0 +1,333 @@
+use tauri::{LogicalSize, Monitor, PhysicalPosition, PhysicalSize, Position, WebviewWindow};
+#[cfg(target_os = "linux")]
+use gtk::prelude::*;
+
+// Where on its monitor a window goes
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Anchor {
+    Center,
+    // Centered horizontally, a quarter of the way down (the spotlight's spot)
+    UpperCenter,
+}
+
+// Logical pixels between the mouse and a window placed next to it
+const CURSOR_GAP: f64 = 16.0;
+
+// A monitor area in physical pixels
+#[derive(Clone, Copy, Debug)]
+pub struct Rect {
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl Rect {
+    fn contains(&self, x: i32, y: i32) -> bool {
+        x >= self.x && x < self.x + self.width as i32 && y >= self.y && y < self.y + self.height as i32
+    }
+}
+
+fn bounds(monitor: &Monitor) -> Rect {
+    Rect {
+        x: monitor.position().x,
+        y: monitor.position().y,
+        width: monitor.size().width,
+        height: monitor.size().height,
+    }
+}
+
+// The part of a monitor not covered by the taskbar, dock or menu bar (and the notch below it)
+#[cfg(target_os = "windows")]
+fn work_area(monitor: &Monitor) -> Option<Rect> {
+    use windows_sys::Win32::Foundation::POINT;
+    use windows_sys::Win32::Graphics::Gdi::{GetMonitorInfoW, MonitorFromPoint, MONITORINFO, MONITOR_DEFAULTTONULL};
+
+    let position = monitor.position();
+    let size = monitor.size();
+    let center = POINT {
+        x: position.x + size.width as i32 / 2,
+        y: position.y + size.height as i32 / 2,
+    };
+    unsafe {
+        let handle = MonitorFromPoint(center, MONITOR_DEFAULTTONULL);
+        if handle == 0 {
+            return None;
+        }
+        let mut info: MONITORINFO = std::mem::zeroed();
+        info.cbSize = std::mem::size_of::<MONITORINFO>() as u32;
+        if GetMonitorInfoW(handle, &mut info) == 0 {
+            return None;
+        }
+        // Already in physical pixels, since the app is per-monitor DPI aware
+        let work = info.rcWork;
+        Some(Rect {
+            x: work.left,
+            y: work.top,
+            width: (work.right - work.left).max(0) as u32,
+            height: (work.bottom - work.top).max(0) as u32,
+        })
+    }
+}
+
+#[cfg(target_os = "macos")]
+#[repr(C)]
+struct NSEdgeInsets {
+    top: f64,
+    left: f64,
+    bottom: f64,
+    right: f64,
+}
+
+// Points at the top of a screen taken by the camera housing. The visible frame already leaves
+// them out below the menu bar, but not while the menu bar is hidden (e.g. set to auto-hide).
+#[cfg(target_os = "macos")]
+unsafe fn notch_height(screen: cocoa::base::id) -> f64 {
+    use cocoa::base::{BOOL, NO};
+    use objc::{msg_send, sel, sel_impl};
+
+    // Only macOS 12 and later know about notches
+    let supported: BOOL = msg_send![screen, respondsToSelector: sel!(safeAreaInsets)];
+    if supported == NO {
+        return 0.0;
+    }
+    let insets: NSEdgeInsets = msg_send![screen, safeAreaInsets];
+    insets.top
+}
+
+#[cfg(target_os = "macos")]
+fn work_area(monitor: &Monitor) -> Option<Rect> {
+    use cocoa::appkit::NSScreen;
+    use cocoa::base::{id, nil};
+    use cocoa::foundation::NSArray;
+
+    let scale = monitor.scale_factor();
+    let position = monitor.position();
+    let size = monitor.size();
+    unsafe {
+        let screens = NSScreen::screens(nil);
+        for index in 0..NSArray::count(screens) {
+            let screen: id = NSArray::objectAtIndex(screens, index);
+            let frame = NSScreen::frame(screen);
+            let visible = NSScreen::visibleFrame(screen);
+
+            // NSScreen works in points from the bottom left, so match on size and x, then apply the insets
+            let same_screen = (frame.size.width * scale - size.width as f64).abs() < 1.0
+                && (frame.size.height * scale - size.height as f64).abs() < 1.0
+                && (frame.origin.x * scale - position.x as f64).abs() < 1.0;
+            if !same_screen {
+                continue;
+            }
+            let left = visible.origin.x - frame.origin.x;
+            let bottom = visible.origin.y - frame.origin.y;
+            let top = (frame.size.height - bottom - visible.size.height).max(notch_height(screen));
+            return Some(Rect {
+                x: position.x + (left * scale).round() as i32,
+                y: position.y + (top * scale).round() as i32,
+                width: (visible.size.width * scale).round() as u32,
+                height: ((frame.size.height - top - bottom).max(0.0) * scale).round() as u32,
+            });
+        }
+    }
+    None
+}
+
+// GDK's view of a monitor, found by its center
+#[cfg(target_os = "linux")]
+pub fn gdk_monitor(monitor: &Monitor) -> Option<gtk::gdk::Monitor> {
+    // GDK may only be used from the main thread
+    if !gtk::is_initialized_main_thread() {
+        return None;
+    }
+
+    let scale = monitor.scale_factor();
+    let position = monitor.position();
+    let size = monitor.size();
+    // GDK works in logical pixels
+    let center_x = (position.x as f64 + size.width as f64 / 2.0) / scale;
+    let center_y = (position.y as f64 + size.height as f64 / 2.0) / scale;
+    gtk::gdk::Display::default()?.monitor_at_point(center_x as i32, center_y as i32)
+}
+
+#[cfg(target_os = "linux")]
+fn work_area(monitor: &Monitor) -> Option<Rect> {
+    let scale = monitor.scale_factor();
+    let area = gdk_monitor(monitor)?.workarea();
+    Some(Rect {
+        x: (area.x() as f64 * scale).round() as i32,
+        y: (area.y() as f64 * scale).round() as i32,
+        width: (area.width() as f64 * scale).round() as u32,
+        height: (area.height() as f64 * scale).round() as u32,
+    })
+}
+
+#[cfg(not(any(target_os = "windows", target_os = "macos", target_os = "linux")))]
+fn work_area(_monitor: &Monitor) -> Option<Rect> {
+    None
+}
+
+// Where the mouse is. Windows reports physical pixels; the others report logical ones
+// that are converted with the scale of the monitor they fall on.
+#[cfg(target_os = "windows")]
+fn cursor_position(_window: &WebviewWindow) -> Option<PhysicalPosition<i32>> {
+    use windows_sys::Win32::Foundation::POINT;
+    use windows_sys::Win32::UI::WindowsAndMessaging::GetCursorPos;
+
+    let mut point = POINT { x: 0, y: 0 };
+    if unsafe { GetCursorPos(&mut point) } == 0 {
+        return None;
+    }
+    Some(PhysicalPosition { x: point.x, y: point.y })
+}
+
+#[cfg(target_os = "macos")]
+fn cursor_position(window: &WebviewWindow) -> Option<PhysicalPosition<i32>> {
+    use cocoa::appkit::{NSEvent, NSScreen};
+    use cocoa::base::{id, nil};
+    use cocoa::foundation::NSArray;
+
+    unsafe {
+        // Points from the bottom left of the primary screen, which is always first
+        let screens = NSScreen::screens(nil);
+        if NSArray::count(screens) == 0 {
+            return None;
+        }
+        let primary: id = NSArray::objectAtIndex(screens, 0);
+        let point = NSEvent::mouseLocation(nil);
+        logical_to_physical(window, point.x, NSScreen::frame(primary).size.height - point.y)
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn cursor_position(window: &WebviewWindow) -> Option<PhysicalPosition<i32>> {
+    // GDK may only be used from the main thread, and native Wayland never says where the pointer is
+    if !gtk::is_initialized_main_thread() {
+        return None;
+    }
+    let pointer = gtk::gdk::Display::default()?.default_seat()?.pointer()?;
+    let (_, x, y) = pointer.position_double();
+    logical_to_physical(window, x, y)
+}
+
+#[cfg(not(any(target_os = "windows", target_os = "macos", target_os = "linux")))]
+fn cursor_position(_window: &WebviewWindow) -> Option<PhysicalPosition<i32>> {
+    None
+}
+
+#[cfg(any(target_os = "macos", target_os = "linux"))]
+fn logical_to_physical(window: &WebviewWindow, x: f64, y: f64) -> Option<PhysicalPosition<i32>> {
+    window.available_monitors().ok()?.into_iter().find_map(|monitor| {
+        let scale = monitor.scale_factor();
+        let area = bounds(&monitor);
+        let (x, y) = ((x * scale).round() as i32, (y * scale).round() as i32);
+        area.contains(x, y).then_some(PhysicalPosition { x, y })
+    })
+}
+
+// Usable area of a monitor, or all of it when the platform doesn't say
+pub fn usable_area(monitor: &Monitor) -> Rect {
+    match work_area(monitor) {
+        Some(area) if area.width > 0 && area.height > 0 => area,
+        _ => bounds(monitor),
+    }
+}
+
+// The window's monitor, falling back to the primary one (e.g. while displays reconnect)
+pub fn monitor_for(window: &WebviewWindow) -> tauri::Result<Option<Monitor>> {
+    match window.current_monitor()? {
+        Some(monitor) => Ok(Some(monitor)),
+        None => window.primary_monitor(),
+    }
+}
+
+// The monitor showing a point, e.g. where the user clicked
+pub fn monitor_at(window: &WebviewWindow, x: i32, y: i32) -> tauri::Result<Option<Monitor>> {
+    Ok(window
+        .available_monitors()?
+        .into_iter()
+        .find(|monitor| bounds(monitor).contains(x, y)))
+}
+
+// The window's size once it's on a monitor, capped to the area it has to fit in
+fn size_on(window: &WebviewWindow, monitor: &Monitor, area: &Rect) -> tauri::Result<(i32, i32)> {
+    // Window sizes are physical at the window's current scale, which differs from the target's on mixed-DPI setups
+    let size: LogicalSize<f64> = window.outer_size()?.to_logical(window.scale_factor()?);
+    let size: PhysicalSize<u32> = size.to_physical(monitor.scale_factor());
+    Ok((size.width.min(area.width) as i32, size.height.min(area.height) as i32))
+}
+
+// Move a window to its anchor within a monitor's usable area, keeping it fully on screen
+pub fn place_on(window: &WebviewWindow, monitor: &Monitor, anchor: Anchor) -> tauri::Result<()> {
+    let area = usable_area(monitor);
+    #[cfg(target_os = "linux")]
+    {
+        if let Some(result) = crate::wayland::place_layer(window, monitor, area, anchor) {
+            return result;
+        }
+    }
+
+    let (width, height) = size_on(window, monitor, &area)?;
+
+    let x = area.x + (area.width as i32 - width) / 2;
+    let y = match anchor {
+        Anchor::Center => area.y + (area.height as i32 - height) / 2,
+        Anchor::UpperCenter => area.y + area.height as i32 / 4 - height / 2,
+    };
+    let y = y.clamp(area.y, area.y + area.height as i32 - height);
+
+    window.set_position(Position::Physical(PhysicalPosition { x, y }))
+}
+
+// Place a window on its own monitor, or just center it when no monitor is reported
+pub fn place(window: &WebviewWindow, anchor: Anchor) -> tauri::Result<()> {
+    match monitor_for(window)? {
+        Some(monitor) => place_on(window, &monitor, anchor),
+        None => window.center(),
+    }
+}
+
+// Put a window just below a rect (e.g. a menu-bar icon), centered on it and kept on screen
+#[cfg_attr(not(target_os = "macos"), allow(dead_code))]
+pub fn place_below(window: &WebviewWindow, target: Rect) -> tauri::Result<()> {
+    let center_x = target.x + target.width as i32 / 2;
+    let monitor = match monitor_at(window, center_x, target.y)? {
+        Some(monitor) => monitor,
+        None => match monitor_for(window)? {
+            Some(monitor) => monitor,
+            None => return window.center(),
+        },
+    };
+    let area = usable_area(&monitor);
+    let (width, height) = size_on(window, &monitor, &area)?;
+
+    let x = (center_x - width / 2).clamp(area.x, area.x + area.width as i32 - width);
+    let y = (target.y + target.height as i32).clamp(area.y, area.y + area.height as i32 - height);
+    window.set_position(Position::Physical(PhysicalPosition { x, y }))
+}
+
+// Put a window right under the mouse, or above it when there's no room below.
+// Falls back to the upper center when the cursor's position isn't known.
+pub fn place_near_cursor(window: &WebviewWindow) -> tauri::Result<()> {
+    let cursor = match cursor_position(window) {
+        Some(cursor) => cursor,
+        None => return place(window, Anchor::UpperCenter),
+    };
+    let monitor = match monitor_at(window, cursor.x, cursor.y)? {
+        Some(monitor) => monitor,
+        None => return place(window, Anchor::UpperCenter),
+    };
+    let area = usable_area(&monitor);
+    let (width, height) = size_on(window, &monitor, &area)?;
+    // Far enough from the pointer that it doesn't cover the input
+    let gap = (CURSOR_GAP * monitor.scale_factor()).round() as i32;
+
+    let x = (cursor.x - width / 2).clamp(area.x, area.x + area.width as i32 - width);
+    let below = cursor.y + gap;
+    let y = if below + height <= area.y + area.height as i32 {
+        below
+    } else {
+        cursor.y - gap - height
+    };
+    let y = y.clamp(area.y, area.y + area.height as i32 - height);
+    window.set_position(Position::Physical(PhysicalPosition { x, y }))
+}
@@ -0,0 +1,328 @@
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::PathBuf;
+
+use crate::approvals::ActionCategory;
+use crate::scopes::Scope;
+use crate::settings::{AllowedCommand, Settings};
+
+// Overrides where the policy is read from, for testing a deployment without installing it. Only
+// honoured in debug builds, so it can't be used to swap out a deployed policy.
+#[cfg(debug_assertions)]
+const POLICY_FILE_ENV: &str = "KRYA_POLICY_FILE";
+
+// Guardrails an administrator deploys machine-wide. They win over the user's settings, which can't
+// change what they lock.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Policy {
+    // A shared backend to use instead of starting one on this machine
+    pub backend_url: Option<String>,
+    pub disable_telemetry: bool,
+    pub disable_crash_reporting: bool,
+    // The only scopes that are granted, exactly these, when set
+    pub granted_scopes: Option<Vec<Scope>>,
+    // Actions that always need confirming, whatever the user chose
+    pub always_confirm: Vec<ActionCategory>,
+    // The only programs exec may run, exactly these, when set
+    pub exec_allowed: Option<Vec<AllowedCommand>>,
+    // The only folders files may be read or written in, exactly these, when set
+    pub file_roots: Option<Vec<String>>,
+}
+
+impl Policy {
+    // In force when a policy is deployed but can't be read: nothing granted, run or reachable, and
+    // every action confirmed, until an administrator fixes it
+    fn lockdown() -> Self {
+        Policy {
+            backend_url: None,
+            disable_telemetry: true,
+            disable_crash_reporting: true,
+            granted_scopes: Some(Vec::new()),
+            always_confirm: ActionCategory::ALL.to_vec(),
+            exec_allowed: Some(Vec::new()),
+            file_roots: Some(Vec::new()),
+        }
+    }
+}
+
+// What the settings pane shows so it can grey out locked controls
+#[derive(Clone, Debug, Serialize)]
+pub struct PolicyStatus {
+    pub path: Option<String>,
+    pub policy: Policy,
+    pub locked: Vec<&'static str>,
+    // Why the policy couldn't be read, when everything is locked down because of it
+    pub error: Option<String>,
+}
+
+struct Loaded {
+    path: Option<PathBuf>,
+    policy: Policy,
+    error: Option<String>,
+}
+
+static POLICY: Lazy<Loaded> = Lazy::new(|| load(path()));
+
+fn path() -> Option<PathBuf> {
+    #[cfg(debug_assertions)]
+    if let Some(path) = std::env::var_os(POLICY_FILE_ENV) {
+        return Some(PathBuf::from(path));
+    }
+    if cfg!(target_os = "windows") {
+        std::env::var_os("ProgramData").map(|dir| PathBuf::from(dir).join("Krya").join("policy.json"))
+    } else if cfg!(target_os = "macos") {
+        Some(PathBuf::from("/Library/Application Support/Krya/policy.json"))
+    } else {
+        Some(PathBuf::from("/etc/krya/policy.json"))
+    }
+}
+
+// Read once; a policy only changes when it's redeployed, and that comes with a restart. One that's
+// there but unreadable or invalid locks everything down rather than being ignored.
+fn load(path: Option<PathBuf>) -> Loaded {
+    // Not exists(), so a dangling symlink counts as a policy that can't be read
+    let path = match path.filter(|path| fs::symlink_metadata(path).is_ok()) {
+        Some(path) => path,
+        None => {
+            return Loaded {
+                path: None,
+                policy: Policy::default(),
+                error: None,
+            }
+        }
+    };
+    let policy = fs::read_to_string(&path)
+        .map_err(|e| format!("Failed to read policy {:?}: {}", path, e))
+        .and_then(|contents| {
+            serde_json::from_str::<Policy>(&contents).map_err(|e| format!("Invalid policy {:?}: {}", path, e))
+        });
+    match policy {
+        Ok(mut policy) => {
+            println!("Enforcing the policy in {:?}", path);
+            policy.backend_url = policy
+                .backend_url
+                .map(|url| url.trim().trim_end_matches('/').to_string())
+                .filter(|url| !url.is_empty());
+            Loaded {
+                path: Some(path),
+                policy,
+                error: None,
+            }
+        }
+        Err(e) => {
+            eprintln!("{}; locking everything down", e);
+            Loaded {
+                path: Some(path),
+                policy: Policy::lockdown(),
+                error: Some(e),
+            }
+        }
+    }
+}
+
+pub fn get() -> &'static Policy {
+    &POLICY.policy
+}
+
+// Settings paths the policy decides, in the order they appear in the settings file
+pub fn locked_settings() -> Vec<&'static str> {
+    locked_by(get())
+}
+
+fn locked_by(policy: &Policy) -> Vec<&'static str> {
+    let mut locked = Vec::new();
+    if policy.disable_crash_reporting {
+        locked.push("crash_reporting.enabled");
+    }
+    if policy.disable_telemetry {
+        locked.push("telemetry.enabled");
+    }
+    if policy.exec_allowed.is_some() {
+        locked.push("exec.allowed");
+    }
+    if policy.granted_scopes.is_some() {
+        locked.push("permissions.granted");
+    }
+    if policy.file_roots.is_some() {
+        locked.push("permissions.file_roots");
+    }
+    if !policy.always_confirm.is_empty() {
+        locked.push("permissions.confirm");
+    }
+    if policy.backend_url.is_some() {
+        locked.push("backend");
+    }
+    locked
+}
+
+// Bring settings in line with the policy. Applied to the settings in force whenever they're read or
+// replaced, so a hand edit or an imported bundle can't get around it; never to what's saved.
+pub fn enforce(settings: &mut Settings) {
+    enforce_with(get(), settings)
+}
+
+fn enforce_with(policy: &Policy, settings: &mut Settings) {
+    if policy.disable_crash_reporting {
+        settings.crash_reporting.enabled = false;
+    }
+    if policy.disable_telemetry {
+        settings.telemetry.enabled = false;
+    }
+    if let Some(commands) = &policy.exec_allowed {
+        settings.exec.allowed = commands.clone();
+    }
+    if let Some(scopes) = &policy.granted_scopes {
+        settings.permissions.granted = scopes.clone();
+    }
+    if let Some(roots) = &policy.file_roots {
+        settings.permissions.file_roots = roots.clone();
+    }
+    for category in &policy.always_confirm {
+        if !settings.permissions.confirm.contains(category) {
+            settings.permissions.confirm.push(*category);
+        }
+    }
+}
+
+// Undo `enforce` on settings edited from the ones in force: what the policy decides is taken from
+// the user's `saved` settings, so saving them doesn't write the policy's values over the user's
+pub fn restore_user_values(settings: &mut Settings, saved: &Settings) {
+    restore_with(get(), settings, saved)
+}
+
+fn restore_with(policy: &Policy, settings: &mut Settings, saved: &Settings) {
+    if policy.disable_crash_reporting {
+        settings.crash_reporting.enabled = saved.crash_reporting.enabled;
+    }
+    if policy.disable_telemetry {
+        settings.telemetry.enabled = saved.telemetry.enabled;
+    }
+    if policy.exec_allowed.is_some() {
+        settings.exec.allowed = saved.exec.allowed.clone();
+    }
+    if policy.granted_scopes.is_some() {
+        settings.permissions.granted = saved.permissions.granted.clone();
+    }
+    if policy.file_roots.is_some() {
+        settings.permissions.file_roots = saved.permissions.file_roots.clone();
+    }
+    // The user may still add or drop the categories the policy doesn't insist on
+    settings
+        .permissions
+        .confirm
+        .retain(|category| !policy.always_confirm.contains(category) || saved.permissions.confirm.contains(category));
+}
+
+// For commands that change one setting, so the user hears why it didn't stick
+pub fn check_unlocked(setting: &str) -> Result<(), String> {
+    check_unlocked_by(get(), setting)
+}
+
+fn check_unlocked_by(policy: &Policy, setting: &str) -> Result<(), String> {
+    if locked_by(policy).contains(&setting) {
+        Err(format!("{} is set by your organization's policy", setting))
+    } else {
+        Ok(())
+    }
+}
+
+#[tauri::command]
+pub fn get_policy() -> PolicyStatus {
+    PolicyStatus {
+        path: POLICY.path.as_ref().map(|path| path.display().to_string()),
+        policy: get().clone(),
+        locked: locked_settings(),
+        error: POLICY.error.clone(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_file(name: &str, contents: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("krya-policy-{}-{}", std::process::id(), name));
+        fs::write(&path, contents).unwrap();
+        path
+    }
+
+    fn allowed(command: &str) -> AllowedCommand {
+        AllowedCommand {
+            command: command.to_string(),
+            timeout_secs: None,
+        }
+    }
+
+    #[test]
+    fn no_policy_locks_nothing() {
+        let loaded = load(None);
+        assert!(loaded.error.is_none());
+        assert!(locked_by(&loaded.policy).is_empty());
+    }
+
+    #[test]
+    fn invalid_policy_locks_everything_down() {
+        let path = temp_file("invalid.json", "{ not json");
+        let loaded = load(Some(path.clone()));
+        fs::remove_file(&path).unwrap();
+        assert!(loaded.error.unwrap().starts_with("Invalid policy"));
+        assert_eq!(loaded.policy.granted_scopes, Some(Vec::new()));
+        assert_eq!(loaded.policy.always_confirm, ActionCategory::ALL);
+        assert!(loaded.policy.exec_allowed.unwrap().is_empty());
+        assert!(loaded.policy.disable_telemetry);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn unreadable_policy_locks_everything_down() {
+        let path = std::env::temp_dir().join(format!("krya-policy-{}-dangling.json", std::process::id()));
+        let _ = fs::remove_file(&path);
+        std::os::unix::fs::symlink("/nonexistent/krya/policy.json", &path).unwrap();
+        let loaded = load(Some(path.clone()));
+        fs::remove_file(&path).unwrap();
+        assert!(loaded.error.unwrap().starts_with("Failed to read policy"));
+        assert_eq!(loaded.policy.file_roots, Some(Vec::new()));
+    }
+
+    #[test]
+    fn check_unlocked_refuses_only_what_the_policy_decides() {
+        let policy = Policy {
+            disable_telemetry: true,
+            exec_allowed: Some(Vec::new()),
+            ..Policy::default()
+        };
+        assert!(check_unlocked_by(&policy, "telemetry.enabled").is_err());
+        assert!(check_unlocked_by(&policy, "exec.allowed").is_err());
+        assert!(check_unlocked_by(&policy, "permissions.granted").is_ok());
+        assert!(check_unlocked_by(&Policy::default(), "telemetry.enabled").is_ok());
+        assert_eq!(locked_by(&Policy::lockdown()).len(), 6);
+    }
+
+    #[test]
+    fn saved_settings_keep_what_the_policy_overrides() {
+        let policy = Policy {
+            exec_allowed: Some(vec![allowed("git")]),
+            always_confirm: vec![ActionCategory::Purchases],
+            ..Policy::default()
+        };
+        let mut saved = Settings::default();
+        saved.exec.allowed = vec![allowed("npm")];
+        saved.permissions.confirm = vec![ActionCategory::FileDeletion];
+
+        let mut in_force = saved.clone();
+        enforce_with(&policy, &mut in_force);
+        assert_eq!(in_force.exec.allowed[0].command, "git");
+        assert!(in_force.permissions.confirm.contains(&ActionCategory::Purchases));
+
+        // An edit to something else made from the settings in force
+        in_force.permissions.confirm.push(ActionCategory::SendingEmail);
+        restore_with(&policy, &mut in_force, &saved);
+        assert_eq!(in_force.exec.allowed[0].command, "npm");
+        assert_eq!(
+            in_force.permissions.confirm,
+            [ActionCategory::FileDeletion, ActionCategory::SendingEmail]
+        );
+    }
+}
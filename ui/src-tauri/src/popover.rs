@@ -0,0 +1,56 @@
+use tauri::Manager;
+
+use crate::errors::ReportExt;
+
+// Small prompt box under the menu-bar icon, for quick prompts without the full spotlight
+pub const POPOVER_WINDOW: &str = "popover";
+
+#[cfg(target_os = "macos")]
+const WIDTH: f64 = 360.0;
+#[cfg(target_os = "macos")]
+const HEIGHT: f64 = 140.0;
+
+// Show the popover under the menu-bar icon the user clicked, or hide it if it's already showing
+#[cfg(target_os = "macos")]
+pub fn toggle(app_handle: &tauri::AppHandle, icon: crate::placement::Rect) {
+    use tauri::{WebviewUrl, WebviewWindowBuilder};
+
+    let window = match app_handle.get_webview_window(POPOVER_WINDOW) {
+        Some(window) => window,
+        None => match WebviewWindowBuilder::new(app_handle, POPOVER_WINDOW, WebviewUrl::App("index.html".into()))
+            .title("Krya.ai")
+            .inner_size(WIDTH, HEIGHT)
+            .resizable(false)
+            .decorations(false)
+            .transparent(true)
+            .always_on_top(true)
+            .skip_taskbar(true)
+            .visible(false)
+            .build()
+        {
+            Ok(window) => window,
+            Err(e) => return crate::errors::report(app_handle, "Failed to open the quick prompt", e),
+        },
+    };
+
+    if window.is_visible().unwrap_or(false) {
+        window.hide().or_report(app_handle, "Failed to hide the quick prompt");
+        return;
+    }
+    crate::placement::place_below(&window, icon).or_report(app_handle, "Failed to position the quick prompt");
+    window.show().or_report(app_handle, "Failed to show the quick prompt");
+    window.set_focus().or_report(app_handle, "Failed to focus the quick prompt");
+}
+
+// Like a native popover, it goes away as soon as the user clicks elsewhere
+pub fn hide(app_handle: &tauri::AppHandle) {
+    if let Some(window) = app_handle.get_webview_window(POPOVER_WINDOW) {
+        window.hide().or_report(app_handle, "Failed to hide the quick prompt");
+    }
+}
+
+// Command for the popover to dismiss itself, e.g. after submitting its prompt
+#[tauri::command]
+pub fn hide_popover(app_handle: tauri::AppHandle) {
+    hide(&app_handle);
+}
@@ -0,0 +1,105 @@
+use serde::Serialize;
+use std::sync::atomic::{AtomicBool, Ordering};
+use tauri::{Emitter, Manager};
+
+use crate::{recorder, settings, tray};
+
+// Privacy mode: while it's on, history, clipboard watching, telemetry and recordings are suspended.
+// What gets written down is decided by the gate in `storage`; this only holds the state.
+// The global toggle is a setting; incognito covers just the current session and is never written down.
+#[derive(Default)]
+pub struct PrivacyState {
+    incognito: AtomicBool,
+    // What the tray and windows were last told, so they only hear about changes
+    shown: AtomicBool,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct PrivacyStatus {
+    pub active: bool,
+    pub enabled: bool,
+    pub incognito: bool,
+}
+
+pub fn status(app_handle: &tauri::AppHandle) -> PrivacyStatus {
+    let enabled = settings::current(app_handle).privacy.enabled;
+    let incognito = app_handle.state::<PrivacyState>().incognito.load(Ordering::SeqCst);
+    PrivacyStatus {
+        active: enabled || incognito,
+        enabled,
+        incognito,
+    }
+}
+
+// Whether nothing about what the user does may be kept right now. Logging starts before
+// settings are loaded, and nothing about the user has happened by then.
+pub fn is_active(app_handle: &tauri::AppHandle) -> bool {
+    app_handle.try_state::<settings::SettingsState>().is_some() && status(app_handle).active
+}
+
+// Show the current state in the tray and windows, and stop recording when it turns on
+pub fn apply(app_handle: &tauri::AppHandle) {
+    let status = status(app_handle);
+    if app_handle.state::<PrivacyState>().shown.swap(status.active, Ordering::SeqCst) == status.active {
+        return;
+    }
+    println!("Privacy mode {}", if status.active { "on" } else { "off" });
+
+    if status.active {
+        recorder::stop();
+    }
+    tray::show_privacy(app_handle, status.active);
+    tray::refresh(app_handle);
+    if let Err(e) = app_handle.emit("privacy-mode-changed", status) {
+        eprintln!("Failed to emit privacy-mode-changed: {}", e);
+    }
+}
+
+pub fn set_enabled(app_handle: &tauri::AppHandle, enabled: bool) -> Result<(), String> {
+    let mut current = settings::current(app_handle);
+    if current.privacy.enabled == enabled {
+        return Ok(());
+    }
+    current.privacy.enabled = enabled;
+    settings::replace(app_handle, current)
+}
+
+fn set_incognito(app_handle: &tauri::AppHandle, incognito: bool) {
+    app_handle.state::<PrivacyState>().incognito.store(incognito, Ordering::SeqCst);
+    apply(app_handle);
+}
+
+// Incognito ends with its session, when another one is started or switched to
+pub fn end_incognito(app_handle: &tauri::AppHandle) {
+    if app_handle.state::<PrivacyState>().incognito.load(Ordering::SeqCst) {
+        set_incognito(app_handle, false);
+    }
+}
+
+// From the tray: turning it off also ends an incognito session
+pub fn toggle(app_handle: &tauri::AppHandle) -> Result<(), String> {
+    if is_active(app_handle) {
+        end_incognito(app_handle);
+        set_enabled(app_handle, false)
+    } else {
+        set_enabled(app_handle, true)
+    }
+}
+
+#[tauri::command]
+pub fn get_privacy_mode(app_handle: tauri::AppHandle) -> PrivacyStatus {
+    status(&app_handle)
+}
+
+#[tauri::command]
+pub fn set_privacy_mode(app_handle: tauri::AppHandle, enabled: bool) -> Result<PrivacyStatus, String> {
+    set_enabled(&app_handle, enabled)?;
+    Ok(status(&app_handle))
+}
+
+// Command to make the current session incognito, or end that early
+#[tauri::command]
+pub fn set_incognito_session(app_handle: tauri::AppHandle, incognito: bool) -> PrivacyStatus {
+    set_incognito(&app_handle, incognito);
+    status(&app_handle)
+}
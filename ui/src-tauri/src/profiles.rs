@@ -0,0 +1,338 @@
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use tauri::{Emitter, Manager};
+
+use crate::util::now_secs;
+use crate::{crash, handshake, history, metrics, sessions, settings, storage, AppState};
+
+// Which profile is active and which exist, shared by all of them
+const PROFILES_FILE: &str = "profiles.json";
+
+// Other profiles keep their own files in a folder under here
+const PROFILES_DIR: &str = "profiles";
+
+// The profile whose files are the data directory's own, as before profiles existed
+pub const DEFAULT_PROFILE: &str = "default";
+
+// The backend's config.json and .env (the API key), within a profile's folder
+const BACKEND_DIR: &str = "backend";
+
+// A set of settings (shortcuts included), history and backend config (API key included) to switch between
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Profile {
+    pub id: String,
+    pub name: String,
+    pub created: u64,
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+struct ProfilesConfig {
+    // None for the default profile
+    active: Option<String>,
+    profiles: Vec<Profile>,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct ProfileList {
+    pub active: String,
+    pub profiles: Vec<Profile>,
+}
+
+pub struct ProfileState(Mutex<ProfilesConfig>);
+
+impl ProfileState {
+    pub fn load(app_handle: &tauri::AppHandle) -> Self {
+        let mut config: ProfilesConfig = storage::load_json(app_handle, PROFILES_FILE);
+        // A profile deleted behind our back falls back to the default one
+        if let Some(active) = &config.active {
+            if !config.profiles.iter().any(|profile| &profile.id == active) {
+                eprintln!("Active profile {} no longer exists, using the default profile", active);
+                config.active = None;
+            }
+        }
+        ProfileState(Mutex::new(config))
+    }
+}
+
+fn active_id(app_handle: &tauri::AppHandle) -> Option<String> {
+    app_handle
+        .try_state::<ProfileState>()
+        .and_then(|state| state.0.lock().unwrap().active.clone())
+}
+
+fn profile_dir(app_handle: &tauri::AppHandle, id: &str) -> Result<PathBuf, String> {
+    let dir = storage::data_dir(app_handle)?.join(PROFILES_DIR).join(id);
+    fs::create_dir_all(&dir).map_err(|e| format!("Failed to create profile directory: {}", e))?;
+    Ok(dir)
+}
+
+// Where the active profile keeps a file, relative to the data directory
+pub fn file(app_handle: &tauri::AppHandle, name: &str) -> String {
+    match active_id(app_handle) {
+        None => name.to_string(),
+        Some(id) => {
+            if let Err(e) = profile_dir(app_handle, &id) {
+                eprintln!("{}", e);
+            }
+            format!("{}/{}/{}", PROFILES_DIR, id, name)
+        }
+    }
+}
+
+// The active profile's backend config, which the default profile keeps in the data directory too,
+// never next to the backend's sources where every account on the machine would share it
+fn backend_dir(app_handle: &tauri::AppHandle) -> Result<PathBuf, String> {
+    let dir = match active_id(app_handle) {
+        Some(id) => profile_dir(app_handle, &id)?.join(BACKEND_DIR),
+        None => storage::data_dir(app_handle)?.join(BACKEND_DIR),
+    };
+    fs::create_dir_all(&dir).map_err(|e| format!("Failed to create {:?}: {}", dir, e))?;
+    Ok(dir)
+}
+
+// Environment pointing the backend at the active profile's config and API key
+pub fn env_hints(app_handle: &tauri::AppHandle) -> Vec<(&'static str, String)> {
+    match backend_dir(app_handle) {
+        Ok(dir) => vec![("KRYA_CONFIG_DIR", dir.to_string_lossy().to_string())],
+        Err(e) => {
+            eprintln!("{}", e);
+            Vec::new()
+        }
+    }
+}
+
+// Whether a file was written by this OS account, rather than another one sharing the install
+fn is_own(path: &Path, data_dir: &Path) -> bool {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::MetadataExt;
+        matches!((path.metadata(), data_dir.metadata()), (Ok(file), Ok(dir)) if file.uid() == dir.uid())
+    }
+    #[cfg(windows)]
+    {
+        same_owner_sid(path, data_dir)
+    }
+}
+
+// The Windows counterpart of comparing uids: whether both files have the same owner SID
+#[cfg(windows)]
+fn same_owner_sid(a: &Path, b: &Path) -> bool {
+    use std::os::windows::ffi::OsStrExt;
+    use std::ptr::null_mut;
+    use windows_sys::Win32::Foundation::{LocalFree, ERROR_SUCCESS, PSID};
+    use windows_sys::Win32::Security::Authorization::{GetNamedSecurityInfoW, SE_FILE_OBJECT};
+    use windows_sys::Win32::Security::{EqualSid, OWNER_SECURITY_INFORMATION, PSECURITY_DESCRIPTOR};
+
+    // The owner SID points into the security descriptor, which is freed once they're compared
+    let owner = |path: &Path| -> Option<(PSID, PSECURITY_DESCRIPTOR)> {
+        let wide: Vec<u16> = path.as_os_str().encode_wide().chain(Some(0)).collect();
+        let mut sid: PSID = null_mut();
+        let mut descriptor: PSECURITY_DESCRIPTOR = null_mut();
+        let status = unsafe {
+            GetNamedSecurityInfoW(
+                wide.as_ptr(),
+                SE_FILE_OBJECT,
+                OWNER_SECURITY_INFORMATION,
+                &mut sid,
+                null_mut(),
+                null_mut(),
+                null_mut(),
+                &mut descriptor,
+            )
+        };
+        (status == ERROR_SUCCESS).then_some((sid, descriptor))
+    };
+
+    let (first, second) = (owner(a), owner(b));
+    let same = match (first, second) {
+        (Some((a, _)), Some((b, _))) => unsafe { EqualSid(a, b) != 0 },
+        _ => false,
+    };
+    for (_, descriptor) in first.into_iter().chain(second) {
+        unsafe { LocalFree(descriptor) };
+    }
+    same
+}
+
+// Before the default profile had its own backend folder, the backend kept its config and API key in
+// its working directory. Take them over once, but only from a copy this account wrote, and don't
+// leave the API key behind where other accounts can read it.
+pub fn adopt_legacy_config(app_handle: &tauri::AppHandle, working_dir: &Path) {
+    let data_dir = match storage::data_dir(app_handle) {
+        Ok(dir) => dir,
+        Err(e) => return eprintln!("{}", e),
+    };
+    let target_dir = data_dir.join(BACKEND_DIR);
+    for (legacy, name) in [
+        (working_dir.join("config").join("config.json"), "config.json"),
+        (working_dir.join(".env"), ".env"),
+    ] {
+        let target = target_dir.join(name);
+        if !legacy.exists() || !is_own(&legacy, &data_dir) {
+            continue;
+        }
+        if target.exists() {
+            // Earlier versions copied it without removing the original
+            if !matches!((fs::read(&legacy), fs::read(&target)), (Ok(old), Ok(new)) if old == new) {
+                continue;
+            }
+        } else if let Err(e) = fs::create_dir_all(&target_dir).and_then(|_| fs::copy(&legacy, &target)) {
+            eprintln!("Failed to copy {:?} to {:?}: {}", legacy, target, e);
+            continue;
+        }
+        // A file that can't be removed is at least emptied
+        match fs::remove_file(&legacy).or_else(|_| fs::write(&legacy, "")) {
+            Ok(_) => println!("Moved the backend's {} into the default profile", name),
+            Err(e) => eprintln!("Copied the backend's {} but failed to remove {:?}: {}", name, legacy, e),
+        }
+    }
+}
+
+// The profiles to offer in the tray
+pub fn tray_profiles(app_handle: &tauri::AppHandle) -> ProfileList {
+    list(&app_handle.state::<ProfileState>().0.lock().unwrap())
+}
+
+fn list(config: &ProfilesConfig) -> ProfileList {
+    let default = Profile {
+        id: DEFAULT_PROFILE.to_string(),
+        name: "Default".to_string(),
+        created: 0,
+    };
+    ProfileList {
+        active: config.active.clone().unwrap_or_else(|| DEFAULT_PROFILE.to_string()),
+        profiles: std::iter::once(default).chain(config.profiles.iter().cloned()).collect(),
+    }
+}
+
+fn save(app_handle: &tauri::AppHandle, config: &ProfilesConfig) -> Result<(), String> {
+    storage::save_json(app_handle, PROFILES_FILE, config, storage::Data::Config)
+}
+
+// Start the backend again, e.g. so it reads the active profile's config
+pub fn restart_backend(app_handle: &tauri::AppHandle) -> Result<(), String> {
+    let app_state = app_handle.state::<AppState>();
+    metrics::record_backend_restart();
+    crate::stop_api_server(&app_state);
+    crate::start_api_server(app_handle, &app_state)?;
+    crash::watch_backend(app_handle, app_state.inner().clone());
+    handshake::negotiate(app_handle, app_state.inner().clone());
+    Ok(())
+}
+
+#[tauri::command]
+pub fn list_profiles(state: tauri::State<ProfileState>) -> ProfileList {
+    list(&state.0.lock().unwrap())
+}
+
+#[tauri::command]
+pub fn create_profile(
+    app_handle: tauri::AppHandle,
+    state: tauri::State<ProfileState>,
+    name: String,
+) -> Result<Profile, String> {
+    let name = name.trim();
+    if name.is_empty() {
+        return Err("Profile name cannot be empty".to_string());
+    }
+    let profile = Profile {
+        id: uuid::Uuid::new_v4().simple().to_string(),
+        name: name.to_string(),
+        created: now_secs(),
+    };
+    let mut config = state.0.lock().unwrap();
+    config.profiles.push(profile.clone());
+    save(&app_handle, &config)?;
+    drop(config);
+    crate::tray::refresh(&app_handle);
+    Ok(profile)
+}
+
+#[tauri::command]
+pub fn rename_profile(
+    app_handle: tauri::AppHandle,
+    state: tauri::State<ProfileState>,
+    id: String,
+    name: String,
+) -> Result<Profile, String> {
+    let name = name.trim();
+    if name.is_empty() {
+        return Err("Profile name cannot be empty".to_string());
+    }
+    let mut config = state.0.lock().unwrap();
+    let profile = config
+        .profiles
+        .iter_mut()
+        .find(|profile| profile.id == id)
+        .ok_or_else(|| format!("Profile not found: {}", id))?;
+    profile.name = name.to_string();
+    let profile = profile.clone();
+    save(&app_handle, &config)?;
+    drop(config);
+    crate::tray::refresh(&app_handle);
+    Ok(profile)
+}
+
+// Command to delete a profile and everything it kept; the active and default profiles can't be deleted
+#[tauri::command]
+pub fn delete_profile(
+    app_handle: tauri::AppHandle,
+    state: tauri::State<ProfileState>,
+    id: String,
+) -> Result<(), String> {
+    let mut config = state.0.lock().unwrap();
+    if config.active.as_deref() == Some(id.as_str()) {
+        return Err("Switch to another profile before deleting this one".to_string());
+    }
+    let before = config.profiles.len();
+    config.profiles.retain(|profile| profile.id != id);
+    if config.profiles.len() == before {
+        return Err(format!("Profile not found: {}", id));
+    }
+    save(&app_handle, &config)?;
+    drop(config);
+    crate::tray::refresh(&app_handle);
+
+    let dir = storage::data_dir(&app_handle)?.join(PROFILES_DIR).join(&id);
+    if dir.exists() {
+        fs::remove_dir_all(&dir).map_err(|e| format!("Failed to delete profile files: {}", e))?;
+    }
+    Ok(())
+}
+
+// Command to switch profiles: settings and shortcuts, history and the backend all restart on the new one's files
+#[tauri::command(async)]
+pub fn switch_profile(app_handle: tauri::AppHandle, id: String) -> Result<ProfileList, String> {
+    let profiles = {
+        let state = app_handle.state::<ProfileState>();
+        let mut config = state.0.lock().unwrap();
+        let active = if id == DEFAULT_PROFILE {
+            None
+        } else if config.profiles.iter().any(|profile| profile.id == id) {
+            Some(id.clone())
+        } else {
+            return Err(format!("Profile not found: {}", id));
+        };
+        if config.active == active {
+            return Ok(list(&config));
+        }
+        config.active = active;
+        save(&app_handle, &config)?;
+        list(&config)
+    };
+    println!("Switching to profile {}", id);
+
+    settings::reload(&app_handle)?;
+    app_handle.state::<history::HistoryState>().reopen(&app_handle);
+    *app_handle.state::<sessions::ActiveSession>().0.lock().unwrap() = None;
+    crate::tray::refresh(&app_handle);
+    restart_backend(&app_handle)?;
+
+    if let Err(e) = app_handle.emit("profile-changed", &profiles) {
+        eprintln!("Failed to emit profile-changed: {}", e);
+    }
+    Ok(profiles)
+}
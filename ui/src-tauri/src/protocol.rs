@@ -0,0 +1,561 @@
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+use reqwest::header::{ACCEPT_ENCODING, CONTENT_ENCODING, CONTENT_TYPE};
+
+use crate::compression::{self, Encoding};
+use crate::metrics::{self, RequestOutcome};
+use crate::{backend, network, recorder, workers};
+
+// Versions of the shell <-> backend contract this shell speaks, oldest first.
+// A breaking change to any method below gets a new version and a new path.
+pub const PROTOCOL_VERSIONS: &[u32] = &[1];
+pub const RPC_PATH: &str = "/rpc/v1";
+
+// JSON-RPC error codes, as used by the backend's dispatcher
+pub const METHOD_NOT_FOUND: i64 = -32601;
+pub const INVALID_PARAMS: i64 = -32602;
+
+static NEXT_ID: AtomicU64 = AtomicU64::new(1);
+
+// One backend method: its wire name and the shapes of its params and result.
+// Every method here has a matching entry in RPC_METHODS in the backend's app.py.
+pub trait Method {
+    const NAME: &'static str;
+    const TIMEOUT: Duration = Duration::from_secs(10);
+    // Only needs the model, so any backend can answer it, workers included
+    const PARALLEL: bool = false;
+    // Goes on to the model provider, so there's no point sending it while offline
+    const ONLINE: bool = false;
+    type Params: Serialize;
+    type Result: DeserializeOwned;
+}
+
+#[derive(Serialize)]
+struct RpcRequest<'a, P> {
+    jsonrpc: &'static str,
+    id: u64,
+    method: &'a str,
+    params: &'a P,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RpcError {
+    pub code: i64,
+    pub message: String,
+    #[serde(default)]
+    pub data: Option<Value>,
+}
+
+#[derive(Deserialize)]
+struct RpcResponse {
+    #[serde(default)]
+    result: Option<Value>,
+    #[serde(default)]
+    error: Option<RpcError>,
+}
+
+impl RpcError {
+    fn describe(&self, method: &str) -> String {
+        // Endpoint errors carry the HTTP status the REST route would have returned
+        let status = self.data.as_ref().and_then(|d| d.get("status")).and_then(|s| s.as_u64());
+        match (self.code, status) {
+            (_, Some(status)) => format!("API server returned {}: {}", status, self.message),
+            (INVALID_PARAMS, _) => format!("API server rejected the parameters for {}: {}", method, self.message),
+            _ => format!("{} failed: {}", method, self.message),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum CallError {
+    // The server isn't up (yet)
+    Unreachable(String),
+    // The server is up but doesn't know this endpoint or method, i.e. it's from another release
+    Unsupported(String),
+    Failed(String),
+    // Not sent: it needs the internet, which can't be reached
+    Offline,
+}
+
+impl fmt::Display for CallError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CallError::Unreachable(e) => write!(f, "Failed to reach API server: {}", e),
+            CallError::Unsupported(method) => write!(f, "API server does not support {}; is it up to date?", method),
+            CallError::Failed(e) => write!(f, "{}", e),
+            CallError::Offline => write!(f, "No internet connection"),
+        }
+    }
+}
+
+// Call a backend method, telling apart why it failed
+pub fn try_call<M: Method>(params: &M::Params) -> Result<M::Result, CallError> {
+    // Fails now rather than after the method's timeout
+    if M::ONLINE && network::is_offline() {
+        return Err(CallError::Offline);
+    }
+    if M::PARALLEL {
+        return workers::call::<M>(params);
+    }
+    try_call_at::<M>(backend::base_url(), params)
+}
+
+pub fn outcome<T>(result: &Result<T, CallError>) -> RequestOutcome {
+    match result {
+        Ok(_) => RequestOutcome::Ok,
+        Err(CallError::Unreachable(_)) | Err(CallError::Offline) => RequestOutcome::Unreachable,
+        Err(CallError::Unsupported(_)) => RequestOutcome::Unsupported,
+        Err(CallError::Failed(_)) => RequestOutcome::Failed,
+    }
+}
+
+// Call a method on the backend at `base_url`
+pub fn try_call_at<M: Method>(base_url: &str, params: &M::Params) -> Result<M::Result, CallError> {
+    let params =
+        serde_json::to_value(params).map_err(|e| CallError::Failed(format!("Failed to encode {}: {}", M::NAME, e)))?;
+    let result = call_raw(base_url, M::NAME, M::TIMEOUT, params)?;
+    serde_json::from_value(result).map_err(|e| CallError::Failed(format!("Invalid {} response: {}", M::NAME, e)))
+}
+
+// Call a method by name with params that are already JSON, returning its undecoded result.
+// Every call to the backend ends up here, so this is where it's measured and recorded.
+pub fn call_raw(base_url: &str, method: &str, timeout: Duration, params: Value) -> Result<Value, CallError> {
+    let started = Instant::now();
+    let result = send(base_url, method, timeout, &params);
+    let outcome = outcome(&result);
+    let elapsed = started.elapsed();
+    metrics::record_request(outcome, elapsed);
+    recorder::record(base_url, method, timeout, &params, &result, outcome, elapsed);
+    result
+}
+
+fn send(base_url: &str, method: &str, timeout: Duration, params: &Value) -> Result<Value, CallError> {
+    let request = RpcRequest {
+        jsonrpc: "2.0",
+        id: NEXT_ID.fetch_add(1, Ordering::Relaxed),
+        method,
+        params,
+    };
+
+    let body =
+        serde_json::to_vec(&request).map_err(|e| CallError::Failed(format!("Failed to encode {}: {}", method, e)))?;
+
+    // Large bodies (screenshots, file contents) are compressed once the handshake agreed on an encoding
+    let negotiated = compression::negotiated();
+    let (body, body_encoding) = compression::maybe_compress(negotiated, body).map_err(CallError::Failed)?;
+
+    let mut builder = backend::client(timeout)
+        .map_err(CallError::Failed)?
+        .post(format!("{}{}", base_url, RPC_PATH))
+        .header(CONTENT_TYPE, "application/json");
+    if let Some(encoding) = body_encoding {
+        builder = builder.header(CONTENT_ENCODING, encoding.name());
+    }
+    if let Some(encoding) = negotiated {
+        builder = builder.header(ACCEPT_ENCODING, encoding.name());
+    }
+    let response = builder
+        .body(body)
+        .send()
+        .map_err(|e| CallError::Unreachable(e.to_string()))?;
+
+    let status = response.status();
+    if status == reqwest::StatusCode::NOT_FOUND {
+        return Err(CallError::Unsupported(method.to_string()));
+    }
+    if !status.is_success() {
+        return Err(CallError::Failed(format!("API server returned {} for {}", status, method)));
+    }
+
+    let response_encoding = response
+        .headers()
+        .get(CONTENT_ENCODING)
+        .and_then(|value| value.to_str().ok())
+        .and_then(Encoding::parse);
+    let bytes = response
+        .bytes()
+        .map_err(|e| CallError::Failed(format!("Failed to read response from API server: {}", e)))?;
+    let bytes = match response_encoding {
+        Some(encoding) => compression::decompress(encoding, &bytes).map_err(CallError::Failed)?,
+        None => bytes.to_vec(),
+    };
+    let response: RpcResponse = serde_json::from_slice(&bytes)
+        .map_err(|e| CallError::Failed(format!("Invalid response from API server: {}", e)))?;
+
+    match response.error {
+        Some(error) if error.code == METHOD_NOT_FOUND => Err(CallError::Unsupported(method.to_string())),
+        Some(error) => Err(CallError::Failed(error.describe(method))),
+        None => Ok(response.result.unwrap_or(Value::Null)),
+    }
+}
+
+// Call a backend method and decode its result
+pub fn call<M: Method>(params: &M::Params) -> Result<M::Result, String> {
+    try_call::<M>(params).map_err(|e| e.to_string())
+}
+
+// Params for methods that take none; sent as {}
+#[derive(Serialize)]
+pub struct NoParams {}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct PingResult {
+    pub status: String,
+    pub service: String,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct HandshakeParams {
+    pub shell_version: String,
+    pub protocol_versions: Vec<u32>,
+    pub capabilities: Vec<String>,
+    // Body encodings the shell can read and write, most preferred first
+    pub encodings: Vec<String>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct HandshakeResult {
+    pub backend_version: String,
+    // Newest version both sides speak, or None if there is none
+    pub protocol_version: Option<u32>,
+    pub protocol_versions: Vec<u32>,
+    #[serde(default)]
+    pub capabilities: Vec<String>,
+    // Encoding both sides will use for large bodies, if any
+    #[serde(default)]
+    pub encoding: Option<String>,
+}
+
+// Optional features the backend can serve right now
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Capabilities {
+    pub vision: bool,
+    pub voice: bool,
+    pub code_exec: bool,
+    pub screenshot: bool,
+    #[serde(default)]
+    pub details: HashMap<String, String>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct StatusMessage {
+    pub status: String,
+    pub message: String,
+}
+
+impl StatusMessage {
+    pub fn into_result(self) -> Result<(), String> {
+        if self.status == "success" {
+            Ok(())
+        } else {
+            Err(self.message)
+        }
+    }
+}
+
+// Mirrors ConfigUpdateRequest; unset fields are left alone by the backend
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct ConfigUpdate {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub api_key: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub provider: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ollama_url: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub model_name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub temperature: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_output_tokens: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub top_p: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub top_k: Option<u32>,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct JobParams {
+    pub job_id: String,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct JobInfo {
+    pub job_id: String,
+    pub prompt: Option<String>,
+    pub status: Option<String>,
+    pub start_time: Option<String>,
+    pub code: Option<String>,
+    pub last_result: Option<String>,
+    // "planning", "awaiting_approval" or "executing" while the job runs
+    pub phase: Option<String>,
+    pub attempt: Option<u32>,
+    pub max_attempts: Option<u32>,
+    // Percent of the script's time budget used, while executing
+    pub progress: Option<u8>,
+    #[serde(default)]
+    pub logs: Vec<Value>,
+    // Clicks, keystrokes and files touched by the latest attempt's script
+    #[serde(default)]
+    pub steps: Vec<Value>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct JobSubmitted {
+    pub job_id: String,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct CompleteParams {
+    pub prompt: String,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct CompleteResult {
+    pub text: String,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct EmbedParams {
+    pub texts: Vec<String>,
+    pub task_type: String,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct EmbedResult {
+    pub embeddings: Vec<Vec<f32>>,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct ScreenshotParams {
+    // Allow large captures to come back as a handoff file
+    pub handoff: bool,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct ScreenshotResult {
+    // Base64-encoded PNG, or the path of a PNG in the handoff directory
+    pub image: Option<String>,
+    pub file: Option<String>,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct AttachParams {
+    // A job id the prompt will be submitted under; it needn't exist yet
+    pub job_id: String,
+    pub name: String,
+    // Copy of the file in the handoff directory
+    pub file: String,
+    pub size: u64,
+    pub mime_type: String,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct WorkingDirectoryParams {
+    // None lifts the restriction
+    pub path: Option<String>,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct RunParams {
+    pub prompt: String,
+    // Chosen by the shell when files were attached to the job before it runs
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub job_id: Option<String>,
+    // Hold the generated script until the shell approves it. Sent with every job, since a backend
+    // the shell didn't start never sees its environment.
+    pub require_approval: bool,
+}
+
+impl RunParams {
+    pub fn new(prompt: String, job_id: Option<String>) -> Self {
+        RunParams {
+            prompt,
+            job_id,
+            require_approval: true,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct PauseParams {
+    pub paused: bool,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct DryRunParams {
+    pub dry_run: bool,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct ApprovalParams {
+    pub job_id: String,
+    // The attempt whose script was reviewed; a retry generates a new one
+    pub attempt: u32,
+    pub approved: bool,
+    pub reason: Option<String>,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct StopParams {
+    pub job_id: String,
+    // Why the shell stopped the job, kept as its result
+    pub reason: Option<String>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct JobStopped {
+    pub job_id: String,
+}
+
+pub struct Ping;
+impl Method for Ping {
+    const NAME: &'static str = "system.ping";
+    const TIMEOUT: Duration = Duration::from_secs(3);
+    type Params = NoParams;
+    type Result = PingResult;
+}
+
+pub struct Handshake;
+impl Method for Handshake {
+    const NAME: &'static str = "system.handshake";
+    const TIMEOUT: Duration = Duration::from_secs(3);
+    type Params = HandshakeParams;
+    type Result = HandshakeResult;
+}
+
+pub struct GetCapabilities;
+impl Method for GetCapabilities {
+    const NAME: &'static str = "system.capabilities";
+    type Params = NoParams;
+    type Result = Capabilities;
+}
+
+pub struct Doctor;
+impl Method for Doctor {
+    const NAME: &'static str = "system.doctor";
+    type Params = NoParams;
+    type Result = Value;
+}
+
+// Config with the API key masked
+pub struct GetConfig;
+impl Method for GetConfig {
+    const NAME: &'static str = "config.get";
+    type Params = NoParams;
+    type Result = Value;
+}
+
+// Config including the API key; the backend only answers this for local clients
+pub struct ExportConfig;
+impl Method for ExportConfig {
+    const NAME: &'static str = "config.export";
+    type Params = NoParams;
+    type Result = Value;
+}
+
+pub struct UpdateConfig;
+impl Method for UpdateConfig {
+    const NAME: &'static str = "config.update";
+    type Params = ConfigUpdate;
+    type Result = StatusMessage;
+}
+
+pub struct GetJob;
+impl Method for GetJob {
+    const NAME: &'static str = "jobs.get";
+    type Params = JobParams;
+    type Result = JobInfo;
+}
+
+pub struct RunJob;
+impl Method for RunJob {
+    const NAME: &'static str = "jobs.run";
+    const ONLINE: bool = true;
+    type Params = RunParams;
+    type Result = JobSubmitted;
+}
+
+pub struct SelfTest;
+impl Method for SelfTest {
+    const NAME: &'static str = "jobs.selfTest";
+    type Params = NoParams;
+    type Result = JobSubmitted;
+}
+
+pub struct AttachFile;
+impl Method for AttachFile {
+    const NAME: &'static str = "jobs.attach";
+    type Params = AttachParams;
+    type Result = StatusMessage;
+}
+
+// The shell's verdict on a script waiting in the "awaiting_approval" phase
+pub struct ApproveJob;
+impl Method for ApproveJob {
+    const NAME: &'static str = "jobs.approve";
+    type Params = ApprovalParams;
+    type Result = StatusMessage;
+}
+
+pub struct StopJob;
+impl Method for StopJob {
+    const NAME: &'static str = "jobs.stop";
+    type Params = StopParams;
+    type Result = JobStopped;
+}
+
+pub struct SetWorkingDirectory;
+impl Method for SetWorkingDirectory {
+    const NAME: &'static str = "session.setWorkingDirectory";
+    type Params = WorkingDirectoryParams;
+    type Result = StatusMessage;
+}
+
+pub struct SetPaused;
+impl Method for SetPaused {
+    const NAME: &'static str = "session.setPaused";
+    type Params = PauseParams;
+    type Result = StatusMessage;
+}
+
+pub struct SetDryRun;
+impl Method for SetDryRun {
+    const NAME: &'static str = "session.setDryRun";
+    type Params = DryRunParams;
+    type Result = StatusMessage;
+}
+
+pub struct Complete;
+impl Method for Complete {
+    const NAME: &'static str = "text.complete";
+    const TIMEOUT: Duration = Duration::from_secs(60);
+    const PARALLEL: bool = true;
+    const ONLINE: bool = true;
+    type Params = CompleteParams;
+    type Result = CompleteResult;
+}
+
+pub struct Embed;
+impl Method for Embed {
+    const NAME: &'static str = "text.embed";
+    const TIMEOUT: Duration = Duration::from_secs(120);
+    const PARALLEL: bool = true;
+    const ONLINE: bool = true;
+    type Params = EmbedParams;
+    type Result = EmbedResult;
+}
+
+pub struct Screenshot;
+impl Method for Screenshot {
+    const NAME: &'static str = "desktop.screenshot";
+    const TIMEOUT: Duration = Duration::from_secs(15);
+    type Params = ScreenshotParams;
+    type Result = ScreenshotResult;
+}
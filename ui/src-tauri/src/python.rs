@@ -0,0 +1,421 @@
+use serde::{Deserialize, Serialize};
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::Mutex;
+use tauri::Manager;
+
+use crate::{settings, storage};
+
+// Oldest Python the backend supports
+const MIN_VERSION: (u32, u32) = (3, 8);
+
+// The interpreter picked last time, so discovery only reruns when it goes missing
+const RECORD_FILE: &str = "python.json";
+
+// Prints the version and the real executable, which launchers and shims hide
+const PROBE: &str = "import sys; print('%d.%d.%d' % sys.version_info[:3]); print(sys.executable)";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum InterpreterSource {
+    // `backend.python` in the settings
+    Override,
+    // The `py` launcher on Windows
+    Launcher,
+    Pyenv,
+    Path,
+    Homebrew,
+    Conda,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Interpreter {
+    pub path: PathBuf,
+    pub version: String,
+    pub source: InterpreterSource,
+    // The conda environment the interpreter belongs to, activated for everything it runs
+    #[serde(default)]
+    pub conda_prefix: Option<PathBuf>,
+}
+
+impl Interpreter {
+    pub fn command(&self) -> Command {
+        let mut command = Command::new(&self.path);
+        command.envs(self.activation_env());
+        command
+    }
+
+    pub fn activation_env(&self) -> Vec<(&'static str, OsString)> {
+        self.conda_prefix.as_deref().map(conda_activation).unwrap_or_default()
+    }
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct FoundInterpreter {
+    #[serde(flatten)]
+    pub interpreter: Interpreter,
+    pub supported: bool,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct CondaEnv {
+    // What `backend.conda_env` is set to to pick it
+    pub name: String,
+    pub prefix: PathBuf,
+}
+
+#[derive(Default, Serialize, Deserialize)]
+#[serde(default)]
+struct Record {
+    interpreter: Option<Interpreter>,
+}
+
+// What the settings ask for, in order of precedence
+#[derive(Clone, Debug, PartialEq)]
+enum Request {
+    Path(String),
+    CondaEnv(String),
+    Auto,
+}
+
+// The interpreter in use, along with the request it was resolved for
+#[derive(Default)]
+pub struct PythonState(Mutex<Option<(Request, Interpreter)>>);
+
+struct Candidate {
+    program: PathBuf,
+    args: &'static [&'static str],
+    source: InterpreterSource,
+    conda_prefix: Option<PathBuf>,
+}
+
+fn candidate(program: impl Into<PathBuf>, args: &'static [&'static str], source: InterpreterSource) -> Candidate {
+    Candidate {
+        program: program.into(),
+        args,
+        source,
+        conda_prefix: None,
+    }
+}
+
+fn conda_python(prefix: &Path) -> PathBuf {
+    if cfg!(target_os = "windows") {
+        prefix.join("python.exe")
+    } else {
+        prefix.join("bin").join("python")
+    }
+}
+
+fn conda_candidate(prefix: &Path) -> Candidate {
+    Candidate {
+        conda_prefix: Some(prefix.to_path_buf()),
+        ..candidate(conda_python(prefix), &[], InterpreterSource::Conda)
+    }
+}
+
+fn is_conda_prefix(prefix: &Path) -> bool {
+    prefix.join("conda-meta").is_dir()
+}
+
+// Conda installations, wherever the installers put them
+fn conda_roots() -> Vec<PathBuf> {
+    let mut roots = Vec::new();
+    // `conda` itself lives in bin/ or Scripts/ of its installation
+    if let Some(conda) = std::env::var_os("CONDA_EXE").map(PathBuf::from) {
+        roots.extend(conda.parent().and_then(Path::parent).map(Path::to_path_buf));
+    }
+    for name in ["miniconda3", "anaconda3", "miniforge3", "mambaforge"] {
+        roots.push(settings::expand_home(&format!("~/{}", name)));
+    }
+    if cfg!(target_os = "macos") {
+        roots.push(PathBuf::from("/opt/homebrew/Caskroom/miniconda/base"));
+    }
+    if cfg!(target_os = "linux") {
+        roots.push(PathBuf::from("/opt/conda"));
+    }
+    let mut found: Vec<PathBuf> = Vec::new();
+    for root in roots.into_iter().filter(|root| is_conda_prefix(root)) {
+        if !found.contains(&root) {
+            found.push(root);
+        }
+    }
+    found
+}
+
+// Every conda environment on the machine: each installation's base and named environments,
+// plus those conda keeps track of elsewhere
+pub fn conda_envs() -> Vec<CondaEnv> {
+    let roots = conda_roots();
+    let mut prefixes: Vec<PathBuf> = Vec::new();
+    let mut env_dirs: Vec<PathBuf> = roots.iter().map(|root| root.join("envs")).collect();
+    env_dirs.push(settings::expand_home("~/.conda/envs"));
+    for root in &roots {
+        prefixes.push(root.clone());
+    }
+    for dir in env_dirs {
+        if let Ok(entries) = std::fs::read_dir(&dir) {
+            prefixes.extend(entries.filter_map(|entry| entry.ok()).map(|entry| entry.path()));
+        }
+    }
+    if let Ok(listed) = std::fs::read_to_string(settings::expand_home("~/.conda/environments.txt")) {
+        prefixes.extend(listed.lines().map(str::trim).filter(|line| !line.is_empty()).map(PathBuf::from));
+    }
+
+    let mut envs: Vec<CondaEnv> = Vec::new();
+    for prefix in prefixes {
+        if !is_conda_prefix(&prefix) || envs.iter().any(|env| env.prefix == prefix) {
+            continue;
+        }
+        let name = if roots.first() == Some(&prefix) {
+            "base".to_string()
+        } else if roots.contains(&prefix) {
+            // Another installation's base can only be told apart by its path
+            prefix.display().to_string()
+        } else {
+            prefix
+                .file_name()
+                .map(|name| name.to_string_lossy().to_string())
+                .unwrap_or_else(|| prefix.display().to_string())
+        };
+        envs.push(CondaEnv { name, prefix });
+    }
+    envs
+}
+
+// What `conda activate` sets up for the environment's programs to find each other and their
+// libraries. Packages' own activation scripts aren't run, as they need the shell
+fn conda_activation(prefix: &Path) -> Vec<(&'static str, OsString)> {
+    let dirs: Vec<PathBuf> = if cfg!(target_os = "windows") {
+        vec![
+            prefix.to_path_buf(),
+            prefix.join("Library").join("mingw-w64").join("bin"),
+            prefix.join("Library").join("usr").join("bin"),
+            prefix.join("Library").join("bin"),
+            prefix.join("Scripts"),
+            prefix.join("bin"),
+        ]
+    } else {
+        vec![prefix.join("bin")]
+    };
+    let inherited = std::env::var_os("PATH").unwrap_or_default();
+    let name = prefix
+        .file_name()
+        .map(|name| name.to_os_string())
+        .unwrap_or_else(|| prefix.as_os_str().to_os_string());
+
+    let mut env = vec![
+        ("CONDA_PREFIX", prefix.as_os_str().to_os_string()),
+        ("CONDA_DEFAULT_ENV", name),
+        ("CONDA_SHLVL", OsString::from("1")),
+    ];
+    match std::env::join_paths(dirs.into_iter().chain(std::env::split_paths(&inherited))) {
+        Ok(path) => env.push(("PATH", path)),
+        Err(e) => eprintln!("Failed to put the conda environment on PATH: {}", e),
+    }
+    env
+}
+
+// Where to look, best first. Apps started from the Dock or Start menu don't get the shell's
+// PATH, so version managers and package managers are also looked for where they install
+fn candidates() -> Vec<Candidate> {
+    let mut candidates = Vec::new();
+    if cfg!(target_os = "windows") {
+        candidates.push(candidate("py", &["-3"], InterpreterSource::Launcher));
+        candidates.push(candidate("python", &[], InterpreterSource::Path));
+        candidates.push(candidate("python3", &[], InterpreterSource::Path));
+    } else {
+        let pyenv_root = std::env::var_os("PYENV_ROOT")
+            .map(PathBuf::from)
+            .unwrap_or_else(|| settings::expand_home("~/.pyenv"));
+        candidates.push(candidate(pyenv_root.join("shims").join("python3"), &[], InterpreterSource::Pyenv));
+        candidates.push(candidate("python3", &[], InterpreterSource::Path));
+        candidates.push(candidate("python", &[], InterpreterSource::Path));
+        if cfg!(target_os = "macos") {
+            candidates.push(candidate("/opt/homebrew/bin/python3", &[], InterpreterSource::Homebrew));
+            candidates.push(candidate("/usr/local/bin/python3", &[], InterpreterSource::Homebrew));
+        }
+    }
+    candidates.extend(conda_roots().iter().map(|root| conda_candidate(root)));
+    candidates
+}
+
+fn parse_version(version: &str) -> Option<(u32, u32)> {
+    let mut parts = version.split('.');
+    let major = parts.next()?.parse().ok()?;
+    let minor = parts.next()?.parse().ok()?;
+    Some((major, minor))
+}
+
+fn supported(version: &str) -> bool {
+    parse_version(version).is_some_and(|version| version >= MIN_VERSION)
+}
+
+fn probe(candidate: &Candidate) -> Option<Interpreter> {
+    // Skip absolute paths that don't exist rather than waiting on a spawn to fail
+    if candidate.program.is_absolute() && !candidate.program.exists() {
+        return None;
+    }
+    let activation = candidate.conda_prefix.as_deref().map(conda_activation).unwrap_or_default();
+    let output = Command::new(&candidate.program)
+        .args(candidate.args)
+        .args(["-c", PROBE])
+        .envs(activation)
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let mut lines = stdout.lines();
+    let version = lines.next()?.trim().to_string();
+    let path = PathBuf::from(lines.next()?.trim());
+    Some(Interpreter {
+        path,
+        version,
+        source: candidate.source,
+        conda_prefix: candidate.conda_prefix.clone(),
+    })
+}
+
+fn requested(app_handle: &tauri::AppHandle) -> Request {
+    let backend = settings::current(app_handle).backend;
+    let set = |value: Option<String>| value.map(|value| value.trim().to_string()).filter(|value| !value.is_empty());
+    if let Some(python) = set(backend.python) {
+        Request::Path(python)
+    } else if let Some(env) = set(backend.conda_env) {
+        Request::CondaEnv(env)
+    } else {
+        Request::Auto
+    }
+}
+
+// Every interpreter that can be found, each listed once, in order of preference
+pub fn discover() -> Vec<FoundInterpreter> {
+    let mut found: Vec<FoundInterpreter> = Vec::new();
+    for interpreter in candidates().iter().filter_map(probe) {
+        if found.iter().any(|f| f.interpreter.path == interpreter.path) {
+            continue;
+        }
+        let supported = supported(&interpreter.version);
+        found.push(FoundInterpreter { interpreter, supported });
+    }
+    found
+}
+
+fn check_supported(interpreter: Interpreter, what: &str) -> Result<Interpreter, String> {
+    if !supported(&interpreter.version) {
+        return Err(format!(
+            "{} is Python {}, but the backend needs {}.{} or newer",
+            what, interpreter.version, MIN_VERSION.0, MIN_VERSION.1
+        ));
+    }
+    Ok(interpreter)
+}
+
+// A named environment, or one given by its path
+fn find_conda_env(env: &str) -> Result<Interpreter, String> {
+    let prefix = if env.contains('/') || env.contains('\\') {
+        settings::expand_home(env)
+    } else {
+        conda_envs()
+            .into_iter()
+            .find(|found| found.name == env)
+            .map(|found| found.prefix)
+            .ok_or_else(|| format!("Conda environment not found: {}", env))?
+    };
+    let interpreter = probe(&conda_candidate(&prefix))
+        .ok_or_else(|| format!("Conda environment {} has no Python that can be run", env))?;
+    check_supported(interpreter, &format!("Conda environment {}", env))
+}
+
+fn find(app_handle: &tauri::AppHandle, request: &Request) -> Result<Interpreter, String> {
+    // An explicit choice is used or reported, never silently swapped for another
+    match request {
+        Request::Path(path) => {
+            let interpreter = probe(&candidate(settings::expand_home(path), &[], InterpreterSource::Override))
+                .ok_or_else(|| format!("The configured Python interpreter can't be run: {}", path))?;
+            return check_supported(interpreter, "The configured Python interpreter");
+        }
+        Request::CondaEnv(env) => return find_conda_env(env),
+        Request::Auto => {}
+    }
+
+    let record: Record = storage::load_json(app_handle, RECORD_FILE);
+    if let Some(recorded) = record.interpreter {
+        let recheck = Candidate {
+            conda_prefix: recorded.conda_prefix.clone(),
+            ..candidate(recorded.path.clone(), &[], recorded.source)
+        };
+        if let Some(interpreter) = probe(&recheck).filter(|i| supported(&i.version)) {
+            return Ok(interpreter);
+        }
+        println!("Python at {} is gone or too old, looking for another", recorded.path.display());
+    }
+
+    let found = discover();
+    let interpreter = match found.iter().find(|f| f.supported) {
+        Some(found) => found.interpreter.clone(),
+        None => {
+            let rejected: Vec<String> = found
+                .iter()
+                .map(|f| format!("{} {}", f.interpreter.path.display(), f.interpreter.version))
+                .collect();
+            let mut message = format!("No Python {}.{} or newer was found", MIN_VERSION.0, MIN_VERSION.1);
+            if !rejected.is_empty() {
+                message.push_str(&format!(" (found {})", rejected.join(", ")));
+            }
+            return Err(message);
+        }
+    };
+    println!("Using Python {} at {}", interpreter.version, interpreter.path.display());
+    let record = Record {
+        interpreter: Some(interpreter.clone()),
+    };
+    if let Err(e) = storage::save_json(app_handle, RECORD_FILE, &record, storage::Data::Config) {
+        eprintln!("Failed to record the Python interpreter: {}", e);
+    }
+    Ok(interpreter)
+}
+
+// The interpreter the backend and its tools run on: the configured one, else the best found
+pub fn resolve(app_handle: &tauri::AppHandle) -> Result<Interpreter, String> {
+    let request = requested(app_handle);
+    let state = app_handle.state::<PythonState>();
+    let mut chosen = state.0.lock().unwrap();
+    if let Some((for_request, interpreter)) = chosen.as_ref() {
+        if *for_request == request {
+            return Ok(interpreter.clone());
+        }
+    }
+    let interpreter = find(app_handle, &request)?;
+    *chosen = Some((request, interpreter.clone()));
+    Ok(interpreter)
+}
+
+// Forget the interpreter picked earlier, so the next lookup searches again
+pub fn forget(app_handle: &tauri::AppHandle) -> Result<(), String> {
+    *app_handle.state::<PythonState>().0.lock().unwrap() = None;
+    storage::save_json(app_handle, RECORD_FILE, &Record::default(), storage::Data::Config)
+}
+
+// Command for the settings pane; `refresh` searches again instead of reusing the last pick
+#[tauri::command(async)]
+pub fn get_python_interpreter(app_handle: tauri::AppHandle, refresh: Option<bool>) -> Result<Interpreter, String> {
+    if refresh.unwrap_or(false) {
+        forget(&app_handle)?;
+    }
+    resolve(&app_handle)
+}
+
+// Command listing the interpreters that could be chosen for `backend.python`
+#[tauri::command(async)]
+pub fn list_python_interpreters() -> Vec<FoundInterpreter> {
+    discover()
+}
+
+// Command listing the environments that could be chosen for `backend.conda_env`
+#[tauri::command(async)]
+pub fn list_conda_envs() -> Vec<CondaEnv> {
+    conda_envs()
+}
@@ -0,0 +1,218 @@
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+use tauri::{Emitter, Manager};
+
+use crate::cache;
+use crate::errors;
+use crate::protocol::{CallError, RunParams};
+use crate::storage;
+use crate::util::now_secs;
+
+// Prompts still waiting when the app closes are picked up again at the next launch
+const QUEUE_FILE: &str = "pending_prompts.json";
+
+// The first retry waits this long, each further one twice as long as the last
+const INITIAL_BACKOFF: Duration = Duration::from_secs(2);
+
+const MAX_BACKOFF: Duration = Duration::from_secs(5 * 60);
+
+// An automation starting long after it was asked for is more surprise than help
+const MAX_AGE_SECS: u64 = 30 * 60;
+
+// How often a waiting retry checks whether the backend came back early
+const TICK: Duration = Duration::from_millis(250);
+
+// A prompt waiting for the backend to become reachable
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct PendingPrompt {
+    pub id: String,
+    pub prompt: String,
+    pub context: Option<String>,
+    pub queued: u64,
+    pub attempts: u32,
+    pub last_error: String,
+    // Queued in privacy mode, so only kept in memory
+    #[serde(skip)]
+    private: bool,
+}
+
+#[derive(Clone, Debug, Serialize)]
+struct PendingStarted {
+    id: String,
+    job_id: String,
+}
+
+#[derive(Default)]
+pub struct QueueState {
+    pending: Mutex<Vec<PendingPrompt>>,
+    // A retry thread is running
+    draining: AtomicBool,
+    // The backend answered a handshake since the last attempt, so retry now
+    recovered: AtomicBool,
+}
+
+impl QueueState {
+    pub fn len(&self) -> usize {
+        self.pending.lock().unwrap().len()
+    }
+}
+
+fn backoff(attempts: u32) -> Duration {
+    INITIAL_BACKOFF
+        .checked_mul(2u32.saturating_pow(attempts.saturating_sub(1)))
+        .map_or(MAX_BACKOFF, |delay| delay.min(MAX_BACKOFF))
+}
+
+fn save(app_handle: &tauri::AppHandle, pending: &[PendingPrompt], data: storage::Data) {
+    let kept: Vec<&PendingPrompt> = pending.iter().filter(|p| !p.private).collect();
+    if let Err(e) = storage::save_json(app_handle, QUEUE_FILE, &kept, data) {
+        eprintln!("Failed to save the queued prompts: {}", e);
+    }
+}
+
+// Save the queue and tell the UI; after every change to it
+fn emit_changed(app_handle: &tauri::AppHandle, data: storage::Data) {
+    let pending = app_handle.state::<QueueState>().pending.lock().unwrap().clone();
+    save(app_handle, &pending, data);
+    let _ = app_handle.emit("pending-prompts-changed", pending);
+}
+
+// Pick up the prompts an earlier run was still waiting to send
+pub fn restore(app_handle: &tauri::AppHandle) {
+    let saved: Vec<PendingPrompt> = storage::load_json(app_handle, QUEUE_FILE);
+    if saved.is_empty() {
+        return;
+    }
+    println!("{} queued prompt(s) left from the last run", saved.len());
+    app_handle.state::<QueueState>().pending.lock().unwrap().extend(saved);
+    emit_changed(app_handle, storage::Data::Removal);
+    drain(app_handle);
+}
+
+// Keep a prompt the backend couldn't take, to run it once the backend is back
+pub fn enqueue(app_handle: &tauri::AppHandle, prompt: String, context: Option<String>, error: String) -> PendingPrompt {
+    // Asking twice while offline still means running it once
+    let existing = app_handle
+        .state::<QueueState>()
+        .pending
+        .lock()
+        .unwrap()
+        .iter()
+        .find(|p| p.prompt == prompt && p.context == context)
+        .cloned();
+    if let Some(existing) = existing {
+        return existing;
+    }
+
+    let pending = PendingPrompt {
+        id: uuid::Uuid::new_v4().to_string(),
+        prompt,
+        context,
+        queued: now_secs(),
+        attempts: 1,
+        last_error: error,
+        private: !storage::may_record(app_handle),
+    };
+    println!("Backend unreachable or offline, queued prompt {}", pending.id);
+    app_handle.state::<QueueState>().pending.lock().unwrap().push(pending.clone());
+    emit_changed(app_handle, storage::Data::Activity);
+    drain(app_handle);
+    pending
+}
+
+// Called once a handshake succeeds or the network is back: don't wait out the backoff
+pub fn backend_recovered(app_handle: &tauri::AppHandle) {
+    let state = app_handle.state::<QueueState>();
+    if !state.pending.lock().unwrap().is_empty() {
+        state.recovered.store(true, Ordering::SeqCst);
+        drain(app_handle);
+    }
+}
+
+// Try to run the oldest prompt again
+fn retry_next(app_handle: &tauri::AppHandle) {
+    let state = app_handle.state::<QueueState>();
+    let pending = match state.pending.lock().unwrap().first().cloned() {
+        Some(pending) => pending,
+        None => return,
+    };
+    let remove = || state.pending.lock().unwrap().retain(|p| p.id != pending.id);
+
+    if now_secs().saturating_sub(pending.queued) > MAX_AGE_SECS {
+        remove();
+        errors::report(
+            app_handle,
+            "A queued prompt was dropped",
+            format!("The backend was unreachable for too long to run \"{}\"", pending.prompt),
+        );
+        return;
+    }
+
+    let key = cache::key(app_handle, "jobs.run", &pending.prompt, pending.context.as_deref());
+    let params = RunParams::new(cache::full_prompt(&pending.prompt, pending.context.as_deref()), None);
+    match cache::start(app_handle, &params, Some(key)) {
+        Ok(job_id) => {
+            println!("Queued prompt {} is running as job {}", pending.id, job_id);
+            remove();
+            let started = PendingStarted {
+                id: pending.id.clone(),
+                job_id,
+            };
+            let _ = app_handle.emit("pending-prompt-started", started);
+        }
+        Err(e @ CallError::Unreachable(_)) | Err(e @ CallError::Offline) => {
+            if let Some(entry) = state.pending.lock().unwrap().iter_mut().find(|p| p.id == pending.id) {
+                entry.attempts += 1;
+                entry.last_error = e.to_string();
+            }
+        }
+        // The backend is up but won't take it (paused, no API key), so retrying won't help
+        Err(e) => {
+            remove();
+            errors::report(app_handle, "A queued prompt could not be run", e);
+        }
+    }
+}
+
+// Work through the queue in the background until it's empty
+fn drain(app_handle: &tauri::AppHandle) {
+    let state = app_handle.state::<QueueState>();
+    if state.draining.swap(true, Ordering::SeqCst) {
+        return;
+    }
+    let app_handle = app_handle.clone();
+    std::thread::spawn(move || {
+        let state = app_handle.state::<QueueState>();
+        loop {
+            let attempts = match state.pending.lock().unwrap().first() {
+                Some(pending) => pending.attempts,
+                None => break,
+            };
+            let mut waited = Duration::from_secs(0);
+            while waited < backoff(attempts) && !state.recovered.swap(false, Ordering::SeqCst) {
+                std::thread::sleep(TICK);
+                waited += TICK;
+            }
+            retry_next(&app_handle);
+            emit_changed(&app_handle, storage::Data::Removal);
+        }
+        state.draining.store(false, Ordering::SeqCst);
+        // Something may have been queued between the last check and clearing the flag
+        if !state.pending.lock().unwrap().is_empty() {
+            drain(&app_handle);
+        }
+    });
+}
+
+#[tauri::command]
+pub fn list_pending_prompts(queue: tauri::State<QueueState>) -> Vec<PendingPrompt> {
+    queue.pending.lock().unwrap().clone()
+}
+
+#[tauri::command]
+pub fn cancel_pending_prompt(app_handle: tauri::AppHandle, id: String) {
+    app_handle.state::<QueueState>().pending.lock().unwrap().retain(|p| p.id != id);
+    emit_changed(&app_handle, storage::Data::Removal);
+}
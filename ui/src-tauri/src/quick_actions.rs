@@ -0,0 +1,275 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Mutex;
+use tauri::{Emitter, Manager};
+
+use crate::storage;
+use crate::errors::ReportExt;
+
+const STORE_FILE: &str = "quick_actions.json";
+
+// A reusable piece of prompt text
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct Snippet {
+    #[serde(default)]
+    pub id: String,
+    pub title: String,
+    pub content: String,
+}
+
+// A saved prompt with optional {{placeholder}} parameters
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct QuickAction {
+    #[serde(default)]
+    pub id: String,
+    pub title: String,
+    pub prompt: String,
+    #[serde(default)]
+    pub show_in_tray: bool,
+}
+
+// Quick action as listed in the spotlight, with its placeholders extracted
+#[derive(Clone, Debug, Serialize)]
+pub struct QuickActionEntry {
+    #[serde(flatten)]
+    pub action: QuickAction,
+    pub placeholders: Vec<String>,
+}
+
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub struct QuickActionStore {
+    #[serde(default)]
+    pub snippets: Vec<Snippet>,
+    #[serde(default)]
+    pub quick_actions: Vec<QuickAction>,
+}
+
+// Managed state wrapping the persisted store
+pub struct QuickActionsState(pub Mutex<QuickActionStore>);
+
+impl QuickActionsState {
+    pub fn load(app_handle: &tauri::AppHandle) -> Self {
+        QuickActionsState(Mutex::new(storage::load_json(app_handle, STORE_FILE)))
+    }
+}
+
+// Quick actions that should appear in the tray menu
+pub fn tray_actions(app_handle: &tauri::AppHandle) -> Vec<QuickAction> {
+    let state = app_handle.state::<QuickActionsState>();
+    let store = state.0.lock().unwrap();
+    store
+        .quick_actions
+        .iter()
+        .filter(|action| action.show_in_tray)
+        .cloned()
+        .collect()
+}
+
+// Extract the unique {{name}} placeholders from a prompt, in order of appearance
+pub fn placeholders(prompt: &str) -> Vec<String> {
+    let mut names = Vec::new();
+    let mut rest = prompt;
+
+    while let Some(start) = rest.find("{{") {
+        let after = &rest[start + 2..];
+        match after.find("}}") {
+            Some(end) => {
+                let name = after[..end].trim().to_string();
+                if !name.is_empty() && !names.contains(&name) {
+                    names.push(name);
+                }
+                rest = &after[end + 2..];
+            }
+            None => break,
+        }
+    }
+
+    names
+}
+
+// Replace {{name}} placeholders with the given values, leaving unknown ones untouched
+pub fn fill_placeholders(prompt: &str, values: &HashMap<String, String>) -> String {
+    let mut result = String::with_capacity(prompt.len());
+    let mut rest = prompt;
+
+    while let Some(start) = rest.find("{{") {
+        result.push_str(&rest[..start]);
+        let after = &rest[start + 2..];
+        match after.find("}}") {
+            Some(end) => {
+                let name = after[..end].trim();
+                match values.get(name) {
+                    Some(value) => result.push_str(value),
+                    None => result.push_str(&rest[start..start + 2 + end + 2]),
+                }
+                rest = &after[end + 2..];
+            }
+            None => {
+                result.push_str(&rest[start..]);
+                rest = "";
+            }
+        }
+    }
+
+    result.push_str(rest);
+    result
+}
+
+fn save(app_handle: &tauri::AppHandle, store: &QuickActionStore) -> Result<(), String> {
+    storage::save_json(app_handle, STORE_FILE, store, storage::Data::Config)
+}
+
+// Replace all snippets and quick actions (used when importing settings)
+pub fn replace_store(app_handle: &tauri::AppHandle, store: QuickActionStore) -> Result<(), String> {
+    {
+        let state = app_handle.state::<QuickActionsState>();
+        let mut current = state.0.lock().unwrap();
+        save(app_handle, &store)?;
+        *current = store;
+    }
+
+    crate::tray::refresh(app_handle);
+    Ok(())
+}
+
+#[tauri::command]
+pub fn list_snippets(state: tauri::State<QuickActionsState>) -> Vec<Snippet> {
+    state.0.lock().unwrap().snippets.clone()
+}
+
+// Create a snippet, or update it when the id already exists
+#[tauri::command]
+pub fn save_snippet(
+    app_handle: tauri::AppHandle,
+    state: tauri::State<QuickActionsState>,
+    mut snippet: Snippet,
+) -> Result<Snippet, String> {
+    let mut store = state.0.lock().unwrap();
+
+    if snippet.id.is_empty() {
+        snippet.id = uuid::Uuid::new_v4().to_string();
+    }
+
+    match store.snippets.iter_mut().find(|s| s.id == snippet.id) {
+        Some(existing) => *existing = snippet.clone(),
+        None => store.snippets.push(snippet.clone()),
+    }
+
+    save(&app_handle, &store)?;
+    Ok(snippet)
+}
+
+#[tauri::command]
+pub fn delete_snippet(
+    app_handle: tauri::AppHandle,
+    state: tauri::State<QuickActionsState>,
+    id: String,
+) -> Result<(), String> {
+    let mut store = state.0.lock().unwrap();
+    store.snippets.retain(|s| s.id != id);
+    save(&app_handle, &store)
+}
+
+#[tauri::command]
+pub fn list_quick_actions(state: tauri::State<QuickActionsState>) -> Vec<QuickActionEntry> {
+    let store = state.0.lock().unwrap();
+    store
+        .quick_actions
+        .iter()
+        .map(|action| QuickActionEntry {
+            action: action.clone(),
+            placeholders: placeholders(&action.prompt),
+        })
+        .collect()
+}
+
+// Create a quick action, or update it when the id already exists
+#[tauri::command]
+pub fn save_quick_action(
+    app_handle: tauri::AppHandle,
+    state: tauri::State<QuickActionsState>,
+    mut action: QuickAction,
+) -> Result<QuickAction, String> {
+    {
+        let mut store = state.0.lock().unwrap();
+
+        if action.id.is_empty() {
+            action.id = uuid::Uuid::new_v4().to_string();
+        }
+
+        match store.quick_actions.iter_mut().find(|a| a.id == action.id) {
+            Some(existing) => *existing = action.clone(),
+            None => store.quick_actions.push(action.clone()),
+        }
+
+        save(&app_handle, &store)?;
+    }
+
+    // The tray lists quick actions, so rebuild it after changes
+    crate::tray::refresh(&app_handle);
+    Ok(action)
+}
+
+#[tauri::command]
+pub fn delete_quick_action(
+    app_handle: tauri::AppHandle,
+    state: tauri::State<QuickActionsState>,
+    id: String,
+) -> Result<(), String> {
+    {
+        let mut store = state.0.lock().unwrap();
+        store.quick_actions.retain(|a| a.id != id);
+        save(&app_handle, &store)?;
+    }
+
+    crate::tray::refresh(&app_handle);
+    Ok(())
+}
+
+// Render a quick action's prompt with the given placeholder values
+#[tauri::command]
+pub fn render_quick_action(
+    state: tauri::State<QuickActionsState>,
+    id: String,
+    values: HashMap<String, String>,
+) -> Result<String, String> {
+    let store = state.0.lock().unwrap();
+    let action = store
+        .quick_actions
+        .iter()
+        .find(|a| a.id == id)
+        .ok_or_else(|| format!("Quick action not found: {}", id))?;
+
+    Ok(fill_placeholders(&action.prompt, &values))
+}
+
+// Show the spotlight and hand the quick action to the frontend
+pub fn trigger_quick_action(app_handle: &tauri::AppHandle, id: &str) {
+    let action = {
+        let state = app_handle.state::<QuickActionsState>();
+        let store = state.0.lock().unwrap();
+        store.quick_actions.iter().find(|a| a.id == id).cloned()
+    };
+
+    let action = match action {
+        Some(action) => action,
+        None => {
+            eprintln!("Quick action not found: {}", id);
+            return;
+        }
+    };
+
+    if let Some(window) = app_handle.get_webview_window("main") {
+        if !window.is_visible().unwrap_or(false) {
+            crate::toggle_spotlight_window(&window).or_report(app_handle, "Failed to show the spotlight");
+        }
+
+        let entry = QuickActionEntry {
+            placeholders: placeholders(&action.prompt),
+            action,
+        };
+        if let Err(e) = window.emit_to(window.label(), "quick-action", entry) {
+            eprintln!("Failed to emit quick action: {}", e);
+        }
+    }
+}
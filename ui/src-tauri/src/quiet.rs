@@ -0,0 +1,211 @@
+use serde::Serialize;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+use sysinfo::System;
+use tauri::{Emitter, Manager};
+
+use crate::{automation, settings};
+
+const POLL_INTERVAL: Duration = Duration::from_secs(5);
+
+// Helpers that only run while a screen is being shared (Zoom's share host, macOS Screen Sharing)
+const SHARING_PROCESSES: &[&str] = &["cpthost", "screensharingd"];
+
+// Why notifications are being held back
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum QuietReason {
+    DoNotDisturb,
+    // Presentation mode or a full-screen game or video; only Windows tells us about these
+    #[cfg_attr(not(target_os = "windows"), allow(dead_code))]
+    Presenting,
+    ScreenSharing,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct QuietStatus {
+    pub active: bool,
+    pub reason: Option<QuietReason>,
+}
+
+#[derive(Default)]
+pub struct QuietState {
+    reason: Mutex<Option<QuietReason>>,
+    // Set when we paused automation, so we only resume what we paused
+    paused_automation: AtomicBool,
+}
+
+// Notifications should stay silent while this is true
+pub fn is_quiet(app_handle: &tauri::AppHandle) -> bool {
+    app_handle.state::<QuietState>().reason.lock().unwrap().is_some()
+}
+
+#[cfg(target_os = "windows")]
+fn os_reason() -> Option<QuietReason> {
+    use windows::Win32::UI::Shell::{
+        SHQueryUserNotificationState, QUNS_BUSY, QUNS_PRESENTATION_MODE, QUNS_QUIET_TIME, QUNS_RUNNING_D3D_FULL_SCREEN,
+    };
+
+    // Focus assist has no public API, but Windows reports the states it's implied by
+    match unsafe { SHQueryUserNotificationState() } {
+        Ok(state) if state == QUNS_PRESENTATION_MODE || state == QUNS_RUNNING_D3D_FULL_SCREEN || state == QUNS_BUSY => {
+            Some(QuietReason::Presenting)
+        }
+        Ok(state) if state == QUNS_QUIET_TIME => Some(QuietReason::DoNotDisturb),
+        _ => None,
+    }
+}
+
+#[cfg(target_os = "macos")]
+fn os_reason() -> Option<QuietReason> {
+    // Focus (macOS 12+) records each active mode as an assertion
+    let home = std::env::var_os("HOME")?;
+    let assertions = std::path::Path::new(&home).join("Library/DoNotDisturb/DB/Assertions.json");
+    if let Ok(data) = std::fs::read_to_string(assertions) {
+        let json: serde_json::Value = serde_json::from_str(&data).ok()?;
+        let focused = json["data"]
+            .as_array()
+            .map(|stores| {
+                stores
+                    .iter()
+                    .any(|store| store["storeAssertionRecords"].as_array().is_some_and(|r| !r.is_empty()))
+            })
+            .unwrap_or(false);
+        return if focused { Some(QuietReason::DoNotDisturb) } else { None };
+    }
+
+    // Older releases keep a plain preference
+    let output = std::process::Command::new("defaults")
+        .args(["-currentHost", "read", "com.apple.notificationcenterui", "doNotDisturb"])
+        .output()
+        .ok()?;
+    if String::from_utf8_lossy(&output.stdout).trim() == "1" {
+        Some(QuietReason::DoNotDisturb)
+    } else {
+        None
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn os_reason() -> Option<QuietReason> {
+    use gtk::gio;
+    use gtk::gio::prelude::*;
+    use gtk::glib::ToVariant;
+
+    // GNOME hides banners in do-not-disturb
+    const GNOME_SCHEMA: &str = "org.gnome.desktop.notifications";
+    let has_schema = gio::SettingsSchemaSource::default()
+        .and_then(|source| source.lookup(GNOME_SCHEMA, true))
+        .is_some();
+    if has_schema && !gio::Settings::new(GNOME_SCHEMA).boolean("show-banners") {
+        return Some(QuietReason::DoNotDisturb);
+    }
+
+    // KDE and others say so on the notification server; don't start one just to ask
+    let connection = gio::bus_get_sync(gio::BusType::Session, gio::Cancellable::NONE).ok()?;
+    let inhibited = connection
+        .call_sync(
+            Some("org.freedesktop.Notifications"),
+            "/org/freedesktop/Notifications",
+            "org.freedesktop.DBus.Properties",
+            "Get",
+            Some(&("org.freedesktop.Notifications", "Inhibited").to_variant()),
+            None,
+            gio::DBusCallFlags::NO_AUTO_START,
+            1000,
+            gio::Cancellable::NONE,
+        )
+        .ok()
+        .and_then(|reply| reply.get::<(gtk::glib::Variant,)>())
+        .and_then(|(value,)| value.get::<bool>())
+        .unwrap_or(false);
+    if inhibited {
+        Some(QuietReason::DoNotDisturb)
+    } else {
+        None
+    }
+}
+
+#[cfg(not(any(target_os = "windows", target_os = "macos", target_os = "linux")))]
+fn os_reason() -> Option<QuietReason> {
+    None
+}
+
+fn is_sharing_screen(system: &mut System) -> bool {
+    system.refresh_processes();
+    system.processes().values().any(|process| {
+        let name = process.name().to_lowercase();
+        let name = name.strip_suffix(".exe").unwrap_or(&name);
+        SHARING_PROCESSES.contains(&name)
+    })
+}
+
+fn detect(system: &mut System) -> Option<QuietReason> {
+    os_reason().or_else(|| {
+        if is_sharing_screen(system) {
+            Some(QuietReason::ScreenSharing)
+        } else {
+            None
+        }
+    })
+}
+
+fn update(app_handle: &tauri::AppHandle, reason: Option<QuietReason>) {
+    let state = app_handle.state::<QuietState>();
+    {
+        let mut current = state.reason.lock().unwrap();
+        if *current == reason {
+            return;
+        }
+        *current = reason;
+    }
+    println!("Quiet mode {}", if reason.is_some() { "on" } else { "off" });
+
+    let status = QuietStatus {
+        active: reason.is_some(),
+        reason,
+    };
+    if let Err(e) = app_handle.emit("quiet-mode-changed", status) {
+        eprintln!("Failed to emit quiet-mode-changed: {}", e);
+    }
+
+    // Never type into a live presentation; only undo a pause we made ourselves
+    let result = if reason.is_some() {
+        if settings::current(app_handle).quiet.pause_automation && !automation::is_paused(app_handle) {
+            state.paused_automation.store(true, Ordering::SeqCst);
+            automation::set_paused(app_handle, true)
+        } else {
+            Ok(())
+        }
+    } else if state.paused_automation.swap(false, Ordering::SeqCst) {
+        automation::set_paused(app_handle, false)
+    } else {
+        Ok(())
+    };
+    if let Err(e) = result {
+        eprintln!("Failed to update the automation pause for quiet mode: {}", e);
+    }
+}
+
+// Watch for do-not-disturb, presentations and screen sharing in the background
+pub fn start_watching(app_handle: &tauri::AppHandle) {
+    let app_handle = app_handle.clone();
+    std::thread::spawn(move || {
+        let mut system = System::new();
+        loop {
+            update(&app_handle, detect(&mut system));
+            std::thread::sleep(POLL_INTERVAL);
+        }
+    });
+}
+
+// Command for the frontend to check before showing a notification
+#[tauri::command]
+pub fn get_quiet_mode(state: tauri::State<QuietState>) -> QuietStatus {
+    let reason = *state.reason.lock().unwrap();
+    QuietStatus {
+        active: reason.is_some(),
+        reason,
+    }
+}
@@ -0,0 +1,329 @@
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::VecDeque;
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+use tauri::Emitter;
+
+use crate::metrics::RequestOutcome;
+use crate::protocol::{self, CallError};
+use crate::{backend, logs, storage};
+
+// Window that recorded requests are streamed to
+const CONSOLE_WINDOW: &str = "console";
+
+// Subdirectory of the log directory holding one trace file per recording
+const TRACE_DIR: &str = "traces";
+
+// Traces of this recording kept in memory for the console
+const RECENT_TRACES: usize = 1000;
+
+// Fields whose values never reach a trace: API keys, MCP tokens and the like
+const SECRET_FIELDS: &[&str] = &["key", "token", "secret", "password", "authorization", "cookie"];
+
+// Screenshots and file contents are cut down to this many characters
+const MAX_STRING_CHARS: usize = 2000;
+
+// Checked on every backend call, so recording costs nothing while it's off
+static ENABLED: AtomicBool = AtomicBool::new(false);
+static NEXT_TRACE_ID: AtomicU64 = AtomicU64::new(1);
+
+static RECORDING: Lazy<Mutex<Option<Recording>>> = Lazy::new(|| Mutex::new(None));
+
+struct Recording {
+    app_handle: tauri::AppHandle,
+    path: PathBuf,
+    file: File,
+    recent: VecDeque<Recorded>,
+}
+
+// A trace and, if the request failed, its params as they were sent, so it can be retried exactly.
+// Only ever kept in memory.
+struct Recorded {
+    trace: Trace,
+    failed_params: Option<Value>,
+}
+
+// One request to the backend and what came back, as written to the trace file
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Trace {
+    pub id: u64,
+    pub timestamp: String,
+    pub url: String,
+    pub method: String,
+    pub timeout_ms: u64,
+    pub params: Value,
+    #[serde(default)]
+    pub result: Option<Value>,
+    #[serde(default)]
+    pub error: Option<String>,
+    pub outcome: String,
+    pub duration_ms: u64,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct RecordingStatus {
+    pub enabled: bool,
+    pub path: Option<String>,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct TraceFile {
+    pub path: String,
+    pub size: u64,
+}
+
+// How a recorded request went when it was sent again
+#[derive(Clone, Debug, Serialize)]
+pub struct ReplayedRequest {
+    pub trace_id: u64,
+    pub method: String,
+    pub recorded: String,
+    pub replayed: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    pub duration_ms: u64,
+}
+
+fn is_secret(field: &str) -> bool {
+    let field = field.to_ascii_lowercase();
+    SECRET_FIELDS
+        .iter()
+        .any(|secret| field == *secret || field.ends_with(&format!("_{}", secret)))
+}
+
+// A copy that's safe to keep on disk and small enough to read
+fn redact(value: &Value) -> Value {
+    match value {
+        Value::Object(fields) => Value::Object(
+            fields
+                .iter()
+                .map(|(field, value)| {
+                    let value = if is_secret(field) && !value.is_null() {
+                        Value::String("[redacted]".to_string())
+                    } else {
+                        redact(value)
+                    };
+                    (field.clone(), value)
+                })
+                .collect(),
+        ),
+        Value::Array(items) => Value::Array(items.iter().map(redact).collect()),
+        Value::String(text) if text.chars().count() > MAX_STRING_CHARS => {
+            let kept: String = text.chars().take(MAX_STRING_CHARS).collect();
+            let cut = text.chars().count() - MAX_STRING_CHARS;
+            Value::String(format!("{}… [{} more characters]", kept, cut))
+        }
+        _ => value.clone(),
+    }
+}
+
+// Called by the protocol for every call to the backend; does nothing unless recording
+pub fn record(
+    base_url: &str,
+    method: &str,
+    timeout: Duration,
+    params: &Value,
+    result: &Result<Value, CallError>,
+    outcome: RequestOutcome,
+    elapsed: Duration,
+) {
+    if !ENABLED.load(Ordering::Relaxed) {
+        return;
+    }
+    let trace = Trace {
+        id: NEXT_TRACE_ID.fetch_add(1, Ordering::Relaxed),
+        timestamp: chrono::Local::now().to_rfc3339_opts(chrono::SecondsFormat::Millis, false),
+        url: base_url.to_string(),
+        method: method.to_string(),
+        timeout_ms: timeout.as_millis() as u64,
+        params: redact(params),
+        result: result.as_ref().ok().map(redact),
+        error: result.as_ref().err().map(|e| e.to_string()),
+        outcome: outcome.name().to_string(),
+        duration_ms: elapsed.as_millis() as u64,
+    };
+
+    let mut recording = RECORDING.lock().unwrap();
+    let recording = match recording.as_mut() {
+        Some(recording) if storage::may_record(&recording.app_handle) => recording,
+        _ => return,
+    };
+    match serde_json::to_string(&trace) {
+        Ok(line) => {
+            if let Err(e) = writeln!(recording.file, "{}", line) {
+                eprintln!("Failed to write request trace to {:?}: {}", recording.path, e);
+            }
+        }
+        Err(e) => eprintln!("Failed to serialize request trace: {}", e),
+    }
+    let _ = recording.app_handle.emit_to(CONSOLE_WINDOW, "request-trace", &trace);
+    if recording.recent.len() >= RECENT_TRACES {
+        recording.recent.pop_front();
+    }
+    let failed_params = result.as_ref().err().map(|_| params.clone());
+    recording.recent.push_back(Recorded { trace, failed_params });
+}
+
+fn trace_dir(app_handle: &tauri::AppHandle) -> Result<PathBuf, String> {
+    let dir = logs::log_dir(app_handle)
+        .ok_or_else(|| "Failed to resolve log directory".to_string())?
+        .join(TRACE_DIR);
+    fs::create_dir_all(&dir).map_err(|e| format!("Failed to create {:?}: {}", dir, e))?;
+    Ok(dir)
+}
+
+fn status() -> RecordingStatus {
+    let recording = RECORDING.lock().unwrap();
+    RecordingStatus {
+        enabled: recording.is_some(),
+        path: recording.as_ref().map(|recording| recording.path.display().to_string()),
+    }
+}
+
+fn stop_recording(recording: &mut Option<Recording>) {
+    ENABLED.store(false, Ordering::Relaxed);
+    if let Some(stopped) = recording.take() {
+        println!("Stopped recording requests to {:?}", stopped.path);
+    }
+}
+
+// Stop the current recording, if any
+pub fn stop() {
+    stop_recording(&mut RECORDING.lock().unwrap());
+}
+
+// Command to start recording into a new trace file, or stop
+#[tauri::command]
+pub fn set_request_recording(app_handle: tauri::AppHandle, enabled: bool) -> Result<RecordingStatus, String> {
+    if enabled {
+        storage::check_may_record(&app_handle, "Requests can't be recorded")?;
+    }
+    let mut recording = RECORDING.lock().unwrap();
+    if !enabled {
+        stop_recording(&mut recording);
+    } else if recording.is_none() {
+        let file_name = format!("requests-{}.jsonl", chrono::Local::now().format("%Y%m%d-%H%M%S"));
+        let path = trace_dir(&app_handle)?.join(file_name);
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&path)
+            .map_err(|e| format!("Failed to create {:?}: {}", path, e))?;
+        println!("Recording requests to {:?}", path);
+        *recording = Some(Recording {
+            app_handle,
+            path,
+            file,
+            recent: VecDeque::new(),
+        });
+        ENABLED.store(true, Ordering::Relaxed);
+    }
+    drop(recording);
+    Ok(status())
+}
+
+#[tauri::command]
+pub fn get_request_recording() -> RecordingStatus {
+    status()
+}
+
+// The most recent requests of the current recording, oldest first
+#[tauri::command]
+pub fn get_request_traces(limit: Option<usize>) -> Vec<Trace> {
+    let recording = RECORDING.lock().unwrap();
+    let recent = match recording.as_ref() {
+        Some(recording) => &recording.recent,
+        None => return Vec::new(),
+    };
+    let skip = recent.len().saturating_sub(limit.unwrap_or(200));
+    recent.iter().skip(skip).map(|recorded| recorded.trace.clone()).collect()
+}
+
+// Trace files from earlier recordings, newest first
+#[tauri::command]
+pub fn list_request_trace_files(app_handle: tauri::AppHandle) -> Result<Vec<TraceFile>, String> {
+    let dir = trace_dir(&app_handle)?;
+    let entries = fs::read_dir(&dir).map_err(|e| format!("Failed to read {:?}: {}", dir, e))?;
+    let mut files: Vec<TraceFile> = entries
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.path().extension().is_some_and(|ext| ext == "jsonl"))
+        .map(|entry| TraceFile {
+            path: entry.path().display().to_string(),
+            size: entry.metadata().map(|metadata| metadata.len()).unwrap_or(0),
+        })
+        .collect();
+    // Named after when they were started
+    files.sort_by(|a, b| b.path.cmp(&a.path));
+    Ok(files)
+}
+
+pub fn read_trace_file(path: &str) -> Result<Vec<Trace>, String> {
+    let file = File::open(path).map_err(|e| format!("Failed to open {}: {}", path, e))?;
+    let mut traces = Vec::new();
+    for line in BufReader::new(file).lines() {
+        let line = line.map_err(|e| format!("Failed to read {}: {}", path, e))?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        // A line cut short by a crash is the last one and holds nothing to replay
+        match serde_json::from_str(&line) {
+            Ok(trace) => traces.push(trace),
+            Err(e) => eprintln!("Skipping unreadable line in {}: {}", path, e),
+        }
+    }
+    Ok(traces)
+}
+
+// Send a recorded request to the main backend again as it was recorded
+pub fn replay(trace: &Trace, params: Value) -> ReplayedRequest {
+    let timeout = Duration::from_millis(trace.timeout_ms.max(1));
+    let started = std::time::Instant::now();
+    let result = protocol::call_raw(backend::base_url(), &trace.method, timeout, params);
+    let replayed = protocol::outcome(&result);
+    ReplayedRequest {
+        trace_id: trace.id,
+        method: trace.method.clone(),
+        recorded: trace.outcome.clone(),
+        replayed: replayed.name().to_string(),
+        error: result.err().map(|e| e.to_string()),
+        duration_ms: started.elapsed().as_millis() as u64,
+    }
+}
+
+// Command to send every request of a trace file to the backend again, in order, for reproducing a bug.
+// Redacted fields and shortened strings are sent as they were written, so requests that need them may fail.
+#[tauri::command(async)]
+pub fn replay_request_trace(path: String) -> Result<Vec<ReplayedRequest>, String> {
+    let traces = read_trace_file(&path)?;
+    println!("Replaying {} requests from {}", traces.len(), path);
+    Ok(traces.iter().map(|trace| replay(trace, trace.params.clone())).collect())
+}
+
+// Command to send a failed request of the current recording again, e.g. to retry a flaky automation at the
+// step that failed. `params` replaces the body it was sent with, for trying out a fix.
+#[tauri::command(async)]
+pub fn replay_request(trace_id: u64, params: Option<Value>) -> Result<ReplayedRequest, String> {
+    let (trace, original) = {
+        let recording = RECORDING.lock().unwrap();
+        let recorded = recording
+            .as_ref()
+            .ok_or_else(|| "Request recording is off".to_string())?
+            .recent
+            .iter()
+            .find(|recorded| recorded.trace.id == trace_id)
+            .ok_or_else(|| format!("Request {} isn't in the current recording", trace_id))?;
+        let original = recorded
+            .failed_params
+            .clone()
+            .ok_or_else(|| format!("Request {} didn't fail", trace_id))?;
+        (recorded.trace.clone(), original)
+    };
+    println!("Replaying {} from request {}", trace.method, trace_id);
+    Ok(replay(&trace, params.unwrap_or(original)))
+}
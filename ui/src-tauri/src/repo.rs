@@ -0,0 +1,143 @@
+use git2::{ErrorCode, Repository, Sort, Status, StatusOptions};
+use serde::Serialize;
+use std::path::Path;
+
+use crate::scopes;
+
+// Enough to describe the state of a checkout without flooding a prompt
+const MAX_DIRTY_FILES: usize = 200;
+const RECENT_COMMITS: usize = 10;
+
+#[derive(Clone, Debug, Serialize)]
+pub struct DirtyFile {
+    pub path: String,
+    pub status: &'static str,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct CommitSummary {
+    pub id: String,
+    pub summary: String,
+    pub author: String,
+    // Seconds since the Unix epoch
+    pub time: i64,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct RepoContext {
+    pub root: String,
+    // None when HEAD is detached or the branch has no commits yet
+    pub branch: Option<String>,
+    pub head: Option<String>,
+    pub dirty: Vec<DirtyFile>,
+    // More files changed than are listed in `dirty`
+    pub dirty_truncated: bool,
+    pub commits: Vec<CommitSummary>,
+}
+
+fn short_id(id: git2::Oid) -> String {
+    id.to_string().chars().take(8).collect()
+}
+
+fn describe_status(status: Status) -> &'static str {
+    if status.is_conflicted() {
+        "conflicted"
+    } else if status.intersects(Status::INDEX_NEW | Status::WT_NEW) {
+        "new"
+    } else if status.intersects(Status::INDEX_DELETED | Status::WT_DELETED) {
+        "deleted"
+    } else if status.intersects(Status::INDEX_RENAMED | Status::WT_RENAMED) {
+        "renamed"
+    } else {
+        "modified"
+    }
+}
+
+fn dirty_files(repo: &Repository) -> Result<(Vec<DirtyFile>, bool), git2::Error> {
+    let mut options = StatusOptions::new();
+    options
+        .include_untracked(true)
+        .recurse_untracked_dirs(false)
+        .exclude_submodules(true);
+    let statuses = repo.statuses(Some(&mut options))?;
+
+    let dirty = statuses
+        .iter()
+        .filter(|entry| !entry.status().is_ignored())
+        .take(MAX_DIRTY_FILES)
+        .map(|entry| DirtyFile {
+            path: entry.path().unwrap_or_default().to_string(),
+            status: describe_status(entry.status()),
+        })
+        .collect::<Vec<_>>();
+    let truncated = statuses.len() > dirty.len();
+    Ok((dirty, truncated))
+}
+
+fn recent_commits(repo: &Repository) -> Result<Vec<CommitSummary>, git2::Error> {
+    let mut walk = repo.revwalk()?;
+    walk.set_sorting(Sort::TIME)?;
+    walk.push_head()?;
+
+    let mut commits = Vec::new();
+    for id in walk.take(RECENT_COMMITS) {
+        let commit = repo.find_commit(id?)?;
+        commits.push(CommitSummary {
+            id: short_id(commit.id()),
+            summary: commit.summary().unwrap_or_default().to_string(),
+            author: commit.author().name().unwrap_or_default().to_string(),
+            time: commit.time().seconds(),
+        });
+    }
+    Ok(commits)
+}
+
+// Branch, changes and history of the repository a folder belongs to; None if it isn't in one
+pub fn context(dir: &Path) -> Result<Option<RepoContext>, String> {
+    let repo = match Repository::discover(dir) {
+        Ok(repo) => repo,
+        Err(e) if e.code() == ErrorCode::NotFound => return Ok(None),
+        Err(e) => return Err(format!("Failed to open repository at {}: {}", dir.display(), e)),
+    };
+    // Bare repositories have no checkout to describe
+    let root = match repo.workdir() {
+        Some(root) => root.to_string_lossy().to_string(),
+        None => return Ok(None),
+    };
+
+    // A fresh repository has no HEAD commit yet, which is not an error here
+    let head = repo.head().ok();
+    let branch = head
+        .as_ref()
+        .filter(|head| head.is_branch())
+        .and_then(|head| head.shorthand())
+        .map(|name| name.to_string());
+    let head_id = head.as_ref().and_then(|head| head.target()).map(short_id);
+
+    let (dirty, dirty_truncated) =
+        dirty_files(&repo).map_err(|e| format!("Failed to read repository status: {}", e))?;
+    let commits = if head_id.is_some() {
+        recent_commits(&repo).map_err(|e| format!("Failed to read repository history: {}", e))?
+    } else {
+        Vec::new()
+    };
+
+    Ok(Some(RepoContext {
+        root,
+        branch,
+        head: head_id,
+        dirty,
+        dirty_truncated,
+        commits,
+    }))
+}
+
+// Context for the working directory, or for a folder inside the allowed scope
+#[tauri::command(async)]
+pub fn get_repo_context(app_handle: tauri::AppHandle, path: Option<String>) -> Result<Option<RepoContext>, String> {
+    let dir = match path {
+        Some(path) => scopes::check_path(&app_handle, &path)?,
+        None => scopes::working_directory(&app_handle).ok_or_else(|| "No working directory is set".to_string())?,
+    };
+    context(&dir)
+}
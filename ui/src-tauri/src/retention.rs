@@ -0,0 +1,336 @@
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+use tauri::Manager;
+use walkdir::WalkDir;
+
+use crate::handoff::HandoffState;
+use crate::paths::{RECORDINGS_DIR, SCREENSHOTS_DIR};
+use crate::{crash, logs, paths, settings, storage};
+
+const SUPPORT_DIR: &str = "support";
+
+const CHECK_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+// A handoff file no job holds may still be on its way to the backend for this long
+const HANDOFF_GRACE: Duration = Duration::from_secs(10 * 60);
+
+// Disposable files clean_storage can clear
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CleanKind {
+    // Everything in the cache directory, which is rebuilt as needed
+    Caches,
+    Recordings,
+    Screenshots,
+    // Files shared with the backend that no running job needs
+    Handoff,
+    // Rotated logs and script logs, but not the logs being written to
+    Logs,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct CleanedKind {
+    pub kind: CleanKind,
+    pub before: CategoryUsage,
+    pub after: CategoryUsage,
+    pub removed_files: usize,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct CleanReport {
+    pub kinds: Vec<CleanedKind>,
+    pub before_bytes: u64,
+    pub after_bytes: u64,
+    pub freed_bytes: u64,
+}
+
+struct Category {
+    name: &'static str,
+    dir: PathBuf,
+    // Whether old files here are deleted to stay under the storage limits
+    capped: bool,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct CategoryUsage {
+    pub name: String,
+    pub path: String,
+    pub bytes: u64,
+    pub files: usize,
+    pub capped: bool,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct StorageUsage {
+    pub categories: Vec<CategoryUsage>,
+    pub total_bytes: u64,
+    // What the capped categories use, against `limit_bytes`
+    pub capped_bytes: u64,
+    pub limit_bytes: u64,
+}
+
+fn categories(app_handle: &tauri::AppHandle) -> Vec<Category> {
+    let mut categories = Vec::new();
+    if let Some(dir) = logs::log_dir(app_handle) {
+        categories.push(Category {
+            name: "logs",
+            dir,
+            capped: true,
+        });
+    }
+    if let Ok(data) = storage::data_dir(app_handle) {
+        for (name, dir, capped) in [
+            ("recordings", RECORDINGS_DIR, true),
+            ("screenshots", SCREENSHOTS_DIR, true),
+            ("support_bundles", SUPPORT_DIR, true),
+            ("crash_reports", crash::CRASH_DIR, false),
+        ] {
+            categories.push(Category {
+                name,
+                dir: data.join(dir),
+                capped,
+            });
+        }
+    }
+    categories
+}
+
+// Regular files under a folder with their size and last modification time
+fn files(dir: &Path) -> Vec<(PathBuf, u64, SystemTime)> {
+    WalkDir::new(dir)
+        .into_iter()
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.file_type().is_file())
+        .filter_map(|entry| {
+            let metadata = entry.metadata().ok()?;
+            let modified = metadata.modified().unwrap_or(SystemTime::UNIX_EPOCH);
+            Some((entry.into_path(), metadata.len(), modified))
+        })
+        .collect()
+}
+
+fn usage(name: &str, dir: &Path, capped: bool) -> CategoryUsage {
+    let files = files(dir);
+    CategoryUsage {
+        name: name.to_string(),
+        path: dir.to_string_lossy().to_string(),
+        bytes: files.iter().map(|(_, size, _)| size).sum(),
+        files: files.len(),
+        capped,
+    }
+}
+
+fn limit_bytes(storage: &settings::StorageSettings) -> u64 {
+    storage.max_total_mb * 1024 * 1024
+}
+
+// The logs being written to are rotated rather than deleted
+fn is_live_log(path: &Path) -> bool {
+    path.file_name()
+        .map(|name| name == logs::APP_LOG_FILE || name == logs::BACKEND_LOG_FILE)
+        .unwrap_or(false)
+}
+
+fn remove(path: &Path) -> bool {
+    match fs::remove_file(path) {
+        Ok(()) => true,
+        Err(e) => {
+            eprintln!("Failed to remove {:?}: {}", path, e);
+            false
+        }
+    }
+}
+
+// Delete expired files, then the oldest ones until the capped folders fit the limit
+pub fn enforce_limits(app_handle: &tauri::AppHandle) {
+    let limits = settings::current(app_handle).storage;
+    let max_age = Duration::from_secs(limits.max_age_days * 24 * 60 * 60);
+    let now = SystemTime::now();
+
+    let mut live_bytes = 0;
+    let mut candidates = Vec::new();
+    for category in categories(app_handle).into_iter().filter(|c| c.capped) {
+        for (path, size, modified) in files(&category.dir) {
+            if is_live_log(&path) {
+                live_bytes += size;
+            } else {
+                candidates.push((path, size, modified));
+            }
+        }
+    }
+
+    let mut removed = 0;
+    candidates.retain(|(path, _, modified)| {
+        let expired = now.duration_since(*modified).map(|age| age > max_age).unwrap_or(false);
+        if expired && remove(path) {
+            removed += 1;
+            return false;
+        }
+        true
+    });
+
+    candidates.sort_by_key(|(_, _, modified)| *modified);
+    let mut total: u64 = live_bytes + candidates.iter().map(|(_, size, _)| size).sum::<u64>();
+    for (path, size, _) in &candidates {
+        if total <= limit_bytes(&limits) {
+            break;
+        }
+        if remove(path) {
+            total -= size;
+            removed += 1;
+        }
+    }
+    if removed > 0 {
+        println!("Removed {} old log, recording and screenshot files", removed);
+    }
+}
+
+impl CleanKind {
+    fn name(self) -> &'static str {
+        match self {
+            CleanKind::Caches => "caches",
+            CleanKind::Recordings => "recordings",
+            CleanKind::Screenshots => "screenshots",
+            CleanKind::Handoff => "handoff",
+            CleanKind::Logs => "logs",
+        }
+    }
+
+    fn dir(self, app_handle: &tauri::AppHandle) -> Result<PathBuf, String> {
+        match self {
+            CleanKind::Caches => paths::cache_dir(app_handle),
+            CleanKind::Recordings => paths::recordings_dir(app_handle),
+            CleanKind::Screenshots => paths::screenshots_dir(app_handle),
+            CleanKind::Handoff => match app_handle.try_state::<HandoffState>() {
+                Some(handoff) => Ok(handoff.dir()?.to_path_buf()),
+                None => Err("Handoff directory is not set up yet".to_string()),
+            },
+            CleanKind::Logs => paths::logs_dir(app_handle),
+        }
+    }
+
+    // Whether a file in this kind's folder has to stay, however old it is
+    fn keeps(self, app_handle: &tauri::AppHandle, path: &Path, age: Duration) -> bool {
+        match self {
+            CleanKind::Handoff => {
+                age < HANDOFF_GRACE || app_handle.state::<HandoffState>().is_held(path)
+            }
+            CleanKind::Logs => is_live_log(path),
+            _ => false,
+        }
+    }
+}
+
+// Clear the given kinds of files, only those untouched for `older_than` if it's given
+pub fn clean(
+    app_handle: &tauri::AppHandle,
+    kinds: &[CleanKind],
+    older_than: Option<Duration>,
+) -> Result<CleanReport, String> {
+    let now = SystemTime::now();
+    let mut cleaned: Vec<CleanedKind> = Vec::new();
+    for &kind in kinds {
+        if cleaned.iter().any(|c| c.kind == kind) {
+            continue;
+        }
+        let dir = kind.dir(app_handle)?;
+        let before = usage(kind.name(), &dir, false);
+        let mut removed_files = 0;
+        for (path, _, modified) in files(&dir) {
+            let age = now.duration_since(modified).unwrap_or_default();
+            if older_than.is_some_and(|min| age < min) || kind.keeps(app_handle, &path, age) {
+                continue;
+            }
+            if remove(&path) {
+                removed_files += 1;
+            }
+        }
+        cleaned.push(CleanedKind {
+            kind,
+            after: usage(kind.name(), &dir, false),
+            before,
+            removed_files,
+        });
+    }
+
+    let before_bytes = cleaned.iter().map(|c| c.before.bytes).sum();
+    let after_bytes = cleaned.iter().map(|c| c.after.bytes).sum();
+    Ok(CleanReport {
+        kinds: cleaned,
+        before_bytes,
+        after_bytes,
+        freed_bytes: before_bytes.saturating_sub(after_bytes),
+    })
+}
+
+// The cleanup policy from settings, if it's on
+fn auto_clean(app_handle: &tauri::AppHandle) {
+    let policy = settings::current(app_handle).storage.cleanup;
+    if !policy.enabled || policy.kinds.is_empty() {
+        return;
+    }
+    let older_than = Duration::from_secs(policy.older_than_days * 24 * 60 * 60);
+    match clean(app_handle, &policy.kinds, Some(older_than)) {
+        Ok(report) if report.freed_bytes > 0 => println!(
+            "Cleanup removed {} files, freeing {} bytes",
+            report.kinds.iter().map(|c| c.removed_files).sum::<usize>(),
+            report.freed_bytes
+        ),
+        Ok(_) => {}
+        Err(e) => eprintln!("Automatic cleanup failed: {}", e),
+    }
+}
+
+// Keep storage within limits now and every hour after
+pub fn start(app_handle: &tauri::AppHandle) {
+    let app_handle = app_handle.clone();
+    std::thread::spawn(move || loop {
+        enforce_limits(&app_handle);
+        auto_clean(&app_handle);
+        std::thread::sleep(CHECK_INTERVAL);
+    });
+}
+
+// Command behind "Free up space"; with no age given everything of each kind is cleared
+#[tauri::command(async)]
+pub fn clean_storage(
+    app_handle: tauri::AppHandle,
+    kinds: Vec<CleanKind>,
+    older_than_days: Option<u64>,
+) -> Result<CleanReport, String> {
+    let older_than = older_than_days.map(|days| Duration::from_secs(days * 24 * 60 * 60));
+    clean(&app_handle, &kinds, older_than)
+}
+
+#[tauri::command(async)]
+pub fn get_storage_usage(app_handle: tauri::AppHandle) -> Result<StorageUsage, String> {
+    let data = storage::data_dir(&app_handle)?;
+    let mut categories: Vec<CategoryUsage> = categories(&app_handle)
+        .iter()
+        .map(|category| usage(category.name, &category.dir, category.capped))
+        .collect();
+
+    // Everything else in the data directory: history, settings, indexes, handoff files
+    let (categorized_bytes, categorized_files) = categories
+        .iter()
+        .filter(|category| Path::new(&category.path).starts_with(&data))
+        .fold((0, 0), |(bytes, files), category| (bytes + category.bytes, files + category.files));
+    let mut other = usage("other", &data, false);
+    other.bytes = other.bytes.saturating_sub(categorized_bytes);
+    other.files = other.files.saturating_sub(categorized_files);
+    categories.push(other);
+
+    let limits = settings::current(&app_handle).storage;
+    Ok(StorageUsage {
+        total_bytes: categories.iter().map(|category| category.bytes).sum(),
+        capped_bytes: categories
+            .iter()
+            .filter(|category| category.capped)
+            .map(|category| category.bytes)
+            .sum(),
+        limit_bytes: limit_bytes(&limits),
+        categories,
+    })
+}
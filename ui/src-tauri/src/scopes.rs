@@ -0,0 +1,191 @@
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use tauri::{Emitter, Manager};
+use tauri_plugin_dialog::DialogExt;
+
+use crate::protocol::{self, WorkingDirectoryParams};
+use crate::{policy, settings, storage};
+
+// Credential stores that are never handed to the backend, even inside an allowed folder
+const PROTECTED_PATHS: &[&str] = &["~/.ssh", "~/.gnupg", "~/.aws", "~/.kube", "~/.docker", "~/.config/gcloud"];
+
+// Capabilities that outside callers (e.g. MCP clients) need the user's explicit grant for
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Scope {
+    Screenshot,
+    InputInjection,
+    AppLaunch,
+    // Not for outside callers, but granted the same way: automations starting without a prompt
+    Triggers,
+}
+
+impl Scope {
+    pub const ALL: &'static [Scope] = &[Scope::Screenshot, Scope::InputInjection, Scope::AppLaunch, Scope::Triggers];
+
+    pub fn description(&self) -> &'static str {
+        match self {
+            Scope::Screenshot => "Capture the screen",
+            Scope::InputInjection => "Type into other applications",
+            Scope::AppLaunch => "Open installed applications",
+            Scope::Triggers => "Run saved automations when system events happen",
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct ScopeStatus {
+    pub scope: Scope,
+    pub granted: bool,
+    // Set when the policy decides which scopes are granted
+    pub locked: bool,
+    pub description: String,
+}
+
+pub fn is_granted(app_handle: &tauri::AppHandle, scope: Scope) -> bool {
+    settings::current(app_handle).permissions.granted.contains(&scope)
+}
+
+pub fn require(app_handle: &tauri::AppHandle, scope: Scope) -> Result<(), String> {
+    if is_granted(app_handle, scope) {
+        Ok(())
+    } else {
+        Err(format!("Permission not granted: {}", scope.description()))
+    }
+}
+
+// Folder this session's file work is confined to, on top of the allowed folders; not persisted
+#[derive(Default)]
+pub struct WorkingDirectory(Mutex<Option<PathBuf>>);
+
+#[derive(Clone, Debug, Serialize)]
+struct WorkingDirectoryChanged {
+    path: Option<String>,
+}
+
+pub fn working_directory(app_handle: &tauri::AppHandle) -> Option<PathBuf> {
+    app_handle.state::<WorkingDirectory>().0.lock().unwrap().clone()
+}
+
+// Resolve a path (following symlinks) and check it's inside an allowed folder and not protected
+fn resolve_allowed(app_handle: &tauri::AppHandle, path: &str) -> Result<PathBuf, String> {
+    let canonical = |path: PathBuf| fs::canonicalize(&path).unwrap_or(path);
+    let resolved =
+        fs::canonicalize(settings::expand_home(path)).map_err(|e| format!("Cannot access {}: {}", path, e))?;
+
+    let allowed = settings::current(app_handle)
+        .permissions
+        .file_roots
+        .iter()
+        .map(|root| canonical(settings::expand_home(root)))
+        .any(|root| resolved.starts_with(root));
+    if !allowed {
+        return Err(format!("{} is outside the folders Krya.ai may access", resolved.display()));
+    }
+
+    // The app's own data holds secrets and other jobs' handoff files
+    let protected = PROTECTED_PATHS
+        .iter()
+        .map(|p| settings::expand_home(p))
+        .chain(storage::data_dir(app_handle).ok())
+        .map(canonical)
+        .any(|protected| resolved.starts_with(protected));
+    if protected {
+        return Err(format!("{} is in a protected location", resolved.display()));
+    }
+    Ok(resolved)
+}
+
+// Like resolve_allowed, but also confined to the working directory when one is set
+pub fn check_path(app_handle: &tauri::AppHandle, path: &str) -> Result<PathBuf, String> {
+    let resolved = resolve_allowed(app_handle, path)?;
+    match working_directory(app_handle) {
+        Some(dir) if !resolved.starts_with(&dir) => Err(format!(
+            "{} is outside the working directory {}",
+            resolved.display(),
+            dir.display()
+        )),
+        _ => Ok(resolved),
+    }
+}
+
+fn display(path: Option<&Path>) -> Option<String> {
+    path.map(|p| p.to_string_lossy().to_string())
+}
+
+// Tell the backend which folder its scripts may work in; also needed after it restarts
+pub fn sync_working_directory(app_handle: &tauri::AppHandle) -> Result<(), String> {
+    let params = WorkingDirectoryParams {
+        path: display(working_directory(app_handle).as_deref()),
+    };
+    protocol::call::<protocol::SetWorkingDirectory>(&params)?.into_result()
+}
+
+fn change_working_directory(app_handle: &tauri::AppHandle, dir: Option<PathBuf>) -> Result<Option<String>, String> {
+    let path = display(dir.as_deref());
+    *app_handle.state::<WorkingDirectory>().0.lock().unwrap() = dir;
+    if let Err(e) = app_handle.emit("working-directory-changed", WorkingDirectoryChanged { path: path.clone() }) {
+        eprintln!("Failed to emit working-directory-changed: {}", e);
+    }
+    sync_working_directory(app_handle)?;
+    Ok(path)
+}
+
+// Confine file work to a folder, asking for one with the native picker when no path is given.
+// Returns the active working directory, unchanged if the picker was cancelled.
+#[tauri::command(async)]
+pub fn set_working_directory(app_handle: tauri::AppHandle, path: Option<String>) -> Result<Option<String>, String> {
+    let path = match path {
+        Some(path) => path,
+        None => match app_handle.dialog().file()
+            .set_title("Choose a working directory")
+            .blocking_pick_folder()
+        {
+            Some(path) => path.to_string(),
+            None => return Ok(display(working_directory(&app_handle).as_deref())),
+        },
+    };
+
+    let dir = resolve_allowed(&app_handle, &path)?;
+    if !dir.is_dir() {
+        return Err(format!("{} is not a folder", dir.display()));
+    }
+    change_working_directory(&app_handle, Some(dir))
+}
+
+#[tauri::command(async)]
+pub fn clear_working_directory(app_handle: tauri::AppHandle) -> Result<(), String> {
+    change_working_directory(&app_handle, None).map(|_| ())
+}
+
+#[tauri::command]
+pub fn get_working_directory(app_handle: tauri::AppHandle) -> Option<String> {
+    display(working_directory(&app_handle).as_deref())
+}
+
+#[tauri::command]
+pub fn get_permission_scopes(app_handle: tauri::AppHandle) -> Vec<ScopeStatus> {
+    let granted = settings::current(&app_handle).permissions.granted;
+    Scope::ALL
+        .iter()
+        .map(|scope| ScopeStatus {
+            scope: *scope,
+            granted: granted.contains(scope),
+            locked: policy::get().granted_scopes.is_some(),
+            description: scope.description().to_string(),
+        })
+        .collect()
+}
+
+#[tauri::command]
+pub fn set_permission_scope(app_handle: tauri::AppHandle, scope: Scope, granted: bool) -> Result<(), String> {
+    policy::check_unlocked("permissions.granted")?;
+    let mut current = settings::current(&app_handle);
+    current.permissions.granted.retain(|existing| *existing != scope);
+    if granted {
+        current.permissions.granted.push(scope);
+    }
+    settings::replace(&app_handle, current)
+}
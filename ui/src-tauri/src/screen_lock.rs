@@ -0,0 +1,260 @@
+use serde::Serialize;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+use tauri::{Emitter, Manager};
+use tauri_plugin_dialog::{DialogExt, MessageDialogButtons, MessageDialogKind};
+
+use crate::{automation, input, jobs};
+
+// Short, since a job could be typing when the screen locks
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+// Why this session can't be typed into
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LockReason {
+    Locked,
+    // Another user's session is on the screen
+    SwitchedAway,
+}
+
+impl LockReason {
+    fn description(&self) -> &'static str {
+        match self {
+            LockReason::Locked => "the screen was locked",
+            LockReason::SwitchedAway => "another user's session took over the screen",
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct LockStatus {
+    pub locked: bool,
+    pub reason: Option<LockReason>,
+}
+
+#[derive(Default)]
+pub struct ScreenLockState {
+    // From the lock until the user agrees to go on, which may be well after unlocking
+    suspended: AtomicBool,
+    // Set when we paused automation, so we only resume what we paused
+    paused_automation: AtomicBool,
+}
+
+#[cfg(target_os = "windows")]
+fn os_reason() -> Option<LockReason> {
+    use windows::Win32::Foundation::FALSE;
+    use windows::Win32::System::RemoteDesktop::{ProcessIdToSessionId, WTSGetActiveConsoleSessionId};
+    use windows::Win32::System::StationsAndDesktops::{
+        CloseDesktop, OpenInputDesktop, DESKTOP_CONTROL_FLAGS, DESKTOP_SWITCHDESKTOP,
+    };
+
+    let mut session = 0;
+    if unsafe { ProcessIdToSessionId(std::process::id(), &mut session) }.is_ok()
+        && unsafe { WTSGetActiveConsoleSessionId() } != session
+    {
+        return Some(LockReason::SwitchedAway);
+    }
+    // The lock screen runs on its own desktop, which a user's process can't switch to
+    match unsafe { OpenInputDesktop(DESKTOP_CONTROL_FLAGS(0), FALSE, DESKTOP_SWITCHDESKTOP) } {
+        Ok(desktop) => {
+            let _ = unsafe { CloseDesktop(desktop) };
+            None
+        }
+        Err(_) => Some(LockReason::Locked),
+    }
+}
+
+#[cfg(target_os = "macos")]
+fn os_reason() -> Option<LockReason> {
+    use cocoa::base::{id, nil};
+    use cocoa::foundation::NSString;
+    use objc::{msg_send, sel, sel_impl};
+
+    #[link(name = "CoreGraphics", kind = "framework")]
+    extern "C" {
+        // A CFDictionary, which is an NSDictionary as far as objc is concerned
+        fn CGSessionCopyCurrentDictionary() -> id;
+    }
+
+    unsafe {
+        let session = CGSessionCopyCurrentDictionary();
+        if session == nil {
+            return None;
+        }
+        let flag = |key: &str| -> bool {
+            let key = NSString::alloc(nil).init_str(key);
+            let value: id = msg_send![session, objectForKey: key];
+            let _: () = msg_send![key, release];
+            value != nil && msg_send![value, boolValue]
+        };
+        let reason = if !flag("kCGSSessionOnConsoleKey") {
+            Some(LockReason::SwitchedAway)
+        } else if flag("CGSSessionScreenIsLocked") {
+            Some(LockReason::Locked)
+        } else {
+            None
+        };
+        let _: () = msg_send![session, release];
+        reason
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn os_reason() -> Option<LockReason> {
+    use gtk::gio;
+    use gtk::glib::{ToVariant, Variant};
+
+    fn property(connection: &gio::DBusConnection, name: &str) -> Option<bool> {
+        connection
+            .call_sync(
+                Some("org.freedesktop.login1"),
+                "/org/freedesktop/login1/session/auto",
+                "org.freedesktop.DBus.Properties",
+                "Get",
+                Some(&("org.freedesktop.login1.Session", name).to_variant()),
+                None,
+                gio::DBusCallFlags::NONE,
+                1000,
+                gio::Cancellable::NONE,
+            )
+            .ok()
+            .and_then(|reply| reply.get::<(Variant,)>())
+            .and_then(|(value,)| value.get::<bool>())
+    }
+
+    // logind knows about both, on any desktop that uses it
+    if let Ok(system) = gio::bus_get_sync(gio::BusType::System, gio::Cancellable::NONE) {
+        if property(&system, "Active") == Some(false) {
+            return Some(LockReason::SwitchedAway);
+        }
+        if let Some(locked) = property(&system, "LockedHint") {
+            return if locked { Some(LockReason::Locked) } else { None };
+        }
+    }
+
+    // Otherwise ask the screen saver; don't start one just to ask
+    let session = gio::bus_get_sync(gio::BusType::Session, gio::Cancellable::NONE).ok()?;
+    let active = session
+        .call_sync(
+            Some("org.freedesktop.ScreenSaver"),
+            "/org/freedesktop/ScreenSaver",
+            "org.freedesktop.ScreenSaver",
+            "GetActive",
+            None,
+            None,
+            gio::DBusCallFlags::NO_AUTO_START,
+            1000,
+            gio::Cancellable::NONE,
+        )
+        .ok()
+        .and_then(|reply| reply.get::<(bool,)>())
+        .is_some_and(|(active,)| active);
+    if active {
+        Some(LockReason::Locked)
+    } else {
+        None
+    }
+}
+
+#[cfg(not(any(target_os = "windows", target_os = "macos", target_os = "linux")))]
+fn os_reason() -> Option<LockReason> {
+    None
+}
+
+fn emit(app_handle: &tauri::AppHandle, reason: Option<LockReason>) {
+    let status = LockStatus {
+        locked: reason.is_some(),
+        reason,
+    };
+    if let Err(e) = app_handle.emit("screen-lock-changed", status) {
+        eprintln!("Failed to emit screen-lock-changed: {}", e);
+    }
+}
+
+// Nothing may type into a lock screen or someone else's session: stop what's running, refuse new jobs
+fn lock(app_handle: &tauri::AppHandle, reason: LockReason) {
+    let state = app_handle.state::<ScreenLockState>();
+    if state.suspended.swap(true, Ordering::SeqCst) {
+        return;
+    }
+    println!("Session locked ({:?}); suspending automation", reason);
+    input::suspend(true);
+    jobs::stop_all(app_handle, &format!("Stopped because {}", reason.description()));
+    if !automation::is_paused(app_handle) {
+        state.paused_automation.store(true, Ordering::SeqCst);
+        if let Err(e) = automation::set_paused(app_handle, true) {
+            eprintln!("Failed to pause automation for the screen lock: {}", e);
+        }
+    }
+    emit(app_handle, Some(reason));
+}
+
+// Let input and jobs go on again after the user confirmed it
+fn resume(app_handle: &tauri::AppHandle) {
+    let state = app_handle.state::<ScreenLockState>();
+    if !state.suspended.swap(false, Ordering::SeqCst) {
+        return;
+    }
+    println!("Resuming automation after the screen lock");
+    input::suspend(false);
+    if state.paused_automation.swap(false, Ordering::SeqCst) {
+        if let Err(e) = automation::set_paused(app_handle, false) {
+            eprintln!("Failed to resume automation after the screen lock: {}", e);
+        }
+    }
+}
+
+// Back in the session, but the user still has to say automation may go on
+fn unlock(app_handle: &tauri::AppHandle) {
+    println!("Session unlocked");
+    emit(app_handle, None);
+
+    let resume = app_handle
+        .dialog()
+        .message(
+            "Automation was stopped while your session was locked. Let Krya.ai control the keyboard and mouse again?",
+        )
+        .title("Krya.ai paused automation")
+        .kind(MessageDialogKind::Info)
+        .buttons(MessageDialogButtons::OkCancelCustom(
+            "Resume".to_string(),
+            "Keep Paused".to_string(),
+        ))
+        .blocking_show();
+    // Otherwise it stays suspended until resumed from the app
+    if resume {
+        self::resume(app_handle);
+    }
+}
+
+// Watch for the screen locking or another user switching in, in the background
+pub fn start_watching(app_handle: &tauri::AppHandle) {
+    let app_handle = app_handle.clone();
+    std::thread::spawn(move || {
+        let mut last = None;
+        loop {
+            let reason = os_reason();
+            if reason != last {
+                match reason {
+                    Some(reason) => lock(&app_handle, reason),
+                    None => unlock(&app_handle),
+                }
+                last = reason;
+            }
+            std::thread::sleep(POLL_INTERVAL);
+        }
+    });
+}
+
+// Command for the frontend to show that automation is suspended after a reload
+#[tauri::command]
+pub fn is_suspended_by_screen_lock(state: tauri::State<ScreenLockState>) -> bool {
+    state.suspended.load(Ordering::SeqCst)
+}
+
+// Command for resuming later when the user kept automation paused on unlock
+#[tauri::command(async)]
+pub fn resume_after_screen_lock(app_handle: tauri::AppHandle) {
+    resume(&app_handle);
+}
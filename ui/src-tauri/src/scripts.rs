@@ -0,0 +1,383 @@
+use enigo::{Axis, Button, Coordinate, Direction, Enigo, Key, Keyboard, Mouse};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+use tauri::Manager;
+
+use crate::protocol::{self, JobParams};
+use crate::util::now_secs;
+use crate::{flags, input, storage};
+
+const STORE_FILE: &str = "automation_scripts.json";
+
+// Every finished job leaves a script behind; older ones are dropped
+const MAX_SCRIPTS: usize = 50;
+
+// pyautogui pauses this long after each call, and some applications rely on it
+const STEP_INTERVAL: Duration = Duration::from_millis(100);
+
+// One concrete action of a job, as recorded by the backend (functions/steps.py)
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(tag = "action", rename_all = "snake_case")]
+pub enum ScriptStep {
+    Click {
+        x: i32,
+        y: i32,
+        #[serde(default = "left")]
+        button: String,
+        #[serde(default = "once")]
+        clicks: u32,
+    },
+    Move {
+        x: i32,
+        y: i32,
+    },
+    Drag {
+        x: i32,
+        y: i32,
+        #[serde(default = "left")]
+        button: String,
+    },
+    Type {
+        text: String,
+    },
+    // Keys pressed one after the other
+    Press {
+        keys: Vec<String>,
+    },
+    // Keys held down together, e.g. ["ctrl", "s"]
+    Hotkey {
+        keys: Vec<String>,
+    },
+    KeyDown {
+        key: String,
+    },
+    KeyUp {
+        key: String,
+    },
+    // Positive amounts scroll up, as in pyautogui
+    Scroll {
+        amount: i32,
+    },
+    Wait {
+        seconds: f64,
+    },
+    // A file the script read or wrote; shown for reference, replaying doesn't touch it
+    File {
+        path: String,
+        access: String,
+    },
+}
+
+fn left() -> String {
+    "left".to_string()
+}
+
+fn once() -> u32 {
+    1
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct AutomationScript {
+    pub id: String,
+    pub name: String,
+    #[serde(default)]
+    pub job_id: Option<String>,
+    #[serde(default)]
+    pub prompt: Option<String>,
+    pub created: u64,
+    pub steps: Vec<ScriptStep>,
+}
+
+pub struct ScriptsState {
+    scripts: Mutex<Vec<AutomationScript>>,
+    playing: AtomicBool,
+    cancel: AtomicBool,
+}
+
+impl ScriptsState {
+    pub fn load(app_handle: &tauri::AppHandle) -> Self {
+        ScriptsState {
+            scripts: Mutex::new(storage::load_json(app_handle, STORE_FILE)),
+            playing: AtomicBool::new(false),
+            cancel: AtomicBool::new(false),
+        }
+    }
+}
+
+fn save(app_handle: &tauri::AppHandle, scripts: &[AutomationScript], data: storage::Data) -> Result<(), String> {
+    storage::save_json(app_handle, STORE_FILE, &scripts, data)
+}
+
+// Steps from a newer backend that this version doesn't know are left out rather than failing the whole script
+fn parse_steps(steps: Vec<Value>) -> Vec<ScriptStep> {
+    steps
+        .into_iter()
+        .filter_map(|step| match serde_json::from_value(step.clone()) {
+            Ok(step) => Some(step),
+            Err(e) => {
+                eprintln!("Skipping recorded step {}: {}", step, e);
+                None
+            }
+        })
+        .collect()
+}
+
+// Keep the actions of a finished job as a script, named after its prompt; none are kept in privacy mode
+pub fn job_finished(app_handle: &tauri::AppHandle, job_id: &str) {
+    if !storage::may_record(app_handle) {
+        return;
+    }
+    let job = match protocol::call::<protocol::GetJob>(&JobParams {
+        job_id: job_id.to_string(),
+    }) {
+        Ok(job) => job,
+        Err(e) => return eprintln!("Failed to fetch the steps of job {}: {}", job_id, e),
+    };
+    let steps = parse_steps(job.steps);
+    if steps.is_empty() {
+        return;
+    }
+
+    let name = job
+        .prompt
+        .as_deref()
+        .map(|prompt| prompt.lines().next().unwrap_or_default().chars().take(60).collect::<String>())
+        .filter(|name| !name.trim().is_empty())
+        .unwrap_or_else(|| format!("Job {}", job_id));
+    let script = AutomationScript {
+        id: uuid::Uuid::new_v4().to_string(),
+        name,
+        job_id: Some(job_id.to_string()),
+        prompt: job.prompt,
+        created: now_secs(),
+        steps,
+    };
+
+    let state = app_handle.state::<ScriptsState>();
+    let mut scripts = state.scripts.lock().unwrap();
+    scripts.retain(|existing| existing.job_id.as_deref() != Some(job_id));
+    scripts.push(script);
+    let excess = scripts.len().saturating_sub(MAX_SCRIPTS);
+    scripts.drain(..excess);
+    if let Err(e) = save(app_handle, &scripts, storage::Data::Activity) {
+        eprintln!("{}", e);
+    }
+}
+
+// pyautogui's key names ("enter", "ctrl", "command", "pgdn", "a") as enigo keys
+fn key(name: &str) -> Result<Key, String> {
+    let lower = name.to_lowercase();
+    let key = match lower.as_str() {
+        "enter" | "return" => Key::Return,
+        "tab" => Key::Tab,
+        "space" => Key::Space,
+        "esc" | "escape" => Key::Escape,
+        "backspace" => Key::Backspace,
+        "delete" | "del" => Key::Delete,
+        "up" => Key::UpArrow,
+        "down" => Key::DownArrow,
+        "left" => Key::LeftArrow,
+        "right" => Key::RightArrow,
+        "home" => Key::Home,
+        "end" => Key::End,
+        "pageup" | "pgup" => Key::PageUp,
+        "pagedown" | "pgdn" => Key::PageDown,
+        "shift" | "shiftleft" | "shiftright" => Key::Shift,
+        "ctrl" | "ctrlleft" | "ctrlright" => Key::Control,
+        "alt" | "altleft" | "altright" | "option" | "optionleft" | "optionright" => Key::Alt,
+        "command" | "cmd" | "win" | "winleft" | "winright" | "super" => Key::Meta,
+        "capslock" => Key::CapsLock,
+        "f1" => Key::F1,
+        "f2" => Key::F2,
+        "f3" => Key::F3,
+        "f4" => Key::F4,
+        "f5" => Key::F5,
+        "f6" => Key::F6,
+        "f7" => Key::F7,
+        "f8" => Key::F8,
+        "f9" => Key::F9,
+        "f10" => Key::F10,
+        "f11" => Key::F11,
+        "f12" => Key::F12,
+        _ => {
+            let mut chars = name.chars();
+            match (chars.next(), chars.next()) {
+                (Some(c), None) => Key::Unicode(c),
+                _ => return Err(format!("Unknown key: {}", name)),
+            }
+        }
+    };
+    Ok(key)
+}
+
+fn button(name: &str) -> Result<Button, String> {
+    match name {
+        "left" | "primary" => Ok(Button::Left),
+        "right" | "secondary" => Ok(Button::Right),
+        "middle" => Ok(Button::Middle),
+        _ => Err(format!("Unknown mouse button: {}", name)),
+    }
+}
+
+// Perform one step; keys it holds down are added to `held` until it lets go of them
+fn perform(enigo: &mut Enigo, step: &ScriptStep, held: &mut Vec<Key>) -> Result<(), String> {
+    let failed = |e| format!("Failed to replay {:?}: {}", step, e);
+    match step {
+        ScriptStep::Click { x, y, button: name, clicks } => {
+            let button = button(name)?;
+            enigo.move_mouse(*x, *y, Coordinate::Abs).map_err(failed)?;
+            for _ in 0..*clicks {
+                enigo.button(button, Direction::Click).map_err(failed)?;
+            }
+        }
+        ScriptStep::Move { x, y } => enigo.move_mouse(*x, *y, Coordinate::Abs).map_err(failed)?,
+        ScriptStep::Drag { x, y, button: name } => {
+            let button = button(name)?;
+            enigo.button(button, Direction::Press).map_err(failed)?;
+            let moved = enigo.move_mouse(*x, *y, Coordinate::Abs).map_err(failed);
+            enigo.button(button, Direction::Release).map_err(failed)?;
+            moved?;
+        }
+        ScriptStep::Type { text } => enigo.text(text).map_err(failed)?,
+        ScriptStep::Press { keys } => {
+            for name in keys {
+                enigo.key(key(name)?, Direction::Click).map_err(failed)?;
+            }
+        }
+        ScriptStep::Hotkey { keys } => {
+            let keys = keys.iter().map(|name| key(name)).collect::<Result<Vec<Key>, String>>()?;
+            let mut pressed = Vec::new();
+            let mut result = Ok(());
+            for key in &keys {
+                result = enigo.key(*key, Direction::Press).map_err(failed);
+                if result.is_err() {
+                    break;
+                }
+                pressed.push(*key);
+            }
+            for key in pressed.iter().rev() {
+                let _ = enigo.key(*key, Direction::Release);
+            }
+            result?;
+        }
+        ScriptStep::KeyDown { key: name } => {
+            let key = key(name)?;
+            enigo.key(key, Direction::Press).map_err(failed)?;
+            held.push(key);
+        }
+        ScriptStep::KeyUp { key: name } => {
+            let key = key(name)?;
+            enigo.key(key, Direction::Release).map_err(failed)?;
+            held.retain(|k| *k != key);
+        }
+        ScriptStep::Scroll { amount } => enigo.scroll(-amount, Axis::Vertical).map_err(failed)?,
+        ScriptStep::Wait { seconds } => {
+            std::thread::sleep(Duration::from_secs_f64(seconds.max(0.0)));
+        }
+        ScriptStep::File { .. } => {}
+    }
+    Ok(())
+}
+
+fn find(app_handle: &tauri::AppHandle, id: &str) -> Result<AutomationScript, String> {
+    app_handle
+        .state::<ScriptsState>()
+        .scripts
+        .lock()
+        .unwrap()
+        .iter()
+        .find(|script| script.id == id)
+        .cloned()
+        .ok_or_else(|| format!("Script not found: {}", id))
+}
+
+#[tauri::command]
+pub fn list_scripts(state: tauri::State<ScriptsState>) -> Vec<AutomationScript> {
+    state.scripts.lock().unwrap().clone()
+}
+
+#[tauri::command]
+pub fn get_script(app_handle: tauri::AppHandle, id: String) -> Result<AutomationScript, String> {
+    find(&app_handle, &id)
+}
+
+// Create or update a script, e.g. after fixing a coordinate or the text it types
+#[tauri::command]
+pub fn save_script(
+    app_handle: tauri::AppHandle,
+    state: tauri::State<ScriptsState>,
+    mut script: AutomationScript,
+) -> Result<AutomationScript, String> {
+    storage::check_may_record(&app_handle, "Scripts can't be saved")?;
+    let mut scripts = state.scripts.lock().unwrap();
+    if script.id.is_empty() {
+        script.id = uuid::Uuid::new_v4().to_string();
+    }
+    match scripts.iter_mut().find(|existing| existing.id == script.id) {
+        Some(existing) => *existing = script.clone(),
+        None => scripts.push(script.clone()),
+    }
+    save(&app_handle, &scripts, storage::Data::Activity)?;
+    Ok(script)
+}
+
+#[tauri::command]
+pub fn delete_script(
+    app_handle: tauri::AppHandle,
+    state: tauri::State<ScriptsState>,
+    id: String,
+) -> Result<(), String> {
+    let mut scripts = state.scripts.lock().unwrap();
+    scripts.retain(|script| script.id != id);
+    save(&app_handle, &scripts, storage::Data::Removal)
+}
+
+// Command to perform a job's recorded actions again without asking the model; `speed` 2.0 halves the waits
+#[tauri::command(async)]
+pub fn replay_script(app_handle: tauri::AppHandle, id: String, speed: Option<f64>) -> Result<(), String> {
+    if !flags::is_enabled(&app_handle, flags::NATIVE_INPUT_INJECTION) {
+        return Err("Native input injection is disabled".to_string());
+    }
+    input::check_target(&app_handle)?;
+    let script = find(&app_handle, &id)?;
+    let state = app_handle.state::<ScriptsState>();
+    if state.playing.swap(true, Ordering::SeqCst) {
+        return Err("A script is already being replayed".to_string());
+    }
+    state.cancel.store(false, Ordering::SeqCst);
+
+    let speed = speed.filter(|speed| *speed > 0.0).unwrap_or(1.0);
+    let result = input::inject(&app_handle, |enigo| {
+        let mut held = Vec::new();
+        let mut result = Ok(());
+        for step in &script.steps {
+            if state.cancel.load(Ordering::SeqCst) {
+                break;
+            }
+            let step = match step {
+                ScriptStep::Wait { seconds } => ScriptStep::Wait { seconds: seconds / speed },
+                step => step.clone(),
+            };
+            if let Err(e) = input::check_target(&app_handle).and_then(|_| perform(enigo, &step, &mut held)) {
+                result = Err(e);
+                break;
+            }
+            std::thread::sleep(STEP_INTERVAL.div_f64(speed));
+        }
+        // Don't leave a key the script held down pressed when it's stopped or fails part way
+        for key in held.iter().rev() {
+            let _ = enigo.key(*key, Direction::Release);
+        }
+        result
+    });
+    state.playing.store(false, Ordering::SeqCst);
+    result
+}
+
+#[tauri::command]
+pub fn stop_script_replay(state: tauri::State<ScriptsState>) {
+    state.cancel.store(true, Ordering::SeqCst);
+}
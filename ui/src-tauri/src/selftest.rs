@@ -0,0 +1,253 @@
+use serde::Serialize;
+use serde_json::Value;
+use std::time::{Duration, Instant};
+use tungstenite::stream::MaybeTlsStream;
+use tungstenite::Message;
+
+use crate::protocol::{self, JobParams, NoParams};
+use crate::{backend, crash, handshake, AppState};
+
+// How long to wait for each stage before calling it failed
+const BACKEND_START_TIMEOUT: Duration = Duration::from_secs(30);
+const STREAM_TIMEOUT: Duration = Duration::from_secs(30);
+const JOB_TIMEOUT: Duration = Duration::from_secs(90);
+
+#[derive(Clone, Debug, Serialize)]
+pub struct StageResult {
+    pub name: String,
+    pub ok: bool,
+    pub detail: String,
+    pub duration_ms: u64,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct SelfTestReport {
+    pub ok: bool,
+    pub stages: Vec<StageResult>,
+}
+
+// Run one stage and record how it went
+fn run_stage<F>(stages: &mut Vec<StageResult>, name: &str, f: F) -> bool
+where
+    F: FnOnce() -> Result<String, String>,
+{
+    let started = Instant::now();
+    let (ok, detail) = match f() {
+        Ok(detail) => (true, detail),
+        Err(detail) => (false, detail),
+    };
+    stages.push(StageResult {
+        name: name.to_string(),
+        ok,
+        detail,
+        duration_ms: started.elapsed().as_millis() as u64,
+    });
+    ok
+}
+
+fn skip_stage(stages: &mut Vec<StageResult>, name: &str, reason: &str) {
+    stages.push(StageResult {
+        name: name.to_string(),
+        ok: false,
+        detail: format!("Skipped: {}", reason),
+        duration_ms: 0,
+    });
+}
+
+// Make sure the API server answers, starting it if it isn't running
+fn ensure_backend(app_handle: &tauri::AppHandle, app_state: &tauri::State<AppState>) -> Result<String, String> {
+    if let Ok(ping) = protocol::call::<protocol::Ping>(&NoParams {}) {
+        return Ok(format!("{} is {}", ping.service, ping.status));
+    }
+
+    let was_running = *app_state.api_server_running.lock().unwrap();
+    crate::start_api_server(app_handle, app_state)?;
+    if !was_running {
+        crash::watch_backend(app_handle, app_state.inner().clone());
+        handshake::negotiate(app_handle, app_state.inner().clone());
+    }
+
+    let started = Instant::now();
+    while started.elapsed() < BACKEND_START_TIMEOUT {
+        if protocol::call::<protocol::Ping>(&NoParams {}).is_ok() {
+            return Ok(format!("API server started in {:.1}s", started.elapsed().as_secs_f32()));
+        }
+        std::thread::sleep(Duration::from_millis(500));
+    }
+    Err(format!("API server did not respond within {}s", BACKEND_START_TIMEOUT.as_secs()))
+}
+
+fn check_doctor() -> Result<String, String> {
+    let report = protocol::call::<protocol::Doctor>(&NoParams {})?;
+    let failed: Vec<String> = report
+        .get("checks")
+        .and_then(|c| c.as_array())
+        .map(|checks| {
+            checks
+                .iter()
+                .filter(|check| !check.get("ok").and_then(|ok| ok.as_bool()).unwrap_or(false))
+                .map(|check| {
+                    format!(
+                        "{} ({})",
+                        check.get("name").and_then(|n| n.as_str()).unwrap_or("unknown"),
+                        check.get("detail").and_then(|d| d.as_str()).unwrap_or("")
+                    )
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+
+    if failed.is_empty() {
+        Ok("All backend checks passed".to_string())
+    } else {
+        Err(format!("Failed checks: {}", failed.join(", ")))
+    }
+}
+
+#[cfg(target_os = "macos")]
+fn check_permissions() -> Result<String, String> {
+    #[link(name = "ApplicationServices", kind = "framework")]
+    extern "C" {
+        fn AXIsProcessTrusted() -> u8;
+    }
+    #[link(name = "CoreGraphics", kind = "framework")]
+    extern "C" {
+        fn CGPreflightScreenCaptureAccess() -> bool;
+    }
+
+    let mut missing = Vec::new();
+    if unsafe { AXIsProcessTrusted() } == 0 {
+        missing.push("Accessibility");
+    }
+    if !unsafe { CGPreflightScreenCaptureAccess() } {
+        missing.push("Screen Recording");
+    }
+
+    if missing.is_empty() {
+        Ok("Accessibility and Screen Recording granted".to_string())
+    } else {
+        Err(format!(
+            "Not granted: {} (System Settings > Privacy & Security)",
+            missing.join(", ")
+        ))
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn check_permissions() -> Result<String, String> {
+    match std::env::var("XDG_SESSION_TYPE") {
+        Ok(session) if session.eq_ignore_ascii_case("wayland") => {
+            Err("Wayland session: global input and screen capture may be blocked".to_string())
+        }
+        _ => Ok("No extra permissions required".to_string()),
+    }
+}
+
+#[cfg(not(any(target_os = "macos", target_os = "linux")))]
+fn check_permissions() -> Result<String, String> {
+    Ok("No extra permissions required".to_string())
+}
+
+type LogSocket = tungstenite::WebSocket<MaybeTlsStream<std::net::TcpStream>>;
+
+fn connect_log_stream() -> Result<LogSocket, String> {
+    let url = format!("{}/logs", backend::base_url().replacen("http", "ws", 1));
+    let (socket, _) = tungstenite::connect(url).map_err(|e| format!("Failed to connect to log stream: {}", e))?;
+    if let MaybeTlsStream::Plain(stream) = socket.get_ref() {
+        stream
+            .set_read_timeout(Some(Duration::from_secs(1)))
+            .map_err(|e| format!("Failed to configure log stream: {}", e))?;
+    }
+    Ok(socket)
+}
+
+fn submit_job() -> Result<String, String> {
+    protocol::call::<protocol::SelfTest>(&NoParams {}).map(|submitted| submitted.job_id)
+}
+
+// Wait for a log entry for the job to arrive over the websocket
+fn wait_for_stream(socket: &mut LogSocket, job_id: &str) -> Result<String, String> {
+    let started = Instant::now();
+    while started.elapsed() < STREAM_TIMEOUT {
+        match socket.read() {
+            Ok(Message::Text(text)) => {
+                let entry: Value = serde_json::from_str(&text).unwrap_or(Value::Null);
+                if entry.get("job_id").and_then(|id| id.as_str()) == Some(job_id) {
+                    let message = entry.get("message").and_then(|m| m.as_str()).unwrap_or("");
+                    return Ok(format!("Received job log: {}", message));
+                }
+            }
+            Ok(_) => {}
+            Err(tungstenite::Error::Io(e))
+                if e.kind() == std::io::ErrorKind::WouldBlock || e.kind() == std::io::ErrorKind::TimedOut => {}
+            Err(e) => return Err(format!("Log stream failed: {}", e)),
+        }
+    }
+    Err(format!("No job logs streamed within {}s", STREAM_TIMEOUT.as_secs()))
+}
+
+fn wait_for_job(job_id: &str) -> Result<String, String> {
+    let started = Instant::now();
+    while started.elapsed() < JOB_TIMEOUT {
+        let job = protocol::call::<protocol::GetJob>(&JobParams {
+            job_id: job_id.to_string(),
+        })?;
+        let result = job.last_result.unwrap_or_default();
+        match job.status.as_deref() {
+            Some("completed") => return Ok(format!("Model replied: {}", result)),
+            Some("failed") => return Err(format!("Job failed: {}", result)),
+            _ => std::thread::sleep(Duration::from_millis(500)),
+        }
+    }
+    Err(format!("Job did not finish within {}s", JOB_TIMEOUT.as_secs()))
+}
+
+// Command to check a whole install in one go: backend, environment, permissions,
+// a trivial job through the model, and log streaming
+#[tauri::command(async)]
+pub fn run_self_test(app_handle: tauri::AppHandle, app_state: tauri::State<AppState>) -> SelfTestReport {
+    let mut stages = Vec::new();
+
+    let backend_ok = run_stage(&mut stages, "backend", || ensure_backend(&app_handle, &app_state));
+    run_stage(&mut stages, "permissions", check_permissions);
+
+    if backend_ok {
+        run_stage(&mut stages, "doctor", check_doctor);
+
+        // Connect before submitting so the job's first log can't be missed
+        let mut socket = connect_log_stream();
+        let mut job_id = None;
+        run_stage(&mut stages, "job_submit", || {
+            let id = submit_job()?;
+            job_id = Some(id.clone());
+            Ok(format!("Submitted job {}", id))
+        });
+
+        match job_id {
+            Some(job_id) => {
+                run_stage(&mut stages, "streaming", || match socket.as_mut() {
+                    Ok(socket) => wait_for_stream(socket, &job_id),
+                    Err(e) => Err(e.clone()),
+                });
+                run_stage(&mut stages, "job_result", || wait_for_job(&job_id));
+            }
+            None => {
+                skip_stage(&mut stages, "streaming", "job was not submitted");
+                skip_stage(&mut stages, "job_result", "job was not submitted");
+            }
+        }
+
+        if let Ok(mut socket) = socket {
+            let _ = socket.close(None);
+        }
+    } else {
+        for name in ["doctor", "job_submit", "streaming", "job_result"] {
+            skip_stage(&mut stages, name, "API server is not running");
+        }
+    }
+
+    SelfTestReport {
+        ok: stages.iter().all(|stage| stage.ok),
+        stages,
+    }
+}
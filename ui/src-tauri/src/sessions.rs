@@ -0,0 +1,221 @@
+use rusqlite::{params, Connection, OptionalExtension, Row};
+use serde::Serialize;
+use std::sync::Mutex;
+use tauri::{Emitter, Manager};
+
+use crate::history::{self, HistoryItem, HistoryState};
+use crate::privacy;
+use crate::util::now_secs;
+
+// A named conversation thread; its messages are history items
+#[derive(Clone, Debug, Serialize)]
+pub struct Session {
+    pub id: i64,
+    pub title: String,
+    pub message_count: u64,
+    pub created_at: u64,
+    pub updated_at: u64,
+}
+
+// The session new prompts go to; None until one is chosen or created
+#[derive(Default)]
+pub struct ActiveSession(pub Mutex<Option<i64>>);
+
+const COLUMNS: &str =
+    "s.id, s.title, (SELECT COUNT(*) FROM session_messages m WHERE m.session_id = s.id), s.created_at, s.updated_at";
+
+fn from_row(row: &Row) -> rusqlite::Result<Session> {
+    Ok(Session {
+        id: row.get(0)?,
+        title: row.get(1)?,
+        message_count: row.get::<_, i64>(2)? as u64,
+        created_at: row.get::<_, i64>(3)? as u64,
+        updated_at: row.get::<_, i64>(4)? as u64,
+    })
+}
+
+fn find(conn: &Connection, id: i64) -> Result<Option<Session>, String> {
+    conn.query_row(
+        &format!("SELECT {} FROM sessions s WHERE s.id = ?1", COLUMNS),
+        params![id],
+        from_row,
+    )
+    .optional()
+    .map_err(|e| format!("Failed to read session: {}", e))
+}
+
+fn insert(conn: &Connection, title: &str) -> Result<Session, String> {
+    let now = now_secs();
+    conn.execute(
+        "INSERT INTO sessions (title, created_at, updated_at) VALUES (?1, ?2, ?2)",
+        params![title, now],
+    )
+    .map_err(|e| format!("Failed to create session: {}", e))?;
+    find(conn, conn.last_insert_rowid())?.ok_or_else(|| "Created session disappeared".to_string())
+}
+
+fn emit_changed(app_handle: &tauri::AppHandle, session: Option<&Session>) {
+    if let Err(e) = app_handle.emit("session-changed", session) {
+        eprintln!("Failed to emit session-changed: {}", e);
+    }
+}
+
+// The active session, falling back to the most recently used one after a restart
+fn active_id(app_handle: &tauri::AppHandle, conn: &Connection) -> Result<Option<i64>, String> {
+    let active = app_handle.state::<ActiveSession>();
+    let mut active = active.0.lock().unwrap();
+    if active.is_none() {
+        *active = conn
+            .query_row("SELECT id FROM sessions ORDER BY updated_at DESC, id DESC LIMIT 1", [], |row| {
+                row.get(0)
+            })
+            .optional()
+            .map_err(|e| format!("Failed to read sessions: {}", e))?;
+    }
+    Ok(*active)
+}
+
+// Add a history item to a session: the one given, else the active one, else a new one named after the prompt
+pub fn attach(
+    app_handle: &tauri::AppHandle,
+    history: &HistoryState,
+    history_id: i64,
+    session_id: Option<i64>,
+    prompt: &str,
+) -> Result<i64, String> {
+    let conn = history.connection()?;
+    let session_id = match session_id {
+        Some(id) => id,
+        None => match active_id(app_handle, &conn)? {
+            Some(id) => id,
+            None => {
+                let session = insert(&conn, &history::default_title(prompt))?;
+                *app_handle.state::<ActiveSession>().0.lock().unwrap() = Some(session.id);
+                emit_changed(app_handle, Some(&session));
+                session.id
+            }
+        },
+    };
+    conn.execute(
+        "INSERT OR IGNORE INTO session_messages (session_id, history_id) VALUES (?1, ?2)",
+        params![session_id, history_id],
+    )
+    .and_then(|_| {
+        conn.execute(
+            "UPDATE sessions SET updated_at = ?2 WHERE id = ?1",
+            params![session_id, now_secs()],
+        )
+    })
+    .map_err(|e| format!("Failed to add message to session: {}", e))?;
+    Ok(session_id)
+}
+
+#[tauri::command]
+pub fn list_sessions(history: tauri::State<HistoryState>) -> Result<Vec<Session>, String> {
+    let conn = history.connection()?;
+    let mut statement = conn
+        .prepare(&format!(
+            "SELECT {} FROM sessions s ORDER BY s.updated_at DESC, s.id DESC",
+            COLUMNS
+        ))
+        .map_err(|e| format!("Failed to read sessions: {}", e))?;
+    let sessions = statement
+        .query_map([], from_row)
+        .and_then(|rows| rows.collect::<Result<Vec<_>, _>>())
+        .map_err(|e| format!("Failed to read sessions: {}", e))?;
+    Ok(sessions)
+}
+
+#[tauri::command]
+pub fn get_active_session(
+    app_handle: tauri::AppHandle,
+    history: tauri::State<HistoryState>,
+) -> Result<Option<Session>, String> {
+    let conn = history.connection()?;
+    match active_id(&app_handle, &conn)? {
+        Some(id) => find(&conn, id),
+        None => Ok(None),
+    }
+}
+
+// Command to start a new thread, which becomes the active one
+#[tauri::command]
+pub fn create_session(
+    app_handle: tauri::AppHandle,
+    history: tauri::State<HistoryState>,
+    title: Option<String>,
+) -> Result<Session, String> {
+    let title = match title.as_deref().map(str::trim) {
+        Some(title) if !title.is_empty() => title.to_string(),
+        _ => "New session".to_string(),
+    };
+    let session = insert(&*history.connection()?, &title)?;
+    privacy::end_incognito(&app_handle);
+    *app_handle.state::<ActiveSession>().0.lock().unwrap() = Some(session.id);
+    emit_changed(&app_handle, Some(&session));
+    Ok(session)
+}
+
+#[tauri::command]
+pub fn switch_session(
+    app_handle: tauri::AppHandle,
+    history: tauri::State<HistoryState>,
+    id: i64,
+) -> Result<Session, String> {
+    let session = find(&*history.connection()?, id)?.ok_or_else(|| format!("Session not found: {}", id))?;
+    privacy::end_incognito(&app_handle);
+    *app_handle.state::<ActiveSession>().0.lock().unwrap() = Some(id);
+    emit_changed(&app_handle, Some(&session));
+    Ok(session)
+}
+
+#[tauri::command]
+pub fn rename_session(history: tauri::State<HistoryState>, id: i64, title: String) -> Result<Session, String> {
+    let title = title.trim();
+    if title.is_empty() {
+        return Err("Session title cannot be empty".to_string());
+    }
+    let conn = history.connection()?;
+    conn.execute("UPDATE sessions SET title = ?2 WHERE id = ?1", params![id, title])
+        .map_err(|e| format!("Failed to rename session: {}", e))?;
+    find(&conn, id)?.ok_or_else(|| format!("Session not found: {}", id))
+}
+
+// Command to delete a thread; its prompts stay in the history
+#[tauri::command]
+pub fn delete_session(
+    app_handle: tauri::AppHandle,
+    history: tauri::State<HistoryState>,
+    id: i64,
+) -> Result<(), String> {
+    let deleted = {
+        let conn = history.connection()?;
+        conn.execute("DELETE FROM session_messages WHERE session_id = ?1", params![id])
+            .and_then(|_| conn.execute("DELETE FROM sessions WHERE id = ?1", params![id]))
+            .map_err(|e| format!("Failed to delete session: {}", e))?
+    };
+    if deleted == 0 {
+        return Err(format!("Session not found: {}", id));
+    }
+
+    let active = app_handle.state::<ActiveSession>();
+    let was_active = {
+        let mut active = active.0.lock().unwrap();
+        let was_active = *active == Some(id);
+        if was_active {
+            // The next prompt goes to the most recent remaining session, or starts one
+            *active = None;
+        }
+        was_active
+    };
+    if was_active {
+        emit_changed(&app_handle, None);
+    }
+    Ok(())
+}
+
+// Command for the console to show any session's messages, oldest first
+#[tauri::command]
+pub fn get_session_messages(history: tauri::State<HistoryState>, id: i64) -> Result<Vec<HistoryItem>, String> {
+    history.in_session(id)
+}
@@ -0,0 +1,813 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use tauri::{Emitter, Manager};
+use tauri_plugin_dialog::{DialogExt, MessageDialogButtons, MessageDialogKind};
+
+use crate::app_profiles::AppProfile;
+use crate::approvals::ActionCategory;
+use crate::clipboard::ClipboardRule;
+use crate::retention::CleanKind;
+use crate::scopes::Scope;
+use crate::{migrations, profiles, storage};
+
+const SETTINGS_FILE: &str = "settings.json";
+
+// Copy of the last settings that were read or saved without error, to fall back on if the file is corrupted
+const GOOD_SNAPSHOT_FILE: &str = "settings.last-good.json";
+
+// Native-side settings, persisted in the app data directory
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    pub shortcuts: ShortcutSettings,
+    pub file_index: FileIndexSettings,
+    pub context: ContextSettings,
+    pub crash_reporting: CrashReportingSettings,
+    pub telemetry: TelemetrySettings,
+    pub flags: FlagSettings,
+    pub ollama: OllamaSettings,
+    pub model: ModelSettings,
+    pub mcp: McpSettings,
+    pub permissions: PermissionSettings,
+    pub storage: StorageSettings,
+    pub appearance: AppearanceSettings,
+    pub hot_corner: HotCornerSettings,
+    pub tray: TraySettings,
+    pub quiet: QuietSettings,
+    pub privacy: PrivacySettings,
+    pub input: InputSettings,
+    pub idle: IdleSettings,
+    pub exec: ExecSettings,
+    pub clipboard: ClipboardSettings,
+    pub backend: BackendSettings,
+    pub metrics: MetricsSettings,
+    pub tls: TlsSettings,
+    // Checked in order; the first one matching the frontmost application applies
+    pub app_profiles: Vec<AppProfile>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ShortcutSettings {
+    // Global accelerators that toggle the spotlight window
+    pub spotlight: Vec<String>,
+}
+
+impl Default for ShortcutSettings {
+    fn default() -> Self {
+        ShortcutSettings {
+            spotlight: vec!["CommandOrControl+K".to_string(), "CommandOrControl+Space".to_string()],
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FileIndexSettings {
+    pub enabled: bool,
+    // Folders to index; `~` expands to the home directory
+    pub include_paths: Vec<String>,
+    // Folder names (e.g. `node_modules`) or full paths to skip
+    pub exclude_paths: Vec<String>,
+    pub max_files: usize,
+}
+
+impl Default for FileIndexSettings {
+    fn default() -> Self {
+        FileIndexSettings {
+            enabled: true,
+            include_paths: vec![
+                "~/Documents".to_string(),
+                "~/Desktop".to_string(),
+                "~/Downloads".to_string(),
+            ],
+            exclude_paths: vec![
+                "node_modules".to_string(),
+                "target".to_string(),
+                "__pycache__".to_string(),
+                "venv".to_string(),
+            ],
+            max_files: 200_000,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ContextSettings {
+    // Folders whose documents are embedded for semantic search
+    pub folders: Vec<String>,
+    // Whether prompts automatically get relevant snippets attached
+    pub auto_attach: bool,
+    pub max_snippets: usize,
+    // Minimum cosine similarity for a snippet to be attached
+    pub min_score: f32,
+}
+
+impl Default for ContextSettings {
+    fn default() -> Self {
+        ContextSettings {
+            folders: Vec::new(),
+            auto_attach: true,
+            max_snippets: 5,
+            min_score: 0.55,
+        }
+    }
+}
+
+// Crash reports are only written, and only uploaded, once the user opts in
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CrashReportingSettings {
+    pub enabled: bool,
+    // Endpoint that receives queued reports as JSON; reports stay local without one
+    pub upload_url: Option<String>,
+}
+
+// Anonymous usage events are only queued, and only sent, once the user opts in
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TelemetrySettings {
+    pub enabled: bool,
+    // Endpoint that receives batches of queued events; events stay local without one
+    pub endpoint: Option<String>,
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FlagSettings {
+    // Local on/off overrides that win over the manifest and built-in defaults
+    pub overrides: HashMap<String, bool>,
+    // JSON manifest of the form {"flags": {"name": true}}, fetched at startup
+    pub manifest_url: Option<String>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct OllamaSettings {
+    pub url: String,
+    // Start `ollama serve` with the app (and stop it on quit) when it isn't already running
+    pub auto_start: bool,
+}
+
+impl Default for OllamaSettings {
+    fn default() -> Self {
+        OllamaSettings {
+            url: "http://localhost:11434".to_string(),
+            auto_start: false,
+        }
+    }
+}
+
+// The model jobs run on; forwarded to the backend at spawn time and when changed
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ModelSettings {
+    // `gemini` or `ollama`
+    pub provider: String,
+    pub name: String,
+}
+
+impl Default for ModelSettings {
+    fn default() -> Self {
+        ModelSettings {
+            provider: "gemini".to_string(),
+            name: "gemini-2.5-flash".to_string(),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct McpSettings {
+    pub servers: Vec<McpServerConfig>,
+    // Loopback port the backend reaches MCP tools through (also serves Krya's own MCP endpoint)
+    pub proxy_port: u16,
+    pub server: McpServerSettings,
+}
+
+impl Default for McpSettings {
+    fn default() -> Self {
+        McpSettings {
+            servers: Vec::new(),
+            proxy_port: 8765,
+            server: McpServerSettings::default(),
+        }
+    }
+}
+
+// An MCP server started over stdio, e.g. `npx -y @modelcontextprotocol/server-github`
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct McpServerConfig {
+    pub id: String,
+    pub name: String,
+    pub command: String,
+    pub args: Vec<String>,
+    pub env: HashMap<String, String>,
+    pub enabled: bool,
+}
+
+// Krya's own MCP server, which exposes granted desktop capabilities to other agents
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct McpServerSettings {
+    pub enabled: bool,
+    pub token: String,
+}
+
+// Capabilities the user has granted to outside callers; nothing is granted by default
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PermissionSettings {
+    pub granted: Vec<Scope>,
+    // Folders files may be attached from; `~` is expanded
+    pub file_roots: Vec<String>,
+    // Largest file read_file reads, or write_file and append_file leave behind
+    pub max_file_mb: u64,
+    // Actions a generated script may only take once the user confirms them
+    pub confirm: Vec<ActionCategory>,
+}
+
+impl Default for PermissionSettings {
+    fn default() -> Self {
+        PermissionSettings {
+            granted: Vec::new(),
+            file_roots: vec!["~".to_string()],
+            max_file_mb: 10,
+            confirm: ActionCategory::ALL.to_vec(),
+        }
+    }
+}
+
+// Limits on what logs, recordings and screenshots may use in the app's folders
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct StorageSettings {
+    // A log file is rotated once it grows past this
+    pub max_log_mb: u64,
+    // Rotated logs, recordings and screenshots older than this are deleted
+    pub max_age_days: u64,
+    // Oldest files are deleted first once these together use more than this
+    pub max_total_mb: u64,
+    // Re-encode screen recordings as MP4 with ffmpeg, if it's installed, while the user is away
+    pub transcode_recordings: bool,
+    pub cleanup: CleanupSettings,
+}
+
+impl Default for StorageSettings {
+    fn default() -> Self {
+        StorageSettings {
+            max_log_mb: 10,
+            max_age_days: 30,
+            max_total_mb: 500,
+            transcode_recordings: true,
+            cleanup: CleanupSettings::default(),
+        }
+    }
+}
+
+// Clearing disposable files automatically, on the same hourly check as the storage limits
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CleanupSettings {
+    pub enabled: bool,
+    pub kinds: Vec<CleanKind>,
+    // Only files untouched for this long are cleared
+    pub older_than_days: u64,
+}
+
+impl Default for CleanupSettings {
+    fn default() -> Self {
+        CleanupSettings {
+            enabled: true,
+            kinds: vec![CleanKind::Caches, CleanKind::Handoff, CleanKind::Logs],
+            older_than_days: 7,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SpotlightPosition {
+    // A quarter of the way down the current monitor
+    #[default]
+    TopCenter,
+    // Next to the mouse, for desks where the top of the screen is far away
+    NearCursor,
+    // A slim strip on a screen edge that opens into a sidebar
+    Docked,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DockedEdge {
+    Left,
+    #[default]
+    Right,
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AppearanceSettings {
+    // macOS only: a launcher doesn't need to sit in the dock, so it runs as an accessory app unless asked
+    pub show_dock_icon: bool,
+    pub spotlight_position: SpotlightPosition,
+    // Which edge the docked spotlight is on; dragging it to the other half of the screen moves it there
+    pub docked_edge: DockedEdge,
+    // Show and hide the spotlight at once instead of fading and sliding it
+    pub reduce_motion: bool,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ScreenCorner {
+    TopLeft,
+    TopRight,
+    BottomLeft,
+    BottomRight,
+}
+
+// Opening the spotlight with the mouse instead of a shortcut; off unless opted in
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct HotCornerSettings {
+    pub enabled: bool,
+    // None leaves only the shake gesture
+    pub corner: Option<ScreenCorner>,
+    // How long the pointer has to rest in the corner, so passing through it doesn't count
+    pub dwell_ms: u64,
+    // Quickly shaking the mouse left and right
+    pub shake: bool,
+    // Applications (by name or executable) where the mouse never opens the spotlight, e.g. games
+    pub excluded_apps: Vec<String>,
+}
+
+impl Default for HotCornerSettings {
+    fn default() -> Self {
+        HotCornerSettings {
+            enabled: false,
+            corner: Some(ScreenCorner::TopRight),
+            dwell_ms: 300,
+            shake: false,
+            excluded_apps: Vec::new(),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TrayClickAction {
+    ToggleSpotlight,
+    // The popover under the menu-bar icon on macOS, the spotlight elsewhere
+    QuickPrompt,
+    // The spotlight with its input cleared
+    NewPrompt,
+    OpenConsole,
+    // Toggles, so the same click resumes
+    PauseAutomation,
+    Nothing,
+}
+
+// What clicking the tray icon does. Linux indicators only ever open the menu, and Tauri
+// reports middle clicks only on Windows and macOS and double clicks only on Windows.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TraySettings {
+    pub left_click: TrayClickAction,
+    pub middle_click: TrayClickAction,
+    // A double click also reports the left click before it
+    pub double_click: TrayClickAction,
+}
+
+impl Default for TraySettings {
+    fn default() -> Self {
+        TraySettings {
+            left_click: if cfg!(target_os = "macos") {
+                TrayClickAction::QuickPrompt
+            } else {
+                TrayClickAction::ToggleSpotlight
+            },
+            middle_click: TrayClickAction::Nothing,
+            double_click: TrayClickAction::Nothing,
+        }
+    }
+}
+
+// Notifications are always held back during do-not-disturb, presentations and screen sharing
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct QuietSettings {
+    // Also pause automation until it's over
+    pub pause_automation: bool,
+}
+
+// Privacy mode, kept on across restarts until it's turned off
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PrivacySettings {
+    pub enabled: bool,
+}
+
+// Which applications may receive synthetic keystrokes and clicks, from jobs, macros or expansions.
+// Apps are given by name or executable, as for app profiles.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct InputSettings {
+    // When not empty, only these applications may be controlled
+    pub allowed_apps: Vec<String>,
+    // Never controlled, even when allowed
+    pub blocked_apps: Vec<String>,
+}
+
+impl Default for InputSettings {
+    fn default() -> Self {
+        InputSettings {
+            allowed_apps: Vec::new(),
+            blocked_apps: [
+                "1Password",
+                "Bitwarden",
+                "Dashlane",
+                "Enpass",
+                "KeePass",
+                "KeePassXC",
+                "Keychain Access",
+                "LastPass",
+                "NordPass",
+                "Proton Pass",
+            ]
+            .iter()
+            .map(|app| app.to_string())
+            .collect(),
+        }
+    }
+}
+
+// Indexing, package checks, transcoding and cleanups wait until the user has stepped away
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct IdleSettings {
+    pub defer_background_work: bool,
+    // Time without keyboard or mouse input before the user counts as away
+    pub idle_after_secs: u64,
+}
+
+impl Default for IdleSettings {
+    fn default() -> Self {
+        IdleSettings {
+            defer_background_work: true,
+            idle_after_secs: 120,
+        }
+    }
+}
+
+// Programs the frontend and backend may run through exec_command; none until the user lists them
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ExecSettings {
+    pub allowed: Vec<AllowedCommand>,
+    // For commands that don't set their own
+    pub default_timeout_secs: u64,
+}
+
+impl Default for ExecSettings {
+    fn default() -> Self {
+        ExecSettings {
+            allowed: Vec::new(),
+            default_timeout_secs: 30,
+        }
+    }
+}
+
+// A program by name, looked up on PATH, or by its full path
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct AllowedCommand {
+    pub command: String,
+    #[serde(default)]
+    pub timeout_secs: Option<u64>,
+}
+
+// Suggestions for copied text; reading every copy is opt-in
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ClipboardSettings {
+    pub enabled: bool,
+    pub rules: Vec<ClipboardRule>,
+}
+
+impl Default for ClipboardSettings {
+    fn default() -> Self {
+        ClipboardSettings {
+            enabled: false,
+            rules: crate::clipboard::default_rules(),
+        }
+    }
+}
+
+// How the Python backend is started
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BackendSettings {
+    // Interpreter to use instead of searching for one; `~` expands to the home directory
+    pub python: Option<String>,
+    // Conda environment to run in, by name or path, when no interpreter is set
+    pub conda_env: Option<String>,
+    // Passed to the interpreter before run_server.py, e.g. `-m debugpy --listen 5678`
+    pub python_args: Vec<String>,
+    // Passed to run_server.py after `--port`
+    pub args: Vec<String>,
+    // Directory the backend runs in, instead of its sources
+    pub working_dir: Option<String>,
+    // Extra backends that answer model calls (completions, embeddings) while the main one is busy
+    pub workers: usize,
+}
+
+// A Prometheus endpoint on localhost, for self-hosters' dashboards
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MetricsSettings {
+    pub enabled: bool,
+    pub port: u16,
+}
+
+impl Default for MetricsSettings {
+    fn default() -> Self {
+        MetricsSettings {
+            enabled: false,
+            port: 9464,
+        }
+    }
+}
+
+// Root certificates trusted on top of the system's, for networks that intercept TLS
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TlsSettings {
+    // PEM or DER files, usually copies made by importing them
+    pub extra_ca_certificates: Vec<String>,
+}
+
+// Why the settings were reset to defaults at startup, until the user restores or dismisses it
+#[derive(Clone, Debug, Serialize)]
+pub struct SettingsRecovery {
+    pub error: String,
+    // Where the unreadable file was moved
+    pub backup: String,
+    pub snapshot_available: bool,
+}
+
+// The settings in force, with the policy applied; why they were reset, if they were; and the user's own
+// as saved, which the policy never touches so nothing it overrides is lost once it's lifted
+pub struct SettingsState(pub Mutex<Settings>, Mutex<Option<SettingsRecovery>>, Mutex<Settings>);
+
+impl SettingsState {
+    pub fn load(app_handle: &tauri::AppHandle) -> Self {
+        let (saved, recovery) = read(app_handle);
+        let mut settings = saved.clone();
+        crate::policy::enforce(&mut settings);
+        SettingsState(Mutex::new(settings), Mutex::new(recovery), Mutex::new(saved))
+    }
+}
+
+fn snapshot_path(app_handle: &tauri::AppHandle) -> Result<PathBuf, String> {
+    Ok(storage::data_dir(app_handle)?.join(profiles::file(app_handle, GOOD_SNAPSHOT_FILE)))
+}
+
+// Read the active profile's settings. A file that doesn't parse is moved aside rather than overwritten
+// by the next save, and defaults are used until the user decides what to do about it.
+fn read(app_handle: &tauri::AppHandle) -> (Settings, Option<SettingsRecovery>) {
+    // A file that can't be migrated is read as it is, and is moved aside below if it doesn't parse.
+    // The snapshot is upgraded too, as it may have been taken by the release before.
+    for file in [SETTINGS_FILE, GOOD_SNAPSHOT_FILE] {
+        if let Err(e) = migrations::migrate_json(app_handle, &profiles::file(app_handle, file), migrations::SETTINGS) {
+            eprintln!("{}", e);
+        }
+    }
+    let path = match storage::data_dir(app_handle) {
+        Ok(dir) => dir.join(profiles::file(app_handle, SETTINGS_FILE)),
+        Err(e) => {
+            eprintln!("{}", e);
+            return (Settings::default(), None);
+        }
+    };
+    let contents = match fs::read_to_string(&path) {
+        Ok(contents) => contents,
+        Err(e) => {
+            if path.exists() {
+                eprintln!("Failed to read {:?}: {}", path, e);
+            }
+            return (Settings::default(), None);
+        }
+    };
+
+    let error = match serde_json::from_str::<Settings>(&contents) {
+        Ok(settings) => {
+            // Hand edits can still make sense to serde and not to the app; say what's off without refusing it
+            for error in crate::validation::validate(&settings) {
+                eprintln!("Invalid setting {}", error);
+            }
+            if let Err(e) = snapshot_path(app_handle).and_then(|snapshot| {
+                fs::write(&snapshot, &contents).map_err(|e| format!("Failed to write {:?}: {}", snapshot, e))
+            }) {
+                eprintln!("{}", e);
+            }
+            return (settings, None);
+        }
+        Err(e) => e.to_string(),
+    };
+
+    let stamp = chrono::Local::now().format("%Y%m%d-%H%M%S");
+    let backup = path.with_file_name(format!("settings.corrupt-{}.json", stamp));
+    if let Err(e) = fs::rename(&path, &backup) {
+        eprintln!("Failed to back up {:?}: {}", path, e);
+    }
+    eprintln!("Failed to parse {:?}, using default settings: {}", path, error);
+    let snapshot_available = snapshot_path(app_handle).is_ok_and(|snapshot| snapshot.exists());
+    let recovery = SettingsRecovery {
+        error,
+        backup: backup.display().to_string(),
+        snapshot_available,
+    };
+    (Settings::default(), Some(recovery))
+}
+
+// Get a copy of the current settings
+pub fn current(app_handle: &tauri::AppHandle) -> Settings {
+    app_handle.state::<SettingsState>().0.lock().unwrap().clone()
+}
+
+// Expand a leading `~` to the user's home directory
+pub fn expand_home(path: &str) -> PathBuf {
+    let home = std::env::var_os("HOME").or_else(|| std::env::var_os("USERPROFILE"));
+
+    match (path.strip_prefix('~'), home) {
+        (Some(rest), Some(home)) => {
+            let rest = rest.trim_start_matches(['/', '\\']);
+            if rest.is_empty() {
+                PathBuf::from(home)
+            } else {
+                PathBuf::from(home).join(rest)
+            }
+        }
+        _ => PathBuf::from(path),
+    }
+}
+
+#[tauri::command]
+pub fn get_settings(state: tauri::State<SettingsState>) -> Settings {
+    state.0.lock().unwrap().clone()
+}
+
+// Persist new settings and let subsystems pick up the new values. Settings that wouldn't work are
+// refused, except for problems the current ones already have.
+pub fn replace(app_handle: &tauri::AppHandle, settings: Settings) -> Result<(), String> {
+    let problems: Vec<String> = crate::validation::new_problems(&current(app_handle), &settings)
+        .iter()
+        .map(ToString::to_string)
+        .collect();
+    if !problems.is_empty() {
+        return Err(format!("Invalid settings: {}", problems.join("; ")));
+    }
+    // Edited from the settings in force, so what the policy decides goes back to the user's own
+    let mut settings = settings;
+    crate::policy::restore_user_values(&mut settings, &app_handle.state::<SettingsState>().2.lock().unwrap());
+    store(app_handle, settings)
+}
+
+// Persist the user's settings as they are, for ones read back from disk, whose problems `read` already
+// reported, and put them in force with the policy applied
+fn store(app_handle: &tauri::AppHandle, saved: Settings) -> Result<(), String> {
+    let mut settings = saved.clone();
+    crate::policy::enforce(&mut settings);
+    let previous = {
+        let state = app_handle.state::<SettingsState>();
+        let mut current = state.0.lock().unwrap();
+        let previous = std::mem::replace(&mut *current, settings.clone());
+        *state.2.lock().unwrap() = saved.clone();
+        storage::save_json(app_handle, &profiles::file(app_handle, SETTINGS_FILE), &saved, storage::Data::Config)?;
+        // Defaults written while recovery is pending mustn't replace the snapshot it would restore
+        if state.1.lock().unwrap().is_none() {
+            storage::save_json(app_handle, &profiles::file(app_handle, GOOD_SNAPSHOT_FILE), &saved, storage::Data::Config)?;
+        }
+        previous
+    };
+
+    crate::register_shortcuts(app_handle);
+    crate::crash::apply_settings(app_handle);
+    crate::logs::apply_settings(app_handle);
+    crate::dock::apply(app_handle);
+    crate::hot_corner::apply(app_handle);
+    crate::sidebar::apply(app_handle);
+    crate::workers::apply(app_handle);
+    crate::metrics::apply(app_handle);
+    crate::network::apply(app_handle);
+    crate::tls::apply(app_handle);
+    crate::flags::emit_changed(app_handle);
+    crate::privacy::apply(app_handle);
+    crate::mcp::apply_in_background(app_handle);
+    // A full scan of the disk, so only when what it covers changed
+    if settings.file_index != previous.file_index {
+        app_handle.state::<crate::files::FileIndex>().request_reindex();
+    }
+    // Re-embeds whatever changed on disk, so again only when the folders or what's skipped in them did
+    if settings.context.folders != previous.context.folders
+        || settings.file_index.exclude_paths != previous.file_index.exclude_paths
+    {
+        app_handle
+            .state::<crate::context::ContextIndex>()
+            .rebuild_in_background(app_handle);
+    }
+    app_handle
+        .emit("settings-changed", settings)
+        .map_err(|e| format!("Failed to emit settings change: {}", e))
+}
+
+// Take up the active profile's settings after switching profiles
+pub fn reload(app_handle: &tauri::AppHandle) -> Result<(), String> {
+    let (settings, recovery) = read(app_handle);
+    let corrupted = recovery.is_some();
+    *app_handle.state::<SettingsState>().1.lock().unwrap() = recovery;
+    store(app_handle, settings)?;
+    if corrupted {
+        offer_recovery(app_handle);
+    }
+    Ok(())
+}
+
+// If the settings file couldn't be read, say so and offer to go back to the last settings that worked
+pub fn offer_recovery(app_handle: &tauri::AppHandle) {
+    let recovery = match app_handle.state::<SettingsState>().1.lock().unwrap().clone() {
+        Some(recovery) => recovery,
+        None => return,
+    };
+    let _ = app_handle.emit("settings-recovery", &recovery);
+
+    let app_handle = app_handle.clone();
+    std::thread::spawn(move || {
+        let message = format!(
+            "Krya.ai couldn't read its settings and started with the defaults:\n\n{}\n\n\
+             The unreadable file was kept at {}.",
+            recovery.error, recovery.backup
+        );
+        if !recovery.snapshot_available {
+            app_handle
+                .dialog()
+                .message(message)
+                .title("Settings were reset")
+                .kind(MessageDialogKind::Warning)
+                .blocking_show();
+            *app_handle.state::<SettingsState>().1.lock().unwrap() = None;
+            return;
+        }
+        let restore = app_handle
+            .dialog()
+            .message(format!("{}\n\nRestore the last settings that worked?", message))
+            .title("Restore your settings?")
+            .kind(MessageDialogKind::Warning)
+            .buttons(MessageDialogButtons::OkCancelCustom(
+                "Restore".to_string(),
+                "Keep Defaults".to_string(),
+            ))
+            .blocking_show();
+        let result = if restore {
+            restore_snapshot(&app_handle).map(|_| ())
+        } else {
+            *app_handle.state::<SettingsState>().1.lock().unwrap() = None;
+            Ok(())
+        };
+        if let Err(e) = result {
+            crate::errors::alert(&app_handle, "Failed to restore the settings", &e);
+        }
+    });
+}
+
+fn restore_snapshot(app_handle: &tauri::AppHandle) -> Result<Settings, String> {
+    let path = snapshot_path(app_handle)?;
+    let contents = fs::read_to_string(&path).map_err(|e| format!("Failed to read {:?}: {}", path, e))?;
+    let settings: Settings =
+        serde_json::from_str(&contents).map_err(|e| format!("Failed to parse {:?}: {}", path, e))?;
+    store(app_handle, settings.clone())?;
+    *app_handle.state::<SettingsState>().1.lock().unwrap() = None;
+    println!("Restored settings from {:?}", path);
+    Ok(settings)
+}
+
+// Command for the settings UI: why the settings were reset, if they were
+#[tauri::command]
+pub fn get_settings_recovery(state: tauri::State<SettingsState>) -> Option<SettingsRecovery> {
+    state.1.lock().unwrap().clone()
+}
+
+// Command to go back to the last settings that were read or saved without error
+#[tauri::command]
+pub fn restore_settings_snapshot(app_handle: tauri::AppHandle) -> Result<Settings, String> {
+    restore_snapshot(&app_handle)
+}
+
+#[tauri::command]
+pub fn save_settings(app_handle: tauri::AppHandle, settings: Settings) -> Result<(), String> {
+    replace(&app_handle, settings)
+}
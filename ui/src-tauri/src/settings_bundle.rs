@@ -0,0 +1,204 @@
+use aes_gcm::aead::rand_core::RngCore;
+use aes_gcm::aead::{Aead, AeadCore, KeyInit, OsRng};
+use aes_gcm::{Aes256Gcm, Key, Nonce};
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha2::Sha256;
+use tauri::Manager;
+
+use crate::expansion::{self, ExpansionConfig};
+use crate::protocol::{self, ConfigUpdate, NoParams};
+use crate::quick_actions::{self, QuickActionStore, QuickActionsState};
+use crate::settings::{self, Settings};
+use crate::templates::{self, PromptTemplate};
+use crate::util::now_secs;
+
+const BUNDLE_FORMAT: &str = "krya-settings";
+const BUNDLE_VERSION: u32 = 1;
+
+// PBKDF2 rounds for deriving the secrets key from the passphrase
+const KDF_ITERATIONS: u32 = 200_000;
+
+// Backend config keys that are secrets and only travel encrypted
+const SECRET_KEYS: &[&str] = &["api_key"];
+
+// Everything needed to move a setup to another machine
+#[derive(Serialize, Deserialize)]
+pub struct SettingsBundle {
+    pub format: String,
+    pub version: u32,
+    pub exported_at: u64,
+    pub settings: Settings,
+    pub quick_actions: QuickActionStore,
+    pub text_expansion: ExpansionConfig,
+    // Missing from bundles exported before templates existed, which then leave them alone
+    #[serde(default)]
+    pub prompt_templates: Option<Vec<PromptTemplate>>,
+    // Model configuration from the Python backend, without secrets
+    pub backend_config: Option<Value>,
+    pub secrets: Option<EncryptedSecrets>,
+}
+
+// AES-256-GCM encrypted JSON object, keyed by PBKDF2-SHA256 of the user's passphrase
+#[derive(Serialize, Deserialize)]
+pub struct EncryptedSecrets {
+    pub salt: String,
+    pub nonce: String,
+    pub ciphertext: String,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct ImportSummary {
+    pub backend_config_imported: bool,
+    pub secrets_imported: bool,
+}
+
+fn derive_key(passphrase: &str, salt: &[u8]) -> Key<Aes256Gcm> {
+    let mut key = [0u8; 32];
+    pbkdf2::pbkdf2_hmac::<Sha256>(passphrase.as_bytes(), salt, KDF_ITERATIONS, &mut key);
+    key.into()
+}
+
+fn encrypt_secrets(secrets: &Value, passphrase: &str) -> Result<EncryptedSecrets, String> {
+    let mut salt = [0u8; 16];
+    OsRng.fill_bytes(&mut salt);
+    let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+
+    let plaintext = serde_json::to_vec(secrets).map_err(|e| format!("Failed to serialize secrets: {}", e))?;
+    let ciphertext = Aes256Gcm::new(&derive_key(passphrase, &salt))
+        .encrypt(&nonce, plaintext.as_slice())
+        .map_err(|_| "Failed to encrypt secrets".to_string())?;
+
+    Ok(EncryptedSecrets {
+        salt: BASE64.encode(salt),
+        nonce: BASE64.encode(nonce),
+        ciphertext: BASE64.encode(ciphertext),
+    })
+}
+
+fn decrypt_secrets(secrets: &EncryptedSecrets, passphrase: &str) -> Result<Value, String> {
+    let decode = |field: &str| BASE64.decode(field).map_err(|e| format!("Corrupt secrets in settings bundle: {}", e));
+    let salt = decode(&secrets.salt)?;
+    let nonce = decode(&secrets.nonce)?;
+    let ciphertext = decode(&secrets.ciphertext)?;
+    if nonce.len() != 12 {
+        return Err("Corrupt secrets in settings bundle".to_string());
+    }
+
+    let plaintext = Aes256Gcm::new(&derive_key(passphrase, &salt))
+        .decrypt(Nonce::from_slice(&nonce), ciphertext.as_slice())
+        .map_err(|_| "Wrong passphrase for the secrets in this settings bundle".to_string())?;
+    serde_json::from_slice(&plaintext).map_err(|e| format!("Corrupt secrets in settings bundle: {}", e))
+}
+
+// Split the backend config into its shareable part and its secrets
+fn split_backend_config(mut config: Value) -> (Value, Value) {
+    let mut secrets = serde_json::Map::new();
+    if let Some(object) = config.as_object_mut() {
+        object.remove("api_key_set");
+        for key in SECRET_KEYS {
+            if let Some(value) = object.remove(*key) {
+                if value.as_str().map(|v| !v.is_empty()).unwrap_or(false) {
+                    secrets.insert(key.to_string(), value);
+                }
+            }
+        }
+    }
+    (config, Value::Object(secrets))
+}
+
+// Command to write all settings to a single file; secrets are only included when a passphrase is given
+#[tauri::command(async)]
+pub fn export_settings(app_handle: tauri::AppHandle, path: String, passphrase: Option<String>) -> Result<(), String> {
+    let include_secrets = passphrase.as_deref().map(|p| !p.is_empty()).unwrap_or(false);
+
+    // The backend may not be running; its config is then simply left out
+    let backend_config = match protocol::call::<protocol::ExportConfig>(&NoParams {}) {
+        Ok(config) => Some(split_backend_config(config)),
+        Err(e) => {
+            eprintln!("Exporting settings without backend config: {}", e);
+            None
+        }
+    };
+
+    let secrets = match (&backend_config, passphrase) {
+        (Some((_, secrets)), Some(passphrase)) if include_secrets => Some(encrypt_secrets(secrets, &passphrase)?),
+        _ => None,
+    };
+
+    let bundle = SettingsBundle {
+        format: BUNDLE_FORMAT.to_string(),
+        version: BUNDLE_VERSION,
+        exported_at: now_secs(),
+        settings: settings::current(&app_handle),
+        quick_actions: app_handle.state::<QuickActionsState>().0.lock().unwrap().clone(),
+        text_expansion: expansion::current_config(&app_handle),
+        prompt_templates: Some(templates::all(&app_handle)),
+        backend_config: backend_config.map(|(config, _)| config),
+        secrets,
+    };
+
+    let contents = serde_json::to_string_pretty(&bundle).map_err(|e| format!("Failed to serialize settings: {}", e))?;
+    std::fs::write(&path, contents).map_err(|e| format!("Failed to write {}: {}", path, e))
+}
+
+// Command to restore settings from a bundle written by export_settings
+#[tauri::command(async)]
+pub fn import_settings(
+    app_handle: tauri::AppHandle,
+    path: String,
+    passphrase: Option<String>,
+) -> Result<ImportSummary, String> {
+    let contents = std::fs::read_to_string(&path).map_err(|e| format!("Failed to read {}: {}", path, e))?;
+    let bundle: SettingsBundle =
+        serde_json::from_str(&contents).map_err(|e| format!("Not a valid settings bundle: {}", e))?;
+
+    if bundle.format != BUNDLE_FORMAT {
+        return Err("Not a valid settings bundle".to_string());
+    }
+    if bundle.version > BUNDLE_VERSION {
+        return Err(format!(
+            "Settings bundle version {} is newer than this app supports",
+            bundle.version
+        ));
+    }
+
+    // Decrypt first so a wrong passphrase doesn't leave a half-imported setup
+    let secrets = match (&bundle.secrets, passphrase.as_deref()) {
+        (Some(secrets), Some(passphrase)) if !passphrase.is_empty() => Some(decrypt_secrets(secrets, passphrase)?),
+        _ => None,
+    };
+
+    settings::replace(&app_handle, bundle.settings)?;
+    quick_actions::replace_store(&app_handle, bundle.quick_actions)?;
+    expansion::replace_config(&app_handle, bundle.text_expansion)?;
+    if let Some(prompt_templates) = bundle.prompt_templates {
+        templates::replace_all(&app_handle, prompt_templates)?;
+    }
+
+    let mut backend_update = bundle.backend_config.unwrap_or_else(|| Value::Object(Default::default()));
+    if let (Some(update), Some(Value::Object(secrets))) = (backend_update.as_object_mut(), secrets.as_ref()) {
+        update.extend(secrets.clone());
+    }
+
+    let has_backend_update = backend_update.as_object().map(|o| !o.is_empty()).unwrap_or(false);
+    let backend_config_imported = has_backend_update
+        && match serde_json::from_value::<ConfigUpdate>(backend_update)
+            .map_err(|e| format!("Invalid backend config in settings bundle: {}", e))
+            .and_then(|update| protocol::call::<protocol::UpdateConfig>(&update))
+            .and_then(|response| response.into_result())
+        {
+            Ok(_) => true,
+            Err(e) => {
+                eprintln!("Failed to import backend config: {}", e);
+                false
+            }
+        };
+
+    Ok(ImportSummary {
+        backend_config_imported,
+        secrets_imported: backend_config_imported && secrets.is_some(),
+    })
+}
@@ -0,0 +1,162 @@
+use serde::Serialize;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+use tauri::{Emitter, LogicalSize, Manager, PhysicalPosition, PhysicalSize, WebviewWindow};
+
+use crate::placement;
+use crate::settings::{self, DockedEdge, SpotlightPosition};
+
+// Logical widths of the docked spotlight collapsed to a strip and opened into a sidebar
+const STRIP_WIDTH: f64 = 6.0;
+const SIDEBAR_WIDTH: f64 = 380.0;
+
+// How long a dragged sidebar has to stay put before it's snapped to the nearest edge
+const SETTLE_DELAY: Duration = Duration::from_millis(300);
+
+// The spotlight's configured size, restored when it's undocked
+const UNDOCKED_SIZE: LogicalSize<f64> = LogicalSize {
+    width: 600.0,
+    height: 120.0,
+};
+
+#[derive(Default)]
+pub struct SidebarState {
+    docked: AtomicBool,
+    expanded: AtomicBool,
+    // Where the sidebar was last put, so moving it there isn't mistaken for a drag
+    placed: Mutex<Option<PhysicalPosition<i32>>>,
+    // Bumped by every move, so only the last one of a drag snaps the sidebar
+    moves: AtomicU64,
+}
+
+#[derive(Clone, Debug, Serialize)]
+struct SidebarChanged {
+    expanded: bool,
+    edge: DockedEdge,
+}
+
+pub fn is_docked(app_handle: &tauri::AppHandle) -> bool {
+    settings::current(app_handle).appearance.spotlight_position == SpotlightPosition::Docked
+}
+
+pub fn is_expanded(window: &WebviewWindow) -> bool {
+    window.state::<SidebarState>().expanded.load(Ordering::SeqCst)
+}
+
+// Pin the window to its edge of the usable area, spanning its height
+fn place(window: &WebviewWindow, expanded: bool) -> tauri::Result<()> {
+    let monitor = match placement::monitor_for(window)? {
+        Some(monitor) => monitor,
+        None => return Ok(()),
+    };
+    let area = placement::usable_area(&monitor);
+    let edge = settings::current(window.app_handle()).appearance.docked_edge;
+    let width = if expanded { SIDEBAR_WIDTH } else { STRIP_WIDTH };
+    let width = ((width * monitor.scale_factor()).round() as u32).min(area.width);
+    let position = PhysicalPosition {
+        x: match edge {
+            DockedEdge::Left => area.x,
+            DockedEdge::Right => area.x + (area.width - width) as i32,
+        },
+        y: area.y,
+    };
+
+    let state = window.state::<SidebarState>();
+    state.expanded.store(expanded, Ordering::SeqCst);
+    *state.placed.lock().unwrap() = Some(position);
+    window.set_size(PhysicalSize::new(width, area.height))?;
+    window.set_position(position)?;
+
+    let changed = SidebarChanged { expanded, edge };
+    if let Err(e) = window.emit_to(window.label(), "sidebar-changed", changed) {
+        eprintln!("Failed to emit sidebar-changed: {}", e);
+    }
+    Ok(())
+}
+
+// Open the strip into the sidebar and give it focus
+pub fn expand(window: &WebviewWindow) -> tauri::Result<()> {
+    place(window, true)?;
+    window.show()?;
+    window.set_focus()
+}
+
+pub fn collapse(window: &WebviewWindow) -> tauri::Result<()> {
+    place(window, false)
+}
+
+// Dock or undock the spotlight to match the settings
+pub fn apply(app_handle: &tauri::AppHandle) {
+    let window = match app_handle.get_webview_window("main") {
+        Some(window) => window,
+        None => return,
+    };
+    let state = app_handle.state::<SidebarState>();
+    let docked = is_docked(app_handle);
+    let result = if docked {
+        // Also moves it when only the edge changed
+        state.docked.store(true, Ordering::SeqCst);
+        place(&window, is_expanded(&window)).and_then(|_| window.show())
+    } else if state.docked.swap(false, Ordering::SeqCst) {
+        state.expanded.store(false, Ordering::SeqCst);
+        window.hide().and_then(|_| window.set_size(UNDOCKED_SIZE))
+    } else {
+        Ok(())
+    };
+    if let Err(e) = result {
+        eprintln!("Failed to {} the spotlight: {}", if docked { "dock" } else { "undock" }, e);
+    }
+}
+
+// The edge nearer to the middle of the window, on whichever monitor it was dragged to
+fn nearest_edge(window: &WebviewWindow) -> tauri::Result<Option<DockedEdge>> {
+    let position = window.outer_position()?;
+    let size = window.outer_size()?;
+    let center_x = position.x + size.width as i32 / 2;
+    let center_y = position.y + size.height as i32 / 2;
+    let monitor = match placement::monitor_at(window, center_x, center_y)? {
+        Some(monitor) => monitor,
+        None => return Ok(None),
+    };
+    let middle = monitor.position().x + monitor.size().width as i32 / 2;
+    Ok(Some(if center_x < middle { DockedEdge::Left } else { DockedEdge::Right }))
+}
+
+fn redock(window: &WebviewWindow) -> tauri::Result<()> {
+    let mut settings = settings::current(window.app_handle());
+    if let Some(edge) = nearest_edge(window)? {
+        if edge != settings.appearance.docked_edge {
+            settings.appearance.docked_edge = edge;
+            if let Err(e) = settings::replace(window.app_handle(), settings) {
+                eprintln!("Failed to save the sidebar's edge: {}", e);
+            }
+        }
+    }
+    place(window, is_expanded(window))
+}
+
+// Called for every move of the spotlight; once a drag of the docked one ends, snap it to an edge
+pub fn handle_moved(window: &WebviewWindow, position: PhysicalPosition<i32>) {
+    let state = window.state::<SidebarState>();
+    if !state.docked.load(Ordering::SeqCst) || *state.placed.lock().unwrap() == Some(position) {
+        return;
+    }
+    let generation = state.moves.fetch_add(1, Ordering::SeqCst) + 1;
+    let window = window.clone();
+    std::thread::spawn(move || {
+        std::thread::sleep(SETTLE_DELAY);
+        if window.state::<SidebarState>().moves.load(Ordering::SeqCst) != generation {
+            return;
+        }
+        let target = window.clone();
+        let result = window.run_on_main_thread(move || {
+            if let Err(e) = redock(&target) {
+                eprintln!("Failed to redock the spotlight: {}", e);
+            }
+        });
+        if let Err(e) = result {
+            eprintln!("Failed to redock the spotlight: {}", e);
+        }
+    });
+}
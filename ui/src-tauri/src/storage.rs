@@ -0,0 +1,151 @@
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::{paths, privacy};
+
+// What a file holds, which decides whether it may be written while privacy mode is on
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Data {
+    // Settings and whatever the user set up on purpose; privacy mode is a setting too
+    Config,
+    // What the user does: history, prompts, notifications, logs, recordings, telemetry
+    Activity,
+    // Activity being deleted, sent or cleared. Nothing recorded in privacy mode gets this far,
+    // so it's written even then, or what was removed would come back after a restart.
+    Removal,
+}
+
+// The privacy gate in front of every persistence sink. Activity is only written down while
+// privacy mode is off; sinks that don't go through `save_json` ask here before writing.
+pub fn may_write(app_handle: &tauri::AppHandle, data: Data) -> bool {
+    allowed(data, in_privacy_mode(app_handle, data))
+}
+
+// Whether what the user does may be written down now
+pub fn may_record(app_handle: &tauri::AppHandle) -> bool {
+    may_write(app_handle, Data::Activity)
+}
+
+// For commands that start keeping activity, with `refusal` saying what can't happen, e.g.
+// "Macros can't be recorded"
+pub fn check_may_record(app_handle: &tauri::AppHandle, refusal: &str) -> Result<(), String> {
+    if may_record(app_handle) {
+        Ok(())
+    } else {
+        Err(format!("{} in privacy mode", refusal))
+    }
+}
+
+// Settings are saved with their lock held, so privacy mode is only looked up when it matters
+fn in_privacy_mode(app_handle: &tauri::AppHandle, data: Data) -> bool {
+    data == Data::Activity && privacy::is_active(app_handle)
+}
+
+fn allowed(data: Data, private: bool) -> bool {
+    data != Data::Activity || !private
+}
+
+// Get the app data directory, creating it if needed
+pub fn data_dir(app_handle: &tauri::AppHandle) -> Result<PathBuf, String> {
+    paths::data_dir(app_handle)
+}
+
+// Load a JSON file from the app data directory, falling back to defaults
+pub fn load_json<T: DeserializeOwned + Default>(app_handle: &tauri::AppHandle, file_name: &str) -> T {
+    match data_dir(app_handle) {
+        Ok(dir) => load_in(&dir, file_name),
+        Err(e) => {
+            eprintln!("{}", e);
+            T::default()
+        }
+    }
+}
+
+// The same, from a given directory
+pub fn load_in<T: DeserializeOwned + Default>(dir: &Path, file_name: &str) -> T {
+    let path = dir.join(file_name);
+    if !path.exists() {
+        return T::default();
+    }
+
+    match fs::read_to_string(&path) {
+        Ok(contents) => serde_json::from_str(&contents).unwrap_or_else(|e| {
+            eprintln!("Failed to parse {:?}: {}", path, e);
+            T::default()
+        }),
+        Err(e) => {
+            eprintln!("Failed to read {:?}: {}", path, e);
+            T::default()
+        }
+    }
+}
+
+// Save a value as JSON in the app data directory; activity is left unwritten in privacy mode
+pub fn save_json<T: Serialize>(
+    app_handle: &tauri::AppHandle,
+    file_name: &str,
+    value: &T,
+    data: Data,
+) -> Result<(), String> {
+    save_in(&data_dir(app_handle)?, file_name, value, data, in_privacy_mode(app_handle, data))
+}
+
+pub fn save_in<T: Serialize>(dir: &Path, file_name: &str, value: &T, data: Data, private: bool) -> Result<(), String> {
+    if !allowed(data, private) {
+        return Ok(());
+    }
+    let path = dir.join(file_name);
+    let contents = serde_json::to_string_pretty(value)
+        .map_err(|e| format!("Failed to serialize {}: {}", file_name, e))?;
+
+    // Write to a temp file first so a crash never leaves a half-written file behind
+    let tmp_path = path.with_extension("json.tmp");
+    fs::write(&tmp_path, contents).map_err(|e| format!("Failed to write {:?}: {}", tmp_path, e))?;
+    fs::rename(&tmp_path, &path).map_err(|e| format!("Failed to save {:?}: {}", path, e))?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("krya-storage-{}-{}", std::process::id(), name));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn files_in(dir: &Path) -> usize {
+        fs::read_dir(dir).unwrap().count()
+    }
+
+    #[test]
+    fn privacy_mode_writes_no_activity() {
+        let dir = temp_dir("private");
+        save_in(&dir, "history.json", &vec!["open the report"], Data::Activity, true).unwrap();
+        assert_eq!(files_in(&dir), 0);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn privacy_mode_still_writes_config() {
+        let dir = temp_dir("config");
+        save_in(&dir, "settings.json", &vec!["privacy"], Data::Config, true).unwrap();
+        assert_eq!(files_in(&dir), 1);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn activity_is_written_otherwise() {
+        let dir = temp_dir("public");
+        save_in(&dir, "history.json", &vec!["open the report"], Data::Activity, false).unwrap();
+        let saved: Vec<String> = serde_json::from_str(&fs::read_to_string(dir.join("history.json")).unwrap()).unwrap();
+        assert_eq!(saved, vec!["open the report"]);
+        assert_eq!(files_in(&dir), 1);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,152 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tauri::{Emitter, Manager};
+use tungstenite::Message;
+
+use crate::backend;
+use crate::jobs::{self, JobPhase};
+
+// How long to wait before reconnecting to a backend that isn't up yet or went away
+const RECONNECT_DELAY: Duration = Duration::from_secs(2);
+
+// Jobs whose output is kept for windows that reload, the most recently active ones
+const MAX_STREAMS: usize = 20;
+
+// The model's reply to a job as it streams in, on `job://<id>/delta` in order of `seq`
+#[derive(Clone, Debug, Serialize)]
+pub struct StreamEvent {
+    pub seq: u64,
+    #[serde(flatten)]
+    pub kind: StreamEventKind,
+}
+
+#[derive(Clone, Debug, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum StreamEventKind {
+    // Appends to the output of `attempt`; a retry starts a new one
+    Delta { attempt: u32, text: String },
+    // Nothing follows; no phase when the job stopped being followed before it finished
+    Done { phase: Option<JobPhase> },
+}
+
+// A streamed piece of a reply, as the backend publishes it on its log socket
+#[derive(Deserialize)]
+struct DeltaEntry {
+    job_id: String,
+    kind: String,
+    #[serde(default)]
+    attempt: u32,
+    #[serde(default)]
+    text: String,
+}
+
+struct JobStream {
+    events: Vec<StreamEvent>,
+    finished: bool,
+    updated: Instant,
+}
+
+#[derive(Default)]
+pub struct StreamState {
+    jobs: Mutex<HashMap<String, JobStream>>,
+}
+
+fn channel(job_id: &str) -> String {
+    format!("job://{}/delta", job_id)
+}
+
+fn publish(app_handle: &tauri::AppHandle, job_id: &str, kind: StreamEventKind) {
+    let state = app_handle.state::<StreamState>();
+    let event = {
+        let mut jobs = state.jobs.lock().unwrap();
+        let stream = jobs.entry(job_id.to_string()).or_insert_with(|| JobStream {
+            events: Vec::new(),
+            finished: false,
+            updated: Instant::now(),
+        });
+        if stream.finished {
+            return;
+        }
+        let event = StreamEvent {
+            seq: stream.events.len() as u64 + 1,
+            kind,
+        };
+        stream.finished = matches!(event.kind, StreamEventKind::Done { .. });
+        stream.updated = Instant::now();
+        stream.events.push(event.clone());
+
+        // Jobs submitted behind the shell's back never get a done marker, so go by activity
+        if jobs.len() > MAX_STREAMS {
+            let stalest = jobs
+                .iter()
+                .min_by_key(|(_, stream)| stream.updated)
+                .map(|(id, _)| id.clone());
+            if let Some(id) = stalest {
+                jobs.remove(&id);
+            }
+        }
+        event
+    };
+    if let Err(e) = app_handle.emit(&channel(job_id), event) {
+        eprintln!("Failed to emit {}: {}", channel(job_id), e);
+    }
+}
+
+// Called by the job tracker once a job has finished, closing its stream
+pub fn finish(app_handle: &tauri::AppHandle, job_id: &str, phase: Option<JobPhase>) {
+    publish(app_handle, job_id, StreamEventKind::Done { phase });
+}
+
+type LogSocket = tungstenite::WebSocket<tungstenite::stream::MaybeTlsStream<std::net::TcpStream>>;
+
+// Publish the delta entries arriving on the log socket until it closes, returning why it did. A job
+// waiting for approval is followed from then on, so it's reviewed even if the shell didn't start it.
+fn relay(app_handle: &tauri::AppHandle, mut socket: LogSocket) -> String {
+    loop {
+        let text = match socket.read() {
+            Ok(Message::Text(text)) => text,
+            Ok(_) => continue,
+            Err(e) => return format!("Log stream closed: {}", e),
+        };
+        // Ordinary log lines don't have a kind
+        if let Ok(entry) = serde_json::from_str::<DeltaEntry>(&text) {
+            if entry.kind == "delta" {
+                let kind = StreamEventKind::Delta {
+                    attempt: entry.attempt,
+                    text: entry.text,
+                };
+                publish(app_handle, &entry.job_id, kind);
+            } else if entry.kind == "awaiting_approval" {
+                jobs::track(app_handle, &entry.job_id);
+            }
+        }
+    }
+}
+
+// Relay streamed model output from the backend to per-job event channels, reconnecting when it restarts
+pub fn start(app_handle: &tauri::AppHandle) {
+    let app_handle = app_handle.clone();
+    let url = format!("{}/logs", backend::base_url().replacen("http", "ws", 1));
+    std::thread::spawn(move || loop {
+        // Failing to connect just means the backend is still starting
+        if let Ok((socket, _)) = tungstenite::connect(url.as_str()) {
+            eprintln!("{}", relay(&app_handle, socket));
+        }
+        std::thread::sleep(RECONNECT_DELAY);
+    });
+}
+
+// Command for a window that (re)loaded mid-job to catch up on output it missed, after event `after`
+#[tauri::command]
+pub fn get_job_stream(state: tauri::State<StreamState>, job_id: String, after: Option<u64>) -> Vec<StreamEvent> {
+    let after = after.unwrap_or(0);
+    state
+        .jobs
+        .lock()
+        .unwrap()
+        .get(&job_id)
+        .map(|stream| stream.events.iter().filter(|event| event.seq > after).cloned().collect())
+        .unwrap_or_default()
+}
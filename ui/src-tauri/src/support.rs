@@ -0,0 +1,91 @@
+use serde_json::{json, Value};
+use std::fs::File;
+use std::io::Write;
+use std::path::Path;
+use std::process::Command;
+use zip::write::FileOptions;
+use zip::ZipWriter;
+use tauri::Manager;
+
+use crate::crash::{self, CrashReporter};
+use crate::protocol::{self, Method, NoParams};
+use crate::util::now_secs;
+use crate::{logs, settings, storage, system_info};
+
+// How much of each log goes into a bundle
+const LOG_TAIL_BYTES: u64 = 2 * 1024 * 1024;
+
+// Ask the backend for a report, recording why if it couldn't be reached
+fn backend_report<M: Method<Params = NoParams, Result = Value>>() -> Value {
+    protocol::call::<M>(&NoParams {}).unwrap_or_else(|e| json!({ "error": e }))
+}
+
+fn add_file<W: Write + std::io::Seek>(zip: &mut ZipWriter<W>, name: &str, contents: &[u8]) -> Result<(), String> {
+    zip.start_file(name, FileOptions::default())
+        .map_err(|e| format!("Failed to add {} to support bundle: {}", name, e))?;
+    zip.write_all(contents)
+        .map_err(|e| format!("Failed to add {} to support bundle: {}", name, e))
+}
+
+fn add_json<W: Write + std::io::Seek>(zip: &mut ZipWriter<W>, name: &str, value: &Value) -> Result<(), String> {
+    let contents = serde_json::to_vec_pretty(value).map_err(|e| format!("Failed to serialize {}: {}", name, e))?;
+    add_file(zip, name, &contents)
+}
+
+// Show a file selected in Finder / Explorer, or open its folder on Linux
+pub fn reveal_in_file_manager(path: &Path) -> Result<(), String> {
+    #[cfg(target_os = "macos")]
+    let result = Command::new("open").arg("-R").arg(path).spawn();
+
+    #[cfg(target_os = "windows")]
+    let result = Command::new("explorer").arg(format!("/select,{}", path.display())).spawn();
+
+    #[cfg(not(any(target_os = "macos", target_os = "windows")))]
+    let result = Command::new("xdg-open")
+        .arg(path.parent().unwrap_or(path))
+        .spawn();
+
+    result
+        .map(|_| ())
+        .map_err(|e| format!("Failed to open file manager: {}", e))
+}
+
+// Command to zip logs, redacted config, system info, crash reports, and the backend doctor report.
+// Returns the archive path after revealing it in the file manager.
+#[tauri::command(async)]
+pub fn generate_support_bundle(app_handle: tauri::AppHandle) -> Result<String, String> {
+    let dir = storage::data_dir(&app_handle)?.join("support");
+    std::fs::create_dir_all(&dir).map_err(|e| format!("Failed to create {:?}: {}", dir, e))?;
+
+    let timestamp = now_secs();
+    let path = dir.join(format!("krya-support-{}.zip", timestamp));
+    let file = File::create(&path).map_err(|e| format!("Failed to create {:?}: {}", path, e))?;
+    let mut zip = ZipWriter::new(file);
+
+    for log_file in [logs::APP_LOG_FILE, logs::BACKEND_LOG_FILE] {
+        if let Some(contents) = logs::tail(&app_handle, log_file, LOG_TAIL_BYTES) {
+            add_file(&mut zip, &format!("logs/{}", log_file), contents.as_bytes())?;
+        }
+    }
+
+    // /config already masks the API key
+    add_json(&mut zip, "config.json", &backend_report::<protocol::GetConfig>())?;
+    let native_settings = serde_json::to_value(settings::current(&app_handle)).unwrap_or(Value::Null);
+    add_json(&mut zip, "settings.json", &native_settings)?;
+    let system = serde_json::to_value(system_info::collect(&app_handle)).unwrap_or(Value::Null);
+    add_json(&mut zip, "system_info.json", &system)?;
+    add_json(&mut zip, "doctor.json", &backend_report::<protocol::Doctor>())?;
+
+    for report in crash::pending_reports(&app_handle.state::<CrashReporter>()) {
+        let value = serde_json::to_value(&report).unwrap_or(Value::Null);
+        add_json(&mut zip, &format!("crashes/{}.json", report.id), &value)?;
+    }
+
+    zip.finish()
+        .map_err(|e| format!("Failed to write support bundle: {}", e))?;
+
+    if let Err(e) = reveal_in_file_manager(&path) {
+        eprintln!("{}", e);
+    }
+    Ok(path.to_string_lossy().to_string())
+}
@@ -0,0 +1,174 @@
+use serde::Serialize;
+use std::process::Command;
+use sysinfo::System;
+
+use crate::python;
+
+#[derive(Clone, Debug, Serialize)]
+pub struct OsInfo {
+    pub name: String,
+    pub version: String,
+    pub kernel: String,
+    pub arch: String,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct CpuInfo {
+    pub brand: String,
+    pub physical_cores: Option<usize>,
+    pub logical_cores: usize,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct DisplayInfo {
+    pub name: Option<String>,
+    pub width: u32,
+    pub height: u32,
+    pub x: i32,
+    pub y: i32,
+    pub scale_factor: f64,
+    pub primary: bool,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct PythonInfo {
+    pub command: String,
+    pub version: String,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct SystemInfo {
+    pub os: OsInfo,
+    pub cpu: CpuInfo,
+    pub total_memory_bytes: u64,
+    pub available_memory_bytes: u64,
+    pub gpus: Vec<String>,
+    pub displays: Vec<DisplayInfo>,
+    // The interpreter the backend would be started with, if one was found
+    pub python: Option<PythonInfo>,
+    pub app_version: String,
+    pub tauri_version: String,
+}
+
+fn command_output(program: &str, args: &[&str]) -> Option<String> {
+    let output = Command::new(program).args(args).output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    Some(String::from_utf8_lossy(&output.stdout).to_string())
+}
+
+#[cfg(target_os = "macos")]
+fn gpu_names() -> Vec<String> {
+    command_output("system_profiler", &["SPDisplaysDataType"])
+        .map(|output| {
+            output
+                .lines()
+                .filter_map(|line| line.trim().strip_prefix("Chipset Model:"))
+                .map(|name| name.trim().to_string())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+#[cfg(target_os = "windows")]
+fn gpu_names() -> Vec<String> {
+    command_output(
+        "powershell",
+        &[
+            "-NoProfile",
+            "-Command",
+            "Get-CimInstance Win32_VideoController | Select-Object -ExpandProperty Name",
+        ],
+    )
+    .map(|output| {
+        output
+            .lines()
+            .map(|line| line.trim().to_string())
+            .filter(|line| !line.is_empty())
+            .collect()
+    })
+    .unwrap_or_default()
+}
+
+#[cfg(not(any(target_os = "macos", target_os = "windows")))]
+fn gpu_names() -> Vec<String> {
+    command_output("lspci", &[])
+        .map(|output| {
+            output
+                .lines()
+                .filter(|line| line.contains("VGA compatible controller") || line.contains("3D controller"))
+                .filter_map(|line| line.split_once(": ").map(|(_, name)| name))
+                .map(|name| name.trim().to_string())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+// The Python the backend would use, as start_api_server picks it
+pub fn python_info(app_handle: &tauri::AppHandle) -> Option<PythonInfo> {
+    let interpreter = python::resolve(app_handle).ok()?;
+    Some(PythonInfo {
+        command: interpreter.path.display().to_string(),
+        version: interpreter.version,
+    })
+}
+
+fn displays(app_handle: &tauri::AppHandle) -> Vec<DisplayInfo> {
+    let primary = app_handle.primary_monitor().ok().flatten();
+
+    app_handle
+        .available_monitors()
+        .unwrap_or_default()
+        .iter()
+        .map(|monitor| DisplayInfo {
+            name: monitor.name().cloned(),
+            width: monitor.size().width,
+            height: monitor.size().height,
+            x: monitor.position().x,
+            y: monitor.position().y,
+            scale_factor: monitor.scale_factor(),
+            primary: primary
+                .as_ref()
+                .map(|p| p.name() == monitor.name() && p.position() == monitor.position())
+                .unwrap_or(false),
+        })
+        .collect()
+}
+
+pub fn collect(app_handle: &tauri::AppHandle) -> SystemInfo {
+    let mut system = System::new();
+    system.refresh_memory();
+    system.refresh_cpu();
+
+    SystemInfo {
+        os: OsInfo {
+            name: System::name().unwrap_or_else(|| std::env::consts::OS.to_string()),
+            version: System::long_os_version().unwrap_or_default(),
+            kernel: System::kernel_version().unwrap_or_default(),
+            arch: std::env::consts::ARCH.to_string(),
+        },
+        cpu: CpuInfo {
+            brand: system
+                .cpus()
+                .first()
+                .map(|cpu| cpu.brand().trim().to_string())
+                .unwrap_or_default(),
+            physical_cores: system.physical_core_count(),
+            logical_cores: system.cpus().len(),
+        },
+        total_memory_bytes: system.total_memory(),
+        available_memory_bytes: system.available_memory(),
+        gpus: gpu_names(),
+        displays: displays(app_handle),
+        python: python_info(app_handle),
+        app_version: app_handle.package_info().version.to_string(),
+        tauri_version: tauri::VERSION.to_string(),
+    }
+}
+
+// Command for the About pane; also included in support bundles
+#[tauri::command(async)]
+pub fn get_system_info(app_handle: tauri::AppHandle) -> SystemInfo {
+    collect(&app_handle)
+}
@@ -0,0 +1,62 @@
+use tauri::Manager;
+
+// What the spotlight's taskbar button shows
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TaskbarState {
+    None,
+    Indeterminate,
+    Normal(u8),
+    Error,
+}
+
+// Progress on the Windows taskbar button; other platforms have no equivalent we use
+#[cfg(target_os = "windows")]
+fn set_state(window: &tauri::WebviewWindow, state: TaskbarState) -> Result<(), String> {
+    use windows::Win32::Foundation::HWND;
+    use windows::Win32::System::Com::{CoCreateInstance, CoInitializeEx, CLSCTX_INPROC_SERVER, COINIT_APARTMENTTHREADED};
+    use windows::Win32::UI::Shell::{
+        ITaskbarList3, TaskbarList, TBPF_ERROR, TBPF_INDETERMINATE, TBPF_NOPROGRESS, TBPF_NORMAL,
+    };
+
+    let hwnd = HWND(window.hwnd().map_err(|e| format!("Failed to get the window handle: {}", e))?.0 as isize);
+    let error = |e: windows::core::Error| format!("Failed to update taskbar progress: {}", e);
+    unsafe {
+        // The webview has already set up COM on the main thread, so this only adds a reference
+        let _ = CoInitializeEx(None, COINIT_APARTMENTTHREADED);
+        let taskbar: ITaskbarList3 = CoCreateInstance(&TaskbarList, None, CLSCTX_INPROC_SERVER).map_err(error)?;
+        taskbar.HrInit().map_err(error)?;
+        match state {
+            TaskbarState::None => taskbar.SetProgressState(hwnd, TBPF_NOPROGRESS),
+            TaskbarState::Indeterminate => taskbar.SetProgressState(hwnd, TBPF_INDETERMINATE),
+            TaskbarState::Normal(percent) => taskbar
+                .SetProgressState(hwnd, TBPF_NORMAL)
+                .and_then(|_| taskbar.SetProgressValue(hwnd, percent.min(100) as u64, 100)),
+            TaskbarState::Error => taskbar
+                .SetProgressState(hwnd, TBPF_ERROR)
+                .and_then(|_| taskbar.SetProgressValue(hwnd, 100, 100)),
+        }
+        .map_err(error)
+    }
+}
+
+#[cfg(not(target_os = "windows"))]
+fn set_state(_window: &tauri::WebviewWindow, _state: TaskbarState) -> Result<(), String> {
+    Ok(())
+}
+
+// Show job progress on the spotlight's taskbar button, from any thread
+pub fn show(app_handle: &tauri::AppHandle, state: TaskbarState) {
+    let window = match app_handle.get_webview_window("main") {
+        Some(window) => window,
+        None => return,
+    };
+    // The taskbar's COM interface belongs to the UI thread
+    let result = app_handle.run_on_main_thread(move || {
+        if let Err(e) = set_state(&window, state) {
+            eprintln!("{}", e);
+        }
+    });
+    if let Err(e) = result {
+        eprintln!("Failed to update taskbar progress: {}", e);
+    }
+}
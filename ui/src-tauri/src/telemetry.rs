@@ -0,0 +1,161 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tauri::Manager;
+
+use crate::util::now_secs;
+use crate::{policy, privacy, settings, storage, tls};
+
+const QUEUE_FILE: &str = "telemetry_queue.json";
+
+// How often queued events are sent
+const FLUSH_INTERVAL: Duration = Duration::from_secs(15 * 60);
+
+// Oldest events are dropped past this, so a missing endpoint can't grow the queue forever
+const MAX_QUEUED_EVENTS: usize = 1000;
+
+// A coarse usage event; properties never contain prompts, results, or paths
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct TelemetryEvent {
+    pub name: String,
+    pub timestamp: u64,
+    #[serde(default)]
+    pub properties: HashMap<String, String>,
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct TelemetryQueue {
+    // Random per-install id, not tied to the user or machine
+    #[serde(default)]
+    pub install_id: String,
+    #[serde(default)]
+    pub events: Vec<TelemetryEvent>,
+}
+
+// What the settings pane shows before the user decides
+#[derive(Clone, Debug, Serialize)]
+pub struct TelemetryPreview {
+    pub enabled: bool,
+    pub install_id: String,
+    pub events: Vec<TelemetryEvent>,
+}
+
+pub struct TelemetryState {
+    queue: Arc<Mutex<TelemetryQueue>>,
+}
+
+impl TelemetryState {
+    pub fn load(app_handle: &tauri::AppHandle) -> Self {
+        let mut queue: TelemetryQueue = storage::load_json(app_handle, QUEUE_FILE);
+        if queue.install_id.is_empty() {
+            queue.install_id = uuid::Uuid::new_v4().to_string();
+        }
+        TelemetryState {
+            queue: Arc::new(Mutex::new(queue)),
+        }
+    }
+
+    // Send queued events on a background thread every FLUSH_INTERVAL
+    pub fn start_flushing(&self, app_handle: &tauri::AppHandle) {
+        let queue = self.queue.clone();
+        let app_handle = app_handle.clone();
+        std::thread::spawn(move || loop {
+            std::thread::sleep(FLUSH_INTERVAL);
+            if let Err(e) = flush(&app_handle, &queue) {
+                eprintln!("Failed to send telemetry: {}", e);
+            }
+        });
+    }
+}
+
+// Queue an event; a no-op unless the user enabled telemetry and privacy mode is off
+pub fn record(app_handle: &tauri::AppHandle, name: &str, properties: &[(&str, &str)]) {
+    if !settings::current(app_handle).telemetry.enabled || !storage::may_record(app_handle) {
+        return;
+    }
+
+    let state = app_handle.state::<TelemetryState>();
+    let mut queue = state.queue.lock().unwrap();
+    queue.events.push(TelemetryEvent {
+        name: name.to_string(),
+        timestamp: now_secs(),
+        properties: properties
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect(),
+    });
+    if queue.events.len() > MAX_QUEUED_EVENTS {
+        let excess = queue.events.len() - MAX_QUEUED_EVENTS;
+        queue.events.drain(..excess);
+    }
+
+    if let Err(e) = storage::save_json(app_handle, QUEUE_FILE, &*queue, storage::Data::Activity) {
+        eprintln!("{}", e);
+    }
+}
+
+fn flush(app_handle: &tauri::AppHandle, queue: &Mutex<TelemetryQueue>) -> Result<(), String> {
+    let config = settings::current(app_handle).telemetry;
+    let endpoint = match config.endpoint {
+        Some(endpoint) if config.enabled && !endpoint.trim().is_empty() && !privacy::is_active(app_handle) => endpoint,
+        _ => return Ok(()),
+    };
+
+    let batch = {
+        let queue = queue.lock().unwrap();
+        if queue.events.is_empty() {
+            return Ok(());
+        }
+        queue.clone()
+    };
+
+    tls::client_builder()
+        .timeout(Duration::from_secs(15))
+        .build()
+        .map_err(|e| format!("Failed to create HTTP client: {}", e))?
+        .post(&endpoint)
+        .json(&batch)
+        .send()
+        .and_then(|r| r.error_for_status())
+        .map_err(|e| e.to_string())?;
+
+    // Only drop what was sent; events recorded meanwhile stay queued
+    let mut queue = queue.lock().unwrap();
+    let sent = batch.events.len().min(queue.events.len());
+    queue.events.drain(..sent);
+    storage::save_json(app_handle, QUEUE_FILE, &*queue, storage::Data::Removal)
+}
+
+// Command to turn telemetry on or off; turning it off also discards anything queued
+#[tauri::command]
+pub fn set_telemetry(
+    app_handle: tauri::AppHandle,
+    state: tauri::State<TelemetryState>,
+    enabled: bool,
+) -> Result<(), String> {
+    if enabled {
+        policy::check_unlocked("telemetry.enabled")?;
+    }
+    let mut current = settings::current(&app_handle);
+    current.telemetry.enabled = enabled;
+    settings::replace(&app_handle, current)?;
+
+    if !enabled {
+        let mut queue = state.queue.lock().unwrap();
+        queue.events.clear();
+        storage::save_json(&app_handle, QUEUE_FILE, &*queue, storage::Data::Removal)?;
+    }
+    Ok(())
+}
+
+// Command to show exactly what would be sent
+#[tauri::command]
+pub fn get_telemetry_preview(app_handle: tauri::AppHandle, state: tauri::State<TelemetryState>) -> TelemetryPreview {
+    let queue = state.queue.lock().unwrap();
+    TelemetryPreview {
+        enabled: settings::current(&app_handle).telemetry.enabled,
+        install_id: queue.install_id.clone(),
+        events: queue.events.clone(),
+    }
+}
@@ -0,0 +1,237 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tauri::Manager;
+
+use crate::{active_app, app_profiles, input, quick_actions, storage};
+
+const STORE_FILE: &str = "prompt_templates.json";
+
+// Filled in by the shell unless the caller passes a value of its own
+const SELECTION: &str = "selection";
+const CLIPBOARD: &str = "clipboard";
+const APP: &str = "app";
+
+// Long enough for the frontmost application to put its selection on the clipboard
+const COPY_DELAY: Duration = Duration::from_millis(150);
+
+// A selection belongs to the spotlight it was captured for, not to one opened much later
+const SELECTION_TTL: Duration = Duration::from_secs(10 * 60);
+
+// A shared prompt with {{placeholder}} variables; {{selection}}, {{clipboard}} and {{app}} are built in
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct PromptTemplate {
+    #[serde(default)]
+    pub id: String,
+    pub title: String,
+    pub body: String,
+    #[serde(default)]
+    pub description: Option<String>,
+}
+
+// Template as listed in the spotlight, with the variables it still needs from the user
+#[derive(Clone, Debug, Serialize)]
+pub struct TemplateEntry {
+    #[serde(flatten)]
+    pub template: PromptTemplate,
+    pub variables: Vec<String>,
+}
+
+pub struct TemplatesState {
+    templates: Mutex<Vec<PromptTemplate>>,
+    // Text selected in the application the spotlight was opened over, and when it was copied
+    selection: Mutex<Option<(String, Instant)>>,
+}
+
+impl TemplatesState {
+    pub fn load(app_handle: &tauri::AppHandle) -> Self {
+        TemplatesState {
+            templates: Mutex::new(storage::load_json(app_handle, STORE_FILE)),
+            selection: Mutex::new(None),
+        }
+    }
+}
+
+fn is_builtin(name: &str) -> bool {
+    name == SELECTION || name == CLIPBOARD || name == APP
+}
+
+fn entry(template: &PromptTemplate) -> TemplateEntry {
+    TemplateEntry {
+        variables: quick_actions::placeholders(&template.body)
+            .into_iter()
+            .filter(|name| !is_builtin(name))
+            .collect(),
+        template: template.clone(),
+    }
+}
+
+pub fn all(app_handle: &tauri::AppHandle) -> Vec<PromptTemplate> {
+    app_handle.state::<TemplatesState>().templates.lock().unwrap().clone()
+}
+
+fn save(app_handle: &tauri::AppHandle, templates: &[PromptTemplate]) -> Result<(), String> {
+    storage::save_json(app_handle, STORE_FILE, &templates, storage::Data::Config)
+}
+
+// Replace every template (used when importing settings)
+pub fn replace_all(app_handle: &tauri::AppHandle, templates: Vec<PromptTemplate>) -> Result<(), String> {
+    let state = app_handle.state::<TemplatesState>();
+    let mut current = state.templates.lock().unwrap();
+    save(app_handle, &templates)?;
+    *current = templates;
+    Ok(())
+}
+
+fn read_clipboard() -> Option<String> {
+    arboard::Clipboard::new().and_then(|mut clipboard| clipboard.get_text()).ok()
+}
+
+// Copy the frontmost application's selection before the spotlight takes focus from it.
+// Pressing the copy shortcut is input, so it's only done when a template asks for the selection
+// and the application's profile allows input; the clipboard is put back afterwards.
+pub fn capture_selection(app_handle: &tauri::AppHandle) {
+    let state = app_handle.state::<TemplatesState>();
+    let wanted = state
+        .templates
+        .lock()
+        .unwrap()
+        .iter()
+        .any(|template| quick_actions::placeholders(&template.body).iter().any(|name| name == SELECTION));
+    let ours = active_app::frontmost_app().map_or(true, |app| app.process_id == std::process::id() as u64);
+    if !wanted || ours || input::check_target(app_handle).is_err() {
+        return;
+    }
+
+    let previous = read_clipboard();
+    if let Err(e) = input::press_copy(app_handle) {
+        return eprintln!("Failed to copy the selection: {}", e);
+    }
+    std::thread::sleep(COPY_DELAY);
+    let copied = read_clipboard();
+    // An unchanged clipboard means nothing was selected
+    let selection = copied.filter(|text| Some(text) != previous.as_ref() && !text.is_empty());
+    if selection.is_some() {
+        if let Some(previous) = previous {
+            if let Err(e) = arboard::Clipboard::new().and_then(|mut clipboard| clipboard.set_text(previous)) {
+                eprintln!("Failed to restore the clipboard: {}", e);
+            }
+        }
+    }
+    *state.selection.lock().unwrap() = selection.map(|text| (text, Instant::now()));
+}
+
+fn selection(app_handle: &tauri::AppHandle) -> Option<String> {
+    match app_handle.state::<TemplatesState>().selection.lock().unwrap().as_ref() {
+        Some((text, copied)) if copied.elapsed() < SELECTION_TTL => Some(text.clone()),
+        _ => None,
+    }
+}
+
+// Fill a template's variables: the caller's values first, then the built-ins it uses
+pub fn render(app_handle: &tauri::AppHandle, template: &PromptTemplate, vars: HashMap<String, String>) -> String {
+    let mut values = vars;
+    for name in quick_actions::placeholders(&template.body) {
+        if values.contains_key(&name) {
+            continue;
+        }
+        let value = match name.as_str() {
+            SELECTION => selection(app_handle),
+            CLIPBOARD => read_clipboard(),
+            APP => app_profiles::opened_over(app_handle).map(|app| app.name),
+            _ => None,
+        };
+        if let Some(value) = value {
+            values.insert(name, value);
+        }
+    }
+    quick_actions::fill_placeholders(&template.body, &values)
+}
+
+#[tauri::command]
+pub fn list_prompt_templates(state: tauri::State<TemplatesState>) -> Vec<TemplateEntry> {
+    state.templates.lock().unwrap().iter().map(entry).collect()
+}
+
+// Create a template, or update it when the id already exists
+#[tauri::command]
+pub fn save_prompt_template(
+    app_handle: tauri::AppHandle,
+    state: tauri::State<TemplatesState>,
+    mut template: PromptTemplate,
+) -> Result<TemplateEntry, String> {
+    let mut templates = state.templates.lock().unwrap();
+    if template.id.is_empty() {
+        template.id = uuid::Uuid::new_v4().to_string();
+    }
+    match templates.iter_mut().find(|t| t.id == template.id) {
+        Some(existing) => *existing = template.clone(),
+        None => templates.push(template.clone()),
+    }
+    save(&app_handle, &templates)?;
+    Ok(entry(&template))
+}
+
+#[tauri::command]
+pub fn delete_prompt_template(
+    app_handle: tauri::AppHandle,
+    state: tauri::State<TemplatesState>,
+    id: String,
+) -> Result<(), String> {
+    let mut templates = state.templates.lock().unwrap();
+    templates.retain(|t| t.id != id);
+    save(&app_handle, &templates)
+}
+
+// Render a template with the given variables; built-ins that aren't given are filled in
+#[tauri::command]
+pub fn render_template(
+    app_handle: tauri::AppHandle,
+    id: String,
+    vars: Option<HashMap<String, String>>,
+) -> Result<String, String> {
+    let template = all(&app_handle)
+        .into_iter()
+        .find(|t| t.id == id)
+        .ok_or_else(|| format!("Prompt template not found: {}", id))?;
+    Ok(render(&app_handle, &template, vars.unwrap_or_default()))
+}
+
+// Command to write templates to a file for sharing with a team; all of them unless ids are given
+#[tauri::command]
+pub fn export_prompt_templates(
+    app_handle: tauri::AppHandle,
+    path: String,
+    ids: Option<Vec<String>>,
+) -> Result<(), String> {
+    let templates: Vec<PromptTemplate> = all(&app_handle)
+        .into_iter()
+        .filter(|t| ids.as_ref().map_or(true, |ids| ids.contains(&t.id)))
+        .collect();
+    let contents =
+        serde_json::to_string_pretty(&templates).map_err(|e| format!("Failed to serialize templates: {}", e))?;
+    std::fs::write(&path, contents).map_err(|e| format!("Failed to write {}: {}", path, e))
+}
+
+// Command to add templates from a shared file; ones with an id that's already here are updated
+#[tauri::command]
+pub fn import_prompt_templates(app_handle: tauri::AppHandle, path: String) -> Result<Vec<TemplateEntry>, String> {
+    let contents = std::fs::read_to_string(&path).map_err(|e| format!("Failed to read {}: {}", path, e))?;
+    let imported: Vec<PromptTemplate> =
+        serde_json::from_str(&contents).map_err(|e| format!("Not a valid template file: {}", e))?;
+
+    let state = app_handle.state::<TemplatesState>();
+    let mut templates = state.templates.lock().unwrap();
+    for mut template in imported {
+        if template.id.is_empty() {
+            template.id = uuid::Uuid::new_v4().to_string();
+        }
+        match templates.iter_mut().find(|t| t.id == template.id) {
+            Some(existing) => *existing = template,
+            None => templates.push(template),
+        }
+    }
+    save(&app_handle, &templates)?;
+    Ok(templates.iter().map(entry).collect())
+}
@@ -0,0 +1,146 @@
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use once_cell::sync::Lazy;
+use reqwest::Certificate;
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+use crate::{settings, storage};
+
+// Subdirectory of the app data directory that imported certificates are copied into
+const CERT_DIR: &str = "certs";
+
+// Every extra root in one PEM file, for the backend to add to the ones it trusts already
+const BUNDLE_FILE: &str = "extra-ca-bundle.pem";
+
+// Roots trusted on top of the system's, as loaded from the settings
+static EXTRA_ROOTS: Lazy<Mutex<Vec<Certificate>>> = Lazy::new(|| Mutex::new(Vec::new()));
+
+fn cert_dir(app_handle: &tauri::AppHandle) -> Result<PathBuf, String> {
+    let dir = storage::data_dir(app_handle)?.join(CERT_DIR);
+    fs::create_dir_all(&dir).map_err(|e| format!("Failed to create {:?}: {}", dir, e))?;
+    Ok(dir)
+}
+
+// Certificates exported from a browser or handed out by IT come as PEM or DER; both end up as PEM
+fn to_pem(bytes: &[u8]) -> Result<String, String> {
+    if let Ok(text) = std::str::from_utf8(bytes) {
+        if text.contains("-----BEGIN CERTIFICATE-----") {
+            let certs = Certificate::from_pem_bundle(bytes).map_err(|e| format!("Invalid certificate: {}", e))?;
+            if certs.is_empty() {
+                return Err("No certificates found in the file".to_string());
+            }
+            return Ok(text.trim().to_string() + "\n");
+        }
+    }
+    Certificate::from_der(bytes).map_err(|e| format!("Invalid certificate: {}", e))?;
+    let encoded = BASE64.encode(bytes);
+    let lines: Vec<&str> = encoded
+        .as_bytes()
+        .chunks(64)
+        .map(|line| std::str::from_utf8(line).unwrap_or_default())
+        .collect();
+    Ok(format!("-----BEGIN CERTIFICATE-----\n{}\n-----END CERTIFICATE-----\n", lines.join("\n")))
+}
+
+// A client for anything beyond localhost, trusting the extra roots as well
+pub fn client_builder() -> reqwest::blocking::ClientBuilder {
+    EXTRA_ROOTS
+        .lock()
+        .unwrap()
+        .iter()
+        .fold(reqwest::blocking::Client::builder(), |builder, cert| builder.add_root_certificate(cert.clone()))
+}
+
+fn bundle_path(app_handle: &tauri::AppHandle) -> Option<PathBuf> {
+    let path = cert_dir(app_handle).ok()?.join(BUNDLE_FILE);
+    if path.exists() {
+        Some(path)
+    } else {
+        None
+    }
+}
+
+// Load the configured roots and rewrite the backend's bundle; the backend picks it up when it next starts
+pub fn apply(app_handle: &tauri::AppHandle) {
+    let mut roots = Vec::new();
+    let mut bundle = String::new();
+    for path in settings::current(app_handle).tls.extra_ca_certificates {
+        let path = settings::expand_home(&path);
+        match fs::read(&path).map_err(|e| e.to_string()).and_then(|bytes| to_pem(&bytes)) {
+            Ok(pem) => match Certificate::from_pem_bundle(pem.as_bytes()) {
+                Ok(certs) => {
+                    roots.extend(certs);
+                    bundle.push_str(&pem);
+                }
+                Err(e) => eprintln!("Skipping certificate {:?}: {}", path, e),
+            },
+            Err(e) => eprintln!("Skipping certificate {:?}: {}", path, e),
+        }
+    }
+    *EXTRA_ROOTS.lock().unwrap() = roots;
+
+    let dir = match cert_dir(app_handle) {
+        Ok(dir) => dir,
+        Err(e) => return eprintln!("{}", e),
+    };
+    let path = dir.join(BUNDLE_FILE);
+    let result = if bundle.is_empty() {
+        fs::remove_file(&path).or_else(|e| if path.exists() { Err(e) } else { Ok(()) })
+    } else {
+        fs::write(&path, bundle)
+    };
+    if let Err(e) = result {
+        eprintln!("Failed to update {:?}: {}", path, e);
+    }
+}
+
+// Environment telling the backend which extra roots to trust
+pub fn env_hints(app_handle: &tauri::AppHandle) -> Vec<(&'static str, String)> {
+    match bundle_path(app_handle) {
+        Some(path) => vec![("KRYA_EXTRA_CA_BUNDLE", path.display().to_string())],
+        None => Vec::new(),
+    }
+}
+
+// Command to trust a root certificate, e.g. the one a corporate proxy signs intercepted connections with.
+// It's copied into the app's data so it keeps working if the original is moved.
+#[tauri::command]
+pub fn import_ca_certificate(app_handle: tauri::AppHandle, path: String) -> Result<Vec<String>, String> {
+    let source = settings::expand_home(&path);
+    let bytes = fs::read(&source).map_err(|e| format!("Failed to read {:?}: {}", source, e))?;
+    let pem = to_pem(&bytes)?;
+
+    let digest: String = Sha256::digest(pem.as_bytes())
+        .iter()
+        .take(8)
+        .map(|byte| format!("{:02x}", byte))
+        .collect();
+    let target = cert_dir(&app_handle)?.join(format!("{}.pem", digest));
+    fs::write(&target, pem).map_err(|e| format!("Failed to save {:?}: {}", target, e))?;
+
+    let mut updated = settings::current(&app_handle);
+    let target = target.display().to_string();
+    if !updated.tls.extra_ca_certificates.contains(&target) {
+        updated.tls.extra_ca_certificates.push(target);
+    }
+    let certificates = updated.tls.extra_ca_certificates.clone();
+    settings::replace(&app_handle, updated)?;
+    Ok(certificates)
+}
+
+// Command to stop trusting a certificate; copies made by importing it are deleted too
+#[tauri::command]
+pub fn remove_ca_certificate(app_handle: tauri::AppHandle, path: String) -> Result<Vec<String>, String> {
+    let mut updated = settings::current(&app_handle);
+    updated.tls.extra_ca_certificates.retain(|existing| *existing != path);
+    let certificates = updated.tls.extra_ca_certificates.clone();
+    settings::replace(&app_handle, updated)?;
+
+    let imported = PathBuf::from(&path);
+    if imported.parent() == Some(cert_dir(&app_handle)?.as_path()) {
+        let _ = fs::remove_file(&imported);
+    }
+    Ok(certificates)
+}
@@ -0,0 +1,159 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, ExitStatus, Stdio};
+use std::time::Duration;
+use walkdir::WalkDir;
+
+use crate::{idle, paths, settings};
+
+const CHECK_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+// Raw captures worth shrinking; MP4s are already what they'd become
+const RAW_EXTENSIONS: &[&str] = &["mov", "webm", "mkv", "avi"];
+
+// Written next to the recording and renamed once ffmpeg is done with it
+const PARTIAL_EXTENSION: &str = "part.mp4";
+
+fn is_partial(path: &Path) -> bool {
+    path.to_string_lossy().ends_with(&format!(".{}", PARTIAL_EXTENSION))
+}
+
+fn is_raw(path: &Path) -> bool {
+    path.extension()
+        .and_then(|extension| extension.to_str())
+        .is_some_and(|extension| RAW_EXTENSIONS.contains(&extension.to_lowercase().as_str()))
+}
+
+// Recordings still to transcode, clearing what an interrupted run left behind
+fn pending(dir: &Path) -> Vec<PathBuf> {
+    let mut sources = Vec::new();
+    for entry in WalkDir::new(dir).into_iter().filter_map(|entry| entry.ok()) {
+        let path = entry.into_path();
+        if !path.is_file() {
+            continue;
+        }
+        if is_partial(&path) {
+            let _ = fs::remove_file(&path);
+        } else if is_raw(&path) && !path.with_extension("mp4").exists() {
+            sources.push(path);
+        }
+    }
+    sources
+}
+
+fn ffmpeg_installed() -> bool {
+    Command::new("ffmpeg")
+        .arg("-version")
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .is_ok_and(|status| status.success())
+}
+
+fn spawn(source: &Path, target: &Path) -> Result<Child, String> {
+    let mut command = Command::new("ffmpeg");
+    command
+        .args(["-nostdin", "-loglevel", "error", "-y", "-i"])
+        .arg(source)
+        .args(["-c:v", "libx264", "-preset", "slow", "-crf", "28", "-c:a", "aac", "-f", "mp4"])
+        .arg(target)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null());
+    #[cfg(target_os = "windows")]
+    {
+        use std::os::windows::process::CommandExt;
+        command.creation_flags(windows_sys::Win32::System::Threading::CREATE_NO_WINDOW);
+    }
+    command.spawn().map_err(|e| format!("Failed to start ffmpeg: {}", e))
+}
+
+// Wait for ffmpeg to finish, or stop it the moment the user is back; None if it was stopped
+fn wait(app_handle: &tauri::AppHandle, child: &mut Child) -> Result<Option<ExitStatus>, String> {
+    loop {
+        if let Some(status) = child.try_wait().map_err(|e| format!("Failed to wait for ffmpeg: {}", e))? {
+            return Ok(Some(status));
+        }
+        if !idle::is_idle(app_handle) {
+            let _ = child.kill();
+            let _ = child.wait();
+            return Ok(None);
+        }
+        std::thread::sleep(POLL_INTERVAL);
+    }
+}
+
+// Re-encode one recording as an MP4 beside it, then delete the original. A run the user interrupts
+// starts over once they step away again, so nothing is left running if the app quits meanwhile.
+fn transcode(app_handle: &tauri::AppHandle, source: &Path) -> Result<PathBuf, String> {
+    let target = source.with_extension("mp4");
+    let partial = source.with_extension(PARTIAL_EXTENSION);
+    loop {
+        idle::wait_until_idle(app_handle);
+        let mut child = spawn(source, &partial)?;
+        match wait(app_handle, &mut child) {
+            Ok(Some(status)) if status.success() => break,
+            Ok(Some(status)) => {
+                let _ = fs::remove_file(&partial);
+                return Err(format!("ffmpeg exited with {}", status));
+            }
+            Ok(None) => {
+                let _ = fs::remove_file(&partial);
+                println!("Paused transcoding {:?} until the user is away", source);
+            }
+            Err(e) => {
+                let _ = child.kill();
+                let _ = fs::remove_file(&partial);
+                return Err(e);
+            }
+        }
+    }
+
+    // Keep the recording's age, so storage limits expire it on the same schedule
+    if let Ok(modified) = fs::metadata(source).and_then(|metadata| metadata.modified()) {
+        if let Err(e) = fs::File::options().write(true).open(&partial).and_then(|file| file.set_modified(modified)) {
+            eprintln!("Failed to keep the time of {:?}: {}", source, e);
+        }
+    }
+    fs::rename(&partial, &target).map_err(|e| format!("Failed to move {:?} to {:?}: {}", partial, target, e))?;
+    fs::remove_file(source).map_err(|e| format!("Failed to remove {:?}: {}", source, e))?;
+    Ok(target)
+}
+
+fn transcode_all(app_handle: &tauri::AppHandle) {
+    let dir = match paths::recordings_dir(app_handle) {
+        Ok(dir) => dir,
+        Err(e) => return eprintln!("{}", e),
+    };
+    let sources = pending(&dir);
+    if sources.is_empty() {
+        return;
+    }
+    if !ffmpeg_installed() {
+        println!("Not transcoding {} recordings, since ffmpeg isn't installed", sources.len());
+        return;
+    }
+    for source in sources {
+        if !settings::current(app_handle).storage.transcode_recordings {
+            return;
+        }
+        match transcode(app_handle, &source) {
+            Ok(target) => println!("Transcoded {:?} to {:?}", source, target),
+            Err(e) => eprintln!("Failed to transcode {:?}: {}", source, e),
+        }
+    }
+}
+
+// Shrink new screen recordings to MP4 every hour, only while the user is away and stopping as soon
+// as they return
+pub fn start(app_handle: &tauri::AppHandle) {
+    let app_handle = app_handle.clone();
+    std::thread::spawn(move || loop {
+        if settings::current(&app_handle).storage.transcode_recordings {
+            transcode_all(&app_handle);
+        }
+        std::thread::sleep(CHECK_INTERVAL);
+    });
+}
@@ -0,0 +1,454 @@
+use serde::Serialize;
+use tauri::image::Image;
+use tauri::menu::{Menu, MenuItem, PredefinedMenuItem, Submenu};
+use tauri::tray::{MouseButton, MouseButtonState, TrayIconBuilder, TrayIconEvent};
+use tauri::{Emitter, Manager, PhysicalPosition, PhysicalSize};
+
+use crate::capabilities::{self, Capability};
+use crate::history::{self, PinnedPrompt};
+use crate::profiles::{self, ProfileList};
+use crate::quick_actions::{self, QuickAction};
+use crate::settings::{self, TrayClickAction};
+#[cfg(target_os = "macos")]
+use crate::placement::Rect;
+#[cfg(target_os = "macos")]
+use crate::popover;
+use crate::errors::ReportExt;
+use crate::{automation, errors, privacy, AppState};
+
+const QUICK_ACTION_PREFIX: &str = "quick_action:";
+const PINNED_PREFIX: &str = "pinned:";
+const PROFILE_PREFIX: &str = "profile:";
+
+const TRAY_ID: &str = "main";
+
+// The app icon, as a template image on macOS
+const TRAY_ICON: &[u8] = include_bytes!("../icons/icon.png");
+
+// Color of the dot on the tray icon while privacy mode is on
+const PRIVACY_DOT: [u8; 4] = [0xE1, 0x1D, 0x48, 0xFF];
+
+// Window that stands in for the tray menu where there's no tray to put it in
+pub const MENU_WINDOW: &str = "menu";
+
+// One item of the tray menu, also listed by the windowed menu
+#[derive(Clone, Debug, Serialize)]
+pub struct MenuEntry {
+    pub id: String,
+    pub title: String,
+    // Submenu the item is in, if any
+    pub group: Option<String>,
+}
+
+impl MenuEntry {
+    fn new(id: impl Into<String>, title: impl Into<String>, group: Option<&str>) -> Self {
+        MenuEntry {
+            id: id.into(),
+            title: title.into(),
+            group: group.map(|g| g.to_string()),
+        }
+    }
+}
+
+// Menu items in order, including quick actions and pinned prompts
+pub fn entries(
+    quick_actions: &[QuickAction],
+    pinned: &[PinnedPrompt],
+    profiles: &ProfileList,
+    paused: bool,
+    private: bool,
+) -> Vec<MenuEntry> {
+    let mut entries = vec![MenuEntry::new("show", "Show", None)];
+    entries.extend(quick_actions.iter().map(|action| {
+        MenuEntry::new(
+            format!("{}{}", QUICK_ACTION_PREFIX, action.id),
+            action.title.clone(),
+            Some("Quick Actions"),
+        )
+    }));
+    entries.extend(pinned.iter().map(|prompt| {
+        MenuEntry::new(format!("{}{}", PINNED_PREFIX, prompt.id), prompt.title.clone(), Some("Pinned"))
+    }));
+    let pause_title = if paused { "Resume Automation" } else { "Pause Automation" };
+    entries.push(MenuEntry::new("pause", pause_title, None));
+    let privacy_title = if private { "Turn Off Privacy Mode" } else { "Turn On Privacy Mode" };
+    entries.push(MenuEntry::new("privacy", privacy_title, None));
+    // Switching profiles is one click away on a shared machine, once there's more than one
+    if profiles.profiles.len() > 1 {
+        entries.extend(profiles.profiles.iter().map(|profile| {
+            let mark = if profile.id == profiles.active { "✓ " } else { "" };
+            MenuEntry::new(
+                format!("{}{}", PROFILE_PREFIX, profile.id),
+                format!("{}{}", mark, profile.name),
+                Some("Profiles"),
+            )
+        }));
+    }
+    entries.push(MenuEntry::new("settings", "Settings", None));
+    entries.push(MenuEntry::new("console", "Console", None));
+    entries.push(MenuEntry::new("quit", "Quit", None));
+    entries
+}
+
+fn current_entries(app_handle: &tauri::AppHandle) -> Vec<MenuEntry> {
+    let paused = automation::is_paused(app_handle);
+    let private = privacy::is_active(app_handle);
+    let profiles = profiles::tray_profiles(app_handle);
+    // Both run prompts as automations, which the backend may not be able to execute
+    if capabilities::is_available(app_handle, Capability::CodeExec) {
+        entries(
+            &quick_actions::tray_actions(app_handle),
+            &history::tray_pinned(app_handle),
+            &profiles,
+            paused,
+            private,
+        )
+    } else {
+        entries(&[], &[], &profiles, paused, private)
+    }
+}
+
+// Build the system tray menu, with consecutive entries of a group in one submenu
+pub fn build_menu(app_handle: &tauri::AppHandle, entries: &[MenuEntry]) -> tauri::Result<Menu<tauri::Wry>> {
+    let menu = Menu::new(app_handle)?;
+    let mut index = 0;
+    while index < entries.len() {
+        let entry = &entries[index];
+        match &entry.group {
+            Some(group) => {
+                let submenu = Submenu::new(app_handle, group, true)?;
+                while index < entries.len() && entries[index].group.as_ref() == Some(group) {
+                    let item = &entries[index];
+                    submenu.append(&MenuItem::with_id(app_handle, &item.id, &item.title, true, None::<&str>)?)?;
+                    index += 1;
+                }
+                menu.append(&submenu)?;
+            }
+            None => {
+                if entry.id == "quit" {
+                    menu.append(&PredefinedMenuItem::separator(app_handle)?)?;
+                }
+                menu.append(&MenuItem::with_id(app_handle, &entry.id, &entry.title, true, None::<&str>)?)?;
+                index += 1;
+            }
+        }
+    }
+    Ok(menu)
+}
+
+// Only Linux desktops can lack a tray
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+enum Availability {
+    Available,
+    // The tray icon can be created but nothing on the desktop will show it
+    Hidden(&'static str),
+    // Creating the tray icon would fail (or, without its library, panic)
+    Missing(&'static str),
+}
+
+#[cfg(target_os = "linux")]
+fn availability() -> Availability {
+    // Tauri's Linux tray is an AppIndicator, loaded from one of these at runtime
+    const LIBRARIES: &[&str] = &[
+        "libayatana-appindicator3.so.1",
+        "libappindicator3.so.1",
+        "libayatana-appindicator3.so",
+        "libappindicator3.so",
+    ];
+    if !LIBRARIES.iter().any(|name| unsafe { libloading::Library::new(name) }.is_ok()) {
+        return Availability::Missing("no AppIndicator library is installed");
+    }
+    if !has_status_notifier_host() {
+        return Availability::Hidden("the desktop has no StatusNotifier host (GNOME needs the AppIndicator extension)");
+    }
+    Availability::Available
+}
+
+#[cfg(not(target_os = "linux"))]
+fn availability() -> Availability {
+    Availability::Available
+}
+
+// Indicators are only shown if something on the session bus hosts them
+#[cfg(target_os = "linux")]
+fn has_status_notifier_host() -> bool {
+    use gtk::gio;
+    use gtk::glib::ToVariant;
+
+    let connection = match gio::bus_get_sync(gio::BusType::Session, gio::Cancellable::NONE) {
+        Ok(connection) => connection,
+        Err(_) => return false,
+    };
+    connection
+        .call_sync(
+            Some("org.freedesktop.DBus"),
+            "/org/freedesktop/DBus",
+            "org.freedesktop.DBus",
+            "NameHasOwner",
+            Some(&("org.kde.StatusNotifierWatcher",).to_variant()),
+            None,
+            gio::DBusCallFlags::NONE,
+            1000,
+            gio::Cancellable::NONE,
+        )
+        .ok()
+        .and_then(|reply| reply.get::<(bool,)>())
+        .map(|(owned,)| owned)
+        .unwrap_or(false)
+}
+
+// Add the tray icon, opening the menu as a window too where the icon won't be reachable
+pub fn create(app_handle: &tauri::AppHandle) {
+    let entries = current_entries(app_handle);
+    let fallback = match availability() {
+        Availability::Available => None,
+        Availability::Hidden(reason) => Some(reason),
+        Availability::Missing(reason) => {
+            eprintln!("No system tray: {}", reason);
+            crate::open_menu_window(app_handle);
+            return;
+        }
+    };
+
+    let tray = build_menu(app_handle, &entries).and_then(|menu| {
+        let mut tray = TrayIconBuilder::with_id(TRAY_ID)
+            .menu(&menu)
+            .icon_as_template(true)
+            // Left clicks do what the settings say; the menu is a right click away
+            .show_menu_on_left_click(false)
+            .on_menu_event(|app, event| activate(app, event.id().as_ref()))
+            .on_tray_icon_event(|tray, event| handle_event(tray.app_handle(), event));
+        match tray_icon(false) {
+            Ok(icon) => tray = tray.icon(icon),
+            Err(e) => eprintln!("{}", e),
+        }
+        tray.build(app_handle)
+    });
+    if let Err(e) = tray {
+        errors::report(app_handle, "Failed to create the tray icon", e);
+        crate::open_menu_window(app_handle);
+        return;
+    }
+    if let Some(reason) = fallback {
+        eprintln!("The tray icon may not be visible: {}", reason);
+        crate::open_menu_window(app_handle);
+    }
+}
+
+// Rebuild the tray menu (and the windowed menu, if open) from the current state
+pub fn refresh(app_handle: &tauri::AppHandle) {
+    let entries = current_entries(app_handle);
+    if let Some(tray) = app_handle.tray_by_id(TRAY_ID) {
+        if let Err(e) = build_menu(app_handle, &entries).and_then(|menu| tray.set_menu(Some(menu))) {
+            eprintln!("Failed to update tray menu: {}", e);
+        }
+    }
+    let _ = app_handle.emit_to(MENU_WINDOW, "menu-changed", &entries);
+}
+
+// Cut a dot into the bottom right corner, with a clear ring so it reads in a template icon too
+fn mark_private(rgba: &mut [u8], width: u32, height: u32) {
+    let radius = width.min(height) as f32 / 4.0;
+    let ring = (radius / 3.0).max(1.0);
+    let (cx, cy) = (width as f32 - radius - 1.0, height as f32 - radius - 1.0);
+    for y in 0..height {
+        for x in 0..width {
+            let distance = ((x as f32 + 0.5 - cx).powi(2) + (y as f32 + 0.5 - cy).powi(2)).sqrt();
+            let offset = ((y * width + x) * 4) as usize;
+            let pixel = &mut rgba[offset..offset + 4];
+            if distance <= radius {
+                pixel.copy_from_slice(&PRIVACY_DOT);
+            } else if distance <= radius + ring {
+                pixel[3] = 0;
+            }
+        }
+    }
+}
+
+fn tray_icon(private: bool) -> Result<Image<'static>, String> {
+    let mut reader = png::Decoder::new(TRAY_ICON)
+        .read_info()
+        .map_err(|e| format!("Failed to read the tray icon: {}", e))?;
+    let mut rgba = vec![0; reader.output_buffer_size()];
+    let info = reader
+        .next_frame(&mut rgba)
+        .map_err(|e| format!("Failed to decode the tray icon: {}", e))?;
+    if info.color_type != png::ColorType::Rgba || info.bit_depth != png::BitDepth::Eight {
+        return Err("The tray icon isn't 8-bit RGBA".to_string());
+    }
+    rgba.truncate(info.buffer_size());
+    if private {
+        mark_private(&mut rgba, info.width, info.height);
+    }
+    Ok(Image::new_owned(rgba, info.width, info.height))
+}
+
+// Mark the tray icon while privacy mode is on
+pub fn show_privacy(app_handle: &tauri::AppHandle, private: bool) {
+    let tray = match app_handle.tray_by_id(TRAY_ID) {
+        Some(tray) => tray,
+        None => return,
+    };
+    match tray_icon(private) {
+        Ok(icon) => {
+            if let Err(e) = tray.set_icon(Some(icon)) {
+                eprintln!("Failed to update the tray icon: {}", e);
+            }
+        }
+        Err(e) => eprintln!("{}", e),
+    }
+    // Linux trays have no tooltips
+    let _ = tray.set_tooltip(Some(if private { "Krya.ai (privacy mode)" } else { "Krya.ai" }));
+}
+
+// Run a menu item, whether it was picked from the tray or the windowed menu
+pub fn activate(app: &tauri::AppHandle, id: &str) {
+    match id {
+        "quit" => {
+            // Stop the API server (and Ollama, if we started it) before quitting
+            let app_state = app.state::<AppState>();
+            crate::stop_api_server(&app_state);
+            crate::ollama::stop(app);
+            app.exit(0);
+        }
+        "show" => crate::toggle_spotlight(app),
+        "settings" => {
+            crate::open_settings_window(app);
+        }
+        "console" => {
+            crate::open_console_window(app);
+        }
+        "pause" => toggle_pause(app),
+        "privacy" => toggle_privacy(app),
+        id if id.starts_with(QUICK_ACTION_PREFIX) => {
+            quick_actions::trigger_quick_action(app, &id[QUICK_ACTION_PREFIX.len()..]);
+        }
+        id if id.starts_with(PINNED_PREFIX) => {
+            if let Ok(pinned_id) = id[PINNED_PREFIX.len()..].parse() {
+                history::trigger_pinned_prompt(app, pinned_id);
+            }
+        }
+        id if id.starts_with(PROFILE_PREFIX) => switch_profile(app, &id[PROFILE_PREFIX.len()..]),
+        _ => {}
+    }
+}
+
+// Talking to the backend blocks, so this stays off the main thread
+fn toggle_pause(app: &tauri::AppHandle) {
+    let app = app.clone();
+    std::thread::spawn(move || {
+        let paused = !automation::is_paused(&app);
+        if let Err(e) = automation::set_paused(&app, paused) {
+            errors::report(&app, "Failed to tell the backend about the automation pause", e);
+        }
+    });
+}
+
+// Saving settings touches the disk and re-registers shortcuts, so this runs off the main thread too
+fn toggle_privacy(app: &tauri::AppHandle) {
+    let app = app.clone();
+    std::thread::spawn(move || {
+        if let Err(e) = privacy::toggle(&app) {
+            errors::report(&app, "Failed to switch privacy mode", e);
+        }
+    });
+}
+
+// Restarts the backend on the profile's files, which takes a while
+fn switch_profile(app: &tauri::AppHandle, id: &str) {
+    let app = app.clone();
+    let id = id.to_string();
+    std::thread::spawn(move || {
+        if let Err(e) = profiles::switch_profile(app.clone(), id) {
+            errors::report(&app, "Failed to switch profiles", e);
+        }
+    });
+}
+
+// Bring up an empty spotlight, dropping whatever was typed before
+fn new_prompt(app: &tauri::AppHandle) {
+    if let Some(window) = app.get_webview_window("main") {
+        if !window.is_visible().unwrap_or(false) {
+            crate::toggle_spotlight_window(&window).or_report(app, "Failed to show the spotlight");
+        }
+        if let Err(e) = window.emit_to(window.label(), "new-prompt", ()) {
+            eprintln!("Failed to emit new-prompt: {}", e);
+        }
+    }
+}
+
+#[cfg(target_os = "macos")]
+fn quick_prompt(app: &tauri::AppHandle, position: PhysicalPosition<f64>, size: PhysicalSize<f64>) {
+    let icon = Rect {
+        x: position.x.round() as i32,
+        y: position.y.round() as i32,
+        width: size.width.round() as u32,
+        height: size.height.round() as u32,
+    };
+    popover::toggle(app, icon);
+}
+
+// The popover hangs off the macOS menu bar; elsewhere the spotlight is the quick prompt
+#[cfg(not(target_os = "macos"))]
+fn quick_prompt(app: &tauri::AppHandle, _position: PhysicalPosition<f64>, _size: PhysicalSize<f64>) {
+    crate::toggle_spotlight(app);
+}
+
+fn click(app: &tauri::AppHandle, action: TrayClickAction, position: PhysicalPosition<f64>, size: PhysicalSize<f64>) {
+    match action {
+        TrayClickAction::ToggleSpotlight => crate::toggle_spotlight(app),
+        TrayClickAction::QuickPrompt => quick_prompt(app, position, size),
+        TrayClickAction::NewPrompt => new_prompt(app),
+        TrayClickAction::OpenConsole => crate::open_console_window(app),
+        TrayClickAction::PauseAutomation => toggle_pause(app),
+        TrayClickAction::Nothing => {}
+    }
+}
+
+// The icon's bounds, which trays report in physical pixels
+fn icon_bounds(rect: tauri::Rect) -> (PhysicalPosition<f64>, PhysicalSize<f64>) {
+    (rect.position.to_physical(1.0), rect.size.to_physical(1.0))
+}
+
+fn handle_event(app: &tauri::AppHandle, event: TrayIconEvent) {
+    match event {
+        TrayIconEvent::Click {
+            rect,
+            button: MouseButton::Left,
+            button_state: MouseButtonState::Up,
+            ..
+        } => {
+            let (position, size) = icon_bounds(rect);
+            click(app, settings::current(app).tray.left_click, position, size)
+        }
+        TrayIconEvent::Click {
+            rect,
+            button: MouseButton::Middle,
+            button_state: MouseButtonState::Up,
+            ..
+        } => {
+            let (position, size) = icon_bounds(rect);
+            click(app, settings::current(app).tray.middle_click, position, size)
+        }
+        TrayIconEvent::DoubleClick {
+            rect,
+            button: MouseButton::Left,
+            ..
+        } => {
+            let (position, size) = icon_bounds(rect);
+            click(app, settings::current(app).tray.double_click, position, size)
+        }
+        _ => {}
+    }
+}
+
+// Command for the windowed menu to list the tray's items
+#[tauri::command]
+pub fn get_menu_items(app_handle: tauri::AppHandle) -> Vec<MenuEntry> {
+    current_entries(&app_handle)
+}
+
+#[tauri::command]
+pub fn activate_menu_item(app_handle: tauri::AppHandle, id: String) {
+    activate(&app_handle, &id);
+}
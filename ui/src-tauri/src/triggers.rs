@@ -0,0 +1,529 @@
+use notify::event::{EventKind, ModifyKind, RenameMode};
+use notify::{RecommendedWatcher, RecursiveMode, Watcher};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::net::{IpAddr, UdpSocket};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, Instant, SystemTime};
+use sysinfo::System;
+use tauri::{Emitter, Manager};
+use tauri_plugin_dialog::{DialogExt, MessageDialogButtons, MessageDialogKind};
+
+use crate::protocol::{self, RunParams};
+use crate::quick_actions::{self, QuickActionsState};
+use crate::scopes::{self, Scope};
+use crate::util::now_secs;
+use crate::{automation, errors, jobs, storage};
+
+const CONFIG_FILE: &str = "triggers.json";
+const HISTORY_FILE: &str = "trigger_history.json";
+
+// Firings kept for the history view
+const MAX_FIRINGS: usize = 200;
+
+// Saving a file often reports several changes; they count once within this
+const FILE_DEBOUNCE: Duration = Duration::from_secs(2);
+
+const POLL_INTERVAL: Duration = Duration::from_secs(5);
+
+// A poll that comes this much later than planned means the machine was asleep in between
+const SLEEP_GAP: Duration = Duration::from_secs(30);
+
+// What happened on the system that can start an automation
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum TriggerEvent {
+    Wake,
+    // The address we'd reach the internet from changed, e.g. joining another Wi-Fi
+    NetworkChange,
+    // A monitor was plugged in or removed
+    DisplayChange,
+    // An application (by name or executable) started
+    AppLaunch { app: String },
+    // Something happened to a file in a folder (not its subfolders) whose name matches a glob pattern
+    File {
+        folder: String,
+        #[serde(default = "default_pattern")]
+        pattern: String,
+        change: FileChange,
+    },
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FileChange {
+    // Also a file moved in, which is how browsers finish downloads
+    Created,
+    Modified,
+    // Also a file moved out
+    Removed,
+}
+
+fn default_pattern() -> String {
+    "*".to_string()
+}
+
+impl TriggerEvent {
+    fn description(&self) -> String {
+        match self {
+            TriggerEvent::Wake => "the computer woke from sleep".to_string(),
+            TriggerEvent::NetworkChange => "the network changed".to_string(),
+            TriggerEvent::DisplayChange => "a display was connected or disconnected".to_string(),
+            TriggerEvent::AppLaunch { app } => format!("{} was opened", app),
+            TriggerEvent::File { folder, change, .. } => {
+                let change = match change {
+                    FileChange::Created => "added to",
+                    FileChange::Modified => "changed in",
+                    FileChange::Removed => "removed from",
+                };
+                format!("a file was {} {}", change, folder)
+            }
+        }
+    }
+}
+
+// Runs a saved quick action whenever its event happens. File triggers fill the action's
+// {{file}} and {{file_name}} placeholders; other placeholders can't be filled unattended.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Trigger {
+    #[serde(default)]
+    pub id: String,
+    #[serde(flatten)]
+    pub event: TriggerEvent,
+    pub quick_action_id: String,
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+    // Ask before every run; only turned off for automations the user trusts to run unattended
+    #[serde(default = "default_true")]
+    pub confirm: bool,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct TriggersConfig {
+    #[serde(default)]
+    pub triggers: Vec<Trigger>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(tag = "outcome", rename_all = "snake_case")]
+pub enum FiringOutcome {
+    Ran { job_id: String },
+    // The user chose not to run it when asked
+    Skipped,
+    Failed { error: String },
+}
+
+// One time a trigger's event happened, and what came of it
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct TriggerFiring {
+    pub trigger_id: String,
+    pub reason: String,
+    pub time: u64,
+    #[serde(flatten)]
+    pub outcome: FiringOutcome,
+}
+
+pub struct TriggersState {
+    config: Mutex<TriggersConfig>,
+    history: Mutex<VecDeque<TriggerFiring>>,
+    // One watcher per enabled file trigger; dropping one stops it
+    watchers: Mutex<HashMap<String, RecommendedWatcher>>,
+    // When each (trigger, file) last fired
+    recent_files: Mutex<HashMap<(String, PathBuf), Instant>>,
+}
+
+impl TriggersState {
+    pub fn load(app_handle: &tauri::AppHandle) -> Self {
+        let history: Vec<TriggerFiring> = storage::load_json(app_handle, HISTORY_FILE);
+        TriggersState {
+            config: Mutex::new(storage::load_json(app_handle, CONFIG_FILE)),
+            history: Mutex::new(history.into()),
+            watchers: Mutex::new(HashMap::new()),
+            recent_files: Mutex::new(HashMap::new()),
+        }
+    }
+}
+
+// Triggers the system event watcher looks after; file triggers have watchers of their own
+fn polled_triggers(app_handle: &tauri::AppHandle) -> Vec<Trigger> {
+    let state = app_handle.state::<TriggersState>();
+    let config = state.config.lock().unwrap();
+    config
+        .triggers
+        .iter()
+        .filter(|t| t.enabled && !matches!(t.event, TriggerEvent::File { .. }))
+        .cloned()
+        .collect()
+}
+
+// What the watcher saw last time, to tell what changed since
+#[derive(Default)]
+struct Snapshot {
+    polled_at: Option<SystemTime>,
+    // None before the first poll, and Some(None) while offline
+    local_address: Option<Option<IpAddr>>,
+    monitors: Option<usize>,
+    processes: Option<HashSet<String>>,
+}
+
+// The source address for outgoing traffic; connecting a UDP socket sends nothing
+fn local_address() -> Option<IpAddr> {
+    let socket = UdpSocket::bind("0.0.0.0:0").ok()?;
+    socket.connect(("8.8.8.8", 80)).ok()?;
+    socket.local_addr().ok().map(|address| address.ip())
+}
+
+fn process_names(system: &mut System) -> HashSet<String> {
+    system.refresh_processes();
+    system
+        .processes()
+        .values()
+        .map(|process| {
+            let name = process.name().to_lowercase();
+            name.strip_suffix(".exe").unwrap_or(&name).to_string()
+        })
+        .collect()
+}
+
+// Everything that happened since the last poll; the first poll only takes the baseline
+fn changes(
+    app_handle: &tauri::AppHandle,
+    snapshot: &mut Snapshot,
+    system: &mut System,
+    watches_apps: bool,
+) -> Vec<TriggerEvent> {
+    let mut events = Vec::new();
+
+    let now = SystemTime::now();
+    if let Some(polled_at) = snapshot.polled_at.replace(now) {
+        if now.duration_since(polled_at).is_ok_and(|gap| gap > POLL_INTERVAL + SLEEP_GAP) {
+            events.push(TriggerEvent::Wake);
+        }
+    }
+
+    let address = local_address();
+    if snapshot.local_address.replace(address).is_some_and(|previous| previous != address) {
+        events.push(TriggerEvent::NetworkChange);
+    }
+
+    if let Some(Ok(monitors)) = app_handle.get_webview_window("main").map(|window| window.available_monitors()) {
+        if snapshot.monitors.replace(monitors.len()).is_some_and(|previous| previous != monitors.len()) {
+            events.push(TriggerEvent::DisplayChange);
+        }
+    }
+
+    // Listing processes is the expensive part, so only when some trigger needs it
+    if watches_apps {
+        let names = process_names(system);
+        if let Some(previous) = snapshot.processes.replace(names.clone()) {
+            events.extend(
+                names
+                    .difference(&previous)
+                    .map(|name| TriggerEvent::AppLaunch { app: name.clone() }),
+            );
+        }
+    } else {
+        snapshot.processes = None;
+    }
+    events
+}
+
+fn is_bound_to(trigger: &TriggerEvent, event: &TriggerEvent) -> bool {
+    match (trigger, event) {
+        (TriggerEvent::AppLaunch { app: pattern }, TriggerEvent::AppLaunch { app }) => {
+            pattern.trim().to_lowercase() == *app
+        }
+        (trigger, event) => trigger == event,
+    }
+}
+
+// Run a trigger's quick action, if the user allows automations to start on their own
+fn run(
+    app_handle: &tauri::AppHandle,
+    trigger: &Trigger,
+    reason: &str,
+    values: &HashMap<String, String>,
+) -> Result<FiringOutcome, String> {
+    scopes::require(app_handle, Scope::Triggers)?;
+    if automation::is_paused(app_handle) {
+        return Err("automation is paused".to_string());
+    }
+
+    let action = {
+        let state = app_handle.state::<QuickActionsState>();
+        let store = state.0.lock().unwrap();
+        store.quick_actions.iter().find(|a| a.id == trigger.quick_action_id).cloned()
+    };
+    let action = action.ok_or_else(|| format!("Quick action not found: {}", trigger.quick_action_id))?;
+    if quick_actions::placeholders(&action.prompt).iter().any(|name| !values.contains_key(name)) {
+        return Err(format!("'{}' has placeholders, which nobody is there to fill in", action.title));
+    }
+
+    if trigger.confirm {
+        let message = format!("Run \"{}\" because {}?", action.title, reason);
+        let run = app_handle
+            .dialog()
+            .message(message)
+            .title("Krya.ai automation")
+            .kind(MessageDialogKind::Info)
+            .buttons(MessageDialogButtons::OkCancelCustom("Run".to_string(), "Skip".to_string()))
+            .blocking_show();
+        if !run {
+            return Ok(FiringOutcome::Skipped);
+        }
+    }
+
+    println!("Trigger {} is running '{}'", trigger.id, action.title);
+    let params = RunParams::new(quick_actions::fill_placeholders(&action.prompt, values), None);
+    let submitted = protocol::call::<protocol::RunJob>(&params)?;
+    jobs::track(app_handle, &submitted.job_id);
+    Ok(FiringOutcome::Ran {
+        job_id: submitted.job_id,
+    })
+}
+
+// Run a trigger and keep a record of it
+fn fire(app_handle: &tauri::AppHandle, trigger: &Trigger, reason: String, values: HashMap<String, String>) {
+    let outcome = run(app_handle, trigger, &reason, &values).unwrap_or_else(|error| {
+        errors::report(app_handle, &format!("Trigger {} did not run", trigger.id), &error);
+        FiringOutcome::Failed { error }
+    });
+    let firing = TriggerFiring {
+        trigger_id: trigger.id.clone(),
+        reason,
+        time: now_secs(),
+        outcome,
+    };
+
+    // The history is kept on disk, so firings in privacy mode aren't added to it
+    if storage::may_record(app_handle) {
+        let state = app_handle.state::<TriggersState>();
+        let mut history = state.history.lock().unwrap();
+        history.push_back(firing.clone());
+        while history.len() > MAX_FIRINGS {
+            history.pop_front();
+        }
+        if let Err(e) = storage::save_json(app_handle, HISTORY_FILE, &*history, storage::Data::Activity) {
+            eprintln!("Failed to save trigger history: {}", e);
+        }
+    }
+    if let Err(e) = app_handle.emit("trigger-fired", firing) {
+        eprintln!("Failed to emit trigger-fired: {}", e);
+    }
+}
+
+fn file_change(kind: &EventKind, paths: &[PathBuf]) -> Option<(FileChange, PathBuf)> {
+    let change = match kind {
+        EventKind::Create(_) | EventKind::Modify(ModifyKind::Name(RenameMode::To)) => FileChange::Created,
+        // Both paths are reported for a rename within the folder: the old name, then the new one
+        EventKind::Modify(ModifyKind::Name(RenameMode::Both)) => {
+            return Some((FileChange::Created, paths.get(1)?.clone()));
+        }
+        EventKind::Modify(ModifyKind::Name(_)) | EventKind::Remove(_) => FileChange::Removed,
+        EventKind::Modify(_) => FileChange::Modified,
+        _ => return None,
+    };
+    Some((change, paths.first()?.clone()))
+}
+
+// Handle one change reported by a file trigger's watcher
+fn on_file_event(app_handle: &tauri::AppHandle, trigger: &Trigger, pattern: &glob::Pattern, event: notify::Event) {
+    let wanted = match &trigger.event {
+        TriggerEvent::File { change, .. } => *change,
+        _ => return,
+    };
+    let (change, path) = match file_change(&event.kind, &event.paths) {
+        Some((change, path)) if change == wanted => (change, path),
+        _ => return,
+    };
+    let name = match path.file_name() {
+        Some(name) => name.to_string_lossy().to_string(),
+        None => return,
+    };
+    if !pattern.matches(&name) || (change != FileChange::Removed && !path.is_file()) {
+        return;
+    }
+
+    {
+        let state = app_handle.state::<TriggersState>();
+        let mut recent = state.recent_files.lock().unwrap();
+        recent.retain(|_, at| at.elapsed() < FILE_DEBOUNCE);
+        if recent.insert((trigger.id.clone(), path.clone()), Instant::now()).is_some() {
+            return;
+        }
+    }
+
+    let mut values = HashMap::new();
+    values.insert("file".to_string(), path.to_string_lossy().to_string());
+    values.insert("file_name".to_string(), name.clone());
+    let reason = format!("{} ({})", trigger.event.description(), name);
+    let app_handle = app_handle.clone();
+    let trigger = trigger.clone();
+    std::thread::spawn(move || fire(&app_handle, &trigger, reason, values));
+}
+
+// Folders may only be watched where the backend could work on their files anyway
+fn watched_folder(app_handle: &tauri::AppHandle, folder: &str) -> Result<PathBuf, String> {
+    let folder = scopes::check_path(app_handle, folder)?;
+    if !folder.is_dir() {
+        return Err(format!("Not a folder: {}", folder.display()));
+    }
+    Ok(folder)
+}
+
+fn watch(
+    app_handle: &tauri::AppHandle,
+    trigger: &Trigger,
+    folder: &Path,
+    pattern: &str,
+) -> Result<RecommendedWatcher, String> {
+    let pattern = glob::Pattern::new(pattern).map_err(|e| format!("Invalid file pattern {}: {}", pattern, e))?;
+    let app_handle = app_handle.clone();
+    let watched = trigger.clone();
+    let mut watcher = notify::recommended_watcher(move |event: notify::Result<notify::Event>| match event {
+        Ok(event) => on_file_event(&app_handle, &watched, &pattern, event),
+        Err(e) => eprintln!("File watch for trigger {} failed: {}", watched.id, e),
+    })
+    .map_err(|e| format!("Failed to watch {}: {}", folder.display(), e))?;
+    watcher
+        .watch(folder, RecursiveMode::NonRecursive)
+        .map_err(|e| format!("Failed to watch {}: {}", folder.display(), e))?;
+    Ok(watcher)
+}
+
+// Start a watcher for each enabled file trigger, stopping any that are no longer needed
+pub fn apply_watches(app_handle: &tauri::AppHandle) {
+    let state = app_handle.state::<TriggersState>();
+    let triggers = state.config.lock().unwrap().triggers.clone();
+    let mut watchers = state.watchers.lock().unwrap();
+    watchers.clear();
+
+    for trigger in triggers.iter().filter(|t| t.enabled) {
+        if let TriggerEvent::File { folder, pattern, .. } = &trigger.event {
+            let watcher =
+                watched_folder(app_handle, folder).and_then(|folder| watch(app_handle, trigger, &folder, pattern));
+            match watcher {
+                Ok(watcher) => {
+                    watchers.insert(trigger.id.clone(), watcher);
+                }
+                Err(e) => errors::report(app_handle, &format!("Trigger {} is not watching", trigger.id), e),
+            }
+        }
+    }
+}
+
+// Watch for system events in the background and fire the triggers bound to them
+pub fn start_watching(app_handle: &tauri::AppHandle) {
+    let app_handle = app_handle.clone();
+    std::thread::spawn(move || {
+        let mut snapshot = Snapshot::default();
+        let mut system = System::new();
+        loop {
+            let triggers = polled_triggers(&app_handle);
+            if triggers.is_empty() {
+                snapshot = Snapshot::default();
+                std::thread::sleep(POLL_INTERVAL);
+                continue;
+            }
+            let watches_apps = triggers
+                .iter()
+                .any(|t| matches!(t.event, TriggerEvent::AppLaunch { .. }));
+
+            for event in changes(&app_handle, &mut snapshot, &mut system, watches_apps) {
+                for trigger in triggers.iter().filter(|t| is_bound_to(&t.event, &event)) {
+                    fire(&app_handle, trigger, event.description(), HashMap::new());
+                }
+            }
+            std::thread::sleep(POLL_INTERVAL);
+        }
+    });
+}
+
+fn save(app_handle: &tauri::AppHandle, config: &TriggersConfig) -> Result<(), String> {
+    storage::save_json(app_handle, CONFIG_FILE, config, storage::Data::Config)
+}
+
+#[tauri::command]
+pub fn list_triggers(state: tauri::State<TriggersState>) -> Vec<Trigger> {
+    state.config.lock().unwrap().triggers.clone()
+}
+
+// Most recent firings first
+#[tauri::command]
+pub fn list_trigger_firings(state: tauri::State<TriggersState>) -> Vec<TriggerFiring> {
+    state.history.lock().unwrap().iter().rev().cloned().collect()
+}
+
+// Create a trigger (when it has no id yet) or replace the one with the same id
+#[tauri::command]
+pub fn save_trigger(
+    app_handle: tauri::AppHandle,
+    state: tauri::State<TriggersState>,
+    mut trigger: Trigger,
+) -> Result<Trigger, String> {
+    match &trigger.event {
+        TriggerEvent::AppLaunch { app } if app.trim().is_empty() => {
+            return Err("Choose the application that starts this automation".to_string());
+        }
+        TriggerEvent::File { folder, pattern, .. } => {
+            watched_folder(&app_handle, folder)?;
+            glob::Pattern::new(pattern).map_err(|e| format!("Invalid file pattern {}: {}", pattern, e))?;
+        }
+        _ => {}
+    }
+
+    {
+        let mut config = state.config.lock().unwrap();
+        if trigger.id.is_empty() {
+            trigger.id = uuid::Uuid::new_v4().to_string();
+        }
+        match config.triggers.iter_mut().find(|t| t.id == trigger.id) {
+            Some(existing) => *existing = trigger.clone(),
+            None => config.triggers.push(trigger.clone()),
+        }
+        save(&app_handle, &config)?;
+    }
+    apply_watches(&app_handle);
+    Ok(trigger)
+}
+
+#[tauri::command]
+pub fn set_trigger_enabled(
+    app_handle: tauri::AppHandle,
+    state: tauri::State<TriggersState>,
+    id: String,
+    enabled: bool,
+) -> Result<(), String> {
+    {
+        let mut config = state.config.lock().unwrap();
+        let trigger = config
+            .triggers
+            .iter_mut()
+            .find(|t| t.id == id)
+            .ok_or_else(|| format!("Trigger not found: {}", id))?;
+        trigger.enabled = enabled;
+        save(&app_handle, &config)?;
+    }
+    apply_watches(&app_handle);
+    Ok(())
+}
+
+#[tauri::command]
+pub fn delete_trigger(
+    app_handle: tauri::AppHandle,
+    state: tauri::State<TriggersState>,
+    id: String,
+) -> Result<(), String> {
+    {
+        let mut config = state.config.lock().unwrap();
+        config.triggers.retain(|t| t.id != id);
+        save(&app_handle, &config)?;
+    }
+    apply_watches(&app_handle);
+    Ok(())
+}
@@ -0,0 +1,441 @@
+use serde::Serialize;
+use std::collections::HashSet;
+use std::fmt;
+
+use crate::clipboard::ClipboardPattern;
+use crate::models;
+use crate::settings::{self, Settings};
+use crate::workers;
+
+// Day counts are turned into seconds, so they're kept well short of overflowing
+const MAX_DAYS: u64 = 36_500;
+
+// One problem with a setting, addressed by its path in the settings file
+#[derive(Clone, Debug, Serialize)]
+pub struct ValidationError {
+    // e.g. `shortcuts.spotlight[1]`
+    pub path: String,
+    pub message: String,
+}
+
+impl fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: {}", self.path, self.message)
+    }
+}
+
+#[derive(Default)]
+struct Errors(Vec<ValidationError>);
+
+impl Errors {
+    fn add(&mut self, path: impl Into<String>, message: impl Into<String>) {
+        self.0.push(ValidationError {
+            path: path.into(),
+            message: message.into(),
+        });
+    }
+}
+
+const MODIFIERS: &[&str] = &[
+    "commandorcontrol",
+    "commandorctrl",
+    "cmdorctrl",
+    "cmdorcontrol",
+    "command",
+    "cmd",
+    "super",
+    "control",
+    "ctrl",
+    "alt",
+    "option",
+    "shift",
+];
+
+// The key names the global shortcut parser (tao's) accepts, besides letters, digits and F1 to F32
+const NAMED_KEYS: &[&str] = &[
+    "backquote",
+    "backslash",
+    "bracketleft",
+    "bracketright",
+    "comma",
+    "plus",
+    "period",
+    "quote",
+    "semicolon",
+    "slash",
+    "backspace",
+    "capslock",
+    "contextmenu",
+    "enter",
+    "space",
+    "tab",
+    "convert",
+    "insert",
+    "delete",
+    "end",
+    "help",
+    "home",
+    "pagedown",
+    "pageup",
+    "down",
+    "arrowdown",
+    "up",
+    "arrowup",
+    "left",
+    "arrowleft",
+    "right",
+    "arrowright",
+    "numlock",
+    "numadd",
+    "numpadadd",
+    "numbackspace",
+    "numpadbackspace",
+    "numclear",
+    "numpadclear",
+    "numcomma",
+    "numpadcomma",
+    "numdivide",
+    "numpaddivide",
+    "numsubstract",
+    "numpadsubstract",
+    "numenter",
+    "numpadenter",
+    "esc",
+    "escape",
+    "fn",
+    "fnlock",
+    "printscreen",
+    "scrolllock",
+    "pause",
+    "volumemute",
+    "volumedown",
+    "volumeup",
+    "medianexttrack",
+    "mediaprevioustrack",
+    "mediaplaypause",
+    "launchmail",
+    "suspend",
+];
+
+// Punctuation that can be given as itself rather than by name
+const SYMBOL_KEYS: &str = "`[],=-.'\\;/";
+
+fn is_key(token: &str) -> bool {
+    let lower = token.to_lowercase();
+    let mut chars = token.chars();
+    if let (Some(c), None) = (chars.next(), chars.next()) {
+        return c.is_ascii_alphanumeric() || SYMBOL_KEYS.contains(c);
+    }
+    let numbered = |prefix: &str, range: std::ops::RangeInclusive<u32>| {
+        lower
+            .strip_prefix(prefix)
+            .and_then(|n| n.parse::<u32>().ok())
+            .is_some_and(|n| range.contains(&n))
+    };
+    NAMED_KEYS.contains(&lower.as_str())
+        || numbered("f", 1..=32)
+        || numbered("numpad", 0..=9)
+        || numbered("num", 0..=9)
+}
+
+// Why a global shortcut like `CommandOrControl+Shift+K` can't be registered, if it can't
+pub fn check_accelerator(accelerator: &str) -> Result<(), String> {
+    let invalid = |reason: &str| Err(format!("invalid accelerator '{}': {}", accelerator, reason));
+    let tokens: Vec<&str> = accelerator.split('+').map(|token| token.trim()).collect();
+    if tokens.iter().any(|token| token.is_empty()) {
+        return invalid("empty key between '+' signs (use 'Plus' for the + key)");
+    }
+    let (key, modifiers) = match tokens.split_last() {
+        Some(split) => split,
+        None => return invalid("no key"),
+    };
+    let mut seen = HashSet::new();
+    for modifier in modifiers {
+        let lower = modifier.to_lowercase();
+        if !MODIFIERS.contains(&lower.as_str()) {
+            return invalid(&format!("'{}' is not a modifier", modifier));
+        }
+        if !seen.insert(lower) {
+            return invalid(&format!("'{}' appears twice", modifier));
+        }
+    }
+    if MODIFIERS.contains(&key.to_lowercase().as_str()) {
+        return invalid("it ends in a modifier instead of a key");
+    }
+    if !is_key(key) {
+        return invalid(&format!("unknown key '{}'", key));
+    }
+    Ok(())
+}
+
+fn check_url(errors: &mut Errors, path: &str, url: Option<&str>) {
+    let url = match url.map(str::trim).filter(|url| !url.is_empty()) {
+        Some(url) => url,
+        None => return,
+    };
+    match reqwest::Url::parse(url) {
+        Ok(parsed) if parsed.scheme() == "http" || parsed.scheme() == "https" => {}
+        Ok(parsed) => errors.add(path, format!("'{}' must be an http or https URL, not {}", url, parsed.scheme())),
+        Err(e) => errors.add(path, format!("invalid URL '{}': {}", url, e)),
+    }
+}
+
+fn check_path(errors: &mut Errors, path: &str, value: Option<&str>, directory: bool) {
+    let value = match value.filter(|value| !value.trim().is_empty()) {
+        Some(value) => value,
+        None => return,
+    };
+    let expanded = settings::expand_home(value);
+    if !expanded.exists() {
+        errors.add(path, format!("'{}' does not exist", value));
+    } else if directory && !expanded.is_dir() {
+        errors.add(path, format!("'{}' is not a folder", value));
+    }
+}
+
+// Everything wrong with the settings, in the order they appear in the file
+pub fn validate(settings: &Settings) -> Vec<ValidationError> {
+    let mut errors = Errors::default();
+
+    let mut shortcuts = HashSet::new();
+    for (i, shortcut) in settings.shortcuts.spotlight.iter().enumerate() {
+        let path = format!("shortcuts.spotlight[{}]", i);
+        if let Err(e) = check_accelerator(shortcut) {
+            errors.add(path, e);
+        } else if !shortcuts.insert(shortcut.to_lowercase()) {
+            errors.add(path, format!("'{}' is listed twice", shortcut));
+        }
+    }
+
+    if settings.file_index.max_files == 0 {
+        errors.add("file_index.max_files", "must be at least 1");
+    }
+    for (i, folder) in settings.context.folders.iter().enumerate() {
+        check_path(&mut errors, &format!("context.folders[{}]", i), Some(folder), true);
+    }
+    if settings.context.max_snippets == 0 {
+        errors.add("context.max_snippets", "must be at least 1");
+    }
+    if !(0.0..=1.0).contains(&settings.context.min_score) {
+        errors.add("context.min_score", format!("{} is outside 0 to 1", settings.context.min_score));
+    }
+
+    check_url(&mut errors, "crash_reporting.upload_url", settings.crash_reporting.upload_url.as_deref());
+    check_url(&mut errors, "telemetry.endpoint", settings.telemetry.endpoint.as_deref());
+    check_url(&mut errors, "flags.manifest_url", settings.flags.manifest_url.as_deref());
+    if settings.ollama.url.trim().is_empty() {
+        errors.add("ollama.url", "must not be empty");
+    }
+    check_url(&mut errors, "ollama.url", Some(&settings.ollama.url));
+
+    if settings.model.provider != models::GEMINI && settings.model.provider != models::OLLAMA {
+        let message = format!(
+            "unknown provider '{}' (expected '{}' or '{}')",
+            settings.model.provider,
+            models::GEMINI,
+            models::OLLAMA
+        );
+        errors.add("model.provider", message);
+    }
+    if settings.model.name.trim().is_empty() {
+        errors.add("model.name", "must not be empty");
+    }
+
+    let mut server_ids = HashSet::new();
+    for (i, server) in settings.mcp.servers.iter().enumerate() {
+        if server.id.trim().is_empty() {
+            errors.add(format!("mcp.servers[{}].id", i), "must not be empty");
+        } else if !server_ids.insert(server.id.as_str()) {
+            errors.add(format!("mcp.servers[{}].id", i), format!("'{}' is used by another server", server.id));
+        }
+        if server.command.trim().is_empty() {
+            errors.add(format!("mcp.servers[{}].command", i), "must not be empty");
+        }
+    }
+    if settings.mcp.proxy_port == 0 {
+        errors.add("mcp.proxy_port", "must be between 1 and 65535");
+    }
+    if settings.mcp.server.enabled && settings.mcp.server.token.trim().is_empty() {
+        errors.add("mcp.server.token", "must be set while the MCP server is enabled");
+    }
+
+    for (i, root) in settings.permissions.file_roots.iter().enumerate() {
+        if root.trim().is_empty() {
+            errors.add(format!("permissions.file_roots[{}]", i), "must not be empty");
+        } else {
+            check_path(&mut errors, &format!("permissions.file_roots[{}]", i), Some(root), true);
+        }
+    }
+    if settings.permissions.max_file_mb == 0 {
+        errors.add("permissions.max_file_mb", "must be at least 1");
+    }
+
+    if settings.storage.max_log_mb == 0 {
+        errors.add("storage.max_log_mb", "must be at least 1");
+    }
+    if settings.storage.max_total_mb < settings.storage.max_log_mb {
+        errors.add(
+            "storage.max_total_mb",
+            format!("must be at least storage.max_log_mb ({})", settings.storage.max_log_mb),
+        );
+    }
+    for (path, days) in [
+        ("storage.max_age_days", settings.storage.max_age_days),
+        ("storage.cleanup.older_than_days", settings.storage.cleanup.older_than_days),
+    ] {
+        if !(1..=MAX_DAYS).contains(&days) {
+            errors.add(path, format!("{} is outside 1 to {}", days, MAX_DAYS));
+        }
+    }
+    if settings.storage.cleanup.enabled && settings.storage.cleanup.kinds.is_empty() {
+        errors.add("storage.cleanup.kinds", "must list something to clean while cleanup is enabled");
+    }
+
+    if settings.hot_corner.dwell_ms > 10_000 {
+        errors.add("hot_corner.dwell_ms", format!("{} is longer than 10 seconds", settings.hot_corner.dwell_ms));
+    }
+    if settings.hot_corner.enabled && settings.hot_corner.corner.is_none() && !settings.hot_corner.shake {
+        errors.add(
+            "hot_corner",
+            "enabled without a corner or the shake gesture, so nothing would open the spotlight",
+        );
+    }
+
+    for (name, apps) in [
+        ("input.allowed_apps", &settings.input.allowed_apps),
+        ("input.blocked_apps", &settings.input.blocked_apps),
+    ] {
+        for (i, app) in apps.iter().enumerate() {
+            if app.trim().is_empty() {
+                errors.add(format!("{}[{}]", name, i), "must name an application");
+            }
+        }
+    }
+
+    if settings.idle.idle_after_secs == 0 {
+        errors.add("idle.idle_after_secs", "must be at least 1");
+    }
+
+    let mut commands = HashSet::new();
+    for (i, allowed) in settings.exec.allowed.iter().enumerate() {
+        if allowed.command.trim().is_empty() {
+            errors.add(format!("exec.allowed[{}].command", i), "must name a program");
+        } else if !commands.insert(allowed.command.trim()) {
+            errors.add(format!("exec.allowed[{}].command", i), format!("'{}' is listed twice", allowed.command));
+        }
+        if allowed.timeout_secs == Some(0) {
+            errors.add(format!("exec.allowed[{}].timeout_secs", i), "must be at least 1");
+        }
+    }
+    if settings.exec.default_timeout_secs == 0 {
+        errors.add("exec.default_timeout_secs", "must be at least 1");
+    }
+
+    for (i, rule) in settings.clipboard.rules.iter().enumerate() {
+        if let ClipboardPattern::Custom { regex } = &rule.pattern {
+            if let Err(e) = regex::Regex::new(regex) {
+                errors.add(format!("clipboard.rules[{}].regex", i), format!("invalid pattern: {}", e));
+            }
+        }
+        if rule.prompt.trim().is_empty() {
+            errors.add(format!("clipboard.rules[{}].prompt", i), "must not be empty");
+        }
+    }
+
+    check_path(&mut errors, "backend.python", settings.backend.python.as_deref(), false);
+    check_path(&mut errors, "backend.working_dir", settings.backend.working_dir.as_deref(), true);
+    if settings.backend.workers >= workers::MAX_BACKENDS {
+        errors.add("backend.workers", format!("must be at most {}", workers::MAX_BACKENDS - 1));
+    }
+
+    if settings.metrics.enabled {
+        if settings.metrics.port == 0 {
+            errors.add("metrics.port", "must be between 1 and 65535");
+        } else if settings.metrics.port == settings.mcp.proxy_port {
+            errors.add("metrics.port", format!("{} is already used by mcp.proxy_port", settings.metrics.port));
+        }
+    }
+
+    for (i, certificate) in settings.tls.extra_ca_certificates.iter().enumerate() {
+        check_path(&mut errors, &format!("tls.extra_ca_certificates[{}]", i), Some(certificate), false);
+    }
+
+    for (i, profile) in settings.app_profiles.iter().enumerate() {
+        if profile.app.trim().is_empty() {
+            errors.add(format!("app_profiles[{}].app", i), "must name an application");
+        }
+    }
+
+    errors.0
+}
+
+// What's wrong with `settings` that wasn't already wrong with `current`, so problems the current
+// settings have (say a folder deleted since) don't block every other change
+pub fn new_problems(current: &Settings, settings: &Settings) -> Vec<ValidationError> {
+    let known: Vec<String> = validate(current).iter().map(ToString::to_string).collect();
+    validate(settings)
+        .into_iter()
+        .filter(|problem| !known.contains(&problem.to_string()))
+        .collect()
+}
+
+// Command for the settings UI to check changes before saving them
+#[tauri::command]
+pub fn validate_settings(settings: Settings) -> Vec<ValidationError> {
+    validate(&settings)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn paths(errors: &[ValidationError]) -> Vec<&str> {
+        errors.iter().map(|error| error.path.as_str()).collect()
+    }
+
+    #[test]
+    fn accelerators_need_known_modifiers_and_one_key() {
+        for accelerator in ["CommandOrControl+Shift+K", "Alt+Space", "ctrl+F12", "Shift+Plus", "Super+/", "NumPad5"] {
+            assert_eq!(check_accelerator(accelerator), Ok(()), "{}", accelerator);
+        }
+        for accelerator in ["", "Ctrl++", "Ctrl+Shift", "Hyper+K", "Ctrl+Ctrl+K", "Ctrl+F33", "Ctrl+Enterr"] {
+            assert!(check_accelerator(accelerator).is_err(), "{}", accelerator);
+        }
+    }
+
+    #[test]
+    fn the_defaults_are_valid() {
+        assert_eq!(paths(&validate(&Settings::default())), Vec::<&str>::new());
+    }
+
+    #[test]
+    fn problems_are_reported_at_their_path() {
+        let mut settings = Settings::default();
+        settings.shortcuts.spotlight = vec!["Alt+Space".into(), "alt+space".into(), "Ctrl+".into()];
+        settings.telemetry.endpoint = Some("ftp://example.com".into());
+        settings.context.folders = vec!["/does/not/exist/krya".into()];
+        settings.context.min_score = 2.0;
+        assert_eq!(
+            paths(&validate(&settings)),
+            [
+                "shortcuts.spotlight[1]",
+                "shortcuts.spotlight[2]",
+                "context.folders[0]",
+                "context.min_score",
+                "telemetry.endpoint",
+            ]
+        );
+    }
+
+    #[test]
+    fn only_new_problems_are_refused() {
+        let mut current = Settings::default();
+        current.context.folders = vec!["/does/not/exist/krya".into()];
+        let mut settings = current.clone();
+        settings.context.max_snippets = 10;
+        assert!(new_problems(&current, &settings).is_empty());
+
+        settings.file_index.max_files = 0;
+        assert_eq!(paths(&new_problems(&current, &settings)), ["file_index.max_files"]);
+    }
+}
@@ -0,0 +1,92 @@
+use gtk::prelude::*;
+use gtk_layer_shell::{Edge, KeyboardMode, Layer, LayerShell};
+use tauri::{Monitor, WebviewWindow};
+
+use crate::placement::{self, Anchor, Rect};
+
+// Wayland doesn't let clients position themselves or stay on top. By default we run through XWayland,
+// where both work (as do the X11-only global shortcuts). KRYA_WAYLAND=native runs as a Wayland client
+// instead and turns the spotlight into a layer-shell overlay on compositors that support it (KDE, wlroots).
+const MODE_VAR: &str = "KRYA_WAYLAND";
+
+const LAYER_NAMESPACE: &str = "krya-spotlight";
+
+fn is_wayland_session() -> bool {
+    std::env::var_os("WAYLAND_DISPLAY").is_some()
+        || std::env::var("XDG_SESSION_TYPE").map(|t| t == "wayland").unwrap_or(false)
+}
+
+// Pick the GDK backend; has to run before GTK is initialized
+pub fn prepare() {
+    // An explicit GDK_BACKEND is the user's call
+    if !is_wayland_session() || std::env::var_os("GDK_BACKEND").is_some() {
+        return;
+    }
+    if std::env::var(MODE_VAR).map(|mode| mode == "native").unwrap_or(false) {
+        println!("Running as a native Wayland client");
+        return;
+    }
+    if std::env::var_os("DISPLAY").is_none() {
+        println!("XWayland isn't available, running as a native Wayland client");
+        return;
+    }
+    std::env::set_var("GDK_BACKEND", "x11");
+}
+
+fn is_wayland_display() -> bool {
+    gtk::gdk::Display::default()
+        .map(|display| display.type_().name() == "GdkWaylandDisplay")
+        .unwrap_or(false)
+}
+
+// Make the spotlight an overlay layer surface when running natively on a compositor that supports it
+pub fn setup_spotlight(window: &WebviewWindow) -> Result<(), String> {
+    if !is_wayland_display() {
+        return Ok(());
+    }
+    if !gtk_layer_shell::is_supported() {
+        eprintln!("The compositor doesn't support layer-shell, so the spotlight can't be positioned or kept on top");
+        return Ok(());
+    }
+
+    let gtk_window = window
+        .gtk_window()
+        .map_err(|e| format!("Failed to get the spotlight window: {}", e))?;
+    // A layer surface has to be set up before the window is mapped
+    if gtk_window.is_realized() {
+        gtk_window.hide();
+        gtk_window.unrealize();
+    }
+    gtk_window.init_layer_shell();
+    gtk_window.set_namespace(LAYER_NAMESPACE);
+    gtk_window.set_layer(Layer::Overlay);
+    gtk_window.set_keyboard_mode(KeyboardMode::OnDemand);
+    // Anchored to the top edge only, so the compositor centers it horizontally
+    gtk_window.set_anchor(Edge::Top, true);
+    Ok(())
+}
+
+// Layer surfaces are placed by their margin from the anchored edge rather than by coordinates.
+// None when the window isn't one, so it's placed the usual way.
+pub fn place_layer(window: &WebviewWindow, monitor: &Monitor, area: Rect, anchor: Anchor) -> Option<tauri::Result<()>> {
+    let gtk_window = window.gtk_window().ok()?;
+    if !gtk_window.is_layer_window() {
+        return None;
+    }
+
+    if let Some(gdk_monitor) = placement::gdk_monitor(monitor) {
+        gtk_window.set_monitor(&gdk_monitor);
+    }
+    // Margins are in logical pixels, measured from the edge of the area panels leave free
+    let height = match (window.outer_size(), window.scale_factor()) {
+        (Ok(size), Ok(scale)) => size.height as f64 / scale,
+        (Err(e), _) | (_, Err(e)) => return Some(Err(e)),
+    };
+    let area_height = area.height as f64 / monitor.scale_factor();
+    let top = match anchor {
+        Anchor::Center => (area_height - height) / 2.0,
+        Anchor::UpperCenter => area_height / 4.0 - height / 2.0,
+    };
+    gtk_window.set_layer_shell_margin(Edge::Top, top.max(0.0).round() as i32);
+    Some(Ok(()))
+}
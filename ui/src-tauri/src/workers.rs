@@ -0,0 +1,193 @@
+use serde::Serialize;
+use std::sync::atomic::{AtomicU32, AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+use tauri::Manager;
+
+use crate::protocol::{self, CallError, Method, NoParams};
+use crate::{backend, settings, AppState};
+
+// The main backend and up to seven workers, on consecutive ports
+pub const MAX_BACKENDS: usize = 8;
+
+// Covers a cold start that has to import the model SDKs
+const STARTUP_TIMEOUT: Duration = Duration::from_secs(60);
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+// Calls in progress on each backend, the main one first
+static BUSY: [AtomicUsize; MAX_BACKENDS] = [
+    AtomicUsize::new(0),
+    AtomicUsize::new(0),
+    AtomicUsize::new(0),
+    AtomicUsize::new(0),
+    AtomicUsize::new(0),
+    AtomicUsize::new(0),
+    AtomicUsize::new(0),
+    AtomicUsize::new(0),
+];
+
+// One bit per worker that has answered a ping
+static READY: AtomicU32 = AtomicU32::new(0);
+
+// Bumped when the workers stop, so startup checks of old ones don't mark new ones ready
+static GENERATION: AtomicU32 = AtomicU32::new(0);
+
+#[derive(Clone, Debug, Serialize)]
+pub struct WorkerStatus {
+    pub port: u16,
+    pub ready: bool,
+    pub busy: usize,
+}
+
+fn port(index: usize) -> u16 {
+    backend::BACKEND_PORT + index as u16
+}
+
+fn is_ready(index: usize) -> bool {
+    READY.load(Ordering::SeqCst) & (1 << index) != 0
+}
+
+pub fn ready_count() -> u32 {
+    READY.load(Ordering::SeqCst).count_ones()
+}
+
+// Workers only answer model calls; the backend refuses to run automations in one
+pub fn env_hints(port: u16) -> Vec<(&'static str, String)> {
+    if port == backend::BACKEND_PORT {
+        Vec::new()
+    } else {
+        vec![("KRYA_WORKER", "1".to_string())]
+    }
+}
+
+// A call counted against the backend it was sent to until it's dropped
+struct Lease(usize);
+
+impl Lease {
+    fn on(index: usize) -> Lease {
+        BUSY[index].fetch_add(1, Ordering::SeqCst);
+        Lease(index)
+    }
+
+    // The least busy backend, a worker when it's a tie, since the main one also runs automations
+    fn acquire() -> Lease {
+        let index = (1..MAX_BACKENDS)
+            .filter(|index| is_ready(*index))
+            .chain(std::iter::once(0))
+            .min_by_key(|index| BUSY[*index].load(Ordering::SeqCst))
+            .unwrap_or(0);
+        Lease::on(index)
+    }
+}
+
+impl Drop for Lease {
+    fn drop(&mut self) {
+        BUSY[self.0].fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+// Send a model call to whichever backend is least busy, falling back to the main one if a worker went away
+pub fn call<M: Method>(params: &M::Params) -> Result<M::Result, CallError> {
+    let lease = Lease::acquire();
+    if lease.0 == 0 {
+        return protocol::try_call_at::<M>(backend::base_url(), params);
+    }
+    match protocol::try_call_at::<M>(&backend::url(port(lease.0)), params) {
+        Err(CallError::Unreachable(e)) => {
+            eprintln!("Backend worker on port {} is unreachable, using the main backend: {}", port(lease.0), e);
+            READY.fetch_and(!(1 << lease.0), Ordering::SeqCst);
+            drop(lease);
+            let _main = Lease::on(0);
+            protocol::try_call_at::<M>(backend::base_url(), params)
+        }
+        result => result,
+    }
+}
+
+// Count the worker in once it answers
+fn wait_until_ready(index: usize, generation: u32) {
+    std::thread::spawn(move || {
+        let url = backend::url(port(index));
+        let started = Instant::now();
+        while started.elapsed() < STARTUP_TIMEOUT {
+            if GENERATION.load(Ordering::SeqCst) != generation {
+                return;
+            }
+            if protocol::try_call_at::<protocol::Ping>(&url, &NoParams {}).is_ok() {
+                println!("Backend worker ready on port {}", port(index));
+                READY.fetch_or(1 << index, Ordering::SeqCst);
+                return;
+            }
+            std::thread::sleep(POLL_INTERVAL);
+        }
+        eprintln!("Backend worker on port {} didn't start in time", port(index));
+    });
+}
+
+// Start as many workers as the settings ask for, unless they're running already
+pub fn start(app_handle: &tauri::AppHandle, app_state: &AppState) {
+    if backend::is_remote() {
+        return;
+    }
+    let count = settings::current(app_handle).backend.workers.min(MAX_BACKENDS - 1);
+    let mut processes = app_state.worker_processes.lock().unwrap();
+    if !processes.is_empty() {
+        return;
+    }
+    let generation = GENERATION.load(Ordering::SeqCst);
+    for index in 1..=count {
+        match crate::spawn_backend(app_handle, port(index)) {
+            Ok(process) => {
+                println!("Backend worker started on port {} with PID: {}", port(index), process.id());
+                processes.push(process);
+                wait_until_ready(index, generation);
+            }
+            Err(e) => {
+                eprintln!("Failed to start backend worker {}: {}", index, e);
+                break;
+            }
+        }
+    }
+}
+
+pub fn stop(app_state: &AppState) {
+    GENERATION.fetch_add(1, Ordering::SeqCst);
+    READY.store(0, Ordering::SeqCst);
+    let mut processes = app_state.worker_processes.lock().unwrap();
+    if !processes.is_empty() {
+        println!("Stopping {} backend workers", processes.len());
+    }
+    for mut process in processes.drain(..) {
+        crate::kill_backend(&mut process);
+    }
+}
+
+// Start or stop workers after the settings changed how many there should be
+pub fn apply(app_handle: &tauri::AppHandle) {
+    let app_state = app_handle.state::<AppState>();
+    if !*app_state.api_server_running.lock().unwrap() {
+        return;
+    }
+    let wanted = settings::current(app_handle).backend.workers.min(MAX_BACKENDS - 1);
+    if app_state.worker_processes.lock().unwrap().len() == wanted {
+        return;
+    }
+    let app_handle = app_handle.clone();
+    std::thread::spawn(move || {
+        let app_state = app_handle.state::<AppState>();
+        stop(&app_state);
+        start(&app_handle, &app_state);
+    });
+}
+
+// Command for the settings pane: every worker that was started and how it's doing
+#[tauri::command]
+pub fn get_backend_workers(app_state: tauri::State<AppState>) -> Vec<WorkerStatus> {
+    let count = app_state.worker_processes.lock().unwrap().len();
+    (1..=count)
+        .map(|index| WorkerStatus {
+            port: port(index),
+            ready: is_ready(index),
+            busy: BUSY[index].load(Ordering::SeqCst),
+        })
+        .collect()
+}